@@ -0,0 +1,232 @@
+#![deny(unsafe_code)]
+#![no_std]
+#![no_main]
+
+use core::fmt::Write;
+
+// Define the panic handler selected by the panic-* features in Cargo.toml.
+//
+// The default is panic-halt. A breakpoint can be set on `rust_begin_unwind` to
+// catch panics.
+//
+wt_support::panic_handler!();
+
+use cortex_m::asm;
+use cortex_m_rt::entry;
+
+use stm32f3xx_hal::{delay::Delay, pac, prelude::*, serial::config, serial::Serial};
+
+use wt_support::adc::{Adc, AdcError, AdcHw, Resolution};
+
+// Channel read, ADC1_IN2 on PA1, which is free on the Discovery board, as in
+// the adc-dma example.
+//
+const CHANNEL: u8 = 2;
+
+// Supply voltage the ADC measures against in millivolts, VDDA, which is 3 V on
+// the Discovery board.
+//
+const VDDA_MV: u32 = 3_000;
+
+// Delay between readings in milliseconds.
+//
+const READ_INTERVAL_MS: u16 = 500;
+
+// ADC1 as wt_support::adc needs it, one register access per step of the
+// calibration sequence documented there.
+//
+struct F3Adc(pac::ADC1);
+
+impl AdcHw for F3Adc {
+    fn enable_regulator(&mut self) {
+        // From the reset state through the intermediate state to enabled.
+        self.0.cr.modify(|_, w| w.advregen().intermediate());
+        self.0.cr.modify(|_, w| w.advregen().enabled());
+    }
+
+    fn start_calibration(&mut self) {
+        self.0
+            .cr
+            .modify(|_, w| w.adcaldif().single_ended().adcal().calibration());
+    }
+
+    fn is_calibrating(&mut self) -> bool {
+        self.0.cr.read().adcal().is_calibration()
+    }
+
+    fn enable(&mut self) {
+        self.0.cr.modify(|_, w| w.aden().enabled());
+    }
+
+    fn is_ready(&mut self) -> bool {
+        let ready = self.0.isr.read().adrdy().is_ready();
+        if ready {
+            self.0.isr.write(|w| w.adrdy().clear());
+        }
+        ready
+    }
+
+    #[allow(unsafe_code)]
+    fn start_conversion(&mut self, channel: u8) {
+        // A sequence of one conversion, of `channel`. Channel numbers aren't
+        // checked against the channels the ADC has, so writing them is unsafe.
+        //
+        // SAFETY: The only channel converted here is CHANNEL, which ADC1 has.
+        //
+        self.0
+            .sqr1
+            .write(|w| unsafe { w.l().bits(0).sq1().bits(channel) });
+        self.0.cr.modify(|_, w| w.adstart().start_conversion());
+    }
+
+    fn conversion(&mut self) -> Option<u16> {
+        // Reading DR clears EOC.
+        if self.0.isr.read().eoc().is_complete() {
+            Some(self.0.dr.read().rdata().bits())
+        } else {
+            None
+        }
+    }
+}
+
+#[entry]
+fn main() -> ! {
+    // Get peripherals.
+    //
+    // take() returns an Option, which requires handling the possibility of the
+    // return of an Err or None instead of the desired value, which is of type
+    // pac::Peripherals in this case.
+    //
+    // Since this is an embedded application, it's not as simple as writing to,
+    // stdout. This is a minimal example, so we'll drop into an inifinite loop
+    // to allow a debugger to find where the failure.
+    //
+    let device_periphs = pac::Peripherals::take().unwrap_or_else(|| {
+        loop {
+            // Failed to take pac::Peripherals.
+            asm::nop(); // If real app, replace with actual error handling.
+        }
+    });
+    let core_periphs = cortex_m::Peripherals::take().unwrap_or_else(|| {
+        loop {
+            // Failed to take cortex_m::Peripherals.
+            asm::nop(); // If real app, replace with actual error handling.
+        }
+    });
+
+    // Enable the clock of ADC1/ADC2 through the PAC, before the RCC is handed
+    // to the HAL, as in the adc-dma example.
+    //
+    device_periphs
+        .RCC
+        .ahbenr
+        .modify(|_, w| w.adc12en().enabled());
+
+    // Get RCC peripheral and set the system clock frequency.
+    //
+    let mut reset_and_clock_control = device_periphs.RCC.constrain();
+    let mut flash = device_periphs.FLASH.constrain();
+    let clocks = reset_and_clock_control
+        .cfgr
+        .sysclk(48.MHz())
+        .freeze(&mut flash.acr);
+
+    let mut delay = Delay::new(core_periphs.SYST, clocks);
+
+    // Get GPIO Ports A and C.
+    //
+    let mut gpioa = device_periphs.GPIOA.split(&mut reset_and_clock_control.ahb);
+    let mut gpioc = device_periphs.GPIOC.split(&mut reset_and_clock_control.ahb);
+
+    // Configure PA1 as an analog input.
+    //
+    // Connect it to the wiper of a potentiometer between GND and 3V. Turned
+    // all the way up to 3V, the input is at full scale, and reads as
+    // saturated.
+    //
+    let _in2 = gpioa.pa1.into_analog(&mut gpioa.moder, &mut gpioa.pupdr);
+
+    // Configure GPIO pins PC10 as TX and PC11 as RX for UART4, the same as in
+    // the uart example, and activate the UART.
+    //
+    let tx_pin = gpioc
+        .pc10
+        .into_af_push_pull(&mut gpioc.moder, &mut gpioc.otyper, &mut gpioc.afrh);
+    let rx_pin = gpioc
+        .pc11
+        .into_af_push_pull(&mut gpioc.moder, &mut gpioc.otyper, &mut gpioc.afrh);
+    let mut uart4 = Serial::new(
+        device_periphs.UART4,
+        (tx_pin, rx_pin),
+        config::Config::default().baudrate(115_200.Bd()),
+        clocks,
+        &mut reset_and_clock_control.apb1,
+    );
+
+    // Clock ADC1 and ADC2 from HCLK / 2, which is 24 MHz.
+    //
+    // Comment this out to see the calibration fail: with CKMODE at its reset
+    // value, the ADC runs from the asynchronous clock from the RCC, which is
+    // off, so ADCAL never clears.
+    //
+    device_periphs
+        .ADC1_2
+        .ccr
+        .modify(|_, w| w.ckmode().sync_div2());
+
+    // Set the sampling time of the channel, and 12-bit, right-aligned single
+    // conversions started from software.
+    //
+    // The ADC's configuration registers can only be written with the ADC
+    // stopped, which it is until it's calibrated and enabled.
+    //
+    let adc1 = device_periphs.ADC1;
+    adc1.smpr1.modify(|_, w| w.smp2().cycles181_5());
+    adc1.cfgr.write(|w| {
+        w.res()
+            .bits12()
+            .align()
+            .right()
+            .cont()
+            .single()
+            .exten()
+            .disabled()
+    });
+
+    // Calibrate the ADC.
+    //
+    // If this fails, the ADC is kept, but every read fails with
+    // AdcError::NotCalibrated, so the loop below reports it each time rather
+    // than printing readings that can't be trusted.
+    //
+    let mut adc = Adc::new(F3Adc(adc1), Resolution::Bits12);
+    match adc.calibrate(&mut delay) {
+        Ok(()) => writeln!(uart4, "ADC calibrated.\r").ok(),
+        Err(e) => writeln!(uart4, "ADC failed calibration: {:?}\r", e).ok(),
+    };
+
+    // Main loop.
+    //
+    // Read the channel and report whether the reading is valid, saturated, or
+    // couldn't be taken at all.
+    //
+    loop {
+        match adc.read(CHANNEL) {
+            Ok(raw) => {
+                let mv = raw as u32 * VDDA_MV / Resolution::Bits12.full_scale() as u32;
+                writeln!(uart4, "Valid: {:4} ({} mV)\r", raw, mv).ok();
+            }
+            Err(AdcError::Saturated) => {
+                writeln!(uart4, "Saturated: at or over {} mV\r", VDDA_MV).ok();
+            }
+            Err(AdcError::NotCalibrated) => {
+                writeln!(uart4, "No reading: the ADC failed calibration\r").ok();
+            }
+            Err(e) => {
+                writeln!(uart4, "No reading: {:?}\r", e).ok();
+            }
+        }
+
+        delay.delay_ms(READ_INTERVAL_MS);
+    }
+}
@@ -0,0 +1,386 @@
+#![deny(unsafe_code)]
+#![no_std]
+#![no_main]
+
+use core::fmt::Write;
+use core::sync::atomic::{compiler_fence, Ordering};
+
+// Define the panic handler selected by the panic-* features in Cargo.toml.
+//
+// The default is panic-halt. A breakpoint can be set on `rust_begin_unwind` to
+// catch panics.
+//
+wt_support::panic_handler!();
+
+use cortex_m::asm;
+use cortex_m_rt::entry;
+
+use stm32f3xx_hal::{delay::Delay, pac, prelude::*, serial::config, serial::Serial};
+
+// Channels converted in each scan, in order.
+//
+// PA1 and PA2 are ADC1_IN2 and ADC1_IN3, and are free on the Discovery board.
+// Channel 16 is the internal temperature sensor and channel 18 the internal
+// voltage reference (VREFINT), which is used to work out the supply voltage
+// the other readings are relative to.
+//
+const CHANNELS: [u8; 4] = [2, 3, 16, 18];
+const CHANNEL_COUNT: usize = CHANNELS.len();
+
+// Indexes of the internal channels in the sample buffer.
+//
+const TEMPERATURE_INDEX: usize = 2;
+const VREFINT_INDEX: usize = 3;
+
+// Address of the regular data register of ADC1 (RM0316: ADC1 at 0x5000_0000,
+// DR at offset 0x40), where the DMA reads each conversion from.
+//
+const ADC1_DR_ADDRESS: u32 = 0x5000_0000 + 0x40;
+
+// Addresses of the factory calibration values (DS9118), each the raw reading
+// of a 12-bit conversion taken with VDDA at 3.3 V: VREFINT_CAL of the internal
+// reference, and TS_CAL1 of the temperature sensor at 30 degrees C.
+//
+const VREFINT_CAL_ADDRESS: usize = 0x1FFF_F7BA;
+const TS_CAL1_ADDRESS: usize = 0x1FFF_F7B8;
+const CALIBRATION_VDDA_MV: u32 = 3_300;
+const TS_CAL1_CENTI_C: i32 = 3_000;
+
+// Typical slope of the temperature sensor (DS9118), in hundredths of a
+// millivolt per degree C. The sensor's voltage goes down as it warms up.
+//
+const TS_SLOPE_CENTI_MV_PER_C: i32 = 430;
+
+// Full scale of a 12-bit conversion.
+//
+const FULL_SCALE: u32 = 4_095;
+
+// Interval between scans.
+//
+const SCAN_INTERVAL_MS: u16 = 500;
+
+// Reads a 16-bit value from the system memory.
+//
+#[allow(unsafe_code)]
+fn read_calibration(address: usize) -> u16 {
+    // SAFETY: The address is that of one of the factory calibration values in
+    // the system memory, which is always mapped and read-only.
+    unsafe { core::ptr::read_volatile(address as *const u16) }
+}
+
+// Sets up DMA1 channel 1 to copy each conversion from ADC1 into `buf`, going
+// back around to the start after the last channel. Channel 1 is the one
+// hardwired to ADC1, per the DMA1 request mapping in the reference manual
+// (RM0316).
+//
+// As in the uart-dma-rx example, the channel is set up through the PAC in
+// circular mode, so it never has to be restarted. Each scan converts exactly
+// CHANNEL_COUNT channels, and the buffer holds exactly that many samples, so
+// the DMA is back at the start of the buffer when each scan starts, and
+// buf[i] is always the latest reading of CHANNELS[i].
+//
+#[allow(unsafe_code)]
+fn start_circular_dma(dma1: &pac::DMA1, buf: &'static mut [u16; CHANNEL_COUNT]) {
+    let ch1 = &dma1.ch1;
+
+    // SAFETY: The peripheral address is that of ADC1_DR. The buffer is
+    // 'static and the reference to it is consumed here, so nothing else can
+    // write to it, and it stays valid for as long as the DMA writes to it.
+    //
+    unsafe {
+        ch1.par.write(|w| w.pa().bits(ADC1_DR_ADDRESS));
+        ch1.mar.write(|w| w.ma().bits(buf.as_mut_ptr() as u32));
+    }
+    ch1.ndtr.write(|w| w.ndt().bits(CHANNEL_COUNT as u16));
+    ch1.cr.write(|w| {
+        w.dir()
+            .from_peripheral()
+            .circ()
+            .enabled()
+            .pinc()
+            .disabled()
+            .minc()
+            .enabled()
+            .psize()
+            .bits16()
+            .msize()
+            .bits16()
+            .pl()
+            .high()
+            .en()
+            .enabled()
+    });
+}
+
+// Sets up ADC1 to convert CHANNELS in sequence each time a scan is started,
+// with a DMA request for each conversion.
+//
+#[allow(unsafe_code)]
+fn configure_adc(adc1: &pac::ADC1, adc1_2: &pac::ADC1_2, delay: &mut Delay) {
+    // Clock ADC1 and ADC2 from HCLK / 2, which is 24 MHz, and turn on the
+    // temperature sensor and VREFINT, which are off out of reset to save
+    // power.
+    //
+    adc1_2
+        .ccr
+        .modify(|_, w| w.ckmode().sync_div2().tsen().enabled().vrefen().enabled());
+
+    // Start the ADC's voltage regulator, which has to go from its reset state
+    // through the intermediate state to enabled, then be given 10 us to
+    // settle (RM0316).
+    //
+    adc1.cr.modify(|_, w| w.advregen().intermediate());
+    adc1.cr.modify(|_, w| w.advregen().enabled());
+    delay.delay_us(10_u32);
+
+    // Calibrate for single-ended conversions, which the ADC has to be disabled
+    // for, then enable it and wait for it to be ready.
+    //
+    adc1.cr
+        .modify(|_, w| w.adcaldif().single_ended().adcal().calibration());
+    while adc1.cr.read().adcal().is_calibration() {}
+    adc1.cr.modify(|_, w| w.aden().enabled());
+    while !adc1.isr.read().adrdy().is_ready() {}
+    adc1.isr.write(|w| w.adrdy().clear());
+
+    // Set the sampling time of each channel.
+    //
+    // The sampling time is how long the ADC's sampling capacitor is connected
+    // to the input before the conversion. The internal channels need at least
+    // 2.2 us (DS9118), so they get the longest, 601.5 ADC clock cycles, which is
+    // about 25 us at 24 MHz. 181.5 cycles is plenty for a low-impedance
+    // external source like a potentiometer.
+    //
+    adc1.smpr1
+        .modify(|_, w| w.smp2().cycles181_5().smp3().cycles181_5());
+    adc1.smpr2
+        .modify(|_, w| w.smp16().cycles601_5().smp18().cycles601_5());
+
+    // Set the regular sequence.
+    //
+    // L is the number of conversions in the sequence minus one, and SQ1 to SQ4
+    // the channels of the first four, in order. Longer sequences continue in
+    // SQR2 to SQR4, up to 16 conversions. Channel numbers aren't checked
+    // against the channels the ADC has, so writing them is unsafe.
+    //
+    // SAFETY: The channels are all ones that ADC1 has.
+    //
+    adc1.sqr1.write(|w| unsafe {
+        w.l()
+            .bits(CHANNEL_COUNT as u8 - 1)
+            .sq1()
+            .bits(CHANNELS[0])
+            .sq2()
+            .bits(CHANNELS[1])
+            .sq3()
+            .bits(CHANNELS[2])
+            .sq4()
+            .bits(CHANNELS[3])
+    });
+
+    // Configure the conversions.
+    //
+    // With a sequence of more than one channel, a start converts the whole
+    // sequence, one channel after the other, which is scan mode. CONT is left
+    // at single, so the ADC stops after the last channel and waits for the
+    // next start, rather than going straight back around. EXTEN disabled
+    // means a start comes from software setting ADSTART, which is the trigger
+    // here.
+    //
+    // DMAEN has the ADC request a DMA transfer after each conversion, and
+    // DMACFG set to circular has it keep doing so indefinitely, to match the
+    // DMA channel's circular mode. In one-shot mode, the ADC would stop
+    // making requests after the DMA's transfer complete, and would have to be
+    // set up again for every scan.
+    //
+    adc1.cfgr.write(|w| {
+        w.res()
+            .bits12()
+            .align()
+            .right()
+            .cont()
+            .single()
+            .exten()
+            .disabled()
+            .dmacfg()
+            .circular()
+            .dmaen()
+            .enabled()
+    });
+}
+
+#[entry]
+fn main() -> ! {
+    // Get peripherals.
+    //
+    // take() returns an Option, which requires handling the possibility of the
+    // return of an Err or None instead of the desired value, which is of type
+    // pac::Peripherals in this case.
+    //
+    // Since this is an embedded application, it's not as simple as writing to,
+    // stdout. This is a minimal example, so we'll drop into an inifinite loop
+    // to allow a debugger to find where the failure.
+    //
+    let device_periphs = pac::Peripherals::take().unwrap_or_else(|| {
+        loop {
+            // Failed to take pac::Peripherals.
+            asm::nop(); // If real app, replace with actual error handling.
+        }
+    });
+    let core_periphs = cortex_m::Peripherals::take().unwrap_or_else(|| {
+        loop {
+            // Failed to take cortex_m::Peripherals.
+            asm::nop(); // If real app, replace with actual error handling.
+        }
+    });
+
+    // Enable the clocks of DMA1 and ADC1/ADC2.
+    //
+    // Both are set up through the PAC, so their clocks are enabled through the
+    // PAC too, before the RCC is handed to the HAL, as in the uart-dma-rx
+    // example.
+    //
+    device_periphs
+        .RCC
+        .ahbenr
+        .modify(|_, w| w.dma1en().enabled().adc12en().enabled());
+
+    // Get RCC peripheral and set the system clock frequency.
+    //
+    let mut reset_and_clock_control = device_periphs.RCC.constrain();
+    let mut flash = device_periphs.FLASH.constrain();
+    let clocks = reset_and_clock_control
+        .cfgr
+        .sysclk(48.MHz())
+        .freeze(&mut flash.acr);
+
+    let mut delay = Delay::new(core_periphs.SYST, clocks);
+
+    // Get GPIO Ports A and C.
+    //
+    let mut gpioa = device_periphs.GPIOA.split(&mut reset_and_clock_control.ahb);
+    let mut gpioc = device_periphs.GPIOC.split(&mut reset_and_clock_control.ahb);
+
+    // Configure PA1 and PA2 as analog inputs.
+    //
+    // Connect each one to something between GND and 3 V, like the wiper of a
+    // potentiometer across the two. Left floating, they read whatever they
+    // pick up.
+    //
+    let _in2 = gpioa.pa1.into_analog(&mut gpioa.moder, &mut gpioa.pupdr);
+    let _in3 = gpioa.pa2.into_analog(&mut gpioa.moder, &mut gpioa.pupdr);
+
+    // Configure GPIO pins PC10 as TX and PC11 as RX for UART4, the same as in
+    // the uart example, and activate the UART.
+    //
+    let tx_pin = gpioc
+        .pc10
+        .into_af_push_pull(&mut gpioc.moder, &mut gpioc.otyper, &mut gpioc.afrh);
+    let rx_pin = gpioc
+        .pc11
+        .into_af_push_pull(&mut gpioc.moder, &mut gpioc.otyper, &mut gpioc.afrh);
+    let mut uart4 = Serial::new(
+        device_periphs.UART4,
+        (tx_pin, rx_pin),
+        config::Config::default().baudrate(115_200.Bd()),
+        clocks,
+        &mut reset_and_clock_control.apb1,
+    );
+
+    // Start the DMA into the sample buffer, then set up the ADC to feed it.
+    //
+    let samples =
+        cortex_m::singleton!(: [u16; CHANNEL_COUNT] = [0; CHANNEL_COUNT]).unwrap_or_else(|| {
+            loop {
+                // Failed to take the sample buffer.
+                asm::nop(); // If real app, replace with actual error handling.
+            }
+        });
+    let samples_ptr = samples.as_ptr();
+    let dma1 = device_periphs.DMA1;
+    start_circular_dma(&dma1, samples);
+
+    let adc1 = device_periphs.ADC1;
+    configure_adc(&adc1, &device_periphs.ADC1_2, &mut delay);
+
+    let vrefint_cal = read_calibration(VREFINT_CAL_ADDRESS) as u32;
+    let ts_cal1 = read_calibration(TS_CAL1_ADDRESS) as u32;
+
+    // Main loop.
+    //
+    // Start a scan, wait for the DMA to finish writing the last channel of it
+    // into the buffer, then print every channel. The CPU isn't involved in
+    // moving any of the samples, which is what lets the same setup scale to a
+    // faster trigger, e.g. from a timer, where the CPU would never keep up with
+    // reading each conversion itself.
+    //
+    loop {
+        adc1.cr.modify(|_, w| w.adstart().start_conversion());
+
+        // The DMA sets its transfer complete flag when it wraps back around to
+        // the start of the buffer, which is after the last channel of the
+        // scan.
+        //
+        while dma1.isr.read().tcif1().is_not_complete() {}
+        dma1.ifcr.write(|w| w.ctcif1().clear());
+
+        // Make sure the reads of the buffer aren't moved ahead of the read of
+        // the flag that showed it's been written.
+        //
+        compiler_fence(Ordering::Acquire);
+        let mut raw = [0_u16; CHANNEL_COUNT];
+        for (i, sample) in raw.iter_mut().enumerate() {
+            // SAFETY: The index is within the buffer, which is 'static. The
+            // read is volatile since the DMA writes to the buffer behind the
+            // compiler's back, and the DMA doesn't write to it again until the
+            // next scan is started.
+            #[allow(unsafe_code)]
+            unsafe {
+                *sample = samples_ptr.add(i).read_volatile();
+            }
+        }
+
+        // Work out the supply voltage from VREFINT.
+        //
+        // The ADC measures each input relative to VDDA, which on the Discovery
+        // board is about 3 V, but not exactly. VREFINT is fixed at about 1.2 V,
+        // and VREFINT_CAL is its reading with VDDA at exactly 3.3 V, so the
+        // ratio of the two readings gives VDDA.
+        //
+        let vdda_mv = CALIBRATION_VDDA_MV * vrefint_cal / (raw[VREFINT_INDEX] as u32).max(1);
+        let to_mv = |sample: u16| sample as u32 * vdda_mv / FULL_SCALE;
+
+        // Work out the temperature from the sensor's voltage, relative to its
+        // voltage at 30 degrees C from TS_CAL1. The sensor has to be
+        // calibrated this way, since its voltage at a given temperature varies
+        // a lot from chip to chip. It measures the die temperature, which is a
+        // few degrees above the air around it.
+        //
+        let ts_mv = to_mv(raw[TEMPERATURE_INDEX]) as i32;
+        let ts_cal1_mv = (ts_cal1 * CALIBRATION_VDDA_MV / FULL_SCALE) as i32;
+        let temperature_centi_c =
+            TS_CAL1_CENTI_C + (ts_cal1_mv - ts_mv) * 10_000 / TS_SLOPE_CENTI_MV_PER_C;
+
+        // The sign is written on its own, since the whole degrees are 0 from
+        // -0.99 to 0.99 C, and 0 has no sign to carry.
+        //
+        let sign = if temperature_centi_c < 0 { "-" } else { "" };
+        writeln!(
+            uart4,
+            "IN2 {:>4} ({:>4} mV), IN3 {:>4} ({:>4} mV), temp {:>4} ({}{}.{:02} C), VREFINT {:>4} (VDDA {} mV)\r",
+            raw[0],
+            to_mv(raw[0]),
+            raw[1],
+            to_mv(raw[1]),
+            raw[TEMPERATURE_INDEX],
+            sign,
+            temperature_centi_c.abs() / 100,
+            temperature_centi_c.abs() % 100,
+            raw[VREFINT_INDEX],
+            vdda_mv,
+        )
+        .ok();
+
+        delay.delay_ms(SCAN_INTERVAL_MS);
+    }
+}
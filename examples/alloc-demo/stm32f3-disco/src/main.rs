@@ -0,0 +1,251 @@
+#![deny(unsafe_code)]
+#![no_std]
+#![no_main]
+
+// Pull in the alloc crate: the parts of std that only need a heap, like Vec,
+// String, and Box, without the parts that need an operating system.
+//
+extern crate alloc;
+
+use alloc::collections::VecDeque;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt::Write;
+
+// Define the panic handler selected by the panic-* features in Cargo.toml.
+//
+// The default is panic-halt. A breakpoint can be set on `rust_begin_unwind` to
+// catch panics.
+//
+wt_support::panic_handler!();
+
+use cortex_m::asm;
+use cortex_m_rt::entry;
+use embedded_alloc::LlffHeap as Heap;
+
+use stm32f3xx_hal::{block, pac, prelude::*, serial::config, serial::Serial};
+
+// Size of the heap, in bytes.
+//
+// The STM32F303VC has 40 KiB of RAM, shared between the heap, the stack, and
+// the statics. The heap is carved out of it at a fixed size, so however the
+// allocations go, the heap can never grow into the stack.
+//
+const HEAP_SIZE: usize = 4 * 1024;
+
+// Number of lines kept in the history.
+//
+const HISTORY_LEN: usize = 4;
+
+// Length of the longest line that's kept, in bytes.
+//
+const MAX_LINE_LEN: usize = 64;
+
+// The global allocator.
+//
+// Any use of the alloc crate goes through the one #[global_allocator] in the
+// program, so this is what Vec and String allocate from. LlffHeap is a
+// linked-list first-fit allocator: it keeps a list of the free blocks and
+// hands out the first one that's big enough. It starts out empty and is given
+// its memory in main().
+//
+#[global_allocator]
+static HEAP: Heap = Heap::empty();
+
+// What happens when the heap runs out.
+//
+// When an allocation fails, the alloc crate calls handle_alloc_error(), which
+// on stable Rust panics, so running out of heap ends up in the panic handler
+// like any other panic. A custom #[alloc_error_handler] to do something else
+// still needs nightly and #![feature(alloc_error_handler)], and it can't
+// return either, so the most it can do is e.g. log the failed Layout before
+// halting or resetting.
+//
+// The only way to recover from running out is to not let the allocation fail
+// in the first place: try_reserve() on Vec and String asks for the memory up
+// front and returns an error instead of panicking if it isn't there. The
+// pushes after it then can't allocate, so they can't fail.
+//
+// The risks of a heap in firmware that has to keep running:
+//
+// - Running out: How much heap is needed depends on what the program does at
+//   run time, so unlike the stack and statics, it can't be checked at compile
+//   time. An input that's longer than expected, or a rare sequence of events,
+//   can exhaust it days into a run.
+// - Fragmentation: Allocating and freeing blocks of different sizes leaves
+//   holes, so an allocation can fail even with enough free bytes in total, if
+//   none of the holes is large enough. A long-running program can slowly
+//   fragment its heap until that happens.
+// - Timing: Finding a free block takes longer the more blocks there are, so an
+//   allocation doesn't take a fixed time, which matters in an interrupt
+//   handler or anything else with a deadline. Allocating in an interrupt
+//   handler also means the allocator has to lock out interrupts while it
+//   works, which is what the critical-section-single-core feature of cortex-m
+//   provides for it here.
+//
+// That's why heapless is often the better choice: its Vec, String, and queues
+// have their capacity fixed in their type, so they live in a static or on the
+// stack, use exactly that much memory, and a push that doesn't fit returns an
+// error right there. The worst case is visible in the code and the memory map.
+// The heap is worth it for crates that only take alloc types, or for data
+// whose size truly varies, which is what this example shows how to set up
+// with every allocation bounded.
+//
+
+#[entry]
+fn main() -> ! {
+    // Give the heap its memory.
+    //
+    // init!() defines a static byte array of HEAP_SIZE and hands it to the
+    // heap. It's unsafe since it has to be called once, before anything is
+    // allocated, which is why it's the first thing in main(). A second call
+    // panics.
+    //
+    #[allow(unsafe_code)]
+    unsafe {
+        embedded_alloc::init!(HEAP, HEAP_SIZE);
+    }
+
+    // Get peripherals.
+    //
+    // take() returns an Option, which requires handling the possibility of the
+    // return of an Err or None instead of the desired value, which is of type
+    // pac::Peripherals in this case.
+    //
+    // Since this is an embedded application, it's not as simple as writing to,
+    // stdout. This is a minimal example, so we'll drop into an inifinite loop
+    // to allow a debugger to find where the failure.
+    //
+    let device_periphs = pac::Peripherals::take().unwrap_or_else(|| {
+        loop {
+            // Failed to take pac::Peripherals.
+            asm::nop(); // If real app, replace with actual error handling.
+        }
+    });
+
+    // Get RCC peripheral and set the system clock frequency.
+    //
+    let mut reset_and_clock_control = device_periphs.RCC.constrain();
+    let mut flash = device_periphs.FLASH.constrain();
+    let clocks = reset_and_clock_control
+        .cfgr
+        .sysclk(48.MHz())
+        .freeze(&mut flash.acr);
+
+    // Get GPIO Port C.
+    //
+    let mut gpioc = device_periphs.GPIOC.split(&mut reset_and_clock_control.ahb);
+
+    // Configure GPIO pins PC10 as TX and PC11 as RX for UART4, the same as in
+    // the uart example, and activate the UART.
+    //
+    let tx_pin = gpioc
+        .pc10
+        .into_af_push_pull(&mut gpioc.moder, &mut gpioc.otyper, &mut gpioc.afrh);
+    let rx_pin = gpioc
+        .pc11
+        .into_af_push_pull(&mut gpioc.moder, &mut gpioc.otyper, &mut gpioc.afrh);
+    let mut uart4 = Serial::new(
+        device_periphs.UART4,
+        (tx_pin, rx_pin),
+        config::Config::default().baudrate(115_200.Bd()),
+        clocks,
+        &mut reset_and_clock_control.apb1,
+    );
+
+    // Use a Vec and a String, the same as with std.
+    //
+    let squares: Vec<u32> = (1..=10).map(|n| n * n).collect();
+    let mut message = String::new();
+    write!(message, "Squares: {:?}", squares).ok();
+    writeln!(uart4, "{}\r", message).ok();
+    writeln!(
+        uart4,
+        "Heap: {} bytes used, {} bytes free\r",
+        HEAP.used(),
+        HEAP.free()
+    )
+    .ok();
+    drop(squares);
+    drop(message);
+
+    // Ask for more than the whole heap without panicking.
+    //
+    let mut too_big: Vec<u8> = Vec::new();
+    if too_big.try_reserve(2 * HEAP_SIZE).is_err() {
+        writeln!(
+            uart4,
+            "Reserving {} bytes failed, as it should.\r",
+            2 * HEAP_SIZE
+        )
+        .ok();
+    }
+
+    writeln!(
+        uart4,
+        "Send lines to keep the last {} of them.\r",
+        HISTORY_LEN
+    )
+    .ok();
+
+    // The last few lines received, oldest first.
+    //
+    // The history is bounded at both levels: it holds at most HISTORY_LEN
+    // lines, and each line at most MAX_LINE_LEN bytes, so the most it can ever
+    // take from the heap is known up front and is well within HEAP_SIZE. The
+    // oldest line is dropped, and its memory freed, before a new one is added.
+    //
+    let mut history: VecDeque<String> = VecDeque::with_capacity(HISTORY_LEN);
+    let mut line = String::with_capacity(MAX_LINE_LEN);
+
+    // Main loop.
+    //
+    // Collect each received line into the String and add it to the history at
+    // the end of the line, then write the history out. line was allocated at
+    // its full size up front, so pushing onto it never allocates, and bytes
+    // past MAX_LINE_LEN are dropped rather than growing it.
+    //
+    loop {
+        let byte = match block!(uart4.read()) {
+            Ok(byte) => byte,
+            Err(_) => continue,
+        };
+
+        if byte != b'\r' && byte != b'\n' {
+            if line.len() < MAX_LINE_LEN && byte.is_ascii() {
+                line.push(byte as char);
+            }
+            continue;
+        }
+        if line.is_empty() {
+            continue;
+        }
+
+        if history.len() == HISTORY_LEN {
+            history.pop_front();
+        }
+
+        // Move the line into the history, and start a new one at full size.
+        //
+        // Taking it with mem::replace() rather than cloning it means the
+        // history keeps the allocation the line was collected in, and only
+        // the new, empty line has to be allocated.
+        //
+        history.push_back(core::mem::replace(
+            &mut line,
+            String::with_capacity(MAX_LINE_LEN),
+        ));
+
+        writeln!(uart4, "History:\r").ok();
+        for (i, entry) in history.iter().enumerate() {
+            writeln!(uart4, "  {}: {}\r", i + 1, entry).ok();
+        }
+        writeln!(
+            uart4,
+            "Heap: {} bytes used, {} bytes free\r",
+            HEAP.used(),
+            HEAP.free()
+        )
+        .ok();
+    }
+}
@@ -0,0 +1,194 @@
+#![deny(unsafe_code)]
+#![no_std]
+#![no_main]
+
+use core::fmt::Write;
+
+// Define the panic handler selected by the panic-* features in Cargo.toml.
+//
+// The default is panic-halt. A breakpoint can be set on `rust_begin_unwind` to
+// catch panics.
+//
+wt_support::panic_handler!();
+
+use cortex_m::asm;
+use cortex_m::peripheral::SCB;
+use cortex_m_rt::entry;
+
+use stm32f3xx_hal::{block, pac, prelude::*, serial::config, serial::Serial};
+
+// Value kept in MAGIC_REGISTER to say the backup registers have been set up.
+//
+// After the backup domain loses power, or is reset, every backup register
+// reads 0. A value that the registers can't end up holding any other way
+// tells that apart from a domain this program has already written, rather
+// than trusting whatever the counter happens to hold.
+//
+const MAGIC: u32 = 0xB007_C0DE;
+
+// Backup registers used, RTC_BKP0R and RTC_BKP1R.
+//
+// The STM32F303 has 16 of them, RTC_BKP0R to RTC_BKP15R, 64 bytes in all, as
+// 32-bit registers in the RTC.
+//
+const MAGIC_REGISTER: usize = 0;
+const COUNTER_REGISTER: usize = 1;
+
+// Runs `f` with writes to the backup domain enabled, and disables them again
+// after it.
+//
+// The backup domain, which holds the RTC, its clock selection in RCC_BDCR,
+// and the backup registers, is write protected out of reset, so that a
+// program going wrong while the rest of the chip comes up can't corrupt it.
+// Setting DBP in PWR_CR lifts the protection, and clearing it puts it back.
+// Reading never needs it.
+//
+// The backup registers don't have the RTC's own write protection, the keys
+// written to RTC_WPR in the rtc-alarm example, so DBP is all it takes.
+// Clearing it again after each write keeps the window that a stray write
+// could land in as short as possible.
+//
+fn with_backup_access(pwr: &pac::PWR, f: impl FnOnce()) {
+    pwr.cr.modify(|_, w| w.dbp().set_bit());
+    f();
+    pwr.cr.modify(|_, w| w.dbp().clear_bit());
+}
+
+#[entry]
+fn main() -> ! {
+    // Get peripherals.
+    //
+    // take() returns an Option, which requires handling the possibility of the
+    // return of an Err or None instead of the desired value, which is of type
+    // pac::Peripherals in this case.
+    //
+    // Since this is an embedded application, it's not as simple as writing to,
+    // stdout. This is a minimal example, so we'll drop into an inifinite loop
+    // to allow a debugger to find where the failure.
+    //
+    let device_periphs = pac::Peripherals::take().unwrap_or_else(|| {
+        loop {
+            // Failed to take pac::Peripherals.
+            asm::nop(); // If real app, replace with actual error handling.
+        }
+    });
+
+    // Enable the clock of the PWR peripheral, which has the DBP bit used by
+    // with_backup_access(), as in the rtc-alarm example. Done before
+    // constrain(), since that takes RCC.
+    //
+    device_periphs
+        .RCC
+        .apb1enr
+        .modify(|_, w| w.pwren().enabled());
+
+    // Get RCC peripheral and set the system clock frequency.
+    //
+    let mut reset_and_clock_control = device_periphs.RCC.constrain();
+    let mut flash = device_periphs.FLASH.constrain();
+    let clocks = reset_and_clock_control
+        .cfgr
+        .sysclk(48.MHz())
+        .freeze(&mut flash.acr);
+
+    // Get GPIO Port C.
+    //
+    let mut gpioc = device_periphs.GPIOC.split(&mut reset_and_clock_control.ahb);
+
+    // Configure GPIO pins PC10 as TX and PC11 as RX for UART4, the same as in
+    // the uart example, and activate the UART.
+    //
+    let tx_pin = gpioc
+        .pc10
+        .into_af_push_pull(&mut gpioc.moder, &mut gpioc.otyper, &mut gpioc.afrh);
+    let rx_pin = gpioc
+        .pc11
+        .into_af_push_pull(&mut gpioc.moder, &mut gpioc.otyper, &mut gpioc.afrh);
+    let mut uart4 = Serial::new(
+        device_periphs.UART4,
+        (tx_pin, rx_pin),
+        config::Config::default().baudrate(115_200.Bd()),
+        clocks,
+        &mut reset_and_clock_control.apb1,
+    );
+
+    let pwr = device_periphs.PWR;
+    let rtc = device_periphs.RTC;
+
+    // Check the magic value, and count this boot.
+    //
+    // The backup domain is powered from VDD while there is one, and switches
+    // over to VBAT when VDD goes away, so the backup registers keep their
+    // values across anything short of VDD and VBAT both going: a press of the
+    // reset button, a software or watchdog reset, even Standby mode. With the
+    // magic value there, this is a warm reset, and the counter is carried on.
+    //
+    // Without it, the domain has come up empty. That's the first boot after
+    // the board was powered up with nothing on VBAT, as on the Discovery,
+    // which has no battery for it, or after a backup domain reset, with BDRST
+    // in RCC_BDCR. It isn't an error, just the case where the registers have
+    // nothing to pass on, so they're set up from scratch.
+    //
+    let warm = rtc.bkpr[MAGIC_REGISTER].read().bits() == MAGIC;
+    let boots = if warm {
+        rtc.bkpr[COUNTER_REGISTER].read().bits().wrapping_add(1)
+    } else {
+        1
+    };
+    with_backup_access(&pwr, || {
+        rtc.bkpr[COUNTER_REGISTER].write(|w| w.bits(boots));
+        rtc.bkpr[MAGIC_REGISTER].write(|w| w.bits(MAGIC));
+    });
+
+    if warm {
+        writeln!(
+            uart4,
+            "Warm reset: the backup registers kept their values. Boot {}.\r",
+            boots
+        )
+        .ok();
+    } else {
+        writeln!(
+            uart4,
+            "First boot: the backup domain lost power or was reset. Boot count started.\r"
+        )
+        .ok();
+    }
+    writeln!(
+        uart4,
+        "Send r to reset, or c to clear the magic value and then reset.\r"
+    )
+    .ok();
+
+    // Main loop.
+    //
+    // Wait for a command. Resetting from here, or with the reset button,
+    // comes back as a warm reset with the count one higher. Clearing the
+    // magic value first makes the next boot look like a first boot, as does
+    // unplugging the board and plugging it back in.
+    //
+    loop {
+        let byte = match block!(uart4.read()) {
+            Ok(byte) => byte,
+            Err(_) => continue,
+        };
+
+        match byte {
+            b'r' | b'c' => {
+                if byte == b'c' {
+                    with_backup_access(&pwr, || {
+                        rtc.bkpr[MAGIC_REGISTER].write(|w| w.bits(0));
+                    });
+                    writeln!(uart4, "Cleared the magic value.\r").ok();
+                }
+                writeln!(uart4, "Resetting.\r").ok();
+                block!(uart4.flush()).ok();
+                SCB::sys_reset();
+            }
+            b'\r' | b'\n' => {}
+            _ => {
+                writeln!(uart4, "Unknown command; send r or c.\r").ok();
+            }
+        }
+    }
+}
@@ -0,0 +1,87 @@
+#![deny(unsafe_code)]
+#![no_std]
+#![no_main]
+
+// Define the panic handler selected by the panic-* features in Cargo.toml.
+//
+// The default is panic-halt. A breakpoint can be set on `rust_begin_unwind` to
+// catch panics.
+//
+wt_support::panic_handler!();
+
+use cortex_m::asm;
+use cortex_m_rt::entry;
+
+use stm32f3xx_hal::{delay::Delay, pac, prelude::*};
+
+use wt_support::blink::blink_pattern;
+use wt_support::led::PinLed;
+
+// The pattern to blink, over and over.
+//
+// A `.` is the LED on for one unit, a `-` on for three, and each is followed
+// by one unit off. A space adds another unit off. This is a heartbeat: two
+// quick beats, then a pause. ". " is on for one unit and off for two, and the
+// six spaces at the end make the pause after the second beat seven units. See
+// wt_support::blink for the rest.
+//
+// Try Morse code too, e.g. "...  ---  ...      " for SOS.
+//
+const HEARTBEAT: &str = ". .      ";
+
+// Length of one unit of the pattern in milliseconds.
+//
+const UNIT_MS: u16 = 100;
+
+#[entry]
+fn main() -> ! {
+    // Get peripherals.
+    //
+    // take() returns an Option, which requires handling the possibility of the
+    // return of an Err or None instead of the desired value, which is of type
+    // pac::Peripherals in this case.
+    //
+    // Since this is an embedded application, it's not as simple as writing to,
+    // stdout. This is a minimal example, so we'll drop into an inifinite loop
+    // to allow a debugger to find where the failure.
+    //
+    let device_periphs = pac::Peripherals::take().unwrap_or_else(|| {
+        loop {
+            // Failed to take pac::Peripherals.
+            asm::nop(); // If real app, replace with actual error handling.
+        }
+    });
+    let core_periphs = cortex_m::Peripherals::take().unwrap_or_else(|| {
+        loop {
+            // Failed to take cortex_m::Peripherals.
+            asm::nop(); // If real app, replace with actual error handling.
+        }
+    });
+
+    // Get RCC peripheral, set up the clocks, and create a SysTick delay, as in
+    // the blinky example.
+    //
+    let mut reset_and_clock_control = device_periphs.RCC.constrain();
+    let mut flash = device_periphs.FLASH.constrain();
+    let clocks = reset_and_clock_control.cfgr.freeze(&mut flash.acr);
+    let mut delay = Delay::new(core_periphs.SYST, clocks);
+
+    // Get GPIO Port E, and wrap LD3 on PE9 as a wt_support::led::PinLed,
+    // which blink_pattern() drives through the UserLed trait.
+    //
+    let mut gpioe = device_periphs.GPIOE.split(&mut reset_and_clock_control.ahb);
+    let mut ld3 = PinLed::new(
+        gpioe
+            .pe9
+            .into_push_pull_output(&mut gpioe.moder, &mut gpioe.otyper),
+    );
+
+    // Main loop.
+    //
+    // blink_pattern() goes through the pattern once and returns with the LED
+    // off, so looping it repeats the pattern without a break in between.
+    //
+    loop {
+        blink_pattern(&mut ld3, &mut delay, HEARTBEAT, UNIT_MS);
+    }
+}
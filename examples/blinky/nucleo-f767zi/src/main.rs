@@ -2,20 +2,20 @@
 #![no_std]
 #![no_main]
 
-// Use halt as the panicking behavior.
+// Define the panic handler selected by the panic-* features in Cargo.toml.
 //
-// A breakpoint can be set on `rust_begin_unwind` to catch panics.
+// The default is panic-halt. A breakpoint can be set on `rust_begin_unwind` to
+// catch panics.
 //
-use panic_halt as _;
-// use panic_abort as _; // requires nightly
-// use panic_itm as _; // logs messages over ITM; requires ITM support
-// use panic_semihosting as _; // logs messages to the host stderr; requires a debugger
+wt_support::panic_handler!();
 
 use cortex_m::asm;
 use cortex_m_rt::entry;
 
 use stm32f7xx_hal::{pac, prelude::*};
 
+use wt_support::led::{blink_all, PinLed};
+
 #[entry]
 fn main() -> ! {
     // Get peripherals.
@@ -70,11 +70,14 @@ fn main() -> ! {
 
     // Create instances of the user LEDs in push-pull output mode.
     //
-    // The initial state is low.
+    // Each pin is wrapped as a wt_support::led::PinLed, which starts it off,
+    // so the LEDs can be blinked with the same blink_all() as in the blinky
+    // example for the STM32F3DISCOVERY. blink_all() takes the LEDs as UserLed
+    // trait objects, so the pins don't need to have the same type.
     //
-    let mut led_ld1 = gpiob.pb0.into_push_pull_output();
-    let mut led_ld2 = gpiob.pb7.into_push_pull_output();
-    let mut led_ld3 = gpiob.pb14.into_push_pull_output();
+    let mut led_ld1 = PinLed::new(gpiob.pb0.into_push_pull_output());
+    let mut led_ld2 = PinLed::new(gpiob.pb7.into_push_pull_output());
+    let mut led_ld3 = PinLed::new(gpiob.pb14.into_push_pull_output());
 
     // Delays in milliseconds for LED states.
     //
@@ -85,13 +88,11 @@ fn main() -> ! {
     //
     loop {
         // Blip the LEDs at 2 Hz for 100ms.
-        led_ld1.set_high();
-        led_ld2.set_high();
-        led_ld3.set_high();
-        delay.delay_ms(LED_ON_DELAY_MS);
-        led_ld1.set_low();
-        led_ld2.set_low();
-        led_ld3.set_low();
-        delay.delay_ms(LED_OFF_DELAY_MS);
+        blink_all(
+            &mut [&mut led_ld1, &mut led_ld2, &mut led_ld3],
+            &mut delay,
+            LED_ON_DELAY_MS,
+            LED_OFF_DELAY_MS,
+        );
     }
 }
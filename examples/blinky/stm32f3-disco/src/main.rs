@@ -2,24 +2,22 @@
 #![no_std]
 #![no_main]
 
-// Use halt as the panicking behavior.
+// Define the panic handler selected by the panic-* features in Cargo.toml.
 //
-// A breakpoint can be set on `rust_begin_unwind` to catch panics.
+// The default is panic-halt. A breakpoint can be set on `rust_begin_unwind` to
+// catch panics.
 //
-use panic_halt as _;
-// use panic_abort as _; // requires nightly
-// use panic_itm as _; // logs messages over ITM; requires ITM support
-// use panic_semihosting as _; // logs messages to the host stderr; requires a debugger
+wt_support::panic_handler!();
 
 use cortex_m::asm;
 use cortex_m_rt::entry;
 
-use stm32f3_discovery::stm32f3xx_hal::delay::Delay;
-use stm32f3_discovery::stm32f3xx_hal::pac;
-use stm32f3_discovery::stm32f3xx_hal::prelude::*;
+use stm32f3xx_hal::delay::Delay;
+use stm32f3xx_hal::gpio::{gpioe, Output, PushPull};
+use stm32f3xx_hal::pac;
+use stm32f3xx_hal::prelude::*;
 
-use stm32f3_discovery::leds::Leds;
-use stm32f3_discovery::switch_hal::ToggleableOutputSwitch;
+use wt_support::led::{blink_all, PinLed, UserLed};
 
 #[entry]
 fn main() -> ! {
@@ -74,43 +72,66 @@ fn main() -> ! {
     // the port at the same time. The mutable borrow allows modification of the
     // borrowed value while ensuring exclusive access.
     //
-    let mut gpioe: stm32f3_discovery::stm32f3xx_hal::gpio::gpioe::Parts =
-        device_periphs.GPIOE.split(&mut reset_and_clock_control.ahb);
+    let mut gpioe: gpioe::Parts = device_periphs.GPIOE.split(&mut reset_and_clock_control.ahb);
 
-    // Create an instance of the board's LEDs.
+    // Create instances of LD3 through LD10 in push-pull output mode.
     //
-    // The constructor of the Leds type takes the specific pins from GPIO Port
-    // E that are attached to the LEDs on the board plus the mode and output
-    // type registers for Port E.
+    // The LEDs are attached to pins PE8 through PE15 of GPIO Port E. Each pin
+    // has its own type, so downgrade() is used to erase the pin number from the
+    // type, which lets them all be collected into one array. This takes the
+    // place of the Leds type from the stm32f3-discovery board crate, which
+    // depends on an older version of stm32f3xx-hal than the other examples.
     //
-    let mut leds = Leds::new(
-        gpioe.pe8,
-        gpioe.pe9,
-        gpioe.pe10,
-        gpioe.pe11,
-        gpioe.pe12,
-        gpioe.pe13,
-        gpioe.pe14,
-        gpioe.pe15,
-        &mut gpioe.moder,
-        &mut gpioe.otyper,
-    );
+    let pins: [gpioe::PEx<Output<PushPull>>; 8] = [
+        gpioe
+            .pe8
+            .into_push_pull_output(&mut gpioe.moder, &mut gpioe.otyper)
+            .downgrade(),
+        gpioe
+            .pe9
+            .into_push_pull_output(&mut gpioe.moder, &mut gpioe.otyper)
+            .downgrade(),
+        gpioe
+            .pe10
+            .into_push_pull_output(&mut gpioe.moder, &mut gpioe.otyper)
+            .downgrade(),
+        gpioe
+            .pe11
+            .into_push_pull_output(&mut gpioe.moder, &mut gpioe.otyper)
+            .downgrade(),
+        gpioe
+            .pe12
+            .into_push_pull_output(&mut gpioe.moder, &mut gpioe.otyper)
+            .downgrade(),
+        gpioe
+            .pe13
+            .into_push_pull_output(&mut gpioe.moder, &mut gpioe.otyper)
+            .downgrade(),
+        gpioe
+            .pe14
+            .into_push_pull_output(&mut gpioe.moder, &mut gpioe.otyper)
+            .downgrade(),
+        gpioe
+            .pe15
+            .into_push_pull_output(&mut gpioe.moder, &mut gpioe.otyper)
+            .downgrade(),
+    ];
 
-    // Delay in milliseconds between toggles of the LEDs.
+    // Wrap each pin as a wt_support::led::PinLed, so the LEDs can be blinked
+    // with the same blink_all() as in the blinky example for the
+    // NUCLEO-F767ZI, which takes them as UserLed trait objects.
     //
-    const LED_TOGGLE_DELAY_MS: u16 = 500;
+    let mut leds = pins.map(PinLed::new);
+    let mut leds = leds.each_mut().map(|led| led as &mut dyn UserLed);
+
+    // Delays in milliseconds for LED states.
+    //
+    const LED_ON_DELAY_MS: u32 = 500;
+    const LED_OFF_DELAY_MS: u32 = 500;
 
     // Main loop.
     //
     loop {
-        leds.ld3.toggle().ok();
-        leds.ld4.toggle().ok();
-        leds.ld5.toggle().ok();
-        leds.ld6.toggle().ok();
-        leds.ld7.toggle().ok();
-        leds.ld8.toggle().ok();
-        leds.ld9.toggle().ok();
-        leds.ld10.toggle().ok();
-        delay.delay_ms(LED_TOGGLE_DELAY_MS);
+        blink_all(&mut leds, &mut delay, LED_ON_DELAY_MS, LED_OFF_DELAY_MS);
     }
 }
@@ -0,0 +1,228 @@
+#![deny(unsafe_code)]
+#![no_std]
+#![no_main]
+
+use core::cell::RefCell;
+use core::fmt::Write;
+
+// Define the panic handler selected by the panic-* features in Cargo.toml.
+//
+// The default is panic-halt. A breakpoint can be set on `rust_begin_unwind` to
+// catch panics.
+//
+wt_support::panic_handler!();
+
+use cortex_m::asm;
+use cortex_m::peripheral::NVIC;
+use cortex_m_rt::entry;
+use critical_section::Mutex;
+
+use stm32f3xx_hal::{
+    gpio::{gpioc, PushPull, AF5},
+    hal::serial,
+    nb,
+    pac::{self, interrupt},
+    prelude::*,
+    serial::{config, Event, Serial},
+};
+
+use wt_support::millis;
+use wt_support::uart_tx::{BufferedTx, TxInterrupt};
+
+// Size of the transmit queue, which holds one byte less than this.
+//
+const TX_QUEUE_SIZE: usize = 256;
+
+// Time between lines logged in milliseconds.
+//
+// Each line is about 30 bytes, so this is about 6 kB/s of output, a little
+// over half of the 11.5 kB/s UART4 can send at 115200 baud.
+//
+const LOG_INTERVAL_MS: u32 = 5;
+
+// Time between toggles of LD3 in milliseconds.
+//
+const BLINK_INTERVAL_MS: u32 = 100;
+
+type Uart4 = Serial<pac::UART4, (gpioc::PC10<AF5<PushPull>>, gpioc::PC11<AF5<PushPull>>)>;
+
+// UART4, with the TXE interrupt control BufferedTx needs.
+//
+// TxInterrupt and Serial both come from other crates, so the trait can't be
+// implemented for Serial here directly. Wrapping it in a type of this crate's
+// own gets around that, at the cost of passing its serial Write through.
+//
+struct Uart(Uart4);
+
+impl serial::Write<u8> for Uart {
+    type Error = <Uart4 as serial::Write<u8>>::Error;
+
+    fn write(&mut self, byte: u8) -> nb::Result<(), Self::Error> {
+        self.0.write(byte)
+    }
+
+    fn flush(&mut self) -> nb::Result<(), Self::Error> {
+        self.0.flush()
+    }
+}
+
+impl TxInterrupt for Uart {
+    fn enable_tx_interrupt(&mut self) {
+        self.0.enable_interrupt(Event::TransmitDataRegisterEmtpy);
+    }
+
+    fn disable_tx_interrupt(&mut self) {
+        self.0.disable_interrupt(Event::TransmitDataRegisterEmtpy);
+    }
+}
+
+// The buffered transmitter, shared between main(), which queues the output,
+// and the UART4 interrupt handler, which sends it, as in the shared-state
+// example.
+//
+static G_TX: Mutex<RefCell<Option<BufferedTx<Uart, TX_QUEUE_SIZE>>>> =
+    Mutex::new(RefCell::new(None));
+
+#[entry]
+fn main() -> ! {
+    // Get peripherals.
+    //
+    // take() returns an Option, which requires handling the possibility of the
+    // return of an Err or None instead of the desired value, which is of type
+    // pac::Peripherals in this case.
+    //
+    // Since this is an embedded application, it's not as simple as writing to,
+    // stdout. This is a minimal example, so we'll drop into an inifinite loop
+    // to allow a debugger to find where the failure.
+    //
+    let device_periphs = pac::Peripherals::take().unwrap_or_else(|| {
+        loop {
+            // Failed to take pac::Peripherals.
+            asm::nop(); // If real app, replace with actual error handling.
+        }
+    });
+    let core_periphs = cortex_m::Peripherals::take().unwrap_or_else(|| {
+        loop {
+            // Failed to take cortex_m::Peripherals.
+            asm::nop(); // If real app, replace with actual error handling.
+        }
+    });
+
+    // Get RCC peripheral and set the system clock frequency.
+    //
+    let mut reset_and_clock_control = device_periphs.RCC.constrain();
+    let mut flash = device_periphs.FLASH.constrain();
+    let clocks = reset_and_clock_control
+        .cfgr
+        .sysclk(48.MHz())
+        .freeze(&mut flash.acr);
+
+    // Start the millisecond tick for timing the blinks and the log lines.
+    //
+    millis::init(core_periphs.SYST, clocks.sysclk().0);
+
+    // Get GPIO Ports C and E.
+    //
+    let mut gpioc = device_periphs.GPIOC.split(&mut reset_and_clock_control.ahb);
+    let mut gpioe = device_periphs.GPIOE.split(&mut reset_and_clock_control.ahb);
+
+    // Configure GPIO pins PC10 as TX and PC11 as RX for UART4, the same as in
+    // the uart example, and activate the UART.
+    //
+    let tx_pin = gpioc
+        .pc10
+        .into_af_push_pull(&mut gpioc.moder, &mut gpioc.otyper, &mut gpioc.afrh);
+    let rx_pin = gpioc
+        .pc11
+        .into_af_push_pull(&mut gpioc.moder, &mut gpioc.otyper, &mut gpioc.afrh);
+    let uart4 = Serial::new(
+        device_periphs.UART4,
+        (tx_pin, rx_pin),
+        config::Config::default().baudrate(115_200.Bd()),
+        clocks,
+        &mut reset_and_clock_control.apb1,
+    );
+    let uart_interrupt = uart4.interrupt();
+
+    // Create an instance of LD3 in push-pull output mode.
+    //
+    let mut ld3 = gpioe
+        .pe9
+        .into_push_pull_output(&mut gpioe.moder, &mut gpioe.otyper);
+
+    // Move the transmitter into the global.
+    //
+    critical_section::with(|cs| {
+        G_TX.borrow(cs).replace(Some(BufferedTx::new(Uart(uart4))));
+    });
+
+    // Unmask the UART4 interrupt in the NVIC.
+    //
+    // Nothing fires yet: BufferedTx only enables the TXE interrupt in the
+    // UART while there's something in the queue, and the queue is empty. Once
+    // main() queues bytes, the handler needs the BufferedTx in G_TX both to
+    // write them out and to disable TXE again when the queue runs dry. TXE
+    // stays set for as long as the data register is empty, so if the handler
+    // couldn't do that, it would run back to back.
+    //
+    #[allow(unsafe_code)]
+    unsafe {
+        NVIC::unmask(uart_interrupt);
+    }
+
+    // Main loop.
+    //
+    // Count as fast as the loop goes round, log the count every
+    // LOG_INTERVAL_MS, and toggle LD3 every BLINK_INTERVAL_MS.
+    //
+    // Writing a line straight to the UART would take about 2.6 ms here, most
+    // of every LOG_INTERVAL_MS, and the count would only go up by one a line.
+    // Queueing it takes a few microseconds, and the interrupt sends it while
+    // the loop carries on, so the count goes up by thousands a line, and the
+    // blinks stay on time. If the output ever does outrun the UART, e.g. with
+    // a shorter LOG_INTERVAL_MS, lines start getting dropped instead of the
+    // loop slowing down, and the dropped count on the lines that do get
+    // through goes up.
+    //
+    let mut count: u32 = 0;
+    let mut last_log = millis::millis();
+    let mut last_blink = millis::millis();
+    loop {
+        count = count.wrapping_add(1);
+
+        if millis::elapsed_since(last_log) >= LOG_INTERVAL_MS {
+            last_log = millis::millis();
+
+            critical_section::with(|cs| {
+                if let Some(tx) = G_TX.borrow(cs).borrow_mut().as_mut() {
+                    let dropped = tx.dropped();
+                    writeln!(tx, "Count: {}  Dropped: {}\r", count, dropped).ok();
+                }
+            });
+        }
+
+        if millis::elapsed_since(last_blink) >= BLINK_INTERVAL_MS {
+            last_blink = millis::millis();
+            ld3.toggle().ok();
+        }
+    }
+}
+
+// Sends the next byte from the queue.
+//
+// Runs whenever UART4's data register is empty while there's something in the
+// queue, so once per byte, roughly every 87 us at 115200 baud, until the queue
+// is empty and BufferedTx turns the interrupt off again.
+//
+// UART4 shares its interrupt with EXTI line 34, its wakeup from Stop mode,
+// hence the name, but that line isn't enabled here, so it only runs for the
+// UART.
+//
+#[interrupt]
+fn UART4_EXTI34() {
+    critical_section::with(|cs| {
+        if let Some(tx) = G_TX.borrow(cs).borrow_mut().as_mut() {
+            tx.on_interrupt();
+        }
+    });
+}
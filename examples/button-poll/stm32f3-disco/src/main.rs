@@ -0,0 +1,98 @@
+#![deny(unsafe_code)]
+#![no_std]
+#![no_main]
+
+// Define the panic handler selected by the panic-* features in Cargo.toml.
+//
+// The default is panic-halt. A breakpoint can be set on `rust_begin_unwind` to
+// catch panics.
+//
+wt_support::panic_handler!();
+
+use cortex_m::asm;
+use cortex_m_rt::entry;
+
+use stm32f3xx_hal::{pac, prelude::*};
+
+#[entry]
+fn main() -> ! {
+    // Get peripherals.
+    //
+    // take() returns an Option, which requires handling the possibility of the
+    // return of an Err or None instead of the desired value, which is of type
+    // pac::Peripherals in this case.
+    //
+    // Since this is an embedded application, it's not as simple as writing to,
+    // stdout. This is a minimal example, so we'll drop into an inifinite loop
+    // to allow a debugger to find where the failure.
+    //
+    let device_periphs = pac::Peripherals::take().unwrap_or_else(|| {
+        loop {
+            // Failed to take pac::Peripherals.
+            asm::nop(); // If real app, replace with actual error handling.
+        }
+    });
+
+    // Get RCC peripheral.
+    //
+    // Only the AHB bus is needed from it to enable the clocks for the GPIO
+    // ports. The system clock is left at its default, since nothing here
+    // depends on it.
+    //
+    let mut reset_and_clock_control = device_periphs.RCC.constrain();
+
+    // Get GPIO Ports A and E.
+    //
+    // The split method here splits out the functionality of each GPIO port
+    // while taking a mutable borrow of an "enabler" that enables the clock for
+    // the port at the same time. The mutable borrow allows modification of the
+    // borrowed value while ensuring exclusive access.
+    //
+    let mut gpioa = device_periphs.GPIOA.split(&mut reset_and_clock_control.ahb);
+    let mut gpioe = device_periphs.GPIOE.split(&mut reset_and_clock_control.ahb);
+
+    // Configure the user button B1 (PA0) as an input.
+    //
+    // The button is active-high: pressing it connects PA0 to VDD. When it's
+    // released, nothing drives the pin, so a pull resistor is needed to hold it
+    // at a known level rather than letting it float and read back noise. The
+    // pull has to be towards the idle level, which is low for this button.
+    //
+    // The board already has an external pull-down on PA0, so a floating input
+    // works here too:
+    //
+    // let button = gpioa.pa0.into_floating_input(&mut gpioa.moder, &mut gpioa.pupdr);
+    //
+    // The internal pull-down is enabled anyway so the example still works with
+    // a button wired up without one. A pull-up would be wrong for this button:
+    // the pin would read high whether or not the button was pressed.
+    //
+    let button = gpioa
+        .pa0
+        .into_pull_down_input(&mut gpioa.moder, &mut gpioa.pupdr);
+
+    // Create an instance of LD3 (PE9) in push-pull output mode.
+    //
+    let mut led_ld3 = gpioe
+        .pe9
+        .into_push_pull_output(&mut gpioe.moder, &mut gpioe.otyper);
+
+    // Main loop.
+    //
+    // The button is polled: its state is read on every pass and LD3 is set to
+    // match. This is the simplest way to read an input, but the CPU is kept
+    // busy checking the pin even when nothing changes, and a press shorter
+    // than one pass of the loop would be missed if the loop did more work.
+    // Configuring an EXTI line for the pin instead raises an interrupt on the
+    // edge, so the CPU can sleep or do other work until the button actually
+    // changes. See the gpio_interrupts example in the stm32f3xx-hal crate for
+    // that approach.
+    //
+    loop {
+        if button.is_high().unwrap_or(false) {
+            led_ld3.set_high().ok();
+        } else {
+            led_ld3.set_low().ok();
+        }
+    }
+}
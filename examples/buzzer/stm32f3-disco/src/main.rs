@@ -0,0 +1,200 @@
+#![deny(unsafe_code)]
+#![no_std]
+#![no_main]
+
+// Define the panic handler selected by the panic-* features in Cargo.toml.
+//
+// The default is panic-halt. A breakpoint can be set on `rust_begin_unwind` to
+// catch panics.
+//
+wt_support::panic_handler!();
+
+use cortex_m::asm;
+use cortex_m_rt::entry;
+
+use stm32f3xx_hal::{
+    delay::Delay,
+    pac,
+    prelude::*,
+    rcc::{BusTimerClock, Enable, Reset},
+};
+
+use wt_support::notes;
+
+// Frequency the timer counts at after the prescaler.
+//
+// 1 MHz gives an auto-reload value of 1_000_000 / f - 1 for a tone of f Hz,
+// which fits in the 16-bit ARR for anything above ~15 Hz and still resolves the
+// top of octave 8 (~7.9 kHz) to within about 0.1%.
+//
+const TIMER_TICK_HZ: u32 = 1_000_000;
+
+// Length of one beat and the gap left between notes.
+//
+// The gap keeps repeated notes from running together into one long tone.
+//
+const BEAT_MS: u32 = 400;
+const GAP_MS: u32 = 40;
+
+// The melody, as note names and lengths in beats. A "-" is a rest.
+//
+// This is the opening of the "Ode to Joy", laid out one bar per line.
+//
+#[rustfmt::skip]
+const MELODY: &[(&str, u32)] = &[
+    ("E4", 1), ("E4", 1), ("F4", 1), ("G4", 1),
+    ("G4", 1), ("F4", 1), ("E4", 1), ("D4", 1),
+    ("C4", 1), ("C4", 1), ("D4", 1), ("E4", 1),
+    ("E4", 1), ("D4", 1), ("D4", 2),
+    ("E4", 1), ("E4", 1), ("F4", 1), ("G4", 1),
+    ("G4", 1), ("F4", 1), ("E4", 1), ("D4", 1),
+    ("C4", 1), ("C4", 1), ("D4", 1), ("E4", 1),
+    ("D4", 1), ("C4", 1), ("C4", 2),
+    ("-", 4),
+];
+
+#[entry]
+fn main() -> ! {
+    // Get peripherals.
+    //
+    // take() returns an Option, which requires handling the possibility of the
+    // return of an Err or None instead of the desired value, which is of type
+    // pac::Peripherals in this case.
+    //
+    // Since this is an embedded application, it's not as simple as writing to,
+    // stdout. This is a minimal example, so we'll drop into an inifinite loop
+    // to allow a debugger to find where the failure.
+    //
+    let device_periphs = pac::Peripherals::take().unwrap_or_else(|| {
+        loop {
+            // Failed to take pac::Peripherals.
+            asm::nop(); // If real app, replace with actual error handling.
+        }
+    });
+    let core_periphs = cortex_m::Peripherals::take().unwrap_or_else(|| {
+        loop {
+            // Failed to take cortex_m::Peripherals.
+            asm::nop(); // If real app, replace with actual error handling.
+        }
+    });
+
+    // Get RCC peripheral and set the system clock frequency.
+    //
+    let mut reset_and_clock_control = device_periphs.RCC.constrain();
+    let mut flash = device_periphs.FLASH.constrain();
+    let clocks = reset_and_clock_control
+        .cfgr
+        .sysclk(48.MHz())
+        .pclk1(24.MHz())
+        .freeze(&mut flash.acr);
+
+    // Get GPIO Port B.
+    //
+    let mut gpiob = device_periphs.GPIOB.split(&mut reset_and_clock_control.ahb);
+
+    // Configure PB4 as TIM3 channel 1 (alternate function 2).
+    //
+    // Connect a passive piezo buzzer between PB4 and GND, through a ~100 ohm
+    // resistor to limit the current. A passive buzzer is needed here, one
+    // without its own oscillator, since the pitch comes from the PWM frequency.
+    // An active buzzer only beeps at its one built-in pitch when powered.
+    //
+    // PB4 doubles as NJTRST for JTAG, which isn't used with the SWD connection
+    // of the on-board ST-Link, so it's free.
+    //
+    let _buzzer_pin =
+        gpiob
+            .pb4
+            .into_af_push_pull::<2>(&mut gpiob.moder, &mut gpiob.otyper, &mut gpiob.afrl);
+
+    // Enable and reset TIM3.
+    //
+    // The PWM abstraction in stm32f3xx-hal fixes the frequency when the timer
+    // is created, so TIM3 is driven through its registers instead so its
+    // period can be changed for every note.
+    //
+    pac::TIM3::enable(&mut reset_and_clock_control.apb1);
+    pac::TIM3::reset(&mut reset_and_clock_control.apb1);
+    let tim3 = device_periphs.TIM3;
+
+    // Set the prescaler so the counter ticks at TIMER_TICK_HZ.
+    //
+    // The timer clock is not simply PCLK1: when the APB1 prescaler is anything
+    // other than 1, the timers are clocked at twice PCLK1. With PCLK1 at 24 MHz
+    // (a prescaler of 2), that's 48 MHz. timer_clock() accounts for this.
+    //
+    let timer_clock_hz = pac::TIM3::timer_clock(&clocks).0;
+    let prescaler = (timer_clock_hz / TIMER_TICK_HZ - 1) as u16;
+    tim3.psc.write(|w| w.psc().bits(prescaler));
+
+    // Configure channel 1 for PWM.
+    //
+    // In PWM mode 1, the output is high while the counter is below the capture
+    // compare register (CCR1) and low from there up to the auto-reload register
+    // (ARR), at which point the counter wraps back to 0. The preload enable
+    // bits buffer writes to CCR1 and ARR until the next update event, so a
+    // change of note always takes effect at the end of a full period rather
+    // than cutting the current one short with a glitch.
+    //
+    tim3.ccmr1_output()
+        .modify(|_, w| w.oc1m().pwm_mode1().oc1pe().enabled());
+    tim3.cr1.modify(|_, w| w.arpe().enabled());
+    tim3.ccer.modify(|_, w| w.cc1e().set_bit());
+
+    // Get a delay provider for the note durations.
+    //
+    let mut delay = Delay::new(core_periphs.SYST, clocks);
+
+    // Main loop.
+    //
+    // Play the melody over and over.
+    //
+    loop {
+        for &(note, beats) in MELODY {
+            match notes::frequency_hz(note) {
+                Some(frequency_hz) => {
+                    // Set the pitch.
+                    //
+                    // The output frequency is the tick frequency divided by the
+                    // number of ticks per period, ARR + 1, so the auto-reload
+                    // value is what sets the pitch: halving it doubles the
+                    // frequency and raises the note an octave.
+                    //
+                    // The duty cycle is CCR1 / (ARR + 1), so CCR1 is set to half
+                    // of the period every time ARR changes to keep the output at
+                    // ~50% duty. A square wave drives a piezo the hardest, so
+                    // that's the loudest setting. Leaving CCR1 at its value for
+                    // the previous note would change the duty, and with it the
+                    // volume and tone, from note to note.
+                    //
+                    let period_ticks = TIMER_TICK_HZ / frequency_hz;
+                    tim3.arr.write(|w| w.arr().bits((period_ticks - 1) as u16));
+                    tim3.ccr1()
+                        .write(|w| w.ccr().bits((period_ticks / 2) as u16));
+
+                    // Generate an update event to load the new values right
+                    // away and reset the counter, then start the timer.
+                    //
+                    tim3.egr.write(|w| w.ug().update());
+                    tim3.cr1.modify(|_, w| w.cen().enabled());
+                }
+                None => {
+                    // A rest (or a note name that isn't valid): stay silent.
+                    //
+                    tim3.cr1.modify(|_, w| w.cen().disabled());
+                }
+            }
+
+            delay.delay_ms(beats * BEAT_MS - GAP_MS);
+
+            // Silence the buzzer between notes.
+            //
+            // The output is left wherever the counter was when the timer
+            // stopped, which is fine for a piezo since no current flows
+            // through it without a changing voltage.
+            //
+            tim3.cr1.modify(|_, w| w.cen().disabled());
+            delay.delay_ms(GAP_MS);
+        }
+    }
+}
@@ -0,0 +1,241 @@
+#![deny(unsafe_code)]
+#![no_std]
+#![no_main]
+
+use core::fmt::Write;
+
+// Define the panic handler selected by the panic-* features in Cargo.toml.
+//
+// The default is panic-halt. A breakpoint can be set on `rust_begin_unwind` to
+// catch panics.
+//
+wt_support::panic_handler!();
+
+use cortex_m::asm;
+use cortex_m_rt::entry;
+
+use bxcan::filter::Mask32;
+use bxcan::{Fifo, Frame, Id, StandardId};
+
+use stm32f3xx_hal::{can::Can, delay::Delay, nb, pac, prelude::*, serial::config, serial::Serial};
+
+// Bit timing for 125 kbit/s with PCLK1 at 24 MHz.
+//
+// Each bit is divided into time quanta (tq) of the CAN clock, PCLK1, divided
+// by the baud rate prescaler (BRP). With BRP = 12, a quantum is 0.5 us, so a
+// 125 kbit/s bit of 8 us is 16 quanta: 1 for the sync segment, every bit's
+// fixed start, then TS1 = 13 before the sample point and TS2 = 2 after it,
+// which puts the sample point at 14 / 16 = 87.5%, the value recommended for
+// CANopen. The resynchronization jump width (SJW), how far the controller can
+// shift the sample point to follow the other nodes' clocks, is 1 quantum.
+//
+const CAN_BRP: u32 = 12;
+const CAN_TS1: u32 = 13;
+const CAN_TS2: u32 = 2;
+const CAN_SJW: u32 = 1;
+
+// Value of the bit timing register (CAN_BTR) for the timing above.
+//
+// Each field is stored as one less than its value: SJW - 1 in bits 24-25,
+// TS2 - 1 in bits 20-22, TS1 - 1 in bits 16-19, and BRP - 1 in bits 0-9
+// (RM0316).
+//
+const CAN_BTR: u32 =
+    (CAN_SJW - 1) << 24 | (CAN_TS2 - 1) << 20 | (CAN_TS1 - 1) << 16 | (CAN_BRP - 1);
+
+// Identifier of the frames sent. In CAN, the identifier says what a frame is
+// rather than who it's to, and a lower identifier wins arbitration when two
+// nodes start sending at the same time.
+//
+const FRAME_ID: u16 = 0x123;
+
+// Time to wait for a frame to come back before giving up, and between frames.
+//
+const RECEIVE_TIMEOUT_MS: u32 = 10;
+const SEND_INTERVAL_MS: u16 = 1_000;
+
+#[entry]
+fn main() -> ! {
+    // Get peripherals.
+    //
+    // take() returns an Option, which requires handling the possibility of the
+    // return of an Err or None instead of the desired value, which is of type
+    // pac::Peripherals in this case.
+    //
+    // Since this is an embedded application, it's not as simple as writing to,
+    // stdout. This is a minimal example, so we'll drop into an inifinite loop
+    // to allow a debugger to find where the failure.
+    //
+    let device_periphs = pac::Peripherals::take().unwrap_or_else(|| {
+        loop {
+            // Failed to take pac::Peripherals.
+            asm::nop(); // If real app, replace with actual error handling.
+        }
+    });
+    let core_periphs = cortex_m::Peripherals::take().unwrap_or_else(|| {
+        loop {
+            // Failed to take cortex_m::Peripherals.
+            asm::nop(); // If real app, replace with actual error handling.
+        }
+    });
+
+    // Get RCC peripheral and set the system clock frequency.
+    //
+    // bxCAN is on APB1 and clocked from PCLK1, which CAN_BTR is worked
+    // out for.
+    //
+    let mut reset_and_clock_control = device_periphs.RCC.constrain();
+    let mut flash = device_periphs.FLASH.constrain();
+    let clocks = reset_and_clock_control
+        .cfgr
+        .sysclk(48.MHz())
+        .pclk1(24.MHz())
+        .freeze(&mut flash.acr);
+
+    let mut delay = Delay::new(core_periphs.SYST, clocks);
+
+    // Get GPIO Ports C and D.
+    //
+    let mut gpioc = device_periphs.GPIOC.split(&mut reset_and_clock_control.ahb);
+    let mut gpiod = device_periphs.GPIOD.split(&mut reset_and_clock_control.ahb);
+
+    // Configure GPIO pins PC10 as TX and PC11 as RX for UART4, the same as in
+    // the uart example, and activate the UART.
+    //
+    let tx_pin = gpioc
+        .pc10
+        .into_af_push_pull(&mut gpioc.moder, &mut gpioc.otyper, &mut gpioc.afrh);
+    let rx_pin = gpioc
+        .pc11
+        .into_af_push_pull(&mut gpioc.moder, &mut gpioc.otyper, &mut gpioc.afrh);
+    let mut uart4 = Serial::new(
+        device_periphs.UART4,
+        (tx_pin, rx_pin),
+        config::Config::default().baudrate(115_200.Bd()),
+        clocks,
+        &mut reset_and_clock_control.apb1,
+    );
+
+    // Configure PD0 as CAN RX and PD1 as CAN TX (alternate function 7).
+    //
+    let can_rx =
+        gpiod
+            .pd0
+            .into_af_push_pull::<7>(&mut gpiod.moder, &mut gpiod.otyper, &mut gpiod.afrl);
+    let can_tx =
+        gpiod
+            .pd1
+            .into_af_push_pull::<7>(&mut gpiod.moder, &mut gpiod.otyper, &mut gpiod.afrl);
+
+    // Set up bxCAN in silent loopback mode.
+    //
+    // In loopback mode, the controller receives its own frames internally
+    // and ignores the RX pin, so it works with nothing connected, not even a
+    // transceiver. Silent mode on top of that holds the TX pin recessive, so
+    // nothing reaches the bus if one is connected.
+    //
+    // To talk to other nodes instead, wire PD1 to the TXD and PD0 to the RXD
+    // of a 3.3 V CAN transceiver, such as an SN65HVD230, connect its CANH and
+    // CANL to the bus, terminated with 120 ohms at each end, and set both
+    // loopback and silent to false. In normal mode, a frame is only sent
+    // successfully once another node acknowledges it, so with no other node on
+    // the bus, the controller keeps retransmitting and never gets a frame
+    // back, since it doesn't receive its own frames outside loopback mode.
+    //
+    let mut can = bxcan::Can::builder(Can::new(
+        device_periphs.CAN,
+        can_tx,
+        can_rx,
+        &mut reset_and_clock_control.apb1,
+    ))
+    .set_bit_timing(CAN_BTR)
+    .set_loopback(true)
+    .set_silent(true)
+    .leave_disabled();
+
+    // Set up a filter to accept every frame.
+    //
+    // Received frames only go into a receive FIFO if they pass at least one
+    // enabled filter bank, and all of the banks are disabled out of reset, so
+    // without this, every frame is dropped, even our own in loopback mode. A
+    // bank in 32-bit mask mode accepts a frame when the bits of its identifier
+    // selected by the mask match the bank's identifier, so a mask of all
+    // zeros, which is what accept_all() sets, selects no bits and matches
+    // everything. A real node would set the mask to pick out only the
+    // identifiers it cares about, so the hardware discards the rest without
+    // the CPU getting involved.
+    //
+    can.modify_filters()
+        .enable_bank(0, Fifo::Fifo0, Mask32::accept_all());
+
+    // Leave initialization mode and wait for the controller to sync to the
+    // bus, which it does on seeing 11 recessive bits in a row. In loopback
+    // mode, that's right away.
+    //
+    nb::block!(can.enable_non_blocking()).ok();
+
+    let id = StandardId::new(FRAME_ID).unwrap_or_else(|| {
+        loop {
+            // FRAME_ID doesn't fit in 11 bits.
+            asm::nop(); // If real app, replace with actual error handling.
+        }
+    });
+
+    // Main loop.
+    //
+    // Send a frame with a counter in its payload, wait for it to come back,
+    // then print what was received.
+    //
+    let mut counter: u32 = 0;
+    loop {
+        let frame = Frame::new_data(id, counter.to_le_bytes());
+
+        // transmit() queues the frame in one of the three transmit mailboxes.
+        // It only blocks if all of them are full.
+        //
+        nb::block!(can.transmit(&frame)).ok();
+
+        let mut received = None;
+        for _ in 0..RECEIVE_TIMEOUT_MS {
+            match can.receive() {
+                Ok(frame) => {
+                    received = Some(frame);
+                    break;
+                }
+                Err(nb::Error::WouldBlock) => delay.delay_ms(1_u16),
+
+                // A frame was received while the FIFO was full, and lost. The
+                // frames in the FIFO are still there to read.
+                Err(nb::Error::Other(_)) => {
+                    writeln!(uart4, "Receive FIFO overrun.\r").ok();
+                }
+            }
+        }
+
+        match received {
+            Some(frame) => {
+                match frame.id() {
+                    Id::Standard(id) => write!(uart4, "Received ID 0x{:03X}", id.as_raw()),
+                    Id::Extended(id) => write!(uart4, "Received ID 0x{:08X}", id.as_raw()),
+                }
+                .ok();
+                write!(uart4, ", {} bytes:", frame.dlc()).ok();
+                for byte in frame.data().map(|data| &data[..]).unwrap_or(&[]) {
+                    write!(uart4, " {:02X}", byte).ok();
+                }
+                writeln!(uart4, "\r").ok();
+            }
+            None => {
+                writeln!(
+                    uart4,
+                    "No frame received within {} ms.\r",
+                    RECEIVE_TIMEOUT_MS
+                )
+                .ok();
+            }
+        }
+
+        counter = counter.wrapping_add(1);
+        delay.delay_ms(SEND_INTERVAL_MS);
+    }
+}
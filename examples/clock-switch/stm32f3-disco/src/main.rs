@@ -0,0 +1,471 @@
+#![deny(unsafe_code)]
+#![no_std]
+#![no_main]
+
+use core::fmt::{self, Write};
+
+// Define the panic handler selected by the panic-* features in Cargo.toml.
+//
+// The default is panic-halt. A breakpoint can be set on `rust_begin_unwind` to
+// catch panics.
+//
+wt_support::panic_handler!();
+
+use cortex_m::asm;
+use cortex_m_rt::entry;
+
+use stm32f3xx_hal::pac;
+
+use wt_support::profile::Stopwatch;
+
+// Baud rate of UART4.
+//
+const BAUD: u32 = 115_200;
+
+// Frequency of HSI, the internal RC oscillator the chip boots on.
+//
+const HSI_HZ: u32 = 8_000_000;
+
+// Frequency of HSE, the 8 MHz clock from the ST-LINK's crystal on the MCO
+// output of its own MCU, which the Discovery board feeds to OSC_IN.
+//
+const HSE_HZ: u32 = 8_000_000;
+
+// The PLL multiplies HSE by 6 for a system clock of 48 MHz, and PCLK1, which
+// UART4 is on, is the system clock divided by 2, since APB1 can't be run
+// faster than 36 MHz.
+//
+const PLL_MUL: u32 = 6;
+const PLL_HZ: u32 = HSE_HZ * PLL_MUL;
+const PLL_APB1_DIV: u32 = 2;
+
+// HSE cycles per capture of TIM16, HSE / 32 with every 8th edge captured, and
+// the number of captures the system clock is counted over, as in the post
+// example.
+//
+const HSE_CYCLES_PER_CAPTURE: u64 = 32 * 8;
+const CAPTURES: u64 = 125;
+
+// How many times to poll a ready or status flag before giving up on it.
+//
+// HSE and the PLL are both ready within a few hundred microseconds, and the
+// switch itself takes a few cycles, so this is far more than enough, even at
+// 8 MHz.
+//
+const READY_POLLS: u32 = 100_000;
+
+// How many cycles to wait for a capture of TIM16, 6 ms at 8 MHz and 1 ms at
+// 48 MHz, either of which is much longer than the 32 us a capture takes.
+//
+const CAPTURE_TIMEOUT_CYCLES: u32 = 48_000;
+
+// Interval the button is polled at, and the number of polls per toggle of
+// LD3, for a blink of 2 Hz.
+//
+const POLL_MS: u32 = 10;
+const POLLS_PER_TOGGLE: u32 = 25;
+
+// Where the system clock is coming from.
+//
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Source {
+    Hsi,
+    HsePll,
+}
+
+// The clock frequencies the program believes it's running at, which the
+// UART's baud rate and the blink's delay are worked out from.
+//
+#[derive(Clone, Copy, Debug)]
+struct Clocks {
+    source: Source,
+    sysclk_hz: u32,
+    pclk1_hz: u32,
+}
+
+const HSI_CLOCKS: Clocks = Clocks {
+    source: Source::Hsi,
+    sysclk_hz: HSI_HZ,
+    pclk1_hz: HSI_HZ,
+};
+
+const HSE_PLL_CLOCKS: Clocks = Clocks {
+    source: Source::HsePll,
+    sysclk_hz: PLL_HZ,
+    pclk1_hz: PLL_HZ / PLL_APB1_DIV,
+};
+
+// Ways the switch to HSE can fail, at the step it failed at. All but the last
+// leave the system clock on HSI.
+//
+// HsiNotConfirmed is when SWS never read PLL, and then never read HSI either
+// after switching back, so the system clock could be on either. The flash
+// and APB1 are left set up for the PLL, which is safe for both, and nothing
+// else can be relied on.
+//
+#[derive(Clone, Copy, Debug)]
+enum SwitchError {
+    HseNotReady,
+    PllNotReady,
+    SwitchNotConfirmed,
+    HsiNotConfirmed,
+}
+
+// Polls `ready` up to READY_POLLS times, returning whether it was ever true.
+//
+fn wait_for(mut ready: impl FnMut() -> bool) -> bool {
+    (0..READY_POLLS).any(|_| ready())
+}
+
+// UART4, set up through its registers as in the uart-errors example, since
+// its baud rate has to be set again whenever PCLK1 changes, which the HAL's
+// Serial has no way to do.
+//
+struct Uart(pac::UART4);
+
+impl Uart {
+    // Sets up and enables UART4 for 8N1 at BAUD, from a PCLK1 of `pclk1_hz`.
+    //
+    // BRR can only be written with the UART disabled.
+    //
+    fn configure(&mut self, pclk1_hz: u32) {
+        self.0.cr1.modify(|_, w| w.ue().disabled());
+        self.0.brr.write(|w| w.brr().bits((pclk1_hz / BAUD) as u16));
+        self.0
+            .cr1
+            .modify(|_, w| w.ue().enabled().re().enabled().te().enabled());
+    }
+
+    // Waits for the last byte written to finish going out, so it isn't cut
+    // off or garbled by a change of clock.
+    //
+    fn flush(&mut self) {
+        while self.0.isr.read().tc().bit_is_clear() {}
+    }
+}
+
+impl fmt::Write for Uart {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        for byte in s.bytes() {
+            while self.0.isr.read().txe().bit_is_clear() {}
+            self.0.tdr.write(|w| w.tdr().bits(byte as u16));
+        }
+        Ok(())
+    }
+}
+
+// Sets TIM16 up to capture every 8th rising edge of HSE / 32 on channel 1, as
+// in the post example.
+//
+// The input remap in OR and the fields of CCMR1 have no named values in the
+// PAC, so they're written as bits, which is unsafe.
+//
+#[allow(unsafe_code)]
+fn start_hse_capture(tim16: &pac::TIM16) {
+    // SAFETY: TI1_RMP = 0b10 connects TIM16_CH1 to HSE / 32 (RM0316, TIM16
+    // option register), and CC1S = 0b01 and IC1PSC = 0b11 capture TI1 on
+    // every 8th edge, all of which are valid values of the fields.
+    //
+    unsafe {
+        tim16.or.write(|w| w.bits(0b10));
+        tim16
+            .ccmr1_input()
+            .write(|w| w.cc1s().bits(0b01).ic1psc().bits(0b11));
+    }
+    tim16.ccer.modify(|_, w| w.cc1e().set_bit());
+    tim16.cr1.modify(|_, w| w.cen().set_bit());
+}
+
+// Waits for the next capture of TIM16, returning false if there isn't one
+// within CAPTURE_TIMEOUT_CYCLES. Reading CCR1 clears the capture flag.
+//
+fn wait_for_capture(tim16: &pac::TIM16) -> bool {
+    let stopwatch = Stopwatch::start();
+    while tim16.sr.read().cc1if().bit_is_clear() {
+        if stopwatch.elapsed_cycles() > CAPTURE_TIMEOUT_CYCLES {
+            return false;
+        }
+    }
+    tim16.ccr1().read();
+    true
+}
+
+// Measures the system clock by counting the core's cycles, on the DWT cycle
+// counter, over CAPTURES captures of HSE / 32, as in the post example, or
+// returns None if there are no captures, which is the case while HSE is off.
+//
+fn measure_sysclk_hz(tim16: &pac::TIM16) -> Option<u32> {
+    if !wait_for_capture(tim16) {
+        return None;
+    }
+
+    let stopwatch = Stopwatch::start();
+    for _ in 0..CAPTURES {
+        if !wait_for_capture(tim16) {
+            return None;
+        }
+    }
+    let cycles = stopwatch.elapsed_cycles() as u64;
+
+    Some((cycles * HSE_HZ as u64 / (CAPTURES * HSE_CYCLES_PER_CAPTURE)) as u32)
+}
+
+// Writes the clocks the program believes it's on, and what the system clock
+// measures as.
+//
+fn report(uart: &mut Uart, tim16: &pac::TIM16, clocks: &Clocks, when: &str) {
+    write!(
+        uart,
+        "{}: {:?}, sysclk {} Hz, measured ",
+        when, clocks.source, clocks.sysclk_hz
+    )
+    .ok();
+    match measure_sysclk_hz(tim16) {
+        Some(hz) => writeln!(uart, "{} Hz.\r", hz).ok(),
+        None => writeln!(uart, "-, with no HSE to measure against.\r").ok(),
+    };
+}
+
+// Turns on HSE, bypassing its oscillator since it's fed a clock rather than a
+// crystal, and waits for it to be ready.
+//
+// If it never gets ready, it's turned back off, rather than left half started
+// for a later switch to pick up.
+//
+fn start_hse(rcc: &pac::RCC) -> Result<(), SwitchError> {
+    rcc.cr.modify(|_, w| w.hsebyp().bypassed().hseon().on());
+    if wait_for(|| rcc.cr.read().hserdy().is_ready()) {
+        Ok(())
+    } else {
+        rcc.cr.modify(|_, w| w.hseon().off());
+        Err(SwitchError::HseNotReady)
+    }
+}
+
+// Switches the system clock from HSI to the PLL, from HSE, which has to be
+// ready already.
+//
+// On a failure, everything that was changed is put back, so the system clock
+// is still HSI, with the flash and buses set up for it, unless the switch
+// back to HSI can't be confirmed either. Then the wait state, APB1 divider,
+// and PLL are left as they are, since lowering them while the core might
+// still be on the PLL is the hazard the rest of the sequence avoids.
+//
+fn switch_to_hse_pll(rcc: &pac::RCC, flash: &pac::FLASH) -> Result<(), SwitchError> {
+    // The flash can't be read at 48 MHz with no wait states, so the wait
+    // state goes in first, while the clock is still slow enough for either.
+    //
+    flash.acr.modify(|_, w| w.latency().ws1());
+
+    // The PLL can only be set up while it's off, which it is, since the
+    // system clock isn't on it.
+    //
+    rcc.cfgr2.modify(|_, w| w.prediv().div1());
+    rcc.cfgr
+        .modify(|_, w| w.pllsrc().hse_div_prediv().pllmul().mul6());
+    rcc.cr.modify(|_, w| w.pllon().on());
+    if !wait_for(|| rcc.cr.read().pllrdy().bit_is_set()) {
+        rcc.cr.modify(|_, w| w.pllon().off());
+        flash.acr.modify(|_, w| w.latency().ws0());
+        return Err(SwitchError::PllNotReady);
+    }
+
+    // Switch, with APB1 divided down in the same write, so it's never over
+    // its 36 MHz limit, and wait for SWS to say the switch has happened.
+    //
+    rcc.cfgr.modify(|_, w| w.ppre1().div2().sw().pll());
+    if !wait_for(|| rcc.cfgr.read().sws().is_pll()) {
+        rcc.cfgr.modify(|_, w| w.sw().hsi());
+        if !wait_for(|| rcc.cfgr.read().sws().is_hsi()) {
+            return Err(SwitchError::HsiNotConfirmed);
+        }
+        rcc.cfgr.modify(|_, w| w.ppre1().div1());
+        rcc.cr.modify(|_, w| w.pllon().off());
+        flash.acr.modify(|_, w| w.latency().ws0());
+        return Err(SwitchError::SwitchNotConfirmed);
+    }
+    Ok(())
+}
+
+// Switches the system clock back to HSI, in the reverse order: the switch
+// first, then the buses, PLL, and flash once the clock is slow again.
+//
+// HSI is never turned off while the system clock is on the PLL here, so it's
+// still ready, and the switch always goes through. HSE is left on, as the
+// reference for the measurement.
+//
+fn switch_to_hsi(rcc: &pac::RCC, flash: &pac::FLASH) {
+    rcc.cfgr.modify(|_, w| w.sw().hsi());
+    while !rcc.cfgr.read().sws().is_hsi() {}
+    rcc.cfgr.modify(|_, w| w.ppre1().div1());
+    rcc.cr.modify(|_, w| w.pllon().off());
+    flash.acr.modify(|_, w| w.latency().ws0());
+}
+
+#[entry]
+fn main() -> ! {
+    // Get peripherals.
+    //
+    // take() returns an Option, which requires handling the possibility of the
+    // return of an Err or None instead of the desired value, which is of type
+    // pac::Peripherals in this case.
+    //
+    // Since this is an embedded application, it's not as simple as writing to,
+    // stdout. This is a minimal example, so we'll drop into an inifinite loop
+    // to allow a debugger to find where the failure.
+    //
+    let device_periphs = pac::Peripherals::take().unwrap_or_else(|| {
+        loop {
+            // Failed to take pac::Peripherals.
+            asm::nop(); // If real app, replace with actual error handling.
+        }
+    });
+    let mut core_periphs = cortex_m::Peripherals::take().unwrap_or_else(|| {
+        loop {
+            // Failed to take cortex_m::Peripherals.
+            asm::nop(); // If real app, replace with actual error handling.
+        }
+    });
+
+    // Keep the RCC rather than handing it to the HAL.
+    //
+    // The HAL sets the clocks up once, in freeze(), and everything it hands
+    // out after that, like a Serial's baud rate or a Delay, is worked out from
+    // the clocks it froze, so it has no way to change them. Here, the RCC is
+    // driven through its registers instead, and so are the GPIO ports and
+    // UART4, since the HAL's versions of those need the parts of the RCC that
+    // constrain() hands out.
+    //
+    // The chip comes out of reset on HSI at 8 MHz, with AHB and both APBs
+    // undivided and no flash wait states, which is where this starts.
+    //
+    let rcc = &device_periphs.RCC;
+    let flash = &device_periphs.FLASH;
+    let mut clocks = HSI_CLOCKS;
+
+    wt_support::dwt_delay::init(&mut core_periphs.DCB, &mut core_periphs.DWT);
+
+    // Enable GPIO Ports A, C, and E, UART4, and TIM16.
+    //
+    rcc.ahbenr
+        .modify(|_, w| w.iopaen().enabled().iopcen().enabled().iopeen().enabled());
+    rcc.apb1enr.modify(|_, w| w.uart4en().enabled());
+    rcc.apb2enr.modify(|_, w| w.tim16en().enabled());
+
+    // B1 on PA0 is an input out of reset, and has a pull-down on the board.
+    // LD3 on PE9 is made an output.
+    //
+    let gpioa = &device_periphs.GPIOA;
+    let gpioe = &device_periphs.GPIOE;
+    gpioe.moder.modify(|_, w| w.moder9().output());
+
+    // Configure PC10 as TX and PC11 as RX for UART4, on alternate function 5,
+    // and enable it.
+    //
+    let gpioc = &device_periphs.GPIOC;
+    gpioc.afrh.modify(|_, w| w.afrh10().af5().afrh11().af5());
+    gpioc
+        .moder
+        .modify(|_, w| w.moder10().alternate().moder11().alternate());
+    let mut uart = Uart(device_periphs.UART4);
+    uart.configure(clocks.pclk1_hz);
+
+    // Start TIM16 capturing HSE / 32, for measuring the system clock once HSE
+    // is on.
+    //
+    let tim16 = device_periphs.TIM16;
+    start_hse_capture(&tim16);
+
+    writeln!(uart, "\r\nPress B1 to switch between HSI and HSE.\r").ok();
+    report(&mut uart, &tim16, &clocks, "Boot");
+
+    // Main loop.
+    //
+    // Switching the system clock at runtime is a sequence, and the order
+    // matters (RM0316, "Clock switch"):
+    //
+    // 1. Enable the new source and wait for its ready flag. For the PLL, that
+    //    means its input first, then the PLL itself.
+    // 2. If the new clock is faster, add the flash wait states it needs
+    //    first, and set the bus prescalers so no bus goes over its limit.
+    // 3. Write the new source to SW in RCC_CFGR.
+    // 4. Wait for SWS to read back the new source. Only then is the core on
+    //    the new clock.
+    // 5. Reconfigure everything whose timing comes from the clock: here, the
+    //    UART's baud rate and the blink's delays.
+    //
+    // The RCC won't switch to a source that isn't ready. If SW is written
+    // anyway, the switch waits, and happens on its own whenever the source
+    // gets ready, if it ever does. Code that carries on as though it had
+    // switched is then working from a clock it isn't on: here, the UART
+    // would send at the wrong baud rate. Code that gives up and puts the
+    // flash wait states back down is worse off, since if the switch then
+    // goes through, the core fetches from the flash at 48 MHz with no wait
+    // states, which reads garbage. Waiting for the ready flag first, and
+    // for SWS after, rules both out, and a bound on each wait means a source
+    // that never starts, like HSE with nothing on OSC_IN, is reported and
+    // left rather than hanging the program on HSI.
+    //
+    // The measurement after a switch to HSE comes out at exactly 48 MHz,
+    // since it's measured against HSE, which is now the clock being measured
+    // too. That still confirms the switch happened and the PLL is multiplying
+    // by what it's meant to. The clock-verify example measures the system
+    // clock against the LSE instead, which is independent of both.
+    //
+    let mut last_pressed = false;
+    let mut polls = 0;
+    loop {
+        asm::delay(clocks.sysclk_hz / 1_000 * POLL_MS);
+
+        polls += 1;
+        if polls == POLLS_PER_TOGGLE {
+            polls = 0;
+            gpioe.odr.modify(|r, w| w.odr9().bit(!r.odr9().bit()));
+        }
+
+        let pressed = gpioa.idr.read().idr0().bit_is_set();
+        let just_pressed = pressed && !last_pressed;
+        last_pressed = pressed;
+        if !just_pressed {
+            continue;
+        }
+
+        match clocks.source {
+            Source::Hsi => {
+                writeln!(uart, "Switching to HSE.\r").ok();
+                if let Err(error) = start_hse(rcc) {
+                    writeln!(uart, "Staying on HSI: {:?}.\r", error).ok();
+                    continue;
+                }
+                report(&mut uart, &tim16, &clocks, "Before");
+
+                uart.flush();
+                match switch_to_hse_pll(rcc, flash) {
+                    Ok(()) => {
+                        clocks = HSE_PLL_CLOCKS;
+                        uart.configure(clocks.pclk1_hz);
+                        report(&mut uart, &tim16, &clocks, "After");
+                    }
+                    Err(SwitchError::HsiNotConfirmed) => loop {
+                        // The system clock is on HSI or the PLL, and the
+                        // UART's baud rate is only right for one of them.
+                        asm::nop(); // If real app, replace with actual error handling.
+                    },
+                    Err(error) => {
+                        writeln!(uart, "Staying on HSI: {:?}.\r", error).ok();
+                    }
+                }
+            }
+            Source::HsePll => {
+                writeln!(uart, "Switching to HSI.\r").ok();
+                report(&mut uart, &tim16, &clocks, "Before");
+
+                uart.flush();
+                switch_to_hsi(rcc, flash);
+                clocks = HSI_CLOCKS;
+                uart.configure(clocks.pclk1_hz);
+                report(&mut uart, &tim16, &clocks, "After");
+            }
+        }
+    }
+}
@@ -0,0 +1,263 @@
+#![deny(unsafe_code)]
+#![no_std]
+#![no_main]
+
+use core::fmt::Write;
+
+// Define the panic handler selected by the panic-* features in Cargo.toml.
+//
+// The default is panic-halt. A breakpoint can be set on `rust_begin_unwind` to
+// catch panics.
+//
+wt_support::panic_handler!();
+
+use cortex_m::asm;
+use cortex_m::peripheral::DWT;
+use cortex_m_rt::entry;
+
+use stm32f3xx_hal::{pac, prelude::*, serial::config, serial::Serial};
+
+use wt_support::dwt_delay;
+
+// Frequency of the HSE input in megahertz, as the HAL is told it is.
+//
+// On the Discovery, the HSE isn't a crystal, but the 8 MHz MCO output of the
+// ST-Link's own STM32F103, on the board's PF0, so it's bypassed rather than
+// started as an oscillator. The HAL has no way to check the frequency it's
+// given against the one that's there, so a wrong value here makes every
+// clock on the chip wrong by the same ratio, while clocks reports the
+// frequencies they were meant to be. Try 12 to see it: the PLL multiplies
+// the real 8 MHz as if it were 12, and the sysclk comes out at 32 MHz
+// rather than 48.
+//
+// The UART's baud rate comes from the same clock, so then it's off by the
+// same ratio, at 76_800 rather than 115_200, and the report comes out as
+// garbage, which is often the first sign of a clock that's wrong. Set the
+// terminal to 76_800 to read it.
+//
+const HSE_MHZ: u32 = 8;
+
+// Frequency of the LSE crystal, X2, in hertz.
+//
+const LSE_HZ: u32 = 32_768;
+
+// Number of LSE periods to count the sysclk over.
+//
+// The RTC's wakeup timer is clocked at LSE / 16 with WUCKSEL = Div16, so it
+// counts 2_048 times a second, the wakeup flag is set every WAKEUP_TICKS of
+// those, and each measurement is over 16 * WAKEUP_TICKS LSE periods: 32_768,
+// a second. A cycle or two of jitter in picking up the flag is then well
+// under 0.1 ppm.
+//
+const WAKEUP_TICKS: u32 = 2_048;
+const LSE_PERIODS: u32 = 16 * WAKEUP_TICKS;
+
+// Number of times to poll LSERDY for, about a millisecond apart, before
+// giving up on the LSE.
+//
+// An LSE crystal takes up to about 2 s to start, and never does if it isn't
+// fitted.
+//
+const LSE_STARTUP_POLLS: u32 = 3_000;
+
+// Number of measurements to make.
+//
+const MEASUREMENTS: u32 = 5;
+
+// Lifts the write protection on the RTC registers, as in the rtc-alarm
+// example.
+//
+fn rtc_unlock(rtc: &pac::RTC) {
+    rtc.wpr.write(|w| w.key().bits(0xCA));
+    rtc.wpr.write(|w| w.key().bits(0x53));
+}
+
+// Puts the write protection on the RTC registers back, with a wrong key.
+//
+fn rtc_lock(rtc: &pac::RTC) {
+    rtc.wpr.write(|w| w.key().bits(0xFF));
+}
+
+// Waits for the next RTC wakeup flag, and clears it.
+//
+fn wait_for_wakeup(rtc: &pac::RTC) {
+    while rtc.isr.read().wutf().bit_is_clear() {}
+    rtc.isr.modify(|_, w| w.wutf().clear_bit());
+}
+
+#[entry]
+fn main() -> ! {
+    // Get peripherals.
+    //
+    // take() returns an Option, which requires handling the possibility of the
+    // return of an Err or None instead of the desired value, which is of type
+    // pac::Peripherals in this case.
+    //
+    // Since this is an embedded application, it's not as simple as writing to,
+    // stdout. This is a minimal example, so we'll drop into an inifinite loop
+    // to allow a debugger to find where the failure.
+    //
+    let device_periphs = pac::Peripherals::take().unwrap_or_else(|| {
+        loop {
+            // Failed to take pac::Peripherals.
+            asm::nop(); // If real app, replace with actual error handling.
+        }
+    });
+    let mut core_periphs = cortex_m::Peripherals::take().unwrap_or_else(|| {
+        loop {
+            // Failed to take cortex_m::Peripherals.
+            asm::nop(); // If real app, replace with actual error handling.
+        }
+    });
+
+    // Start the LSE and clock the RTC from it.
+    //
+    // This is the same sequence as the rtc-alarm example uses for the LSI:
+    // enable PWR, set DBP to unlock the backup domain, and if the RTC isn't
+    // already on the LSE, reset the backup domain to be allowed to change its
+    // clock. That reset also turns the LSE off, so it's started after.
+    //
+    // The wait for LSERDY is bounded, so a missing crystal is reported rather
+    // than hanging here. It's done before constrain(), since that takes RCC,
+    // while the core is still on the 8 MHz HSI, which asm::delay() counts
+    // cycles of.
+    //
+    let rcc = &device_periphs.RCC;
+    rcc.apb1enr.modify(|_, w| w.pwren().enabled());
+    device_periphs.PWR.cr.modify(|_, w| w.dbp().set_bit());
+
+    if !rcc.bdcr.read().rtcsel().is_lse() {
+        rcc.bdcr.modify(|_, w| w.bdrst().enabled());
+        rcc.bdcr.modify(|_, w| w.bdrst().disabled());
+    }
+    rcc.bdcr.modify(|_, w| w.lseon().on());
+    let lse_ready = (0..LSE_STARTUP_POLLS).any(|_| {
+        asm::delay(8_000);
+        rcc.bdcr.read().lserdy().is_ready()
+    });
+    if lse_ready {
+        rcc.bdcr.modify(|_, w| w.rtcsel().lse().rtcen().enabled());
+    }
+
+    // Get RCC peripheral and set the system clock frequency, from the PLL,
+    // from the HSE.
+    //
+    let mut reset_and_clock_control = device_periphs.RCC.constrain();
+    let mut flash = device_periphs.FLASH.constrain();
+    let clocks = reset_and_clock_control
+        .cfgr
+        .use_hse(HSE_MHZ.MHz())
+        .bypass_hse()
+        .sysclk(48.MHz())
+        .freeze(&mut flash.acr);
+
+    // Get GPIO Port C.
+    //
+    let mut gpioc = device_periphs.GPIOC.split(&mut reset_and_clock_control.ahb);
+
+    // Configure GPIO pins PC10 as TX and PC11 as RX for UART4, the same as in
+    // the uart example, and activate the UART.
+    //
+    let tx_pin = gpioc
+        .pc10
+        .into_af_push_pull(&mut gpioc.moder, &mut gpioc.otyper, &mut gpioc.afrh);
+    let rx_pin = gpioc
+        .pc11
+        .into_af_push_pull(&mut gpioc.moder, &mut gpioc.otyper, &mut gpioc.afrh);
+    let mut uart4 = Serial::new(
+        device_periphs.UART4,
+        (tx_pin, rx_pin),
+        config::Config::default().baudrate(115_200.Bd()),
+        clocks,
+        &mut reset_and_clock_control.apb1,
+    );
+
+    let expected_hz = clocks.sysclk().0;
+    writeln!(
+        uart4,
+        "Configured sysclk: {} Hz, from a {} MHz HSE.\r",
+        expected_hz, HSE_MHZ
+    )
+    .ok();
+
+    if !lse_ready {
+        writeln!(
+            uart4,
+            "The LSE didn't start. Is the 32.768 kHz crystal X2 fitted?\r"
+        )
+        .ok();
+        loop {
+            asm::wfi();
+        }
+    }
+
+    // Start the DWT cycle counter, which counts sysclk cycles, as in the
+    // dwt-delay example.
+    //
+    dwt_delay::init(&mut core_periphs.DCB, &mut core_periphs.DWT);
+
+    // Set the RTC's wakeup timer to set its flag every WAKEUP_TICKS ticks of
+    // LSE / 16.
+    //
+    // WUTR can only be written with the timer disabled, once the RTC says so
+    // with WUTWF, since the RTC runs on its own clock.
+    //
+    let rtc = device_periphs.RTC;
+    rtc_unlock(&rtc);
+    rtc.cr.modify(|_, w| w.wute().clear_bit());
+    while rtc.isr.read().wutwf().bit_is_clear() {}
+    rtc.wutr.write(|w| w.wut().bits((WAKEUP_TICKS - 1) as u16));
+    rtc.cr.modify(|_, w| w.wucksel().div16().wute().set_bit());
+    rtc_lock(&rtc);
+
+    // Measure.
+    //
+    // The LSE is a separate 32.768 kHz crystal, accurate to some tens of ppm,
+    // that nothing in the sysclk's configuration depends on, which is what
+    // makes it a reference to measure the sysclk against. The RTC's wakeup
+    // timer gates the measurement: it sets its flag at the end of every
+    // LSE_PERIODS periods of the LSE, and the DWT cycle counter is read each
+    // time it's seen. The difference between two readings is how many sysclk
+    // cycles fitted in that many LSE periods, which scales to the sysclk
+    // frequency by LSE_HZ / LSE_PERIODS.
+    //
+    // The first flag is waited for and thrown away, since the timer was
+    // started partway through a tick of its clock. The rest are all exactly
+    // a gate apart, with only the few cycles the polling loop takes to pick
+    // each one up as error, which cancels out from one to the next.
+    //
+    // Some STM32s can route the LSE to a timer's input capture, which does
+    // the same in hardware. The RTC needs no routing or wiring, since it's
+    // clocked from the LSE here anyway, and the polling gives the same
+    // accuracy over a gate this long.
+    //
+    wait_for_wakeup(&rtc);
+    let mut last_count = DWT::cycle_count();
+    for _ in 0..MEASUREMENTS {
+        wait_for_wakeup(&rtc);
+        let count = DWT::cycle_count();
+        let cycles = count.wrapping_sub(last_count);
+        last_count = count;
+
+        let measured_hz = u64::from(cycles) * u64::from(LSE_HZ) / u64::from(LSE_PERIODS);
+        let error_ppm =
+            (measured_hz as i64 - i64::from(expected_hz)) * 1_000_000 / i64::from(expected_hz);
+        writeln!(
+            uart4,
+            "Measured sysclk: {} Hz ({:+} ppm)\r",
+            measured_hz, error_ppm
+        )
+        .ok();
+    }
+
+    // An error of a few tens of ppm, which is the LSE's own tolerance as much
+    // as the HSE's, means the clock is what it's configured to be. An error
+    // of a percent or so is an HSI rather than a crystal, and one of tens of
+    // percent is a wrong HSE_MHZ, or a sysclk that isn't from where it's
+    // meant to be at all.
+    //
+    writeln!(uart4, "Done.\r").ok();
+    loop {
+        asm::wfi();
+    }
+}
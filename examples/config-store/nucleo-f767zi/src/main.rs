@@ -0,0 +1,195 @@
+#![deny(unsafe_code)]
+#![no_std]
+#![no_main]
+
+use core::fmt::Write;
+
+// Define the panic handler selected by the panic-* features in Cargo.toml.
+//
+// The default is panic-halt. A breakpoint can be set on `rust_begin_unwind` to
+// catch panics.
+//
+wt_support::panic_handler!();
+
+use cortex_m::asm;
+use cortex_m_rt::entry;
+
+use stm32f7xx_hal::{
+    flash::{self, Flash},
+    pac,
+    prelude::*,
+    serial::{self, Serial},
+};
+
+use wt_support::config_store::{Config, ConfigStore, SlotFlash};
+
+// Location of the slots in flash.
+//
+// They're kept at the start of sector 11, the last 256 KiB sector, which is
+// left out of FLASH in memory.x, the same as in the flash-store example.
+//
+const CONFIG_SECTOR: u8 = 11;
+const CONFIG_OFFSET: usize = 0x1C_0000;
+const FLASH_BASE_ADDRESS: usize = 0x0800_0000;
+
+// Number and size of the slots.
+//
+// A record of the config below is 12 bytes, so each one fits in a 16-byte
+// slot, and 64 of them take up the first 1 KiB of the sector. That's 64 boots
+// between erases of the sector instead of one. The sector has room for far
+// more slots, but load() reads every one of them on boot, so more slots make
+// for a slower boot as well as fewer erases.
+//
+const SLOT_COUNT: usize = 64;
+const SLOT_SIZE: usize = 16;
+
+// Sector 11 of the F767ZI's flash, as a region for the config store.
+//
+struct SectorFlash {
+    flash: Flash,
+}
+
+impl SlotFlash for SectorFlash {
+    type Error = flash::Error;
+
+    fn read(&mut self, offset: usize, buf: &mut [u8]) -> Result<(), flash::Error> {
+        let start = FLASH_BASE_ADDRESS + CONFIG_OFFSET + offset;
+        for (i, byte) in buf.iter_mut().enumerate() {
+            // Flash is memory-mapped, so it can be read like any other memory,
+            // but there's no safe way to read from an arbitrary address.
+            //
+            // SAFETY: The address is in sector 11, which is always mapped and
+            // never part of the program.
+            #[allow(unsafe_code)]
+            unsafe {
+                *byte = core::ptr::read_volatile((start + i) as *const u8);
+            }
+        }
+        Ok(())
+    }
+
+    fn write(&mut self, offset: usize, data: &[u8]) -> Result<(), flash::Error> {
+        // The flash is locked against writes out of reset, so unlock it to
+        // program, then lock it again to guard against stray writes.
+        //
+        self.flash.unlock();
+        let result = self.flash.blocking_program(CONFIG_OFFSET + offset, data);
+        self.flash.lock();
+        result
+    }
+
+    fn erase(&mut self) -> Result<(), flash::Error> {
+        self.flash.unlock();
+        let result = self.flash.blocking_erase_sector(CONFIG_SECTOR);
+        self.flash.lock();
+        result
+    }
+}
+
+// The config kept in flash.
+//
+// It's #[repr(C)] so the layout is fixed, but it's still stored through
+// encode() and decode() rather than as the raw bytes of the struct. See the
+// config_store module for why.
+//
+#[repr(C)]
+#[derive(Default)]
+struct BootConfig {
+    boot_count: u32,
+}
+
+impl Config for BootConfig {
+    const LEN: usize = 4;
+
+    fn encode(&self, buf: &mut [u8]) {
+        buf.copy_from_slice(&self.boot_count.to_le_bytes());
+    }
+
+    fn decode(buf: &[u8]) -> Self {
+        BootConfig {
+            boot_count: u32::from_le_bytes([buf[0], buf[1], buf[2], buf[3]]),
+        }
+    }
+}
+
+#[entry]
+fn main() -> ! {
+    // Get peripherals.
+    //
+    // take() returns an Option, which requires handling the possibility of the
+    // return of an Err or None instead of the desired value, which is of type
+    // pac::Peripherals in this case.
+    //
+    // Since this is an embedded application, it's not as simple as writing to,
+    // stdout. This is a minimal example, so we'll drop into an inifinite loop
+    // to allow a debugger to find where the failure.
+    //
+    let device_periphs = pac::Peripherals::take().unwrap_or_else(|| {
+        loop {
+            // Failed to take pac::Peripherals.
+            asm::nop(); // If real app, replace with actual error handling.
+        }
+    });
+
+    // Get RCC peripheral and set the system clock frequency.
+    //
+    let reset_and_clock_control = device_periphs.RCC.constrain();
+    let clocks = reset_and_clock_control.cfgr.sysclk(48.MHz()).freeze();
+
+    // Set up USART3 on PD8/PD9, which is bridged to the ST-Link virtual COM
+    // port. See the Nucleo UART example for the details.
+    //
+    let gpiod: stm32f7xx_hal::gpio::gpiod::Parts = device_periphs.GPIOD.split();
+    let usart3 = Serial::new(
+        device_periphs.USART3,
+        (gpiod.pd8.into_alternate(), gpiod.pd9.into_alternate()),
+        &clocks,
+        serial::Config {
+            baud_rate: 115_200.bps(),
+            ..Default::default()
+        },
+    );
+    let (mut tx, _rx) = usart3.split();
+
+    // Load the config saved on the last boot, or start from the default if
+    // nothing has been saved yet, then save it back with the count
+    // incremented.
+    //
+    // Each save goes to the next slot along, so the sector is only erased
+    // every SLOT_COUNT boots, when the slots run out. Resetting partway
+    // through a save leaves a record that fails its CRC, and the next boot
+    // loads the one before it instead.
+    //
+    let mut store = ConfigStore::new(
+        SectorFlash {
+            flash: Flash::new(device_periphs.FLASH),
+        },
+        SLOT_COUNT,
+        SLOT_SIZE,
+    );
+    let result = store.load::<BootConfig>().and_then(|config| {
+        let mut config = config.unwrap_or_default();
+        config.boot_count += 1;
+        store.save(&config).map(|_| config)
+    });
+
+    match result {
+        Ok(config) => writeln!(tx, "Boot count: {}\r", config.boot_count),
+        Err(error) => writeln!(tx, "Failed to store config: {:?}\r", error),
+    }
+    .unwrap_or_else(|_| {
+        loop {
+            // Failed to write to USART3.
+            asm::nop(); // If real app, replace with actual error handling.
+        }
+    });
+
+    // Main loop.
+    //
+    // Nothing else to do. Press the reset button to boot again and increment
+    // the count.
+    //
+    loop {
+        asm::wfi();
+    }
+}
@@ -0,0 +1,247 @@
+#![deny(unsafe_code)]
+#![no_std]
+#![no_main]
+
+use core::fmt::Write;
+
+// Define the panic handler selected by the panic-* features in Cargo.toml.
+//
+// The default is panic-halt. A breakpoint can be set on `rust_begin_unwind` to
+// catch panics.
+//
+wt_support::panic_handler!();
+
+use cortex_m::asm;
+use cortex_m_rt::entry;
+
+use stm32f3xx_hal::{
+    delay::Delay,
+    pac,
+    prelude::*,
+    rcc::{Enable, AHB},
+    serial::{config, Serial},
+};
+
+use wt_support::crc;
+
+// Data to compute the CRC of and its expected CRC-32.
+//
+// The expected value was computed ahead of time with a software CRC-32 (e.g.
+// zlib.crc32() in Python), and the host unit tests of wt-support::crc check
+// the software implementation against it too.
+//
+static DATA: &[u8] = b"The quick brown fox jumps over the lazy dog";
+const EXPECTED_CRC: u32 = 0x414F_A339;
+
+// The standard check input for a CRC and its CRC-32.
+//
+static CHECK_DATA: &[u8] = b"123456789";
+const CHECK_CRC: u32 = 0xCBF4_3926;
+
+// Delay in milliseconds between runs.
+//
+const RUN_DELAY_MS: u16 = 5_000;
+
+// The CRC unit, set up to compute the standard CRC-32.
+//
+// This wraps the registers of the unit so the rest of the example doesn't
+// touch them directly. stm32f3xx-hal doesn't provide a CRC type.
+//
+// The CRC unit of the STM32F303 is the newer, programmable kind: the
+// polynomial, its size (7, 8, 16, or 32 bits), the initial value, and whether
+// the input and output bits are reversed can all be set. Out of reset it's set
+// up for the 32-bit polynomial 0x04C11DB7, initial value 0xFFFFFFFF, and no
+// reversal, which is CRC-32/MPEG-2. That's also the only thing the older,
+// fixed CRC units on parts like the STM32F1 and STM32F4 can compute, so
+// getting the more common standard CRC-32 out of those takes reversing the
+// bits of the data and the result in software. Here, the unit reverses them
+// itself.
+//
+struct Crc32 {
+    crc: pac::CRC,
+}
+
+impl Crc32 {
+    fn new(crc: pac::CRC, ahb: &mut AHB) -> Self {
+        pac::CRC::enable(ahb);
+
+        // The polynomial and the initial value are already right after reset,
+        // but are set anyway so this doesn't depend on that.
+        //
+        // The input is reversed by byte, so each byte is processed least
+        // significant bit first no matter what size the write was, and the
+        // output is reversed as a whole word.
+        //
+        crc.pol.write(|w| w.pol().bits(crc::POLYNOMIAL));
+        crc.init.write(|w| w.init().bits(crc::INITIAL));
+        crc.cr.write(|w| {
+            w.polysize()
+                .polysize32()
+                .rev_in()
+                .byte()
+                .rev_out()
+                .reversed()
+        });
+
+        Crc32 { crc }
+    }
+
+    // Resets the data register to the initial value to start a new CRC.
+    //
+    // The data register holds the running CRC, so every write continues from
+    // whatever was computed before it. Without a reset between buffers, the
+    // result is the CRC of everything written since the last reset, not of
+    // the new buffer alone. The reset takes effect immediately, so the next
+    // write can follow right away.
+    //
+    fn reset(&mut self) {
+        self.crc.cr.modify(|_, w| w.reset().reset());
+    }
+
+    // Adds the bytes of data to the running CRC.
+    //
+    // Most of the data is written a word at a time, which processes four bytes
+    // per write. The unit processes a 32-bit write from its most significant
+    // byte down, so each word is assembled big-endian to keep the bytes in
+    // order. The 0-3 bytes left over at the end are written one at a time
+    // through the byte-sized view of the data register.
+    //
+    fn feed(&mut self, data: &[u8]) {
+        let mut words = data.chunks_exact(4);
+        for word in &mut words {
+            let word = u32::from_be_bytes([word[0], word[1], word[2], word[3]]);
+            self.crc.dr().write(|w| w.dr().bits(word));
+        }
+        for &byte in words.remainder() {
+            self.crc.dr8().write(|w| w.dr8().bits(byte));
+        }
+    }
+
+    // Returns the CRC of everything fed since the last reset.
+    //
+    // The final XOR of the standard CRC-32 isn't something the unit does, so
+    // it's done here.
+    //
+    fn result(&self) -> u32 {
+        self.crc.dr().read().dr().bits() ^ crc::FINAL_XOR
+    }
+}
+
+#[entry]
+fn main() -> ! {
+    // Get peripherals.
+    //
+    // take() returns an Option, which requires handling the possibility of the
+    // return of an Err or None instead of the desired value, which is of type
+    // pac::Peripherals in this case.
+    //
+    // Since this is an embedded application, it's not as simple as writing to,
+    // stdout. This is a minimal example, so we'll drop into an inifinite loop
+    // to allow a debugger to find where the failure.
+    //
+    let device_periphs = pac::Peripherals::take().unwrap_or_else(|| {
+        loop {
+            // Failed to take pac::Peripherals.
+            asm::nop(); // If real app, replace with actual error handling.
+        }
+    });
+
+    // Get RCC peripheral and set the system clock frequency.
+    //
+    let mut reset_and_clock_control = device_periphs.RCC.constrain();
+    let mut flash = device_periphs.FLASH.constrain();
+    let clocks = reset_and_clock_control
+        .cfgr
+        .sysclk(48.MHz())
+        .freeze(&mut flash.acr);
+
+    // Set up delay capability.
+    //
+    let core_periphs = cortex_m::Peripherals::take().unwrap_or_else(|| {
+        loop {
+            // Failed to take cortex_m::Peripherals.
+            asm::nop(); // If real app, replace with actual error handling.
+        }
+    });
+    let mut delay = Delay::new(core_periphs.SYST, clocks);
+
+    // Get GPIO Port C.
+    //
+    let mut gpioc = device_periphs.GPIOC.split(&mut reset_and_clock_control.ahb);
+
+    // Configure GPIO pins PC10 as TX and PC11 as RX for UART4, the same as in
+    // the uart example, and activate the UART.
+    //
+    let tx_pin = gpioc
+        .pc10
+        .into_af_push_pull(&mut gpioc.moder, &mut gpioc.otyper, &mut gpioc.afrh);
+    let rx_pin = gpioc
+        .pc11
+        .into_af_push_pull(&mut gpioc.moder, &mut gpioc.otyper, &mut gpioc.afrh);
+    let mut uart4 = Serial::new(
+        device_periphs.UART4,
+        (tx_pin, rx_pin),
+        config::Config::default().baudrate(115_200.Bd()),
+        clocks,
+        &mut reset_and_clock_control.apb1,
+    );
+
+    // Set up the CRC unit.
+    //
+    let mut crc32 = Crc32::new(device_periphs.CRC, &mut reset_and_clock_control.ahb);
+
+    loop {
+        // Compute the CRC of DATA and compare it to the expected value.
+        //
+        crc32.reset();
+        crc32.feed(DATA);
+        let data_crc = crc32.result();
+        writeln!(
+            uart4,
+            "CRC-32 of {:?}: 0x{:08X} (expected 0x{:08X}) {}\r",
+            core::str::from_utf8(DATA).unwrap_or(""),
+            data_crc,
+            EXPECTED_CRC,
+            if data_crc == EXPECTED_CRC {
+                "OK"
+            } else {
+                "MISMATCH"
+            }
+        )
+        .ok();
+
+        // Compute the CRC of the check data, resetting first.
+        //
+        crc32.reset();
+        crc32.feed(CHECK_DATA);
+        let check_crc = crc32.result();
+        writeln!(
+            uart4,
+            "CRC-32 of {:?}: 0x{:08X} (expected 0x{:08X}) {}\r",
+            core::str::from_utf8(CHECK_DATA).unwrap_or(""),
+            check_crc,
+            CHECK_CRC,
+            if check_crc == CHECK_CRC {
+                "OK"
+            } else {
+                "MISMATCH"
+            }
+        )
+        .ok();
+
+        // Compute it again without resetting.
+        //
+        // This continues from the CRC of the check data, so the result is the
+        // CRC of the check data twice over rather than the check value.
+        //
+        crc32.feed(CHECK_DATA);
+        writeln!(
+            uart4,
+            "Without a reset in between, the same data gives 0x{:08X}.\r\n",
+            crc32.result()
+        )
+        .ok();
+
+        delay.delay_ms(RUN_DELAY_MS);
+    }
+}
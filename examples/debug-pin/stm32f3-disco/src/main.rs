@@ -0,0 +1,143 @@
+#![deny(unsafe_code)]
+#![no_std]
+#![no_main]
+
+use core::fmt::Write;
+
+// Define the panic handler selected by the panic-* features in Cargo.toml.
+//
+// The default is panic-halt. A breakpoint can be set on `rust_begin_unwind` to
+// catch panics.
+//
+wt_support::panic_handler!();
+
+use cortex_m::asm;
+use cortex_m_rt::entry;
+
+use stm32f3xx_hal::{
+    delay::Delay,
+    pac,
+    prelude::*,
+    serial::{config, Serial},
+};
+
+use wt_support::debug_pin::DebugPin;
+use wt_support::led::{PinLed, UserLed};
+
+// Time LD3 stays in each state in milliseconds.
+//
+const BLINK_DELAY_MS: u16 = 250;
+
+#[entry]
+fn main() -> ! {
+    // Get peripherals.
+    //
+    // take() returns an Option, which requires handling the possibility of the
+    // return of an Err or None instead of the desired value, which is of type
+    // pac::Peripherals in this case.
+    //
+    // Since this is an embedded application, it's not as simple as writing to,
+    // stdout. This is a minimal example, so we'll drop into an inifinite loop
+    // to allow a debugger to find where the failure.
+    //
+    let device_periphs = pac::Peripherals::take().unwrap_or_else(|| {
+        loop {
+            // Failed to take pac::Peripherals.
+            asm::nop(); // If real app, replace with actual error handling.
+        }
+    });
+    let core_periphs = cortex_m::Peripherals::take().unwrap_or_else(|| {
+        loop {
+            // Failed to take cortex_m::Peripherals.
+            asm::nop(); // If real app, replace with actual error handling.
+        }
+    });
+
+    // Get RCC peripheral and set the system clock frequency.
+    //
+    let mut reset_and_clock_control = device_periphs.RCC.constrain();
+    let mut flash = device_periphs.FLASH.constrain();
+    let clocks = reset_and_clock_control
+        .cfgr
+        .sysclk(48.MHz())
+        .freeze(&mut flash.acr);
+    let mut delay = Delay::new(core_periphs.SYST, clocks);
+
+    // Get GPIO Ports C, D, and E.
+    //
+    let mut gpioc = device_periphs.GPIOC.split(&mut reset_and_clock_control.ahb);
+    let mut gpiod = device_periphs.GPIOD.split(&mut reset_and_clock_control.ahb);
+    let mut gpioe = device_periphs.GPIOE.split(&mut reset_and_clock_control.ahb);
+
+    // Configure GPIO pins PC10 as TX and PC11 as RX for UART4, the same as in
+    // the uart example, and activate the UART.
+    //
+    let tx_pin = gpioc
+        .pc10
+        .into_af_push_pull(&mut gpioc.moder, &mut gpioc.otyper, &mut gpioc.afrh);
+    let rx_pin = gpioc
+        .pc11
+        .into_af_push_pull(&mut gpioc.moder, &mut gpioc.otyper, &mut gpioc.afrh);
+    let mut uart4 = Serial::new(
+        device_periphs.UART4,
+        (tx_pin, rx_pin),
+        config::Config::default().baudrate(115_200.Bd()),
+        clocks,
+        &mut reset_and_clock_control.apb1,
+    );
+
+    // Create LD3 as a wt_support::led::PinLed.
+    //
+    let mut ld3 = PinLed::new(
+        gpioe
+            .pe9
+            .into_push_pull_output(&mut gpioe.moder, &mut gpioe.otyper),
+    );
+
+    // Create the debug pins, on PD0 and PD1, which nothing else on the board
+    // uses.
+    //
+    // Connect a logic analyzer or scope to both, with its ground on GND.
+    //
+    // - PD0 pulses once at the top of each pass of the main loop, so the time
+    //   between pulses is how long a pass takes, delay and all.
+    // - PD1 is high while the body of the loop is running, which is everything
+    //   but the delay, so its width is how long the loop's own work takes.
+    //
+    let mut loop_pin = DebugPin::new(
+        gpiod
+            .pd0
+            .into_push_pull_output(&mut gpiod.moder, &mut gpiod.otyper),
+    );
+    let mut body_pin = DebugPin::new(
+        gpiod
+            .pd1
+            .into_push_pull_output(&mut gpiod.moder, &mut gpiod.otyper),
+    );
+
+    // Main loop.
+    //
+    // Toggle LD3 and write a line out via UART4, then wait. At 115200 baud,
+    // each character takes about 87 us to send, and write!() blocks until the
+    // last one is in the transmit register, so the PD1 pulse is mostly the
+    // UART: around a millisecond for the dozen or so characters of the line,
+    // against a few hundred nanoseconds for the toggle. The PD0 pulses come
+    // BLINK_DELAY_MS plus that apart.
+    //
+    // That's the kind of thing this makes plain: slowing the line down or
+    // speeding it up shows straight away on PD1, with nothing added to the code
+    // being timed but two stores.
+    //
+    let mut count: u32 = 0;
+    loop {
+        loop_pin.pulse();
+
+        body_pin.mark_start();
+        ld3.toggle();
+        count = count.wrapping_add(1);
+        writeln!(uart4, "Blink {}\r", count).ok();
+        body_pin.mark_end();
+
+        delay.delay_ms(BLINK_DELAY_MS);
+    }
+}
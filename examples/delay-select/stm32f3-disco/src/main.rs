@@ -0,0 +1,204 @@
+#![deny(unsafe_code)]
+#![no_std]
+#![no_main]
+
+use core::fmt::Write;
+
+// Define the panic handler selected by the panic-* features in Cargo.toml.
+//
+// The default is panic-halt. A breakpoint can be set on `rust_begin_unwind` to
+// catch panics.
+//
+wt_support::panic_handler!();
+
+use cortex_m::asm;
+use cortex_m_rt::entry;
+
+use stm32f3xx_hal::{
+    pac,
+    prelude::*,
+    rcc::{BusTimerClock, Enable, Reset},
+    serial::config,
+    serial::Serial,
+};
+
+use wt_support::delay::{BlockingDelay, DwtDelay, SysTickDelay};
+use wt_support::dwt_delay;
+use wt_support::profile::Stopwatch;
+use wt_support::tim_delay::{FreeRunningCounter, TimerDelay};
+
+// Frequency TIM2 counts at after the prescaler.
+//
+// At 1 MHz, the TIM2 delay resolves to a microsecond, and the 32-bit counter
+// wraps around every 71 minutes or so.
+//
+const TIM2_TICK_HZ: u32 = 1_000_000;
+
+// TIM2 as a free-running counter for the TIM2 delay, the same as in the
+// tim-delay example.
+//
+struct Tim2Counter(pac::TIM2);
+
+impl FreeRunningCounter for Tim2Counter {
+    const MAX: u32 = u32::MAX;
+
+    fn count(&self) -> u32 {
+        self.0.cnt.read().bits()
+    }
+}
+
+// Interval between toggles of LD3, and the number of times it's toggled with
+// each delay before moving on to the next.
+//
+const LED_TOGGLE_INTERVAL_MS: u32 = 250;
+const TOGGLES_PER_DELAY: u32 = 8;
+
+// Short delay to time with each of the delays, to show their overhead.
+//
+const SHORT_DELAY_US: u32 = 10;
+
+#[entry]
+fn main() -> ! {
+    // Get peripherals.
+    //
+    // take() returns an Option, which requires handling the possibility of the
+    // return of an Err or None instead of the desired value, which is of type
+    // pac::Peripherals in this case.
+    //
+    // Since this is an embedded application, it's not as simple as writing to,
+    // stdout. This is a minimal example, so we'll drop into an inifinite loop
+    // to allow a debugger to find where the failure.
+    //
+    let device_periphs = pac::Peripherals::take().unwrap_or_else(|| {
+        loop {
+            // Failed to take pac::Peripherals.
+            asm::nop(); // If real app, replace with actual error handling.
+        }
+    });
+    let mut core_periphs = cortex_m::Peripherals::take().unwrap_or_else(|| {
+        loop {
+            // Failed to take cortex_m::Peripherals.
+            asm::nop(); // If real app, replace with actual error handling.
+        }
+    });
+
+    // Get RCC peripheral and set the system clock frequency.
+    //
+    let mut reset_and_clock_control = device_periphs.RCC.constrain();
+    let mut flash = device_periphs.FLASH.constrain();
+    let clocks = reset_and_clock_control
+        .cfgr
+        .sysclk(48.MHz())
+        .pclk1(24.MHz())
+        .freeze(&mut flash.acr);
+    let sysclk_hz = clocks.sysclk().0;
+
+    // Enable the DWT cycle counter for the DWT delay and for timing the
+    // delays.
+    //
+    if !dwt_delay::init(&mut core_periphs.DCB, &mut core_periphs.DWT) {
+        loop {
+            // No DWT cycle counter.
+            asm::nop(); // If real app, replace with actual error handling.
+        }
+    }
+
+    // Set up TIM2 as a free-running counter for the TIM2 delay.
+    //
+    // The timer is started once and never stopped: the delay only ever reads
+    // the counter, so it counts up through the whole 32 bits and wraps around
+    // to 0. As in the servo example, the timer clock is twice PCLK1, since the
+    // APB1 prescaler isn't 1, which timer_clock() accounts for.
+    //
+    pac::TIM2::enable(&mut reset_and_clock_control.apb1);
+    pac::TIM2::reset(&mut reset_and_clock_control.apb1);
+    let tim2 = device_periphs.TIM2;
+    let prescaler = (pac::TIM2::timer_clock(&clocks).0 / TIM2_TICK_HZ - 1) as u16;
+    tim2.psc.write(|w| w.psc().bits(prescaler));
+    tim2.arr.write(|w| w.arr().bits(Tim2Counter::MAX));
+    tim2.egr.write(|w| w.ug().update());
+    tim2.cr1.modify(|_, w| w.cen().enabled());
+
+    // Create the three delays.
+    //
+    // SysTickDelay takes SysTick, so with it in use, wt_support::millis or an
+    // RTOS tick couldn't have SysTick too. The other two leave it free: the
+    // TIM2 delay takes a timer instead, and the DWT delay only reads the cycle
+    // counter, which other code, like the Stopwatch below, can read at the same
+    // time.
+    //
+    let mut systick_delay = SysTickDelay::new(core_periphs.SYST, sysclk_hz);
+    let mut tim2_delay = TimerDelay::new(Tim2Counter(tim2), TIM2_TICK_HZ);
+    let mut dwt_delay = DwtDelay::new(sysclk_hz);
+    let mut delays: [(&str, &mut dyn BlockingDelay); 3] = [
+        ("SysTick", &mut systick_delay),
+        ("TIM2", &mut tim2_delay),
+        ("DWT", &mut dwt_delay),
+    ];
+
+    // Get GPIO Ports C and E.
+    //
+    let mut gpioc = device_periphs.GPIOC.split(&mut reset_and_clock_control.ahb);
+    let mut gpioe = device_periphs.GPIOE.split(&mut reset_and_clock_control.ahb);
+
+    // Configure GPIO pins PC10 as TX and PC11 as RX for UART4, the same as in
+    // the uart example, and activate the UART.
+    //
+    let tx_pin = gpioc
+        .pc10
+        .into_af_push_pull(&mut gpioc.moder, &mut gpioc.otyper, &mut gpioc.afrh);
+    let rx_pin = gpioc
+        .pc11
+        .into_af_push_pull(&mut gpioc.moder, &mut gpioc.otyper, &mut gpioc.afrh);
+    let mut uart4 = Serial::new(
+        device_periphs.UART4,
+        (tx_pin, rx_pin),
+        config::Config::default().baudrate(115_200.Bd()),
+        clocks,
+        &mut reset_and_clock_control.apb1,
+    );
+
+    // Time a short delay with each one.
+    //
+    // The longer delays in the blinking below come out within a fraction of a
+    // percent of what's asked with all three, since they all count the same
+    // clock, but a short one shows the overhead of each: SysTickDelay
+    // reprograms SysTick for every delay, and the TIM2 delay reads the counter
+    // over the APB1 bus and waits a tick more than asked, while the DWT delay
+    // only reads a core register.
+    //
+    for (name, delay) in delays.iter_mut() {
+        let cycles = Stopwatch::measure(|| delay.delay_us(SHORT_DELAY_US));
+        writeln!(
+            uart4,
+            "{} us with {}: {} cycles ({} requested)\r",
+            SHORT_DELAY_US,
+            name,
+            cycles,
+            SHORT_DELAY_US * (sysclk_hz / 1_000_000)
+        )
+        .ok();
+    }
+
+    // Create an instance of LD3 (PE9) in push-pull output mode.
+    //
+    let mut led = gpioe
+        .pe9
+        .into_push_pull_output(&mut gpioe.moder, &mut gpioe.otyper);
+
+    // Main loop.
+    //
+    // Blink LD3 with each delay in turn. The blinking looks the same whichever
+    // delay is doing it, since the code doing the blinking only sees a
+    // BlockingDelay.
+    //
+    loop {
+        for (name, delay) in delays.iter_mut() {
+            writeln!(uart4, "Blinking with the {} delay.\r", name).ok();
+            for _ in 0..TOGGLES_PER_DELAY {
+                led.toggle().ok();
+                delay.delay_ms(LED_TOGGLE_INTERVAL_MS);
+            }
+        }
+    }
+}
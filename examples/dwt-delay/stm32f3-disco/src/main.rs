@@ -0,0 +1,163 @@
+#![deny(unsafe_code)]
+#![no_std]
+#![no_main]
+
+use core::fmt::Write;
+
+// Define the panic handler selected by the panic-* features in Cargo.toml.
+//
+// The default is panic-halt. A breakpoint can be set on `rust_begin_unwind` to
+// catch panics.
+//
+wt_support::panic_handler!();
+
+use cortex_m::asm;
+use cortex_m::peripheral::DWT;
+use cortex_m_rt::entry;
+
+use stm32f3xx_hal::{delay::Delay, pac, prelude::*, serial::config, serial::Serial};
+
+use wt_support::dwt_delay;
+
+// Delays to compare, in microseconds.
+//
+const REQUESTED_US: [u32; 5] = [1, 5, 10, 100, 1_000];
+
+// Interval between toggles of LD3.
+//
+const LED_TOGGLE_INTERVAL_US: u32 = 500_000;
+
+// Measures how long `delay` takes, in core clock cycles.
+//
+// The cycle counter is read right before and right after, so the result also
+// includes the few cycles it takes to call into the delay and read the
+// counter, which is part of the overhead of a short delay anyway.
+//
+fn measure_cycles(delay: impl FnOnce()) -> u32 {
+    let start = DWT::cycle_count();
+    delay();
+    DWT::cycle_count().wrapping_sub(start)
+}
+
+#[entry]
+fn main() -> ! {
+    // Get peripherals.
+    //
+    // take() returns an Option, which requires handling the possibility of the
+    // return of an Err or None instead of the desired value, which is of type
+    // pac::Peripherals in this case.
+    //
+    // Since this is an embedded application, it's not as simple as writing to,
+    // stdout. This is a minimal example, so we'll drop into an inifinite loop
+    // to allow a debugger to find where the failure.
+    //
+    let device_periphs = pac::Peripherals::take().unwrap_or_else(|| {
+        loop {
+            // Failed to take pac::Peripherals.
+            asm::nop(); // If real app, replace with actual error handling.
+        }
+    });
+    let mut core_periphs = cortex_m::Peripherals::take().unwrap_or_else(|| {
+        loop {
+            // Failed to take cortex_m::Peripherals.
+            asm::nop(); // If real app, replace with actual error handling.
+        }
+    });
+
+    // Get RCC peripheral and set the system clock frequency.
+    //
+    let mut reset_and_clock_control = device_periphs.RCC.constrain();
+    let mut flash = device_periphs.FLASH.constrain();
+    let clocks = reset_and_clock_control
+        .cfgr
+        .sysclk(48.MHz())
+        .freeze(&mut flash.acr);
+    let sysclk_hz = clocks.sysclk().0;
+
+    // Enable the DWT cycle counter.
+    //
+    // The Cortex-M4F of the STM32F303 always has one, so this can't fail here,
+    // but the delays would hang without it, so check anyway.
+    //
+    if !dwt_delay::init(&mut core_periphs.DCB, &mut core_periphs.DWT) {
+        loop {
+            // No DWT cycle counter.
+            asm::nop(); // If real app, replace with actual error handling.
+        }
+    }
+
+    // Set up the SysTick delay to compare against.
+    //
+    let mut delay = Delay::new(core_periphs.SYST, clocks);
+
+    // Get GPIO Ports C and E.
+    //
+    let mut gpioc = device_periphs.GPIOC.split(&mut reset_and_clock_control.ahb);
+    let mut gpioe = device_periphs.GPIOE.split(&mut reset_and_clock_control.ahb);
+
+    // Configure GPIO pins PC10 as TX and PC11 as RX for UART4, the same as in
+    // the uart example, and activate the UART.
+    //
+    let tx_pin = gpioc
+        .pc10
+        .into_af_push_pull(&mut gpioc.moder, &mut gpioc.otyper, &mut gpioc.afrh);
+    let rx_pin = gpioc
+        .pc11
+        .into_af_push_pull(&mut gpioc.moder, &mut gpioc.otyper, &mut gpioc.afrh);
+    let mut uart4 = Serial::new(
+        device_periphs.UART4,
+        (tx_pin, rx_pin),
+        config::Config::default().baudrate(115_200.Bd()),
+        clocks,
+        &mut reset_and_clock_control.apb1,
+    );
+
+    // Compare the two delays.
+    //
+    // Both are timed with the cycle counter, and the results converted to
+    // microseconds with two decimal places. The SysTick Delay works out a
+    // reload value for each call, programs SysTick, and polls its COUNTFLAG, so
+    // it has a fixed overhead on top of the time asked for that swamps the
+    // shortest delays. The DWT delay just reads the free-running counter, so it
+    // comes within a few cycles of the time asked for, even at 1 us.
+    //
+    writeln!(uart4, "Requested vs. measured delay at {} Hz:\r", sysclk_hz).ok();
+    for us in REQUESTED_US {
+        let dwt_cycles = measure_cycles(|| dwt_delay::delay_us(us, sysclk_hz));
+        let systick_cycles = measure_cycles(|| delay.delay_us(us));
+
+        // Converts cycles to hundredths of a microsecond.
+        let to_centi_us = |cycles: u32| cycles as u64 * 100_000_000 / sysclk_hz as u64;
+        let dwt = to_centi_us(dwt_cycles);
+        let systick = to_centi_us(systick_cycles);
+        writeln!(
+            uart4,
+            "{:>5} us: DWT {:>4}.{:02} us ({} cycles), SysTick {:>4}.{:02} us ({} cycles)\r",
+            us,
+            dwt / 100,
+            dwt % 100,
+            dwt_cycles,
+            systick / 100,
+            systick % 100,
+            systick_cycles,
+        )
+        .ok();
+    }
+
+    // Create an instance of LD3 (PE9) in push-pull output mode.
+    //
+    let mut led = gpioe
+        .pe9
+        .into_push_pull_output(&mut gpioe.moder, &mut gpioe.otyper);
+
+    // Main loop.
+    //
+    // Blink LD3 with the DWT delay. It doesn't need SysTick, so without the
+    // comparison above, SysTick would be left free for wt_support::millis or
+    // an RTOS tick.
+    //
+    loop {
+        led.toggle().ok();
+        dwt_delay::delay_us(LED_TOGGLE_INTERVAL_US, sysclk_hz);
+    }
+}
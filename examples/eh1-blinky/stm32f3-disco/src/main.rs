@@ -0,0 +1,169 @@
+#![deny(unsafe_code)]
+#![no_std]
+#![no_main]
+
+use core::convert::Infallible;
+
+// Define the panic handler selected by the panic-* features in Cargo.toml.
+//
+// The default is panic-halt. A breakpoint can be set on `rust_begin_unwind` to
+// catch panics.
+//
+wt_support::panic_handler!();
+
+use cortex_m::asm;
+use cortex_m_rt::entry;
+
+use embedded_hal_1::delay::DelayNs;
+use embedded_hal_1::digital::{ErrorType, OutputPin};
+
+use stm32f3xx_hal::delay::Delay;
+use stm32f3xx_hal::hal as embedded_hal_02;
+use stm32f3xx_hal::pac;
+use stm32f3xx_hal::prelude::*;
+
+// Time the LED is on and then off for in each blink, in milliseconds.
+//
+const BLINK_HALF_PERIOD_MS: u32 = 500;
+
+// Blinks `pin` once: high, then low, for BLINK_HALF_PERIOD_MS each.
+//
+// This is the portable part. It only knows about the embedded-hal 1.0 traits,
+// not about the STM32F3, GPIO Port E, or SysTick, so it would build unchanged
+// against any HAL that implements them, for any chip. A driver written this
+// way, e.g. for an LED strip or a display, can be published once and used on
+// every board.
+//
+// The 1.0 OutputPin methods return a Result, with the error type of the pin.
+// The pins here can't fail, but the function doesn't know that, so it passes
+// the error up for the caller to deal with.
+//
+fn blink<P: OutputPin, D: DelayNs>(pin: &mut P, delay: &mut D) -> Result<(), P::Error> {
+    pin.set_high()?;
+    delay.delay_ms(BLINK_HALF_PERIOD_MS);
+    pin.set_low()?;
+    delay.delay_ms(BLINK_HALF_PERIOD_MS);
+    Ok(())
+}
+
+// An output pin of stm32f3xx-hal, given the embedded-hal 1.0 OutputPin trait.
+//
+// stm32f3xx-hal 0.10 only implements the embedded-hal 0.2 traits, so its pins
+// can't be passed to blink() as they are. This wrapper implements the 1.0
+// trait by calling through to the 0.2 one. A HAL that implements 1.0 itself,
+// such as embassy-stm32 in the embassy examples, or a newer stm32f3xx-hal,
+// doesn't need it.
+//
+struct Eh1Pin<P>(P);
+
+impl<P> ErrorType for Eh1Pin<P> {
+    type Error = Infallible;
+}
+
+impl<P: embedded_hal_02::digital::v2::OutputPin<Error = Infallible>> OutputPin for Eh1Pin<P> {
+    fn set_low(&mut self) -> Result<(), Self::Error> {
+        self.0.set_low()
+    }
+
+    fn set_high(&mut self) -> Result<(), Self::Error> {
+        self.0.set_high()
+    }
+}
+
+// The SysTick delay of stm32f3xx-hal, given the embedded-hal 1.0 DelayNs trait,
+// for the same reason as Eh1Pin.
+//
+// DelayNs only requires delay_ns(). The SysTick delay can't time anything
+// shorter than a microsecond, so it's rounded up to a whole number of them,
+// since a delay is allowed to be longer than asked for but never shorter.
+// delay_us() and delay_ms() are passed straight through, rather than going
+// through delay_ns() and overflowing it for anything over about 4 seconds.
+//
+struct Eh1Delay(Delay);
+
+impl DelayNs for Eh1Delay {
+    fn delay_ns(&mut self, ns: u32) {
+        embedded_hal_02::blocking::delay::DelayUs::delay_us(&mut self.0, ns.div_ceil(1_000));
+    }
+
+    fn delay_us(&mut self, us: u32) {
+        embedded_hal_02::blocking::delay::DelayUs::delay_us(&mut self.0, us);
+    }
+
+    fn delay_ms(&mut self, ms: u32) {
+        embedded_hal_02::blocking::delay::DelayMs::delay_ms(&mut self.0, ms);
+    }
+}
+
+#[entry]
+fn main() -> ! {
+    // Get peripherals.
+    //
+    // take() returns an Option, which requires handling the possibility of the
+    // return of an Err or None instead of the desired value, which is of type
+    // pac::Peripherals in this case.
+    //
+    // Since this is an embedded application, it's not as simple as writing to,
+    // stdout. This is a minimal example, so we'll drop into an inifinite loop
+    // to allow a debugger to find where the failure.
+    //
+    let device_periphs = pac::Peripherals::take().unwrap_or_else(|| {
+        loop {
+            // Failed to take pac::Peripherals.
+            asm::nop(); // If real app, replace with actual error handling.
+        }
+    });
+    let core_periphs = cortex_m::Peripherals::take().unwrap_or_else(|| {
+        loop {
+            // Failed to take cortex_m::Peripherals.
+            asm::nop(); // If real app, replace with actual error handling.
+        }
+    });
+
+    // Get RCC peripheral and set up the clocks and the delay, as in the
+    // blinky example, but with the delay wrapped to give it the 1.0 trait.
+    //
+    let mut reset_and_clock_control = device_periphs.RCC.constrain();
+    let mut flash = device_periphs.FLASH.constrain();
+    let clocks = reset_and_clock_control.cfgr.freeze(&mut flash.acr);
+    let mut delay = Eh1Delay(Delay::new(core_periphs.SYST, clocks));
+
+    // Get GPIO Port E.
+    //
+    let mut gpioe = device_periphs.GPIOE.split(&mut reset_and_clock_control.ahb);
+
+    // Create an instance of LD3 (PE9) in push-pull output mode, wrapped to give
+    // it the 1.0 trait.
+    //
+    // Compared to the switch-hal crate:
+    //
+    // The stm32f3-discovery board crate, whose Leds type the blinky example
+    // replaces, hands out its LEDs as switch-hal OutputSwitch values, with
+    // on() and off() methods. OutputSwitch sits on top of an embedded-hal 0.2
+    // OutputPin and adds whether the pin is active-high or active-low, so
+    // on() drives the pin to whichever level lights the LED. It describes
+    // what the pin is for, and the board crate picks the level.
+    //
+    // The embedded-hal OutputPin, on the other hand, is just the electrical
+    // level of the pin, high or low, with nothing about what's attached to
+    // it. It's the trait every HAL implements, and the one drivers are written
+    // against, so code written for it, like blink(), runs on anything. LD3 is
+    // active-high, so high is on here.
+    //
+    // switch-hal 0.4 is built on embedded-hal 0.2, so it can't wrap a 1.0 pin.
+    //
+    let mut led = Eh1Pin(
+        gpioe
+            .pe9
+            .into_push_pull_output(&mut gpioe.moder, &mut gpioe.otyper),
+    );
+
+    // Main loop.
+    //
+    // The error type of the pin is Infallible, so the Result can be matched
+    // away with no error handling at all.
+    //
+    loop {
+        let Ok(()) = blink(&mut led, &mut delay);
+    }
+}
@@ -0,0 +1,181 @@
+#![deny(unsafe_code)]
+#![no_std]
+#![no_main]
+
+// Define the panic handler selected by the panic-* features in Cargo.toml.
+//
+// The default is panic-halt. A breakpoint can be set on `rust_begin_unwind` to
+// catch panics.
+//
+wt_support::panic_handler!();
+
+// Use RTT as the transport for the defmt log messages.
+//
+// RTT (Real-Time Transfer) writes the messages into a ring buffer in RAM that
+// the debug probe reads out in the background, so logging doesn't need a UART
+// and barely slows the program down. The messages are read and decoded on the
+// host with e.g. probe-rs:
+//
+// $ probe-rs run --chip STM32F303VCTx target/thumbv7em-none-eabihf/debug/example-embassy-i2c
+//
+// The log level is set at compile time with DEFMT_LOG, which the
+// .cargo/config.toml in the root of the repo sets to info.
+//
+use defmt_rtt as _;
+
+use cortex_m::asm;
+use embassy_executor::Spawner;
+use embassy_stm32::{
+    bind_interrupts, dma,
+    i2c::{self, I2c, Master},
+    mode::Async,
+    peripherals,
+};
+use embassy_time::Timer;
+
+// I2C address of the accelerometer in the LSM303DLHC, with SA0 tied high on the
+// STM32F3DISCOVERY.
+//
+const ACCEL_ADDRESS: u8 = 0x19;
+
+// Accelerometer registers.
+//
+const CTRL_REG1_A: u8 = 0x20;
+const CTRL_REG4_A: u8 = 0x23;
+const OUT_X_L_A: u8 = 0x28;
+
+// Setting the MSB of the register address makes the LSM303DLHC increment the
+// address after each byte, so all six output registers can be read in one
+// transfer.
+//
+const AUTO_INCREMENT: u8 = 0x80;
+
+// CTRL_REG1_A: 100 Hz output data rate, normal power mode, all three axes
+// enabled.
+//
+const CTRL_REG1_A_100HZ_XYZ: u8 = 0x57;
+
+// CTRL_REG4_A: High resolution (12-bit) output at the default full scale of
+// +/-2 g, where one LSB of the output shifted down to 12 bits is 1 mg.
+//
+const CTRL_REG4_A_HR: u8 = 0x08;
+
+// Interval between readings.
+//
+const READ_INTERVAL_MS: u64 = 500;
+
+// Bind the interrupt handlers the async I2C driver needs.
+//
+// The driver needs both of the I2C1 interrupts, the event one and the error
+// one, and the interrupts of the two DMA channels it transfers the data with.
+// Each await of a transfer starts it, then suspends the task until one of
+// these handlers wakes it again once the transfer is done or has failed.
+//
+// The DMA channels aren't free to pick. On the STM32F303, the I2C1 requests are
+// hardwired to DMA1: TX to channel 6 and RX to channel 7, per the DMA1 request
+// mapping in the reference manual (RM0316). I2c::new() only accepts those two,
+// since the driver only implements its TxDma and RxDma traits for them, so
+// passing another channel doesn't compile. A channel can only serve one
+// peripheral at a time, so these can't also be used for e.g. USART2, which
+// shares them.
+//
+bind_interrupts!(struct Irqs {
+    I2C1_EV => i2c::EventInterruptHandler<peripherals::I2C1>;
+    I2C1_ER => i2c::ErrorInterruptHandler<peripherals::I2C1>;
+    DMA1_CHANNEL6 => dma::InterruptHandler<peripherals::DMA1_CH6>;
+    DMA1_CHANNEL7 => dma::InterruptHandler<peripherals::DMA1_CH7>;
+});
+
+// Reads the acceleration on each axis, in mg.
+//
+// write_read() writes the register address, then reads the six output
+// registers starting from it with a repeated start, the same as the blocking
+// WriteRead trait. It returns a future, and the await suspends the task until
+// the DMA has moved all six bytes, instead of spinning on the I2C flags for
+// each one.
+//
+async fn read_accel(i2c: &mut I2c<'static, Async, Master>) -> Result<[i16; 3], i2c::Error> {
+    let mut buf = [0_u8; 6];
+    i2c.write_read(ACCEL_ADDRESS, &[OUT_X_L_A | AUTO_INCREMENT], &mut buf)
+        .await?;
+
+    // Each axis is a little-endian, left-justified 12-bit value, so it's
+    // shifted down by 4 to get the value in mg.
+    //
+    let axis = |i: usize| i16::from_le_bytes([buf[i], buf[i + 1]]) >> 4;
+    Ok([axis(0), axis(2), axis(4)])
+}
+
+// Sets up the accelerometer and logs its readings.
+//
+// The errors are logged with Debug2Format, which formats them with their Debug
+// impl, since embassy-stm32 only implements defmt's Format for its types with
+// its defmt feature enabled. That feature isn't enabled here because it would
+// also be enabled for the embassy-uart example in the same workspace, which
+// doesn't link a defmt transport, and that wouldn't link.
+//
+#[embassy_executor::task]
+async fn accel(mut i2c: I2c<'static, Async, Master>) {
+    for (register, value) in [
+        (CTRL_REG1_A, CTRL_REG1_A_100HZ_XYZ),
+        (CTRL_REG4_A, CTRL_REG4_A_HR),
+    ] {
+        if let Err(error) = i2c.write(ACCEL_ADDRESS, &[register, value]).await {
+            defmt::error!(
+                "Failed to configure the accelerometer: {}",
+                defmt::Debug2Format(&error)
+            );
+            return;
+        }
+    }
+
+    loop {
+        match read_accel(&mut i2c).await {
+            Ok([x, y, z]) => defmt::info!("x: {} mg, y: {} mg, z: {} mg", x, y, z),
+            Err(error) => defmt::warn!(
+                "Failed to read the accelerometer: {}",
+                defmt::Debug2Format(&error)
+            ),
+        }
+        Timer::after_millis(READ_INTERVAL_MS).await;
+    }
+}
+
+#[embassy_executor::main]
+async fn main(spawner: Spawner) {
+    // Initialize embassy-stm32 and get the peripherals.
+    //
+    let p = embassy_stm32::init(Default::default());
+
+    // Create the async I2C master on I2C1 with SCL on PB6 and SDA on PB7, the
+    // pins the LSM303DLHC is wired to, at the default 100 kHz.
+    //
+    // The board already has pull-ups on PB6 and PB7, so the internal ones are
+    // left off.
+    //
+    let i2c = I2c::new(
+        p.I2C1,
+        p.PB6,
+        p.PB7,
+        p.DMA1_CH6,
+        p.DMA1_CH7,
+        Irqs,
+        i2c::Config::default(),
+    );
+
+    // Spawn the accelerometer task.
+    //
+    // The I2C driver is moved into the task, which is the only one using it.
+    // main() has nothing left to do, so it returns, and the executor keeps
+    // running the task, sleeping whenever it's waiting on a transfer or the
+    // timer.
+    //
+    spawner.spawn(accel(i2c).unwrap_or_else(|_| {
+        loop {
+            // Failed to allocate the accelerometer task.
+            asm::nop(); // If real app, replace with actual error handling.
+        }
+    }));
+
+    defmt::info!("Reading the accelerometer every {} ms", READ_INTERVAL_MS);
+}
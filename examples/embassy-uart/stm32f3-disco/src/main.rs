@@ -0,0 +1,136 @@
+#![deny(unsafe_code)]
+#![no_std]
+#![no_main]
+
+// Define the panic handler selected by the panic-* features in Cargo.toml.
+//
+// The default is panic-halt. A breakpoint can be set on `rust_begin_unwind` to
+// catch panics.
+//
+wt_support::panic_handler!();
+
+use cortex_m::asm;
+use embassy_executor::Spawner;
+use embassy_stm32::{
+    bind_interrupts, dma,
+    gpio::{Level, Output, Speed},
+    peripherals,
+    usart::{self, Uart},
+};
+use embassy_time::Timer;
+
+// Interval between toggles of LD3.
+//
+const LED_TOGGLE_INTERVAL_MS: u64 = 500;
+
+// Bind the interrupt handlers the async UART driver needs.
+//
+// The driver doesn't poll anything. Each await of a transfer sets up the DMA
+// channel, registers a waker, and returns Pending to the executor. The
+// interrupt handlers here run when the UART or a DMA channel needs attention
+// and wake the task waiting on it, so the task gets polled again only once the
+// transfer has actually made progress. bind_interrupts! defines the interrupt
+// handlers and a type, Irqs, proving to Uart::new() that they're bound.
+//
+// UART4's requests are hardwired to DMA2: RX to channel 3 and TX to channel 5,
+// per the DMA2 request mapping in the reference manual (RM0316). Passing any
+// other channel to Uart::new() doesn't compile, since the driver only
+// implements its RxDma and TxDma traits for those two.
+//
+bind_interrupts!(struct Irqs {
+    UART4 => usart::InterruptHandler<peripherals::UART4>;
+    DMA2_CHANNEL3 => dma::InterruptHandler<peripherals::DMA2_CH3>;
+    DMA2_CHANNEL5 => dma::InterruptHandler<peripherals::DMA2_CH5>;
+});
+
+// Blinks LD3 (PE9).
+//
+// Timer::after() doesn't spin. It arms an alarm in the time driver and awaits
+// it, so the executor is free to run the echo in main() in the meantime, and
+// sleeps when neither has anything to do.
+//
+#[embassy_executor::task]
+async fn blink(mut led: Output<'static>) {
+    loop {
+        led.toggle();
+        Timer::after_millis(LED_TOGGLE_INTERVAL_MS).await;
+    }
+}
+
+// Echoes every byte received via UART4 (PC10/PC11, TX/RX) at 115200 baud.
+//
+// Building this takes two things in Cargo.toml beyond the chip feature of
+// embassy-stm32:
+//
+// - The time-driver-any feature of embassy-stm32, which lets it pick a timer
+//   to implement the embassy-time driver. Timer::after() needs a time driver
+//   to arm its alarms, and linking fails without one. A specific timer can be
+//   picked instead with e.g. time-driver-tim2.
+// - The platform-cortex-m and executor-thread features of embassy-executor,
+//   for the executor that #[embassy_executor::main] runs main() on, which
+//   sleeps with WFE whenever no task is ready.
+//
+// The DMA support is always built into embassy-stm32, so there's no feature
+// for it. The async UART just needs a DMA channel for each direction passed
+// in, along with their interrupts. The critical-section-single-core feature of
+// cortex-m provides the critical sections embassy uses internally.
+//
+#[embassy_executor::main]
+async fn main(spawner: Spawner) {
+    // Initialize embassy-stm32 and get the peripherals.
+    //
+    // The default config runs the system clock from the 8 MHz HSI, which is
+    // plenty for this.
+    //
+    let p = embassy_stm32::init(Default::default());
+
+    // Start the blink task on LD3 (PE9).
+    //
+    let led = Output::new(p.PE9, Level::Low, Speed::Low);
+    spawner.spawn(blink(led).unwrap_or_else(|_| {
+        loop {
+            // Failed to allocate the blink task.
+            asm::nop(); // If real app, replace with actual error handling.
+        }
+    }));
+
+    // Create the async UART on UART4 with DMA for both directions.
+    //
+    let mut config = usart::Config::default();
+    config.baudrate = 115_200;
+    let mut uart4 = Uart::new(
+        p.UART4, p.PC11, p.PC10, p.DMA2_CH5, p.DMA2_CH3, Irqs, config,
+    )
+    .unwrap_or_else(|_| {
+        loop {
+            // Failed to configure UART4.
+            asm::nop(); // If real app, replace with actual error handling.
+        }
+    });
+
+    uart4.write(b"Type to echo.\r\n").await.ok();
+
+    // Main loop.
+    //
+    // read() returns once buf is full, which is after every byte since it's
+    // one byte long. While it's waiting, the task is suspended at the await
+    // rather than spinning on the RXNE flag the way the blocking uart example
+    // does, and blink keeps running. The same goes for write(), which hands
+    // the bytes to DMA and waits for it to finish. A longer buf would wait for
+    // that many bytes; read_until_idle() returns early when the line goes
+    // quiet instead, which suits input of unknown length.
+    //
+    let mut buf = [0_u8; 1];
+    loop {
+        match uart4.read(&mut buf).await {
+            Ok(()) => {
+                uart4.write(&buf).await.ok();
+            }
+            Err(_) => {
+                // A framing, noise, overrun, or parity error. The byte is
+                // lost, but the UART keeps working, so there's nothing to do
+                // but carry on.
+            }
+        }
+    }
+}
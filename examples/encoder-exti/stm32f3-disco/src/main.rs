@@ -0,0 +1,270 @@
+#![deny(unsafe_code)]
+#![no_std]
+#![no_main]
+
+use core::cell::RefCell;
+use core::fmt::Write;
+
+// Define the panic handler selected by the panic-* features in Cargo.toml.
+//
+// The default is panic-halt. A breakpoint can be set on `rust_begin_unwind` to
+// catch panics.
+//
+wt_support::panic_handler!();
+
+use cortex_m::asm;
+use cortex_m::peripheral::NVIC;
+use cortex_m_rt::entry;
+use critical_section::Mutex;
+
+use stm32f3xx_hal::{
+    gpio::{gpiod, gpioe, Edge, Input, Output, PushPull},
+    pac::{self, interrupt},
+    prelude::*,
+    serial::config,
+    serial::Serial,
+};
+
+use wt_support::quadrature::QuadratureDecoder;
+
+// Steps of the decoder per detent of the encoder.
+//
+// Most mechanical encoders, like the common KY-040 module, go through a whole
+// quadrature cycle per click. Change this to 2 for one that stops at every
+// half cycle instead, or 1 to see every step.
+//
+const STEPS_PER_DETENT: i32 = 4;
+
+// Channels A and B of the encoder, as inputs.
+//
+struct EncoderPins {
+    a: gpiod::PD6<Input>,
+    b: gpiod::PD7<Input>,
+}
+
+// The encoder's pins, shared between main(), which sets them up, and the
+// EXTI9_5 interrupt handler, as in the shared-state example.
+//
+static ENCODER_PINS: Mutex<RefCell<Option<EncoderPins>>> = Mutex::new(RefCell::new(None));
+
+// The decoder, updated by the handler and read by main().
+//
+// QuadratureDecoder::new() is a const fn, so this can start out as the
+// decoder itself rather than an Option. It's replaced in main() with one
+// that starts from the levels the pins are actually at.
+//
+static DECODER: Mutex<RefCell<QuadratureDecoder>> =
+    Mutex::new(RefCell::new(QuadratureDecoder::new(true, true)));
+
+#[entry]
+fn main() -> ! {
+    // Get peripherals.
+    //
+    // take() returns an Option, which requires handling the possibility of the
+    // return of an Err or None instead of the desired value, which is of type
+    // pac::Peripherals in this case.
+    //
+    // Since this is an embedded application, it's not as simple as writing to,
+    // stdout. This is a minimal example, so we'll drop into an inifinite loop
+    // to allow a debugger to find where the failure.
+    //
+    let device_periphs = pac::Peripherals::take().unwrap_or_else(|| {
+        loop {
+            // Failed to take pac::Peripherals.
+            asm::nop(); // If real app, replace with actual error handling.
+        }
+    });
+
+    // Get RCC peripheral and set the system clock frequency.
+    //
+    let mut reset_and_clock_control = device_periphs.RCC.constrain();
+    let mut flash = device_periphs.FLASH.constrain();
+    let clocks = reset_and_clock_control
+        .cfgr
+        .sysclk(48.MHz())
+        .freeze(&mut flash.acr);
+
+    // Get GPIO Ports C, D, and E.
+    //
+    let mut gpioc = device_periphs.GPIOC.split(&mut reset_and_clock_control.ahb);
+    let mut gpiod = device_periphs.GPIOD.split(&mut reset_and_clock_control.ahb);
+    let mut gpioe = device_periphs.GPIOE.split(&mut reset_and_clock_control.ahb);
+
+    // Configure GPIO pins PC10 as TX and PC11 as RX for UART4, the same as in
+    // the uart example, and activate the UART.
+    //
+    let tx_pin = gpioc
+        .pc10
+        .into_af_push_pull(&mut gpioc.moder, &mut gpioc.otyper, &mut gpioc.afrh);
+    let rx_pin = gpioc
+        .pc11
+        .into_af_push_pull(&mut gpioc.moder, &mut gpioc.otyper, &mut gpioc.afrh);
+    let mut uart4 = Serial::new(
+        device_periphs.UART4,
+        (tx_pin, rx_pin),
+        config::Config::default().baudrate(115_200.Bd()),
+        clocks,
+        &mut reset_and_clock_control.apb1,
+    );
+
+    // Create instances of LD3 through LD10 in push-pull output mode, in order
+    // clockwise around the compass rose from LD3 at the top, as in the
+    // joystick example, so turning the encoder clockwise moves the lit LED
+    // clockwise.
+    //
+    let mut leds: [gpioe::PEx<Output<PushPull>>; 8] = [
+        gpioe
+            .pe9
+            .into_push_pull_output(&mut gpioe.moder, &mut gpioe.otyper)
+            .downgrade(),
+        gpioe
+            .pe10
+            .into_push_pull_output(&mut gpioe.moder, &mut gpioe.otyper)
+            .downgrade(),
+        gpioe
+            .pe11
+            .into_push_pull_output(&mut gpioe.moder, &mut gpioe.otyper)
+            .downgrade(),
+        gpioe
+            .pe12
+            .into_push_pull_output(&mut gpioe.moder, &mut gpioe.otyper)
+            .downgrade(),
+        gpioe
+            .pe13
+            .into_push_pull_output(&mut gpioe.moder, &mut gpioe.otyper)
+            .downgrade(),
+        gpioe
+            .pe14
+            .into_push_pull_output(&mut gpioe.moder, &mut gpioe.otyper)
+            .downgrade(),
+        gpioe
+            .pe15
+            .into_push_pull_output(&mut gpioe.moder, &mut gpioe.otyper)
+            .downgrade(),
+        gpioe
+            .pe8
+            .into_push_pull_output(&mut gpioe.moder, &mut gpioe.otyper)
+            .downgrade(),
+    ];
+
+    // Configure PD6 and PD7 as inputs with pull-ups, for channels A and B,
+    // interrupting on both edges.
+    //
+    // Connect the encoder's common pin to GND, A (CLK on a KY-040) to PD6,
+    // and B (DT) to PD7. A KY-040 has pull-ups of its own, so connect its +
+    // to 3V as well; the internal ones are then just in parallel.
+    //
+    // Both pins are on EXTI lines that share the EXTI9_5 interrupt, as in the
+    // multi-exti example, so a single handler runs for an edge of either and
+    // can decode them together.
+    //
+    // Why not a timer?
+    //
+    // TIM2, TIM3, TIM4, and the advanced timers, TIM1 and TIM8, each have an
+    // encoder mode, where the slave mode controller counts the counter up or
+    // down on the edges of TI1 and TI2, the same way as the table in
+    // wt_support::quadrature. That needs no CPU time at all, however fast the
+    // encoder turns, and filters the inputs too, so it's what to use when one
+    // is free. But it only works on the pins that are channels 1 and 2 of a
+    // timer, and the timer can't do anything else while it's counting the
+    // encoder. In a design where those timers or pins are already taken,
+    // e.g. for PWM, or where the encoder is wired to other pins, decoding in
+    // software from EXTI works on any two pins with different numbers, at
+    // the cost of an interrupt per edge. For a knob turned by hand, that's a
+    // few hundred interrupts a second at most.
+    //
+    let mut syscfg = device_periphs
+        .SYSCFG
+        .constrain(&mut reset_and_clock_control.apb2);
+    let mut exti = device_periphs.EXTI;
+    let mut a = gpiod
+        .pd6
+        .into_pull_up_input(&mut gpiod.moder, &mut gpiod.pupdr);
+    let mut b = gpiod
+        .pd7
+        .into_pull_up_input(&mut gpiod.moder, &mut gpiod.pupdr);
+    syscfg.select_exti_interrupt_source(&a);
+    a.trigger_on_edge(&mut exti, Edge::RisingFalling);
+    a.enable_interrupt(&mut exti);
+    syscfg.select_exti_interrupt_source(&b);
+    b.trigger_on_edge(&mut exti, Edge::RisingFalling);
+    b.enable_interrupt(&mut exti);
+    let encoder_interrupt = a.interrupt();
+
+    // Move the pins into the global, and start the decoder from their levels.
+    //
+    critical_section::with(|cs| {
+        DECODER.borrow(cs).replace(QuadratureDecoder::new(
+            a.is_high().unwrap_or(true),
+            b.is_high().unwrap_or(true),
+        ));
+        ENCODER_PINS.borrow(cs).replace(Some(EncoderPins { a, b }));
+    });
+
+    // Unmask the EXTI9_5 interrupt in the NVIC.
+    //
+    // The handler clears the pending bits of both channels and reads their
+    // levels through ENCODER_PINS. If it ran before the pins were there, a
+    // turn of the knob during setup would leave the lines pending, and the
+    // handler would run over and over without ever updating DECODER.
+    //
+    #[allow(unsafe_code)]
+    unsafe {
+        NVIC::unmask(encoder_interrupt);
+    }
+
+    writeln!(uart4, "Turn the encoder on PD6 and PD7.\r").ok();
+
+    // Position last written out, in detents, and the LED lit for it.
+    //
+    let mut last_detents = 0;
+    leds[0].set_high().ok();
+
+    // Main loop.
+    //
+    // Sleep until an interrupt, then write the position out and move the lit
+    // LED if the encoder has moved a detent. The handler does the decoding,
+    // so nothing is missed while the UART is busy.
+    //
+    loop {
+        asm::wfi();
+
+        let (position, errors) = critical_section::with(|cs| {
+            let decoder = DECODER.borrow(cs).borrow();
+            (decoder.position(), decoder.errors())
+        });
+        let detents = position.div_euclid(STEPS_PER_DETENT);
+        if detents != last_detents {
+            leds[last_detents.rem_euclid(8) as usize].set_low().ok();
+            leds[detents.rem_euclid(8) as usize].set_high().ok();
+            last_detents = detents;
+
+            writeln!(uart4, "Position: {}  Missed steps: {}\r", detents, errors).ok();
+        }
+    }
+}
+
+// Decodes an edge of either channel.
+//
+// Whichever line's edge it was, both pending bits are cleared, and then both
+// pins are read and given to the decoder together. A bounce can put an edge
+// on each line in quick succession, and by the time the handler runs, the
+// levels read already include both, so servicing them together is right.
+//
+// Clearing the bits before reading the pins means an edge that comes in
+// between sets its bit again, and runs the handler again as soon as it
+// returns, to read the levels again. Clearing them after would lose it.
+//
+#[interrupt]
+fn EXTI9_5() {
+    critical_section::with(|cs| {
+        if let Some(pins) = ENCODER_PINS.borrow(cs).borrow_mut().as_mut() {
+            pins.a.clear_interrupt();
+            pins.b.clear_interrupt();
+
+            let a = pins.a.is_high().unwrap_or(true);
+            let b = pins.b.is_high().unwrap_or(true);
+            DECODER.borrow(cs).borrow_mut().update(a, b);
+        }
+    });
+}
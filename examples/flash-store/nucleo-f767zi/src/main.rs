@@ -0,0 +1,146 @@
+#![deny(unsafe_code)]
+#![no_std]
+#![no_main]
+
+use core::fmt::Write;
+
+// Use halt as the panicking behavior.
+//
+// A breakpoint can be set on `rust_begin_unwind` to catch panics.
+//
+use panic_halt as _;
+// use panic_abort as _; // requires nightly
+// use panic_itm as _; // logs messages over ITM; requires ITM support
+// use panic_semihosting as _; // logs messages to the host stderr; requires a debugger
+
+use cortex_m::asm;
+use cortex_m_rt::entry;
+
+use stm32f7xx_hal::{
+    flash::Flash,
+    pac,
+    prelude::*,
+    serial::{self, Serial},
+};
+
+// Location of the boot counter in flash.
+//
+// The F767ZI's 2 MiB of flash is split into 12 sectors of different sizes in
+// the default single-bank configuration. The counter is kept at the start of
+// the last one, sector 11, which is 256 KiB long and starts 0x1C_0000 bytes into
+// flash. The sector is left out of FLASH in memory.x so the program itself can
+// never end up in the sector being erased.
+//
+const COUNTER_SECTOR: u8 = 11;
+const COUNTER_OFFSET: usize = 0x1C_0000;
+const FLASH_BASE_ADDRESS: usize = 0x0800_0000;
+
+// Reading the counter through a *const u32 requires the address to be 4-byte
+// aligned, which the start of a sector always is. This check keeps it that way
+// if the offset is ever changed.
+//
+const _: () = assert!(COUNTER_OFFSET.is_multiple_of(4));
+
+// Erased flash reads back as all ones, so this is the value of the counter
+// before it has ever been written.
+//
+const ERASED_WORD: u32 = 0xFFFF_FFFF;
+
+#[entry]
+fn main() -> ! {
+    // Get peripherals.
+    //
+    // take() returns an Option, which requires handling the possibility of the
+    // return of an Err or None instead of the desired value, which is of type
+    // pac::Peripherals in this case.
+    //
+    // Since this is an embedded application, it's not as simple as writing to,
+    // stdout. This is a minimal example, so we'll drop into an inifinite loop
+    // to allow a debugger to find where the failure.
+    //
+    let device_periphs = pac::Peripherals::take().unwrap_or_else(|| {
+        loop {
+            // Failed to take pac::Peripherals.
+            asm::nop(); // If real app, replace with actual error handling.
+        }
+    });
+
+    // Get RCC peripheral.
+    //
+    // The constrain() method is used here to provide a higher-level abstraction
+    // of the peripheral rather than raw register access. The method consumes
+    // the raw peripheral and returns an instance of the RCC peripheral with
+    // higher-level safe abstractions provided by the HAL, which is of type Rcc,
+    // while setting the system clock frequency.
+    //
+    let reset_and_clock_control = device_periphs.RCC.constrain();
+    let clocks = reset_and_clock_control.cfgr.sysclk(48.MHz()).freeze();
+
+    // Set up USART3 on PD8/PD9, which is bridged to the ST-Link virtual COM
+    // port. See the Nucleo UART example for the details.
+    //
+    let gpiod: stm32f7xx_hal::gpio::gpiod::Parts = device_periphs.GPIOD.split();
+    let usart3 = Serial::new(
+        device_periphs.USART3,
+        (gpiod.pd8.into_alternate(), gpiod.pd9.into_alternate()),
+        &clocks,
+        serial::Config {
+            baud_rate: 115_200.bps(),
+            ..Default::default()
+        },
+    );
+    let (mut tx, _rx) = usart3.split();
+
+    // Read the current count.
+    //
+    // Flash is memory-mapped, so it can be read like any other memory. There's
+    // no safe way to create a reference to an arbitrary address, so this is
+    // the one spot in the example that needs unsafe.
+    //
+    #[allow(unsafe_code)]
+    let stored =
+        unsafe { core::ptr::read_volatile((FLASH_BASE_ADDRESS + COUNTER_OFFSET) as *const u32) };
+    let previous_count = if stored == ERASED_WORD { 0 } else { stored };
+    let boot_count = previous_count + 1;
+
+    // Write back the incremented count.
+    //
+    // Programming can only clear bits from 1 to 0, so a word can't simply be
+    // overwritten with a new value. The whole sector has to be erased back to
+    // all ones first, then the new value programmed in. The flash is also
+    // locked against writes out of reset and has to be unlocked before erasing
+    // or programming, then locked again afterwards to guard against stray
+    // writes.
+    //
+    // Each sector is only rated for about 10,000 erase cycles. This is fine
+    // once per boot, but erasing and programming in a tight loop would wear
+    // the sector out in seconds, so never do this anywhere it can repeat
+    // quickly.
+    //
+    let mut flash = Flash::new(device_periphs.FLASH);
+    flash.unlock();
+    let result = flash
+        .blocking_erase_sector(COUNTER_SECTOR)
+        .and_then(|_| flash.blocking_program(COUNTER_OFFSET, &boot_count.to_le_bytes()));
+    flash.lock();
+
+    match result {
+        Ok(()) => writeln!(tx, "Boot count: {}\r", boot_count),
+        Err(error) => writeln!(tx, "Failed to store boot count: {:?}\r", error),
+    }
+    .unwrap_or_else(|_| {
+        loop {
+            // Failed to write to USART3.
+            asm::nop(); // If real app, replace with actual error handling.
+        }
+    });
+
+    // Main loop.
+    //
+    // Nothing else to do. Press the reset button to boot again and increment
+    // the count.
+    //
+    loop {
+        asm::wfi();
+    }
+}
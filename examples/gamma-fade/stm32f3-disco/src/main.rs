@@ -0,0 +1,145 @@
+#![deny(unsafe_code)]
+#![no_std]
+#![no_main]
+
+// Define the panic handler selected by the panic-* features in Cargo.toml.
+//
+// The default is panic-halt. A breakpoint can be set on `rust_begin_unwind` to
+// catch panics.
+//
+wt_support::panic_handler!();
+
+use cortex_m::asm;
+use cortex_m_rt::entry;
+
+use stm32f3xx_hal::{
+    delay::Delay,
+    pac,
+    prelude::*,
+    rcc::{BusTimerClock, Enable, Reset},
+};
+
+use wt_support::gamma;
+
+// Frequency of the PWM.
+//
+// Well above the 100 Hz or so the eye can see flicker at, even out of the
+// corner of the eye.
+//
+const PWM_HZ: u32 = 1_000;
+
+// Number of timer ticks in each period of the PWM, and so the duty that keeps
+// the output on for the whole period, the same as the 8-bit duties out of
+// the gamma table.
+//
+const MAX_DUTY: u32 = 255;
+
+// Time taken for each step of the fade.
+//
+// Fading all the way up and back down takes 510 steps, so about 2 s.
+//
+const FADE_STEP_MS: u16 = 4;
+
+#[entry]
+fn main() -> ! {
+    // Get peripherals.
+    //
+    // take() returns an Option, which requires handling the possibility of the
+    // return of an Err or None instead of the desired value, which is of type
+    // pac::Peripherals in this case.
+    //
+    // Since this is an embedded application, it's not as simple as writing to,
+    // stdout. This is a minimal example, so we'll drop into an inifinite loop
+    // to allow a debugger to find where the failure.
+    //
+    let device_periphs = pac::Peripherals::take().unwrap_or_else(|| {
+        loop {
+            // Failed to take pac::Peripherals.
+            asm::nop(); // If real app, replace with actual error handling.
+        }
+    });
+    let core_periphs = cortex_m::Peripherals::take().unwrap_or_else(|| {
+        loop {
+            // Failed to take cortex_m::Peripherals.
+            asm::nop(); // If real app, replace with actual error handling.
+        }
+    });
+
+    // Get RCC peripheral and set the system clock frequency.
+    //
+    let mut reset_and_clock_control = device_periphs.RCC.constrain();
+    let mut flash = device_periphs.FLASH.constrain();
+    let clocks = reset_and_clock_control
+        .cfgr
+        .sysclk(48.MHz())
+        .freeze(&mut flash.acr);
+
+    // Configure the pins of the two red LEDs as TIM1 channels (alternate
+    // function 2): LD3 (PE9) on channel 1, and LD10 (PE13), across the
+    // compass from it, on channel 3.
+    //
+    let mut gpioe = device_periphs.GPIOE.split(&mut reset_and_clock_control.ahb);
+    let _ld3_pin =
+        gpioe
+            .pe9
+            .into_af_push_pull::<2>(&mut gpioe.moder, &mut gpioe.otyper, &mut gpioe.afrh);
+    let _ld10_pin =
+        gpioe
+            .pe13
+            .into_af_push_pull::<2>(&mut gpioe.moder, &mut gpioe.otyper, &mut gpioe.afrh);
+
+    // Enable and reset TIM1, and set it up for PWM on channels 1 and 3, the
+    // same way as TIM3 in the servo example.
+    //
+    // The counter ticks at PWM_HZ * MAX_DUTY and comes round every MAX_DUTY
+    // ticks, with ARR one less than that, so a CCR of MAX_DUTY keeps the
+    // output on for the whole period. TIM1 is an advanced-control timer, so
+    // its outputs also need MOE, as in the timer-toggle example.
+    //
+    pac::TIM1::enable(&mut reset_and_clock_control.apb2);
+    pac::TIM1::reset(&mut reset_and_clock_control.apb2);
+    let timer_clock_hz = pac::TIM1::timer_clock(&clocks).0;
+    let tim1 = device_periphs.TIM1;
+    tim1.psc.write(|w| {
+        w.psc()
+            .bits((timer_clock_hz / (PWM_HZ * MAX_DUTY) - 1) as u16)
+    });
+    tim1.arr.write(|w| w.arr().bits((MAX_DUTY - 1) as u16));
+    tim1.ccmr1_output()
+        .modify(|_, w| w.oc1m().pwm_mode1().oc1pe().enabled());
+    tim1.ccmr2_output()
+        .modify(|_, w| w.oc3m().pwm_mode1().oc3pe().enabled());
+    tim1.ccer.modify(|_, w| w.cc1e().set_bit().cc3e().set_bit());
+    tim1.egr.write(|w| w.ug().update());
+    tim1.bdtr.modify(|_, w| w.moe().set_bit());
+    tim1.cr1.modify(|_, w| w.cen().enabled());
+
+    // Get a delay provider for the steps of the fade.
+    //
+    let mut delay = Delay::new(core_periphs.SYST, clocks);
+
+    // Main loop.
+    //
+    // Fade both LEDs up from off to fully on and back down together, stepping
+    // the brightness level evenly from 0 to 255 and back. The way down stops
+    // at 1, since the next pass up starts at 0, so neither end is shown
+    // twice. LD3 gets the level as its duty, uncorrected, and LD10 gets it
+    // gamma corrected through wt_support::gamma.
+    //
+    // Watch the two side by side. LD3 brightens quickly at first, looks most
+    // of the way up by halfway, and then seems to change less and less, and
+    // on the way down, it hangs near full and then drops out quickly. LD10
+    // seems to brighten and dim at a steady rate the whole way, though its
+    // duty is only 56 of 255 at the halfway point, where LD3's is 128. The
+    // eye's response to light, not the LED's, is what makes the difference;
+    // see the gamma module.
+    //
+    loop {
+        for level in (0..=u8::MAX).chain((1..u8::MAX).rev()) {
+            tim1.ccr1().write(|w| w.ccr().bits(u16::from(level)));
+            tim1.ccr3()
+                .write(|w| w.ccr().bits(u16::from(gamma::gamma_correct(level))));
+            delay.delay_ms(FADE_STEP_MS);
+        }
+    }
+}
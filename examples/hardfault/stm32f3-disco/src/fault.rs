@@ -0,0 +1,44 @@
+//! Deliberately faulty operations, to trigger the HardFault handler.
+//!
+//! Everything in here is unsafe on purpose, so this module is exempt from the
+//! `#![deny(unsafe_code)]` on the rest of the crate. Safe Rust can't cause
+//! either of these faults, which is the point of keeping the unsafe code in
+//! one small, clearly marked place.
+
+#![allow(unsafe_code)]
+
+use cortex_m::peripheral::SCB;
+
+/// UNALIGN_TRP bit of SCB CCR.
+const CCR_UNALIGN_TRP: u32 = 1 << 3;
+
+/// An address with nothing behind it.
+///
+/// The STM32F303VC's 40 KiB of SRAM ends at 0x2000_A000, and nothing is mapped
+/// right after it, so a read from here fails on the bus.
+const UNMAPPED_ADDRESS: usize = 0x2000_A000;
+
+/// Reads a word from an address that isn't a multiple of 4.
+///
+/// The Cortex-M4 normally handles an unaligned LDR by itself, splitting it
+/// into smaller accesses, so on its own this wouldn't fault. Setting
+/// UNALIGN_TRP in SCB CCR makes every unaligned access a UsageFault instead,
+/// and since the UsageFault handler isn't enabled, it's escalated to a
+/// HardFault.
+pub fn unaligned_read() -> u32 {
+    let words = [0_u32; 2];
+    let unaligned = (words.as_ptr() as usize + 1) as *const u32;
+
+    unsafe {
+        (*SCB::PTR).ccr.modify(|ccr| ccr | CCR_UNALIGN_TRP);
+        unaligned.read_volatile()
+    }
+}
+
+/// Reads a word from an address with no memory or peripheral behind it.
+///
+/// That's a BusFault, which is escalated to a HardFault as well, since the
+/// BusFault handler isn't enabled either.
+pub fn unmapped_read() -> u32 {
+    unsafe { (UNMAPPED_ADDRESS as *const u32).read_volatile() }
+}
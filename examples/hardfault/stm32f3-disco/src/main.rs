@@ -0,0 +1,202 @@
+#![deny(unsafe_code)]
+#![no_std]
+#![no_main]
+
+use core::cell::RefCell;
+use core::fmt::Write;
+
+// Define the panic handler selected by the panic-* features in Cargo.toml.
+//
+// The default is panic-halt. A breakpoint can be set on `rust_begin_unwind` to
+// catch panics.
+//
+wt_support::panic_handler!();
+
+use cortex_m::asm;
+use cortex_m::peripheral::SCB;
+use cortex_m_rt::{entry, exception, ExceptionFrame};
+use critical_section::Mutex;
+
+use stm32f3xx_hal::{
+    block,
+    gpio::{gpioc, PushPull, AF5},
+    pac,
+    prelude::*,
+    serial::config,
+    serial::Serial,
+};
+
+mod fault;
+
+type Uart4 = Serial<pac::UART4, (gpioc::PC10<AF5<PushPull>>, gpioc::PC11<AF5<PushPull>>)>;
+
+// UART4, shared between main() and the HardFault handler, which writes the
+// dump out through it.
+//
+static G_UART: Mutex<RefCell<Option<Uart4>>> = Mutex::new(RefCell::new(None));
+
+#[entry]
+fn main() -> ! {
+    // Get peripherals.
+    //
+    // take() returns an Option, which requires handling the possibility of the
+    // return of an Err or None instead of the desired value, which is of type
+    // pac::Peripherals in this case.
+    //
+    // Since this is an embedded application, it's not as simple as writing to,
+    // stdout. This is a minimal example, so we'll drop into an inifinite loop
+    // to allow a debugger to find where the failure.
+    //
+    let device_periphs = pac::Peripherals::take().unwrap_or_else(|| {
+        loop {
+            // Failed to take pac::Peripherals.
+            asm::nop(); // If real app, replace with actual error handling.
+        }
+    });
+
+    // Get RCC peripheral and set the system clock frequency.
+    //
+    let mut reset_and_clock_control = device_periphs.RCC.constrain();
+    let mut flash = device_periphs.FLASH.constrain();
+    let clocks = reset_and_clock_control
+        .cfgr
+        .sysclk(48.MHz())
+        .freeze(&mut flash.acr);
+
+    // Get GPIO Port C.
+    //
+    let mut gpioc = device_periphs.GPIOC.split(&mut reset_and_clock_control.ahb);
+
+    // Configure GPIO pins PC10 as TX and PC11 as RX for UART4, the same as in
+    // the uart example, and activate the UART.
+    //
+    let tx_pin = gpioc
+        .pc10
+        .into_af_push_pull(&mut gpioc.moder, &mut gpioc.otyper, &mut gpioc.afrh);
+    let rx_pin = gpioc
+        .pc11
+        .into_af_push_pull(&mut gpioc.moder, &mut gpioc.otyper, &mut gpioc.afrh);
+    let mut uart4 = Serial::new(
+        device_periphs.UART4,
+        (tx_pin, rx_pin),
+        config::Config::default().baudrate(115_200.Bd()),
+        clocks,
+        &mut reset_and_clock_control.apb1,
+    );
+
+    writeln!(
+        uart4,
+        "Send u for an unaligned access or b for a read from an unmapped address.\r"
+    )
+    .ok();
+
+    // Move UART4 into the global for the HardFault handler.
+    //
+    critical_section::with(|cs| G_UART.borrow(cs).replace(Some(uart4)));
+
+    // Main loop.
+    //
+    // Wait for a command and run the faulty operation it asks for. The result
+    // is passed to black_box() so the read can't be optimized away, though it
+    // never gets that far.
+    //
+    loop {
+        let byte = critical_section::with(|cs| {
+            G_UART
+                .borrow(cs)
+                .borrow_mut()
+                .as_mut()
+                .and_then(|uart4| uart4.read().ok())
+        });
+
+        match byte {
+            Some(b'u') => {
+                core::hint::black_box(fault::unaligned_read());
+            }
+            Some(b'b') => {
+                core::hint::black_box(fault::unmapped_read());
+            }
+            _ => {}
+        }
+    }
+}
+
+// Writes out the registers the core stacked on entry to the HardFault, then
+// halts.
+//
+// On any exception, the core pushes R0-R3, R12, LR, PC, and xPSR onto the stack
+// of the code it interrupted, and cortex-m-rt passes them in as the
+// ExceptionFrame. For a fault, PC is the address of the instruction that
+// faulted, and LR is the return address of the function it was in, which is
+// usually enough to find where things went wrong.
+//
+// To map the PC back to a line of source, look it up in the ELF file of the
+// build that faulted, which has the debug info even in the release profile
+// here, so for a release build:
+//
+// $ arm-none-eabi-addr2line -f -C -e target/thumbv7em-none-eabihf/release/example-hardfault 0x08000abc
+//
+// or the same path with debug/ in place of release/ for a debug build.
+//
+// -f prints the function name and -C demangles it. addr2line from
+// `cargo install addr2line` takes the same arguments. The LR can be looked up
+// the same way to find the caller, after clearing its lowest bit, which the core
+// sets to mark Thumb code.
+//
+// CFSR, the Configurable Fault Status Register, tells the kinds of fault
+// apart. Neither the UsageFault nor the BusFault handler is enabled, so both
+// faults here are escalated to a HardFault, but CFSR still records the
+// original one: UNALIGNED (bit 24) for the unaligned access, and PRECISERR
+// (bit 9) or IMPRECISERR (bit 10) for the unmapped read.
+//
+// The handler takes UART4 from the global rather than setting up a new one, so
+// it only works once main() has put it there. If the fault happened while
+// main() had the RefCell borrowed, try_borrow_mut() fails and the dump is
+// skipped instead of panicking inside the fault handler. The handler is unsafe
+// because cortex-m-rt requires it to be, since it can run at any point,
+// including in the middle of code that's left memory in an invalid state.
+//
+#[allow(unsafe_code)]
+#[exception]
+unsafe fn HardFault(frame: &ExceptionFrame) -> ! {
+    let cfsr = (*SCB::PTR).cfsr.read();
+
+    critical_section::with(|cs| {
+        if let Ok(mut uart4) = G_UART.borrow(cs).try_borrow_mut() {
+            if let Some(uart4) = uart4.as_mut() {
+                writeln!(uart4, "\r\nHardFault!\r").ok();
+                writeln!(
+                    uart4,
+                    "R0:   {:#010x}  R1:  {:#010x}  R2: {:#010x}  R3: {:#010x}\r",
+                    frame.r0(),
+                    frame.r1(),
+                    frame.r2(),
+                    frame.r3()
+                )
+                .ok();
+                writeln!(
+                    uart4,
+                    "R12:  {:#010x}  LR:  {:#010x}  PC: {:#010x}\r",
+                    frame.r12(),
+                    frame.lr(),
+                    frame.pc()
+                )
+                .ok();
+                writeln!(
+                    uart4,
+                    "xPSR: {:#010x}  CFSR: {:#010x}\r",
+                    frame.xpsr(),
+                    cfsr
+                )
+                .ok();
+                block!(uart4.flush()).ok();
+            }
+        }
+    });
+
+    loop {
+        // Halted after a HardFault. A debugger can attach here to look around
+        // with everything still as it was.
+        asm::nop();
+    }
+}
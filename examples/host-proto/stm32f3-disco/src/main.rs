@@ -0,0 +1,343 @@
+#![deny(unsafe_code)]
+#![no_std]
+#![no_main]
+
+// Define the panic handler selected by the panic-* features in Cargo.toml.
+//
+// The default is panic-halt. A breakpoint can be set on `rust_begin_unwind` to
+// catch panics.
+//
+wt_support::panic_handler!();
+
+use core::convert::TryFrom;
+
+use cortex_m::asm;
+use cortex_m_rt::entry;
+
+use stm32f3xx_hal::{
+    adc::{self, config::SampleTime, Adc, CommonAdc},
+    block,
+    gpio::{gpioe, Output, PushPull},
+    pac,
+    prelude::*,
+    serial::{config, Serial},
+};
+
+use wt_support::led::{PinLed, UserLed};
+use wt_support::proto::{self, DecodeError};
+
+// The commands and responses carried in the payloads of the frames.
+//
+// The framing itself, the start byte, length, and CRC-8 around each payload,
+// is described in the documentation of wt_support::proto. The first byte of
+// each payload says what it is, and the rest are its arguments:
+//
+// | Command      | Request                | Response                          |
+// |--------------|------------------------|-----------------------------------|
+// | Set LED mask | 01 mask                | 81 mask                           |
+// | Read temp    | 02                     | 82 lo hi                          |
+// | (any)        |                        | FF error command                  |
+//
+// - mask: One bit per LED, in the order of their pins, from bit 0 for LD4 on
+//   PE8 to bit 7 for LD6 on PE15.
+// - lo hi: The temperature of the die in hundredths of a degree C, as a
+//   little-endian i16.
+// - error: One of the ERROR_* codes, with the command byte of the request it
+//   was about, or 0 if there wasn't one.
+//
+// Every request gets exactly one response, so the host can send a command,
+// wait for the response, and send it again if none comes within, say, 100 ms.
+// A minimal host with Python and pyserial, with a USB-serial adapter on UART4
+// showing up as /dev/ttyUSB0:
+//
+// >>> import serial
+// >>> def crc8(data):
+// ...     crc = 0
+// ...     for byte in data:
+// ...         crc ^= byte
+// ...         for _ in range(8):
+// ...             crc = ((crc << 1) ^ 0x07 if crc & 0x80 else crc << 1) & 0xFF
+// ...     return crc
+// >>> def frame(payload):
+// ...     body = bytes([len(payload)]) + bytes(payload)
+// ...     return b"\x7e" + body + bytes([crc8(body)])
+// >>> port = serial.Serial("/dev/ttyUSB0", 115200, timeout=0.1)
+// >>> port.write(frame([0x01, 0x55]))
+// >>> port.read(5).hex(" ")
+// '7e 02 81 55 d9'
+//
+// The response frame has to be checked the same way the device checks the
+// request: the first byte is 0x7E, the second is the length, and the CRC-8 of
+// the length and payload matches the last byte.
+//
+const CMD_SET_LEDS: u8 = 0x01;
+const CMD_READ_TEMP: u8 = 0x02;
+
+// Bit set in the command byte of a request to make that of its response.
+//
+const RESPONSE: u8 = 0x80;
+
+// First byte of an error response.
+//
+const ERROR: u8 = 0xFF;
+
+// Codes in an error response.
+//
+// - ERROR_BAD_FRAME: A frame was received with a bad CRC or length, so it was
+//   dropped. Its command byte can't be trusted, so the response carries 0.
+// - ERROR_UNKNOWN_COMMAND: The command byte isn't one of the CMD_* values.
+// - ERROR_BAD_LENGTH: The request has the wrong number of arguments for its
+//   command.
+// - ERROR_SENSOR: The temperature couldn't be read.
+//
+const ERROR_BAD_FRAME: u8 = 0x01;
+const ERROR_UNKNOWN_COMMAND: u8 = 0x02;
+const ERROR_BAD_LENGTH: u8 = 0x03;
+const ERROR_SENSOR: u8 = 0x04;
+
+// Number of LEDs in the compass.
+//
+const LED_COUNT: usize = 8;
+
+// Addresses of the factory calibration values of the temperature sensor and
+// the internal reference, the same as in the temp-logger example, which
+// explains them.
+//
+const TS_CAL1_ADDRESS: usize = 0x1FFF_F7B8;
+const TS_CAL2_ADDRESS: usize = 0x1FFF_F7C2;
+const VREFINT_CAL_ADDRESS: usize = 0x1FFF_F7BA;
+
+// Temperatures at which TS_CAL1 and TS_CAL2 were taken, in hundredths of a
+// degree C.
+//
+const TS_CAL1_TEMP_CENTI_C: i32 = 3_000;
+const TS_CAL2_TEMP_CENTI_C: i32 = 11_000;
+
+// Reads a 16-bit factory calibration value.
+//
+fn read_calibration(address: usize) -> u16 {
+    // Reading system memory through a raw pointer is unsafe, since the
+    // compiler can't check the address. These are fixed, documented addresses
+    // of read-only values, so the reads are fine.
+    //
+    #[allow(unsafe_code)]
+    unsafe {
+        core::ptr::read_volatile(address as *const u16)
+    }
+}
+
+// Returns the payload of the response to the request `request`, and its
+// length, with the LEDs and temperature reached through `set_leds` and
+// `read_temp`.
+//
+fn respond(
+    request: &[u8],
+    set_leds: &mut impl FnMut(u8),
+    read_temp: &mut impl FnMut() -> Option<i16>,
+    response: &mut [u8; proto::MAX_PAYLOAD],
+) -> usize {
+    let (&command, args) = match request.split_first() {
+        Some(split) => split,
+        None => return error_response(ERROR_UNKNOWN_COMMAND, 0, response),
+    };
+
+    match (command, args) {
+        (CMD_SET_LEDS, &[mask]) => {
+            set_leds(mask);
+            response[..2].copy_from_slice(&[CMD_SET_LEDS | RESPONSE, mask]);
+            2
+        }
+        (CMD_READ_TEMP, &[]) => match read_temp() {
+            Some(temp_centi_c) => {
+                let [lo, hi] = temp_centi_c.to_le_bytes();
+                response[..3].copy_from_slice(&[CMD_READ_TEMP | RESPONSE, lo, hi]);
+                3
+            }
+            None => error_response(ERROR_SENSOR, command, response),
+        },
+        (CMD_SET_LEDS | CMD_READ_TEMP, _) => error_response(ERROR_BAD_LENGTH, command, response),
+        _ => error_response(ERROR_UNKNOWN_COMMAND, command, response),
+    }
+}
+
+// Returns the payload of an error response, and its length.
+//
+fn error_response(code: u8, command: u8, response: &mut [u8; proto::MAX_PAYLOAD]) -> usize {
+    response[..3].copy_from_slice(&[ERROR, code, command]);
+    3
+}
+
+#[entry]
+fn main() -> ! {
+    // Get peripherals.
+    //
+    // take() returns an Option, which requires handling the possibility of the
+    // return of an Err or None instead of the desired value, which is of type
+    // pac::Peripherals in this case.
+    //
+    // Since this is an embedded application, it's not as simple as writing to,
+    // stdout. This is a minimal example, so we'll drop into an inifinite loop
+    // to allow a debugger to find where the failure.
+    //
+    let device_periphs = pac::Peripherals::take().unwrap_or_else(|| {
+        loop {
+            // Failed to take pac::Peripherals.
+            asm::nop(); // If real app, replace with actual error handling.
+        }
+    });
+
+    // Get RCC peripheral and set the system clock frequency.
+    //
+    let mut reset_and_clock_control = device_periphs.RCC.constrain();
+    let mut flash = device_periphs.FLASH.constrain();
+    let clocks = reset_and_clock_control
+        .cfgr
+        .sysclk(48.MHz())
+        .freeze(&mut flash.acr);
+
+    // Configure the eight LEDs of the compass as outputs, bit 0 of the mask
+    // to bit 7, in pin order.
+    //
+    let mut gpioe = device_periphs.GPIOE.split(&mut reset_and_clock_control.ahb);
+    let pins: [gpioe::PEx<Output<PushPull>>; LED_COUNT] = [
+        gpioe
+            .pe8
+            .into_push_pull_output(&mut gpioe.moder, &mut gpioe.otyper)
+            .downgrade(),
+        gpioe
+            .pe9
+            .into_push_pull_output(&mut gpioe.moder, &mut gpioe.otyper)
+            .downgrade(),
+        gpioe
+            .pe10
+            .into_push_pull_output(&mut gpioe.moder, &mut gpioe.otyper)
+            .downgrade(),
+        gpioe
+            .pe11
+            .into_push_pull_output(&mut gpioe.moder, &mut gpioe.otyper)
+            .downgrade(),
+        gpioe
+            .pe12
+            .into_push_pull_output(&mut gpioe.moder, &mut gpioe.otyper)
+            .downgrade(),
+        gpioe
+            .pe13
+            .into_push_pull_output(&mut gpioe.moder, &mut gpioe.otyper)
+            .downgrade(),
+        gpioe
+            .pe14
+            .into_push_pull_output(&mut gpioe.moder, &mut gpioe.otyper)
+            .downgrade(),
+        gpioe
+            .pe15
+            .into_push_pull_output(&mut gpioe.moder, &mut gpioe.otyper)
+            .downgrade(),
+    ];
+    let mut leds = pins.map(PinLed::new);
+    let mut set_leds = |mask: u8| {
+        for (bit, led) in leds.iter_mut().enumerate() {
+            if mask & (1 << bit) != 0 {
+                led.on();
+            } else {
+                led.off();
+            }
+        }
+    };
+
+    // Get GPIO Port C.
+    //
+    let mut gpioc = device_periphs.GPIOC.split(&mut reset_and_clock_control.ahb);
+
+    // Configure GPIO pins PC10 as TX and PC11 as RX for UART4, the same as in
+    // the uart example, and activate the UART.
+    //
+    let tx_pin = gpioc
+        .pc10
+        .into_af_push_pull(&mut gpioc.moder, &mut gpioc.otyper, &mut gpioc.afrh);
+    let rx_pin = gpioc
+        .pc11
+        .into_af_push_pull(&mut gpioc.moder, &mut gpioc.otyper, &mut gpioc.afrh);
+    let mut uart4 = Serial::new(
+        device_periphs.UART4,
+        (tx_pin, rx_pin),
+        config::Config::default().baudrate(115_200.Bd()),
+        clocks,
+        &mut reset_and_clock_control.apb1,
+    );
+
+    // Set up ADC1 with the temperature sensor and the internal reference, the
+    // same as in the temp-logger example.
+    //
+    let mut adc_common = CommonAdc::new(
+        device_periphs.ADC1_2,
+        &clocks,
+        &mut reset_and_clock_control.ahb,
+    );
+    let mut adcs = (device_periphs.ADC1, device_periphs.ADC2);
+    let mut temp_sensor = adc::TemperatureSensor::new(&mut adc_common, &mut adcs);
+    let mut vref_int = adc::VoltageInternalReference::new(&mut adc_common, &mut adcs);
+    let mut adc1 = Adc::new(adcs.0, adc::config::Config::default(), &clocks, &adc_common);
+    adc1.set_sample_time(&temp_sensor, SampleTime::Cycles601C5);
+    adc1.set_sample_time(&vref_int, SampleTime::Cycles601C5);
+
+    let ts_cal1 = read_calibration(TS_CAL1_ADDRESS) as i32;
+    let ts_cal2 = read_calibration(TS_CAL2_ADDRESS) as i32;
+    let vrefint_cal = read_calibration(VREFINT_CAL_ADDRESS) as i32;
+
+    // Reads the temperature of the die in hundredths of a degree C, the same
+    // as in the temp-logger example, or returns None if either reading can't
+    // be used.
+    //
+    let mut read_temp = || -> Option<i16> {
+        let temp_raw: u16 = adc1.read(&mut temp_sensor).ok()?;
+        let vrefint_raw: u16 = adc1.read(&mut vref_int).ok()?;
+        if vrefint_raw == 0 || ts_cal2 == ts_cal1 {
+            return None;
+        }
+        let temp_raw_3v3 = temp_raw as i32 * vrefint_cal / vrefint_raw as i32;
+        let temp_centi_c = TS_CAL1_TEMP_CENTI_C
+            + (temp_raw_3v3 - ts_cal1) * (TS_CAL2_TEMP_CENTI_C - TS_CAL1_TEMP_CENTI_C)
+                / (ts_cal2 - ts_cal1);
+        i16::try_from(temp_centi_c).ok()
+    };
+
+    let mut decoder = proto::Decoder::new();
+    let mut response = [0; proto::MAX_PAYLOAD];
+    let mut frame = [0; proto::MAX_FRAME];
+
+    // Main loop.
+    //
+    // The bytes of a request come in one at a time, and the decoder holds on
+    // to them until the frame is complete, however the UART splits them up.
+    // Each complete frame gets exactly one response, and a bad one gets an
+    // error response, so the host knows to send it again rather than waiting
+    // out its timeout. Bytes between frames are skipped. A receive error, like
+    // an overrun, loses a byte, so it drops the partial frame; the CRC would
+    // catch it anyway, but this way, the next start byte begins a new frame
+    // right away.
+    //
+    loop {
+        let byte = match block!(uart4.read()) {
+            Ok(byte) => byte,
+            Err(_) => {
+                decoder.reset();
+                continue;
+            }
+        };
+
+        let len = match decoder.push(byte) {
+            None => continue,
+            Some(Ok(request)) => respond(request, &mut set_leds, &mut read_temp, &mut response),
+            Some(Err(DecodeError::CrcMismatch { .. } | DecodeError::TooLong(_))) => {
+                error_response(ERROR_BAD_FRAME, 0, &mut response)
+            }
+            Some(Err(_)) => continue,
+        };
+
+        if let Ok(frame_len) = proto::encode(&response[..len], &mut frame) {
+            for &byte in &frame[..frame_len] {
+                block!(uart4.write(byte)).ok();
+            }
+        }
+    }
+}
@@ -0,0 +1,205 @@
+#![deny(unsafe_code)]
+#![no_std]
+#![no_main]
+
+use core::{fmt::Write, ops::RangeInclusive};
+
+// Define the panic handler selected by the panic-* features in Cargo.toml.
+//
+// The default is panic-halt. A breakpoint can be set on `rust_begin_unwind` to
+// catch panics.
+//
+wt_support::panic_handler!();
+
+use cortex_m::asm;
+use cortex_m_rt::entry;
+
+use stm32f3xx_hal::{
+    delay::Delay,
+    i2c::{self, I2c},
+    pac,
+    prelude::*,
+    serial::{config, Serial},
+};
+
+// 7-bit addresses that can be assigned to devices.
+//
+// Addresses 0x00-0x07 and 0x78-0x7F are reserved by the I2C specification for
+// things like the general call address and 10-bit addressing, so nothing
+// should answer there and they're skipped.
+//
+const VALID_ADDRESSES: RangeInclusive<u8> = 0x08..=0x77;
+
+// Delay in milliseconds between scans.
+//
+const SCAN_DELAY_MS: u16 = 5_000;
+
+#[entry]
+fn main() -> ! {
+    // Get peripherals.
+    //
+    // take() returns an Option, which requires handling the possibility of the
+    // return of an Err or None instead of the desired value, which is of type
+    // pac::Peripherals in this case.
+    //
+    // Since this is an embedded application, it's not as simple as writing to,
+    // stdout. This is a minimal example, so we'll drop into an inifinite loop
+    // to allow a debugger to find where the failure.
+    //
+    let device_periphs = pac::Peripherals::take().unwrap_or_else(|| {
+        loop {
+            // Failed to take pac::Peripherals.
+            asm::nop(); // If real app, replace with actual error handling.
+        }
+    });
+
+    // Get RCC peripheral and set the system clock frequency.
+    //
+    let mut reset_and_clock_control = device_periphs.RCC.constrain();
+    let mut flash = device_periphs.FLASH.constrain();
+    let clocks = reset_and_clock_control
+        .cfgr
+        .sysclk(48.MHz())
+        .pclk1(24.MHz())
+        .freeze(&mut flash.acr);
+
+    // Set up delay capability.
+    //
+    let core_periphs = cortex_m::Peripherals::take().unwrap_or_else(|| {
+        loop {
+            // Failed to take cortex_m::Peripherals.
+            asm::nop(); // If real app, replace with actual error handling.
+        }
+    });
+    let mut delay = Delay::new(core_periphs.SYST, clocks);
+
+    // Get GPIO Ports B and C.
+    //
+    let mut gpiob = device_periphs.GPIOB.split(&mut reset_and_clock_control.ahb);
+    let mut gpioc = device_periphs.GPIOC.split(&mut reset_and_clock_control.ahb);
+
+    // Configure GPIO pins PC10 as TX and PC11 as RX for UART4, the same as in
+    // the uart example, and activate the UART.
+    //
+    let tx_pin = gpioc
+        .pc10
+        .into_af_push_pull(&mut gpioc.moder, &mut gpioc.otyper, &mut gpioc.afrh);
+    let rx_pin = gpioc
+        .pc11
+        .into_af_push_pull(&mut gpioc.moder, &mut gpioc.otyper, &mut gpioc.afrh);
+    let mut uart4 = Serial::new(
+        device_periphs.UART4,
+        (tx_pin, rx_pin),
+        config::Config::default().baudrate(115_200.Bd()),
+        clocks,
+        &mut reset_and_clock_control.apb1,
+    );
+
+    // Configure PB6 as SCL and PB7 as SDA for I2C1, and activate the I2C.
+    //
+    // These are the pins the on-board LSM303 is wired to, so devices wired up
+    // to them are scanned along with it. I2C lines are open drain: devices only
+    // ever pull them low, and pull-up resistors bring them back high. The
+    // board already has pull-ups on PB6 and PB7 for the LSM303, so none are
+    // needed for breadboarded devices on this bus. On a bus without them, the
+    // scan finds nothing, since every address reads back as a NACK, or fails
+    // with bus errors.
+    //
+    let scl = gpiob
+        .pb6
+        .into_af_open_drain(&mut gpiob.moder, &mut gpiob.otyper, &mut gpiob.afrl);
+    let sda = gpiob
+        .pb7
+        .into_af_open_drain(&mut gpiob.moder, &mut gpiob.otyper, &mut gpiob.afrl);
+    let mut i2c1 = I2c::new(
+        device_periphs.I2C1,
+        (scl, sda),
+        100_000.Hz(),
+        clocks,
+        &mut reset_and_clock_control.apb1,
+    );
+
+    loop {
+        // Scan the bus.
+        //
+        // Each address is probed with a zero-length write: a start condition,
+        // the address byte with the write bit, then a stop condition, with no
+        // data in between. A device with that address pulls SDA low to ACK the
+        // address byte, and write() returns Ok. With no device there, nothing
+        // pulls SDA low, the controller sees a NACK, and write() returns
+        // Err(Nack). Nothing is actually written to a device that's found,
+        // so the probe is safe for almost any device.
+        //
+        // The output is laid out like i2cdetect on Linux, with one row per 16
+        // addresses, "--" for no device, and the address in hex for a device:
+        //
+        //      0  1  2  3  4  5  6  7  8  9  a  b  c  d  e  f
+        // 00:                         -- -- -- -- -- -- -- --
+        // 10: -- -- -- -- -- -- -- -- -- 19 -- -- -- -- 1e --
+        // ...
+        //
+        // On the STM32F3DISCOVERY, the LSM303 answers at two addresses: 0x19
+        // for the accelerometer and 0x1e for the magnetometer. If those two
+        // don't show up, something is wrong with the I2C setup rather than
+        // the wiring of an external device.
+        //
+        writeln!(
+            uart4,
+            "\r\n     0  1  2  3  4  5  6  7  8  9  a  b  c  d  e  f\r"
+        )
+        .ok();
+
+        let mut found = 0;
+        let mut bus_errors = 0;
+        for address in 0x00_u8..0x80 {
+            if address % 0x10 == 0 {
+                write!(uart4, "{:02x}:", address).ok();
+            }
+
+            if !VALID_ADDRESSES.contains(&address) {
+                write!(uart4, "   ").ok();
+            } else {
+                match i2c1.write(address, &[]) {
+                    Ok(()) => {
+                        found += 1;
+                        write!(uart4, " {:02x}", address).ok();
+                    }
+
+                    // No device at the address. This is the expected result for
+                    // most addresses, not a failure, so the scan carries on.
+                    //
+                    Err(i2c::Error::Nack) => {
+                        write!(uart4, " --").ok();
+                    }
+
+                    // Anything else is a problem with the bus itself, such as
+                    // a line being held low. It's counted and reported after
+                    // the table, and treated as no device so the scan still
+                    // finishes.
+                    //
+                    Err(_) => {
+                        bus_errors += 1;
+                        write!(uart4, " --").ok();
+                    }
+                }
+            }
+
+            if address % 0x10 == 0x0F {
+                writeln!(uart4, "\r").ok();
+            }
+        }
+
+        writeln!(uart4, "{} device(s) found.\r", found).ok();
+        if bus_errors > 0 {
+            writeln!(
+                uart4,
+                "{} address(es) failed with a bus error instead of a NACK. Check the \
+                 pull-ups and that nothing is holding SCL or SDA low.\r",
+                bus_errors
+            )
+            .ok();
+        }
+
+        delay.delay_ms(SCAN_DELAY_MS);
+    }
+}
@@ -0,0 +1,166 @@
+#![deny(unsafe_code)]
+#![no_std]
+#![no_main]
+
+// Define the panic handler selected by the panic-* features in Cargo.toml.
+//
+// The default is panic-halt. A breakpoint can be set on `rust_begin_unwind` to
+// catch panics.
+//
+wt_support::panic_handler!();
+
+use cortex_m::asm;
+use cortex_m_rt::entry;
+
+use stm32f3xx_hal::gpio::{gpioe, Output, PushPull};
+use stm32f3xx_hal::pac;
+use stm32f3xx_hal::prelude::*;
+
+use wt_support::led::{PinLed, UserLed};
+use wt_support::millis;
+
+// Number of LEDs, LD3 through LD10.
+//
+const LED_COUNT: usize = 8;
+
+// Time each LED stays in each state in milliseconds, so that the LED on PE8
+// blinks at 1 Hz, the one on PE9 at 2 Hz, and so on up to 8 Hz on PE15.
+//
+// A full blink is on then off, so each is half the period: 1000 / (2 * n).
+// The ones that don't divide evenly are rounded to the nearest millisecond,
+// so e.g. 3 Hz comes out at 2.99 Hz. Going around the compass rose from LD3,
+// the rates are 2, 3, 4, 5, 6, 7, 8, and 1 Hz.
+//
+const HALF_PERIODS_MS: [u32; LED_COUNT] = [500, 250, 167, 125, 100, 83, 71, 63];
+
+// Returns whether the tick count `deadline` has been reached at `now`.
+//
+// Comparing the two with `now >= deadline` would go wrong when the tick count
+// wraps around, every 49.7 days, since a deadline just past the wrap would be
+// a small number, and look long gone. The difference between the two, taken
+// as signed, stays right across a wrap, as long as the deadline is never
+// more than 24.8 days away.
+//
+fn is_due(now: u32, deadline: u32) -> bool {
+    now.wrapping_sub(deadline) as i32 >= 0
+}
+
+#[entry]
+fn main() -> ! {
+    // Get peripherals.
+    //
+    // take() returns an Option, which requires handling the possibility of the
+    // return of an Err or None instead of the desired value, which is of type
+    // pac::Peripherals in this case.
+    //
+    // Since this is an embedded application, it's not as simple as writing to,
+    // stdout. This is a minimal example, so we'll drop into an inifinite loop
+    // to allow a debugger to find where the failure.
+    //
+    let device_periphs = pac::Peripherals::take().unwrap_or_else(|| {
+        loop {
+            // Failed to take pac::Peripherals.
+            asm::nop(); // If real app, replace with actual error handling.
+        }
+    });
+    let core_periphs = cortex_m::Peripherals::take().unwrap_or_else(|| {
+        loop {
+            // Failed to take cortex_m::Peripherals.
+            asm::nop(); // If real app, replace with actual error handling.
+        }
+    });
+
+    // Get RCC peripheral and set the system clock frequency.
+    //
+    let mut reset_and_clock_control = device_periphs.RCC.constrain();
+    let mut flash = device_periphs.FLASH.constrain();
+    let clocks = reset_and_clock_control
+        .cfgr
+        .sysclk(48.MHz())
+        .freeze(&mut flash.acr);
+
+    // Start the millisecond tick, which all the LEDs are timed from.
+    //
+    millis::init(core_periphs.SYST, clocks.sysclk().0);
+
+    // Get GPIO Port E, and create LD3 through LD10, on pins PE8 through PE15,
+    // as wt_support::led::PinLeds, with their pin numbers erased so they fit
+    // in one array, as in the blinky example.
+    //
+    let mut gpioe = device_periphs.GPIOE.split(&mut reset_and_clock_control.ahb);
+    let pins: [gpioe::PEx<Output<PushPull>>; LED_COUNT] = [
+        gpioe
+            .pe8
+            .into_push_pull_output(&mut gpioe.moder, &mut gpioe.otyper)
+            .downgrade(),
+        gpioe
+            .pe9
+            .into_push_pull_output(&mut gpioe.moder, &mut gpioe.otyper)
+            .downgrade(),
+        gpioe
+            .pe10
+            .into_push_pull_output(&mut gpioe.moder, &mut gpioe.otyper)
+            .downgrade(),
+        gpioe
+            .pe11
+            .into_push_pull_output(&mut gpioe.moder, &mut gpioe.otyper)
+            .downgrade(),
+        gpioe
+            .pe12
+            .into_push_pull_output(&mut gpioe.moder, &mut gpioe.otyper)
+            .downgrade(),
+        gpioe
+            .pe13
+            .into_push_pull_output(&mut gpioe.moder, &mut gpioe.otyper)
+            .downgrade(),
+        gpioe
+            .pe14
+            .into_push_pull_output(&mut gpioe.moder, &mut gpioe.otyper)
+            .downgrade(),
+        gpioe
+            .pe15
+            .into_push_pull_output(&mut gpioe.moder, &mut gpioe.otyper)
+            .downgrade(),
+    ];
+    let mut leds = pins.map(PinLed::new);
+
+    // When each LED is next due to toggle, as a tick count.
+    //
+    let start = millis::millis();
+    let mut next_toggle = HALF_PERIODS_MS.map(|half_period| start.wrapping_add(half_period));
+
+    // Main loop.
+    //
+    // The blinky example blinks its LEDs with delay_ms(), which doesn't return
+    // until the time is up. While it's waiting, nothing else runs, so all the
+    // LEDs can do is change together, once per delay. To blink them at
+    // different rates that way, the delays would have to be worked out from
+    // all eight rates together, down to the moments any of them changes, and
+    // redone for any change of rate. And the more there is to do, the worse
+    // it gets: any other job would add its time onto every delay.
+    //
+    // Here, nothing waits. Each pass of the loop checks each LED against its
+    // own deadline, toggles the ones that are due, and moves on, so every LED
+    // keeps its own time, and the loop is free for anything else in between,
+    // as long as each job is quick and returns, which is cooperative timing.
+    //
+    // Each deadline moves on by exactly one half period from the last one,
+    // rather than from when the toggle happened, so a pass that runs late
+    // doesn't push that LED's later toggles back with it, and the rates don't
+    // drift.
+    //
+    loop {
+        let now = millis::millis();
+
+        for ((led, next), half_period) in leds
+            .iter_mut()
+            .zip(next_toggle.iter_mut())
+            .zip(HALF_PERIODS_MS)
+        {
+            if is_due(now, *next) {
+                led.toggle();
+                *next = next.wrapping_add(half_period);
+            }
+        }
+    }
+}
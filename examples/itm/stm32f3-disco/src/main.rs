@@ -0,0 +1,173 @@
+#![deny(unsafe_code)]
+#![no_std]
+#![no_main]
+
+// Define the panic handler selected by the panic-* features in Cargo.toml.
+//
+// This example defaults to panic-itm, which writes the panic message out ITM
+// stimulus port 0, the same channel as the messages below, and halts. Press
+// the user button to see it.
+//
+wt_support::panic_handler!();
+
+use cortex_m::asm;
+use cortex_m::iprintln;
+use cortex_m::peripheral::ITM;
+use cortex_m_rt::entry;
+
+use stm32f3xx_hal::{delay::Delay, pac, prelude::*};
+
+// Interval between messages.
+//
+const MESSAGE_INTERVAL_MS: u16 = 1_000;
+
+// ITMENA bit of ITM TCR.
+//
+const TCR_ITMENA: u32 = 1 << 0;
+
+// Returns whether the ITM and its stimulus port 0 have been enabled.
+//
+// The firmware doesn't set up the ITM itself. The debugger does, once it's
+// been told to capture SWO (see openocd.gdb), so without it, iprintln!()
+// would write into a disabled ITM, where the bytes go nowhere. Checking first
+// means the program runs the same with or without anything listening.
+//
+fn itm_port0_enabled(itm: &ITM) -> bool {
+    itm.tcr.read() & TCR_ITMENA != 0 && itm.ter[0].read() & 1 != 0
+}
+
+// Writes formatted messages through the ITM, which come out of the SWO pin.
+//
+// The ITM (Instrumentation Trace Macrocell) is a debug block in the core with
+// 32 stimulus ports. A write to a port is packaged up with the port number
+// and sent out through the TPIU (Trace Port Interface Unit) on the SWO
+// (Serial Wire Output) pin, where the debug probe picks it up. iprintln!() is
+// println!() for a stimulus port. Writing a byte takes a few cycles to put it
+// into the ITM's FIFO, and the core only waits if the FIFO is full, so it's
+// far faster than semihosting and doesn't need a UART.
+//
+// Setting it up has a few parts:
+//
+// - SWO pin: The SWO of the STM32F303 is PB3 (TRACESWO), which is already
+//   set to that alternate function out of reset, so the firmware leaves it
+//   alone. The STM32F3DISCOVERY connects PB3 to the SWO input of its ST-LINK
+//   through solder bridge SB10. Some revisions of the board ship with SB10
+//   open, so if nothing comes out, check that it's closed (UM1570).
+// - Trace clock: The SWO output runs at a baud rate divided down from the
+//   core clock, so the debugger has to be told the core clock, here 48 MHz,
+//   to work out the divider. A mismatch looks like garbage or nothing at all,
+//   which is the first thing to check after changing the clocks.
+// - Enabling: The debugger enables tracing in DBGMCU and the DCB, sets up the
+//   TPIU for asynchronous (UART-like) output at the SWO baud rate, and enables
+//   the ITM and stimulus port 0.
+//
+// With OpenOCD, openocd.gdb here does the setup and writes the output to
+// itm.txt, which itmdump decodes:
+//
+// $ openocd -f openocd.cfg
+// $ arm-none-eabi-gdb -x openocd.gdb target/thumbv7em-none-eabihf/debug/example-itm
+// $ itmdump -F -f itm.txt
+//
+// probe-rs can capture SWO too; see `probe-rs itm --help`.
+//
+// Compared to the other ways the examples get text out of the chip:
+//
+// - Semihosting (panic-semihosting): Each write halts the core until the
+//   debugger has copied the text out, which takes milliseconds, so it's only
+//   good for the odd message and bends the timing of everything else around
+//   it. It needs no pin and works everywhere, including QEMU.
+// - RTT (the embassy-i2c example): Writes into a ring buffer in RAM that the
+//   probe reads out over SWD in the background. About as fast as ITM, works
+//   on any core, and needs no extra pin, but the probe has to know where the
+//   buffer is, so it needs a tool like probe-rs that reads it from the ELF.
+// - ITM: Hardware-assisted, with no buffer in RAM and no extra code in the
+//   program, but needs the SWO pin wired to the probe and a core that has an
+//   ITM, which the Cortex-M0 and M0+ don't.
+//
+#[entry]
+fn main() -> ! {
+    // Get peripherals.
+    //
+    // take() returns an Option, which requires handling the possibility of the
+    // return of an Err or None instead of the desired value, which is of type
+    // pac::Peripherals in this case.
+    //
+    // Since this is an embedded application, it's not as simple as writing to,
+    // stdout. This is a minimal example, so we'll drop into an inifinite loop
+    // to allow a debugger to find where the failure.
+    //
+    let device_periphs = pac::Peripherals::take().unwrap_or_else(|| {
+        loop {
+            // Failed to take pac::Peripherals.
+            asm::nop(); // If real app, replace with actual error handling.
+        }
+    });
+    let mut core_periphs = cortex_m::Peripherals::take().unwrap_or_else(|| {
+        loop {
+            // Failed to take cortex_m::Peripherals.
+            asm::nop(); // If real app, replace with actual error handling.
+        }
+    });
+
+    // Get RCC peripheral and set the system clock frequency.
+    //
+    // openocd.gdb tells OpenOCD the same 48 MHz for the trace clock.
+    //
+    let mut reset_and_clock_control = device_periphs.RCC.constrain();
+    let mut flash = device_periphs.FLASH.constrain();
+    let clocks = reset_and_clock_control
+        .cfgr
+        .sysclk(48.MHz())
+        .freeze(&mut flash.acr);
+    let mut delay = Delay::new(core_periphs.SYST, clocks);
+
+    // Get GPIO Ports A and E.
+    //
+    let mut gpioa = device_periphs.GPIOA.split(&mut reset_and_clock_control.ahb);
+    let mut gpioe = device_periphs.GPIOE.split(&mut reset_and_clock_control.ahb);
+
+    // Create instances of LD3 (PE9) in push-pull output mode and the user
+    // button B1 (PA0) as an input with a pull-down, as in the button-poll
+    // example.
+    //
+    let mut led = gpioe
+        .pe9
+        .into_push_pull_output(&mut gpioe.moder, &mut gpioe.otyper);
+    let button = gpioa
+        .pa0
+        .into_pull_down_input(&mut gpioa.moder, &mut gpioa.pupdr);
+
+    let itm = &mut core_periphs.ITM;
+    if itm_port0_enabled(itm) {
+        iprintln!(&mut itm.stim[0], "Hello from the ITM!");
+    }
+
+    // Main loop.
+    //
+    // Blink LD3 and write a message with a count each time, and panic when B1
+    // is pressed, to show the panic message arriving the same way.
+    //
+    let mut count: u32 = 0;
+    loop {
+        if button.is_high().unwrap_or(false) {
+            panic!("B1 pressed after {} messages", count);
+        }
+
+        led.toggle().ok();
+        if itm_port0_enabled(itm) {
+            iprintln!(
+                &mut itm.stim[0],
+                "Message {}, LD3 is {}",
+                count,
+                if led.is_set_high().unwrap_or(false) {
+                    "on"
+                } else {
+                    "off"
+                }
+            );
+        }
+        count = count.wrapping_add(1);
+
+        delay.delay_ms(MESSAGE_INTERVAL_MS);
+    }
+}
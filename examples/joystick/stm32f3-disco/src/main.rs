@@ -0,0 +1,259 @@
+#![deny(unsafe_code)]
+#![no_std]
+#![no_main]
+
+use core::fmt::Write;
+
+// Define the panic handler selected by the panic-* features in Cargo.toml.
+//
+// The default is panic-halt. A breakpoint can be set on `rust_begin_unwind` to
+// catch panics.
+//
+wt_support::panic_handler!();
+
+use cortex_m::asm;
+use cortex_m_rt::entry;
+
+use stm32f3xx_hal::{
+    adc::{self, config::SampleTime, Adc, CommonAdc},
+    delay::Delay,
+    gpio::{gpioe, Output, PushPull},
+    pac,
+    prelude::*,
+    serial::{config, Serial},
+};
+
+use wt_support::joystick::{center_of, Axis, Direction};
+
+// Highest reading of the ADC, at its default 12-bit resolution.
+//
+const ADC_FULL_SCALE: u16 = 4_095;
+
+// Number of readings of each axis averaged to find its center, and the time
+// between them in milliseconds.
+//
+const CALIBRATION_READINGS: usize = 32;
+const CALIBRATION_INTERVAL_MS: u32 = 2;
+
+// Deadzone around the center, out of the 100 of a full push.
+//
+// Increase this if the LEDs flicker or the position wanders with the stick
+// let go.
+//
+const DEADZONE: u8 = 10;
+
+// Whether to flip either axis.
+//
+// Which way is positive depends on the module and which way round it's
+// wired, so if the LED lights up on the opposite side to the push, flip that
+// axis here.
+//
+const INVERT_X: bool = false;
+const INVERT_Y: bool = false;
+
+// Time between readings in milliseconds.
+//
+const READ_INTERVAL_MS: u32 = 100;
+
+#[entry]
+fn main() -> ! {
+    // Get peripherals.
+    //
+    // take() returns an Option, which requires handling the possibility of the
+    // return of an Err or None instead of the desired value, which is of type
+    // pac::Peripherals in this case.
+    //
+    // Since this is an embedded application, it's not as simple as writing to,
+    // stdout. This is a minimal example, so we'll drop into an inifinite loop
+    // to allow a debugger to find where the failure.
+    //
+    let device_periphs = pac::Peripherals::take().unwrap_or_else(|| {
+        loop {
+            // Failed to take pac::Peripherals.
+            asm::nop(); // If real app, replace with actual error handling.
+        }
+    });
+    let core_periphs = cortex_m::Peripherals::take().unwrap_or_else(|| {
+        loop {
+            // Failed to take cortex_m::Peripherals.
+            asm::nop(); // If real app, replace with actual error handling.
+        }
+    });
+
+    // Get RCC peripheral and set the system clock frequency.
+    //
+    let mut reset_and_clock_control = device_periphs.RCC.constrain();
+    let mut flash = device_periphs.FLASH.constrain();
+    let clocks = reset_and_clock_control
+        .cfgr
+        .sysclk(48.MHz())
+        .freeze(&mut flash.acr);
+
+    let mut delay = Delay::new(core_periphs.SYST, clocks);
+
+    // Get GPIO Ports A, C, and E.
+    //
+    let mut gpioa = device_periphs.GPIOA.split(&mut reset_and_clock_control.ahb);
+    let mut gpioc = device_periphs.GPIOC.split(&mut reset_and_clock_control.ahb);
+    let mut gpioe = device_periphs.GPIOE.split(&mut reset_and_clock_control.ahb);
+
+    // Configure GPIO pins PC10 as TX and PC11 as RX for UART4, the same as in
+    // the uart example, and activate the UART.
+    //
+    let tx_pin = gpioc
+        .pc10
+        .into_af_push_pull(&mut gpioc.moder, &mut gpioc.otyper, &mut gpioc.afrh);
+    let rx_pin = gpioc
+        .pc11
+        .into_af_push_pull(&mut gpioc.moder, &mut gpioc.otyper, &mut gpioc.afrh);
+    let mut uart4 = Serial::new(
+        device_periphs.UART4,
+        (tx_pin, rx_pin),
+        config::Config::default().baudrate(115_200.Bd()),
+        clocks,
+        &mut reset_and_clock_control.apb1,
+    );
+
+    // Create instances of LD3 through LD10 in push-pull output mode, as in the
+    // blinky example, but in order clockwise around the compass rose from
+    // LD3 at the top, so that they line up with Direction::ALL:
+    //
+    // North:     LD3 (PE9)
+    // NorthEast: LD5 (PE10)
+    // East:      LD7 (PE11)
+    // SouthEast: LD9 (PE12)
+    // South:     LD10 (PE13)
+    // SouthWest: LD8 (PE14)
+    // West:      LD6 (PE15)
+    // NorthWest: LD4 (PE8)
+    //
+    // Hold the board with the USB connectors at the top for up to be north.
+    //
+    let mut leds: [gpioe::PEx<Output<PushPull>>; 8] = [
+        gpioe
+            .pe9
+            .into_push_pull_output(&mut gpioe.moder, &mut gpioe.otyper)
+            .downgrade(),
+        gpioe
+            .pe10
+            .into_push_pull_output(&mut gpioe.moder, &mut gpioe.otyper)
+            .downgrade(),
+        gpioe
+            .pe11
+            .into_push_pull_output(&mut gpioe.moder, &mut gpioe.otyper)
+            .downgrade(),
+        gpioe
+            .pe12
+            .into_push_pull_output(&mut gpioe.moder, &mut gpioe.otyper)
+            .downgrade(),
+        gpioe
+            .pe13
+            .into_push_pull_output(&mut gpioe.moder, &mut gpioe.otyper)
+            .downgrade(),
+        gpioe
+            .pe14
+            .into_push_pull_output(&mut gpioe.moder, &mut gpioe.otyper)
+            .downgrade(),
+        gpioe
+            .pe15
+            .into_push_pull_output(&mut gpioe.moder, &mut gpioe.otyper)
+            .downgrade(),
+        gpioe
+            .pe8
+            .into_push_pull_output(&mut gpioe.moder, &mut gpioe.otyper)
+            .downgrade(),
+    ];
+
+    // Configure PA1 and PA2 as analog inputs for the X and Y axes, on ADC1
+    // channels 2 and 3.
+    //
+    // Connect the thumbstick module's VRx to PA1, VRy to PA2, +5V to 3V on
+    // the board, not 5V, so its outputs stay within what the ADC can read,
+    // and GND to GND. The ADC reads each input as a fraction of VDDA, which
+    // the stick's potentiometers are a divider of the same supply for, so
+    // the readings don't depend on the supply voltage.
+    //
+    let mut x_pin = gpioa.pa1.into_analog(&mut gpioa.moder, &mut gpioa.pupdr);
+    let mut y_pin = gpioa.pa2.into_analog(&mut gpioa.moder, &mut gpioa.pupdr);
+
+    // Set up ADC1.
+    //
+    let adc_common = CommonAdc::new(
+        device_periphs.ADC1_2,
+        &clocks,
+        &mut reset_and_clock_control.ahb,
+    );
+    let mut adc1 = Adc::new(
+        device_periphs.ADC1,
+        adc::config::Config::default(),
+        &clocks,
+        &adc_common,
+    );
+
+    // Use a longer sample time than the default of 1.5 ADC clock cycles.
+    //
+    // The potentiometers in a thumbstick are usually 10 kohms, so the source
+    // impedance seen by the ADC can be up to a quarter of that at the center,
+    // well over what the shortest sample time is good for.
+    //
+    adc1.set_sample_time(&x_pin, SampleTime::Cycles181C5);
+    adc1.set_sample_time(&y_pin, SampleTime::Cycles181C5);
+
+    // Calibrate the center of each axis.
+    //
+    // With the stick let go, take CALIBRATION_READINGS readings of each axis
+    // and average them to find where it rests, which the positions are then
+    // measured from. The stick mustn't be touched until this is done, or the
+    // center is off by however far it was pushed, so the LEDs are all lit to
+    // show it's happening.
+    //
+    writeln!(uart4, "Calibrating, don't touch the stick.\r").ok();
+    for led in leds.iter_mut() {
+        led.set_high().ok();
+    }
+    let mut x_readings = [0_u16; CALIBRATION_READINGS];
+    let mut y_readings = [0_u16; CALIBRATION_READINGS];
+    for (x_reading, y_reading) in x_readings.iter_mut().zip(y_readings.iter_mut()) {
+        *x_reading = adc1.read(&mut x_pin).unwrap_or(0);
+        *y_reading = adc1.read(&mut y_pin).unwrap_or(0);
+        delay.delay_ms(CALIBRATION_INTERVAL_MS);
+    }
+    let x_center = center_of(x_readings).unwrap_or(ADC_FULL_SCALE / 2);
+    let y_center = center_of(y_readings).unwrap_or(ADC_FULL_SCALE / 2);
+    for led in leds.iter_mut() {
+        led.set_low().ok();
+    }
+    writeln!(uart4, "Center: x {}, y {}\r", x_center, y_center).ok();
+
+    let x_axis = Axis::new(x_center, ADC_FULL_SCALE, DEADZONE);
+    let y_axis = Axis::new(y_center, ADC_FULL_SCALE, DEADZONE);
+
+    // Main loop.
+    //
+    // Read both axes, write the position out, and light the LED in the
+    // direction the stick is pushed, or none with it at the center.
+    //
+    loop {
+        let mut x = x_axis.position(adc1.read(&mut x_pin).unwrap_or(x_center));
+        let mut y = y_axis.position(adc1.read(&mut y_pin).unwrap_or(y_center));
+        if INVERT_X {
+            x = -x;
+        }
+        if INVERT_Y {
+            y = -y;
+        }
+
+        writeln!(uart4, "x: {:4}, y: {:4}\r", x, y).ok();
+
+        let lit = Direction::of(x, y).map(Direction::index);
+        for (index, led) in leds.iter_mut().enumerate() {
+            if Some(index) == lit {
+                led.set_high().ok();
+            } else {
+                led.set_low().ok();
+            }
+        }
+
+        delay.delay_ms(READ_INTERVAL_MS);
+    }
+}
@@ -0,0 +1,217 @@
+#![deny(unsafe_code)]
+#![no_std]
+#![no_main]
+
+use core::fmt::Write;
+
+// Define the panic handler selected by the panic-* features in Cargo.toml.
+//
+// The default is panic-halt. A breakpoint can be set on `rust_begin_unwind` to
+// catch panics.
+//
+wt_support::panic_handler!();
+
+use cortex_m::asm;
+use cortex_m_rt::entry;
+use serde::Serialize;
+
+use stm32f3xx_hal::{
+    delay::Delay,
+    i2c::I2c,
+    pac,
+    prelude::*,
+    serial::{config, Serial},
+};
+
+use wt_support::lsm303::Lsm303;
+
+// Capacity of the buffer each line of JSON is written into, in bytes.
+//
+// serde-json-core writes into a fixed-capacity buffer rather than a growable
+// one, so it has to be big enough for the longest line there can be, worked
+// out up front:
+//
+// - The keys, braces, colons, and commas of {"ax":,"ay":,"az":} are 19 bytes.
+// - Each value is an f32, which serde-json-core formats as the shortest
+//   decimal that reads back as the same f32, the way ryu does. That's at
+//   most 9 significant digits, with a sign, a decimal point, and an exponent
+//   for very small or large values, e.g. -1.1754944e-38, 14 bytes.
+//
+// So a line is at most 19 + 3 * 14 = 61 bytes, and 64 is enough. A capacity
+// that's too small isn't caught at compile time: serialization fails at
+// runtime, and that's handled below, but only for the lines that happen to
+// be too long, which with floats may well be rare.
+//
+const JSON_CAPACITY: usize = 64;
+
+// Interval between readings in milliseconds.
+//
+const READ_INTERVAL_MS: u16 = 1_000;
+
+// One reading of the accelerometer, in g, as it goes out in the JSON.
+//
+// #[derive(Serialize)] writes the code that hands each field to a serializer
+// by name, so serde-json-core writes it out as an object with one key per
+// field, in the order they're declared here. The field names are the keys,
+// so they're kept short to keep the line short.
+//
+#[derive(Serialize)]
+struct Telemetry {
+    ax: f32,
+    ay: f32,
+    az: f32,
+}
+
+// A line sent in place of a reading when there isn't one, so the host sees
+// valid JSON on every line, and can tell a failure from a gap.
+//
+#[derive(Serialize)]
+struct Fault {
+    error: &'static str,
+}
+
+#[entry]
+fn main() -> ! {
+    // Get peripherals.
+    //
+    // take() returns an Option, which requires handling the possibility of the
+    // return of an Err or None instead of the desired value, which is of type
+    // pac::Peripherals in this case.
+    //
+    // Since this is an embedded application, it's not as simple as writing to,
+    // stdout. This is a minimal example, so we'll drop into an inifinite loop
+    // to allow a debugger to find where the failure.
+    //
+    let device_periphs = pac::Peripherals::take().unwrap_or_else(|| {
+        loop {
+            // Failed to take pac::Peripherals.
+            asm::nop(); // If real app, replace with actual error handling.
+        }
+    });
+
+    // Get RCC peripheral and set the system clock frequency.
+    //
+    let mut reset_and_clock_control = device_periphs.RCC.constrain();
+    let mut flash = device_periphs.FLASH.constrain();
+    let clocks = reset_and_clock_control
+        .cfgr
+        .sysclk(48.MHz())
+        .pclk1(24.MHz())
+        .freeze(&mut flash.acr);
+
+    // Set up delay capability.
+    //
+    let core_periphs = cortex_m::Peripherals::take().unwrap_or_else(|| {
+        loop {
+            // Failed to take cortex_m::Peripherals.
+            asm::nop(); // If real app, replace with actual error handling.
+        }
+    });
+    let mut delay = Delay::new(core_periphs.SYST, clocks);
+
+    // Get GPIO Ports B and C.
+    //
+    let mut gpiob = device_periphs.GPIOB.split(&mut reset_and_clock_control.ahb);
+    let mut gpioc = device_periphs.GPIOC.split(&mut reset_and_clock_control.ahb);
+
+    // Configure GPIO pins PC10 as TX and PC11 as RX for UART4, the same as in
+    // the uart example, and activate the UART.
+    //
+    let tx_pin = gpioc
+        .pc10
+        .into_af_push_pull(&mut gpioc.moder, &mut gpioc.otyper, &mut gpioc.afrh);
+    let rx_pin = gpioc
+        .pc11
+        .into_af_push_pull(&mut gpioc.moder, &mut gpioc.otyper, &mut gpioc.afrh);
+    let mut uart4 = Serial::new(
+        device_periphs.UART4,
+        (tx_pin, rx_pin),
+        config::Config::default().baudrate(115_200.Bd()),
+        clocks,
+        &mut reset_and_clock_control.apb1,
+    );
+
+    // Configure PB6 as SCL and PB7 as SDA for I2C1, the pins the on-board
+    // LSM303DLHC is wired to, and hand it to the driver from wt-support, as in
+    // the tilt-compass example.
+    //
+    let scl = gpiob
+        .pb6
+        .into_af_open_drain(&mut gpiob.moder, &mut gpiob.otyper, &mut gpiob.afrl);
+    let sda = gpiob
+        .pb7
+        .into_af_open_drain(&mut gpiob.moder, &mut gpiob.otyper, &mut gpiob.afrl);
+    let i2c1 = I2c::new(
+        device_periphs.I2C1,
+        (scl, sda),
+        100_000.Hz(),
+        clocks,
+        &mut reset_and_clock_control.apb1,
+    );
+    let mut sensor = Lsm303::new(i2c1);
+    let mut sensor_ready = sensor.init().is_ok();
+
+    // Main loop.
+    //
+    // Each reading goes out as one line of compact JSON, e.g.:
+    //
+    // {"ax":0.012,"ay":-0.98,"az":0.031}
+    //
+    // A host can parse the lines with any JSON library. With a USB-serial
+    // adapter on UART4 showing up as /dev/ttyUSB0, in Python with pyserial:
+    //
+    // >>> import json, serial
+    // >>> for line in serial.Serial("/dev/ttyUSB0", 115200):
+    // ...     print(json.loads(line))
+    //
+    // A line capture starts partway through won't parse, so drop any that
+    // fail rather than stopping.
+    //
+    // serde-json-core has two ways to serialize, both without an allocator:
+    //
+    // - to_string::<_, N>(&value) returns a heapless::String<N>, a string with
+    //   a fixed capacity of N bytes that lives on the stack, as used here.
+    // - to_slice(&value, &mut buf) writes into a byte buffer the caller
+    //   provides, like a DMA buffer, and returns how many bytes it wrote.
+    //
+    // Both return Err(serde_json_core::ser::Error::BufferFull) if the JSON
+    // doesn't fit, rather than truncating it, and the partial JSON in the
+    // buffer is then of no use. That would be a bug in JSON_CAPACITY, so it's
+    // reported as a line of its own, which always fits, rather than going
+    // out as a broken line.
+    //
+    loop {
+        if !sensor_ready {
+            sensor_ready = sensor.init().is_ok();
+        }
+
+        // Read, and convert from mg, what the driver returns, to g.
+        //
+        // If the sensor can't be set up or read, try setting it up again next
+        // time round, in case it was only a glitch on the bus.
+        //
+        let reading = if sensor_ready {
+            sensor.read_accel().ok()
+        } else {
+            None
+        };
+        let json: Result<heapless::String<JSON_CAPACITY>, _> = match reading {
+            Some(accel) => serde_json_core::to_string(&Telemetry {
+                ax: f32::from(accel.x) / 1_000.0,
+                ay: f32::from(accel.y) / 1_000.0,
+                az: f32::from(accel.z) / 1_000.0,
+            }),
+            None => {
+                sensor_ready = false;
+                serde_json_core::to_string(&Fault { error: "accel" })
+            }
+        };
+
+        match json {
+            Ok(line) => writeln!(uart4, "{}\r", line).ok(),
+            Err(_) => writeln!(uart4, "{{\"error\":\"buffer\"}}\r").ok(),
+        };
+
+        delay.delay_ms(READ_INTERVAL_MS);
+    }
+}
@@ -0,0 +1,181 @@
+#![deny(unsafe_code)]
+#![no_std]
+#![no_main]
+
+use core::fmt::Write;
+
+// Define the panic handler selected by the panic-* features in Cargo.toml.
+//
+// The default is panic-halt. A breakpoint can be set on `rust_begin_unwind` to
+// catch panics.
+//
+wt_support::panic_handler!();
+
+use cortex_m::asm;
+use cortex_m_rt::entry;
+
+use stm32f3xx_hal::{delay::Delay, pac, prelude::*, serial::config, serial::Serial};
+
+use wt_support::keypad::Keypad;
+
+// Key map of a common 4x4 membrane keypad, with keys[row][col] for the key
+// joining that row and column.
+//
+const KEYS: [[char; 4]; 4] = [
+    ['1', '2', '3', 'A'],
+    ['4', '5', '6', 'B'],
+    ['7', '8', '9', 'C'],
+    ['*', '0', '#', 'D'],
+];
+
+// Time to let the columns settle after driving each row low, in
+// microseconds.
+//
+const SETTLE_US: u32 = 10;
+
+// Time between scans in milliseconds.
+//
+// Longer than the contacts of a key bounce for, so that two scans in a row
+// agreeing is enough to debounce.
+//
+const SCAN_INTERVAL_MS: u32 = 10;
+
+#[entry]
+fn main() -> ! {
+    // Get peripherals.
+    //
+    // take() returns an Option, which requires handling the possibility of the
+    // return of an Err or None instead of the desired value, which is of type
+    // pac::Peripherals in this case.
+    //
+    // Since this is an embedded application, it's not as simple as writing to,
+    // stdout. This is a minimal example, so we'll drop into an inifinite loop
+    // to allow a debugger to find where the failure.
+    //
+    let device_periphs = pac::Peripherals::take().unwrap_or_else(|| {
+        loop {
+            // Failed to take pac::Peripherals.
+            asm::nop(); // If real app, replace with actual error handling.
+        }
+    });
+    let core_periphs = cortex_m::Peripherals::take().unwrap_or_else(|| {
+        loop {
+            // Failed to take cortex_m::Peripherals.
+            asm::nop(); // If real app, replace with actual error handling.
+        }
+    });
+
+    // Get RCC peripheral and set the system clock frequency.
+    //
+    let mut reset_and_clock_control = device_periphs.RCC.constrain();
+    let mut flash = device_periphs.FLASH.constrain();
+    let clocks = reset_and_clock_control
+        .cfgr
+        .sysclk(48.MHz())
+        .freeze(&mut flash.acr);
+
+    // Get a delay provider for the settling time and the time between scans.
+    //
+    let mut delay = Delay::new(core_periphs.SYST, clocks);
+
+    // Get GPIO Ports C and D.
+    //
+    let mut gpioc = device_periphs.GPIOC.split(&mut reset_and_clock_control.ahb);
+    let mut gpiod = device_periphs.GPIOD.split(&mut reset_and_clock_control.ahb);
+
+    // Configure GPIO pins PC10 as TX and PC11 as RX for UART4, the same as in
+    // the uart example, and activate the UART.
+    //
+    let tx_pin = gpioc
+        .pc10
+        .into_af_push_pull(&mut gpioc.moder, &mut gpioc.otyper, &mut gpioc.afrh);
+    let rx_pin = gpioc
+        .pc11
+        .into_af_push_pull(&mut gpioc.moder, &mut gpioc.otyper, &mut gpioc.afrh);
+    let mut uart4 = Serial::new(
+        device_periphs.UART4,
+        (tx_pin, rx_pin),
+        config::Config::default().baudrate(115_200.Bd()),
+        clocks,
+        &mut reset_and_clock_control.apb1,
+    );
+
+    // Configure PD0 through PD3 as the rows and PD4 through PD7 as the
+    // columns of the keypad.
+    //
+    // A 4x4 membrane keypad has 8 pins in a row, the 4 rows from the top and
+    // then the 4 columns from the left, so wired in order, pin 1 goes to PD0
+    // and pin 8 to PD7.
+    //
+    // The rows are open-drain outputs, set high, i.e. released, to start
+    // with, and the columns are inputs with the internal pull-ups, so they
+    // read high with no key pressed. Each pin is downgraded to erase its pin
+    // number from its type, so the rows and the columns can each go in an
+    // array.
+    //
+    let mut rows = [
+        gpiod
+            .pd0
+            .into_open_drain_output(&mut gpiod.moder, &mut gpiod.otyper)
+            .downgrade(),
+        gpiod
+            .pd1
+            .into_open_drain_output(&mut gpiod.moder, &mut gpiod.otyper)
+            .downgrade(),
+        gpiod
+            .pd2
+            .into_open_drain_output(&mut gpiod.moder, &mut gpiod.otyper)
+            .downgrade(),
+        gpiod
+            .pd3
+            .into_open_drain_output(&mut gpiod.moder, &mut gpiod.otyper)
+            .downgrade(),
+    ];
+    for row in rows.iter_mut() {
+        row.set_high().ok();
+    }
+    let cols = [
+        gpiod
+            .pd4
+            .into_pull_up_input(&mut gpiod.moder, &mut gpiod.pupdr)
+            .downgrade(),
+        gpiod
+            .pd5
+            .into_pull_up_input(&mut gpiod.moder, &mut gpiod.pupdr)
+            .downgrade(),
+        gpiod
+            .pd6
+            .into_pull_up_input(&mut gpiod.moder, &mut gpiod.pupdr)
+            .downgrade(),
+        gpiod
+            .pd7
+            .into_pull_up_input(&mut gpiod.moder, &mut gpiod.pupdr)
+            .downgrade(),
+    ];
+
+    let mut keypad = Keypad::new(rows, cols, KEYS, SETTLE_US);
+
+    writeln!(uart4, "Press a key.\r").ok();
+
+    // Main loop.
+    //
+    // Scan the keypad every SCAN_INTERVAL_MS, and write a key out when it's
+    // pressed. A key only counts once two scans in a row agree on it, which
+    // debounces it, and is only written out once per press, however long
+    // it's held for.
+    //
+    let mut last_scan = None;
+    let mut held = None;
+    loop {
+        let key = keypad.scan(&mut delay).unwrap_or(None);
+        if key == last_scan && key != held {
+            held = key;
+            if let Some(key) = key {
+                writeln!(uart4, "{}\r", key).ok();
+            }
+        }
+        last_scan = key;
+
+        delay.delay_ms(SCAN_INTERVAL_MS);
+    }
+}
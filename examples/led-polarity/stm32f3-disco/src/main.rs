@@ -0,0 +1,108 @@
+#![deny(unsafe_code)]
+#![no_std]
+#![no_main]
+
+// Define the panic handler selected by the panic-* features in Cargo.toml.
+//
+// The default is panic-halt. A breakpoint can be set on `rust_begin_unwind` to
+// catch panics.
+//
+wt_support::panic_handler!();
+
+use cortex_m::asm;
+use cortex_m_rt::entry;
+
+use stm32f3xx_hal::delay::Delay;
+use stm32f3xx_hal::pac;
+use stm32f3xx_hal::prelude::*;
+
+use wt_support::led::{blink_all, ActiveLevel, PinLed, UserLed};
+
+// Delays in milliseconds for LED states.
+//
+const LED_ON_DELAY_MS: u32 = 200;
+const LED_OFF_DELAY_MS: u32 = 800;
+
+#[entry]
+fn main() -> ! {
+    // Get peripherals.
+    //
+    // take() returns an Option, which requires handling the possibility of the
+    // return of an Err or None instead of the desired value, which is of type
+    // pac::Peripherals in this case.
+    //
+    // Since this is an embedded application, it's not as simple as writing to,
+    // stdout. This is a minimal example, so we'll drop into an inifinite loop
+    // to allow a debugger to find where the failure.
+    //
+    let device_periphs = pac::Peripherals::take().unwrap_or_else(|| {
+        loop {
+            // Failed to take pac::Peripherals.
+            asm::nop(); // If real app, replace with actual error handling.
+        }
+    });
+    let core_periphs = cortex_m::Peripherals::take().unwrap_or_else(|| {
+        loop {
+            // Failed to take cortex_m::Peripherals.
+            asm::nop(); // If real app, replace with actual error handling.
+        }
+    });
+
+    // Get RCC peripheral and set the system clock frequency.
+    //
+    let mut reset_and_clock_control = device_periphs.RCC.constrain();
+    let mut flash = device_periphs.FLASH.constrain();
+    let clocks = reset_and_clock_control
+        .cfgr
+        .sysclk(48.MHz())
+        .freeze(&mut flash.acr);
+
+    let mut delay = Delay::new(core_periphs.SYST, clocks);
+
+    // Get GPIO Ports D and E.
+    //
+    let mut gpiod = device_periphs.GPIOD.split(&mut reset_and_clock_control.ahb);
+    let mut gpioe = device_periphs.GPIOE.split(&mut reset_and_clock_control.ahb);
+
+    // LD3 (PE9) and LD4 (PE8), on the board, are wired from the pin through a
+    // resistor to GND, so they light with the pin high: active high.
+    //
+    let mut ld3 = PinLed::new(
+        gpioe
+            .pe9
+            .into_push_pull_output(&mut gpioe.moder, &mut gpioe.otyper),
+    );
+    let mut ld4 = PinLed::new(
+        gpioe
+            .pe8
+            .into_push_pull_output(&mut gpioe.moder, &mut gpioe.otyper),
+    );
+
+    // An external LED on PD0, wired the other way round: from 3V, through a
+    // resistor of around 330 ohms, to the LED's anode, and from its cathode
+    // to PD0. Current only flows with PD0 low, so it's active low.
+    //
+    // With PinLed::new(), which takes the LED to be active high, it would be
+    // on while LD3 and LD4 are off, and off while they're on. Saying it's
+    // ActiveLevel::Low, once, here, makes on() drive PD0 low and off() drive
+    // it high, so from here on it's used exactly like the others.
+    //
+    let mut external = PinLed::with_level(
+        gpiod
+            .pd0
+            .into_push_pull_output(&mut gpiod.moder, &mut gpiod.otyper),
+        ActiveLevel::Low,
+    );
+
+    // Main loop.
+    //
+    // Blink all three together. If the external LED is wired as above, it
+    // flashes briefly in step with LD3 and LD4. If it's on for most of the
+    // time instead, it's really active high, and should be created with
+    // PinLed::new().
+    //
+    let mut leds: [&mut dyn UserLed; 3] = [&mut ld3, &mut ld4, &mut external];
+    loop {
+        blink_all(&mut leds, &mut delay, LED_ON_DELAY_MS, LED_OFF_DELAY_MS);
+    }
+}
@@ -0,0 +1,362 @@
+#![deny(unsafe_code)]
+#![no_std]
+#![no_main]
+
+// Define the panic handler selected by the panic-* features in Cargo.toml.
+//
+// The default is panic-halt. A breakpoint can be set on `rust_begin_unwind` to
+// catch panics.
+//
+wt_support::panic_handler!();
+
+use cortex_m::asm;
+use cortex_m_rt::entry;
+
+use stm32f3xx_hal::{delay::Delay, pac, prelude::*};
+
+use wt_support::adc::{Adc, AdcHw, Resolution};
+use wt_support::millis;
+
+// Time between samples in milliseconds, for 100 samples a second.
+//
+// A frame is 4 bytes, which take about 350 us to send at 115200 baud, so the
+// UART could keep up with a rate past 2 kHz.
+//
+const SAMPLE_INTERVAL_MS: u32 = 10;
+
+// ADC1 channels of the internal temperature sensor and voltage reference.
+//
+const TEMPERATURE_CHANNEL: u8 = 16;
+const VREFINT_CHANNEL: u8 = 18;
+
+// Addresses of the factory calibration values (DS9118), as in the adc-dma
+// example, each the raw reading of a 12-bit conversion taken with VDDA at
+// 3.3 V: VREFINT_CAL of the internal reference, and TS_CAL1 of the temperature
+// sensor at 30 degrees C.
+//
+const VREFINT_CAL_ADDRESS: usize = 0x1FFF_F7BA;
+const TS_CAL1_ADDRESS: usize = 0x1FFF_F7B8;
+const CALIBRATION_VDDA_MV: u32 = 3_300;
+const TS_CAL1_CENTI_C: i32 = 3_000;
+
+// Typical slope of the temperature sensor (DS9118), in hundredths of a
+// millivolt per degree C. The sensor's voltage goes down as it warms up.
+//
+const TS_SLOPE_CENTI_MV_PER_C: i32 = 430;
+
+// Full scale of a 12-bit conversion.
+//
+const FULL_SCALE: u32 = 4_095;
+
+// ITM stimulus port the samples are written to with plot-itm.
+//
+// Port 0 is left for text, e.g. iprintln!() as in the itm example, so the
+// data viewer doesn't mix the two up.
+//
+#[cfg(feature = "plot-itm")]
+const ITM_PORT: usize = 1;
+
+// Frames sent over the UART with plot-uart.
+//
+// Each sample goes out as 4 bytes:
+//
+// | Byte | Contents                                          |
+// | ---- | ------------------------------------------------- |
+// | 0    | SYNC, 0xA5                                        |
+// | 1    | Low byte of the sample                            |
+// | 2    | High byte of the sample                           |
+// | 3    | Checksum, which makes the 4 bytes add up to 0x00  |
+//
+// The sample is the die temperature in hundredths of a degree C, as a
+// little-endian u16, e.g. 2_847 for 28.47 C.
+//
+// A host that starts listening partway through a frame, or loses a byte, has
+// to find where the next frame starts. It does that by looking for SYNC, but
+// SYNC can turn up in the sample too, so a candidate frame is only accepted
+// if its checksum checks out:
+//
+// 1. Read bytes until one is SYNC.
+// 2. Read the 3 after it. If the 4 add up to 0 modulo 256, it's a frame.
+// 3. If not, the SYNC was a byte of a sample, so go back to step 1 from the
+//    byte after it, not from after the 3, since one of them could be the
+//    real SYNC.
+//
+// In Python, with `buf` a bytearray of what's been received:
+//
+//     while len(buf) >= 4:
+//         if buf[0] == 0xA5 and sum(buf[:4]) % 256 == 0:
+//             plot(int.from_bytes(buf[1:3], "little") / 100)
+//             del buf[:4]
+//         else:
+//             del buf[0]
+//
+// A byte of a sample followed by 3 others that happen to make a valid frame
+// gets through once in 256 tries, but the next check puts the host back in
+// step, so a bad sample shows up as a single spike at most. A stream that
+// needs better than that can use a CRC in place of the checksum.
+//
+// SYNC is the first byte of each frame.
+//
+#[cfg(feature = "plot-uart")]
+const SYNC: u8 = 0xA5;
+
+// Returns the UART frame of a sample.
+//
+#[cfg(feature = "plot-uart")]
+fn frame(sample: u16) -> [u8; 4] {
+    let [low, high] = sample.to_le_bytes();
+    let checksum = 0_u8.wrapping_sub(SYNC.wrapping_add(low).wrapping_add(high));
+    [SYNC, low, high, checksum]
+}
+
+// Reads a 16-bit value from the system memory.
+//
+#[allow(unsafe_code)]
+fn read_calibration(address: usize) -> u16 {
+    // SAFETY: The address is that of one of the factory calibration values in
+    // the system memory, which is always mapped and read-only.
+    unsafe { core::ptr::read_volatile(address as *const u16) }
+}
+
+// ADC1 as wt_support::adc needs it, as in the adc-check example.
+//
+struct F3Adc(pac::ADC1);
+
+impl AdcHw for F3Adc {
+    fn enable_regulator(&mut self) {
+        self.0.cr.modify(|_, w| w.advregen().intermediate());
+        self.0.cr.modify(|_, w| w.advregen().enabled());
+    }
+
+    fn start_calibration(&mut self) {
+        self.0
+            .cr
+            .modify(|_, w| w.adcaldif().single_ended().adcal().calibration());
+    }
+
+    fn is_calibrating(&mut self) -> bool {
+        self.0.cr.read().adcal().is_calibration()
+    }
+
+    fn enable(&mut self) {
+        self.0.cr.modify(|_, w| w.aden().enabled());
+    }
+
+    fn is_ready(&mut self) -> bool {
+        let ready = self.0.isr.read().adrdy().is_ready();
+        if ready {
+            self.0.isr.write(|w| w.adrdy().clear());
+        }
+        ready
+    }
+
+    #[allow(unsafe_code)]
+    fn start_conversion(&mut self, channel: u8) {
+        // SAFETY: The only channels converted here are the temperature sensor
+        // and VREFINT, which ADC1 has.
+        //
+        self.0
+            .sqr1
+            .write(|w| unsafe { w.l().bits(0).sq1().bits(channel) });
+        self.0.cr.modify(|_, w| w.adstart().start_conversion());
+    }
+
+    fn conversion(&mut self) -> Option<u16> {
+        if self.0.isr.read().eoc().is_complete() {
+            Some(self.0.dr.read().rdata().bits())
+        } else {
+            None
+        }
+    }
+}
+
+#[entry]
+fn main() -> ! {
+    // Get peripherals.
+    //
+    // take() returns an Option, which requires handling the possibility of the
+    // return of an Err or None instead of the desired value, which is of type
+    // pac::Peripherals in this case.
+    //
+    // Since this is an embedded application, it's not as simple as writing to,
+    // stdout. This is a minimal example, so we'll drop into an inifinite loop
+    // to allow a debugger to find where the failure.
+    //
+    let device_periphs = pac::Peripherals::take().unwrap_or_else(|| {
+        loop {
+            // Failed to take pac::Peripherals.
+            asm::nop(); // If real app, replace with actual error handling.
+        }
+    });
+    let core_periphs = cortex_m::Peripherals::take().unwrap_or_else(|| {
+        loop {
+            // Failed to take cortex_m::Peripherals.
+            asm::nop(); // If real app, replace with actual error handling.
+        }
+    });
+
+    // Enable the clock of ADC1/ADC2 through the PAC, before the RCC is handed
+    // to the HAL, as in the adc-dma example.
+    //
+    device_periphs
+        .RCC
+        .ahbenr
+        .modify(|_, w| w.adc12en().enabled());
+
+    // Get RCC peripheral and set the system clock frequency.
+    //
+    // The RCC is only needed again to set up UART4, so with plot-uart off, it
+    // doesn't need to be mutable.
+    //
+    #[cfg_attr(not(feature = "plot-uart"), allow(unused_mut))]
+    let mut reset_and_clock_control = device_periphs.RCC.constrain();
+    let mut flash = device_periphs.FLASH.constrain();
+    let clocks = reset_and_clock_control
+        .cfgr
+        .sysclk(48.MHz())
+        .freeze(&mut flash.acr);
+
+    // Create a SysTick delay for calibrating the ADC, which needs a
+    // microsecond delay.
+    //
+    // SysTick is handed over to the millisecond tick that times the samples
+    // afterwards, since it can't be both at once.
+    //
+    let mut delay = Delay::new(core_periphs.SYST, clocks);
+
+    // Configure GPIO pins PC10 as TX and PC11 as RX for UART4, the same as in
+    // the uart example, and activate the UART.
+    //
+    #[cfg(feature = "plot-uart")]
+    let mut uart4 = {
+        use stm32f3xx_hal::serial::{config, Serial};
+
+        let mut gpioc = device_periphs.GPIOC.split(&mut reset_and_clock_control.ahb);
+        let tx_pin =
+            gpioc
+                .pc10
+                .into_af_push_pull(&mut gpioc.moder, &mut gpioc.otyper, &mut gpioc.afrh);
+        let rx_pin =
+            gpioc
+                .pc11
+                .into_af_push_pull(&mut gpioc.moder, &mut gpioc.otyper, &mut gpioc.afrh);
+        Serial::new(
+            device_periphs.UART4,
+            (tx_pin, rx_pin),
+            config::Config::default().baudrate(115_200.Bd()),
+            clocks,
+            &mut reset_and_clock_control.apb1,
+        )
+    };
+
+    // Take the ITM, which the debugger sets up, as in the itm example.
+    //
+    // For an IDE's SWV data trace viewer, e.g. STM32CubeIDE's SWV Data Trace
+    // Timeline Graph, enable SWV with the core clock at 48 MHz and tick
+    // stimulus port ITM_PORT. Each sample arrives as a 16-bit write to it.
+    //
+    #[cfg(feature = "plot-itm")]
+    let mut itm = core_periphs.ITM;
+
+    // Clock ADC1 and ADC2 from HCLK / 2, which is 24 MHz, and turn on the
+    // temperature sensor and VREFINT, which are off out of reset to save
+    // power.
+    //
+    device_periphs
+        .ADC1_2
+        .ccr
+        .modify(|_, w| w.ckmode().sync_div2().tsen().enabled().vrefen().enabled());
+
+    // Give both internal channels the longest sampling time, 601.5 ADC clock
+    // cycles, about 25 us at 24 MHz, over the 2.2 us they need at least
+    // (DS9118), and set up 12-bit, right-aligned single conversions started
+    // from software.
+    //
+    let adc1 = device_periphs.ADC1;
+    adc1.smpr2
+        .modify(|_, w| w.smp16().cycles601_5().smp18().cycles601_5());
+    adc1.cfgr.write(|w| {
+        w.res()
+            .bits12()
+            .align()
+            .right()
+            .cont()
+            .single()
+            .exten()
+            .disabled()
+    });
+
+    // Calibrate the ADC as in the adc-check example.
+    //
+    // There's nowhere to report a failure but the stream itself, so it stops
+    // here instead, where a debugger can find it.
+    //
+    let mut adc = Adc::new(F3Adc(adc1), Resolution::Bits12);
+    adc.calibrate(&mut delay).unwrap_or_else(|_| {
+        loop {
+            // The ADC failed calibration.
+            asm::nop(); // If real app, replace with actual error handling.
+        }
+    });
+
+    // Hand SysTick over to the millisecond tick.
+    //
+    millis::init(delay.free(), clocks.sysclk().0);
+
+    let vrefint_cal = read_calibration(VREFINT_CAL_ADDRESS) as u32;
+    let ts_cal1 = read_calibration(TS_CAL1_ADDRESS) as u32;
+    let ts_cal1_mv = (ts_cal1 * CALIBRATION_VDDA_MV / FULL_SCALE) as i32;
+
+    // Main loop.
+    //
+    // Every SAMPLE_INTERVAL_MS, read VREFINT and the temperature sensor, work
+    // out the temperature as in the adc-dma example, and stream it out each
+    // way selected in Cargo.toml. Timing the samples from millis rather than a
+    // delay after each one keeps the rate fixed, however long sending takes.
+    //
+    // A sample whose conversion fails, e.g. one that saturates, is skipped,
+    // rather than streaming a value that isn't a reading.
+    //
+    let mut last_sample = millis::millis();
+    loop {
+        if millis::elapsed_since(last_sample) < SAMPLE_INTERVAL_MS {
+            continue;
+        }
+        last_sample = last_sample.wrapping_add(SAMPLE_INTERVAL_MS);
+
+        let (vrefint, ts) = match (adc.read(VREFINT_CHANNEL), adc.read(TEMPERATURE_CHANNEL)) {
+            (Ok(vrefint), Ok(ts)) => (vrefint as u32, ts as u32),
+            _ => continue,
+        };
+
+        let vdda_mv = CALIBRATION_VDDA_MV * vrefint_cal / vrefint.max(1);
+        let ts_mv = (ts * vdda_mv / FULL_SCALE) as i32;
+        let temperature_centi_c =
+            TS_CAL1_CENTI_C + (ts_cal1_mv - ts_mv) * 10_000 / TS_SLOPE_CENTI_MV_PER_C;
+
+        // The die is never below 0 C on a desk, but the stream's unsigned, so
+        // clamp rather than wrap if it ever is.
+        //
+        #[cfg_attr(
+            not(any(feature = "plot-uart", feature = "plot-itm")),
+            allow(unused_variables)
+        )]
+        let sample = temperature_centi_c.clamp(0, u16::MAX as i32) as u16;
+
+        #[cfg(feature = "plot-uart")]
+        for byte in frame(sample) {
+            stm32f3xx_hal::nb::block!(uart4.write(byte)).ok();
+        }
+
+        // Only write to the ITM once the debugger has enabled it and the
+        // port, since writing to a disabled one would wait forever for the
+        // FIFO. See the itm example.
+        //
+        #[cfg(feature = "plot-itm")]
+        if itm.tcr.read() & 1 != 0 && itm.ter[0].read() & (1 << ITM_PORT) != 0 {
+            let port = &mut itm.stim[ITM_PORT];
+            while !port.is_fifo_ready() {}
+            port.write_u16(sample);
+        }
+    }
+}
@@ -0,0 +1,251 @@
+#![deny(unsafe_code)]
+#![no_std]
+#![no_main]
+
+// Define the panic handler selected by the panic-* features in Cargo.toml.
+//
+// The default is panic-halt. A breakpoint can be set on `rust_begin_unwind` to
+// catch panics.
+//
+wt_support::panic_handler!();
+
+use cortex_m::asm;
+use cortex_m_rt::entry;
+
+use stm32f3xx_hal::{pac, prelude::*};
+
+use wt_support::{log_error, log_info, log_warn, millis};
+
+// Time between readings in milliseconds.
+//
+const READ_INTERVAL_MS: u32 = 1_000;
+
+// Thresholds for the pretend temperature, in degrees C.
+//
+const WARN_TEMP_C: i32 = 30;
+const ERROR_TEMP_C: i32 = 35;
+
+// The UART the logs go to with the log-uart feature, through the buffered
+// transmitter from the buffered-uart example, so a log line never holds up
+// the main loop.
+//
+#[cfg(feature = "log-uart")]
+mod uart_log {
+    use core::cell::RefCell;
+    use core::fmt::{self, Write};
+
+    use cortex_m::peripheral::NVIC;
+    use critical_section::Mutex;
+
+    use stm32f3xx_hal::{
+        gpio::{gpioc, PushPull, AF5},
+        hal::serial,
+        nb,
+        pac::{self, interrupt},
+        prelude::*,
+        rcc::{Clocks, AHB, APB1},
+        serial::{config, Event, Serial},
+    };
+
+    use wt_support::uart_tx::{BufferedTx, TxInterrupt};
+
+    // Size of the transmit queue, which holds one byte less than this.
+    //
+    const TX_QUEUE_SIZE: usize = 256;
+
+    type Uart4 = Serial<pac::UART4, (gpioc::PC10<AF5<PushPull>>, gpioc::PC11<AF5<PushPull>>)>;
+
+    // UART4, with the TXE interrupt control BufferedTx needs, as in the
+    // buffered-uart example.
+    //
+    struct Uart(Uart4);
+
+    impl serial::Write<u8> for Uart {
+        type Error = <Uart4 as serial::Write<u8>>::Error;
+
+        fn write(&mut self, byte: u8) -> nb::Result<(), Self::Error> {
+            self.0.write(byte)
+        }
+
+        fn flush(&mut self) -> nb::Result<(), Self::Error> {
+            self.0.flush()
+        }
+    }
+
+    impl TxInterrupt for Uart {
+        fn enable_tx_interrupt(&mut self) {
+            self.0.enable_interrupt(Event::TransmitDataRegisterEmtpy);
+        }
+
+        fn disable_tx_interrupt(&mut self) {
+            self.0.disable_interrupt(Event::TransmitDataRegisterEmtpy);
+        }
+    }
+
+    static G_TX: Mutex<RefCell<Option<BufferedTx<Uart, TX_QUEUE_SIZE>>>> =
+        Mutex::new(RefCell::new(None));
+
+    // Queues a line of the log.
+    //
+    fn write(args: fmt::Arguments) {
+        critical_section::with(|cs| {
+            if let Some(tx) = G_TX.borrow(cs).borrow_mut().as_mut() {
+                tx.write_fmt(args).ok();
+            }
+        });
+    }
+
+    // Configures GPIO pins PC10 as TX and PC11 as RX for UART4, the same as in
+    // the uart example, activates the UART, moves it into the global,
+    // unmasks its interrupt, and makes it the log's UART.
+    //
+    pub fn init(
+        uart4: pac::UART4,
+        gpioc: pac::GPIOC,
+        clocks: Clocks,
+        ahb: &mut AHB,
+        apb1: &mut APB1,
+    ) {
+        let mut gpioc = gpioc.split(ahb);
+        let tx_pin =
+            gpioc
+                .pc10
+                .into_af_push_pull(&mut gpioc.moder, &mut gpioc.otyper, &mut gpioc.afrh);
+        let rx_pin =
+            gpioc
+                .pc11
+                .into_af_push_pull(&mut gpioc.moder, &mut gpioc.otyper, &mut gpioc.afrh);
+        let uart4: Uart4 = Serial::new(
+            uart4,
+            (tx_pin, rx_pin),
+            config::Config::default().baudrate(115_200.Bd()),
+            clocks,
+            apb1,
+        );
+
+        let uart_interrupt = uart4.interrupt();
+        critical_section::with(|cs| {
+            G_TX.borrow(cs).replace(Some(BufferedTx::new(Uart(uart4))));
+        });
+
+        // Unmask UART4 now that G_TX is filled in. Nothing fires until
+        // uart_log::write() queues a message, which enables TXE, and from then
+        // on the handler needs the BufferedTx in G_TX to send the bytes and to
+        // disable TXE once the queue is empty.
+        //
+        #[allow(unsafe_code)]
+        unsafe {
+            NVIC::unmask(uart_interrupt);
+        }
+
+        wt_support::log::set_uart_writer(write);
+    }
+
+    // Sends the next byte of the log.
+    //
+    #[interrupt]
+    fn UART4_EXTI34() {
+        critical_section::with(|cs| {
+            if let Some(tx) = G_TX.borrow(cs).borrow_mut().as_mut() {
+                tx.on_interrupt();
+            }
+        });
+    }
+}
+
+#[entry]
+fn main() -> ! {
+    // Get peripherals.
+    //
+    // take() returns an Option, which requires handling the possibility of the
+    // return of an Err or None instead of the desired value, which is of type
+    // pac::Peripherals in this case.
+    //
+    // Since this is an embedded application, it's not as simple as writing to,
+    // stdout. This is a minimal example, so we'll drop into an inifinite loop
+    // to allow a debugger to find where the failure.
+    //
+    let device_periphs = pac::Peripherals::take().unwrap_or_else(|| {
+        loop {
+            // Failed to take pac::Peripherals.
+            asm::nop(); // If real app, replace with actual error handling.
+        }
+    });
+    let core_periphs = cortex_m::Peripherals::take().unwrap_or_else(|| {
+        loop {
+            // Failed to take cortex_m::Peripherals.
+            asm::nop(); // If real app, replace with actual error handling.
+        }
+    });
+
+    // Get RCC peripheral and set the system clock frequency.
+    //
+    // The RCC is only needed again to set up UART4, so with log-uart off, it
+    // doesn't need to be mutable.
+    //
+    #[cfg_attr(not(feature = "log-uart"), allow(unused_mut))]
+    let mut reset_and_clock_control = device_periphs.RCC.constrain();
+    let mut flash = device_periphs.FLASH.constrain();
+    let clocks = reset_and_clock_control
+        .cfgr
+        .sysclk(48.MHz())
+        .freeze(&mut flash.acr);
+
+    // Start the millisecond tick, which timestamps the log lines as well as
+    // timing the readings.
+    //
+    millis::init(core_periphs.SYST, clocks.sysclk().0);
+
+    // Set up the backends selected in Cargo.toml.
+    //
+    // This is the only part of the program that knows where the logs go.
+    // Everything after it just uses the log_*!() macros.
+    //
+    // - log-uart: Set up UART4 and hand it to the log.
+    // - log-itm: Nothing to do here. The debugger sets up the ITM, as in the
+    //   itm example, and until it has, the lines are dropped.
+    // - log-rtt: Set up the RTT control block and channel 0, which probe-rs
+    //   finds in RAM and reads the lines from.
+    //
+    #[cfg(feature = "log-uart")]
+    uart_log::init(
+        device_periphs.UART4,
+        device_periphs.GPIOC,
+        clocks,
+        &mut reset_and_clock_control.ahb,
+        &mut reset_and_clock_control.apb1,
+    );
+
+    #[cfg(feature = "log-rtt")]
+    rtt_target::rtt_init_print!();
+
+    log_info!("Started at {} MHz", clocks.sysclk().0 / 1_000_000);
+
+    // Main loop.
+    //
+    // Take a pretend temperature reading every READ_INTERVAL_MS, rising by a
+    // degree each time from 20 C and starting again after 40 C, and log it at
+    // a level that depends on how hot it is.
+    //
+    let mut temp_c = 20;
+    let mut last_read = millis::millis();
+    loop {
+        if millis::elapsed_since(last_read) >= READ_INTERVAL_MS {
+            last_read = millis::millis();
+
+            if temp_c >= ERROR_TEMP_C {
+                log_error!(
+                    "Temperature {} C, over the limit of {} C",
+                    temp_c,
+                    ERROR_TEMP_C
+                );
+            } else if temp_c >= WARN_TEMP_C {
+                log_warn!("Temperature {} C, getting warm", temp_c);
+            } else {
+                log_info!("Temperature {} C", temp_c);
+            }
+
+            temp_c = if temp_c >= 40 { 20 } else { temp_c + 1 };
+        }
+    }
+}
@@ -0,0 +1,135 @@
+#![deny(unsafe_code)]
+#![no_std]
+#![no_main]
+
+// Define the panic handler selected by the panic-* features in Cargo.toml.
+//
+// The default is panic-halt. A breakpoint can be set on `rust_begin_unwind` to
+// catch panics.
+//
+wt_support::panic_handler!();
+
+use cortex_m::asm;
+use cortex_m_rt::entry;
+
+use stm32f3xx_hal::delay::Delay;
+use stm32f3xx_hal::gpio::Speed;
+use stm32f3xx_hal::pac;
+use stm32f3xx_hal::prelude::*;
+
+// Interval between toggles of LD3, to show the firmware is running.
+//
+const LED_TOGGLE_INTERVAL_MS: u16 = 500;
+
+#[entry]
+fn main() -> ! {
+    // Get peripherals.
+    //
+    // take() returns an Option, which requires handling the possibility of the
+    // return of an Err or None instead of the desired value, which is of type
+    // pac::Peripherals in this case.
+    //
+    // Since this is an embedded application, it's not as simple as writing to,
+    // stdout. This is a minimal example, so we'll drop into an inifinite loop
+    // to allow a debugger to find where the failure.
+    //
+    let device_periphs = pac::Peripherals::take().unwrap_or_else(|| {
+        loop {
+            // Failed to take pac::Peripherals.
+            asm::nop(); // If real app, replace with actual error handling.
+        }
+    });
+    let core_periphs = cortex_m::Peripherals::take().unwrap_or_else(|| {
+        loop {
+            // Failed to take cortex_m::Peripherals.
+            asm::nop(); // If real app, replace with actual error handling.
+        }
+    });
+
+    // Select the clock output on the MCO (Microcontroller Clock Output) pin.
+    //
+    // The HAL doesn't have a method for the MCO, so the MCO field of RCC_CFGR
+    // is set directly, through the PAC, before the RCC is handed to the HAL.
+    // That's still safe code: the PAC checks the value against the field. The
+    // HAL only ever modifies RCC_CFGR when it sets up the clocks in freeze(),
+    // so the selection survives it.
+    //
+    // The MCO can output any one of these clocks (RM0316, section 9.2.13):
+    //
+    // - SYSCLK: The system clock, which is what everything else is derived
+    //   from. That's the one output here, so it should come out at the 48 MHz
+    //   set up below.
+    // - HSI: The internal 8 MHz RC oscillator.
+    // - HSE: The external clock input, which the STM32F3DISCOVERY feeds with
+    //   the fixed 8 MHz MCO of the ST-LINK's own microcontroller. It only runs
+    //   once it's enabled, e.g. with use_hse() on the clock config.
+    // - PLL: The PLL output divided by 2.
+    // - LSI: The internal ~40 kHz RC oscillator, which only runs while
+    //   something like the IWDG or the RTC has it enabled.
+    // - LSE: The external 32.768 kHz crystal, if one is fitted and enabled.
+    //
+    // Some other STM32F3s can also divide the MCO output by up to 128 with the
+    // MCOPRE field, and output the PLL undivided, but neither is available on
+    // the STM32F303xC on this board. Dividing the output down isn't an option
+    // here, so the PLL source is the only way to get a lower frequency from the
+    // PLL, and a slower SYSCLK would have to be set up to get a slower SYSCLK
+    // output.
+    //
+    // The GPIO output limits how fast the MCO can usefully be. Per the
+    // datasheet, a pin at its highest speed setting toggles at up to 50 MHz
+    // into 30 pF, so SYSCLK at its full 72 MHz wouldn't come out cleanly. Even
+    // at 48 MHz, it takes a scope with a bandwidth of 100 MHz or more and a
+    // probe with a short ground lead to see more than a rough sine wave. A
+    // frequency counter doesn't care about the shape and is the easier way to
+    // read the frequency.
+    //
+    device_periphs.RCC.cfgr.modify(|_, w| w.mco().sysclk());
+
+    // Get RCC peripheral and set the system clock frequency.
+    //
+    // This is the clock to check on the MCO pin.
+    //
+    let mut reset_and_clock_control = device_periphs.RCC.constrain();
+    let mut flash = device_periphs.FLASH.constrain();
+    let clocks = reset_and_clock_control
+        .cfgr
+        .sysclk(48.MHz())
+        .freeze(&mut flash.acr);
+    let mut delay = Delay::new(core_periphs.SYST, clocks);
+
+    // Get GPIO Ports A and E.
+    //
+    let mut gpioa = device_periphs.GPIOA.split(&mut reset_and_clock_control.ahb);
+    let mut gpioe = device_periphs.GPIOE.split(&mut reset_and_clock_control.ahb);
+
+    // Configure PA8 as the MCO output.
+    //
+    // The MCO is alternate function 0 of PA8, and PA8 is the only pin it's on
+    // for the STM32F303. The pin starts out at its lowest speed setting, which
+    // slows its edges enough to noticeably round off even a few MHz, so it's
+    // set to the highest.
+    //
+    let mut mco_pin =
+        gpioa
+            .pa8
+            .into_af_push_pull::<0>(&mut gpioa.moder, &mut gpioa.otyper, &mut gpioa.afrh);
+    mco_pin.set_speed(&mut gpioa.ospeedr, Speed::High);
+
+    // Create an instance of LD3 (PE9) in push-pull output mode.
+    //
+    let mut led = gpioe
+        .pe9
+        .into_push_pull_output(&mut gpioe.moder, &mut gpioe.otyper);
+
+    // Main loop.
+    //
+    // The MCO runs by itself once it's set up, so all that's left to do is
+    // blink LD3 to show the firmware is running. If LD3 blinks at the expected
+    // rate while the MCO reads 48 MHz, the Delay's idea of the clock matches
+    // the real one.
+    //
+    loop {
+        led.toggle().ok();
+        delay.delay_ms(LED_TOGGLE_INTERVAL_MS);
+    }
+}
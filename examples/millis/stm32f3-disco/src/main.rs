@@ -0,0 +1,120 @@
+#![deny(unsafe_code)]
+#![no_std]
+#![no_main]
+
+// Define the panic handler selected by the panic-* features in Cargo.toml.
+//
+// The default is panic-halt. A breakpoint can be set on `rust_begin_unwind` to
+// catch panics.
+//
+wt_support::panic_handler!();
+
+use cortex_m::asm;
+use cortex_m_rt::entry;
+
+use stm32f3xx_hal::{pac, prelude::*};
+
+use wt_support::millis;
+
+#[entry]
+fn main() -> ! {
+    // Get peripherals.
+    //
+    // take() returns an Option, which requires handling the possibility of the
+    // return of an Err or None instead of the desired value, which is of type
+    // pac::Peripherals in this case.
+    //
+    // Since this is an embedded application, it's not as simple as writing to,
+    // stdout. This is a minimal example, so we'll drop into an inifinite loop
+    // to allow a debugger to find where the failure.
+    //
+    let device_periphs = pac::Peripherals::take().unwrap_or_else(|| {
+        loop {
+            // Failed to take pac::Peripherals.
+            asm::nop(); // If real app, replace with actual error handling.
+        }
+    });
+
+    // Get RCC peripheral.
+    //
+    // The constrain() method is used here to provide a higher-level abstraction
+    // of the peripheral rather than raw register access. The method consumes
+    // the raw peripheral and returns an instance of the RCC peripheral with
+    // higher-level safe abstractions provided by the HAL, which is of type Rcc,
+    // while setting the system clock frequency.
+    //
+    let mut reset_and_clock_control = device_periphs.RCC.constrain();
+    let mut flash = device_periphs.FLASH.constrain();
+    let clocks = reset_and_clock_control
+        .cfgr
+        .sysclk(48.MHz())
+        .freeze(&mut flash.acr);
+
+    // Start the millisecond tick.
+    //
+    // SysTick (SYST) is handed over to the millis module, which configures it
+    // to fire an exception every 1 ms from the system clock. This replaces the
+    // Delay abstraction used in the other examples, since both need SysTick.
+    //
+    let core_periphs = cortex_m::Peripherals::take().unwrap_or_else(|| {
+        loop {
+            // Failed to take cortex_m::Peripherals.
+            asm::nop(); // If real app, replace with actual error handling.
+        }
+    });
+    millis::init(core_periphs.SYST, clocks.sysclk().0);
+
+    // Get GPIO Port E.
+    //
+    // The split method here splits out the functionality of the GPIO Port E
+    // while taking a mutable borrow of an "enabler" that enables the clock for
+    // the port at the same time. The mutable borrow allows modification of the
+    // borrowed value while ensuring exclusive access.
+    //
+    let mut gpioe: stm32f3xx_hal::gpio::gpioe::Parts =
+        device_periphs.GPIOE.split(&mut reset_and_clock_control.ahb);
+
+    // Create instances of LD3 (PE9) and LD4 (PE8) in push-pull output mode.
+    //
+    let mut led_ld3 = gpioe
+        .pe9
+        .into_push_pull_output(&mut gpioe.moder, &mut gpioe.otyper);
+    let mut led_ld4 = gpioe
+        .pe8
+        .into_push_pull_output(&mut gpioe.moder, &mut gpioe.otyper);
+
+    // Intervals in milliseconds between toggles of each LED.
+    //
+    const LD3_TOGGLE_INTERVAL_MS: u32 = 500;
+    const LD4_TOGGLE_INTERVAL_MS: u32 = 125;
+
+    // Tick counts at which each LED was last toggled.
+    //
+    let mut ld3_last_toggle = millis::millis();
+    let mut ld4_last_toggle = millis::millis();
+
+    // Main loop.
+    //
+    // Nothing in here blocks. Each pass checks how long it has been since each
+    // LED was last toggled and only toggles it once its interval has elapsed,
+    // so the two LEDs blink at independent rates and the loop is free to do
+    // other work in between.
+    //
+    // The elapsed time is always computed with elapsed_since(), which uses
+    // wrapping subtraction, rather than by comparing tick counts directly. See
+    // the millis module for why this is correct across a wraparound.
+    //
+    loop {
+        if millis::elapsed_since(ld3_last_toggle) >= LD3_TOGGLE_INTERVAL_MS {
+            ld3_last_toggle = ld3_last_toggle.wrapping_add(LD3_TOGGLE_INTERVAL_MS);
+            led_ld3.toggle().ok();
+        }
+
+        if millis::elapsed_since(ld4_last_toggle) >= LD4_TOGGLE_INTERVAL_MS {
+            ld4_last_toggle = ld4_last_toggle.wrapping_add(LD4_TOGGLE_INTERVAL_MS);
+            led_ld4.toggle().ok();
+        }
+
+        // Other work goes here.
+    }
+}
@@ -0,0 +1,311 @@
+#![deny(unsafe_code)]
+#![no_std]
+#![no_main]
+
+use core::cell::RefCell;
+
+// Define the panic handler selected by the panic-* features in Cargo.toml.
+//
+// The default is panic-halt. A breakpoint can be set on `rust_begin_unwind` to
+// catch panics.
+//
+wt_support::panic_handler!();
+
+use cortex_m::asm;
+use cortex_m::peripheral::NVIC;
+use cortex_m_rt::entry;
+use critical_section::Mutex;
+
+use stm32f3xx_hal::{
+    gpio::{Edge, Input, Output, PXx, PushPull},
+    pac::{self, interrupt},
+    prelude::*,
+    syscfg::SysCfg,
+};
+
+// A button and the LED it toggles.
+//
+// The pins are downgraded all the way to PXx, erasing the port as well as the
+// pin number from their types, so that buttons on different ports can go in
+// the same array. The EXTI methods still work on them, looking up the line
+// from the pin number at runtime.
+//
+struct Channel {
+    button: PXx<Input>,
+    led: PXx<Output<PushPull>>,
+}
+
+impl Channel {
+    // Toggles the LED if the button's EXTI line is pending, and clears it.
+    //
+    fn service(&mut self) {
+        if self.button.is_interrupt_pending() {
+            self.button.clear_interrupt();
+            self.led.toggle().ok();
+        }
+    }
+}
+
+// The buttons, grouped by the interrupt their EXTI lines share, each shared
+// between main(), which sets it up, and its handler, as in the shared-state
+// example.
+//
+// Which interrupt a button gets depends on its pin number, not its port:
+//
+// | EXTI line | Pin number | Interrupt   |
+// | --------- | ---------- | ----------- |
+// | 0         | 0          | EXTI0       |
+// | 1         | 1          | EXTI1       |
+// | 2         | 2          | EXTI2_TSC   |
+// | 3         | 3          | EXTI3       |
+// | 4         | 4          | EXTI4       |
+// | 5 - 9     | 5 - 9      | EXTI9_5     |
+// | 10 - 15   | 10 - 15    | EXTI15_10   |
+//
+// Lines 0 to 4 each have an interrupt of their own, so a handler for one of
+// those knows which line fired just from running. Lines 5 to 9 share one
+// interrupt, and so do lines 10 to 15, so their handlers have to read the
+// pending register, EXTI_PR1, to tell which pin it was.
+//
+// It follows from the table that two pins with the same number, e.g. PA0
+// and PC0, can't both have interrupts, since they'd need the same line, and
+// SYSCFG only connects one port to each.
+//
+static EXTI0_CHANNEL: Mutex<RefCell<Option<Channel>>> = Mutex::new(RefCell::new(None));
+static EXTI9_5_CHANNELS: Mutex<RefCell<Option<[Channel; 2]>>> = Mutex::new(RefCell::new(None));
+static EXTI15_10_CHANNELS: Mutex<RefCell<Option<[Channel; 2]>>> = Mutex::new(RefCell::new(None));
+
+// Connects a button's EXTI line to its port, and enables its interrupt on
+// the given edge.
+//
+fn enable_button_interrupt(
+    button: &mut PXx<Input>,
+    syscfg: &mut SysCfg,
+    exti: &mut pac::EXTI,
+    edge: Edge,
+) {
+    syscfg.select_exti_interrupt_source(button);
+    button.trigger_on_edge(exti, edge);
+    button.enable_interrupt(exti);
+}
+
+#[entry]
+fn main() -> ! {
+    // Get peripherals.
+    //
+    // take() returns an Option, which requires handling the possibility of the
+    // return of an Err or None instead of the desired value, which is of type
+    // pac::Peripherals in this case.
+    //
+    // Since this is an embedded application, it's not as simple as writing to,
+    // stdout. This is a minimal example, so we'll drop into an inifinite loop
+    // to allow a debugger to find where the failure.
+    //
+    let device_periphs = pac::Peripherals::take().unwrap_or_else(|| {
+        loop {
+            // Failed to take pac::Peripherals.
+            asm::nop(); // If real app, replace with actual error handling.
+        }
+    });
+
+    // Get RCC peripheral and set the system clock frequency.
+    //
+    let mut reset_and_clock_control = device_periphs.RCC.constrain();
+    let mut flash = device_periphs.FLASH.constrain();
+    let _clocks = reset_and_clock_control
+        .cfgr
+        .sysclk(48.MHz())
+        .freeze(&mut flash.acr);
+
+    // Get GPIO Ports A, C, D, and E.
+    //
+    let mut gpioa = device_periphs.GPIOA.split(&mut reset_and_clock_control.ahb);
+    let mut gpioc = device_periphs.GPIOC.split(&mut reset_and_clock_control.ahb);
+    let mut gpiod = device_periphs.GPIOD.split(&mut reset_and_clock_control.ahb);
+    let mut gpioe = device_periphs.GPIOE.split(&mut reset_and_clock_control.ahb);
+
+    // Configure the buttons.
+    //
+    // B1 (PA0) is the user button, pressed high, with a pull-down, as in the
+    // button-poll example, so it interrupts on the rising edge.
+    //
+    // The other four are external buttons, each wired between its pin and
+    // GND, with a pull-up, so they interrupt on the falling edge:
+    //
+    // - PD6 and PD7, on EXTI lines 6 and 7, which share EXTI9_5.
+    // - PD12 and PC13, on EXTI lines 12 and 13, which share EXTI15_10. Being
+    //   on different ports doesn't matter, only the pin numbers do.
+    //
+    let mut b1 = gpioa
+        .pa0
+        .into_pull_down_input(&mut gpioa.moder, &mut gpioa.pupdr)
+        .downgrade()
+        .downgrade();
+    let mut pd6 = gpiod
+        .pd6
+        .into_pull_up_input(&mut gpiod.moder, &mut gpiod.pupdr)
+        .downgrade()
+        .downgrade();
+    let mut pd7 = gpiod
+        .pd7
+        .into_pull_up_input(&mut gpiod.moder, &mut gpiod.pupdr)
+        .downgrade()
+        .downgrade();
+    let mut pd12 = gpiod
+        .pd12
+        .into_pull_up_input(&mut gpiod.moder, &mut gpiod.pupdr)
+        .downgrade()
+        .downgrade();
+    let mut pc13 = gpioc
+        .pc13
+        .into_pull_up_input(&mut gpioc.moder, &mut gpioc.pupdr)
+        .downgrade()
+        .downgrade();
+
+    let mut syscfg = device_periphs
+        .SYSCFG
+        .constrain(&mut reset_and_clock_control.apb2);
+    let mut exti = device_periphs.EXTI;
+    enable_button_interrupt(&mut b1, &mut syscfg, &mut exti, Edge::Rising);
+    enable_button_interrupt(&mut pd6, &mut syscfg, &mut exti, Edge::Falling);
+    enable_button_interrupt(&mut pd7, &mut syscfg, &mut exti, Edge::Falling);
+    enable_button_interrupt(&mut pd12, &mut syscfg, &mut exti, Edge::Falling);
+    enable_button_interrupt(&mut pc13, &mut syscfg, &mut exti, Edge::Falling);
+
+    // Create instances of LD3 (PE9), LD4 (PE8), LD5 (PE10), LD6 (PE15), and
+    // LD7 (PE11) in push-pull output mode, one for each button.
+    //
+    let ld3 = gpioe
+        .pe9
+        .into_push_pull_output(&mut gpioe.moder, &mut gpioe.otyper)
+        .downgrade()
+        .downgrade();
+    let ld4 = gpioe
+        .pe8
+        .into_push_pull_output(&mut gpioe.moder, &mut gpioe.otyper)
+        .downgrade()
+        .downgrade();
+    let ld5 = gpioe
+        .pe10
+        .into_push_pull_output(&mut gpioe.moder, &mut gpioe.otyper)
+        .downgrade()
+        .downgrade();
+    let ld6 = gpioe
+        .pe15
+        .into_push_pull_output(&mut gpioe.moder, &mut gpioe.otyper)
+        .downgrade()
+        .downgrade();
+    let ld7 = gpioe
+        .pe11
+        .into_push_pull_output(&mut gpioe.moder, &mut gpioe.otyper)
+        .downgrade()
+        .downgrade();
+
+    // Move the channels into the globals.
+    //
+    critical_section::with(|cs| {
+        EXTI0_CHANNEL.borrow(cs).replace(Some(Channel {
+            button: b1,
+            led: ld3,
+        }));
+        EXTI9_5_CHANNELS.borrow(cs).replace(Some([
+            Channel {
+                button: pd6,
+                led: ld4,
+            },
+            Channel {
+                button: pd7,
+                led: ld5,
+            },
+        ]));
+        EXTI15_10_CHANNELS.borrow(cs).replace(Some([
+            Channel {
+                button: pd12,
+                led: ld6,
+            },
+            Channel {
+                button: pc13,
+                led: ld7,
+            },
+        ]));
+    });
+
+    // Unmask the three interrupts in the NVIC.
+    //
+    // A handler can only clear a pending line through the button pin in its
+    // channels, so a press made while the pins were still being set up would
+    // have it re-entered over and over if its global were still empty. All
+    // three globals are filled by now, so the three interrupts can be unmasked
+    // together, in any order.
+    //
+    #[allow(unsafe_code)]
+    unsafe {
+        NVIC::unmask(interrupt::EXTI0);
+        NVIC::unmask(interrupt::EXTI9_5);
+        NVIC::unmask(interrupt::EXTI15_10);
+    }
+
+    // Main loop.
+    //
+    // Everything happens in the handlers, so just sleep.
+    //
+    // The buttons aren't debounced, so a press can bounce and toggle its LED
+    // more than once.
+    //
+    loop {
+        asm::wfi();
+    }
+}
+
+// Toggles LD3 for B1.
+//
+// EXTI0 only ever runs for line 0, so there's no need to check which line is
+// pending, but the pending bit still has to be cleared.
+//
+#[interrupt]
+fn EXTI0() {
+    critical_section::with(|cs| {
+        if let Some(channel) = EXTI0_CHANNEL.borrow(cs).borrow_mut().as_mut() {
+            channel.service();
+        }
+    });
+}
+
+// Toggles LD4 or LD5 for whichever of PD6 and PD7 was pressed.
+//
+// Runs for any of lines 5 to 9, so it checks the pending bit of each
+// channel's line, and services every one that's set, rather than just the
+// first. Both buttons can be pressed close enough together that both lines
+// are pending by the time the handler runs. Any pending line left set would
+// just run the handler again as soon as it returns.
+//
+// Common mistakes here are clearing the whole pending register, or just one
+// pin's bit without checking it, which loses presses of the other pins, and
+// writing an EXTI6 or EXTI7 handler, which doesn't exist on this chip:
+// #[interrupt] only accepts names from the vector table.
+//
+#[interrupt]
+fn EXTI9_5() {
+    critical_section::with(|cs| {
+        if let Some(channels) = EXTI9_5_CHANNELS.borrow(cs).borrow_mut().as_mut() {
+            for channel in channels.iter_mut() {
+                channel.service();
+            }
+        }
+    });
+}
+
+// Toggles LD6 or LD7 for whichever of PD12 and PC13 was pressed.
+//
+// Works the same way as EXTI9_5, for lines 10 to 15.
+//
+#[interrupt]
+fn EXTI15_10() {
+    critical_section::with(|cs| {
+        if let Some(channels) = EXTI15_10_CHANNELS.borrow(cs).borrow_mut().as_mut() {
+            for channel in channels.iter_mut() {
+                channel.service();
+            }
+        }
+    });
+}
@@ -0,0 +1,204 @@
+#![deny(unsafe_code)]
+#![no_std]
+#![no_main]
+
+use core::fmt::Write;
+
+// Define the panic handler selected by the panic-* features in Cargo.toml.
+//
+// The default is panic-halt. A breakpoint can be set on `rust_begin_unwind` to
+// catch panics.
+//
+wt_support::panic_handler!();
+
+use cortex_m::asm;
+use cortex_m_rt::entry;
+
+use stm32f3xx_hal::{
+    delay::Delay,
+    pac,
+    prelude::*,
+    rcc::{BusTimerClock, Enable, Reset},
+    serial::{config, Serial},
+};
+
+use wt_support::led::{PinLed, UserLed};
+use wt_support::one_shot::{OnePulseTimer, OneShotPulse, PulseTiming};
+
+// Width of each pulse in microseconds.
+//
+const PULSE_WIDTH_US: u32 = 10;
+
+// How often the button is checked, in milliseconds, which is longer than it
+// bounces for, so one press is only seen once.
+//
+const POLL_INTERVAL_MS: u16 = 10;
+
+// TIM4 in one-pulse mode, with channel 1 in PWM mode 2, as wt_support::one_shot
+// needs it.
+//
+struct Tim4OnePulse(pac::TIM4);
+
+impl Tim4OnePulse {
+    // Sets up TIM4 for one-pulse mode on channel 1, following the steps in
+    // wt_support::one_shot. TIM4 isn't an advanced timer, so it has no MOE to
+    // set.
+    //
+    fn new(tim4: pac::TIM4) -> Self {
+        tim4.cr1.modify(|_, w| w.opm().enabled());
+        tim4.ccmr1_output().modify(|_, w| w.oc1m().pwm_mode2());
+        tim4.ccer.modify(|_, w| w.cc1e().set_bit());
+        Tim4OnePulse(tim4)
+    }
+}
+
+impl OnePulseTimer for Tim4OnePulse {
+    fn set_timing(&mut self, timing: PulseTiming) {
+        self.0.psc.write(|w| w.psc().bits(timing.prescaler));
+        self.0.ccr1().write(|w| w.ccr().bits(timing.compare));
+        self.0.arr.write(|w| w.arr().bits(timing.reload));
+        self.0.egr.write(|w| w.ug().update());
+    }
+
+    fn start(&mut self) {
+        self.0.cr1.modify(|_, w| w.cen().enabled());
+    }
+
+    fn is_running(&self) -> bool {
+        self.0.cr1.read().cen().is_enabled()
+    }
+}
+
+#[entry]
+fn main() -> ! {
+    // Get peripherals.
+    //
+    // take() returns an Option, which requires handling the possibility of the
+    // return of an Err or None instead of the desired value, which is of type
+    // pac::Peripherals in this case.
+    //
+    // Since this is an embedded application, it's not as simple as writing to,
+    // stdout. This is a minimal example, so we'll drop into an inifinite loop
+    // to allow a debugger to find where the failure.
+    //
+    let device_periphs = pac::Peripherals::take().unwrap_or_else(|| {
+        loop {
+            // Failed to take pac::Peripherals.
+            asm::nop(); // If real app, replace with actual error handling.
+        }
+    });
+    let core_periphs = cortex_m::Peripherals::take().unwrap_or_else(|| {
+        loop {
+            // Failed to take cortex_m::Peripherals.
+            asm::nop(); // If real app, replace with actual error handling.
+        }
+    });
+
+    // Get RCC peripheral and set the system clock frequency.
+    //
+    let mut reset_and_clock_control = device_periphs.RCC.constrain();
+    let mut flash = device_periphs.FLASH.constrain();
+    let clocks = reset_and_clock_control
+        .cfgr
+        .sysclk(48.MHz())
+        .freeze(&mut flash.acr);
+
+    let mut delay = Delay::new(core_periphs.SYST, clocks);
+
+    // Get GPIO Ports A, C, D, and E.
+    //
+    let mut gpioa = device_periphs.GPIOA.split(&mut reset_and_clock_control.ahb);
+    let mut gpioc = device_periphs.GPIOC.split(&mut reset_and_clock_control.ahb);
+    let mut gpiod = device_periphs.GPIOD.split(&mut reset_and_clock_control.ahb);
+    let mut gpioe = device_periphs.GPIOE.split(&mut reset_and_clock_control.ahb);
+
+    // Configure the user button B1 (PA0) as an input, as in the button-poll
+    // example. The board has a pull-down on it.
+    //
+    let button = gpioa
+        .pa0
+        .into_floating_input(&mut gpioa.moder, &mut gpioa.pupdr);
+
+    // LD3 toggles with each pulse, since a 10 us pulse is far too short to
+    // see.
+    //
+    let mut ld3 = PinLed::new(
+        gpioe
+            .pe9
+            .into_push_pull_output(&mut gpioe.moder, &mut gpioe.otyper),
+    );
+
+    // Configure GPIO pins PC10 as TX and PC11 as RX for UART4, the same as in
+    // the uart example, and activate the UART.
+    //
+    let tx_pin = gpioc
+        .pc10
+        .into_af_push_pull(&mut gpioc.moder, &mut gpioc.otyper, &mut gpioc.afrh);
+    let rx_pin = gpioc
+        .pc11
+        .into_af_push_pull(&mut gpioc.moder, &mut gpioc.otyper, &mut gpioc.afrh);
+    let mut uart4 = Serial::new(
+        device_periphs.UART4,
+        (tx_pin, rx_pin),
+        config::Config::default().baudrate(115_200.Bd()),
+        clocks,
+        &mut reset_and_clock_control.apb1,
+    );
+
+    // Configure PD12 as TIM4 channel 1 (alternate function 2), where the
+    // pulses come out. Put a scope or logic analyzer on it to see them.
+    //
+    // The pin is low between pulses, since the timer holds the channel
+    // inactive while it's stopped.
+    //
+    let _pulse_pin =
+        gpiod
+            .pd12
+            .into_af_push_pull::<2>(&mut gpiod.moder, &mut gpiod.otyper, &mut gpiod.afrh);
+
+    // Enable and reset TIM4, and set it up for pulses of PULSE_WIDTH_US.
+    //
+    // The APB1 timers run at 48 MHz, as in the tim-delay example, so a 10 us
+    // pulse is 480 ticks of the timer clock, with no prescaler.
+    //
+    pac::TIM4::enable(&mut reset_and_clock_control.apb1);
+    pac::TIM4::reset(&mut reset_and_clock_control.apb1);
+    let timer_clock_hz = pac::TIM4::timer_clock(&clocks).0;
+    let timer = Tim4OnePulse::new(device_periphs.TIM4);
+    let mut pulse = OneShotPulse::new(timer, timer_clock_hz, PULSE_WIDTH_US).unwrap_or_else(|_| {
+        loop {
+            // The width can't be made with TIM4.
+            asm::nop(); // If real app, replace with actual error handling.
+        }
+    });
+
+    writeln!(
+        uart4,
+        "Press B1 for a {} ns pulse on PD12 ({} ticks of {} Hz).\r",
+        pulse.width_ns(),
+        pulse.timing().width_ticks(),
+        timer_clock_hz / (pulse.timing().prescaler as u32 + 1)
+    )
+    .ok();
+
+    // Main loop.
+    //
+    // Send a pulse on each press of B1. trigger() only starts TIM4 and
+    // returns, and the timer ends the pulse by itself, so nothing here waits
+    // for it, and the pulse is the same width however long the writes to the
+    // UART after it take.
+    //
+    let mut was_pressed = false;
+    let mut pulses: u32 = 0;
+    loop {
+        let pressed = button.is_high().unwrap_or(false);
+        if pressed && !was_pressed && pulse.trigger().is_ok() {
+            pulses = pulses.wrapping_add(1);
+            ld3.toggle();
+            writeln!(uart4, "Pulse {}\r", pulses).ok();
+        }
+        was_pressed = pressed;
+
+        delay.delay_ms(POLL_INTERVAL_MS);
+    }
+}
@@ -0,0 +1,136 @@
+#![deny(unsafe_code)]
+#![no_std]
+#![no_main]
+
+// Define the panic handler selected by the panic-* features in Cargo.toml.
+//
+// The default here is panic-sos, which blinks SOS on the LED registered below
+// with wt_support::panic::register_sos_led(). Build with e.g.
+// `--no-default-features --features panic-halt` to compare it with a panic
+// that shows nothing.
+//
+wt_support::panic_handler!();
+
+use cortex_m::asm;
+use cortex_m_rt::entry;
+
+use stm32f3xx_hal::{
+    delay::Delay,
+    gpio::{gpioe, Output, PushPull},
+    pac,
+    prelude::*,
+};
+
+use wt_support::led::{PinLed, UserLed};
+
+// LD3, the red LED at the top of the compass rose, which blinks the SOS.
+//
+type Ld3 = PinLed<gpioe::PE9<Output<PushPull>>>;
+
+// How often the button is checked, in milliseconds.
+//
+const POLL_INTERVAL_MS: u16 = 10;
+
+// Number of polls between toggles of LD4, which blinks while all is well.
+//
+const POLLS_PER_TOGGLE: u32 = 25;
+
+#[entry]
+fn main() -> ! {
+    // Get peripherals.
+    //
+    // take() returns an Option, which requires handling the possibility of the
+    // return of an Err or None instead of the desired value, which is of type
+    // pac::Peripherals in this case.
+    //
+    // Since this is an embedded application, it's not as simple as writing to,
+    // stdout. This is a minimal example, so we'll drop into an inifinite loop
+    // to allow a debugger to find where the failure.
+    //
+    let device_periphs = pac::Peripherals::take().unwrap_or_else(|| {
+        loop {
+            // Failed to take pac::Peripherals.
+            asm::nop(); // If real app, replace with actual error handling.
+        }
+    });
+    let core_periphs = cortex_m::Peripherals::take().unwrap_or_else(|| {
+        loop {
+            // Failed to take cortex_m::Peripherals.
+            asm::nop(); // If real app, replace with actual error handling.
+        }
+    });
+
+    // Get RCC peripheral and set the system clock frequency.
+    //
+    let mut reset_and_clock_control = device_periphs.RCC.constrain();
+    let mut flash = device_periphs.FLASH.constrain();
+    let clocks = reset_and_clock_control
+        .cfgr
+        .sysclk(48.MHz())
+        .freeze(&mut flash.acr);
+
+    let mut delay = Delay::new(core_periphs.SYST, clocks);
+
+    // Get GPIO Ports A and E.
+    //
+    let mut gpioa = device_periphs.GPIOA.split(&mut reset_and_clock_control.ahb);
+    let mut gpioe = device_periphs.GPIOE.split(&mut reset_and_clock_control.ahb);
+
+    // Configure the user button B1 (PA0) as an input, as in the button-poll
+    // example. The board has a pull-down on it.
+    //
+    let button = gpioa
+        .pa0
+        .into_floating_input(&mut gpioa.moder, &mut gpioa.pupdr);
+
+    // Register LD3 for the panic handler to blink.
+    //
+    // The handler can't take the peripherals itself, since they're already
+    // taken by then, so the LED has to be set up here and handed over in
+    // advance, for good. It goes to a static, so it has to live forever:
+    // singleton!() makes it a &'static mut, as with the DMA buffers in the
+    // adc-dma example.
+    //
+    // Registering it as early as possible leaves the least time for a panic
+    // with no LED to blink, which just halts.
+    //
+    let ld3_pin = gpioe
+        .pe9
+        .into_push_pull_output(&mut gpioe.moder, &mut gpioe.otyper);
+    let ld3 = cortex_m::singleton!(: Ld3 = PinLed::new(ld3_pin)).unwrap_or_else(|| {
+        loop {
+            // Failed to create LD3, which can only happen if this runs twice.
+            asm::nop(); // If real app, replace with actual error handling.
+        }
+    });
+    wt_support::panic::register_sos_led(ld3, clocks.sysclk().0);
+
+    // LD4, the blue LED next to LD3, blinks to show the program is running.
+    //
+    let mut ld4 = PinLed::new(
+        gpioe
+            .pe8
+            .into_push_pull_output(&mut gpioe.moder, &mut gpioe.otyper),
+    );
+
+    // Main loop.
+    //
+    // Blink LD4 until B1 is pressed, then panic. LD4 stops where it is, and
+    // LD3 blinks SOS, three short, three long, three short, until the board
+    // is reset.
+    //
+    let mut polls: u32 = 0;
+    loop {
+        if button.is_high().unwrap_or(false) {
+            panic!("B1 was pressed");
+        }
+
+        polls += 1;
+        if polls == POLLS_PER_TOGGLE {
+            polls = 0;
+            ld4.toggle();
+        }
+
+        delay.delay_ms(POLL_INTERVAL_MS);
+    }
+}
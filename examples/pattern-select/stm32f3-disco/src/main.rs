@@ -0,0 +1,369 @@
+#![deny(unsafe_code)]
+#![no_std]
+#![no_main]
+
+use core::cell::RefCell;
+use core::fmt::Write;
+
+// Define the panic handler selected by the panic-* features in Cargo.toml.
+//
+// The default is panic-halt. A breakpoint can be set on `rust_begin_unwind` to
+// catch panics.
+//
+wt_support::panic_handler!();
+
+use cortex_m::asm;
+use cortex_m::peripheral::NVIC;
+use cortex_m_rt::entry;
+use critical_section::Mutex;
+
+use stm32f3xx_hal::{
+    gpio::{gpioe, Output, PushPull},
+    nb,
+    pac::{self, interrupt},
+    prelude::*,
+    serial::config,
+    serial::Serial,
+    timer::{Event, Timer},
+};
+
+use wt_support::millis;
+use wt_support::soft_pwm::{SoftPwm, MAX_DUTY_PERCENT};
+
+// Number of ticks in each period of the LED PWM and the interval between the
+// TIM2 interrupts that tick it, for 100 Hz PWM in steps of 1%, the same as in
+// the soft-pwm example.
+//
+const PWM_STEPS: u16 = 100;
+const TICK_INTERVAL_US: u32 = 100;
+
+// Number of user LEDs.
+//
+const LED_COUNT: usize = 8;
+
+// Change in duty per step of the breathing pattern, in percent.
+//
+const BREATHING_STEP_PERCENT: u8 = 2;
+
+// An LED driven as a software PWM output.
+//
+type LedPwm = SoftPwm<gpioe::PEx<Output<PushPull>>>;
+
+// The LEDs and the timer ticking their PWM, shared between main() and the TIM2
+// interrupt handler, as in the soft-pwm example.
+//
+static G_LEDS: Mutex<RefCell<Option<[LedPwm; LED_COUNT]>>> = Mutex::new(RefCell::new(None));
+static G_TIMER: Mutex<RefCell<Option<Timer<pac::TIM2>>>> = Mutex::new(RefCell::new(None));
+
+// The LED animations, selected by sending their number via UART4.
+//
+// Each pattern is a sequence of frames, where a frame is the duty of each LED.
+// The current pattern and its position in the sequence are the whole state of
+// the animation, so moving to the next frame or switching to another pattern
+// is just an update of two variables, and never has to wait for anything.
+//
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Pattern {
+    // All of the LEDs blinking together.
+    AllBlink,
+    // A single LED going around the compass rose.
+    Sweep,
+    // All of the LEDs fading up and down together.
+    Breathing,
+}
+
+impl Pattern {
+    // Returns the pattern selected by a command byte, if any.
+    //
+    fn from_command(byte: u8) -> Option<Self> {
+        match byte {
+            b'1' => Some(Pattern::AllBlink),
+            b'2' => Some(Pattern::Sweep),
+            b'3' => Some(Pattern::Breathing),
+            _ => None,
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Pattern::AllBlink => "all-blink",
+            Pattern::Sweep => "sweep",
+            Pattern::Breathing => "breathing",
+        }
+    }
+
+    // Time each frame of the pattern is shown for, in milliseconds.
+    //
+    fn frame_interval_ms(self) -> u32 {
+        match self {
+            Pattern::AllBlink => 250,
+            Pattern::Sweep => 100,
+            Pattern::Breathing => 20,
+        }
+    }
+
+    // Returns the number of frames before the pattern repeats.
+    //
+    fn frame_count(self) -> u32 {
+        match self {
+            Pattern::AllBlink => 2,
+            Pattern::Sweep => LED_COUNT as u32,
+            Pattern::Breathing => 2 * (MAX_DUTY_PERCENT / BREATHING_STEP_PERCENT) as u32,
+        }
+    }
+
+    // Returns the duty of each LED for frame number `frame`.
+    //
+    // The LEDs are indexed in their order around the compass rose, so sweeping
+    // through the indexes goes around the circle.
+    //
+    fn frame(self, frame: u32) -> [u8; LED_COUNT] {
+        match self {
+            Pattern::AllBlink => {
+                let duty = if frame == 0 { MAX_DUTY_PERCENT } else { 0 };
+                [duty; LED_COUNT]
+            }
+            Pattern::Sweep => {
+                let mut duties = [0; LED_COUNT];
+                duties[frame as usize] = MAX_DUTY_PERCENT;
+                duties
+            }
+            Pattern::Breathing => {
+                // Up for the first half of the frames and back down for the
+                // second.
+                let half = self.frame_count() / 2;
+                let level = if frame < half {
+                    frame
+                } else {
+                    2 * half - frame
+                };
+                [level as u8 * BREATHING_STEP_PERCENT; LED_COUNT]
+            }
+        }
+    }
+}
+
+// Sets the duty of each LED.
+//
+fn show(duties: [u8; LED_COUNT]) {
+    critical_section::with(|cs| {
+        if let Some(leds) = G_LEDS.borrow(cs).borrow_mut().as_mut() {
+            for (led, duty) in leds.iter_mut().zip(duties) {
+                led.set_duty(duty);
+            }
+        }
+    });
+}
+
+#[entry]
+fn main() -> ! {
+    // Get peripherals.
+    //
+    // take() returns an Option, which requires handling the possibility of the
+    // return of an Err or None instead of the desired value, which is of type
+    // pac::Peripherals in this case.
+    //
+    // Since this is an embedded application, it's not as simple as writing to,
+    // stdout. This is a minimal example, so we'll drop into an inifinite loop
+    // to allow a debugger to find where the failure.
+    //
+    let device_periphs = pac::Peripherals::take().unwrap_or_else(|| {
+        loop {
+            // Failed to take pac::Peripherals.
+            asm::nop(); // If real app, replace with actual error handling.
+        }
+    });
+    let core_periphs = cortex_m::Peripherals::take().unwrap_or_else(|| {
+        loop {
+            // Failed to take cortex_m::Peripherals.
+            asm::nop(); // If real app, replace with actual error handling.
+        }
+    });
+
+    // Get RCC peripheral and set the system clock frequency.
+    //
+    let mut reset_and_clock_control = device_periphs.RCC.constrain();
+    let mut flash = device_periphs.FLASH.constrain();
+    let clocks = reset_and_clock_control
+        .cfgr
+        .sysclk(48.MHz())
+        .pclk1(24.MHz())
+        .freeze(&mut flash.acr);
+
+    // Start the millisecond tick for timing the frames.
+    //
+    millis::init(core_periphs.SYST, clocks.sysclk().0);
+
+    // Get GPIO Ports C and E.
+    //
+    let mut gpioc = device_periphs.GPIOC.split(&mut reset_and_clock_control.ahb);
+    let mut gpioe = device_periphs.GPIOE.split(&mut reset_and_clock_control.ahb);
+
+    // Configure GPIO pins PC10 as TX and PC11 as RX for UART4, the same as in
+    // the uart example, and activate the UART.
+    //
+    let tx_pin = gpioc
+        .pc10
+        .into_af_push_pull(&mut gpioc.moder, &mut gpioc.otyper, &mut gpioc.afrh);
+    let rx_pin = gpioc
+        .pc11
+        .into_af_push_pull(&mut gpioc.moder, &mut gpioc.otyper, &mut gpioc.afrh);
+    let mut uart4 = Serial::new(
+        device_periphs.UART4,
+        (tx_pin, rx_pin),
+        config::Config::default().baudrate(115_200.Bd()),
+        clocks,
+        &mut reset_and_clock_control.apb1,
+    );
+
+    // Create instances of LD3 through LD10 in push-pull output mode, in their
+    // order going clockwise around the compass rose from north (LD3), and move
+    // them into the global as PWM outputs.
+    //
+    // Every pattern drives the LEDs through their duty, so the blinking ones
+    // just switch between 0% and 100%. That way, the pins all stay in one place,
+    // owned by the PWM, however the pattern changes.
+    //
+    let led = |pin: gpioe::PEx<Output<PushPull>>| SoftPwm::new(pin, PWM_STEPS);
+    let leds = [
+        led(gpioe
+            .pe9
+            .into_push_pull_output(&mut gpioe.moder, &mut gpioe.otyper)
+            .downgrade()),
+        led(gpioe
+            .pe10
+            .into_push_pull_output(&mut gpioe.moder, &mut gpioe.otyper)
+            .downgrade()),
+        led(gpioe
+            .pe11
+            .into_push_pull_output(&mut gpioe.moder, &mut gpioe.otyper)
+            .downgrade()),
+        led(gpioe
+            .pe12
+            .into_push_pull_output(&mut gpioe.moder, &mut gpioe.otyper)
+            .downgrade()),
+        led(gpioe
+            .pe13
+            .into_push_pull_output(&mut gpioe.moder, &mut gpioe.otyper)
+            .downgrade()),
+        led(gpioe
+            .pe14
+            .into_push_pull_output(&mut gpioe.moder, &mut gpioe.otyper)
+            .downgrade()),
+        led(gpioe
+            .pe15
+            .into_push_pull_output(&mut gpioe.moder, &mut gpioe.otyper)
+            .downgrade()),
+        led(gpioe
+            .pe8
+            .into_push_pull_output(&mut gpioe.moder, &mut gpioe.otyper)
+            .downgrade()),
+    ];
+    critical_section::with(|cs| G_LEDS.borrow(cs).replace(Some(leds)));
+
+    // Set up TIM2 to tick the PWM and unmask its interrupt, as in the soft-pwm
+    // example.
+    //
+    let mut timer = Timer::new(
+        device_periphs.TIM2,
+        clocks,
+        &mut reset_and_clock_control.apb1,
+    );
+    timer.enable_interrupt(Event::Update);
+    timer.start(TICK_INTERVAL_US.microseconds());
+    let timer_interrupt = timer.interrupt();
+    critical_section::with(|cs| G_TIMER.borrow(cs).replace(Some(timer)));
+
+    #[allow(unsafe_code)]
+    unsafe {
+        NVIC::unmask(timer_interrupt);
+    }
+
+    writeln!(
+        uart4,
+        "Send 1 for all-blink, 2 for sweep, or 3 for breathing.\r"
+    )
+    .ok();
+
+    // The state of the animation: the current pattern, the frame of it being
+    // shown, and when that frame was shown.
+    //
+    let mut pattern = Pattern::AllBlink;
+    let mut frame = 0;
+    let mut last_frame = millis::millis();
+    show(pattern.frame(frame));
+
+    // Main loop.
+    //
+    // The animation never waits, so every pass both checks for a command and
+    // checks whether it's time for the next frame. A pass takes a few
+    // microseconds, so a command is picked up within about the time it takes
+    // for the byte to arrive, however slow the pattern is. If the animation
+    // instead waited out each frame in delay_ms(), a command could sit
+    // unnoticed for a whole frame, and any more bytes arriving in the meantime
+    // would overrun the UART and be lost.
+    //
+    // The one thing that does block is the reply to a command. writeln!()
+    // waits for each byte to go out, so at 115200 baud, "Pattern: breathing"
+    // holds up the loop for about 2 ms, and the unknown pattern reply for
+    // about 3 ms. The UART only holds one received byte, so a second byte
+    // arriving during that, e.g. from pasting several commands at once, would
+    // overrun it and be lost. Typed one key at a time, the commands are far
+    // enough apart for that not to happen. The buffered-uart example queues
+    // its output for an interrupt to send instead.
+    //
+    // The LEDs themselves are switched by the TIM2 interrupt, so main() only
+    // ever sets their duties.
+    //
+    loop {
+        match uart4.read() {
+            Ok(byte) => match Pattern::from_command(byte) {
+                Some(new_pattern) => {
+                    // Switch to the new pattern right away, starting from its
+                    // first frame, rather than waiting for the current frame
+                    // to finish.
+                    pattern = new_pattern;
+                    frame = 0;
+                    last_frame = millis::millis();
+                    show(pattern.frame(frame));
+                    writeln!(uart4, "Pattern: {}\r", pattern.name()).ok();
+                }
+                None if byte == b'\r' || byte == b'\n' => {}
+                None => {
+                    writeln!(uart4, "Unknown pattern; send 1, 2, or 3.\r").ok();
+                }
+            },
+
+            // Nothing has been received.
+            Err(nb::Error::WouldBlock) => {}
+
+            // A framing, noise, or overrun error. The byte is lost, so there's
+            // nothing to act on.
+            Err(nb::Error::Other(_)) => {}
+        }
+
+        let interval_ms = pattern.frame_interval_ms();
+        if millis::elapsed_since(last_frame) >= interval_ms {
+            last_frame = last_frame.wrapping_add(interval_ms);
+            frame = (frame + 1) % pattern.frame_count();
+            show(pattern.frame(frame));
+        }
+    }
+}
+
+#[interrupt]
+fn TIM2() {
+    critical_section::with(|cs| {
+        // Clear the update flag.
+        //
+        if let Some(timer) = G_TIMER.borrow(cs).borrow_mut().as_mut() {
+            timer.clear_event(Event::Update);
+        }
+
+        if let Some(leds) = G_LEDS.borrow(cs).borrow_mut().as_mut() {
+            for led in leds.iter_mut() {
+                led.tick().ok();
+            }
+        }
+    });
+}
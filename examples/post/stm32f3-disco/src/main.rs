@@ -0,0 +1,348 @@
+#![deny(unsafe_code)]
+#![no_std]
+#![no_main]
+
+use core::fmt::{self, Write};
+
+// Define the panic handler selected by the panic-* features in Cargo.toml.
+//
+// The default is panic-halt. A breakpoint can be set on `rust_begin_unwind` to
+// catch panics.
+//
+wt_support::panic_handler!();
+
+use cortex_m::asm;
+use cortex_m_rt::entry;
+
+use stm32f3xx_hal::{
+    delay::Delay,
+    gpio::{gpioc, PushPull, AF5},
+    hal::blocking::i2c::{Write as I2cWrite, WriteRead},
+    i2c::I2c,
+    pac,
+    prelude::*,
+    rcc::{Enable, Reset},
+};
+
+use wt_support::blink;
+use wt_support::led::{PinLed, UserLed};
+use wt_support::lsm303::{self, Lsm303};
+use wt_support::post::{self, PostConfig, PostHw};
+use wt_support::profile::Stopwatch;
+
+// What the self-test expects of the board: the LSM303's accelerometer, and
+// the system clock as set up below, to within 2%, which allows for the HSI
+// it's run from being off by up to 1% at room temperature, while still being
+// close enough for a UART.
+//
+// Change sysclk_hz to e.g. 72_000_000 to see the clock check fail, with
+// code 4.
+//
+const POST_CONFIG: PostConfig = PostConfig {
+    accel_id: lsm303::WHO_AM_I_A_VALUE,
+    sysclk_hz: 48_000_000,
+    sysclk_tolerance_hz: 960_000,
+};
+
+// Baud rate of UART4.
+//
+const BAUD: u32 = 115_200;
+
+// Frequency of HSE, the 8 MHz clock from the ST-LINK's crystal on the MCO
+// output of its own MCU, which the Discovery board feeds to OSC_IN.
+//
+const HSE_HZ: u64 = 8_000_000;
+
+// HSE cycles per capture of TIM16: HSE / 32 is the input, and the input
+// capture prescaler only captures every 8th edge of it.
+//
+const HSE_CYCLES_PER_CAPTURE: u64 = 32 * 8;
+
+// Number of captures the system clock is counted over, which at 32 us each is
+// 4 ms.
+//
+const CAPTURES: u64 = 125;
+
+// How many times to poll for HSE to be ready before giving up on it, and how
+// many cycles to wait for a capture or a received byte, which at 48 MHz is
+// 1 ms, much longer than either takes.
+//
+const HSE_READY_POLLS: u32 = 100_000;
+const TIMEOUT_CYCLES: u32 = 48_000;
+
+// Length of one unit of the failure code blinked on LD3, in milliseconds.
+//
+const BLINK_UNIT_MS: u16 = 150;
+
+// UART4, set up through its registers as in the uart-errors example, since
+// the loopback is done in a mode the HAL's Serial doesn't have.
+//
+struct Uart(pac::UART4);
+
+impl Uart {
+    // Sets up and enables UART4 for 8N1 at BAUD, from a PCLK1 of `pclk1_hz`,
+    // in half-duplex mode if `half_duplex`.
+    //
+    // In half-duplex mode, TX and RX are connected inside the USART, so it
+    // receives whatever it sends, which is the loopback. HDSEL can only be
+    // changed with the UART disabled.
+    //
+    fn configure(&mut self, pclk1_hz: u32, half_duplex: bool) {
+        self.0.cr1.modify(|_, w| w.ue().disabled());
+        self.0.brr.write(|w| w.brr().bits((pclk1_hz / BAUD) as u16));
+        self.0.cr3.modify(|_, w| w.hdsel().bit(half_duplex));
+        self.0
+            .cr1
+            .modify(|_, w| w.ue().enabled().re().enabled().te().enabled());
+    }
+}
+
+impl fmt::Write for Uart {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        for byte in s.bytes() {
+            while self.0.isr.read().txe().bit_is_clear() {}
+            self.0.tdr.write(|w| w.tdr().bits(byte as u16));
+        }
+        Ok(())
+    }
+}
+
+// Sets TIM16 up to capture every 8th rising edge of HSE / 32 on channel 1.
+//
+// The input remap in OR and the fields of CCMR1 have no named values in the
+// PAC, so they're written as bits, which is unsafe.
+//
+#[allow(unsafe_code)]
+fn start_hse_capture(tim16: &pac::TIM16) {
+    // SAFETY: TI1_RMP = 0b10 connects TIM16_CH1 to HSE / 32 (RM0316, TIM16
+    // option register), and CC1S = 0b01 and IC1PSC = 0b11 capture TI1 on
+    // every 8th edge, all of which are valid values of the fields.
+    //
+    unsafe {
+        tim16.or.write(|w| w.bits(0b10));
+        tim16
+            .ccmr1_input()
+            .write(|w| w.cc1s().bits(0b01).ic1psc().bits(0b11));
+    }
+    tim16.ccer.modify(|_, w| w.cc1e().set_bit());
+    tim16.cr1.modify(|_, w| w.cen().set_bit());
+}
+
+// The board's hardware as wt_support::post needs it.
+//
+struct Board<I2C> {
+    sensor: Lsm303<I2C>,
+    uart: Uart,
+    tim16: pac::TIM16,
+    hse_ready: bool,
+}
+
+impl<I2C> Board<I2C> {
+    // Waits for the next capture of TIM16, returning false if there isn't one
+    // within TIMEOUT_CYCLES. Reading CCR1 clears the capture flag.
+    //
+    fn wait_for_capture(&self) -> bool {
+        let stopwatch = Stopwatch::start();
+        while self.tim16.sr.read().cc1if().bit_is_clear() {
+            if stopwatch.elapsed_cycles() > TIMEOUT_CYCLES {
+                return false;
+            }
+        }
+        self.tim16.ccr1().read();
+        true
+    }
+}
+
+impl<I2C, E> PostHw for Board<I2C>
+where
+    I2C: I2cWrite<Error = E> + WriteRead<Error = E>,
+{
+    fn accel_id(&mut self) -> Option<u8> {
+        self.sensor.accel_id().ok()
+    }
+
+    // Counts the core's cycles, on the DWT cycle counter, over CAPTURES
+    // captures of HSE / 32.
+    //
+    // HSE comes from a different crystal than the HSI the system clock is
+    // made from here, so if the PLL is set up wrong, the count shows it.
+    //
+    fn measure_sysclk_hz(&mut self) -> Option<u32> {
+        if !self.hse_ready || !self.wait_for_capture() {
+            return None;
+        }
+
+        let stopwatch = Stopwatch::start();
+        for _ in 0..CAPTURES {
+            if !self.wait_for_capture() {
+                return None;
+            }
+        }
+        let cycles = stopwatch.elapsed_cycles() as u64;
+
+        Some((cycles * HSE_HZ / (CAPTURES * HSE_CYCLES_PER_CAPTURE)) as u32)
+    }
+
+    fn loopback(&mut self, byte: u8) -> Option<u8> {
+        let uart = &self.uart.0;
+        uart.tdr.write(|w| w.tdr().bits(byte as u16));
+
+        let stopwatch = Stopwatch::start();
+        while uart.isr.read().rxne().bit_is_clear() {
+            if stopwatch.elapsed_cycles() > TIMEOUT_CYCLES {
+                return None;
+            }
+        }
+        Some(uart.rdr.read().rdr().bits() as u8)
+    }
+}
+
+#[entry]
+fn main() -> ! {
+    // Get peripherals.
+    //
+    // take() returns an Option, which requires handling the possibility of the
+    // return of an Err or None instead of the desired value, which is of type
+    // pac::Peripherals in this case.
+    //
+    // Since this is an embedded application, it's not as simple as writing to,
+    // stdout. This is a minimal example, so we'll drop into an inifinite loop
+    // to allow a debugger to find where the failure.
+    //
+    let device_periphs = pac::Peripherals::take().unwrap_or_else(|| {
+        loop {
+            // Failed to take pac::Peripherals.
+            asm::nop(); // If real app, replace with actual error handling.
+        }
+    });
+    let mut core_periphs = cortex_m::Peripherals::take().unwrap_or_else(|| {
+        loop {
+            // Failed to take cortex_m::Peripherals.
+            asm::nop(); // If real app, replace with actual error handling.
+        }
+    });
+
+    // Turn on HSE, bypassing its oscillator since it's fed a clock rather
+    // than a crystal, as the reference for the clock check, before the RCC
+    // is handed to the HAL. Without a clock on OSC_IN, it never gets ready,
+    // and the clock check fails with code 3.
+    //
+    // The HAL leaves HSE alone, since the system clock isn't set up from it.
+    //
+    let rcc = &device_periphs.RCC;
+    rcc.cr.modify(|_, w| w.hsebyp().bypassed().hseon().on());
+    let hse_ready = (0..HSE_READY_POLLS).any(|_| rcc.cr.read().hserdy().is_ready());
+
+    // Get RCC peripheral and set the system clock frequency.
+    //
+    let mut reset_and_clock_control = device_periphs.RCC.constrain();
+    let mut flash = device_periphs.FLASH.constrain();
+    let clocks = reset_and_clock_control
+        .cfgr
+        .sysclk(48.MHz())
+        .freeze(&mut flash.acr);
+
+    wt_support::dwt_delay::init(&mut core_periphs.DCB, &mut core_periphs.DWT);
+    let mut delay = Delay::new(core_periphs.SYST, clocks);
+
+    // Get GPIO Ports B, C, and E.
+    //
+    let mut gpiob = device_periphs.GPIOB.split(&mut reset_and_clock_control.ahb);
+    let mut gpioc = device_periphs.GPIOC.split(&mut reset_and_clock_control.ahb);
+    let mut gpioe = device_periphs.GPIOE.split(&mut reset_and_clock_control.ahb);
+
+    // LD3, the red LED, blinks the failure code, and LD6, the green one,
+    // blinks once the self-test has passed.
+    //
+    let mut ld3 = PinLed::new(
+        gpioe
+            .pe9
+            .into_push_pull_output(&mut gpioe.moder, &mut gpioe.otyper),
+    );
+    let mut ld6 = PinLed::new(
+        gpioe
+            .pe15
+            .into_push_pull_output(&mut gpioe.moder, &mut gpioe.otyper),
+    );
+
+    // Configure PB6 as SCL and PB7 as SDA for I2C1, and hand it to the
+    // LSM303DLHC driver, as in the tilt-compass example.
+    //
+    let scl = gpiob
+        .pb6
+        .into_af_open_drain(&mut gpiob.moder, &mut gpiob.otyper, &mut gpiob.afrl);
+    let sda = gpiob
+        .pb7
+        .into_af_open_drain(&mut gpiob.moder, &mut gpiob.otyper, &mut gpiob.afrl);
+    let i2c1 = I2c::new(
+        device_periphs.I2C1,
+        (scl, sda),
+        100_000.Hz(),
+        clocks,
+        &mut reset_and_clock_control.apb1,
+    );
+
+    // Configure PC10 as TX and PC11 as RX for UART4, and enable it in
+    // half-duplex mode for the loopback check, as in the uart-errors example.
+    //
+    // In half-duplex mode, the bytes sent also go out on PC10, so a terminal
+    // connected to it shows a few stray characters before the report.
+    //
+    let _tx_pin: gpioc::PC10<AF5<PushPull>> =
+        gpioc
+            .pc10
+            .into_af_push_pull(&mut gpioc.moder, &mut gpioc.otyper, &mut gpioc.afrh);
+    let _rx_pin: gpioc::PC11<AF5<PushPull>> =
+        gpioc
+            .pc11
+            .into_af_push_pull(&mut gpioc.moder, &mut gpioc.otyper, &mut gpioc.afrh);
+    pac::UART4::enable(&mut reset_and_clock_control.apb1);
+    pac::UART4::reset(&mut reset_and_clock_control.apb1);
+    let mut uart = Uart(device_periphs.UART4);
+    uart.configure(clocks.pclk1().0, true);
+
+    // Start TIM16 capturing HSE / 32 for the clock check.
+    //
+    pac::TIM16::enable(&mut reset_and_clock_control.apb2);
+    pac::TIM16::reset(&mut reset_and_clock_control.apb2);
+    start_hse_capture(&device_periphs.TIM16);
+
+    // Run the self-test.
+    //
+    let mut board = Board {
+        sensor: Lsm303::new(i2c1),
+        uart,
+        tim16: device_periphs.TIM16,
+        hse_ready,
+    };
+    let result = post::run_self_test(&mut board, &POST_CONFIG);
+    let mut uart = board.uart;
+
+    // Back to normal, full-duplex operation for the report.
+    //
+    uart.configure(clocks.pclk1().0, false);
+    writeln!(uart, "\r").ok();
+
+    // On a failure, report it, and blink its code on LD3 forever, rather than
+    // carrying on with hardware that isn't working. The report only gets out
+    // if the UART and the clock are working, which is why the code is
+    // blinked as well.
+    //
+    if let Err(e) = result {
+        writeln!(uart, "POST failed with code {}: {:?}\r", e.code(), e).ok();
+        loop {
+            blink::blink_pattern(&mut ld3, &mut delay, e.blink_pattern(), BLINK_UNIT_MS);
+        }
+    }
+    writeln!(uart, "POST passed.\r").ok();
+
+    // Main loop.
+    //
+    // The self-test passed, so this is where the program proper would start.
+    // Here, it just blinks LD6.
+    //
+    loop {
+        ld6.toggle();
+        delay.delay_ms(500_u16);
+    }
+}
@@ -0,0 +1,162 @@
+#![deny(unsafe_code)]
+#![no_std]
+#![no_main]
+
+// Define the panic handler selected by the panic-* features in Cargo.toml.
+//
+// The default is panic-halt. A breakpoint can be set on `rust_begin_unwind` to
+// catch panics.
+//
+wt_support::panic_handler!();
+
+use cortex_m::asm;
+use cortex_m_rt::entry;
+
+use stm32f3xx_hal::{
+    gpio::{gpioe, Output, PushPull},
+    pac,
+    prelude::*,
+};
+
+use wt_support::millis;
+use wt_support::press::{PressDetector, PressEvent};
+
+// How long the button has to be held for a long press, in milliseconds.
+//
+const LONG_PRESS_MS: u32 = 1_000;
+
+// Time between samples of the button in milliseconds.
+//
+// Longer than the contacts of the button bounce for, so that two samples in a
+// row agreeing is enough to debounce it, as in the keypad example.
+//
+const SAMPLE_INTERVAL_MS: u32 = 10;
+
+#[entry]
+fn main() -> ! {
+    // Get peripherals.
+    //
+    // take() returns an Option, which requires handling the possibility of the
+    // return of an Err or None instead of the desired value, which is of type
+    // pac::Peripherals in this case.
+    //
+    // Since this is an embedded application, it's not as simple as writing to,
+    // stdout. This is a minimal example, so we'll drop into an inifinite loop
+    // to allow a debugger to find where the failure.
+    //
+    let device_periphs = pac::Peripherals::take().unwrap_or_else(|| {
+        loop {
+            // Failed to take pac::Peripherals.
+            asm::nop(); // If real app, replace with actual error handling.
+        }
+    });
+    let core_periphs = cortex_m::Peripherals::take().unwrap_or_else(|| {
+        loop {
+            // Failed to take cortex_m::Peripherals.
+            asm::nop(); // If real app, replace with actual error handling.
+        }
+    });
+
+    // Get RCC peripheral and set the system clock frequency.
+    //
+    let mut reset_and_clock_control = device_periphs.RCC.constrain();
+    let mut flash = device_periphs.FLASH.constrain();
+    let clocks = reset_and_clock_control
+        .cfgr
+        .sysclk(48.MHz())
+        .freeze(&mut flash.acr);
+
+    // Start the millisecond tick for timing the samples and the presses.
+    //
+    millis::init(core_periphs.SYST, clocks.sysclk().0);
+
+    // Get GPIO Ports A and E.
+    //
+    let mut gpioa = device_periphs.GPIOA.split(&mut reset_and_clock_control.ahb);
+    let mut gpioe = device_periphs.GPIOE.split(&mut reset_and_clock_control.ahb);
+
+    // Configure the user button B1 (PA0) as an input with a pull-down, as in
+    // the button-poll example. It reads high while pressed.
+    //
+    let button = gpioa
+        .pa0
+        .into_pull_down_input(&mut gpioa.moder, &mut gpioa.pupdr);
+
+    // Create instances of LD3 through LD10 in push-pull output mode, as in the
+    // blinky example, with LD3 first.
+    //
+    let mut leds: [gpioe::PEx<Output<PushPull>>; 8] = [
+        gpioe
+            .pe9
+            .into_push_pull_output(&mut gpioe.moder, &mut gpioe.otyper)
+            .downgrade(),
+        gpioe
+            .pe10
+            .into_push_pull_output(&mut gpioe.moder, &mut gpioe.otyper)
+            .downgrade(),
+        gpioe
+            .pe11
+            .into_push_pull_output(&mut gpioe.moder, &mut gpioe.otyper)
+            .downgrade(),
+        gpioe
+            .pe12
+            .into_push_pull_output(&mut gpioe.moder, &mut gpioe.otyper)
+            .downgrade(),
+        gpioe
+            .pe13
+            .into_push_pull_output(&mut gpioe.moder, &mut gpioe.otyper)
+            .downgrade(),
+        gpioe
+            .pe14
+            .into_push_pull_output(&mut gpioe.moder, &mut gpioe.otyper)
+            .downgrade(),
+        gpioe
+            .pe15
+            .into_push_pull_output(&mut gpioe.moder, &mut gpioe.otyper)
+            .downgrade(),
+        gpioe
+            .pe8
+            .into_push_pull_output(&mut gpioe.moder, &mut gpioe.otyper)
+            .downgrade(),
+    ];
+
+    let mut detector = PressDetector::new(LONG_PRESS_MS);
+
+    // Main loop.
+    //
+    // Sample the button every SAMPLE_INTERVAL_MS. Its debounced state only
+    // changes once two samples in a row agree on the new one, and that's what
+    // the detector is fed, so the bouncing of the contacts never reaches it.
+    //
+    // A short press toggles LD3 alone, and a long press toggles all of the
+    // LEDs, as soon as the button has been held for LONG_PRESS_MS, before it's
+    // let go.
+    //
+    let mut last_sample = millis::millis();
+    let mut last_reading = false;
+    let mut pressed = false;
+    loop {
+        if millis::elapsed_since(last_sample) < SAMPLE_INTERVAL_MS {
+            continue;
+        }
+        last_sample = last_sample.wrapping_add(SAMPLE_INTERVAL_MS);
+
+        let reading = button.is_high().unwrap_or(false);
+        if reading == last_reading {
+            pressed = reading;
+        }
+        last_reading = reading;
+
+        match detector.update(pressed, millis::millis()) {
+            Some(PressEvent::Short) => {
+                leds[0].toggle().ok();
+            }
+            Some(PressEvent::Long) => {
+                for led in leds.iter_mut() {
+                    led.toggle().ok();
+                }
+            }
+            None => {}
+        }
+    }
+}
@@ -0,0 +1,179 @@
+#![deny(unsafe_code)]
+#![no_std]
+#![no_main]
+
+use core::fmt::Write;
+use core::hint::black_box;
+
+// Define the panic handler selected by the panic-* features in Cargo.toml.
+//
+// The default is panic-halt. A breakpoint can be set on `rust_begin_unwind` to
+// catch panics.
+//
+wt_support::panic_handler!();
+
+use cortex_m::asm;
+use cortex_m_rt::entry;
+
+use stm32f3xx_hal::{delay::Delay, pac, prelude::*, serial::config, serial::Serial};
+
+use wt_support::dwt_delay;
+use wt_support::profile::{self, Stopwatch};
+
+// Number of iterations of the floating-point computation.
+//
+const ITERATIONS: u32 = 10;
+
+// Interval between rounds of measurements.
+//
+const MEASURE_INTERVAL_MS: u16 = 2_000;
+
+// Approximates the square root of `x` with Newton's method, in single
+// precision.
+//
+// The Cortex-M4F has an FPU for f32, so each operation here is a single
+// instruction taking one cycle or, for the division, 14.
+//
+fn sqrt_f32(x: f32) -> f32 {
+    let mut y = x;
+    for _ in 0..ITERATIONS {
+        y = 0.5 * (y + x / y);
+    }
+    y
+}
+
+// The same in double precision.
+//
+// The FPU of the Cortex-M4F only does single precision, so every f64 operation
+// is a call into a software floating-point routine in compiler-builtins.
+//
+fn sqrt_f64(x: f64) -> f64 {
+    let mut y = x;
+    for _ in 0..ITERATIONS {
+        y = 0.5 * (y + x / y);
+    }
+    y
+}
+
+#[entry]
+fn main() -> ! {
+    // Get peripherals.
+    //
+    // take() returns an Option, which requires handling the possibility of the
+    // return of an Err or None instead of the desired value, which is of type
+    // pac::Peripherals in this case.
+    //
+    // Since this is an embedded application, it's not as simple as writing to,
+    // stdout. This is a minimal example, so we'll drop into an inifinite loop
+    // to allow a debugger to find where the failure.
+    //
+    let device_periphs = pac::Peripherals::take().unwrap_or_else(|| {
+        loop {
+            // Failed to take pac::Peripherals.
+            asm::nop(); // If real app, replace with actual error handling.
+        }
+    });
+    let mut core_periphs = cortex_m::Peripherals::take().unwrap_or_else(|| {
+        loop {
+            // Failed to take cortex_m::Peripherals.
+            asm::nop(); // If real app, replace with actual error handling.
+        }
+    });
+
+    // Get RCC peripheral and set the system clock frequency.
+    //
+    let mut reset_and_clock_control = device_periphs.RCC.constrain();
+    let mut flash = device_periphs.FLASH.constrain();
+    let clocks = reset_and_clock_control
+        .cfgr
+        .sysclk(48.MHz())
+        .freeze(&mut flash.acr);
+    let mut delay = Delay::new(core_periphs.SYST, clocks);
+
+    // Enable the DWT cycle counter that the stopwatch reads, as in the
+    // dwt-delay example.
+    //
+    if !dwt_delay::init(&mut core_periphs.DCB, &mut core_periphs.DWT) {
+        loop {
+            // No DWT cycle counter.
+            asm::nop(); // If real app, replace with actual error handling.
+        }
+    }
+
+    // Get GPIO Ports C and E.
+    //
+    let mut gpioc = device_periphs.GPIOC.split(&mut reset_and_clock_control.ahb);
+    let mut gpioe = device_periphs.GPIOE.split(&mut reset_and_clock_control.ahb);
+
+    // Configure GPIO pins PC10 as TX and PC11 as RX for UART4, the same as in
+    // the uart example, and activate the UART.
+    //
+    let tx_pin = gpioc
+        .pc10
+        .into_af_push_pull(&mut gpioc.moder, &mut gpioc.otyper, &mut gpioc.afrh);
+    let rx_pin = gpioc
+        .pc11
+        .into_af_push_pull(&mut gpioc.moder, &mut gpioc.otyper, &mut gpioc.afrh);
+    let mut uart4 = Serial::new(
+        device_periphs.UART4,
+        (tx_pin, rx_pin),
+        config::Config::default().baudrate(115_200.Bd()),
+        clocks,
+        &mut reset_and_clock_control.apb1,
+    );
+
+    // Create an instance of LD3 (PE9) in push-pull output mode.
+    //
+    let mut led = gpioe
+        .pe9
+        .into_push_pull_output(&mut gpioe.moder, &mut gpioe.otyper);
+
+    // Measure the overhead of the measurement itself.
+    //
+    // Every count below includes the cycles it takes to read the counter at
+    // either end, on top of the code being measured. An empty closure takes
+    // nothing but those, so its count is the baseline to subtract.
+    //
+    let overhead = profile::overhead_cycles();
+    writeln!(uart4, "Measurement overhead: {} cycles\r", overhead).ok();
+
+    // Main loop.
+    //
+    // Measure each piece of code, subtract the baseline, and write the counts
+    // out. The inputs and results go through black_box(), which the optimizer
+    // has to treat as opaque, so it can't work out the results at compile time
+    // or drop the computations since nothing uses them.
+    //
+    // The counts are only meaningful in a release build. In a debug build,
+    // even the LED toggle goes through several layers of unoptimized calls.
+    //
+    // The write to UART4 is itself slow enough to measure the same way, which
+    // is a good reminder that writing anything out in the middle of code being
+    // profiled changes the answer.
+    //
+    loop {
+        let toggle = Stopwatch::measure(|| {
+            led.toggle().ok();
+        });
+        let single = Stopwatch::measure(|| {
+            black_box(sqrt_f32(black_box(2.0)));
+        });
+        let double = Stopwatch::measure(|| {
+            black_box(sqrt_f64(black_box(2.0)));
+        });
+
+        let stopwatch = Stopwatch::start();
+        writeln!(
+            uart4,
+            "LED toggle: {} cycles, f32 sqrt: {} cycles, f64 sqrt: {} cycles\r",
+            toggle.saturating_sub(overhead),
+            single.saturating_sub(overhead),
+            double.saturating_sub(overhead),
+        )
+        .ok();
+        let write = stopwatch.elapsed_cycles().saturating_sub(overhead);
+        writeln!(uart4, "Writing that line out: {} cycles\r", write).ok();
+
+        delay.delay_ms(MEASURE_INTERVAL_MS);
+    }
+}
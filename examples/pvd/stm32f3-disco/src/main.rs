@@ -0,0 +1,261 @@
+#![deny(unsafe_code)]
+#![no_std]
+#![no_main]
+
+use core::cell::RefCell;
+use core::fmt::Write;
+use core::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+
+// Define the panic handler selected by the panic-* features in Cargo.toml.
+//
+// The default is panic-halt. A breakpoint can be set on `rust_begin_unwind` to
+// catch panics.
+//
+wt_support::panic_handler!();
+
+use cortex_m::asm;
+use cortex_m::peripheral::NVIC;
+use cortex_m_rt::entry;
+use critical_section::Mutex;
+
+use stm32f3xx_hal::{
+    pac::{self, interrupt},
+    prelude::*,
+    rcc::Enable,
+    serial::config,
+    serial::Serial,
+};
+
+// PVD level selection, the PLS field of PWR_CR, from 0 to 7.
+//
+// The PVD compares VDD against one of eight thresholds, about 0.1 V apart,
+// from roughly 2.1 V at PLS = 0 up to roughly 2.8 V at PLS = 7 for a falling
+// VDD. Each has around 0.1 V of hysteresis, so VDD has to rise that much
+// further above the threshold before the PVD says it's back. The exact
+// values, with their minimums and maximums, are in the "Programmable voltage
+// detector characteristics" table of the STM32F303xC datasheet.
+//
+// 6 puts the threshold at about 2.7 V for a falling VDD, and 2.8 V for a
+// rising one, comfortably below the 3 V the Discovery's regulator supplies,
+// but while the chip and everything else on 3 V still works, leaving time to
+// do something about it.
+//
+const PVD_LEVEL: u8 = 6;
+
+// EXTI line that the PVD output is wired to.
+//
+// As with the RTC alarm on line 17 in the rtc-alarm example, the PVD doesn't
+// go to the NVIC directly, but through one of the EXTI lines above the 16 for
+// GPIO, line 16 on the STM32F303 (RM0316, table 82 in the EXTI section).
+//
+const PVD_EXTI_LINE: u8 = 16;
+
+// The PWR and EXTI peripherals, shared between main(), which sets them up,
+// and the PVD interrupt handler, as in the shared-state example.
+//
+static PWR: Mutex<RefCell<Option<pac::PWR>>> = Mutex::new(RefCell::new(None));
+static EXTI: Mutex<RefCell<Option<pac::EXTI>>> = Mutex::new(RefCell::new(None));
+
+// Whether VDD was below the threshold at the last PVD interrupt.
+//
+static VDD_LOW: AtomicBool = AtomicBool::new(false);
+
+// Number of PVD interrupts handled, for main() to tell when there's been one.
+//
+static PVD_COUNT: AtomicU32 = AtomicU32::new(0);
+
+#[entry]
+fn main() -> ! {
+    // Get peripherals.
+    //
+    // take() returns an Option, which requires handling the possibility of the
+    // return of an Err or None instead of the desired value, which is of type
+    // pac::Peripherals in this case.
+    //
+    // Since this is an embedded application, it's not as simple as writing to,
+    // stdout. This is a minimal example, so we'll drop into an inifinite loop
+    // to allow a debugger to find where the failure.
+    //
+    let device_periphs = pac::Peripherals::take().unwrap_or_else(|| {
+        loop {
+            // Failed to take pac::Peripherals.
+            asm::nop(); // If real app, replace with actual error handling.
+        }
+    });
+
+    // Get RCC peripheral and set the system clock frequency.
+    //
+    let mut reset_and_clock_control = device_periphs.RCC.constrain();
+    let mut flash = device_periphs.FLASH.constrain();
+    let clocks = reset_and_clock_control
+        .cfgr
+        .sysclk(48.MHz())
+        .freeze(&mut flash.acr);
+
+    // Get GPIO Port C.
+    //
+    let mut gpioc = device_periphs.GPIOC.split(&mut reset_and_clock_control.ahb);
+
+    // Configure GPIO pins PC10 as TX and PC11 as RX for UART4, the same as in
+    // the uart example, and activate the UART.
+    //
+    let tx_pin = gpioc
+        .pc10
+        .into_af_push_pull(&mut gpioc.moder, &mut gpioc.otyper, &mut gpioc.afrh);
+    let rx_pin = gpioc
+        .pc11
+        .into_af_push_pull(&mut gpioc.moder, &mut gpioc.otyper, &mut gpioc.afrh);
+    let mut uart4 = Serial::new(
+        device_periphs.UART4,
+        (tx_pin, rx_pin),
+        config::Config::default().baudrate(115_200.Bd()),
+        clocks,
+        &mut reset_and_clock_control.apb1,
+    );
+
+    // Enable the Programmable Voltage Detector.
+    //
+    // The PVD is part of the PWR peripheral, which needs its clock first. It
+    // then only takes the level and the enable bit, PVDE, in PWR_CR. Its
+    // output, PVDO in PWR_CSR, is set while VDD is below the threshold, and
+    // clear while it's above.
+    //
+    // Setting the level is unsafe in the PAC, since PLS is a 3-bit field
+    // written as raw bits, but any value from 0 to 7 is a valid level.
+    //
+    pac::PWR::enable(&mut reset_and_clock_control.apb1);
+    let pwr = device_periphs.PWR;
+    #[allow(unsafe_code)]
+    pwr.cr.modify(|_, w| unsafe { w.pls().bits(PVD_LEVEL) });
+    pwr.cr.modify(|_, w| w.pvde().set_bit());
+
+    // Give the PVD time to settle before reading its output, a few
+    // microseconds at most, well within 1000 cycles at 48 MHz.
+    //
+    asm::delay(1_000);
+
+    // Route the PVD output through EXTI to the NVIC.
+    //
+    // EXTI line 16 follows PVDO, so its rising edge is VDD falling below the
+    // threshold, and its falling edge is VDD rising back above it. Both are
+    // enabled, to report the warning and the recovery.
+    //
+    // EXTI only sees edges, so if VDD is already low when this runs, there's
+    // no interrupt for it. PVDO is read once here for that.
+    //
+    let exti = device_periphs.EXTI;
+    exti.imr1.modify(|_, w| w.mr16().unmasked());
+    exti.rtsr1.modify(|_, w| w.tr16().enabled());
+    exti.ftsr1.modify(|_, w| w.tr16().enabled());
+
+    let low_at_start = pwr.csr.read().pvdo().bit_is_set();
+    VDD_LOW.store(low_at_start, Ordering::Relaxed);
+
+    // Move PWR and EXTI into the globals.
+    //
+    critical_section::with(|cs| {
+        PWR.borrow(cs).replace(Some(pwr));
+        EXTI.borrow(cs).replace(Some(exti));
+    });
+
+    // Unmask the PVD interrupt in the NVIC.
+    //
+    // The handler needs EXTI to clear the pending bit of line 16. If VDD is
+    // hovering around the threshold, an edge can already be pending from when
+    // the line was unmasked in EXTI above, and a handler that ran before EXTI
+    // was in its global would leave the bit set and be re-entered over and
+    // over. It also needs PWR to read PVDO, which is how VDD_LOW is kept
+    // right when VDD crosses back before the handler gets to run.
+    //
+    #[allow(unsafe_code)]
+    unsafe {
+        NVIC::unmask(interrupt::PVD);
+    }
+
+    writeln!(
+        uart4,
+        "Watching VDD with PVD level {}, on EXTI line {}.\r",
+        PVD_LEVEL, PVD_EXTI_LINE
+    )
+    .ok();
+    if low_at_start {
+        writeln!(
+            uart4,
+            "Low voltage warning: VDD is already below the threshold!\r"
+        )
+        .ok();
+    }
+
+    // PVD interrupt count last reported.
+    //
+    let mut last_count = 0;
+
+    // Main loop.
+    //
+    // Sleep until an interrupt, then report whether VDD has gone low or come
+    // back. The handler runs on every edge, so a VDD hovering around the
+    // threshold could give a burst of them, which the hysteresis keeps down.
+    //
+    // To see it work, power the board from an adjustable bench supply on its
+    // 3V and GND pins instead of USB, and turn it down slowly from 3 V, and
+    // back up again. The USB to serial adapter for UART4 needs its own power
+    // then. Don't take VDD below 2 V or above 3.6 V.
+    //
+    // Brown-out reset:
+    //
+    // The PVD only warns. The chip carries on running, and it's up to the
+    // program what to do with the time it has left, e.g. save state to flash
+    // or the backup registers, or stop driving a motor, before VDD drops too
+    // far for the chip to work reliably. What stops it is a reset, which on
+    // the STM32F303 is the fixed power-down reset (PDR), at around 1.8 V,
+    // held until VDD comes back above the power-on reset (POR) level, just
+    // above it.
+    //
+    // Other STM32s also have a brown-out reset (BOR), which holds the chip in
+    // reset below a higher level, chosen in the option bytes, so it never
+    // runs in the range where the flash or the clocks might not be reliable.
+    // On the STM32F767 of the NUCLEO-F767ZI, BOR_LEV in the option bytes
+    // selects off, which leaves just the POR/PDR, or one of three levels at
+    // about 2.1, 2.4, or 2.7 V. Being in the option bytes, it's set with a
+    // flash tool such as STM32CubeProgrammer rather than by the program, and
+    // it stays set across resets and power cycles. The STM32F303 has no BOR,
+    // which leaves the PVD as the way to react to VDD dropping before the
+    // PDR.
+    //
+    loop {
+        asm::wfi();
+
+        let count = PVD_COUNT.load(Ordering::Relaxed);
+        if count != last_count {
+            last_count = count;
+            if VDD_LOW.load(Ordering::Relaxed) {
+                writeln!(uart4, "Low voltage warning: VDD below the threshold!\r").ok();
+            } else {
+                writeln!(uart4, "VDD back above the threshold.\r").ok();
+            }
+        }
+    }
+}
+
+// Records whether VDD has gone below the threshold or come back above it.
+//
+#[interrupt]
+fn PVD() {
+    critical_section::with(|cs| {
+        // Read PVDO rather than assuming which edge it was, since VDD could
+        // have crossed back by the time the handler runs.
+        //
+        if let Some(pwr) = PWR.borrow(cs).borrow().as_ref() {
+            VDD_LOW.store(pwr.csr.read().pvdo().bit_is_set(), Ordering::Relaxed);
+        }
+
+        // Clear the pending bit in EXTI, or the handler runs again as soon
+        // as it returns. Writing 0 to the other bits doesn't change them.
+        //
+        if let Some(exti) = EXTI.borrow(cs).borrow().as_ref() {
+            exti.pr1.write(|w| w.pr16().clear());
+        }
+    });
+
+    PVD_COUNT.fetch_add(1, Ordering::Relaxed);
+}
@@ -0,0 +1,255 @@
+#![deny(unsafe_code)]
+#![no_std]
+#![no_main]
+
+use core::cell::RefCell;
+
+// Define the panic handler selected by the panic-* features in Cargo.toml.
+//
+// The default is panic-halt. A breakpoint can be set on `rust_begin_unwind` to
+// catch panics.
+//
+wt_support::panic_handler!();
+
+use cortex_m::asm;
+use cortex_m::peripheral::NVIC;
+use cortex_m_rt::entry;
+use critical_section::Mutex;
+
+use stm32f3xx_hal::{
+    pac::{self, interrupt},
+    prelude::*,
+    rcc::{Enable, Reset},
+    timer::{Event, Timer},
+};
+
+// The sound, as 8-bit unsigned PCM samples at SAMPLE_RATE_HZ, mono, with 128
+// as silence.
+//
+// include_bytes!() builds the file into the binary, so the samples are stored
+// in flash and read straight from there, taking no RAM. ding.raw is half a
+// second of a bell-like tone, 4_000 bytes. Any other sound can be converted
+// to the same format with SoX, e.g.:
+//
+// sox input.wav -r 8000 -c 1 -b 8 -e unsigned-integer ding.raw
+//
+// Flash is the limit on length: at 8 kHz, each second takes 8 KB of the 256
+// KB on the STM32F303VC.
+//
+static SAMPLES: &[u8] = include_bytes!("../ding.raw");
+
+// Rate the samples were recorded at, and so the rate they're played back at.
+//
+const SAMPLE_RATE_HZ: u32 = 8_000;
+
+// Whether to play the sound over and over, or just once and then go silent.
+//
+const LOOP: bool = true;
+
+// Duty of the PWM for silence, halfway between the lowest and highest
+// samples.
+//
+const SILENCE: u8 = 128;
+
+// Plays SAMPLES through the PWM, one sample per tick of the sample rate timer.
+//
+struct Player {
+    pwm: pac::TIM3,
+    samples: &'static [u8],
+    position: usize,
+}
+
+impl Player {
+    // Sets the duty to the next sample and moves past it, and returns whether
+    // there are any more to play.
+    //
+    fn step(&mut self) -> bool {
+        match self.samples.get(self.position) {
+            Some(&sample) => {
+                self.set_duty(sample);
+                self.position += 1;
+                true
+            }
+            None if LOOP && !self.samples.is_empty() => {
+                // Start again from the beginning, playing the first sample
+                // now, so there's no gap of a sample between the end of one
+                // loop and the start of the next.
+                self.position = 0;
+                self.step()
+            }
+            None => {
+                // The end of the samples, or no samples at all. Leave the
+                // output at the silence level rather than the last sample, so
+                // there's no click if playback is started again.
+                self.set_duty(SILENCE);
+                false
+            }
+        }
+    }
+
+    fn set_duty(&mut self, duty: u8) {
+        self.pwm.ccr1().write(|w| w.ccr().bits(u16::from(duty)));
+    }
+}
+
+// The player and the sample rate timer, shared between main() and the TIM2
+// interrupt handler.
+//
+static G_PLAYER: Mutex<RefCell<Option<Player>>> = Mutex::new(RefCell::new(None));
+static G_TIMER: Mutex<RefCell<Option<Timer<pac::TIM2>>>> = Mutex::new(RefCell::new(None));
+
+#[entry]
+fn main() -> ! {
+    // Get peripherals.
+    //
+    // take() returns an Option, which requires handling the possibility of the
+    // return of an Err or None instead of the desired value, which is of type
+    // pac::Peripherals in this case.
+    //
+    // Since this is an embedded application, it's not as simple as writing to,
+    // stdout. This is a minimal example, so we'll drop into an inifinite loop
+    // to allow a debugger to find where the failure.
+    //
+    let device_periphs = pac::Peripherals::take().unwrap_or_else(|| {
+        loop {
+            // Failed to take pac::Peripherals.
+            asm::nop(); // If real app, replace with actual error handling.
+        }
+    });
+
+    // Get RCC peripheral and set the system clock frequency.
+    //
+    // With PCLK1 at 24 MHz, the APB1 timers are clocked at twice that, 48
+    // MHz, as in the buzzer example.
+    //
+    let mut reset_and_clock_control = device_periphs.RCC.constrain();
+    let mut flash = device_periphs.FLASH.constrain();
+    let clocks = reset_and_clock_control
+        .cfgr
+        .sysclk(48.MHz())
+        .pclk1(24.MHz())
+        .freeze(&mut flash.acr);
+
+    // Get GPIO Port B.
+    //
+    let mut gpiob = device_periphs.GPIOB.split(&mut reset_and_clock_control.ahb);
+
+    // Configure PB4 as TIM3 channel 1 (alternate function 2), as in the buzzer
+    // example.
+    //
+    // The board has no speaker of its own. Connect a small speaker through a
+    // transistor, or a piezo disc, between PB4 and GND, with a ~100 ohm
+    // resistor to limit the current. For much better sound, add an RC low-pass
+    // filter of 1 kohm and 33 nF, which cuts off at about 4.8 kHz, and feed the
+    // voltage across the capacitor to an amplified speaker. That takes out the
+    // PWM carrier and leaves only the sound.
+    //
+    let _audio_pin =
+        gpiob
+            .pb4
+            .into_af_push_pull::<2>(&mut gpiob.moder, &mut gpiob.otyper, &mut gpiob.afrl);
+
+    // Set up TIM3 as the PWM carrier.
+    //
+    // An 8-bit sample is played by setting the duty of the PWM to it, out of
+    // 256, so the average voltage of the output follows the sound, and the
+    // speaker or the filter averages out the individual pulses. For that to
+    // work, the PWM frequency, the carrier, has to be well above anything
+    // that can be heard, or it's heard as a whine on top of the sound, and
+    // well above the sample rate, so each sample lasts for many periods of it.
+    //
+    // With no prescaler and ARR at 255, TIM3 counts the 256 steps of the duty
+    // at the full 48 MHz of the timer clock, for a carrier of 48 MHz / 256 =
+    // 187.5 kHz. That's more than 9 times the ~20 kHz limit of hearing, and 23
+    // periods of the carrier per sample at 8 kHz. A carrier that high is only
+    // possible because the samples are 8-bit: 16-bit samples would need 65_536
+    // steps, and the carrier would drop to 732 Hz, right in the middle of what
+    // can be heard.
+    //
+    // The preload enable bit buffers writes to CCR1 until the next update, as
+    // in the buzzer example, so a new sample always starts at the beginning of
+    // a period.
+    //
+    pac::TIM3::enable(&mut reset_and_clock_control.apb1);
+    pac::TIM3::reset(&mut reset_and_clock_control.apb1);
+    let tim3 = device_periphs.TIM3;
+    tim3.psc.write(|w| w.psc().bits(0));
+    tim3.arr.write(|w| w.arr().bits(u8::MAX.into()));
+    tim3.ccr1().write(|w| w.ccr().bits(SILENCE.into()));
+    tim3.ccmr1_output()
+        .modify(|_, w| w.oc1m().pwm_mode1().oc1pe().enabled());
+    tim3.cr1.modify(|_, w| w.arpe().enabled());
+    tim3.ccer.modify(|_, w| w.cc1e().set_bit());
+    tim3.egr.write(|w| w.ug().update());
+    tim3.cr1.modify(|_, w| w.cen().enabled());
+
+    critical_section::with(|cs| {
+        G_PLAYER.borrow(cs).replace(Some(Player {
+            pwm: tim3,
+            samples: SAMPLES,
+            position: 0,
+        }))
+    });
+
+    // Set up TIM2 as the sample rate timer and unmask its interrupt.
+    //
+    // The update interrupt of TIM2 fires once every sample, 1 / SAMPLE_RATE_HZ
+    // = 125 us at 8 kHz, and the handler moves the PWM on to the next sample.
+    // Playing back at a different rate than the samples were recorded at
+    // changes the pitch and the speed together, like a record played at the
+    // wrong speed.
+    //
+    // The handler only does a few register writes, so even though it runs
+    // 8_000 times a second, it takes a few percent of the CPU time at 48 MHz,
+    // at most.
+    //
+    let mut timer = Timer::new(
+        device_periphs.TIM2,
+        clocks,
+        &mut reset_and_clock_control.apb1,
+    );
+    timer.enable_interrupt(Event::Update);
+    timer.start((1_000_000 / SAMPLE_RATE_HZ).microseconds());
+    let timer_interrupt = timer.interrupt();
+    critical_section::with(|cs| G_TIMER.borrow(cs).replace(Some(timer)));
+
+    #[allow(unsafe_code)]
+    unsafe {
+        NVIC::unmask(timer_interrupt);
+    }
+
+    // Main loop.
+    //
+    // Everything happens in the TIM2 interrupt, so sleep until it fires.
+    //
+    loop {
+        asm::wfi();
+    }
+}
+
+#[interrupt]
+fn TIM2() {
+    critical_section::with(|cs| {
+        let mut timer = G_TIMER.borrow(cs).borrow_mut();
+        let Some(timer) = timer.as_mut() else {
+            return;
+        };
+
+        // Clear the update flag.
+        //
+        timer.clear_event(Event::Update);
+
+        // Play the next sample. Once the end has been reached with LOOP off,
+        // stop the timer, so the interrupt stops firing and the CPU can sleep
+        // for good.
+        //
+        let playing = G_PLAYER
+            .borrow(cs)
+            .borrow_mut()
+            .as_mut()
+            .is_some_and(Player::step);
+        if !playing {
+            timer.stop();
+        }
+    });
+}
@@ -0,0 +1,242 @@
+#![deny(unsafe_code)]
+#![no_std]
+#![no_main]
+
+use core::fmt::Write;
+
+// Define the panic handler selected by the panic-* features in Cargo.toml.
+//
+// The default is panic-halt. A breakpoint can be set on `rust_begin_unwind` to
+// catch panics.
+//
+wt_support::panic_handler!();
+
+use cortex_m::asm;
+use cortex_m_rt::entry;
+
+use stm32f3xx_hal::{
+    delay::Delay,
+    pac,
+    prelude::*,
+    rcc::{BusTimerClock, Enable, Reset},
+    serial::{config, Serial},
+};
+
+// Frequency both timers count at after their prescalers.
+//
+// At 1 MHz, a period is measured to the nearest microsecond, and the 32-bit
+// counter of TIM2 doesn't overflow for over an hour, so there's no practical
+// lower limit on the frequency it can measure because of it.
+//
+const TIMER_TICK_HZ: u32 = 1_000_000;
+
+// Period and high time of the test signal generated on PB4, in ticks: 1 kHz
+// at 25% duty.
+//
+const TEST_PERIOD_TICKS: u16 = 1_000;
+const TEST_HIGH_TICKS: u16 = 250;
+
+// Time between measurements in milliseconds.
+//
+// A signal has to have at least one rising edge in this time to be measured,
+// so anything slower than 1 / READ_INTERVAL_MS, 2 Hz here, reads as no signal.
+//
+const READ_INTERVAL_MS: u32 = 500;
+
+#[entry]
+fn main() -> ! {
+    // Get peripherals.
+    //
+    // take() returns an Option, which requires handling the possibility of the
+    // return of an Err or None instead of the desired value, which is of type
+    // pac::Peripherals in this case.
+    //
+    // Since this is an embedded application, it's not as simple as writing to,
+    // stdout. This is a minimal example, so we'll drop into an inifinite loop
+    // to allow a debugger to find where the failure.
+    //
+    let device_periphs = pac::Peripherals::take().unwrap_or_else(|| {
+        loop {
+            // Failed to take pac::Peripherals.
+            asm::nop(); // If real app, replace with actual error handling.
+        }
+    });
+    let core_periphs = cortex_m::Peripherals::take().unwrap_or_else(|| {
+        loop {
+            // Failed to take cortex_m::Peripherals.
+            asm::nop(); // If real app, replace with actual error handling.
+        }
+    });
+
+    // Get RCC peripheral and set the system clock frequency.
+    //
+    // With PCLK1 at 24 MHz, the APB1 timers, TIM2 and TIM3, are clocked at
+    // twice that, 48 MHz, as in the buzzer example.
+    //
+    let mut reset_and_clock_control = device_periphs.RCC.constrain();
+    let mut flash = device_periphs.FLASH.constrain();
+    let clocks = reset_and_clock_control
+        .cfgr
+        .sysclk(48.MHz())
+        .pclk1(24.MHz())
+        .freeze(&mut flash.acr);
+
+    let mut delay = Delay::new(core_periphs.SYST, clocks);
+
+    // Get GPIO Ports A, B, and C.
+    //
+    let mut gpioa = device_periphs.GPIOA.split(&mut reset_and_clock_control.ahb);
+    let mut gpiob = device_periphs.GPIOB.split(&mut reset_and_clock_control.ahb);
+    let mut gpioc = device_periphs.GPIOC.split(&mut reset_and_clock_control.ahb);
+
+    // Configure GPIO pins PC10 as TX and PC11 as RX for UART4, the same as in
+    // the uart example, and activate the UART.
+    //
+    let tx_pin = gpioc
+        .pc10
+        .into_af_push_pull(&mut gpioc.moder, &mut gpioc.otyper, &mut gpioc.afrh);
+    let rx_pin = gpioc
+        .pc11
+        .into_af_push_pull(&mut gpioc.moder, &mut gpioc.otyper, &mut gpioc.afrh);
+    let mut uart4 = Serial::new(
+        device_periphs.UART4,
+        (tx_pin, rx_pin),
+        config::Config::default().baudrate(115_200.Bd()),
+        clocks,
+        &mut reset_and_clock_control.apb1,
+    );
+
+    let timer_clock_hz = pac::TIM2::timer_clock(&clocks).0;
+    let prescaler = timer_clock_hz / TIMER_TICK_HZ - 1;
+
+    // Generate a test signal on PB4 with TIM3 channel 1 (alternate function
+    // 2), the same as the buzzer example, but at a fixed frequency and duty.
+    //
+    // Connect PB4 to PA15 with a jumper wire to measure it, or connect any
+    // other 3.3 V signal to PA15 instead, with its ground to GND.
+    //
+    let _test_signal_pin =
+        gpiob
+            .pb4
+            .into_af_push_pull::<2>(&mut gpiob.moder, &mut gpiob.otyper, &mut gpiob.afrl);
+    pac::TIM3::enable(&mut reset_and_clock_control.apb1);
+    pac::TIM3::reset(&mut reset_and_clock_control.apb1);
+    let tim3 = device_periphs.TIM3;
+    tim3.psc.write(|w| w.psc().bits(prescaler as u16));
+    tim3.arr.write(|w| w.arr().bits(TEST_PERIOD_TICKS - 1));
+    tim3.ccr1().write(|w| w.ccr().bits(TEST_HIGH_TICKS));
+    tim3.ccmr1_output()
+        .modify(|_, w| w.oc1m().pwm_mode1().oc1pe().enabled());
+    tim3.cr1.modify(|_, w| w.arpe().enabled());
+    tim3.ccer.modify(|_, w| w.cc1e().set_bit());
+    tim3.egr.write(|w| w.ug().update());
+    tim3.cr1.modify(|_, w| w.cen().enabled());
+
+    // Configure PA15 as TIM2 channel 1 (alternate function 1), the input.
+    //
+    // PA15 doubles as JTDI for JTAG, which isn't used with the SWD connection
+    // of the on-board ST-Link, so it's free, like PB4.
+    //
+    let _input_pin =
+        gpioa
+            .pa15
+            .into_af_push_pull::<1>(&mut gpioa.moder, &mut gpioa.otyper, &mut gpioa.afrh);
+
+    // Set up TIM2 in PWM input mode.
+    //
+    // An ordinary input capture uses one channel per input, and copies the
+    // counter into its CCR on one kind of edge. Measuring a period that way
+    // takes two captures and a subtraction, and measuring the high time as
+    // well takes an interrupt on every edge to switch which edge is captured.
+    //
+    // PWM input mode measures both with no software at all, using two
+    // channels on the same pin:
+    //
+    // - Channel 1 captures TI1, the signal on the channel 1 pin, on the rising
+    //   edge (CC1S = TI1, CC1P = 0).
+    // - Channel 2 also captures TI1, rather than its own pin, on the falling
+    //   edge (CC2S = TI1, CC2P = 1).
+    // - The slave mode controller resets the counter on the rising edge of
+    //   TI1 (the trigger TS = TI1FP1, in SMS = reset mode).
+    //
+    // So the counter starts from 0 at every rising edge, and at the next one,
+    // channel 1 captures it just before it's reset, which is the period in
+    // ticks. At the falling edge in between, channel 2 captures how far it had
+    // got, which is the high time. Both are updated on every cycle of the
+    // input, and the main loop can read them whenever it likes.
+    //
+    // Only channels 1 and 2 can do this, since only TI1 and TI2 can be the
+    // trigger, and only on the timers with a slave mode controller. TIM2 is
+    // used because its counter is 32 bits, so slow signals don't overflow it.
+    //
+    pac::TIM2::enable(&mut reset_and_clock_control.apb1);
+    pac::TIM2::reset(&mut reset_and_clock_control.apb1);
+    let tim2 = device_periphs.TIM2;
+    tim2.psc.write(|w| w.psc().bits(prescaler as u16));
+    tim2.arr.write(|w| w.arr().bits(u32::MAX));
+    tim2.ccmr1_input().write(|w| w.cc1s().ti1().cc2s().ti1());
+    tim2.ccer.write(|w| {
+        w.cc1p()
+            .clear_bit()
+            .cc1np()
+            .clear_bit()
+            .cc1e()
+            .set_bit()
+            .cc2p()
+            .set_bit()
+            .cc2np()
+            .clear_bit()
+            .cc2e()
+            .set_bit()
+    });
+    tim2.smcr.write(|w| w.ts().ti1fp1().sms().reset_mode());
+    tim2.egr.write(|w| w.ug().update());
+    tim2.cr1.modify(|_, w| w.cen().enabled());
+
+    writeln!(uart4, "Measuring the signal on PA15.\r").ok();
+
+    // Main loop.
+    //
+    // Every READ_INTERVAL_MS, read the period and the high time and write out
+    // the frequency and duty they come to.
+    //
+    // With no signal, the CCRs just keep their last values, so they can't be
+    // told apart from a signal that's still there. CC1IF tells them apart: the
+    // timer sets it on every capture by channel 1, and reading CCR1 clears it,
+    // so it's only set if there's been a rising edge since the last read. A
+    // period of 0, which would be a divide by zero, is treated as no signal
+    // too; a signal as fast as the timer tick can't be measured anyway.
+    //
+    loop {
+        delay.delay_ms(READ_INTERVAL_MS);
+
+        let captured = tim2.sr.read().cc1if().is_match();
+        let period_ticks = tim2.ccr1().read().ccr().bits();
+        let high_ticks = tim2.ccr2().read().ccr().bits();
+
+        if !captured || period_ticks == 0 {
+            writeln!(uart4, "No signal\r").ok();
+            continue;
+        }
+
+        // The frequency in tenths of a hertz and the duty in tenths of a
+        // percent, to write out with one decimal place, in u64 so the
+        // multiplications can't overflow. The high time can read as more
+        // than the period if the signal changed in between the two captures,
+        // so the duty is capped at 100%.
+        //
+        let period_ticks = u64::from(period_ticks);
+        let decihertz = u64::from(TIMER_TICK_HZ) * 10 / period_ticks;
+        let permille = (u64::from(high_ticks) * 1_000 / period_ticks).min(1_000);
+
+        writeln!(
+            uart4,
+            "Frequency: {}.{} Hz, duty: {}.{}%\r",
+            decihertz / 10,
+            decihertz % 10,
+            permille / 10,
+            permille % 10
+        )
+        .ok();
+    }
+}
@@ -0,0 +1,40 @@
+//! This build script copies the `memory.x` file from the crate root into
+//! a directory where the linker can always find it at build time, and sets
+//! the linker flags to tell it which link script to use, as in the qemu
+//! crate.
+//!
+//! Unlike there, the linker flags are only set when building for a Cortex-M
+//! target. This crate's integration test is built for the host, and
+//! `rustc-link-arg` applies to every target linked in the package, so
+//! setting them there would have the host's linker look for cortex-m-rt's
+//! link script.
+
+use std::env;
+use std::fs::File;
+use std::io::Write;
+use std::path::PathBuf;
+
+fn main() {
+    // Put `memory.x` in our output directory and ensure it's
+    // on the linker search path.
+    let out = &PathBuf::from(env::var_os("OUT_DIR").unwrap());
+    File::create(out.join("memory.x"))
+        .unwrap()
+        .write_all(include_bytes!("memory.x"))
+        .unwrap();
+    println!("cargo:rustc-link-search={}", out.display());
+
+    // Only re-run this when `memory.x` or this script change.
+    println!("cargo:rerun-if-changed=memory.x");
+    println!("cargo:rerun-if-changed=build.rs");
+
+    if env::var("TARGET").is_ok_and(|target| target.starts_with("thumb")) {
+        // `--nmagic` is required if memory section addresses are not aligned
+        // to 0x10000, for example the FLASH and RAM sections in `memory.x`.
+        // See https://github.com/rust-embedded/cortex-m-quickstart/pull/95
+        println!("cargo:rustc-link-arg=--nmagic");
+
+        // Set the linker script to the one provided by cortex-m-rt.
+        println!("cargo:rustc-link-arg=-Tlink.x");
+    }
+}
@@ -0,0 +1,56 @@
+//! Prints a greeting and a CRC-32 on the host console using semihosting, then
+//! exits QEMU.
+//!
+//! Run it on QEMU with `cargo run --example hello --release` from this
+//! directory, or build it and run it under QEMU, checking what it prints,
+//! with `cargo test-qemu` from the root of the repo. See tests/qemu.rs.
+
+#![deny(unsafe_code)]
+#![no_main]
+#![no_std]
+
+use core::panic::PanicInfo;
+
+use cortex_m_rt::entry;
+use cortex_m_semihosting::{debug, heprintln, hprintln};
+
+use example_qemu_hello::{CRC_INPUT, GREETING};
+use wt_support::crc;
+
+#[entry]
+fn main() -> ! {
+    hprintln!("{}", GREETING);
+
+    // Run some of the code shared with the boards, to show it works the same
+    // on the emulated Cortex-M3 as in the unit tests on the host, where the
+    // CRC-32 of "123456789" is 0xcbf43926.
+    //
+    hprintln!("crc32 = {:#010x}", crc::crc32(CRC_INPUT));
+
+    // Exit QEMU with exit code 0.
+    //
+    // Semihosting stops the core for each call and waits for the debugger to
+    // answer it. Under QEMU, QEMU answers it, but on hardware with no debugger
+    // attached, nothing does, and the core hard faults instead. Don't run this
+    // on hardware; it can also corrupt OpenOCD's state when there is one.
+    //
+    // debug::exit() only returns if the debugger answering it doesn't stop the
+    // program, as with some setups of OpenOCD, so halt after it.
+    //
+    debug::exit(debug::EXIT_SUCCESS);
+
+    wt_support::panic::halt()
+}
+
+// Print the panic message and exit QEMU with a failure, rather than halting
+// with one of the handlers of wt_support::panic_handler!(), so that a panic
+// fails the test straight away instead of leaving QEMU running until the test
+// times out.
+//
+#[panic_handler]
+fn panic_exit(info: &PanicInfo) -> ! {
+    heprintln!("{}", info);
+    debug::exit(debug::EXIT_FAILURE);
+
+    wt_support::panic::halt()
+}
@@ -0,0 +1,15 @@
+//! What the hello firmware in `examples/hello.rs` prints, shared with the
+//! integration test in `tests/qemu.rs` that runs it under QEMU and checks
+//! its output.
+//!
+//! The firmware is built for the LM3S6965 and the test for the host, so this
+//! is the only code the two have in common.
+
+#![no_std]
+
+/// The first line the firmware prints.
+pub const GREETING: &str = "Hello from QEMU!";
+
+/// The input the firmware computes a CRC-32 of with `wt_support::crc`, the
+/// standard check string of the CRC catalogues.
+pub const CRC_INPUT: &[u8] = b"123456789";
@@ -0,0 +1,150 @@
+//! Builds the hello firmware, runs it under QEMU, and checks what it prints
+//! and that it exits cleanly.
+//!
+//! This runs on the host, not the target, so run it with an explicit host
+//! target, from the root of the repo with
+//!
+//! ``` console
+//! $ cargo test-qemu
+//! ```
+//!
+//! or from this directory with `cargo test --target host-tuple --test qemu`.
+//! The host target has to be given since the `.cargo/config.toml` here makes
+//! thumbv7m-none-eabi the default, and the test has to be, or cargo builds
+//! the firmware for the host too, which fails.
+//!
+//! The test needs `qemu-system-arm` on the `PATH`, from e.g. the
+//! `qemu-system-arm` package on Debian and Ubuntu. Without it, the test
+//! prints that it's skipped and passes, so that `cargo test` still works
+//! everywhere, unless `WT_REQUIRE_QEMU` is set, as it should be in CI, in
+//! which case it fails.
+
+use std::env;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use example_qemu_hello::GREETING;
+
+/// The target the firmware is built for.
+const TARGET: &str = "thumbv7m-none-eabi";
+
+/// How long QEMU gets to run the firmware before the test gives up on it.
+///
+/// The firmware exits as soon as it's printed, well within a second, so this
+/// only matters if it hangs.
+const TIMEOUT: Duration = Duration::from_secs(30);
+
+#[test]
+fn hello_prints_and_exits_cleanly() {
+    if !qemu_is_installed() {
+        if env::var_os("WT_REQUIRE_QEMU").is_some() {
+            panic!("qemu-system-arm not found, and WT_REQUIRE_QEMU is set");
+        }
+        eprintln!("skipped: qemu-system-arm not found");
+        return;
+    }
+
+    let elf = build_firmware();
+
+    // The same command `cargo run` uses, from the runner in
+    // .cargo/config.toml.
+    let mut qemu = Command::new("qemu-system-arm")
+        .args(["-cpu", "cortex-m3", "-machine", "lm3s6965evb", "-nographic"])
+        .args(["-semihosting-config", "enable=on,target=native", "-kernel"])
+        .arg(&elf)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .expect("failed to run qemu-system-arm");
+
+    // Wait for QEMU to exit, killing it if the firmware doesn't get there.
+    // The firmware prints far less than a pipe holds, so QEMU can't block on
+    // writing to it before it's read.
+    let started = Instant::now();
+    let status = loop {
+        if let Some(status) = qemu.try_wait().expect("failed to wait for QEMU") {
+            break status;
+        }
+        if started.elapsed() > TIMEOUT {
+            qemu.kill().ok();
+            qemu.wait().ok();
+            panic!("QEMU still running after {:?}", TIMEOUT);
+        }
+        thread::sleep(Duration::from_millis(50));
+    };
+
+    let mut stdout = String::new();
+    let mut stderr = String::new();
+    qemu.stdout
+        .take()
+        .unwrap()
+        .read_to_string(&mut stdout)
+        .unwrap();
+    qemu.stderr
+        .take()
+        .unwrap()
+        .read_to_string(&mut stderr)
+        .unwrap();
+
+    // QEMU passes on the code given to debug::exit() as its own exit code.
+    assert!(
+        status.success(),
+        "QEMU exited with {}\nstdout:\n{}\nstderr:\n{}",
+        status,
+        stdout,
+        stderr
+    );
+
+    // 0xcbf43926 is the CRC-32 of CRC_INPUT, "123456789", as checked by the
+    // unit tests of wt_support::crc on the host.
+    assert_eq!(
+        stdout.lines().collect::<Vec<_>>(),
+        [GREETING, "crc32 = 0xcbf43926"],
+        "stderr:\n{}",
+        stderr
+    );
+}
+
+fn qemu_is_installed() -> bool {
+    Command::new("qemu-system-arm")
+        .arg("--version")
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .is_ok()
+}
+
+/// Builds the firmware in release mode and returns the path to its ELF.
+fn build_firmware() -> PathBuf {
+    let manifest_dir = Path::new(env!("CARGO_MANIFEST_DIR"));
+
+    // Build into a target directory of its own, since the cargo running this
+    // test holds a lock on the usual one until it's finished.
+    let target_dir = manifest_dir.join("target").join("qemu");
+
+    let status = Command::new(env!("CARGO"))
+        .args([
+            "build",
+            "--release",
+            "--example",
+            "hello",
+            "--target",
+            TARGET,
+        ])
+        .arg("--target-dir")
+        .arg(&target_dir)
+        .current_dir(manifest_dir)
+        .status()
+        .expect("failed to run cargo");
+    assert!(status.success(), "building the firmware failed");
+
+    target_dir
+        .join(TARGET)
+        .join("release")
+        .join("examples")
+        .join("hello")
+}
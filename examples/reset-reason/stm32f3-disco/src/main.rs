@@ -0,0 +1,136 @@
+#![deny(unsafe_code)]
+#![no_std]
+#![no_main]
+
+use core::fmt::Write;
+
+// Define the panic handler selected by the panic-* features in Cargo.toml.
+//
+// The default is panic-halt. A breakpoint can be set on `rust_begin_unwind` to
+// catch panics.
+//
+wt_support::panic_handler!();
+
+use cortex_m::asm;
+use cortex_m::peripheral::SCB;
+use cortex_m_rt::entry;
+
+use stm32f3xx_hal::{
+    block, pac, prelude::*, serial::config, serial::Serial, watchdog::IndependentWatchDog,
+};
+
+use wt_support::reset_reason::ResetReason;
+
+// Timeout of the IWDG started by the w command.
+//
+const WATCHDOG_TIMEOUT_MS: u32 = 1_000;
+
+#[entry]
+fn main() -> ! {
+    // Get peripherals.
+    //
+    // take() returns an Option, which requires handling the possibility of the
+    // return of an Err or None instead of the desired value, which is of type
+    // pac::Peripherals in this case.
+    //
+    // Since this is an embedded application, it's not as simple as writing to,
+    // stdout. This is a minimal example, so we'll drop into an inifinite loop
+    // to allow a debugger to find where the failure.
+    //
+    let device_periphs = pac::Peripherals::take().unwrap_or_else(|| {
+        loop {
+            // Failed to take pac::Peripherals.
+            asm::nop(); // If real app, replace with actual error handling.
+        }
+    });
+
+    // Read why the chip was reset, before RCC is handed to the HAL.
+    //
+    // Setting RMVF afterwards clears the reset flags, which otherwise stay set
+    // across every reset other than a power-on, so the next boot only sees the
+    // flag for its own reset.
+    //
+    let reason = ResetReason::from_csr(device_periphs.RCC.csr.read().bits());
+    device_periphs.RCC.csr.modify(|_, w| w.rmvf().set_bit());
+
+    // Get RCC peripheral and set the system clock frequency.
+    //
+    let mut reset_and_clock_control = device_periphs.RCC.constrain();
+    let mut flash = device_periphs.FLASH.constrain();
+    let clocks = reset_and_clock_control
+        .cfgr
+        .sysclk(48.MHz())
+        .freeze(&mut flash.acr);
+
+    // Get GPIO Port C.
+    //
+    let mut gpioc = device_periphs.GPIOC.split(&mut reset_and_clock_control.ahb);
+
+    // Configure GPIO pins PC10 as TX and PC11 as RX for UART4, the same as in
+    // the uart example, and activate the UART.
+    //
+    let tx_pin = gpioc
+        .pc10
+        .into_af_push_pull(&mut gpioc.moder, &mut gpioc.otyper, &mut gpioc.afrh);
+    let rx_pin = gpioc
+        .pc11
+        .into_af_push_pull(&mut gpioc.moder, &mut gpioc.otyper, &mut gpioc.afrh);
+    let mut uart4 = Serial::new(
+        device_periphs.UART4,
+        (tx_pin, rx_pin),
+        config::Config::default().baudrate(115_200.Bd()),
+        clocks,
+        &mut reset_and_clock_control.apb1,
+    );
+
+    writeln!(uart4, "Reset reason: {:?}\r", reason).ok();
+    writeln!(
+        uart4,
+        "Send s for a software reset or w for a watchdog reset, or press the reset button.\r"
+    )
+    .ok();
+
+    // The IWDG, which is only started by the w command.
+    //
+    let mut iwdg = Some(IndependentWatchDog::new(device_periphs.IWDG));
+
+    // Main loop.
+    //
+    // Wait for a command and reset the chip the way it asks for. Each reset
+    // comes back through main(), which writes out the reason, so every kind of
+    // reset can be tried in turn. Unplugging the board and plugging it back in
+    // gives a power-on reset.
+    //
+    loop {
+        let byte = match block!(uart4.read()) {
+            Ok(byte) => byte,
+            Err(_) => continue,
+        };
+
+        match byte {
+            b's' => {
+                writeln!(uart4, "Resetting.\r").ok();
+                block!(uart4.flush()).ok();
+                SCB::sys_reset();
+            }
+            b'w' => {
+                // Start the IWDG and never feed it, so it times out and resets
+                // the chip. Once started, the IWDG can't be stopped by
+                // anything short of a reset.
+                if let Some(mut iwdg) = iwdg.take() {
+                    writeln!(
+                        uart4,
+                        "Starting the watchdog without feeding it. Reset in {} ms.\r",
+                        WATCHDOG_TIMEOUT_MS
+                    )
+                    .ok();
+                    iwdg.start(WATCHDOG_TIMEOUT_MS.milliseconds());
+                }
+            }
+            b'\r' | b'\n' => {}
+            _ => {
+                writeln!(uart4, "Unknown command; send s or w.\r").ok();
+            }
+        }
+    }
+}
@@ -0,0 +1,197 @@
+#![deny(unsafe_code)]
+#![no_std]
+#![no_main]
+
+use core::fmt::Write;
+
+// Define the panic handler selected by the panic-* features in Cargo.toml.
+//
+// The default is panic-halt. A breakpoint can be set on `rust_begin_unwind` to
+// catch panics.
+//
+wt_support::panic_handler!();
+
+use cortex_m::asm;
+use cortex_m_rt::entry;
+
+use stm32f3xx_hal::{
+    delay::Delay,
+    gpio::{gpioc, gpioe, Output, PushPull, AF5},
+    pac,
+    prelude::*,
+    serial::{config, Serial},
+};
+
+use wt_support::led::{PinLed, UserLed};
+
+// Time between beats of the main loop in milliseconds.
+//
+const BEAT_MS: u16 = 250;
+
+// UART4 on PC10 (TX) and PC11 (RX), as set up in the uart example.
+//
+// Spelling out the full type of a HAL peripheral is the price of keeping it in
+// a struct, where it can't be inferred. A type alias keeps that in one place.
+//
+type Uart4 = Serial<pac::UART4, (gpioc::PC10<AF5<PushPull>>, gpioc::PC11<AF5<PushPull>>)>;
+
+// Everything the application uses, set up and ready to go.
+//
+// The flat examples take the peripherals, set up the clocks, and configure
+// the pins in main(), then run the loop at the bottom of it, with every local
+// in scope of the loop whether it uses it or not. Here the two halves are
+// separated: App::new() does all the take(), constrain(), and split() calls,
+// and hands back an App that owns exactly the resources the loop needs, and
+// App::run() is the loop, which only sees those, through self.
+//
+// This scales better as firmware grows:
+//
+// - main() stays one line, however much there is to set up.
+// - The fields are the list of what the application owns. The pins and
+//   peripherals that aren't in it were dropped at the end of new(), so the
+//   loop can't touch them by accident, and they show up as unused if they're
+//   configured and never stored.
+// - The loop can be split into methods, e.g. one per task, that all share the
+//   resources through &mut self instead of taking a growing list of
+//   arguments.
+// - Setup that can fail does so in one place, before anything runs.
+//
+// take() can only succeed once, so App::new() can only succeed once too,
+// which makes the App the single owner of the board, the same guarantee the
+// PAC gives for the peripherals themselves.
+//
+struct App {
+    ld3: PinLed<gpioe::PE9<Output<PushPull>>>,
+    ld4: PinLed<gpioe::PE8<Output<PushPull>>>,
+    delay: Delay,
+    uart: Uart4,
+    beats: u32,
+}
+
+impl App {
+    // Takes the peripherals and sets up everything the application uses.
+    //
+    fn new() -> Self {
+        // Get peripherals.
+        //
+        // take() returns an Option, which requires handling the possibility of
+        // the return of an Err or None instead of the desired value, which is
+        // of type pac::Peripherals in this case.
+        //
+        // Since this is an embedded application, it's not as simple as
+        // writing to, stdout. This is a minimal example, so we'll drop into an
+        // inifinite loop to allow a debugger to find where the failure.
+        //
+        let device_periphs = pac::Peripherals::take().unwrap_or_else(|| {
+            loop {
+                // Failed to take pac::Peripherals.
+                asm::nop(); // If real app, replace with actual error handling.
+            }
+        });
+        let core_periphs = cortex_m::Peripherals::take().unwrap_or_else(|| {
+            loop {
+                // Failed to take cortex_m::Peripherals.
+                asm::nop(); // If real app, replace with actual error handling.
+            }
+        });
+
+        // Get RCC peripheral and set the system clock frequency.
+        //
+        let mut reset_and_clock_control = device_periphs.RCC.constrain();
+        let mut flash = device_periphs.FLASH.constrain();
+        let clocks = reset_and_clock_control
+            .cfgr
+            .sysclk(48.MHz())
+            .freeze(&mut flash.acr);
+
+        // Create a SysTick delay.
+        //
+        let delay = Delay::new(core_periphs.SYST, clocks);
+
+        // Get GPIO Ports C and E.
+        //
+        let mut gpioc = device_periphs.GPIOC.split(&mut reset_and_clock_control.ahb);
+        let mut gpioe = device_periphs.GPIOE.split(&mut reset_and_clock_control.ahb);
+
+        // Create LD3 and LD4 as wt_support::led::PinLeds.
+        //
+        let ld3 = PinLed::new(
+            gpioe
+                .pe9
+                .into_push_pull_output(&mut gpioe.moder, &mut gpioe.otyper),
+        );
+        let ld4 = PinLed::new(
+            gpioe
+                .pe8
+                .into_push_pull_output(&mut gpioe.moder, &mut gpioe.otyper),
+        );
+
+        // Configure GPIO pins PC10 as TX and PC11 as RX for UART4, and
+        // activate the UART.
+        //
+        let tx_pin =
+            gpioc
+                .pc10
+                .into_af_push_pull(&mut gpioc.moder, &mut gpioc.otyper, &mut gpioc.afrh);
+        let rx_pin =
+            gpioc
+                .pc11
+                .into_af_push_pull(&mut gpioc.moder, &mut gpioc.otyper, &mut gpioc.afrh);
+        let uart = Serial::new(
+            device_periphs.UART4,
+            (tx_pin, rx_pin),
+            config::Config::default().baudrate(115_200.Bd()),
+            clocks,
+            &mut reset_and_clock_control.apb1,
+        );
+
+        // Everything else, e.g. the rest of the GPIO ports and the other
+        // LEDs, is dropped here, unconfigured.
+        //
+        App {
+            ld3,
+            ld4,
+            delay,
+            uart,
+            beats: 0,
+        }
+    }
+
+    // Runs the application.
+    //
+    // Alternately lights LD3 and LD4 every BEAT_MS, and writes a count of
+    // the beats out via UART4.
+    //
+    fn run(&mut self) -> ! {
+        writeln!(self.uart, "App ready.\r").ok();
+
+        loop {
+            self.beat();
+            self.delay.delay_ms(BEAT_MS);
+        }
+    }
+
+    // Does one beat of the main loop.
+    //
+    // The loop body as a method of its own has the same access to the
+    // resources as run(), with nothing to pass in.
+    //
+    fn beat(&mut self) {
+        self.beats = self.beats.wrapping_add(1);
+
+        if self.beats.is_multiple_of(2) {
+            self.ld3.on();
+            self.ld4.off();
+        } else {
+            self.ld3.off();
+            self.ld4.on();
+        }
+
+        writeln!(self.uart, "Beat {}\r", self.beats).ok();
+    }
+}
+
+#[entry]
+fn main() -> ! {
+    App::new().run()
+}
@@ -0,0 +1,193 @@
+#![deny(unsafe_code)]
+#![no_std]
+#![no_main]
+
+use core::fmt::Write;
+
+// Define the panic handler selected by the panic-* features in Cargo.toml.
+//
+// The default is panic-halt. A breakpoint can be set on `rust_begin_unwind` to
+// catch panics.
+//
+wt_support::panic_handler!();
+
+use cortex_m::asm;
+use cortex_m_rt::entry;
+
+use stm32f7xx_hal::{
+    pac,
+    prelude::*,
+    rcc::PLL48CLK,
+    rng::{ErrorKind, RngExt},
+    serial::{self, Serial},
+};
+
+// Number of random values written out per batch.
+//
+const VALUES_PER_BATCH: usize = 8;
+
+// Number of 32-bit samples taken for the bias check.
+//
+const BIAS_SAMPLES: u32 = 10_000;
+
+// Delay in milliseconds between batches.
+//
+const BATCH_DELAY_MS: u32 = 2_000;
+
+#[entry]
+fn main() -> ! {
+    // Get peripherals.
+    //
+    // take() returns an Option, which requires handling the possibility of the
+    // return of an Err or None instead of the desired value, which is of type
+    // pac::Peripherals in this case.
+    //
+    // Since this is an embedded application, it's not as simple as writing to,
+    // stdout. This is a minimal example, so we'll drop into an inifinite loop
+    // to allow a debugger to find where the failure.
+    //
+    let device_periphs = pac::Peripherals::take().unwrap_or_else(|| {
+        loop {
+            // Failed to take pac::Peripherals.
+            asm::nop(); // If real app, replace with actual error handling.
+        }
+    });
+
+    // Get RCC peripheral and configure the clocks, including the RNG clock.
+    //
+    // The RNG isn't clocked from the bus it's on, but from the 48 MHz clock
+    // shared with USB and the SDMMC, which comes from the Q output of the main
+    // PLL (or from the SAI PLL). use_pll48clk() has the HAL pick a PLLQ divider
+    // that gives 48 MHz alongside the requested system clock.
+    //
+    // The RNG needs its clock to be running at no less than 1/16 of HCLK, or it
+    // flags a clock error and stops producing data. With HCLK at 96 MHz, that's
+    // a minimum of 6 MHz, which 48 MHz is well above. The 48 MHz is nominal
+    // rather than required for the RNG itself, but it has to be accurate for
+    // USB, so it's worth getting right anyway.
+    //
+    let reset_and_clock_control = device_periphs.RCC.constrain();
+    let clocks = reset_and_clock_control
+        .cfgr
+        .sysclk(96.MHz())
+        .use_pll48clk(PLL48CLK::Pllq)
+        .freeze();
+
+    // Set up delay capability.
+    //
+    let core_periphs = cortex_m::Peripherals::take().unwrap_or_else(|| {
+        loop {
+            // Failed to take cortex_m::Peripherals.
+            asm::nop(); // If real app, replace with actual error handling.
+        }
+    });
+    let mut delay = core_periphs.SYST.delay(&clocks);
+
+    // Get GPIO Port D.
+    //
+    let gpiod = device_periphs.GPIOD.split();
+
+    // Configure GPIO pins PD8 as TX and PD9 as RX for USART3 and activate it,
+    // the same as in the uart example. USART3 is bridged to a virtual COM port
+    // by the on-board ST-Link.
+    //
+    let tx_pin = gpiod.pd8.into_alternate();
+    let rx_pin = gpiod.pd9.into_alternate();
+    let usart3 = Serial::new(
+        device_periphs.USART3,
+        (tx_pin, rx_pin),
+        &clocks,
+        serial::Config {
+            baud_rate: 115_200.bps(),
+            ..Default::default()
+        },
+    );
+    let (mut tx, _rx) = usart3.split();
+
+    if !clocks.is_pll48clk_valid() {
+        writeln!(tx, "The 48 MHz clock isn't within tolerance.\r").ok();
+    }
+
+    // Enable the RNG.
+    //
+    // init() enables the RNG clock and the RNG, then waits for the first value
+    // to be ready. It panics if the RNG flags a clock error while it waits, so
+    // a misconfigured RNG clock shows up here rather than the first time a
+    // value is read.
+    //
+    let mut rng = device_periphs.RNG.init();
+
+    loop {
+        // Write out a batch of random values.
+        //
+        // get_rand() checks the error flags before returning each value:
+        //
+        // - A clock error means the RNG clock is too slow compared to HCLK, as
+        //   described above. It's a configuration problem, so there's nothing
+        //   to do about it at runtime but report it.
+        //
+        // - A seed error means the health check built into the RNG found the
+        //   analog noise source stuck, producing a run of more than 64 equal
+        //   bits or 32 alternating pairs. The reference manual's recovery is to
+        //   disable and re-enable the RNG, which release() and init() do, and
+        //   to throw away any value read around the error.
+        //
+        for _ in 0..VALUES_PER_BATCH {
+            match rng.get_rand() {
+                Ok(value) => {
+                    writeln!(tx, "0x{:08X}\r", value).ok();
+                }
+                Err(ErrorKind::ClockError) => {
+                    writeln!(tx, "RNG clock error. Check the RNG clock configuration.\r").ok();
+                }
+                Err(ErrorKind::SeedError) => {
+                    writeln!(tx, "RNG seed error. Restarting the RNG.\r").ok();
+                    rng = rng.release().init();
+                }
+            }
+        }
+
+        // Check the values for bias.
+        //
+        // Each bit of a random value should be set half of the time, so the
+        // proportion of set bits over many samples should come out very close
+        // to 50%. Over 10_000 samples, that's 320_000 bits, and the count of
+        // set bits should land within about 0.3% of half of them. A result
+        // well outside 49-51% means something is badly wrong. This is only a
+        // sanity check, though: a generator can pass it and still be
+        // predictable. For anything security-relevant, rely on the statistical
+        // test suites like NIST SP 800-22 that ST evaluated the RNG against.
+        //
+        let mut set_bits: u32 = 0;
+        let mut samples: u32 = 0;
+        while samples < BIAS_SAMPLES {
+            match rng.get_rand() {
+                Ok(value) => {
+                    set_bits += value.count_ones();
+                    samples += 1;
+                }
+                Err(ErrorKind::ClockError) => break,
+                Err(ErrorKind::SeedError) => rng = rng.release().init(),
+            }
+        }
+
+        // The percentage, in hundredths of a percent, to print it with two
+        // decimal places without floating point formatting.
+        //
+        let total_bits = samples * 32;
+        if total_bits > 0 {
+            let hundredths_percent = (set_bits as u64 * 10_000 / total_bits as u64) as u32;
+            writeln!(
+                tx,
+                "Bias check: {} of {} bits set ({}.{:02}%)\r\n",
+                set_bits,
+                total_bits,
+                hundredths_percent / 100,
+                hundredths_percent % 100
+            )
+            .ok();
+        }
+
+        delay.delay_ms(BATCH_DELAY_MS);
+    }
+}
@@ -0,0 +1,352 @@
+#![deny(unsafe_code)]
+#![no_std]
+#![no_main]
+
+use core::cell::RefCell;
+use core::fmt::Write;
+use core::sync::atomic::{AtomicU32, Ordering};
+
+// Define the panic handler selected by the panic-* features in Cargo.toml.
+//
+// The default is panic-halt. A breakpoint can be set on `rust_begin_unwind` to
+// catch panics.
+//
+wt_support::panic_handler!();
+
+use cortex_m::asm;
+use cortex_m::peripheral::NVIC;
+use cortex_m_rt::entry;
+use critical_section::Mutex;
+
+use stm32f3xx_hal::{
+    gpio::{gpioe, Output, PushPull},
+    pac::{self, interrupt},
+    prelude::*,
+    serial::config,
+    serial::Serial,
+};
+
+// Interval between alarms, in seconds.
+//
+// Alarm A is set to match on the seconds field alone, so it fires once a
+// minute at most unless it's moved on after each match, which the handler
+// does. That only works for intervals that fit in a minute, so this has to be
+// between 1 and 59.
+//
+const ALARM_INTERVAL_S: u8 = 5;
+
+// Prescalers that divide the LSI down to the 1 Hz that the calendar counts.
+//
+// RTCCLK is divided by PREDIV_A + 1 and then by PREDIV_S + 1. With the LSI at
+// its nominal 40 kHz, 128 * 312 = 39_936, close enough to 1 Hz. The
+// asynchronous prescaler is set as high as it goes, since the power used by
+// the RTC goes down with the frequency after it.
+//
+const RTC_PREDIV_A: u8 = 127;
+const RTC_PREDIV_S: u16 = 311;
+
+// EXTI line that the RTC alarm is wired to.
+//
+// Only the GPIO inputs get EXTI lines 0-15. The lines after that are wired
+// to internal events, and line 17 is the RTC alarms, A and B together, on the
+// STM32F303 (RM0316, table 82 in the EXTI section).
+//
+const RTC_ALARM_EXTI_LINE: u8 = 17;
+
+// LD3 (PE9), the red LED at the top of the compass rose, as an output.
+//
+type LedPin = gpioe::PE9<Output<PushPull>>;
+
+// The RTC, the EXTI controller, and the LED, shared between main(), which sets
+// them up, and the RTC_Alarm interrupt handler, as in the shared-state
+// example.
+//
+static RTC: Mutex<RefCell<Option<pac::RTC>>> = Mutex::new(RefCell::new(None));
+static EXTI: Mutex<RefCell<Option<pac::EXTI>>> = Mutex::new(RefCell::new(None));
+static LED: Mutex<RefCell<Option<LedPin>>> = Mutex::new(RefCell::new(None));
+
+// Number of alarms handled.
+//
+static ALARM_COUNT: AtomicU32 = AtomicU32::new(0);
+
+// Converts a value from 0 to 99 to binary-coded decimal (BCD), as the RTC
+// calendar and alarm registers hold it, returning the tens and the units.
+//
+fn to_bcd(value: u8) -> (u8, u8) {
+    (value / 10, value % 10)
+}
+
+// Converts the tens and units of a BCD value back to binary.
+//
+fn from_bcd(tens: u8, units: u8) -> u8 {
+    tens * 10 + units
+}
+
+// Lifts the write protection on the RTC registers.
+//
+// Out of reset, every RTC register except ISR's flags and WPR itself ignores
+// writes. Writing the two keys, 0xCA and then 0x53, to WPR unlocks them until
+// anything else is written to WPR.
+//
+fn rtc_unlock(rtc: &pac::RTC) {
+    rtc.wpr.write(|w| w.key().bits(0xCA));
+    rtc.wpr.write(|w| w.key().bits(0x53));
+}
+
+// Puts the write protection on the RTC registers back, with a wrong key.
+//
+fn rtc_lock(rtc: &pac::RTC) {
+    rtc.wpr.write(|w| w.key().bits(0xFF));
+}
+
+// Sets Alarm A to fire when the seconds of the calendar next reach `seconds`,
+// whatever the minutes, hours, and date are, and enables its interrupt.
+//
+fn set_alarm_seconds(rtc: &pac::RTC, seconds: u8) {
+    rtc_unlock(rtc);
+
+    // The alarm register can only be written with the alarm disabled, and
+    // once the RTC has said so with ALRAWF, which takes up to 2 RTCCLK
+    // cycles, since the RTC runs on its own clock.
+    //
+    rtc.cr.modify(|_, w| w.alrae().disabled());
+    while rtc.isr.read().alrawf().bit_is_clear() {}
+
+    // Each MSKx bit set leaves one field out of the comparison, and MSK1 is
+    // the seconds, the only one that's compared here.
+    //
+    let (tens, units) = to_bcd(seconds);
+    rtc.alrmar().write(|w| {
+        w.msk4()
+            .mask()
+            .msk3()
+            .mask()
+            .msk2()
+            .mask()
+            .msk1()
+            .not_mask()
+            .st()
+            .bits(tens)
+            .su()
+            .bits(units)
+    });
+
+    rtc.cr.modify(|_, w| w.alrae().enabled().alraie().enabled());
+
+    rtc_lock(rtc);
+}
+
+#[entry]
+fn main() -> ! {
+    // Get peripherals.
+    //
+    // take() returns an Option, which requires handling the possibility of the
+    // return of an Err or None instead of the desired value, which is of type
+    // pac::Peripherals in this case.
+    //
+    // Since this is an embedded application, it's not as simple as writing to,
+    // stdout. This is a minimal example, so we'll drop into an inifinite loop
+    // to allow a debugger to find where the failure.
+    //
+    let device_periphs = pac::Peripherals::take().unwrap_or_else(|| {
+        loop {
+            // Failed to take pac::Peripherals.
+            asm::nop(); // If real app, replace with actual error handling.
+        }
+    });
+
+    // Unlock the backup domain and clock the RTC from the LSI.
+    //
+    // The RTC is in the backup domain, along with its clock selection in
+    // RCC_BDCR and the backup registers, which keeps running from VBAT while
+    // the rest of the chip is powered down or reset. To keep a crash or a
+    // reset from corrupting it, everything in the backup domain is write
+    // protected out of reset, until the DBP bit in PWR_CR is set, which needs
+    // the PWR peripheral's clock first.
+    //
+    // The HAL only supports clocking the RTC from the LSE, a 32.768 kHz
+    // crystal, which is X2 on the Discovery board and isn't always fitted, so
+    // this uses the LSI, the internal low-speed RC oscillator, instead. It
+    // needs nothing on the board, but is only specified to be somewhere
+    // between 30 and 50 kHz, so the seconds here can be off by as much as a
+    // quarter. Use the LSE for anything that needs to keep time.
+    //
+    // The RTC clock source can only be changed after a reset of the backup
+    // domain, which also clears the backup registers and stops the RTC, so
+    // that's only done if the RTC isn't already on the LSI, e.g. after power
+    // up. Done before constrain(), since that takes RCC.
+    //
+    let rcc = &device_periphs.RCC;
+    rcc.apb1enr.modify(|_, w| w.pwren().enabled());
+    device_periphs.PWR.cr.modify(|_, w| w.dbp().set_bit());
+
+    rcc.csr.modify(|_, w| w.lsion().on());
+    while rcc.csr.read().lsirdy().bit_is_clear() {}
+
+    if !rcc.bdcr.read().rtcsel().is_lsi() {
+        rcc.bdcr.modify(|_, w| w.bdrst().enabled());
+        rcc.bdcr.modify(|_, w| w.bdrst().disabled());
+        rcc.bdcr.modify(|_, w| w.rtcsel().lsi());
+    }
+    rcc.bdcr.modify(|_, w| w.rtcen().enabled());
+
+    // Get RCC peripheral and set the system clock frequency.
+    //
+    let mut reset_and_clock_control = device_periphs.RCC.constrain();
+    let mut flash = device_periphs.FLASH.constrain();
+    let clocks = reset_and_clock_control
+        .cfgr
+        .sysclk(48.MHz())
+        .freeze(&mut flash.acr);
+
+    // Get GPIO Ports C and E.
+    //
+    let mut gpioc = device_periphs.GPIOC.split(&mut reset_and_clock_control.ahb);
+    let mut gpioe = device_periphs.GPIOE.split(&mut reset_and_clock_control.ahb);
+
+    // Configure GPIO pins PC10 as TX and PC11 as RX for UART4, the same as in
+    // the uart example, and activate the UART.
+    //
+    let tx_pin = gpioc
+        .pc10
+        .into_af_push_pull(&mut gpioc.moder, &mut gpioc.otyper, &mut gpioc.afrh);
+    let rx_pin = gpioc
+        .pc11
+        .into_af_push_pull(&mut gpioc.moder, &mut gpioc.otyper, &mut gpioc.afrh);
+    let mut uart4 = Serial::new(
+        device_periphs.UART4,
+        (tx_pin, rx_pin),
+        config::Config::default().baudrate(115_200.Bd()),
+        clocks,
+        &mut reset_and_clock_control.apb1,
+    );
+
+    // Create an instance of LD3 (PE9) in push-pull output mode.
+    //
+    let led = gpioe
+        .pe9
+        .into_push_pull_output(&mut gpioe.moder, &mut gpioe.otyper);
+
+    // Set up the RTC's prescalers and start the calendar at midnight.
+    //
+    // The prescalers and the calendar can only be written in initialization
+    // mode, which stops the calendar, and which the RTC says it has entered
+    // with INITF, after up to 2 RTCCLK cycles. Leaving it starts the calendar
+    // counting from the time written.
+    //
+    let rtc = device_periphs.RTC;
+    rtc_unlock(&rtc);
+    rtc.isr.modify(|_, w| w.init().init_mode());
+    while rtc.isr.read().initf().bit_is_clear() {}
+    rtc.prer.write(|w| {
+        w.prediv_a()
+            .bits(RTC_PREDIV_A)
+            .prediv_s()
+            .bits(RTC_PREDIV_S)
+    });
+    rtc.tr.reset();
+    rtc.isr.modify(|_, w| w.init().free_running_mode());
+    rtc_lock(&rtc);
+
+    // Set the first alarm, ALARM_INTERVAL_S from midnight.
+    //
+    set_alarm_seconds(&rtc, ALARM_INTERVAL_S);
+
+    // Route the alarm through EXTI to the NVIC.
+    //
+    // The RTC's alarm interrupt doesn't go to the NVIC directly, but through
+    // EXTI line 17, which has to be unmasked and set to trigger on the rising
+    // edge of the alarm flag, like a button's line would be. Going through
+    // EXTI is what lets the alarm wake the chip from Stop mode, where the
+    // NVIC's clock is off but EXTI still watches its lines.
+    //
+    let exti = device_periphs.EXTI;
+    exti.imr1.modify(|_, w| w.mr17().unmasked());
+    exti.rtsr1.modify(|_, w| w.tr17().enabled());
+
+    // Move the RTC, EXTI, and LED into the globals.
+    //
+    critical_section::with(|cs| {
+        RTC.borrow(cs).replace(Some(rtc));
+        EXTI.borrow(cs).replace(Some(exti));
+        LED.borrow(cs).replace(Some(led));
+    });
+
+    // Unmask the RTC_Alarm interrupt in the NVIC.
+    //
+    // The RTC has been counting since the alarm was set, so the first alarm
+    // can already be pending. The handler has to find the RTC in its global to
+    // clear ALRAF and set the next alarm, since if it missed that once, the
+    // flag would stay high, EXTI would never see another rising edge, and the
+    // LED would stop toggling for good.
+    //
+    #[allow(unsafe_code)]
+    unsafe {
+        NVIC::unmask(interrupt::RTCALARM);
+    }
+
+    writeln!(
+        uart4,
+        "Toggling LD3 every {} s on the RTC alarm, on EXTI line {}.\r",
+        ALARM_INTERVAL_S, RTC_ALARM_EXTI_LINE
+    )
+    .ok();
+
+    // Alarm count last written out.
+    //
+    let mut last_count = 0;
+
+    // Main loop.
+    //
+    // Sleep until an interrupt, then write the count out if it has changed.
+    // The LED is toggled by the handler, so the core spends nearly all of its
+    // time asleep here.
+    //
+    loop {
+        asm::wfi();
+
+        let count = ALARM_COUNT.load(Ordering::Relaxed);
+        if count != last_count {
+            last_count = count;
+            writeln!(uart4, "Alarms: {}\r", count).ok();
+        }
+    }
+}
+
+// Toggles the LED and sets the next alarm.
+//
+#[interrupt]
+fn RTCALARM() {
+    ALARM_COUNT.fetch_add(1, Ordering::Relaxed);
+
+    critical_section::with(|cs| {
+        // Clear the alarm flag in the RTC and then the pending bit in EXTI.
+        //
+        // Both have to be cleared. The EXTI pending bit is what the NVIC sees,
+        // so if it's left set, the handler runs again as soon as it returns.
+        // The alarm flag is what EXTI sees, and EXTI only triggers on its
+        // rising edge, so if it's left set, it never rises again and there
+        // are no more alarms. The flag is cleared first so it's low by the
+        // time the pending bit is cleared. Writing 0 to the other bits of
+        // either register doesn't change them.
+        //
+        if let Some(rtc) = RTC.borrow(cs).borrow().as_ref() {
+            rtc.isr.modify(|_, w| w.alraf().clear());
+
+            // Move the alarm on from the seconds it just matched, rather
+            // than from the time now, so the interval doesn't drift by the
+            // time the handler takes to run.
+            //
+            let alarm = rtc.alrmar().read();
+            let seconds = from_bcd(alarm.st().bits(), alarm.su().bits());
+            set_alarm_seconds(rtc, (seconds + ALARM_INTERVAL_S) % 60);
+        }
+        if let Some(exti) = EXTI.borrow(cs).borrow().as_ref() {
+            exti.pr1.write(|w| w.pr17().clear());
+        }
+
+        if let Some(led) = LED.borrow(cs).borrow_mut().as_mut() {
+            led.toggle().ok();
+        }
+    });
+}
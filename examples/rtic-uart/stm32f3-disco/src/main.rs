@@ -0,0 +1,322 @@
+#![deny(unsafe_code)]
+#![no_std]
+#![no_main]
+
+// Define the panic handler selected by the panic-* features in Cargo.toml.
+//
+// The default is panic-halt. A breakpoint can be set on `rust_begin_unwind` to
+// catch panics.
+//
+wt_support::panic_handler!();
+
+// A line-based UART echo on UART4, split into three RTIC tasks.
+//
+// - rx (priority 2, bound to the UART4 interrupt): Takes each byte out of the
+//   UART as soon as it arrives and pushes it onto a byte queue, then spawns
+//   process at the end of a line.
+// - process (priority 1, software task): Pops the bytes off the queue,
+//   assembles them into lines, and spawns transmit with a response for each
+//   complete line.
+// - transmit (priority 1, software task): Writes a response out.
+//
+// The rx task runs at the highest priority and does as little as possible, so
+// it always gets to the UART before the next byte arrives and overruns it. The
+// slow work of handling a line and writing out the response happens at a lower
+// priority, where it's preempted by rx whenever another byte comes in.
+//
+// Compared to the same application written bare-metal, RTIC takes care of the
+// parts that would otherwise be done by hand:
+//
+// - The byte queue would be a heapless::spsc::Queue in a static, split into a
+//   producer for the UART interrupt handler and a consumer for main(). Getting
+//   the 'static halves out of it takes either unsafe code or a
+//   Mutex<RefCell<Option<_>>> global per half for the handler to take its half
+//   from. Here, the queue is a local resource of init(), which RTIC gives a
+//   'static lifetime, and each half is moved into the local resources of the
+//   one task that uses it, so no global, Mutex, or unsafe code is needed.
+// - The UART is used from both rx and transmit, which run at different
+//   priorities. Bare-metal, it would go in a Mutex<RefCell<Option<_>>> global
+//   and every access would be wrapped in a critical section that masks all
+//   interrupts. As a shared resource, RTIC works out from the priorities of
+//   the tasks that use it that rx never needs a lock, since nothing that uses
+//   the UART can preempt it, and that transmit does. Its lock only raises the
+//   priority to that of rx for the duration (the Stack Resource Policy), so
+//   unrelated higher-priority interrupts still run.
+// - main() polling the queue in a loop is replaced by spawning process when
+//   there's a line to handle. The CPU sleeps in between.
+//
+#[rtic::app(device = stm32f3xx_hal::pac, dispatchers = [SPI2])]
+mod app {
+    use core::fmt::Write;
+
+    use heapless::{
+        spsc::{Consumer, Producer, Queue},
+        String, Vec,
+    };
+
+    use stm32f3xx_hal::{
+        gpio::{gpioc, PushPull, AF5},
+        nb, pac,
+        prelude::*,
+        serial::{config, Event, Serial},
+    };
+
+    // Size of the queue of received bytes between rx and process.
+    //
+    // A heapless spsc::Queue of size N holds up to N - 1 items, so this holds
+    // 127 bytes: a bit over 11 ms of input at 115200 baud for process to fall
+    // behind by before rx has to drop bytes.
+    //
+    const RX_QUEUE_SIZE: usize = 128;
+
+    // Length of the longest line that can be received, in bytes.
+    //
+    const LINE_CAPACITY: usize = 64;
+
+    // Length of the longest response, in bytes, with room for the prefix and
+    // the line ending around a full line.
+    //
+    const RESPONSE_CAPACITY: usize = LINE_CAPACITY + 16;
+
+    type Uart4 = Serial<pac::UART4, (gpioc::PC10<AF5<PushPull>>, gpioc::PC11<AF5<PushPull>>)>;
+    type Line = Vec<u8, LINE_CAPACITY>;
+    type Response = String<RESPONSE_CAPACITY>;
+
+    // Counts of the data lost to each kind of backpressure, written out with
+    // the stats command.
+    //
+    #[derive(Default)]
+    pub struct Stats {
+        // Bytes dropped by rx because the byte queue was full.
+        rx_queue_full: u32,
+        // Framing, noise, overrun, or parity errors seen by rx.
+        rx_errors: u32,
+        // Lines dropped by process because they were longer than a line can
+        // be.
+        long_lines: u32,
+        // Responses dropped by process because the message queue of transmit
+        // was full.
+        responses_dropped: u32,
+    }
+
+    // Resources used by more than one task. RTIC only gives access to them
+    // through lock(), except for the highest-priority task using each one.
+    //
+    #[shared]
+    struct Shared {
+        uart4: Uart4,
+        stats: Stats,
+    }
+
+    // Resources owned by a single task each, which gets &mut access to them
+    // without locking.
+    //
+    #[local]
+    struct Local {
+        producer: Producer<'static, u8, RX_QUEUE_SIZE>,
+        consumer: Consumer<'static, u8, RX_QUEUE_SIZE>,
+    }
+
+    // The queue itself is a local resource of init(). RTIC gives those a
+    // 'static lifetime, which is what lets the producer and consumer halves be
+    // moved into the local resources of the tasks.
+    //
+    #[init(local = [rx_queue: Queue<u8, RX_QUEUE_SIZE> = Queue::new()])]
+    fn init(cx: init::Context) -> (Shared, Local, init::Monotonics) {
+        // Get RCC peripheral and set the system clock frequency.
+        //
+        // RTIC has already taken the device and core peripherals and passes
+        // them in through the context, so there's no take() here.
+        //
+        let mut reset_and_clock_control = cx.device.RCC.constrain();
+        let mut flash = cx.device.FLASH.constrain();
+        let clocks = reset_and_clock_control
+            .cfgr
+            .sysclk(48.MHz())
+            .freeze(&mut flash.acr);
+
+        // Get GPIO Port C.
+        //
+        let mut gpioc = cx.device.GPIOC.split(&mut reset_and_clock_control.ahb);
+
+        // Configure GPIO pins PC10 as TX and PC11 as RX for UART4, the same as
+        // in the uart example, and activate the UART with its receive
+        // interrupt enabled.
+        //
+        // The interrupt itself is unmasked in the NVIC by RTIC once init()
+        // returns, since rx is bound to it.
+        //
+        let tx_pin =
+            gpioc
+                .pc10
+                .into_af_push_pull(&mut gpioc.moder, &mut gpioc.otyper, &mut gpioc.afrh);
+        let rx_pin =
+            gpioc
+                .pc11
+                .into_af_push_pull(&mut gpioc.moder, &mut gpioc.otyper, &mut gpioc.afrh);
+        let mut uart4 = Serial::new(
+            cx.device.UART4,
+            (tx_pin, rx_pin),
+            config::Config::default().baudrate(115_200.Bd()),
+            clocks,
+            &mut reset_and_clock_control.apb1,
+        );
+        uart4.configure_interrupt(Event::ReceiveDataRegisterNotEmpty, true);
+
+        writeln!(
+            uart4,
+            "Send a line to echo it, or stats for the counters.\r"
+        )
+        .ok();
+
+        let (producer, consumer) = cx.local.rx_queue.split();
+
+        (
+            Shared {
+                uart4,
+                stats: Stats::default(),
+            },
+            Local { producer, consumer },
+            init::Monotonics(),
+        )
+    }
+
+    // Receives a byte from UART4.
+    //
+    // This is the highest-priority task using uart4 and stats, so they're
+    // accessed with lock() like in the other tasks, but RTIC compiles the lock
+    // down to nothing here.
+    //
+    #[task(binds = UART4_EXTI34, priority = 2, shared = [uart4, stats], local = [producer])]
+    fn rx(mut cx: rx::Context) {
+        let byte = match cx.shared.uart4.lock(|uart4| uart4.read()) {
+            Ok(byte) => byte,
+            Err(nb::Error::WouldBlock) => return,
+            Err(nb::Error::Other(_)) => {
+                cx.shared.stats.lock(|stats| stats.rx_errors += 1);
+                return;
+            }
+        };
+
+        // Backpressure on the byte queue.
+        //
+        // There's nowhere to hold a byte rx can't queue, and it can't wait for
+        // process to make room, since process can't run until rx returns. The
+        // only thing it can do is drop the byte and count it.
+        //
+        if cx.local.producer.enqueue(byte).is_err() {
+            cx.shared.stats.lock(|stats| stats.rx_queue_full += 1);
+        }
+
+        // Hand over to process at the end of each line.
+        //
+        // spawn() fails if process is already waiting to run, since its
+        // message queue has the default capacity of 1. That's fine: once it
+        // runs, it handles every line in the byte queue, including this one.
+        //
+        if byte == b'\r' || byte == b'\n' {
+            process::spawn().ok();
+        }
+    }
+
+    // Assembles received bytes into lines and queues a response for each.
+    //
+    // The line being assembled is a local resource, so a line split across
+    // two runs of process picks up where the first run left off.
+    //
+    #[task(priority = 1, shared = [stats], local = [consumer, line: Line = Vec::new(), overflowed: bool = false])]
+    fn process(mut cx: process::Context) {
+        let line = cx.local.line;
+
+        while let Some(byte) = cx.local.consumer.dequeue() {
+            if byte != b'\r' && byte != b'\n' {
+                if line.push(byte).is_err() {
+                    *cx.local.overflowed = true;
+                }
+                continue;
+            }
+
+            // A \r\n line ending leaves an empty line behind the \r.
+            //
+            if line.is_empty() && !*cx.local.overflowed {
+                continue;
+            }
+
+            if *cx.local.overflowed {
+                cx.shared.stats.lock(|stats| stats.long_lines += 1);
+            } else {
+                let response = respond(line, &mut cx.shared.stats);
+
+                // Backpressure on the message queue of transmit.
+                //
+                // transmit runs at the same priority as process, so it can't
+                // start until process returns, and each response spawned
+                // until then waits in its message queue. If a burst of lines
+                // fills the queue, spawn() fails and hands the message back
+                // rather than blocking or allocating more room. Here, the
+                // response is dropped and counted. Alternatives would be to
+                // stop handling lines until there's room again, leaving the
+                // rest of the bytes in the byte queue, or to give transmit a
+                // higher priority so it runs, and frees its slot, as soon as
+                // it's spawned.
+                //
+                if transmit::spawn(response).is_err() {
+                    cx.shared.stats.lock(|stats| stats.responses_dropped += 1);
+                }
+            }
+
+            line.clear();
+            *cx.local.overflowed = false;
+        }
+    }
+
+    // Writes a response out via UART4.
+    //
+    // RTIC gives each software task a message queue of its own, with room for
+    // the number of messages set by capacity. Each spawn() moves its message
+    // into a free slot in the queue, so a response is queued by value without
+    // any heap.
+    //
+    // uart4 is locked for each byte rather than for the whole response. The
+    // lock masks rx for as long as it's held, and a byte takes about 87 us to
+    // go out at 115200 baud, so holding it for a whole response would make rx
+    // miss incoming bytes and overrun the UART.
+    //
+    #[task(priority = 1, capacity = 4, shared = [uart4])]
+    fn transmit(mut cx: transmit::Context, response: Response) {
+        for byte in response.bytes() {
+            while cx.shared.uart4.lock(|uart4| uart4.write(byte)).is_err() {}
+        }
+    }
+
+    // Builds the response to a line.
+    //
+    fn respond(line: &[u8], stats: &mut impl rtic::Mutex<T = Stats>) -> Response {
+        let mut response = Response::new();
+        let text = core::str::from_utf8(line).unwrap_or("<not UTF-8>");
+
+        if text.trim() == "stats" {
+            stats.lock(|stats| {
+                write!(
+                    response,
+                    "rx queue full: {}, rx errors: {}, long lines: {}, responses dropped: {}\r\n",
+                    stats.rx_queue_full, stats.rx_errors, stats.long_lines, stats.responses_dropped,
+                )
+                .ok();
+            });
+        } else {
+            write!(response, "echo: {}\r\n", text).ok();
+        }
+
+        response
+    }
+
+    // Sleeps whenever no task is running.
+    //
+    #[idle]
+    fn idle(_: idle::Context) -> ! {
+        loop {
+            cortex_m::asm::wfi();
+        }
+    }
+}
@@ -0,0 +1,177 @@
+#![deny(unsafe_code)]
+#![no_std]
+#![no_main]
+
+use core::cell::RefCell;
+use core::fmt::Write;
+use core::sync::atomic::{AtomicU32, Ordering};
+
+// Define the panic handler selected by the panic-* features in Cargo.toml.
+//
+// The default is panic-halt. A breakpoint can be set on `rust_begin_unwind` to
+// catch panics.
+//
+wt_support::panic_handler!();
+
+use cortex_m::asm;
+use cortex_m_rt::entry;
+use critical_section::Mutex;
+
+use stm32f3xx_hal::{
+    gpio::{gpioc, gpioe, Output, PushPull, AF5},
+    pac,
+    prelude::*,
+    serial::config,
+    serial::Serial,
+};
+
+use wt_support::millis::{self, SysTickMillis};
+use wt_support::scheduler::{Scheduler, Task};
+
+// Intervals the tasks run at.
+//
+const BLINK_INTERVAL_MS: u32 = 500;
+const HEARTBEAT_INTERVAL_MS: u32 = 1_000;
+
+type Uart4 = Serial<pac::UART4, (gpioc::PC10<AF5<PushPull>>, gpioc::PC11<AF5<PushPull>>)>;
+
+// LD3 (PE9), the red LED at the top of the compass rose, as an output.
+//
+type LedPin = gpioe::PE9<Output<PushPull>>;
+
+// The LED and UART4, moved into globals by main() for the tasks to use.
+//
+// A task is a plain fn() with no arguments, so it can't be handed anything to
+// work on and has to find it in a global instead, the same as an interrupt
+// handler does in the shared-state example. Only the main loop calls the
+// tasks here, so the critical sections never actually hold anything off, but
+// the Mutex is still what makes the globals safe to have.
+//
+static G_LED: Mutex<RefCell<Option<LedPin>>> = Mutex::new(RefCell::new(None));
+static G_UART: Mutex<RefCell<Option<Uart4>>> = Mutex::new(RefCell::new(None));
+
+// Number of heartbeats written out.
+//
+static HEARTBEAT_COUNT: AtomicU32 = AtomicU32::new(0);
+
+// Blink task: Toggles LD3.
+//
+fn blink() {
+    critical_section::with(|cs| {
+        if let Some(led) = G_LED.borrow(cs).borrow_mut().as_mut() {
+            led.toggle().ok();
+        }
+    });
+}
+
+// Heartbeat task: Writes a count and the uptime out to UART4.
+//
+fn heartbeat() {
+    let count = HEARTBEAT_COUNT.fetch_add(1, Ordering::Relaxed) + 1;
+    let uptime_ms = millis::millis();
+    critical_section::with(|cs| {
+        if let Some(uart4) = G_UART.borrow(cs).borrow_mut().as_mut() {
+            writeln!(uart4, "Heartbeat {} at {} ms\r", count, uptime_ms).ok();
+        }
+    });
+}
+
+#[entry]
+fn main() -> ! {
+    // Get peripherals.
+    //
+    // take() returns an Option, which requires handling the possibility of the
+    // return of an Err or None instead of the desired value, which is of type
+    // pac::Peripherals in this case.
+    //
+    // Since this is an embedded application, it's not as simple as writing to,
+    // stdout. This is a minimal example, so we'll drop into an inifinite loop
+    // to allow a debugger to find where the failure.
+    //
+    let device_periphs = pac::Peripherals::take().unwrap_or_else(|| {
+        loop {
+            // Failed to take pac::Peripherals.
+            asm::nop(); // If real app, replace with actual error handling.
+        }
+    });
+    let core_periphs = cortex_m::Peripherals::take().unwrap_or_else(|| {
+        loop {
+            // Failed to take cortex_m::Peripherals.
+            asm::nop(); // If real app, replace with actual error handling.
+        }
+    });
+
+    // Get RCC peripheral and set the system clock frequency.
+    //
+    let mut reset_and_clock_control = device_periphs.RCC.constrain();
+    let mut flash = device_periphs.FLASH.constrain();
+    let clocks = reset_and_clock_control
+        .cfgr
+        .sysclk(48.MHz())
+        .freeze(&mut flash.acr);
+
+    // Get GPIO Ports C and E.
+    //
+    let mut gpioc = device_periphs.GPIOC.split(&mut reset_and_clock_control.ahb);
+    let mut gpioe = device_periphs.GPIOE.split(&mut reset_and_clock_control.ahb);
+
+    // Configure GPIO pins PC10 as TX and PC11 as RX for UART4, the same as in
+    // the uart example, and activate the UART.
+    //
+    let tx_pin = gpioc
+        .pc10
+        .into_af_push_pull(&mut gpioc.moder, &mut gpioc.otyper, &mut gpioc.afrh);
+    let rx_pin = gpioc
+        .pc11
+        .into_af_push_pull(&mut gpioc.moder, &mut gpioc.otyper, &mut gpioc.afrh);
+    let mut uart4 = Serial::new(
+        device_periphs.UART4,
+        (tx_pin, rx_pin),
+        config::Config::default().baudrate(115_200.Bd()),
+        clocks,
+        &mut reset_and_clock_control.apb1,
+    );
+
+    // Create an instance of LD3 (PE9) in push-pull output mode.
+    //
+    let led = gpioe
+        .pe9
+        .into_push_pull_output(&mut gpioe.moder, &mut gpioe.otyper);
+
+    writeln!(
+        uart4,
+        "Blinking every {} ms, heartbeat every {} ms.\r",
+        BLINK_INTERVAL_MS, HEARTBEAT_INTERVAL_MS
+    )
+    .ok();
+
+    // Move the LED and UART4 into the globals.
+    //
+    critical_section::with(|cs| {
+        G_LED.borrow(cs).replace(Some(led));
+        G_UART.borrow(cs).replace(Some(uart4));
+    });
+
+    // Start the millisecond tick the scheduler runs on, and register the
+    // tasks.
+    //
+    millis::init(core_periphs.SYST, clocks.sysclk().0);
+    let mut scheduler = Scheduler::starting_now(
+        [
+            Task::new(BLINK_INTERVAL_MS, blink),
+            Task::new(HEARTBEAT_INTERVAL_MS, heartbeat),
+        ],
+        &SysTickMillis,
+    );
+
+    // Main loop.
+    //
+    // Run whichever tasks are due, then sleep until the next interrupt. The
+    // SysTick exception behind the millisecond tick is one, so the core wakes
+    // up every millisecond to check again, and sleeps the rest of the time.
+    //
+    loop {
+        scheduler.run(&SysTickMillis);
+        asm::wfi();
+    }
+}
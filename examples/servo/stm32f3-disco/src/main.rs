@@ -0,0 +1,173 @@
+#![deny(unsafe_code)]
+#![no_std]
+#![no_main]
+
+// Define the panic handler selected by the panic-* features in Cargo.toml.
+//
+// The default is panic-halt. A breakpoint can be set on `rust_begin_unwind` to
+// catch panics.
+//
+wt_support::panic_handler!();
+
+use cortex_m::asm;
+use cortex_m_rt::entry;
+
+use stm32f3xx_hal::{
+    delay::Delay,
+    pac,
+    prelude::*,
+    rcc::{BusTimerClock, Enable, Reset},
+};
+
+use wt_support::servo;
+
+// Frequency the timer counts at after the prescaler.
+//
+// At 1 MHz, one tick is 1 us, so the period and the pulse widths from the servo
+// module can be used as tick counts directly.
+//
+const TIMER_TICK_HZ: u32 = 1_000_000;
+
+// Number of ticks in the 20 ms (50 Hz) period of the servo signal.
+//
+// The counter runs from 0 up to ARR inclusive, so a period of 20_000 ticks
+// takes an ARR of 19_999.
+//
+const PERIOD_TICKS: u32 = servo::PERIOD_US * (TIMER_TICK_HZ / 1_000_000);
+
+// Time taken for each step of one degree in the sweep.
+//
+// That works out to 1.8 s for the full 180 degrees. Most hobby servos are rated
+// at around 0.1-0.2 s per 60 degrees, so the servo keeps up easily.
+//
+const STEP_MS: u32 = 10;
+
+#[entry]
+fn main() -> ! {
+    // Get peripherals.
+    //
+    // take() returns an Option, which requires handling the possibility of the
+    // return of an Err or None instead of the desired value, which is of type
+    // pac::Peripherals in this case.
+    //
+    // Since this is an embedded application, it's not as simple as writing to,
+    // stdout. This is a minimal example, so we'll drop into an inifinite loop
+    // to allow a debugger to find where the failure.
+    //
+    let device_periphs = pac::Peripherals::take().unwrap_or_else(|| {
+        loop {
+            // Failed to take pac::Peripherals.
+            asm::nop(); // If real app, replace with actual error handling.
+        }
+    });
+    let core_periphs = cortex_m::Peripherals::take().unwrap_or_else(|| {
+        loop {
+            // Failed to take cortex_m::Peripherals.
+            asm::nop(); // If real app, replace with actual error handling.
+        }
+    });
+
+    // Get RCC peripheral and set the system clock frequency.
+    //
+    let mut reset_and_clock_control = device_periphs.RCC.constrain();
+    let mut flash = device_periphs.FLASH.constrain();
+    let clocks = reset_and_clock_control
+        .cfgr
+        .sysclk(48.MHz())
+        .pclk1(24.MHz())
+        .freeze(&mut flash.acr);
+
+    // Get GPIO Port B.
+    //
+    let mut gpiob = device_periphs.GPIOB.split(&mut reset_and_clock_control.ahb);
+
+    // Configure PB4 as TIM3 channel 1 (alternate function 2).
+    //
+    // Connect the signal wire of the servo (usually orange, yellow, or white)
+    // to PB4. Power the servo from its own 5 V supply rather than the board,
+    // since the current drawn by the motor when it moves can brown out the
+    // board, and connect the grounds of the supply and the board together. A
+    // 3.3 V signal is enough for almost all hobby servos.
+    //
+    // PB4 doubles as NJTRST for JTAG, which isn't used with the SWD connection
+    // of the on-board ST-Link, so it's free.
+    //
+    let _servo_pin =
+        gpiob
+            .pb4
+            .into_af_push_pull::<2>(&mut gpiob.moder, &mut gpiob.otyper, &mut gpiob.afrl);
+
+    // Enable and reset TIM3.
+    //
+    // The PWM abstraction in stm32f3xx-hal is deprecated as of 0.10, so TIM3 is
+    // driven through its registers instead, the same way as in the buzzer
+    // example.
+    //
+    pac::TIM3::enable(&mut reset_and_clock_control.apb1);
+    pac::TIM3::reset(&mut reset_and_clock_control.apb1);
+    let tim3 = device_periphs.TIM3;
+
+    // Set the prescaler so the counter ticks at TIMER_TICK_HZ.
+    //
+    // The timer clock is not simply PCLK1: when the APB1 prescaler is anything
+    // other than 1, the timers are clocked at twice PCLK1. With PCLK1 at 24 MHz
+    // (a prescaler of 2), that's 48 MHz. timer_clock() accounts for this.
+    //
+    let timer_clock_hz = pac::TIM3::timer_clock(&clocks).0;
+    let prescaler = (timer_clock_hz / TIMER_TICK_HZ - 1) as u16;
+    tim3.psc.write(|w| w.psc().bits(prescaler));
+
+    // Set the period to 20 ms.
+    //
+    // The PWM frequency is the tick frequency divided by ARR + 1, so with a
+    // 1 MHz tick, an ARR of 19_999 gives 50 Hz.
+    //
+    tim3.arr.write(|w| w.arr().bits((PERIOD_TICKS - 1) as u16));
+
+    // Configure channel 1 for PWM.
+    //
+    // In PWM mode 1, the output is high while the counter is below the capture
+    // compare register (CCR1), so CCR1 is the width of the pulse in ticks. The
+    // preload enable bit buffers writes to CCR1 until the next update event, so
+    // a new angle always takes effect at the start of a period, and a pulse is
+    // never cut short or stretched by a write in the middle of it.
+    //
+    // The maximum duty, the CCR1 value that would keep the output high for the
+    // whole period, is ARR + 1. That's what angle_to_duty() scales the pulse
+    // width against: a 1.5 ms pulse in a 20 ms period is a duty of 1.5 / 20 of
+    // the maximum, which is 1_500 here.
+    //
+    let max_duty = PERIOD_TICKS as u16;
+    tim3.ccmr1_output()
+        .modify(|_, w| w.oc1m().pwm_mode1().oc1pe().enabled());
+    tim3.ccr1()
+        .write(|w| w.ccr().bits(servo::angle_to_duty(0, max_duty)));
+    tim3.ccer.modify(|_, w| w.cc1e().set_bit());
+
+    // Generate an update event to load the preloaded values, then start the
+    // timer.
+    //
+    tim3.egr.write(|w| w.ug().update());
+    tim3.cr1.modify(|_, w| w.cen().enabled());
+
+    // Get a delay provider for the steps of the sweep.
+    //
+    let mut delay = Delay::new(core_periphs.SYST, clocks);
+
+    // Give the servo time to get to 0 degrees from wherever it was left.
+    //
+    delay.delay_ms(1_000_u32);
+
+    // Main loop.
+    //
+    // Sweep the servo from 0 to 180 degrees and back, one degree at a time.
+    // Only CCR1 changes; the period stays at 20 ms the whole time.
+    //
+    loop {
+        for angle_deg in (0..=servo::MAX_ANGLE_DEG).chain((0..servo::MAX_ANGLE_DEG).rev()) {
+            tim3.ccr1()
+                .write(|w| w.ccr().bits(servo::angle_to_duty(angle_deg, max_duty)));
+            delay.delay_ms(STEP_MS);
+        }
+    }
+}
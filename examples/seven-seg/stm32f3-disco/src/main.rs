@@ -0,0 +1,234 @@
+#![deny(unsafe_code)]
+#![no_std]
+#![no_main]
+
+use core::cell::RefCell;
+
+// Define the panic handler selected by the panic-* features in Cargo.toml.
+//
+// The default is panic-halt. A breakpoint can be set on `rust_begin_unwind` to
+// catch panics.
+//
+wt_support::panic_handler!();
+
+use cortex_m::asm;
+use cortex_m::peripheral::NVIC;
+use cortex_m_rt::entry;
+use critical_section::Mutex;
+
+use stm32f3xx_hal::{
+    gpio::{gpiod, Output, PushPull},
+    pac::{self, interrupt},
+    prelude::*,
+    timer::{Event, Timer},
+};
+
+use wt_support::millis;
+use wt_support::seven_seg::{MultiplexedDisplay, Wiring};
+
+// Time between refreshes of the display in microseconds.
+//
+// Each refresh lights the next of the 4 digits, so every digit is lit once
+// every 4 ms, 250 times a second, well clear of any flicker.
+//
+const REFRESH_INTERVAL_US: u32 = 1_000;
+
+// Time between counts in milliseconds.
+//
+const COUNT_INTERVAL_MS: u32 = 100;
+
+type DisplayPin = gpiod::PDx<Output<PushPull>>;
+type Display = MultiplexedDisplay<DisplayPin, DisplayPin, 4>;
+
+// The display and the refresh timer, shared between main(), which sets them
+// up and changes the number shown, and the TIM2 interrupt handler, which
+// refreshes the display, as in the shared-state example.
+//
+static G_DISPLAY: Mutex<RefCell<Option<Display>>> = Mutex::new(RefCell::new(None));
+static G_TIMER: Mutex<RefCell<Option<Timer<pac::TIM2>>>> = Mutex::new(RefCell::new(None));
+
+#[entry]
+fn main() -> ! {
+    // Get peripherals.
+    //
+    // take() returns an Option, which requires handling the possibility of the
+    // return of an Err or None instead of the desired value, which is of type
+    // pac::Peripherals in this case.
+    //
+    // Since this is an embedded application, it's not as simple as writing to,
+    // stdout. This is a minimal example, so we'll drop into an inifinite loop
+    // to allow a debugger to find where the failure.
+    //
+    let device_periphs = pac::Peripherals::take().unwrap_or_else(|| {
+        loop {
+            // Failed to take pac::Peripherals.
+            asm::nop(); // If real app, replace with actual error handling.
+        }
+    });
+    let core_periphs = cortex_m::Peripherals::take().unwrap_or_else(|| {
+        loop {
+            // Failed to take cortex_m::Peripherals.
+            asm::nop(); // If real app, replace with actual error handling.
+        }
+    });
+
+    // Get RCC peripheral and set the system clock frequency.
+    //
+    let mut reset_and_clock_control = device_periphs.RCC.constrain();
+    let mut flash = device_periphs.FLASH.constrain();
+    let clocks = reset_and_clock_control
+        .cfgr
+        .sysclk(48.MHz())
+        .freeze(&mut flash.acr);
+
+    // Start the millisecond tick for timing the counts.
+    //
+    millis::init(core_periphs.SYST, clocks.sysclk().0);
+
+    // Get GPIO Port D.
+    //
+    let mut gpiod = device_periphs.GPIOD.split(&mut reset_and_clock_control.ahb);
+
+    // Configure the display's pins as push-pull outputs.
+    //
+    // For a 4-digit common cathode display, such as a 5641AS, connect:
+    //
+    // - Segments a to g to PD0 to PD6, each through a 1 kohm resistor.
+    // - The common cathodes of digits 1 to 4, from the left, to PD8 to PD11
+    //   directly.
+    //
+    // The pins drive the cathodes themselves, rather than through
+    // transistors, so the resistors keep the segments to a couple of
+    // milliamps each, and a digit with all 7 lit to well within what one pin
+    // can sink. See wt_support::seven_seg for more on the current.
+    //
+    // The digit pins are set high, i.e. off, before anything else, so no
+    // digit lights up until the first refresh. For a common anode display,
+    // set them low instead, and use Wiring::COMMON_ANODE.
+    //
+    // downgrade() erases the pin numbers from the types, so each set of pins
+    // can go in an array, as in the blinky example.
+    //
+    let segments = [
+        gpiod
+            .pd0
+            .into_push_pull_output(&mut gpiod.moder, &mut gpiod.otyper)
+            .downgrade(),
+        gpiod
+            .pd1
+            .into_push_pull_output(&mut gpiod.moder, &mut gpiod.otyper)
+            .downgrade(),
+        gpiod
+            .pd2
+            .into_push_pull_output(&mut gpiod.moder, &mut gpiod.otyper)
+            .downgrade(),
+        gpiod
+            .pd3
+            .into_push_pull_output(&mut gpiod.moder, &mut gpiod.otyper)
+            .downgrade(),
+        gpiod
+            .pd4
+            .into_push_pull_output(&mut gpiod.moder, &mut gpiod.otyper)
+            .downgrade(),
+        gpiod
+            .pd5
+            .into_push_pull_output(&mut gpiod.moder, &mut gpiod.otyper)
+            .downgrade(),
+        gpiod
+            .pd6
+            .into_push_pull_output(&mut gpiod.moder, &mut gpiod.otyper)
+            .downgrade(),
+    ];
+    let mut digits = [
+        gpiod
+            .pd8
+            .into_push_pull_output(&mut gpiod.moder, &mut gpiod.otyper)
+            .downgrade(),
+        gpiod
+            .pd9
+            .into_push_pull_output(&mut gpiod.moder, &mut gpiod.otyper)
+            .downgrade(),
+        gpiod
+            .pd10
+            .into_push_pull_output(&mut gpiod.moder, &mut gpiod.otyper)
+            .downgrade(),
+        gpiod
+            .pd11
+            .into_push_pull_output(&mut gpiod.moder, &mut gpiod.otyper)
+            .downgrade(),
+    ];
+    for digit in digits.iter_mut() {
+        digit.set_high().ok();
+    }
+
+    let mut display = MultiplexedDisplay::new(segments, digits, Wiring::COMMON_CATHODE);
+    display.show(0);
+
+    // Set up TIM2 as the refresh timer, as the sample rate timer is in the
+    // pwm-audio example.
+    //
+    let mut timer = Timer::new(
+        device_periphs.TIM2,
+        clocks,
+        &mut reset_and_clock_control.apb1,
+    );
+    timer.enable_interrupt(Event::Update);
+    timer.start(REFRESH_INTERVAL_US.microseconds());
+    let timer_interrupt = timer.interrupt();
+
+    // Move the display and the timer into the globals.
+    //
+    critical_section::with(|cs| {
+        G_DISPLAY.borrow(cs).replace(Some(display));
+        G_TIMER.borrow(cs).replace(Some(timer));
+    });
+
+    // Unmask the TIM2 interrupt in the NVIC.
+    //
+    // TIM2 has been running since it was started above, so an update is
+    // likely pending already. The handler needs the timer to clear that
+    // update, or TIM2 would stay pending and the handler would run back to
+    // back, starving main(). It needs the display to move on to the next
+    // digit, so with either missing, the display would never be refreshed.
+    //
+    #[allow(unsafe_code)]
+    unsafe {
+        NVIC::unmask(timer_interrupt);
+    }
+
+    // Main loop.
+    //
+    // Count from 0 to 9999 and round again, one count every COUNT_INTERVAL_MS.
+    // Only the number shown is changed here. The refreshes carry on in the
+    // interrupt, however long the main loop takes, so the display stays
+    // steady.
+    //
+    let mut count: u16 = 0;
+    let mut last_count = millis::millis();
+    loop {
+        if millis::elapsed_since(last_count) >= COUNT_INTERVAL_MS {
+            last_count = millis::millis();
+            count = (count + 1) % 10_000;
+
+            critical_section::with(|cs| {
+                if let Some(display) = G_DISPLAY.borrow(cs).borrow_mut().as_mut() {
+                    display.show(count);
+                }
+            });
+        }
+    }
+}
+
+// Lights the next digit of the display.
+//
+#[interrupt]
+fn TIM2() {
+    critical_section::with(|cs| {
+        if let Some(timer) = G_TIMER.borrow(cs).borrow_mut().as_mut() {
+            timer.clear_event(Event::Update);
+        }
+        if let Some(display) = G_DISPLAY.borrow(cs).borrow_mut().as_mut() {
+            display.refresh().ok();
+        }
+    });
+}
@@ -0,0 +1,217 @@
+#![deny(unsafe_code)]
+#![no_std]
+#![no_main]
+
+use core::cell::RefCell;
+use core::fmt::Write;
+use core::sync::atomic::{AtomicU32, Ordering};
+
+// Define the panic handler selected by the panic-* features in Cargo.toml.
+//
+// The default is panic-halt. A breakpoint can be set on `rust_begin_unwind` to
+// catch panics.
+//
+wt_support::panic_handler!();
+
+use cortex_m::asm;
+use cortex_m::peripheral::NVIC;
+use cortex_m_rt::entry;
+use critical_section::Mutex;
+
+use stm32f3xx_hal::{
+    gpio::{gpioa, Edge, Input},
+    pac::{self, interrupt},
+    prelude::*,
+    serial::config,
+    serial::Serial,
+};
+
+// The user button B1 (PA0) as an input.
+//
+type ButtonPin = gpioa::PA0<Input>;
+
+// The button, shared between main(), which sets it up, and the EXTI0
+// interrupt handler, which needs it to clear the interrupt's pending bit.
+//
+// Why not just a `static mut BUTTON: Option<ButtonPin> = None;`? Every access to
+// a static mut is unsafe, and for good reason here. main() and the handler run
+// concurrently as far as the compiler is concerned: the handler can preempt
+// main() between any two instructions. If main() were partway through writing
+// the Option when the interrupt fired, the handler would read a half-written
+// value. Even without a torn write, the handler and main() would each hold a
+// &mut to the same value at the same time, which is undefined behavior in
+// Rust whether or not anything goes visibly wrong, and lets the compiler make
+// optimizations that break the program, like keeping the value in a register
+// across the interrupt. Nothing stops either side from doing this by accident,
+// so the unsafe block would need every access in the program to be checked by
+// hand, forever.
+//
+// Each layer of the type here removes one of those problems:
+//
+// - Mutex (from critical-section, not std): Only hands out a reference to its
+//   contents with a CriticalSection token, which is only available inside
+//   critical_section::with(). On this single-core chip that masks interrupts
+//   for the duration, so the handler can't run while main() holds the
+//   reference, and main() isn't running at all while the handler does. That
+//   makes at most one side able to touch the contents at a time, which is
+//   also why the Mutex makes the static Sync, and so allowed as a plain
+//   static.
+// - RefCell: The Mutex only gives out a shared reference, since a
+//   CriticalSection token can be copied. The RefCell allows mutating through
+//   it, checking at runtime that there's only one borrow_mut() at a time.
+// - Option: The pin can't be set up in a static initializer, since that needs
+//   the peripherals from main(), so the global starts out as None and has the
+//   pin moved into it during init.
+//
+// This is all safe code: the compiler checks that every access goes through a
+// critical section.
+//
+static BUTTON: Mutex<RefCell<Option<ButtonPin>>> = Mutex::new(RefCell::new(None));
+
+// Number of presses counted by the interrupt handler.
+//
+// A plain integer doesn't need the Mutex and RefCell: an AtomicU32 can be
+// read and written from anywhere through a shared reference, and each load,
+// store, or fetch_add() happens as one indivisible operation, so neither side
+// can see it half-updated. That's what makes it Sync and allowed as a plain
+// static. It's only good for a single value, though. Anything that needs to be
+// updated together with something else, or that isn't an integer, like the
+// pin, still needs a critical section.
+//
+// Cortex-M4 has native atomic read-modify-write instructions (LDREX/STREX), so
+// fetch_add() is lock-free here. Relaxed ordering is enough since the count
+// isn't used to publish any other data.
+//
+static PRESS_COUNT: AtomicU32 = AtomicU32::new(0);
+
+#[entry]
+fn main() -> ! {
+    // Get peripherals.
+    //
+    // take() returns an Option, which requires handling the possibility of the
+    // return of an Err or None instead of the desired value, which is of type
+    // pac::Peripherals in this case.
+    //
+    // Since this is an embedded application, it's not as simple as writing to,
+    // stdout. This is a minimal example, so we'll drop into an inifinite loop
+    // to allow a debugger to find where the failure.
+    //
+    let device_periphs = pac::Peripherals::take().unwrap_or_else(|| {
+        loop {
+            // Failed to take pac::Peripherals.
+            asm::nop(); // If real app, replace with actual error handling.
+        }
+    });
+
+    // Get RCC peripheral and set the system clock frequency.
+    //
+    let mut reset_and_clock_control = device_periphs.RCC.constrain();
+    let mut flash = device_periphs.FLASH.constrain();
+    let clocks = reset_and_clock_control
+        .cfgr
+        .sysclk(48.MHz())
+        .freeze(&mut flash.acr);
+
+    // Get GPIO Ports A and C.
+    //
+    let mut gpioa = device_periphs.GPIOA.split(&mut reset_and_clock_control.ahb);
+    let mut gpioc = device_periphs.GPIOC.split(&mut reset_and_clock_control.ahb);
+
+    // Configure GPIO pins PC10 as TX and PC11 as RX for UART4, the same as in
+    // the uart example, and activate the UART.
+    //
+    let tx_pin = gpioc
+        .pc10
+        .into_af_push_pull(&mut gpioc.moder, &mut gpioc.otyper, &mut gpioc.afrh);
+    let rx_pin = gpioc
+        .pc11
+        .into_af_push_pull(&mut gpioc.moder, &mut gpioc.otyper, &mut gpioc.afrh);
+    let mut uart4 = Serial::new(
+        device_periphs.UART4,
+        (tx_pin, rx_pin),
+        config::Config::default().baudrate(115_200.Bd()),
+        clocks,
+        &mut reset_and_clock_control.apb1,
+    );
+
+    // Configure the user button B1 (PA0) as an input with a pull-down, as in
+    // the button-poll example, to interrupt on its rising edge, i.e. when it's
+    // pressed.
+    //
+    // EXTI line 0 can be connected to pin 0 of any port, so SYSCFG has to
+    // select port A for it first.
+    //
+    let mut syscfg = device_periphs
+        .SYSCFG
+        .constrain(&mut reset_and_clock_control.apb2);
+    let mut exti = device_periphs.EXTI;
+    let mut button = gpioa
+        .pa0
+        .into_pull_down_input(&mut gpioa.moder, &mut gpioa.pupdr);
+    syscfg.select_exti_interrupt_source(&button);
+    button.trigger_on_edge(&mut exti, Edge::Rising);
+    button.enable_interrupt(&mut exti);
+    let button_interrupt = button.interrupt();
+
+    // Move the button into the global.
+    //
+    // From here on, main() no longer owns the pin, so the only way left to get
+    // at it is through the Mutex, from inside a critical section.
+    //
+    critical_section::with(|cs| BUTTON.borrow(cs).replace(Some(button)));
+
+    // Unmask the EXTI0 interrupt in the NVIC.
+    //
+    // The handler clears the pending bit through the button, so if it ran
+    // with BUTTON still empty, a press made during setup would leave the bit
+    // set, and the handler would run again and again, adding presses that
+    // never happened to PRESS_COUNT.
+    //
+    #[allow(unsafe_code)]
+    unsafe {
+        NVIC::unmask(button_interrupt);
+    }
+
+    writeln!(uart4, "Press B1 to count presses.\r").ok();
+
+    // Press count last written out.
+    //
+    let mut last_count = 0;
+
+    // Main loop.
+    //
+    // Sleep until an interrupt, then write the count out if it has changed.
+    // Reading the atomic doesn't need a critical section, so main() never
+    // holds off the handler here.
+    //
+    loop {
+        asm::wfi();
+
+        let count = PRESS_COUNT.load(Ordering::Relaxed);
+        if count != last_count {
+            last_count = count;
+            writeln!(uart4, "Presses: {}\r", count).ok();
+        }
+    }
+}
+
+// Counts presses of the user button.
+//
+// The button isn't debounced, so a single press can bounce and count more than
+// once.
+//
+#[interrupt]
+fn EXTI0() {
+    PRESS_COUNT.fetch_add(1, Ordering::Relaxed);
+
+    critical_section::with(|cs| {
+        // Clear the pending bit.
+        //
+        // The bit stays set until it's cleared by software and the interrupt
+        // fires again as soon as the handler returns if it isn't cleared.
+        //
+        if let Some(button) = BUTTON.borrow(cs).borrow_mut().as_mut() {
+            button.clear_interrupt();
+        }
+    });
+}
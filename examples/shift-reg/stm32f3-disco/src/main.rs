@@ -0,0 +1,113 @@
+#![deny(unsafe_code)]
+#![no_std]
+#![no_main]
+
+// Define the panic handler selected by the panic-* features in Cargo.toml.
+//
+// The default is panic-halt. A breakpoint can be set on `rust_begin_unwind` to
+// catch panics.
+//
+wt_support::panic_handler!();
+
+use cortex_m::asm;
+use cortex_m_rt::entry;
+
+use stm32f3xx_hal::{delay::Delay, pac, prelude::*};
+
+use wt_support::shift_reg::ShiftRegister;
+
+// Time each LED of the chase stays lit in milliseconds.
+//
+const CHASE_STEP_MS: u32 = 80;
+
+#[entry]
+fn main() -> ! {
+    // Get peripherals.
+    //
+    // take() returns an Option, which requires handling the possibility of the
+    // return of an Err or None instead of the desired value, which is of type
+    // pac::Peripherals in this case.
+    //
+    // Since this is an embedded application, it's not as simple as writing to,
+    // stdout. This is a minimal example, so we'll drop into an inifinite loop
+    // to allow a debugger to find where the failure.
+    //
+    let device_periphs = pac::Peripherals::take().unwrap_or_else(|| {
+        loop {
+            // Failed to take pac::Peripherals.
+            asm::nop(); // If real app, replace with actual error handling.
+        }
+    });
+    let core_periphs = cortex_m::Peripherals::take().unwrap_or_else(|| {
+        loop {
+            // Failed to take cortex_m::Peripherals.
+            asm::nop(); // If real app, replace with actual error handling.
+        }
+    });
+
+    // Get RCC peripheral and set the system clock frequency.
+    //
+    // The shift register driver doesn't wait between pin writes, and relies on
+    // them being slow enough for the 74HC595 at this clock speed.
+    //
+    let mut reset_and_clock_control = device_periphs.RCC.constrain();
+    let mut flash = device_periphs.FLASH.constrain();
+    let clocks = reset_and_clock_control
+        .cfgr
+        .sysclk(48.MHz())
+        .freeze(&mut flash.acr);
+
+    // Get GPIO Port D.
+    //
+    let mut gpiod = device_periphs.GPIOD.split(&mut reset_and_clock_control.ahb);
+
+    // Configure PD8 through PD10 as push-pull outputs for the 74HC595.
+    //
+    // PD8  -> SER (pin 14), data
+    // PD9  -> SRCLK (pin 11), shift clock
+    // PD10 -> RCLK (pin 12), latch
+    //
+    // Tie OE (pin 13) to GND so the outputs are always driven, and SRCLR
+    // (pin 10) to 3.3 V so the register is never cleared. Power the 74HC595
+    // from the board's 3.3 V, so its inputs see the same levels the pins put
+    // out, with a 100 nF capacitor from VCC (pin 16) to GND (pin 8) close to
+    // the chip. Connect each of the outputs, QA (pin 15) and QB-QH (pins 1-7),
+    // to an LED and a resistor of a few hundred ohms in series to GND. The
+    // 74HC595 can only supply about 70 mA across all of its outputs at once,
+    // so keep the current through each LED to a few mA.
+    //
+    // The clock and latch pins start out low, as the driver expects.
+    //
+    let data_pin = gpiod
+        .pd8
+        .into_push_pull_output(&mut gpiod.moder, &mut gpiod.otyper);
+    let clock_pin = gpiod
+        .pd9
+        .into_push_pull_output(&mut gpiod.moder, &mut gpiod.otyper);
+    let latch_pin = gpiod
+        .pd10
+        .into_push_pull_output(&mut gpiod.moder, &mut gpiod.otyper);
+
+    let mut shift_reg = ShiftRegister::new(data_pin, clock_pin, latch_pin);
+
+    // Get a delay provider for the time between steps of the chase.
+    //
+    let mut delay = Delay::new(core_periphs.SYST, clocks);
+
+    // Main loop.
+    //
+    // Light one LED at a time, from QA up to QH and back down again. Each
+    // step is a whole new byte written to the register, and since the
+    // outputs only change on the latch, the LED that was lit goes out at the
+    // same moment the next one comes on.
+    //
+    // The GPIO pins on this HAL can't fail to be set, so the results of the
+    // writes are ignored.
+    //
+    loop {
+        for bit in (0..8).chain((1..7).rev()) {
+            shift_reg.write_byte(1 << bit).ok();
+            delay.delay_ms(CHASE_STEP_MS);
+        }
+    }
+}
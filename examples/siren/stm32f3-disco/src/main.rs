@@ -0,0 +1,179 @@
+#![deny(unsafe_code)]
+#![no_std]
+#![no_main]
+
+// Define the panic handler selected by the panic-* features in Cargo.toml.
+//
+// The default is panic-halt. A breakpoint can be set on `rust_begin_unwind` to
+// catch panics.
+//
+wt_support::panic_handler!();
+
+use cortex_m::asm;
+use cortex_m_rt::entry;
+
+use stm32f3xx_hal::{
+    delay::Delay,
+    pac,
+    prelude::*,
+    rcc::{BusTimerClock, Enable, Reset},
+};
+
+// Frequency the timer counts at after the prescaler, as in the buzzer
+// example.
+//
+const TIMER_TICK_HZ: u32 = 1_000_000;
+
+// Lowest and highest frequencies of the sweep, in hertz.
+//
+// The sweep goes from MIN_FREQUENCY_HZ up to MAX_FREQUENCY_HZ and back down
+// again, over and over. Both have to be above ~15 Hz, for the period to fit
+// in the 16-bit ARR at TIMER_TICK_HZ.
+//
+const MIN_FREQUENCY_HZ: u32 = 600;
+const MAX_FREQUENCY_HZ: u32 = 1_600;
+
+// Time taken to sweep from one bound to the other, in milliseconds.
+//
+// A whole rise and fall takes twice this. Around a second each way sounds
+// like a wailing siren; a tenth of that sounds like a yelp.
+//
+const SWEEP_MS: u32 = 1_000;
+
+// Time between frequency changes, in milliseconds.
+//
+// Each step changes the frequency by (MAX_FREQUENCY_HZ - MIN_FREQUENCY_HZ) *
+// STEP_MS / SWEEP_MS, 5 Hz here, small enough that the sweep sounds smooth
+// rather than like a run of separate notes.
+//
+const STEP_MS: u32 = 5;
+
+// Number of steps in one sweep from one bound to the other.
+//
+const STEPS_PER_SWEEP: u32 = SWEEP_MS / STEP_MS;
+
+// Returns the frequency at `step` steps into a sweep up, from
+// MIN_FREQUENCY_HZ at 0 to MAX_FREQUENCY_HZ at STEPS_PER_SWEEP.
+//
+// The frequency goes up by the same number of hertz every step. That sounds
+// fast at the bottom and slow at the top, since the ear hears pitch by
+// ratios, but that's how a mechanical siren sounds too.
+//
+fn frequency_hz(step: u32) -> u32 {
+    MIN_FREQUENCY_HZ + (MAX_FREQUENCY_HZ - MIN_FREQUENCY_HZ) * step / STEPS_PER_SWEEP
+}
+
+#[entry]
+fn main() -> ! {
+    // Get peripherals.
+    //
+    // take() returns an Option, which requires handling the possibility of the
+    // return of an Err or None instead of the desired value, which is of type
+    // pac::Peripherals in this case.
+    //
+    // Since this is an embedded application, it's not as simple as writing to,
+    // stdout. This is a minimal example, so we'll drop into an inifinite loop
+    // to allow a debugger to find where the failure.
+    //
+    let device_periphs = pac::Peripherals::take().unwrap_or_else(|| {
+        loop {
+            // Failed to take pac::Peripherals.
+            asm::nop(); // If real app, replace with actual error handling.
+        }
+    });
+    let core_periphs = cortex_m::Peripherals::take().unwrap_or_else(|| {
+        loop {
+            // Failed to take cortex_m::Peripherals.
+            asm::nop(); // If real app, replace with actual error handling.
+        }
+    });
+
+    // Get RCC peripheral and set the system clock frequency.
+    //
+    let mut reset_and_clock_control = device_periphs.RCC.constrain();
+    let mut flash = device_periphs.FLASH.constrain();
+    let clocks = reset_and_clock_control
+        .cfgr
+        .sysclk(48.MHz())
+        .pclk1(24.MHz())
+        .freeze(&mut flash.acr);
+
+    // Get GPIO Port B.
+    //
+    let mut gpiob = device_periphs.GPIOB.split(&mut reset_and_clock_control.ahb);
+
+    // Configure PB4 as TIM3 channel 1 (alternate function 2), with a passive
+    // piezo buzzer wired to it as in the buzzer example.
+    //
+    let _buzzer_pin =
+        gpiob
+            .pb4
+            .into_af_push_pull::<2>(&mut gpiob.moder, &mut gpiob.otyper, &mut gpiob.afrl);
+
+    // Enable and reset TIM3, and set it up for PWM on channel 1 the same way
+    // as the buzzer example does, with its prescaler set for TIMER_TICK_HZ.
+    //
+    pac::TIM3::enable(&mut reset_and_clock_control.apb1);
+    pac::TIM3::reset(&mut reset_and_clock_control.apb1);
+    let tim3 = device_periphs.TIM3;
+
+    let timer_clock_hz = pac::TIM3::timer_clock(&clocks).0;
+    let prescaler = (timer_clock_hz / TIMER_TICK_HZ - 1) as u16;
+    tim3.psc.write(|w| w.psc().bits(prescaler));
+
+    tim3.ccmr1_output()
+        .modify(|_, w| w.oc1m().pwm_mode1().oc1pe().enabled());
+    tim3.cr1.modify(|_, w| w.arpe().enabled());
+    tim3.ccer.modify(|_, w| w.cc1e().set_bit());
+
+    // Start at the bottom of the sweep. The update event loads the first ARR
+    // and CCR1 straight away, before the timer starts.
+    //
+    let period_ticks = TIMER_TICK_HZ / MIN_FREQUENCY_HZ;
+    tim3.arr.write(|w| w.arr().bits((period_ticks - 1) as u16));
+    tim3.ccr1()
+        .write(|w| w.ccr().bits((period_ticks / 2) as u16));
+    tim3.egr.write(|w| w.ug().update());
+    tim3.cr1.modify(|_, w| w.cen().enabled());
+
+    // Get a delay provider for the steps.
+    //
+    let mut delay = Delay::new(core_periphs.SYST, clocks);
+
+    // Main loop.
+    //
+    // Sweep up and down, a step at a time.
+    //
+    // Unlike between the notes of the buzzer example, the timer keeps running
+    // through every change here, and no update event is generated by
+    // software, which would restart the period the output is halfway through
+    // with an audible click. The new ARR and CCR1 are only written to their
+    // preload registers, and the timer moves both into the active registers
+    // together, at the end of the period it's in. Every period is then a
+    // whole one, at either the old frequency or the new one.
+    //
+    // The duty is CCR1 / (ARR + 1), so CCR1 has to be recomputed from every
+    // new ARR, as half of the new period, to stay at ~50%. Left alone, the
+    // duty would drift across the sweep as the period changes under it: up
+    // towards 100% as the frequency rises and the period shrinks towards
+    // CCR1, and down as it falls, changing the volume and tone along the way.
+    // If the period ever shrank below CCR1, the output would stay high
+    // throughout and the buzzer would go silent.
+    //
+    // The preload matters for the same reason. Without it, ARR and CCR1 would
+    // each change the instant they're written, in the middle of a period, one
+    // before the other. If the counter had already passed the new ARR, it
+    // would carry on counting all the way to 65_535 and round to 0 before the
+    // period ended, a glitch of tens of milliseconds at 1 MHz.
+    //
+    loop {
+        for step in (0..STEPS_PER_SWEEP).chain((1..=STEPS_PER_SWEEP).rev()) {
+            let period_ticks = TIMER_TICK_HZ / frequency_hz(step);
+            tim3.arr.write(|w| w.arr().bits((period_ticks - 1) as u16));
+            tim3.ccr1()
+                .write(|w| w.ccr().bits((period_ticks / 2) as u16));
+
+            delay.delay_ms(STEP_MS);
+        }
+    }
+}
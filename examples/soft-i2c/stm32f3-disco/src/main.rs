@@ -0,0 +1,173 @@
+#![deny(unsafe_code)]
+#![no_std]
+#![no_main]
+
+use core::fmt::Write;
+
+// Define the panic handler selected by the panic-* features in Cargo.toml.
+//
+// The default is panic-halt. A breakpoint can be set on `rust_begin_unwind` to
+// catch panics.
+//
+wt_support::panic_handler!();
+
+use cortex_m::asm;
+use cortex_m_rt::entry;
+
+use stm32f3xx_hal::{
+    delay::Delay,
+    pac,
+    prelude::*,
+    serial::{config, Serial},
+};
+
+use wt_support::soft_i2c::{self, SoftI2c};
+
+// I2C address of the accelerometer in the on-board LSM303.
+//
+const ACCEL_ADDRESS: u8 = 0x19;
+
+// Address of the WHO_AM_I register of the accelerometer and the value it
+// should read back.
+//
+// The register is documented for the LSM303AGR on newer revisions of the board.
+// The LSM303DLHC on older ones doesn't document it, but reads back the same
+// value from the same address, so the check works on both.
+//
+const WHO_AM_I_A: u8 = 0x0F;
+const WHO_AM_I_A_VALUE: u8 = 0x33;
+
+// Half of the I2C clock period in microseconds, for a clock of at most 100 kHz.
+//
+const HALF_PERIOD_US: u32 = 5;
+
+// Time between reads in CPU cycles, about 2 s at 48 MHz.
+//
+// The SysTick delay is owned by the I2C master to time the clock, so the time
+// between reads is counted in cycles instead.
+//
+const READ_DELAY_CYCLES: u32 = 96_000_000;
+
+#[entry]
+fn main() -> ! {
+    // Get peripherals.
+    //
+    // take() returns an Option, which requires handling the possibility of the
+    // return of an Err or None instead of the desired value, which is of type
+    // pac::Peripherals in this case.
+    //
+    // Since this is an embedded application, it's not as simple as writing to,
+    // stdout. This is a minimal example, so we'll drop into an inifinite loop
+    // to allow a debugger to find where the failure.
+    //
+    let device_periphs = pac::Peripherals::take().unwrap_or_else(|| {
+        loop {
+            // Failed to take pac::Peripherals.
+            asm::nop(); // If real app, replace with actual error handling.
+        }
+    });
+
+    // Get RCC peripheral and set the system clock frequency.
+    //
+    let mut reset_and_clock_control = device_periphs.RCC.constrain();
+    let mut flash = device_periphs.FLASH.constrain();
+    let clocks = reset_and_clock_control
+        .cfgr
+        .sysclk(48.MHz())
+        .freeze(&mut flash.acr);
+
+    // Set up delay capability for timing the I2C clock.
+    //
+    let core_periphs = cortex_m::Peripherals::take().unwrap_or_else(|| {
+        loop {
+            // Failed to take cortex_m::Peripherals.
+            asm::nop(); // If real app, replace with actual error handling.
+        }
+    });
+    let delay = Delay::new(core_periphs.SYST, clocks);
+
+    // Get GPIO Ports B and C.
+    //
+    let mut gpiob = device_periphs.GPIOB.split(&mut reset_and_clock_control.ahb);
+    let mut gpioc = device_periphs.GPIOC.split(&mut reset_and_clock_control.ahb);
+
+    // Configure GPIO pins PC10 as TX and PC11 as RX for UART4, the same as in
+    // the uart example, and activate the UART.
+    //
+    let tx_pin = gpioc
+        .pc10
+        .into_af_push_pull(&mut gpioc.moder, &mut gpioc.otyper, &mut gpioc.afrh);
+    let rx_pin = gpioc
+        .pc11
+        .into_af_push_pull(&mut gpioc.moder, &mut gpioc.otyper, &mut gpioc.afrh);
+    let mut uart4 = Serial::new(
+        device_periphs.UART4,
+        (tx_pin, rx_pin),
+        config::Config::default().baudrate(115_200.Bd()),
+        clocks,
+        &mut reset_and_clock_control.apb1,
+    );
+
+    // Configure PB6 as SCL and PB7 as SDA as plain open-drain outputs.
+    //
+    // These are the pins the LSM303 is wired to, which the I2C1 peripheral
+    // would normally use, but here they're ordinary GPIO pins driven by the
+    // software master. Any other pair of pins would work the same for an
+    // external device. Open drain is what makes them usable for I2C: setting
+    // one high releases its line instead of driving it, and the pin can still
+    // be read to see what the line is actually doing. The board has pull-ups on
+    // these two lines for the LSM303, so no internal pull-ups are needed.
+    //
+    let scl = gpiob
+        .pb6
+        .into_open_drain_output(&mut gpiob.moder, &mut gpiob.otyper);
+    let sda = gpiob
+        .pb7
+        .into_open_drain_output(&mut gpiob.moder, &mut gpiob.otyper);
+
+    // Create the software I2C master.
+    //
+    // It implements the same embedded-hal I2C traits as the hardware I2c type,
+    // so it can be handed to any driver written against them.
+    //
+    let mut i2c = SoftI2c::new(scl, sda, delay, HALF_PERIOD_US).unwrap_or_else(|_| {
+        loop {
+            // Failed to set up the software I2C master.
+            asm::nop(); // If real app, replace with actual error handling.
+        }
+    });
+
+    loop {
+        // Read the WHO_AM_I register.
+        //
+        // This writes the register address, then reads one byte back after a
+        // repeated start, which is how registers are read from most I2C
+        // devices.
+        //
+        let mut who_am_i = [0];
+        match i2c.write_read(ACCEL_ADDRESS, &[WHO_AM_I_A], &mut who_am_i) {
+            Ok(()) if who_am_i[0] == WHO_AM_I_A_VALUE => {
+                writeln!(uart4, "WHO_AM_I: 0x{:02X} (OK)\r", who_am_i[0]).ok();
+            }
+            Ok(()) => {
+                writeln!(
+                    uart4,
+                    "WHO_AM_I: 0x{:02X} (expected 0x{:02X})\r",
+                    who_am_i[0], WHO_AM_I_A_VALUE
+                )
+                .ok();
+            }
+            Err(soft_i2c::Error::Nack) => {
+                writeln!(uart4, "No ACK from 0x{:02X}.\r", ACCEL_ADDRESS).ok();
+            }
+            Err(soft_i2c::Error::Timeout) => {
+                writeln!(uart4, "SCL held low. Check the pull-ups.\r").ok();
+            }
+            Err(soft_i2c::Error::Pin(_)) => {
+                writeln!(uart4, "GPIO error.\r").ok();
+            }
+        }
+
+        asm::delay(READ_DELAY_CYCLES);
+    }
+}
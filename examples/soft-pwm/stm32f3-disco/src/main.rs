@@ -0,0 +1,182 @@
+#![deny(unsafe_code)]
+#![no_std]
+#![no_main]
+
+// Define the panic handler selected by the panic-* features in Cargo.toml.
+//
+// The default is panic-halt. A breakpoint can be set on `rust_begin_unwind` to
+// catch panics.
+//
+wt_support::panic_handler!();
+
+use core::cell::RefCell;
+
+use cortex_m::asm;
+use cortex_m::peripheral::NVIC;
+use cortex_m_rt::entry;
+use critical_section::Mutex;
+
+use stm32f3xx_hal::{
+    delay::Delay,
+    gpio::{gpioe, Output, PushPull},
+    pac::{self, interrupt},
+    prelude::*,
+    timer::{Event, Timer},
+};
+
+use wt_support::soft_pwm::{SoftPwm, MAX_DUTY_PERCENT};
+
+// Number of ticks in each period of the PWM, which is also the resolution.
+//
+// 100 steps gives a resolution of 1%, which matches the percent duties set by
+// set_duty().
+//
+const PWM_STEPS: u16 = 100;
+
+// Interval between the TIM2 interrupts that tick the PWM.
+//
+// A tick every 100 us is a 10 kHz interrupt, which with 100 steps per period
+// is a PWM frequency of 100 Hz: just fast enough for the LED not to flicker.
+// See the soft_pwm module for the tradeoff between resolution, frequency, and
+// the time spent in the interrupt handler.
+//
+const TICK_INTERVAL_US: u32 = 100;
+
+// Time taken for each step of 1% in the fade.
+//
+// Fading all the way up and back down takes 200 steps, so 2 s.
+//
+const FADE_STEP_MS: u32 = 10;
+
+// LD6 (PE15) driven as a software PWM output.
+//
+type LedPwm = SoftPwm<gpioe::PE15<Output<PushPull>>>;
+
+// The PWM output and the timer ticking it, shared between main() and the TIM2
+// interrupt handler.
+//
+// Both are moved in from main() once set up. The handler ticks the PWM and
+// clears the timer's update flag, and main() changes the duty, so both only
+// ever access them inside a critical section. They start out as None since
+// the peripherals can't be set up in a static initializer.
+//
+static G_PWM: Mutex<RefCell<Option<LedPwm>>> = Mutex::new(RefCell::new(None));
+static G_TIMER: Mutex<RefCell<Option<Timer<pac::TIM2>>>> = Mutex::new(RefCell::new(None));
+
+#[entry]
+fn main() -> ! {
+    // Get peripherals.
+    //
+    // take() returns an Option, which requires handling the possibility of the
+    // return of an Err or None instead of the desired value, which is of type
+    // pac::Peripherals in this case.
+    //
+    // Since this is an embedded application, it's not as simple as writing to,
+    // stdout. This is a minimal example, so we'll drop into an inifinite loop
+    // to allow a debugger to find where the failure.
+    //
+    let device_periphs = pac::Peripherals::take().unwrap_or_else(|| {
+        loop {
+            // Failed to take pac::Peripherals.
+            asm::nop(); // If real app, replace with actual error handling.
+        }
+    });
+    let core_periphs = cortex_m::Peripherals::take().unwrap_or_else(|| {
+        loop {
+            // Failed to take cortex_m::Peripherals.
+            asm::nop(); // If real app, replace with actual error handling.
+        }
+    });
+
+    // Get RCC peripheral and set the system clock frequency.
+    //
+    let mut reset_and_clock_control = device_periphs.RCC.constrain();
+    let mut flash = device_periphs.FLASH.constrain();
+    let clocks = reset_and_clock_control
+        .cfgr
+        .sysclk(48.MHz())
+        .pclk1(24.MHz())
+        .freeze(&mut flash.acr);
+
+    // Get GPIO Port E.
+    //
+    let mut gpioe = device_periphs.GPIOE.split(&mut reset_and_clock_control.ahb);
+
+    // Create an instance of LD6 (PE15) in push-pull output mode and move it
+    // into the global as a PWM output, starting at a duty of 0%.
+    //
+    // The other LEDs on PE8-PE14 are all on TIM1 channels and could be dimmed
+    // with hardware PWM, but no timer channel can be routed to PE15, so it
+    // takes software PWM.
+    //
+    let led_ld6 = gpioe
+        .pe15
+        .into_push_pull_output(&mut gpioe.moder, &mut gpioe.otyper);
+    let pwm = SoftPwm::new(led_ld6, PWM_STEPS);
+    critical_section::with(|cs| G_PWM.borrow(cs).replace(Some(pwm)));
+
+    // Set up TIM2 to generate an update interrupt every tick and move it into
+    // the global for the interrupt handler, which needs it to clear the update
+    // flag.
+    //
+    let mut timer = Timer::new(
+        device_periphs.TIM2,
+        clocks,
+        &mut reset_and_clock_control.apb1,
+    );
+    timer.enable_interrupt(Event::Update);
+    timer.start(TICK_INTERVAL_US.microseconds());
+    let timer_interrupt = timer.interrupt();
+    critical_section::with(|cs| G_TIMER.borrow(cs).replace(Some(timer)));
+
+    // Unmask the TIM2 interrupt in the NVIC.
+    //
+    // Every tick of the PWM is a TIM2 update, so the handler needs G_TIMER to
+    // clear each one and G_PWM to advance the output. Without the timer, the
+    // first update would never be cleared, and the handler would run back to
+    // back from then on, with main() never getting to set a duty.
+    //
+    #[allow(unsafe_code)]
+    unsafe {
+        NVIC::unmask(timer_interrupt);
+    }
+
+    // Get a delay provider for the steps of the fade.
+    //
+    let mut delay = Delay::new(core_periphs.SYST, clocks);
+
+    // Main loop.
+    //
+    // Fade LD6 up from off to fully on and back down, one percent at a time.
+    // Only the duty changes here; the interrupt handler does the switching of
+    // the pin in the background.
+    //
+    loop {
+        for duty in (0..=MAX_DUTY_PERCENT).chain((0..MAX_DUTY_PERCENT).rev()) {
+            critical_section::with(|cs| {
+                if let Some(pwm) = G_PWM.borrow(cs).borrow_mut().as_mut() {
+                    pwm.set_duty(duty);
+                }
+            });
+            delay.delay_ms(FADE_STEP_MS);
+        }
+    }
+}
+
+#[interrupt]
+fn TIM2() {
+    critical_section::with(|cs| {
+        // Clear the update flag.
+        //
+        // The flag stays set until it's cleared by software and the interrupt
+        // fires again as soon as the handler returns if it isn't cleared.
+        //
+        if let Some(timer) = G_TIMER.borrow(cs).borrow_mut().as_mut() {
+            timer.clear_event(Event::Update);
+        }
+
+        if let Some(pwm) = G_PWM.borrow(cs).borrow_mut().as_mut() {
+            pwm.tick().ok();
+        }
+    });
+}
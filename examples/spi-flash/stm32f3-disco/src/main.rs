@@ -0,0 +1,250 @@
+#![deny(unsafe_code)]
+#![no_std]
+#![no_main]
+
+use core::fmt::Write;
+
+// Define the panic handler selected by the panic-* features in Cargo.toml.
+//
+// The default is panic-halt. A breakpoint can be set on `rust_begin_unwind` to
+// catch panics.
+//
+wt_support::panic_handler!();
+
+use cortex_m::asm;
+use cortex_m_rt::entry;
+
+use embedded_hal::{blocking::spi::Transfer, digital::v2::OutputPin};
+
+use stm32f3xx_hal::{
+    delay::Delay,
+    pac,
+    prelude::*,
+    serial::{config, Serial},
+    spi::{self, Spi},
+};
+
+// W25Q-series instruction codes used here.
+//
+// Most SPI NOR flash chips from other manufacturers use the same codes for
+// these, since they're part of the de facto standard command set.
+//
+const CMD_READ_JEDEC_ID: u8 = 0x9F;
+const CMD_RELEASE_POWER_DOWN: u8 = 0xAB;
+
+// Time from the end of a Release Power-down command until the chip accepts
+// other commands (tRES1), in microseconds. The W25Q datasheets give 3 us.
+//
+const RELEASE_POWER_DOWN_US: u32 = 3;
+
+// Delay in milliseconds between reads of the ID.
+//
+const READ_DELAY_MS: u16 = 2_000;
+
+// Runs one command on the flash chip.
+//
+// SPI is full duplex: on every clock, one bit goes out on MOSI and one comes
+// back on MISO, so bytes are always exchanged rather than just sent or
+// received. transfer() does exactly that, writing each byte of buf out and
+// replacing it with the byte clocked in at the same time.
+//
+// A command is framed by chip select. CS going low starts it, the first byte
+// is the instruction, and any bytes after that are either arguments to it or
+// dummy bytes clocked out just to clock the response in. CS going high again
+// ends it. The chip only returns to waiting for a new instruction on that
+// rising edge, so CS has to be deasserted between commands rather than held low
+// across several of them. Without it, the next instruction byte would be taken
+// as more of the previous command, and for the commands that change the
+// contents of the flash (program, erase, write enable), the rising edge of CS is
+// what actually starts the operation.
+//
+fn command<SPI, CS>(spi: &mut SPI, cs: &mut CS, buf: &mut [u8]) -> Result<(), SPI::Error>
+where
+    SPI: Transfer<u8>,
+    CS: OutputPin,
+{
+    cs.set_low().ok();
+    let result = spi.transfer(buf).map(|_| ());
+
+    // Deassert CS whether or not the transfer succeeded so the next command
+    // starts from a clean frame.
+    //
+    cs.set_high().ok();
+    result
+}
+
+#[entry]
+fn main() -> ! {
+    // Get peripherals.
+    //
+    // take() returns an Option, which requires handling the possibility of the
+    // return of an Err or None instead of the desired value, which is of type
+    // pac::Peripherals in this case.
+    //
+    // Since this is an embedded application, it's not as simple as writing to,
+    // stdout. This is a minimal example, so we'll drop into an inifinite loop
+    // to allow a debugger to find where the failure.
+    //
+    let device_periphs = pac::Peripherals::take().unwrap_or_else(|| {
+        loop {
+            // Failed to take pac::Peripherals.
+            asm::nop(); // If real app, replace with actual error handling.
+        }
+    });
+
+    // Get RCC peripheral and set the system clock frequency.
+    //
+    let mut reset_and_clock_control = device_periphs.RCC.constrain();
+    let mut flash = device_periphs.FLASH.constrain();
+    let clocks = reset_and_clock_control
+        .cfgr
+        .sysclk(48.MHz())
+        .pclk1(24.MHz())
+        .freeze(&mut flash.acr);
+
+    // Set up delay capability.
+    //
+    let core_periphs = cortex_m::Peripherals::take().unwrap_or_else(|| {
+        loop {
+            // Failed to take cortex_m::Peripherals.
+            asm::nop(); // If real app, replace with actual error handling.
+        }
+    });
+    let mut delay = Delay::new(core_periphs.SYST, clocks);
+
+    // Get GPIO Ports A, C, and E.
+    //
+    let mut gpioa = device_periphs.GPIOA.split(&mut reset_and_clock_control.ahb);
+    let mut gpioc = device_periphs.GPIOC.split(&mut reset_and_clock_control.ahb);
+    let mut gpioe = device_periphs.GPIOE.split(&mut reset_and_clock_control.ahb);
+
+    // Configure GPIO pins PC10 as TX and PC11 as RX for UART4, the same as in
+    // the uart example, and activate the UART.
+    //
+    let tx_pin = gpioc
+        .pc10
+        .into_af_push_pull(&mut gpioc.moder, &mut gpioc.otyper, &mut gpioc.afrh);
+    let rx_pin = gpioc
+        .pc11
+        .into_af_push_pull(&mut gpioc.moder, &mut gpioc.otyper, &mut gpioc.afrh);
+    let mut uart4 = Serial::new(
+        device_periphs.UART4,
+        (tx_pin, rx_pin),
+        config::Config::default().baudrate(115_200.Bd()),
+        clocks,
+        &mut reset_and_clock_control.apb1,
+    );
+
+    // Deselect the on-board gyro.
+    //
+    // SPI1 is already wired to the gyro on the board, with its chip select on
+    // PE3, so the flash chip shares the bus with it. Driving PE3 high keeps the
+    // gyro off the bus, so it doesn't drive MISO at the same time as the flash
+    // chip.
+    //
+    let mut gyro_cs = gpioe
+        .pe3
+        .into_push_pull_output(&mut gpioe.moder, &mut gpioe.otyper);
+    gyro_cs.set_high().ok();
+
+    // Configure PA4 as the chip select for the flash chip.
+    //
+    // The chip select is a plain GPIO output driven by this code rather than
+    // the hardware NSS output of the SPI peripheral, which gives full control
+    // over where each command starts and ends. It's set high, deasserted,
+    // before the SPI pins are configured so the chip never sees a glitch on the
+    // clock while it's selected.
+    //
+    let mut flash_cs = gpioa
+        .pa4
+        .into_push_pull_output(&mut gpioa.moder, &mut gpioa.otyper);
+    flash_cs.set_high().ok();
+
+    // Configure PA5 as SCK, PA6 as MISO, and PA7 as MOSI for SPI1, and activate
+    // the SPI.
+    //
+    // Wire the flash chip up as follows, and tie its /WP and /HOLD pins high to
+    // 3V:
+    //
+    // PA4 -> /CS
+    // PA5 -> CLK
+    // PA6 <- DO
+    // PA7 -> DI
+    //
+    // W25Q-series chips support SPI modes 0 and 3. Mode 0 is used here at 1 MHz,
+    // well under the chip's limit, to keep the wiring on a breadboard from
+    // being a problem.
+    //
+    let sck = gpioa
+        .pa5
+        .into_af_push_pull(&mut gpioa.moder, &mut gpioa.otyper, &mut gpioa.afrl);
+    let miso = gpioa
+        .pa6
+        .into_af_push_pull(&mut gpioa.moder, &mut gpioa.otyper, &mut gpioa.afrl);
+    let mosi = gpioa
+        .pa7
+        .into_af_push_pull(&mut gpioa.moder, &mut gpioa.otyper, &mut gpioa.afrl);
+    let mut spi1 = Spi::new(
+        device_periphs.SPI1,
+        (sck, miso, mosi),
+        spi::config::Config::default()
+            .frequency(1.MHz())
+            .mode(embedded_hal::spi::MODE_0),
+        clocks,
+        &mut reset_and_clock_control.apb2,
+    );
+
+    // Wake the chip up in case it was left in power-down mode.
+    //
+    // The chip ignores everything but this command while powered down, so
+    // it's sent first as its own command, then the chip is given tRES1 to wake
+    // up before the next one.
+    //
+    let mut release = [CMD_RELEASE_POWER_DOWN];
+    command(&mut spi1, &mut flash_cs, &mut release).ok();
+    delay.delay_us(RELEASE_POWER_DOWN_US);
+
+    loop {
+        // Read the JEDEC ID.
+        //
+        // The command is the instruction byte followed by three dummy bytes.
+        // Nothing useful comes back during the instruction byte, since the chip
+        // doesn't know what to send until it has received it, and the three
+        // bytes of the ID come back during the dummy bytes:
+        //
+        // MOSI: 0x9F   0x00          0x00         0x00
+        // MISO: (junk) manufacturer  memory type  capacity
+        //
+        let mut buf = [CMD_READ_JEDEC_ID, 0, 0, 0];
+        match command(&mut spi1, &mut flash_cs, &mut buf) {
+            Ok(()) => {
+                let [_, manufacturer, memory_type, capacity] = buf;
+                writeln!(
+                    uart4,
+                    "JEDEC ID: manufacturer 0x{:02X}, memory type 0x{:02X}, capacity 0x{:02X}\r",
+                    manufacturer, memory_type, capacity
+                )
+                .ok();
+
+                // Interpret the ID.
+                //
+                // All 0x00 or all 0xFF means nothing drove MISO: MISO floats or
+                // is pulled to one level when there's no chip there or it isn't
+                // selected, so check the wiring. Otherwise, 0xEF is Winbond and
+                // the capacity byte is log2 of the size in bytes, e.g. 0x17
+                // for a W25Q64 is 2^23 bytes, 8 MiB.
+                //
+                if buf[1..].iter().all(|&b| b == 0x00) || buf[1..].iter().all(|&b| b == 0xFF) {
+                    writeln!(uart4, "No response, check the wiring.\r").ok();
+                } else if (10..32).contains(&capacity) {
+                    writeln!(uart4, "Capacity: {} KiB\r", (1_u32 << capacity) / 1024).ok();
+                }
+            }
+            Err(_) => {
+                writeln!(uart4, "SPI transfer failed.\r").ok();
+            }
+        }
+
+        delay.delay_ms(READ_DELAY_MS);
+    }
+}
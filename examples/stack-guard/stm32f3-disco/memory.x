@@ -0,0 +1,25 @@
+/* Linker script for the STM32F303VCT6, with the stack at the bottom of RAM */
+MEMORY
+{
+  /* NOTE 1 K = 1 KiBi = 1024 bytes */
+  FLASH : ORIGIN = 0x08000000, LENGTH = 256K
+
+  /* The 40K of SRAM, split in two: the first 8K for the stack, and the rest,
+     above it, for .data, .bss, and .uninit. */
+  STACK : ORIGIN = 0x20000000, LENGTH = 8K
+  RAM : ORIGIN = 0x20002000, LENGTH = 32K
+}
+
+/* By default, cortex-m-rt starts the stack at the top of RAM, growing down
+   towards the statics at the bottom, with nothing in between to stop it.
+   Here, the stack starts at the top of its own region instead, just below the
+   statics, and grows down towards the start of SRAM at 0x2000_0000. Below that
+   is a reserved part of the address space, so a push past the bottom of the
+   stack faults on the bus, rather than overwriting the statics. This is the
+   same layout that flip-link makes, without needing it as the linker. */
+_stack_start = ORIGIN(STACK) + LENGTH(STACK);
+
+/* The lowest address the stack may use. cortex-m-rt otherwise takes it to be
+   the end of the statics in RAM, and checks that it's below _stack_start,
+   which it no longer is. */
+_stack_end = ORIGIN(STACK);
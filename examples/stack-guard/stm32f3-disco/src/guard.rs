@@ -0,0 +1,45 @@
+//! The entry to the HardFault handler, which moves the stack pointer back
+//! into the stack before running any Rust code.
+//!
+//! Everything in here is unsafe, so this module is exempt from the
+//! `#![deny(unsafe_code)]` on the rest of the crate, the same as the fault
+//! module of the hardfault example.
+
+#![allow(unsafe_code)]
+
+use cortex_m::peripheral::SCB;
+
+// Replaces the HardFault handler that cortex-m-rt's #[exception] would define.
+//
+// After a stack overflow, the stack pointer is below the bottom of the stack,
+// where nothing can be written. The core still enters the HardFault, but a
+// handler written in Rust starts by pushing registers onto the stack, which
+// would fault again. A fault inside the HardFault handler can't be handled,
+// so the core would lock up, and the report would never get out.
+//
+// So this is the HardFault handler itself, in assembly, which the vector
+// table points to directly in place of the one #[exception] would generate.
+// Without pushing anything, it reads the stack pointer the fault left into
+// r0, loads the bottom of the stack into r1, resets MSP to the top of the
+// stack, and branches on to crate::on_hard_fault(), with r0 and r1 as its
+// arguments. Whatever was on the stack is abandoned, which is fine, since the
+// handler never returns.
+//
+core::arch::global_asm!(
+    ".section .HardFault.user, \"ax\"",
+    ".global HardFault",
+    ".type HardFault, %function",
+    ".thumb_func",
+    "HardFault:",
+    "mrs r0, MSP",
+    "ldr r1, =_stack_end",
+    "ldr r2, =_stack_start",
+    "msr MSP, r2",
+    "b {handler}",
+    handler = sym crate::on_hard_fault,
+);
+
+/// Returns the Configurable Fault Status Register.
+pub fn cfsr() -> u32 {
+    unsafe { (*SCB::PTR).cfsr.read() }
+}
@@ -0,0 +1,195 @@
+#![deny(unsafe_code)]
+#![no_std]
+#![no_main]
+
+use core::cell::RefCell;
+use core::fmt::Write;
+use core::sync::atomic::{AtomicU32, Ordering};
+
+// Define the panic handler selected by the panic-* features in Cargo.toml.
+//
+// The default is panic-halt. A breakpoint can be set on `rust_begin_unwind` to
+// catch panics.
+//
+wt_support::panic_handler!();
+
+use cortex_m::asm;
+use cortex_m_rt::entry;
+use critical_section::Mutex;
+
+use stm32f3xx_hal::{
+    block,
+    gpio::{gpioc, PushPull, AF5},
+    pac,
+    prelude::*,
+    serial::config,
+    serial::Serial,
+};
+
+mod guard;
+
+type Uart4 = Serial<pac::UART4, (gpioc::PC10<AF5<PushPull>>, gpioc::PC11<AF5<PushPull>>)>;
+
+// UART4, shared between main() and the HardFault handler, which writes the
+// report out through it, as in the hardfault example.
+//
+static G_UART: Mutex<RefCell<Option<Uart4>>> = Mutex::new(RefCell::new(None));
+
+// Number of calls deep recurse() has gone.
+//
+static DEPTH: AtomicU32 = AtomicU32::new(0);
+
+// Calls itself until the stack runs out.
+//
+// Every call puts a 64-byte buffer on the stack, and passes it to black_box()
+// so it can't be optimized away, and neither can the recursion, since the
+// result depends on every call. With 8K of stack, it takes around a hundred
+// calls to reach the bottom. It never returns, which is the point, so the
+// warning for that is allowed.
+//
+#[allow(unconditional_recursion)]
+#[inline(never)]
+fn recurse(depth: u32) -> u32 {
+    DEPTH.store(depth, Ordering::Relaxed);
+    let buffer = core::hint::black_box([depth as u8; 64]);
+    recurse(depth + 1).wrapping_add(u32::from(buffer[63]))
+}
+
+#[entry]
+fn main() -> ! {
+    // Get peripherals.
+    //
+    // take() returns an Option, which requires handling the possibility of the
+    // return of an Err or None instead of the desired value, which is of type
+    // pac::Peripherals in this case.
+    //
+    // Since this is an embedded application, it's not as simple as writing to,
+    // stdout. This is a minimal example, so we'll drop into an inifinite loop
+    // to allow a debugger to find where the failure.
+    //
+    let device_periphs = pac::Peripherals::take().unwrap_or_else(|| {
+        loop {
+            // Failed to take pac::Peripherals.
+            asm::nop(); // If real app, replace with actual error handling.
+        }
+    });
+
+    // Get RCC peripheral and set the system clock frequency.
+    //
+    let mut reset_and_clock_control = device_periphs.RCC.constrain();
+    let mut flash = device_periphs.FLASH.constrain();
+    let clocks = reset_and_clock_control
+        .cfgr
+        .sysclk(48.MHz())
+        .freeze(&mut flash.acr);
+
+    // Get GPIO Port C.
+    //
+    let mut gpioc = device_periphs.GPIOC.split(&mut reset_and_clock_control.ahb);
+
+    // Configure GPIO pins PC10 as TX and PC11 as RX for UART4, the same as in
+    // the uart example, and activate the UART.
+    //
+    let tx_pin = gpioc
+        .pc10
+        .into_af_push_pull(&mut gpioc.moder, &mut gpioc.otyper, &mut gpioc.afrh);
+    let rx_pin = gpioc
+        .pc11
+        .into_af_push_pull(&mut gpioc.moder, &mut gpioc.otyper, &mut gpioc.afrh);
+    let mut uart4 = Serial::new(
+        device_periphs.UART4,
+        (tx_pin, rx_pin),
+        config::Config::default().baudrate(115_200.Bd()),
+        clocks,
+        &mut reset_and_clock_control.apb1,
+    );
+
+    writeln!(uart4, "Send r to recurse until the stack overflows.\r").ok();
+
+    // Move UART4 into the global for the HardFault handler.
+    //
+    critical_section::with(|cs| G_UART.borrow(cs).replace(Some(uart4)));
+
+    // Main loop.
+    //
+    // Wait for the command, then recurse. The critical section is over by the
+    // time recurse() is called, so the HardFault handler can borrow UART4.
+    //
+    loop {
+        let byte = critical_section::with(|cs| {
+            G_UART
+                .borrow(cs)
+                .borrow_mut()
+                .as_mut()
+                .and_then(|uart4| uart4.read().ok())
+        });
+
+        if byte == Some(b'r') {
+            core::hint::black_box(recurse(1));
+        }
+    }
+}
+
+// Reports the HardFault over UART4, then halts.
+//
+// Why the layout in memory.x matters:
+//
+// With the default layout, the stack starts at the top of RAM and grows down
+// towards .bss and .data at the bottom. Nothing marks where one ends and the
+// other begins, so a stack that grows too far just carries on into the
+// statics, overwriting them with return addresses and local variables, and
+// then gets overwritten back whenever the statics are written. Nothing
+// faults. The program carries on with corrupted state, and fails later, in
+// some other place, in a way that has nothing to do with the cause, if it
+// visibly fails at all. A global Mutex<RefCell<..>> like G_UART is as exposed
+// as anything else.
+//
+// memory.x here puts the stack below the statics instead, at the start of
+// SRAM, so it grows down towards the reserved space below 0x2000_0000 rather
+// than towards anything else. The first push past the bottom is a BusFault,
+// escalated to a HardFault, right at the point the stack ran out, with the
+// statics untouched. That makes the report below trustworthy enough to send.
+//
+// guard.rs has already moved the stack pointer back to the top of the stack
+// by the time this runs, and passes in where it was, `sp`, and the bottom of
+// the stack, `stack_bottom`. The stack pointer being below the bottom is what
+// tells a stack overflow apart from any other fault. CFSR shows STKERR (bit
+// 12) when it's the core pushing the exception frame that hit the bottom,
+// and PRECISERR (bit 9) or IMPRECISERR (bit 10) when it was a push by the
+// code itself.
+//
+// The interrupted code's exception frame isn't printed, unlike in the
+// hardfault example, since after an overflow it was never written.
+//
+extern "C" fn on_hard_fault(sp: u32, stack_bottom: u32) -> ! {
+    let cfsr = guard::cfsr();
+
+    critical_section::with(|cs| {
+        if let Ok(mut uart4) = G_UART.borrow(cs).try_borrow_mut() {
+            if let Some(uart4) = uart4.as_mut() {
+                if sp < stack_bottom {
+                    writeln!(
+                        uart4,
+                        "\r\nHardFault: stack overflow after {} calls!\r",
+                        DEPTH.load(Ordering::Relaxed)
+                    )
+                    .ok();
+                } else {
+                    writeln!(uart4, "\r\nHardFault!\r").ok();
+                }
+                writeln!(
+                    uart4,
+                    "SP: {:#010x}  Stack bottom: {:#010x}  CFSR: {:#010x}\r",
+                    sp, stack_bottom, cfsr
+                )
+                .ok();
+                block!(uart4.flush()).ok();
+            }
+        }
+    });
+
+    loop {
+        // Halted after a HardFault. A debugger can attach here to look around.
+        asm::nop();
+    }
+}
@@ -0,0 +1,150 @@
+#![deny(unsafe_code)]
+#![no_std]
+#![no_main]
+
+// Define the panic handler selected by the panic-* features in Cargo.toml.
+//
+// The default is panic-halt. A breakpoint can be set on `rust_begin_unwind` to
+// catch panics.
+//
+wt_support::panic_handler!();
+
+use cortex_m::asm;
+use cortex_m_rt::entry;
+
+use stm32f3xx_hal::{delay::Delay, pac, prelude::*};
+
+use wt_support::stepper::{StepMode, Stepper};
+
+// Excitation sequence to step the motor with.
+//
+// Change this to StepMode::HalfStep for twice the resolution. Each call to
+// step_forward() then turns the motor by half as much, so STEPS has to be
+// doubled to turn it the same distance.
+//
+const STEP_MODE: StepMode = StepMode::FullStep;
+
+// Number of steps to turn each way.
+//
+// 200 steps is a full revolution of a typical 1.8-degree-per-step motor in
+// full-step mode.
+//
+const STEPS: u32 = 200;
+
+// Time between steps in milliseconds.
+//
+// This sets the speed: 200 steps at 5 ms per step is 1 s per revolution, or 60
+// RPM. A motor only has so much torque to accelerate the rotor to the next
+// step, so if it buzzes and stalls instead of turning, increase this.
+//
+const STEP_DELAY_MS: u32 = 5;
+
+// Time to pause at each end in milliseconds.
+//
+const PAUSE_MS: u32 = 1_000;
+
+#[entry]
+fn main() -> ! {
+    // Get peripherals.
+    //
+    // take() returns an Option, which requires handling the possibility of the
+    // return of an Err or None instead of the desired value, which is of type
+    // pac::Peripherals in this case.
+    //
+    // Since this is an embedded application, it's not as simple as writing to,
+    // stdout. This is a minimal example, so we'll drop into an inifinite loop
+    // to allow a debugger to find where the failure.
+    //
+    let device_periphs = pac::Peripherals::take().unwrap_or_else(|| {
+        loop {
+            // Failed to take pac::Peripherals.
+            asm::nop(); // If real app, replace with actual error handling.
+        }
+    });
+    let core_periphs = cortex_m::Peripherals::take().unwrap_or_else(|| {
+        loop {
+            // Failed to take cortex_m::Peripherals.
+            asm::nop(); // If real app, replace with actual error handling.
+        }
+    });
+
+    // Get RCC peripheral and set the system clock frequency.
+    //
+    let mut reset_and_clock_control = device_periphs.RCC.constrain();
+    let mut flash = device_periphs.FLASH.constrain();
+    let clocks = reset_and_clock_control
+        .cfgr
+        .sysclk(48.MHz())
+        .freeze(&mut flash.acr);
+
+    // Get GPIO Port D.
+    //
+    let mut gpiod = device_periphs.GPIOD.split(&mut reset_and_clock_control.ahb);
+
+    // Configure PD0 through PD3 as push-pull outputs for the driver inputs.
+    //
+    // A stepper coil needs far more current than a GPIO pin can supply, and
+    // has to be driven in both directions, so the pins go to the inputs of a
+    // dual H-bridge driver board such as a DRV8833 or an L298N rather than to
+    // the motor itself:
+    //
+    // PD0 -> IN1 (coil A, +)
+    // PD1 -> IN2 (coil A, -)
+    // PD2 -> IN3 (coil B, +)
+    // PD3 -> IN4 (coil B, -)
+    //
+    // The motor is powered from the driver's own supply, with its ground
+    // connected to the board's. The two wires of each coil can be found with a
+    // multimeter: they're the pairs with a few ohms between them. If the motor
+    // just vibrates in place, one coil is wired backwards or the coils are
+    // mixed up.
+    //
+    let coil_a_pos = gpiod
+        .pd0
+        .into_push_pull_output(&mut gpiod.moder, &mut gpiod.otyper);
+    let coil_a_neg = gpiod
+        .pd1
+        .into_push_pull_output(&mut gpiod.moder, &mut gpiod.otyper);
+    let coil_b_pos = gpiod
+        .pd2
+        .into_push_pull_output(&mut gpiod.moder, &mut gpiod.otyper);
+    let coil_b_neg = gpiod
+        .pd3
+        .into_push_pull_output(&mut gpiod.moder, &mut gpiod.otyper);
+
+    let mut stepper = Stepper::new(coil_a_pos, coil_a_neg, coil_b_pos, coil_b_neg, STEP_MODE);
+
+    // Get a delay provider for the time between steps.
+    //
+    let mut delay = Delay::new(core_periphs.SYST, clocks);
+
+    // Energize the coils at the first phase of the sequence.
+    //
+    // This pulls the rotor to the nearest position for that phase, so the
+    // steps after it are counted from a known position.
+    //
+    stepper.energize().ok();
+    delay.delay_ms(PAUSE_MS);
+
+    // Main loop.
+    //
+    // Turn STEPS steps forward, pause, then turn back the same number of steps
+    // to where it started, and pause again.
+    //
+    // The GPIO pins on this HAL can't fail to be set, so the results of the
+    // steps are ignored.
+    //
+    loop {
+        for _ in 0..STEPS {
+            stepper.step_forward().ok();
+            delay.delay_ms(STEP_DELAY_MS);
+        }
+        delay.delay_ms(PAUSE_MS);
+
+        for _ in 0..STEPS {
+            stepper.step_backward().ok();
+            delay.delay_ms(STEP_DELAY_MS);
+        }
+        delay.delay_ms(PAUSE_MS);
+    }
+}
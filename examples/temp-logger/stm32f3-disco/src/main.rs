@@ -0,0 +1,249 @@
+#![deny(unsafe_code)]
+#![no_std]
+#![no_main]
+
+use core::fmt::Write;
+
+// Define the panic handler selected by the panic-* features in Cargo.toml.
+//
+// The default is panic-halt. A breakpoint can be set on `rust_begin_unwind` to
+// catch panics.
+//
+wt_support::panic_handler!();
+
+use cortex_m::asm;
+use cortex_m_rt::entry;
+
+use stm32f3xx_hal::{
+    adc::{self, config::SampleTime, Adc, CommonAdc},
+    pac,
+    prelude::*,
+    serial::{config, Serial},
+};
+
+use wt_support::millis;
+
+// Addresses of the factory calibration values in system memory.
+//
+// Each part is calibrated at the factory, and the raw ADC readings taken are
+// stored in system memory, from where they're read at runtime. All three were
+// taken with VDDA at 3.3 V. See the "Temperature sensor calibration values"
+// and "Internal reference voltage calibration values" tables in the STM32F303xC
+// datasheet.
+//
+// - TS_CAL1: The temperature sensor at 30 C.
+// - TS_CAL2: The temperature sensor at 110 C.
+// - VREFINT_CAL: The internal reference voltage.
+//
+const TS_CAL1_ADDRESS: usize = 0x1FFF_F7B8;
+const TS_CAL2_ADDRESS: usize = 0x1FFF_F7C2;
+const VREFINT_CAL_ADDRESS: usize = 0x1FFF_F7BA;
+
+// Temperatures at which TS_CAL1 and TS_CAL2 were taken, in hundredths of a
+// degree C.
+//
+const TS_CAL1_TEMP_CENTI_C: i32 = 3_000;
+const TS_CAL2_TEMP_CENTI_C: i32 = 11_000;
+
+// Interval between readings in milliseconds.
+//
+const LOG_INTERVAL_MS: u32 = 1_000;
+
+// Reads a 16-bit factory calibration value.
+//
+fn read_calibration(address: usize) -> u16 {
+    // Reading system memory through a raw pointer is unsafe, since the
+    // compiler can't check the address. These are fixed, documented addresses
+    // of read-only values, so the reads are fine.
+    //
+    #[allow(unsafe_code)]
+    unsafe {
+        core::ptr::read_volatile(address as *const u16)
+    }
+}
+
+#[entry]
+fn main() -> ! {
+    // Get peripherals.
+    //
+    // take() returns an Option, which requires handling the possibility of the
+    // return of an Err or None instead of the desired value, which is of type
+    // pac::Peripherals in this case.
+    //
+    // Since this is an embedded application, it's not as simple as writing to,
+    // stdout. This is a minimal example, so we'll drop into an inifinite loop
+    // to allow a debugger to find where the failure.
+    //
+    let device_periphs = pac::Peripherals::take().unwrap_or_else(|| {
+        loop {
+            // Failed to take pac::Peripherals.
+            asm::nop(); // If real app, replace with actual error handling.
+        }
+    });
+
+    // Get RCC peripheral and set the system clock frequency.
+    //
+    let mut reset_and_clock_control = device_periphs.RCC.constrain();
+    let mut flash = device_periphs.FLASH.constrain();
+    let clocks = reset_and_clock_control
+        .cfgr
+        .sysclk(48.MHz())
+        .freeze(&mut flash.acr);
+
+    // Start the millisecond tick for the timestamps.
+    //
+    let core_periphs = cortex_m::Peripherals::take().unwrap_or_else(|| {
+        loop {
+            // Failed to take cortex_m::Peripherals.
+            asm::nop(); // If real app, replace with actual error handling.
+        }
+    });
+    millis::init(core_periphs.SYST, clocks.sysclk().0);
+
+    // Get GPIO Port C.
+    //
+    let mut gpioc = device_periphs.GPIOC.split(&mut reset_and_clock_control.ahb);
+
+    // Configure GPIO pins PC10 as TX and PC11 as RX for UART4, the same as in
+    // the uart example, and activate the UART.
+    //
+    let tx_pin = gpioc
+        .pc10
+        .into_af_push_pull(&mut gpioc.moder, &mut gpioc.otyper, &mut gpioc.afrh);
+    let rx_pin = gpioc
+        .pc11
+        .into_af_push_pull(&mut gpioc.moder, &mut gpioc.otyper, &mut gpioc.afrh);
+    let mut uart4 = Serial::new(
+        device_periphs.UART4,
+        (tx_pin, rx_pin),
+        config::Config::default().baudrate(115_200.Bd()),
+        clocks,
+        &mut reset_and_clock_control.apb1,
+    );
+
+    // Set up ADC1 with the temperature sensor and the internal reference.
+    //
+    // The internal sensors are enabled through the common registers shared by
+    // ADC1 and ADC2, which can only be done while both are disabled, so the
+    // sensors are created before ADC1 is enabled.
+    //
+    let mut adc_common = CommonAdc::new(
+        device_periphs.ADC1_2,
+        &clocks,
+        &mut reset_and_clock_control.ahb,
+    );
+    let mut adcs = (device_periphs.ADC1, device_periphs.ADC2);
+    let mut temp_sensor = adc::TemperatureSensor::new(&mut adc_common, &mut adcs);
+    let mut vref_int = adc::VoltageInternalReference::new(&mut adc_common, &mut adcs);
+    let mut adc1 = Adc::new(adcs.0, adc::config::Config::default(), &clocks, &adc_common);
+
+    // Use the longest sample time for both sensors.
+    //
+    // The datasheet calls for a sample time of at least 2.2 us for the
+    // temperature sensor. The default of 1.5 ADC clock cycles is far too short
+    // for that, and reads low. 601.5 cycles is plenty at any ADC clock.
+    //
+    adc1.set_sample_time(&temp_sensor, SampleTime::Cycles601C5);
+    adc1.set_sample_time(&vref_int, SampleTime::Cycles601C5);
+
+    let ts_cal1 = read_calibration(TS_CAL1_ADDRESS) as i32;
+    let ts_cal2 = read_calibration(TS_CAL2_ADDRESS) as i32;
+    let vrefint_cal = read_calibration(VREFINT_CAL_ADDRESS) as i32;
+
+    // Write the CSV header.
+    //
+    // The output is plain CSV, one line per reading, so it can be captured on
+    // the host straight to a file. With a USB-serial adapter on UART4 showing
+    // up as /dev/ttyUSB0 on Linux, for example:
+    //
+    // $ stty -F /dev/ttyUSB0 115200 raw -echo
+    // $ cat /dev/ttyUSB0 | tee temp.csv
+    //
+    // The file can then be opened in a spreadsheet, or plotted with e.g.
+    // pandas:
+    //
+    // >>> pandas.read_csv("temp.csv").plot(x="millis", y="temp_c")
+    //
+    // The header goes out again after a reset, so if the capture was already
+    // running, there's a second header in the file to delete first. Lines
+    // capture starts partway through should be dropped too.
+    //
+    writeln!(uart4, "millis,temp_c\r").ok();
+
+    // Time the last reading was scheduled for, or None before the first one.
+    //
+    // The first reading is taken right away rather than one interval after
+    // startup, so there's no gap at the start of the log. It can't be timed
+    // the same way as the others, from the time of the reading before it,
+    // since there isn't one, so it's handled on its own. Its timestamp is
+    // likely to be 0, since the tick counter has only just been started, and
+    // that's correct rather than a placeholder: the timestamps are the time
+    // since the tick was started.
+    //
+    // Every later reading is scheduled one interval after the one before it,
+    // rather than one interval after it actually happened, so the readings
+    // don't drift later and later because of the time spent taking and writing
+    // them out.
+    //
+    let mut last_scheduled: Option<u32> = None;
+
+    // Main loop.
+    //
+    loop {
+        let scheduled = match last_scheduled {
+            None => millis::millis(),
+            Some(last) if millis::elapsed_since(last) >= LOG_INTERVAL_MS => {
+                last.wrapping_add(LOG_INTERVAL_MS)
+            }
+            Some(_) => continue,
+        };
+        last_scheduled = Some(scheduled);
+
+        let timestamp = millis::millis();
+
+        // Read the temperature sensor and the internal reference.
+        //
+        // The ADC reads an input as a fraction of VDDA, and on this board, VDDA
+        // is 3.0 V, not the 3.3 V the calibration values were taken at. The
+        // internal reference is a fixed voltage, so reading it measures VDDA:
+        // vrefint_cal / vrefint_raw is the ratio of 3.3 V to the actual VDDA.
+        // Scaling the temperature reading by it gives what it would have read
+        // at 3.3 V, so it can be compared to the calibration values.
+        //
+        let temp_raw: u16 = adc1.read(&mut temp_sensor).unwrap_or(0);
+        let vrefint_raw: u16 = adc1.read(&mut vref_int).unwrap_or(0);
+        // If either reading can't be used, the line still goes out with an
+        // empty temperature, so the gap shows up in the log.
+        //
+        if vrefint_raw == 0 || ts_cal2 == ts_cal1 {
+            writeln!(uart4, "{},\r", timestamp).ok();
+            continue;
+        }
+        let temp_raw_3v3 = temp_raw as i32 * vrefint_cal / vrefint_raw as i32;
+
+        // Convert to hundredths of a degree C.
+        //
+        // The sensor's output is linear in temperature, so the temperature is
+        // interpolated along the line through the two calibration points. It's
+        // the temperature of the die, which runs a few degrees above the air
+        // around the board.
+        //
+        let temp_centi_c = TS_CAL1_TEMP_CENTI_C
+            + (temp_raw_3v3 - ts_cal1) * (TS_CAL2_TEMP_CENTI_C - TS_CAL1_TEMP_CENTI_C)
+                / (ts_cal2 - ts_cal1);
+
+        // Write out the CSV line, formatting the temperature with two decimal
+        // places without floating point formatting.
+        //
+        let sign = if temp_centi_c < 0 { "-" } else { "" };
+        writeln!(
+            uart4,
+            "{},{}{}.{:02}\r",
+            timestamp,
+            sign,
+            temp_centi_c.abs() / 100,
+            temp_centi_c.abs() % 100
+        )
+        .ok();
+    }
+}
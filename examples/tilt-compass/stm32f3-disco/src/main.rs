@@ -0,0 +1,292 @@
+#![deny(unsafe_code)]
+#![no_std]
+#![no_main]
+
+use core::fmt::Write;
+
+// Define the panic handler selected by the panic-* features in Cargo.toml.
+//
+// The default is panic-halt. A breakpoint can be set on `rust_begin_unwind` to
+// catch panics.
+//
+wt_support::panic_handler!();
+
+use cortex_m::asm;
+use cortex_m_rt::entry;
+use libm::{atan2f, sincosf, sqrtf};
+
+use stm32f3xx_hal::{
+    delay::Delay,
+    i2c::I2c,
+    pac,
+    prelude::*,
+    serial::{config, Serial},
+};
+
+use wt_support::lsm303::{Lsm303, Vector, MAG_GAIN_XY, MAG_GAIN_Z};
+
+// Calibration offsets.
+//
+// ACCEL_OFFSET is subtracted from the accelerometer readings, in mg. With the
+// board lying flat and still, X and Y should read 0 and Z 1000, and the
+// offset is how far off they are. A few tens of mg is typical, and only
+// shifts the roll and pitch by a degree or two, so it can be left at zero.
+//
+// MAG_OFFSET is the hard-iron offset, subtracted from the magnetometer
+// readings, in counts. Anything magnetised near the sensor, on the board or
+// around it, adds a field that turns with the board, shifting every reading
+// by the same amount. It's usually larger than the Earth's field itself, so
+// without it the heading is useless. To find it, add the raw readings to the
+// output, turn the board slowly through every orientation, and note the
+// lowest and highest reading on each axis. The offset is halfway between
+// them: (max + min) / 2.
+//
+// MAG_SCALE is a simple soft-iron correction, multiplying each axis after
+// the offset is taken off. Magnetically soft material nearby, or a
+// difference between the axes' sensitivities, stretches the sphere the
+// readings move around into an ellipsoid. From the same minimums and
+// maximums, the scale for each axis is the average of the three half-ranges,
+// (max - min) / 2, divided by that axis's own half-range. 1.0 is no
+// correction.
+//
+// The values here are for no calibration at all. They're different for every
+// board, and change if it's mounted near anything else, so calibrate it where
+// it's going to be used.
+//
+const ACCEL_OFFSET: [i16; 3] = [0, 0, 0];
+const MAG_OFFSET: [i16; 3] = [0, 0, 0];
+const MAG_SCALE: [f32; 3] = [1.0, 1.0, 1.0];
+
+// Magnetic declination in degrees, east positive.
+//
+// The magnetometer finds magnetic north, which is off from true north by an
+// angle that depends on where on Earth it is, and drifts by a fraction of a
+// degree a year. Look it up for the location, e.g. from NOAA's calculator,
+// and set it here to get headings from true north instead.
+//
+const DECLINATION_DEG: f32 = 0.0;
+
+// Delay in milliseconds between readings.
+//
+const READ_INTERVAL_MS: u16 = 500;
+
+// Converts an accelerometer reading to mg, with the offset taken off.
+//
+fn accel_mg(accel: Vector) -> [f32; 3] {
+    let raw = accel.to_array();
+    let axis = |i: usize| f32::from(raw[i]) - f32::from(ACCEL_OFFSET[i]);
+    [axis(0), axis(1), axis(2)]
+}
+
+// Converts a magnetometer reading to gauss, with the calibration applied.
+//
+// The Z axis is less sensitive than X and Y, so each axis is divided by its
+// own gain to put all three in gauss before they're combined.
+//
+fn mag_gauss(mag: Vector) -> [f32; 3] {
+    let raw = mag.to_array();
+    let gain = [MAG_GAIN_XY, MAG_GAIN_XY, MAG_GAIN_Z];
+    let axis = |i: usize| (f32::from(raw[i]) - f32::from(MAG_OFFSET[i])) * MAG_SCALE[i] / gain[i];
+    [axis(0), axis(1), axis(2)]
+}
+
+// Orientation of the board, in degrees.
+//
+struct Orientation {
+    heading: f32,
+    roll: f32,
+    pitch: f32,
+}
+
+// Works out the tilt-compensated heading from the acceleration and the
+// magnetic field.
+//
+// The sensor's axes are right-handed, with Z out of the top of the board, and
+// the accelerometer and the magnetometer in the LSM303DLHC share them. The
+// heading is the direction the sensor's X axis points in, clockwise from
+// north.
+//
+// Roll and pitch
+//
+// Held still, the accelerometer only measures gravity, or rather the push
+// back against it, 1 g straight up. Rolling the board by R around its X axis,
+// then pitching it by P around its Y axis, turns that into:
+//
+//   ax = -sin(P)
+//   ay = sin(R) * cos(P)
+//   az = cos(R) * cos(P)
+//
+// so:
+//
+//   R = atan2(ay, az)
+//   P = atan2(-ax, sqrt(ay^2 + az^2))
+//
+// atan2() rather than asin() keeps the full range of roll, and works with the
+// readings as they are, in mg, without scaling them to exactly 1 g first. Near
+// a pitch of +/-90 degrees, ay and az both go to zero, the roll can't be
+// known, and neither can the heading.
+//
+// Tilt compensation
+//
+// Flat, the heading would just be atan2(my, mx), but tilted, the vertical
+// part of the Earth's field, which is larger than the horizontal part at most
+// latitudes, leaks into X and Y, and a few degrees of tilt swings the heading
+// by tens of degrees. So the field is rotated back into the horizontal plane
+// first, undoing the roll around X and then the pitch around Y:
+//
+//   Xh = mx * cos(P) + (my * sin(R) + mz * cos(R)) * sin(P)
+//   Yh = my * cos(R) - mz * sin(R)
+//
+// (Xh, Yh) is then the horizontal field as if the board were flat, and
+// pointing X north puts it all on Xh, while pointing it east puts it all on
+// Yh, since north is then to the left, along +Y. So the heading is:
+//
+//   heading = atan2(Yh, Xh)
+//
+// brought into the range 0 to 360 degrees, with the declination added.
+//
+// Accelerating the board, rather than just tilting it, adds to what the
+// accelerometer reads, and throws the roll and pitch off with it, so it
+// only gets them right while it's still or moving steadily.
+//
+fn orientation(accel: [f32; 3], mag: [f32; 3]) -> Orientation {
+    let [ax, ay, az] = accel;
+    let [mx, my, mz] = mag;
+
+    let roll = atan2f(ay, az);
+    let pitch = atan2f(-ax, sqrtf(ay * ay + az * az));
+
+    let (sin_roll, cos_roll) = sincosf(roll);
+    let (sin_pitch, cos_pitch) = sincosf(pitch);
+    let xh = mx * cos_pitch + (my * sin_roll + mz * cos_roll) * sin_pitch;
+    let yh = my * cos_roll - mz * sin_roll;
+
+    let mut heading = atan2f(yh, xh).to_degrees() + DECLINATION_DEG;
+    if heading < 0.0 {
+        heading += 360.0;
+    } else if heading >= 360.0 {
+        heading -= 360.0;
+    }
+
+    Orientation {
+        heading,
+        roll: roll.to_degrees(),
+        pitch: pitch.to_degrees(),
+    }
+}
+
+#[entry]
+fn main() -> ! {
+    // Get peripherals.
+    //
+    // take() returns an Option, which requires handling the possibility of the
+    // return of an Err or None instead of the desired value, which is of type
+    // pac::Peripherals in this case.
+    //
+    // Since this is an embedded application, it's not as simple as writing to,
+    // stdout. This is a minimal example, so we'll drop into an inifinite loop
+    // to allow a debugger to find where the failure.
+    //
+    let device_periphs = pac::Peripherals::take().unwrap_or_else(|| {
+        loop {
+            // Failed to take pac::Peripherals.
+            asm::nop(); // If real app, replace with actual error handling.
+        }
+    });
+
+    // Get RCC peripheral and set the system clock frequency.
+    //
+    let mut reset_and_clock_control = device_periphs.RCC.constrain();
+    let mut flash = device_periphs.FLASH.constrain();
+    let clocks = reset_and_clock_control
+        .cfgr
+        .sysclk(48.MHz())
+        .pclk1(24.MHz())
+        .freeze(&mut flash.acr);
+
+    // Set up delay capability.
+    //
+    let core_periphs = cortex_m::Peripherals::take().unwrap_or_else(|| {
+        loop {
+            // Failed to take cortex_m::Peripherals.
+            asm::nop(); // If real app, replace with actual error handling.
+        }
+    });
+    let mut delay = Delay::new(core_periphs.SYST, clocks);
+
+    // Get GPIO Ports B and C.
+    //
+    let mut gpiob = device_periphs.GPIOB.split(&mut reset_and_clock_control.ahb);
+    let mut gpioc = device_periphs.GPIOC.split(&mut reset_and_clock_control.ahb);
+
+    // Configure GPIO pins PC10 as TX and PC11 as RX for UART4, the same as in
+    // the uart example, and activate the UART.
+    //
+    let tx_pin = gpioc
+        .pc10
+        .into_af_push_pull(&mut gpioc.moder, &mut gpioc.otyper, &mut gpioc.afrh);
+    let rx_pin = gpioc
+        .pc11
+        .into_af_push_pull(&mut gpioc.moder, &mut gpioc.otyper, &mut gpioc.afrh);
+    let mut uart4 = Serial::new(
+        device_periphs.UART4,
+        (tx_pin, rx_pin),
+        config::Config::default().baudrate(115_200.Bd()),
+        clocks,
+        &mut reset_and_clock_control.apb1,
+    );
+
+    // Configure PB6 as SCL and PB7 as SDA for I2C1, the pins the on-board
+    // LSM303DLHC is wired to, as in the i2c-scan example, and activate the
+    // I2C.
+    //
+    let scl = gpiob
+        .pb6
+        .into_af_open_drain(&mut gpiob.moder, &mut gpiob.otyper, &mut gpiob.afrl);
+    let sda = gpiob
+        .pb7
+        .into_af_open_drain(&mut gpiob.moder, &mut gpiob.otyper, &mut gpiob.afrl);
+    let i2c1 = I2c::new(
+        device_periphs.I2C1,
+        (scl, sda),
+        100_000.Hz(),
+        clocks,
+        &mut reset_and_clock_control.apb1,
+    );
+
+    // Hand the I2C to the LSM303DLHC driver from wt-support, which sets up and
+    // reads both halves of the chip. It only needs the embedded-hal I2C
+    // traits, which the HAL's I2c implements, so the same driver is tested on
+    // the host against a mock bus.
+    //
+    let mut sensor = Lsm303::new(i2c1);
+    if sensor.init().is_err() {
+        writeln!(uart4, "Failed to set up the LSM303DLHC.\r").ok();
+        loop {
+            asm::wfi();
+        }
+    }
+
+    loop {
+        match (sensor.read_accel(), sensor.read_mag()) {
+            (Ok(accel), Ok(mag)) => {
+                let Orientation {
+                    heading,
+                    roll,
+                    pitch,
+                } = orientation(accel_mg(accel), mag_gauss(mag));
+                writeln!(
+                    uart4,
+                    "Heading: {:5.1}  Roll: {:6.1}  Pitch: {:6.1}\r",
+                    heading, roll, pitch
+                )
+                .ok();
+            }
+            _ => {
+                writeln!(uart4, "Failed to read the LSM303DLHC.\r").ok();
+            }
+        }
+
+        delay.delay_ms(READ_INTERVAL_MS);
+    }
+}
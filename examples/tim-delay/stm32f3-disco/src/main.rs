@@ -0,0 +1,286 @@
+#![deny(unsafe_code)]
+#![no_std]
+#![no_main]
+
+use core::fmt::Write;
+
+// Define the panic handler selected by the panic-* features in Cargo.toml.
+//
+// The default is panic-halt. A breakpoint can be set on `rust_begin_unwind` to
+// catch panics.
+//
+wt_support::panic_handler!();
+
+use cortex_m::asm;
+use cortex_m::peripheral::DWT;
+use cortex_m_rt::entry;
+
+use stm32f3xx_hal::{
+    gpio::{gpiod, Input, Output, PushPull},
+    pac,
+    prelude::*,
+    rcc::{BusTimerClock, Enable, Reset},
+    serial::{config, Serial},
+};
+
+use wt_support::dwt_delay;
+use wt_support::tim_delay::{self, FreeRunningCounter, TimerDelay};
+
+// Length of each half of a clock pulse in microseconds.
+//
+// The HX711 needs its clock high for at least 0.2 us and at most 50 us per
+// bit, and low for at least 0.2 us. 2 us is comfortably inside that, and far
+// too short for a millisecond delay, but with the 1 MHz tick, each half can
+// come out anywhere from 2 to 3 us. See wt_support::tim_delay.
+//
+const PULSE_US: u32 = 2;
+
+// Longest the clock can be high for in microseconds before the HX711 takes it
+// as a request to power down, which it does after 60 us. Every pulse is
+// checked against the 50 us limit for a bit instead, to leave a margin.
+//
+const MAX_HIGH_US: u32 = 50;
+
+// Number of clock pulses per reading: 24 for the bits of the value, then 1 more
+// to pick channel A with a gain of 128 for the next one.
+//
+const PULSES_PER_READING: usize = 25;
+
+// How long to wait for a reading to be ready in microseconds.
+//
+// At the default 10 samples/s, a new one is ready every 100 ms.
+//
+const READY_TIMEOUT_US: u32 = 200_000;
+
+// Delay in milliseconds between readings.
+//
+const READ_INTERVAL_MS: u32 = 500;
+
+// TIM2 as a free-running counter for TimerDelay.
+//
+// TIM2 is 32 bits wide on the STM32F303, so at 1 MHz it only wraps every 71.6
+// minutes. TIM3 or TIM4 would do just as well with MAX set to 0xFFFF.
+//
+struct Tim2Counter(pac::TIM2);
+
+impl FreeRunningCounter for Tim2Counter {
+    const MAX: u32 = u32::MAX;
+
+    fn count(&self) -> u32 {
+        self.0.cnt.read().bits()
+    }
+}
+
+// Timing of the high half of the clock pulses of one reading, in core clock
+// cycles.
+//
+struct PulseTiming {
+    shortest: u32,
+    longest: u32,
+}
+
+// Clocks a reading out of an HX711, or just the clock pulses for one if
+// nothing is answering.
+//
+// Each pulse sets PD_SCK high, waits PULSE_US, reads a bit from DOUT, which
+// the HX711 shifts out on the rising edge, then sets PD_SCK low and waits
+// again. The bits come MSB first, as a 24-bit two's complement value, which
+// is sign extended to an i32 here.
+//
+// The whole reading runs in a critical section, since an interrupt that
+// happened to land while PD_SCK was high could hold it there past 60 us and
+// power the HX711 down partway through. Nothing else uses interrupts in this
+// example, but a real application would.
+//
+// The DWT cycle counter is read either side of each high half, to check how
+// long it actually came out against what was asked for.
+//
+fn read_hx711(
+    sck: &mut gpiod::PD0<Output<PushPull>>,
+    dout: &gpiod::PD1<Input>,
+    delay: &mut TimerDelay<Tim2Counter>,
+) -> (i32, PulseTiming) {
+    let mut raw: u32 = 0;
+    let mut timing = PulseTiming {
+        shortest: u32::MAX,
+        longest: 0,
+    };
+
+    critical_section::with(|_| {
+        for pulse in 0..PULSES_PER_READING {
+            let start = DWT::cycle_count();
+            sck.set_high().ok();
+            delay.delay_us(PULSE_US);
+            let bit = dout.is_high().unwrap_or(false);
+            sck.set_low().ok();
+            let cycles = DWT::cycle_count().wrapping_sub(start);
+
+            timing.shortest = timing.shortest.min(cycles);
+            timing.longest = timing.longest.max(cycles);
+
+            if pulse < 24 {
+                raw = (raw << 1) | bit as u32;
+            }
+            delay.delay_us(PULSE_US);
+        }
+    });
+
+    // Shift the 24-bit value up to the top of the i32 and back down, which
+    // copies its sign bit into the top 8 bits.
+    let value = ((raw << 8) as i32) >> 8;
+    (value, timing)
+}
+
+// Writes a number of core clock cycles at sysclk_hz out in microseconds, to
+// two decimal places.
+//
+fn write_us(uart: &mut impl Write, cycles: u32, sysclk_hz: u32) {
+    let hundredths = cycles as u64 * 100_000_000 / sysclk_hz as u64;
+    write!(uart, "{}.{:02} us", hundredths / 100, hundredths % 100).ok();
+}
+
+#[entry]
+fn main() -> ! {
+    // Get peripherals.
+    //
+    // take() returns an Option, which requires handling the possibility of the
+    // return of an Err or None instead of the desired value, which is of type
+    // pac::Peripherals in this case.
+    //
+    // Since this is an embedded application, it's not as simple as writing to,
+    // stdout. This is a minimal example, so we'll drop into an inifinite loop
+    // to allow a debugger to find where the failure.
+    //
+    let device_periphs = pac::Peripherals::take().unwrap_or_else(|| {
+        loop {
+            // Failed to take pac::Peripherals.
+            asm::nop(); // If real app, replace with actual error handling.
+        }
+    });
+    let mut core_periphs = cortex_m::Peripherals::take().unwrap_or_else(|| {
+        loop {
+            // Failed to take cortex_m::Peripherals.
+            asm::nop(); // If real app, replace with actual error handling.
+        }
+    });
+
+    // Get RCC peripheral and set the system clock frequency.
+    //
+    let mut reset_and_clock_control = device_periphs.RCC.constrain();
+    let mut flash = device_periphs.FLASH.constrain();
+    let clocks = reset_and_clock_control
+        .cfgr
+        .sysclk(48.MHz())
+        .freeze(&mut flash.acr);
+    let sysclk_hz = clocks.sysclk().0;
+
+    // Start the DWT cycle counter, as in the dwt-delay example, to check the
+    // timing of the pulses against.
+    //
+    dwt_delay::init(&mut core_periphs.DCB, &mut core_periphs.DWT);
+
+    // Enable and reset TIM2, and set it counting freely at 1 MHz.
+    //
+    // With sysclk at 48 MHz, PCLK1 is 24 MHz, and the APB1 timers run at
+    // twice that, 48 MHz, so the prescaler is 48 - 1 = 47. PSC is only loaded
+    // at an update event, so UG forces one before the counter starts. ARR is
+    // already at its largest out of reset, but it's set anyway, since
+    // TimerDelay relies on it.
+    //
+    pac::TIM2::enable(&mut reset_and_clock_control.apb1);
+    pac::TIM2::reset(&mut reset_and_clock_control.apb1);
+    let tim2 = device_periphs.TIM2;
+
+    let timer_clock_hz = pac::TIM2::timer_clock(&clocks).0;
+    let prescaler = tim_delay::prescaler(timer_clock_hz, tim_delay::TICK_HZ).unwrap_or_else(|| {
+        loop {
+            // No whole prescaler gives a 1 MHz tick from this timer clock.
+            asm::nop(); // If real app, replace with actual error handling.
+        }
+    });
+    tim2.psc.write(|w| w.psc().bits(prescaler));
+    tim2.arr.write(|w| w.arr().bits(Tim2Counter::MAX));
+    tim2.egr.write(|w| w.ug().update());
+    tim2.cr1.modify(|_, w| w.cen().enabled());
+
+    let mut delay = TimerDelay::new(Tim2Counter(tim2), tim_delay::TICK_HZ);
+
+    // Get GPIO Ports C and D.
+    //
+    let mut gpioc = device_periphs.GPIOC.split(&mut reset_and_clock_control.ahb);
+    let mut gpiod = device_periphs.GPIOD.split(&mut reset_and_clock_control.ahb);
+
+    // Configure GPIO pins PC10 as TX and PC11 as RX for UART4, the same as in
+    // the uart example, and activate the UART.
+    //
+    let tx_pin = gpioc
+        .pc10
+        .into_af_push_pull(&mut gpioc.moder, &mut gpioc.otyper, &mut gpioc.afrh);
+    let rx_pin = gpioc
+        .pc11
+        .into_af_push_pull(&mut gpioc.moder, &mut gpioc.otyper, &mut gpioc.afrh);
+    let mut uart4 = Serial::new(
+        device_periphs.UART4,
+        (tx_pin, rx_pin),
+        config::Config::default().baudrate(115_200.Bd()),
+        clocks,
+        &mut reset_and_clock_control.apb1,
+    );
+
+    // Configure the pins for an HX711 load cell amplifier module.
+    //
+    // Connect its VCC to 3V, GND to GND, SCK (PD_SCK) to PD0, and DT (DOUT)
+    // to PD1. PD_SCK is held low between readings, since high for over 60 us
+    // powers the HX711 down. DOUT gets a pull-up so that, with nothing
+    // connected, it reads high, i.e. not ready, rather than floating.
+    //
+    let mut sck = gpiod
+        .pd0
+        .into_push_pull_output(&mut gpiod.moder, &mut gpiod.otyper);
+    sck.set_low().ok();
+    let dout = gpiod
+        .pd1
+        .into_pull_up_input(&mut gpiod.moder, &mut gpiod.pupdr);
+
+    writeln!(
+        uart4,
+        "Clocking an HX711 on PD0/PD1 with {} us half pulses.\r",
+        PULSE_US
+    )
+    .ok();
+
+    // Main loop.
+    //
+    // Wait for the HX711 to pull DOUT low, meaning a reading is ready, then
+    // clock it out and write it out with the shortest and longest the clock
+    // was high for. With no HX711 connected, DOUT never goes low, so the
+    // pulses are sent anyway, to check their timing on their own, with a
+    // scope on PD0 as well as with the cycle counter.
+    //
+    loop {
+        let mut waited_us = 0;
+        while dout.is_high().unwrap_or(true) && waited_us < READY_TIMEOUT_US {
+            delay.delay_us(100);
+            waited_us += 100;
+        }
+        let ready = dout.is_low().unwrap_or(false);
+
+        let (value, timing) = read_hx711(&mut sck, &dout, &mut delay);
+
+        if ready {
+            write!(uart4, "Reading: {:8}  ", value).ok();
+        } else {
+            write!(uart4, "No HX711 ready  ").ok();
+        }
+        write!(uart4, "High: ").ok();
+        write_us(&mut uart4, timing.shortest, sysclk_hz);
+        write!(uart4, " to ").ok();
+        write_us(&mut uart4, timing.longest, sysclk_hz);
+        if timing.longest > MAX_HIGH_US * (sysclk_hz / 1_000_000) {
+            write!(uart4, "  Over {} us!", MAX_HIGH_US).ok();
+        }
+        writeln!(uart4, "\r").ok();
+
+        delay.delay_ms(READ_INTERVAL_MS);
+    }
+}
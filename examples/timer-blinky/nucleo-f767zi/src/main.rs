@@ -0,0 +1,175 @@
+#![deny(unsafe_code)]
+#![no_std]
+#![no_main]
+
+// Use halt as the panicking behavior.
+//
+// A breakpoint can be set on `rust_begin_unwind` to catch panics.
+//
+use panic_halt as _;
+// use panic_abort as _; // requires nightly
+// use panic_itm as _; // logs messages over ITM; requires ITM support
+// use panic_semihosting as _; // logs messages to the host stderr; requires a debugger
+
+use core::cell::RefCell;
+
+use cortex_m::asm;
+use cortex_m::peripheral::NVIC;
+use cortex_m_rt::entry;
+use critical_section::Mutex;
+
+use stm32f7xx_hal::{
+    gpio::{Output, PushPull, PB0, PB14, PB7},
+    pac::{self, interrupt, Interrupt, TIM2},
+    prelude::*,
+    timer::{CounterHz, Event},
+};
+
+// The user LEDs LD1, LD2, and LD3 in push-pull output mode.
+//
+struct Leds {
+    ld1: PB0<Output<PushPull>>,
+    ld2: PB7<Output<PushPull>>,
+    ld3: PB14<Output<PushPull>>,
+}
+
+// The LEDs and the timer shared between main() and the TIM2 interrupt handler.
+//
+// Each is moved in from main() once set up and then only accessed from the
+// interrupt handler. The Mutex only gives access to its contents inside a
+// critical section, which is what makes sharing them with an interrupt
+// handler safe, and the RefCell allows mutating them through the shared
+// reference the Mutex hands out. They start out as None since the
+// peripherals can't be set up in a static initializer.
+//
+static G_LEDS: Mutex<RefCell<Option<Leds>>> = Mutex::new(RefCell::new(None));
+static G_TIMER: Mutex<RefCell<Option<CounterHz<TIM2>>>> = Mutex::new(RefCell::new(None));
+
+#[entry]
+fn main() -> ! {
+    // Get peripherals.
+    //
+    // take() returns an Option, which requires handling the possibility of the
+    // return of an Err or None instead of the desired value, which is of type
+    // pac::Peripherals in this case.
+    //
+    // Since this is an embedded application, it's not as simple as writing to,
+    // stdout. This is a minimal example, so we'll drop into an inifinite loop
+    // to allow a debugger to find where the failure.
+    //
+    let device_periphs = pac::Peripherals::take().unwrap_or_else(|| {
+        loop {
+            // Failed to take pac::Peripherals.
+            asm::nop(); // If real app, replace with actual error handling code.
+        }
+    });
+
+    // Get RCC peripheral.
+    //
+    // The constrain() method is used here to provide a higher-level abstraction
+    // of the peripheral rather than raw register access. The method consumes
+    // the raw peripheral and returns an instance of the RCC peripheral with
+    // higher-level safe abstractions provided by the HAL, which is of type Rcc,
+    // while setting the system clock frequency.
+    //
+    // The APB1 clock, which TIM2 hangs off of, is set to half of the system
+    // clock here. On the F7, the timers on an APB bus aren't clocked at the
+    // frequency of the bus itself. If the APB prescaler is 1, they run at the
+    // bus frequency, but for any other prescaler they run at twice the bus
+    // frequency, so TIM2 is clocked at 48 MHz rather than 24 MHz with this
+    // configuration. Setting the TIMPRE bit in RCC_DCKCFGR1 changes this again
+    // to up to four times the bus frequency, but the HAL leaves it cleared. The
+    // HAL accounts for the multiplier in clocks.timclk1() and uses it when
+    // computing the prescaler and reload values for a requested frequency, but
+    // it matters when computing them by hand from the reference manual.
+    //
+    let reset_and_clock_control = device_periphs.RCC.constrain();
+    let clocks = reset_and_clock_control
+        .cfgr
+        .sysclk(48.MHz())
+        .pclk1(24.MHz())
+        .freeze();
+
+    // Get GPIO Port B.
+    //
+    // The split method here splits out the functionality of the GPIO Port B
+    // while taking a mutable borrow of an "enabler" that enables the clock for
+    // the port at the same time. The mutable borrow allows modification of the
+    // borrowed value while ensuring exclusive access.
+    //
+    let gpiob: stm32f7xx_hal::gpio::gpiob::Parts = device_periphs.GPIOB.split();
+
+    // Create instances of the user LEDs in push-pull output mode and move them
+    // into the global for the interrupt handler.
+    //
+    // The initial state is low.
+    //
+    let leds = Leds {
+        ld1: gpiob.pb0.into_push_pull_output(),
+        ld2: gpiob.pb7.into_push_pull_output(),
+        ld3: gpiob.pb14.into_push_pull_output(),
+    };
+    critical_section::with(|cs| G_LEDS.borrow(cs).replace(Some(leds)));
+
+    // Frequency of the timer's update event, which toggles the LEDs.
+    //
+    // The LEDs toggle twice per blink, so this blinks them at 2 Hz.
+    //
+    const LED_TOGGLE_FREQ_HZ: u32 = 4;
+
+    // Set up TIM2 to generate an update interrupt at the toggle frequency and
+    // move it into the global for the interrupt handler, which needs it to
+    // clear the update flag.
+    //
+    let mut timer = device_periphs.TIM2.counter_hz(&clocks);
+    timer.start(LED_TOGGLE_FREQ_HZ.Hz()).unwrap_or_else(|_| {
+        loop {
+            // Failed to start TIM2.
+            asm::nop(); // If real app, replace with actual error handling code.
+        }
+    });
+    timer.listen(Event::Update);
+    critical_section::with(|cs| G_TIMER.borrow(cs).replace(Some(timer)));
+
+    // Unmask the TIM2 interrupt in the NVIC.
+    //
+    // The timer raising its interrupt line does nothing until the interrupt is
+    // also unmasked in the NVIC. This is done last so the handler can't run
+    // before both globals have been filled in. It's unsafe in case a critical
+    // section relies on TIM2 staying masked, but G_LEDS and G_TIMER are only
+    // ever used inside critical_section::with(), which masks with PRIMASK.
+    //
+    #[allow(unsafe_code)]
+    unsafe {
+        NVIC::unmask(Interrupt::TIM2);
+    }
+
+    // Main loop.
+    //
+    // The LEDs are toggled entirely from the interrupt handler, so there's
+    // nothing to do here but sleep until the next interrupt.
+    //
+    loop {
+        asm::wfi();
+    }
+}
+
+#[interrupt]
+fn TIM2() {
+    critical_section::with(|cs| {
+        // Clear the update flag.
+        //
+        // The flag stays set until it's cleared by software and the interrupt
+        // fires again as soon as the handler returns if it isn't cleared.
+        //
+        if let Some(timer) = G_TIMER.borrow(cs).borrow_mut().as_mut() {
+            timer.clear_interrupt(Event::Update);
+        }
+
+        if let Some(leds) = G_LEDS.borrow(cs).borrow_mut().as_mut() {
+            leds.ld1.toggle();
+            leds.ld2.toggle();
+            leds.ld3.toggle();
+        }
+    });
+}
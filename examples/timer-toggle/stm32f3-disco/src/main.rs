@@ -0,0 +1,143 @@
+#![deny(unsafe_code)]
+#![no_std]
+#![no_main]
+
+// Define the panic handler selected by the panic-* features in Cargo.toml.
+//
+// The default is panic-halt. A breakpoint can be set on `rust_begin_unwind` to
+// catch panics.
+//
+wt_support::panic_handler!();
+
+use cortex_m::asm;
+use cortex_m_rt::entry;
+
+use stm32f3xx_hal::{
+    pac,
+    prelude::*,
+    rcc::{BusTimerClock, Enable, Reset},
+};
+
+// Frequency the counter of TIM1 counts at, after the prescaler, in hertz.
+//
+const TICK_HZ: u32 = 10_000;
+
+// Number of times a second LD3 toggles, which makes it blink at half that,
+// 1 Hz.
+//
+const TOGGLE_HZ: u32 = 2;
+
+#[entry]
+fn main() -> ! {
+    // Get peripherals.
+    //
+    // take() returns an Option, which requires handling the possibility of the
+    // return of an Err or None instead of the desired value, which is of type
+    // pac::Peripherals in this case.
+    //
+    // Since this is an embedded application, it's not as simple as writing to,
+    // stdout. This is a minimal example, so we'll drop into an inifinite loop
+    // to allow a debugger to find where the failure.
+    //
+    let device_periphs = pac::Peripherals::take().unwrap_or_else(|| {
+        loop {
+            // Failed to take pac::Peripherals.
+            asm::nop(); // If real app, replace with actual error handling.
+        }
+    });
+
+    // Get RCC peripheral and set the system clock frequency.
+    //
+    let mut reset_and_clock_control = device_periphs.RCC.constrain();
+    let mut flash = device_periphs.FLASH.constrain();
+    let clocks = reset_and_clock_control
+        .cfgr
+        .sysclk(48.MHz())
+        .freeze(&mut flash.acr);
+
+    // Configure LD3's pin, PE9, as TIM1 channel 1 (alternate function 2), so
+    // the timer drives the LED rather than the GPIO's output register.
+    //
+    let mut gpioe = device_periphs.GPIOE.split(&mut reset_and_clock_control.ahb);
+    let _ld3_pin =
+        gpioe
+            .pe9
+            .into_af_push_pull::<2>(&mut gpioe.moder, &mut gpioe.otyper, &mut gpioe.afrh);
+
+    // Enable and reset TIM1.
+    //
+    pac::TIM1::enable(&mut reset_and_clock_control.apb2);
+    pac::TIM1::reset(&mut reset_and_clock_control.apb2);
+    let timer_clock_hz = pac::TIM1::timer_clock(&clocks).0;
+    let tim1 = device_periphs.TIM1;
+
+    // Set up channel 1 to toggle on every compare match.
+    //
+    // The counter counts up from 0 at the timer clock divided by PSC + 1, and
+    // when it reaches ARR, it goes back to 0 on the next tick, so it comes
+    // round once every (PSC + 1) * (ARR + 1) ticks of the timer clock. With
+    // channel 1 in output compare mode with OC1M set to toggle, its output
+    // flips each time the count passes CCR1, which happens once per lap, so:
+    //
+    // toggle_hz = timer_clock_hz / ((PSC + 1) * (ARR + 1))
+    // blink_hz = toggle_hz / 2
+    //
+    // Here, the APB2 timers run at 48 MHz, PSC = 4_799 brings that down to a
+    // 10 kHz tick, and ARR = 4_999 makes a lap 5_000 ticks, or 0.5 s, so LD3
+    // toggles twice a second, and blinks once. CCR1 can be any count from 0
+    // to ARR; it only sets where in the lap the toggle happens, not how
+    // often. Slower blinks need a bigger ARR, up to 65_535, or a bigger PSC.
+    //
+    // The rest of the setup:
+    //
+    // - CC1S is left at its reset value of 0, which makes channel 1 an
+    //   output. The PAC writes OC1M through ccmr1_output() for that reason.
+    // - CC1E in CCER connects the channel to its pin.
+    // - Writing UG in EGR loads PSC. It's buffered, and only takes effect at
+    //   an update, so without this, the first lap would run at the timer
+    //   clock undivided.
+    // - TIM1 is an advanced-control timer, whose outputs stay off until MOE
+    //   in BDTR is set, as a safety for driving motors. General-purpose timers
+    //   like TIM4 have no MOE, and this step isn't needed on them.
+    // - CEN in CR1 starts the counter.
+    //
+    tim1.psc
+        .write(|w| w.psc().bits((timer_clock_hz / TICK_HZ - 1) as u16));
+    tim1.arr
+        .write(|w| w.arr().bits((TICK_HZ / TOGGLE_HZ - 1) as u16));
+    tim1.ccr1().write(|w| w.ccr().bits(0));
+    tim1.ccmr1_output().modify(|_, w| w.oc1m().toggle());
+    tim1.ccer.modify(|_, w| w.cc1e().set_bit());
+    tim1.egr.write(|w| w.ug().update());
+    tim1.bdtr.modify(|_, w| w.moe().set_bit());
+    tim1.cr1.modify(|_, w| w.cen().enabled());
+
+    // Main loop.
+    //
+    // There's nothing left to do: the timer toggles LD3 on its own, so the
+    // core sleeps for good. No interrupt is enabled, so wfi() doesn't return,
+    // and the blink carries on regardless, with its timing set by the timer
+    // clock alone.
+    //
+    // Compare the other ways of blinking an LED in this work-through:
+    //
+    // - blinky busy-waits in a delay between toggles, so the core is running
+    //   flat out the whole time, and anything else it does stretches the
+    //   blink.
+    // - timer-blinky, on the NUCLEO-F767ZI, lets the core sleep between
+    //   toggles, but wakes it with a timer interrupt to do each toggle in
+    //   software, so the toggle is a little late by however long it takes to
+    //   get into the handler, and later still if another interrupt is
+    //   running.
+    // - Here, the core does nothing at all after setup, and the edges are
+    //   exact to the tick of the timer clock. The cost is that the LED has to
+    //   be on a pin a timer channel can reach, which all of the LEDs on PE8
+    //   to PE14 are, on TIM1's channels and complementary outputs, but LD6
+    //   on PE15 isn't, and the pattern is limited to what the timer can make
+    //   by itself: a square wave here, or PWM, as in the servo and pwm-audio
+    //   examples.
+    //
+    loop {
+        asm::wfi();
+    }
+}
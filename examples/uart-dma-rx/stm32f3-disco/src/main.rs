@@ -0,0 +1,292 @@
+#![deny(unsafe_code)]
+#![no_std]
+#![no_main]
+
+use core::fmt::Write;
+use core::sync::atomic::{compiler_fence, Ordering};
+
+// Define the panic handler selected by the panic-* features in Cargo.toml.
+//
+// The default is panic-halt. A breakpoint can be set on `rust_begin_unwind` to
+// catch panics.
+//
+wt_support::panic_handler!();
+
+use cortex_m::asm;
+use cortex_m_rt::entry;
+
+use stm32f3xx_hal::{delay::Delay, dma::Target, pac, prelude::*, serial::config, serial::Serial};
+
+// Size of the ring buffer the DMA writes received bytes into.
+//
+// This is how far the main loop can fall behind before bytes are lost. At
+// 115200 baud, a byte arrives about every 87 us, so 256 bytes is about 22 ms
+// of continuous input.
+//
+const RX_BUF_LEN: usize = 256;
+
+// Time the main loop spends on other work between checks of the buffer.
+//
+// Stands in for whatever else a real application would do. Bytes keep
+// arriving in the buffer in the meantime, without the CPU being involved.
+//
+const OTHER_WORK_MS: u16 = 10;
+
+// Length of the longest line that's kept, in bytes.
+//
+const LINE_CAPACITY: usize = 64;
+
+// Address of the receive data register of UART4 (RM0316: UART4 at
+// 0x4000_4C00, RDR at offset 0x24), where the DMA reads each byte from.
+//
+const UART4_RDR_ADDRESS: u32 = 0x4000_4C00 + 0x24;
+
+// Sets up DMA2 channel 3 to copy every byte received by UART4 into `buf`,
+// going back around to the start each time it gets to the end. Channel 3 is
+// the one hardwired to UART4 RX, per the DMA2 request mapping in the reference
+// manual (RM0316).
+//
+// The HAL only does one-shot transfers, so the channel is set up through the
+// PAC. In circular mode, the channel reloads its count of transfers left
+// (CNDTR) and goes back to the start of the buffer after the last one, without
+// being restarted, so it runs for as long as the program does.
+//
+#[allow(unsafe_code)]
+fn start_circular_rx(dma2: &pac::DMA2, buf: &'static mut [u8; RX_BUF_LEN]) {
+    let ch3 = &dma2.ch3;
+
+    // The peripheral and memory addresses aren't checked by anything, so
+    // writing them is unsafe.
+    //
+    // SAFETY: The peripheral address is that of UART4_RDR. The buffer is
+    // 'static and the reference to it is consumed here, so nothing else can
+    // write to it, and it stays valid for as long as the DMA writes to it.
+    //
+    unsafe {
+        ch3.par.write(|w| w.pa().bits(UART4_RDR_ADDRESS));
+        ch3.mar.write(|w| w.ma().bits(buf.as_mut_ptr() as u32));
+    }
+    ch3.ndtr.write(|w| w.ndt().bits(RX_BUF_LEN as u16));
+    ch3.cr.write(|w| {
+        w.dir()
+            .from_peripheral()
+            .circ()
+            .enabled()
+            .pinc()
+            .disabled()
+            .minc()
+            .enabled()
+            .psize()
+            .bits8()
+            .msize()
+            .bits8()
+            .pl()
+            .high()
+            .en()
+            .enabled()
+    });
+}
+
+// Tracks how far the main loop has read through the ring buffer.
+//
+struct RxRing {
+    buf: *const u8,
+    read_index: usize,
+}
+
+impl RxRing {
+    // Returns the index in the buffer the DMA will write the next byte to.
+    //
+    // CNDTR counts down from RX_BUF_LEN as the bytes come in, so the number of
+    // bytes written since the start of the buffer is RX_BUF_LEN - CNDTR. The
+    // channel reloads CNDTR as soon as it reaches 0, but a read can catch it
+    // at 0 just before the reload, which gives RX_BUF_LEN. That's the same
+    // place as 0 in the ring, so it's wrapped around with the modulo.
+    //
+    fn write_index(dma2: &pac::DMA2) -> usize {
+        (RX_BUF_LEN - dma2.ch3.ndtr.read().ndt().bits() as usize) % RX_BUF_LEN
+    }
+
+    // Returns the number of bytes written by the DMA but not read yet.
+    //
+    // Normally that's just the write index minus the read index. Once the DMA
+    // has wrapped back around to the start of the buffer and the main loop
+    // hasn't, the write index is behind the read index, and the bytes between
+    // them are split in two: from the read index to the end, then from the
+    // start to the write index. Adding RX_BUF_LEN before subtracting, and
+    // taking the result modulo RX_BUF_LEN, covers both cases.
+    //
+    // Equal indexes mean there's nothing new, so at most RX_BUF_LEN - 1 bytes
+    // can be told apart from an empty buffer. If the main loop falls a whole
+    // buffer or more behind, the DMA overwrites bytes that haven't been read,
+    // and the count comes out short by a multiple of RX_BUF_LEN, with nothing
+    // to show it. Sizing the buffer for the longest the main loop can be away
+    // is what keeps that from happening. The half-transfer and transfer
+    // complete flags of the channel could be used to detect it.
+    //
+    fn available(write_index: usize, read_index: usize) -> usize {
+        (write_index + RX_BUF_LEN - read_index) % RX_BUF_LEN
+    }
+
+    // Returns the next byte written by the DMA, if there is one.
+    //
+    #[allow(unsafe_code)]
+    fn pop(&mut self, dma2: &pac::DMA2) -> Option<u8> {
+        if Self::available(Self::write_index(dma2), self.read_index) == 0 {
+            return None;
+        }
+
+        // Make sure the read of the byte isn't moved ahead of the read of
+        // CNDTR that showed it's been written.
+        compiler_fence(Ordering::Acquire);
+
+        // SAFETY: The index is within the buffer, which is 'static. The read
+        // is volatile since the DMA writes to the buffer behind the compiler's
+        // back. The DMA never writes to the byte at the read index while it's
+        // counted as available, as long as it doesn't lap the main loop.
+        let byte = unsafe { self.buf.add(self.read_index).read_volatile() };
+        self.read_index = (self.read_index + 1) % RX_BUF_LEN;
+        Some(byte)
+    }
+}
+
+#[entry]
+fn main() -> ! {
+    // Get peripherals.
+    //
+    // take() returns an Option, which requires handling the possibility of the
+    // return of an Err or None instead of the desired value, which is of type
+    // pac::Peripherals in this case.
+    //
+    // Since this is an embedded application, it's not as simple as writing to,
+    // stdout. This is a minimal example, so we'll drop into an inifinite loop
+    // to allow a debugger to find where the failure.
+    //
+    let device_periphs = pac::Peripherals::take().unwrap_or_else(|| {
+        loop {
+            // Failed to take pac::Peripherals.
+            asm::nop(); // If real app, replace with actual error handling.
+        }
+    });
+    let core_periphs = cortex_m::Peripherals::take().unwrap_or_else(|| {
+        loop {
+            // Failed to take cortex_m::Peripherals.
+            asm::nop(); // If real app, replace with actual error handling.
+        }
+    });
+
+    // Enable the clock of DMA2.
+    //
+    // Since the channel is set up through the PAC rather than the HAL's
+    // split(), its clock is enabled through the PAC too, before the RCC is
+    // handed to the HAL, as in the mco example.
+    //
+    device_periphs
+        .RCC
+        .ahbenr
+        .modify(|_, w| w.dma2en().enabled());
+
+    // Get RCC peripheral and set the system clock frequency.
+    //
+    let mut reset_and_clock_control = device_periphs.RCC.constrain();
+    let mut flash = device_periphs.FLASH.constrain();
+    let clocks = reset_and_clock_control
+        .cfgr
+        .sysclk(48.MHz())
+        .freeze(&mut flash.acr);
+    let mut delay = Delay::new(core_periphs.SYST, clocks);
+
+    // Get GPIO Port C.
+    //
+    let mut gpioc = device_periphs.GPIOC.split(&mut reset_and_clock_control.ahb);
+
+    // Configure GPIO pins PC10 as TX and PC11 as RX for UART4, the same as in
+    // the uart example, and activate the UART.
+    //
+    let tx_pin = gpioc
+        .pc10
+        .into_af_push_pull(&mut gpioc.moder, &mut gpioc.otyper, &mut gpioc.afrh);
+    let rx_pin = gpioc
+        .pc11
+        .into_af_push_pull(&mut gpioc.moder, &mut gpioc.otyper, &mut gpioc.afrh);
+    let mut uart4 = Serial::new(
+        device_periphs.UART4,
+        (tx_pin, rx_pin),
+        config::Config::default().baudrate(115_200.Bd()),
+        clocks,
+        &mut reset_and_clock_control.apb1,
+    );
+
+    // Start the DMA into the ring buffer, then have the UART request a DMA
+    // transfer for each byte it receives.
+    //
+    // enable_dma() from the HAL's DMA Target trait sets both DMAR and DMAT.
+    // No channel is set up for TX, so the TX requests go nowhere, and writes
+    // to the UART from the CPU work as usual.
+    //
+    // singleton!() hands out a &'static mut to a buffer it allocates
+    // statically, once, which is what the DMA needs to write into.
+    //
+    let rx_buf = cortex_m::singleton!(: [u8; RX_BUF_LEN] = [0; RX_BUF_LEN]).unwrap_or_else(|| {
+        loop {
+            // Failed to take the receive buffer.
+            asm::nop(); // If real app, replace with actual error handling.
+        }
+    });
+    let mut ring = RxRing {
+        buf: rx_buf.as_ptr(),
+        read_index: 0,
+    };
+    let dma2 = device_periphs.DMA2;
+    start_circular_rx(&dma2, rx_buf);
+    uart4.enable_dma();
+
+    writeln!(uart4, "Send lines to have them echoed back.\r").ok();
+
+    // The line being assembled and the largest number of bytes seen waiting
+    // in the buffer so far.
+    //
+    let mut line = [0_u8; LINE_CAPACITY];
+    let mut line_len = 0;
+    let mut max_backlog = 0;
+
+    // Main loop.
+    //
+    // Drain whatever the DMA has written since the last pass, then go off and
+    // do other work for a while. The UART doesn't interrupt the CPU for each
+    // byte, or at all: the DMA moves each byte into the buffer as it arrives,
+    // and the main loop catches up in one go. A paste of a long burst of text
+    // is handled as long as no more than RX_BUF_LEN - 1 bytes of it arrive
+    // during the other work.
+    //
+    loop {
+        let backlog = RxRing::available(RxRing::write_index(&dma2), ring.read_index);
+        if backlog > max_backlog {
+            max_backlog = backlog;
+        }
+
+        while let Some(byte) = ring.pop(&dma2) {
+            if byte != b'\r' && byte != b'\n' {
+                if line_len < LINE_CAPACITY {
+                    line[line_len] = byte;
+                    line_len += 1;
+                }
+                continue;
+            }
+            if line_len == 0 {
+                continue;
+            }
+
+            let text = core::str::from_utf8(&line[..line_len]).unwrap_or("<not UTF-8>");
+            writeln!(
+                uart4,
+                "echo: {} (most bytes waiting so far: {})\r",
+                text, max_backlog
+            )
+            .ok();
+            line_len = 0;
+        }
+
+        delay.delay_ms(OTHER_WORK_MS);
+    }
+}
@@ -0,0 +1,235 @@
+#![deny(unsafe_code)]
+#![no_std]
+#![no_main]
+
+use core::fmt::{self, Write};
+
+// Define the panic handler selected by the panic-* features in Cargo.toml.
+//
+// The default is panic-halt. A breakpoint can be set on `rust_begin_unwind` to
+// catch panics.
+//
+wt_support::panic_handler!();
+
+use cortex_m::asm;
+use cortex_m_rt::entry;
+
+use stm32f3xx_hal::{
+    gpio::{gpioc, PushPull, AF5},
+    pac,
+    prelude::*,
+    rcc::{Enable, Reset},
+};
+
+use wt_support::millis;
+use wt_support::uart_errors::{self, ErrorCounts, ErrorFlags, RxRegisters, RxStatus};
+
+// Baud rate of UART4.
+//
+const BAUD: u32 = 115_200;
+
+// Time between reports of the error counts in milliseconds.
+//
+const REPORT_INTERVAL_MS: u32 = 2_000;
+
+// How long each pass of the loop takes while B1 is held, in milliseconds,
+// standing in for a program that's busy with something else. At 115200 baud,
+// over 200 bytes could arrive in that time, so anything more than one byte
+// sent at once overruns.
+//
+const BUSY_PASS_MS: u32 = 20;
+
+// UART4, set up and used through its registers, since the error flags and the
+// order they're cleared in are the point of the example.
+//
+// The HAL's Serial does check the flags in read(), but returns one error per
+// call, with no byte, and on an overrun throws away the good byte left in RDR
+// along with it, so it can't count errors that arrive together or hand on
+// that byte.
+//
+struct Uart(pac::UART4);
+
+impl RxRegisters for Uart {
+    fn status(&mut self) -> RxStatus {
+        let isr = self.0.isr.read();
+        RxStatus {
+            errors: ErrorFlags {
+                overrun: isr.ore().bit_is_set(),
+                noise: isr.nf().bit_is_set(),
+                framing: isr.fe().bit_is_set(),
+                parity: isr.pe().bit_is_set(),
+            },
+            rx_not_empty: isr.rxne().bit_is_set(),
+        }
+    }
+
+    fn read_data(&mut self) -> u8 {
+        self.0.rdr.read().rdr().bits() as u8
+    }
+
+    fn clear_errors(&mut self, errors: ErrorFlags) {
+        // ICR is write-only, and a 0 leaves a flag alone, so a write clears
+        // exactly the flags that were set.
+        self.0.icr.write(|w| {
+            w.orecf()
+                .bit(errors.overrun)
+                .ncf()
+                .bit(errors.noise)
+                .fecf()
+                .bit(errors.framing)
+                .pecf()
+                .bit(errors.parity)
+        });
+    }
+}
+
+impl fmt::Write for Uart {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        for byte in s.bytes() {
+            while self.0.isr.read().txe().bit_is_clear() {}
+            self.0.tdr.write(|w| w.tdr().bits(byte as u16));
+        }
+        Ok(())
+    }
+}
+
+#[entry]
+fn main() -> ! {
+    // Get peripherals.
+    //
+    // take() returns an Option, which requires handling the possibility of the
+    // return of an Err or None instead of the desired value, which is of type
+    // pac::Peripherals in this case.
+    //
+    // Since this is an embedded application, it's not as simple as writing to,
+    // stdout. This is a minimal example, so we'll drop into an inifinite loop
+    // to allow a debugger to find where the failure.
+    //
+    let device_periphs = pac::Peripherals::take().unwrap_or_else(|| {
+        loop {
+            // Failed to take pac::Peripherals.
+            asm::nop(); // If real app, replace with actual error handling.
+        }
+    });
+    let core_periphs = cortex_m::Peripherals::take().unwrap_or_else(|| {
+        loop {
+            // Failed to take cortex_m::Peripherals.
+            asm::nop(); // If real app, replace with actual error handling.
+        }
+    });
+
+    // Get RCC peripheral and set the system clock frequency.
+    //
+    let mut reset_and_clock_control = device_periphs.RCC.constrain();
+    let mut flash = device_periphs.FLASH.constrain();
+    let clocks = reset_and_clock_control
+        .cfgr
+        .sysclk(48.MHz())
+        .freeze(&mut flash.acr);
+
+    millis::init(core_periphs.SYST, clocks.sysclk().0);
+
+    // Get GPIO Ports A and C.
+    //
+    let mut gpioa = device_periphs.GPIOA.split(&mut reset_and_clock_control.ahb);
+    let mut gpioc = device_periphs.GPIOC.split(&mut reset_and_clock_control.ahb);
+
+    // Configure the user button B1 (PA0) as an input, as in the button-poll
+    // example. The board has a pull-down on it.
+    //
+    let button = gpioa
+        .pa0
+        .into_floating_input(&mut gpioa.moder, &mut gpioa.pupdr);
+
+    // Configure GPIO pins PC10 as TX and PC11 as RX for UART4, the same as in
+    // the uart example.
+    //
+    // Without the HAL's Serial to pick it, the alternate function has to be
+    // given in the type: AF5, UART4's on both pins.
+    //
+    let _tx_pin: gpioc::PC10<AF5<PushPull>> =
+        gpioc
+            .pc10
+            .into_af_push_pull(&mut gpioc.moder, &mut gpioc.otyper, &mut gpioc.afrh);
+    let _rx_pin: gpioc::PC11<AF5<PushPull>> =
+        gpioc
+            .pc11
+            .into_af_push_pull(&mut gpioc.moder, &mut gpioc.otyper, &mut gpioc.afrh);
+
+    // Enable and reset UART4, and set it up for 8N1 at BAUD.
+    //
+    // UART4 is clocked from PCLK1, 24 MHz with sysclk at 48 MHz, and with the
+    // default oversampling by 16, BRR is just the clock divided by the baud
+    // rate. 8 data bits, no parity, and 1 stop bit is what CR1 and CR2 are out
+    // of reset, so only the enables have to be set.
+    //
+    pac::UART4::enable(&mut reset_and_clock_control.apb1);
+    pac::UART4::reset(&mut reset_and_clock_control.apb1);
+    let uart4 = device_periphs.UART4;
+    uart4
+        .brr
+        .write(|w| w.brr().bits((clocks.pclk1().0 / BAUD) as u16));
+    uart4
+        .cr1
+        .modify(|_, w| w.ue().enabled().re().enabled().te().enabled());
+    let mut uart = Uart(uart4);
+
+    writeln!(
+        uart,
+        "Type or paste text to echo it. Hold B1 to stop checking for errors.\r"
+    )
+    .ok();
+
+    // Main loop.
+    //
+    // Normally, each pass polls UART4 with uart_errors::receive(), which
+    // counts and clears any errors and echoes the byte back. The counts are
+    // reported every REPORT_INTERVAL_MS.
+    //
+    // While B1 is held, the loop instead does what the unchecked UART code
+    // does: it reads RDR whenever RXNE is set, and ignores the error flags.
+    // Each pass also takes BUSY_PASS_MS, so sending more than a byte at once
+    // overruns. From then on, ORE stays set, RXNE never sets again, and nothing
+    // more is echoed, however much is sent: reception has silently stalled.
+    // The reports show the stall, with ORE set and the received count stuck.
+    //
+    // Letting go of B1 goes back to checking, and the first poll finds ORE,
+    // counts it, and clears it, and reception picks up again.
+    //
+    let mut counts = ErrorCounts::default();
+    let mut received: u32 = 0;
+    let mut last_report = millis::millis();
+    loop {
+        if button.is_high().unwrap_or(false) {
+            let pass_start = millis::millis();
+
+            if uart.status().rx_not_empty {
+                let byte = uart.read_data();
+                received = received.wrapping_add(1);
+                write!(uart, "{}", byte as char).ok();
+            }
+
+            while millis::elapsed_since(pass_start) < BUSY_PASS_MS {}
+        } else if let Some(byte) = uart_errors::receive(&mut uart, &mut counts) {
+            received = received.wrapping_add(1);
+            write!(uart, "{}", byte as char).ok();
+        }
+
+        if millis::elapsed_since(last_report) >= REPORT_INTERVAL_MS {
+            last_report = millis::millis();
+
+            let ore = uart.status().errors.overrun;
+            writeln!(
+                uart,
+                "\r\n[received {}, overrun {}, noise {}, framing {}, parity {}, ORE {}]\r",
+                received,
+                counts.overrun,
+                counts.noise,
+                counts.framing,
+                counts.parity,
+                if ore { "set" } else { "clear" },
+            )
+            .ok();
+        }
+    }
+}
@@ -0,0 +1,182 @@
+#![deny(unsafe_code)]
+#![no_std]
+#![no_main]
+
+use core::fmt::Write;
+
+// Define the panic handler selected by the panic-* features in Cargo.toml.
+//
+// The default is panic-halt. A breakpoint can be set on `rust_begin_unwind` to
+// catch panics.
+//
+wt_support::panic_handler!();
+
+use cortex_m::asm;
+use cortex_m_rt::entry;
+
+use stm32f3xx_hal::{pac, prelude::*, serial::config, serial::Serial};
+
+use wt_support::millis;
+use wt_support::uart_timeout::{read_with_timeout, SysTickMillis, TimeoutError};
+
+// Longest gap allowed between bytes before giving up on a line.
+//
+const INPUT_TIMEOUT_MS: u32 = 5_000;
+
+// Length of the longest line that's kept, in bytes.
+//
+const LINE_CAPACITY: usize = 64;
+
+// Number of blinks of the error LED after a timeout, and the time it spends on
+// and off for each.
+//
+const ERROR_BLINKS: u32 = 3;
+const ERROR_BLINK_MS: u32 = 100;
+
+// Waits for `ms` milliseconds on the millis tick.
+//
+// The HAL Delay can't be used here, since it would take SysTick away from the
+// millis module that the timeouts are measured against.
+//
+fn wait_ms(ms: u32) {
+    let start = millis::millis();
+    while millis::elapsed_since(start) < ms {}
+}
+
+#[entry]
+fn main() -> ! {
+    // Get peripherals.
+    //
+    // take() returns an Option, which requires handling the possibility of the
+    // return of an Err or None instead of the desired value, which is of type
+    // pac::Peripherals in this case.
+    //
+    // Since this is an embedded application, it's not as simple as writing to,
+    // stdout. This is a minimal example, so we'll drop into an inifinite loop
+    // to allow a debugger to find where the failure.
+    //
+    let device_periphs = pac::Peripherals::take().unwrap_or_else(|| {
+        loop {
+            // Failed to take pac::Peripherals.
+            asm::nop(); // If real app, replace with actual error handling.
+        }
+    });
+    let core_periphs = cortex_m::Peripherals::take().unwrap_or_else(|| {
+        loop {
+            // Failed to take cortex_m::Peripherals.
+            asm::nop(); // If real app, replace with actual error handling.
+        }
+    });
+
+    // Get RCC peripheral and set the system clock frequency.
+    //
+    let mut reset_and_clock_control = device_periphs.RCC.constrain();
+    let mut flash = device_periphs.FLASH.constrain();
+    let clocks = reset_and_clock_control
+        .cfgr
+        .sysclk(48.MHz())
+        .freeze(&mut flash.acr);
+
+    // Start the millisecond tick the timeouts are measured against.
+    //
+    millis::init(core_periphs.SYST, clocks.sysclk().0);
+
+    // Get GPIO Ports C and E.
+    //
+    let mut gpioc = device_periphs.GPIOC.split(&mut reset_and_clock_control.ahb);
+    let mut gpioe = device_periphs.GPIOE.split(&mut reset_and_clock_control.ahb);
+
+    // Configure GPIO pins PC10 as TX and PC11 as RX for UART4, the same as in
+    // the uart example, and activate the UART.
+    //
+    let tx_pin = gpioc
+        .pc10
+        .into_af_push_pull(&mut gpioc.moder, &mut gpioc.otyper, &mut gpioc.afrh);
+    let rx_pin = gpioc
+        .pc11
+        .into_af_push_pull(&mut gpioc.moder, &mut gpioc.otyper, &mut gpioc.afrh);
+    let mut uart4 = Serial::new(
+        device_periphs.UART4,
+        (tx_pin, rx_pin),
+        config::Config::default().baudrate(115_200.Bd()),
+        clocks,
+        &mut reset_and_clock_control.apb1,
+    );
+
+    // Create an instance of LD3 (PE9), the red LED at the top of the compass
+    // rose, in push-pull output mode, to show that a read timed out.
+    //
+    let mut error_led = gpioe
+        .pe9
+        .into_push_pull_output(&mut gpioe.moder, &mut gpioe.otyper);
+
+    // Main loop.
+    //
+    // Read a line, one byte at a time, with a timeout on the wait for each
+    // byte. The timeout covers the gap between bytes rather than the whole
+    // line, so a line can take as long as it likes to type, as long as it
+    // never stalls for INPUT_TIMEOUT_MS.
+    //
+    // When a read times out, whatever has been received of the line so far
+    // is thrown away rather than kept for the next line to be added to, so
+    // a line cut off partway through can't be mistaken for the start of the
+    // next one. That's the point where a real protocol would resynchronize,
+    // e.g. by waiting for the start byte of the next message.
+    //
+    let mut line = [0_u8; LINE_CAPACITY];
+    loop {
+        writeln!(
+            uart4,
+            "Type a line within {} s.\r",
+            INPUT_TIMEOUT_MS / 1_000
+        )
+        .ok();
+
+        let mut line_len = 0;
+        let result = loop {
+            match read_with_timeout(&mut uart4, INPUT_TIMEOUT_MS, &SysTickMillis) {
+                Ok(b'\r') | Ok(b'\n') if line_len > 0 => break Ok(line_len),
+                Ok(b'\r') | Ok(b'\n') => {}
+                Ok(byte) => {
+                    if line_len < LINE_CAPACITY {
+                        line[line_len] = byte;
+                        line_len += 1;
+                    }
+                }
+                Err(error) => break Err(error),
+            }
+        };
+
+        match result {
+            Ok(len) => {
+                let text = core::str::from_utf8(&line[..len]).unwrap_or("<not UTF-8>");
+                writeln!(uart4, "Got: {}\r", text).ok();
+            }
+            Err(TimeoutError::Timeout) => {
+                if line_len == 0 {
+                    writeln!(uart4, "No input.\r").ok();
+                } else {
+                    writeln!(
+                        uart4,
+                        "\r\nTimed out partway through a line; dropped {} bytes.\r",
+                        line_len
+                    )
+                    .ok();
+                }
+
+                for _ in 0..ERROR_BLINKS {
+                    error_led.set_high().ok();
+                    wait_ms(ERROR_BLINK_MS);
+                    error_led.set_low().ok();
+                    wait_ms(ERROR_BLINK_MS);
+                }
+            }
+
+            // A framing, noise, or overrun error. The byte is lost, and the
+            // line with it.
+            Err(TimeoutError::Serial(_)) => {
+                writeln!(uart4, "Receive error; dropped the line.\r").ok();
+            }
+        }
+    }
+}
@@ -0,0 +1,121 @@
+#![deny(unsafe_code)]
+#![no_std]
+#![no_main]
+
+use core::fmt::Write;
+
+// Use halt as the panicking behavior.
+//
+// A breakpoint can be set on `rust_begin_unwind` to catch panics.
+//
+use panic_halt as _;
+// use panic_abort as _; // requires nightly
+// use panic_itm as _; // logs messages over ITM; requires ITM support
+// use panic_semihosting as _; // logs messages to the host stderr; requires a debugger
+
+use cortex_m::asm;
+use cortex_m_rt::entry;
+
+use stm32f7xx_hal::{
+    pac,
+    prelude::*,
+    serial::{self, Serial},
+};
+
+#[entry]
+fn main() -> ! {
+    // Get peripherals.
+    //
+    // take() returns an Option, which requires handling the possibility of the
+    // return of an Err or None instead of the desired value, which is of type
+    // pac::Peripherals in this case.
+    //
+    // Since this is an embedded application, it's not as simple as writing to,
+    // stdout. This is a minimal example, so we'll drop into an inifinite loop
+    // to allow a debugger to find where the failure.
+    //
+    let device_periphs = pac::Peripherals::take().unwrap_or_else(|| {
+        loop {
+            // Failed to take pac::Peripherals.
+            asm::nop(); // If real app, replace with actual error handling.
+        }
+    });
+
+    // Get RCC peripheral.
+    //
+    // The constrain() method is used here to provide a higher-level abstraction
+    // of the peripheral rather than raw register access. The method consumes
+    // the raw peripheral and returns an instance of the RCC peripheral with
+    // higher-level safe abstractions provided by the HAL, which is of type Rcc,
+    // while setting the system clock frequency.
+    //
+    let reset_and_clock_control = device_periphs.RCC.constrain();
+    let clocks = reset_and_clock_control.cfgr.sysclk(48.MHz()).freeze();
+
+    // Set up delay capability.
+    //
+    // Use the same unwrap method to get the core periphs, then
+    // create a delay abstrction using SysTick (SYST).
+    //
+    let core_periphs = cortex_m::Peripherals::take().unwrap_or_else(|| {
+        loop {
+            // Failed to take cortex_m::Peripherals.
+            asm::nop(); // If real app, replace with actual error handling.
+        }
+    });
+    let mut delay = core_periphs.SYST.delay(&clocks);
+
+    // Get GPIO Port D.
+    //
+    // The split method here splits out the functionality of the GPIO Port D
+    // and enables the clock for the port at the same time.
+    //
+    let gpiod: stm32f7xx_hal::gpio::gpiod::Parts = device_periphs.GPIOD.split();
+
+    // Configure GPIO pins PD8 as TX and PD9 as RX for USART3.
+    //
+    // Both pins are put into alternate function 7, which is the USART3
+    // function for these pins. The type of the alternate function is inferred
+    // from the pins Serial::new() accepts for USART3.
+    //
+    let tx_pin = gpiod.pd8.into_alternate();
+    let rx_pin = gpiod.pd9.into_alternate();
+
+    // Activate the USART.
+    //
+    // USART3 on PD8/PD9 is wired to the ST-Link on the Nucleo board, which
+    // bridges it to a virtual COM port over the same USB cable used for
+    // flashing and debugging, so no external USB-serial adapter is needed. It
+    // shows up on the host as e.g. /dev/ttyACM0 on Linux.
+    //
+    let usart3 = Serial::new(
+        device_periphs.USART3,
+        (tx_pin, rx_pin),
+        &clocks,
+        serial::Config {
+            baud_rate: 115_200.bps(),
+            ..Default::default()
+        },
+    );
+
+    // Split the USART into its TX and RX halves.
+    //
+    // Only the TX half is used here. The formatted write support in the HAL is
+    // implemented on the TX half rather than on Serial itself.
+    //
+    let (mut tx, _rx) = usart3.split();
+
+    // Delay in milliseconds between UART writes.
+    //
+    const UART_WRITE_DELAY_MS: u32 = 2_000;
+
+    loop {
+        tx.write_str("Hello, World!\r\n").unwrap_or_else(|_| {
+            loop {
+                // Failed to write to USART3.
+                asm::nop(); // If real app, replace with actual error handling.
+            }
+        });
+        delay.delay_ms(UART_WRITE_DELAY_MS);
+    }
+}
@@ -16,7 +16,35 @@ use panic_halt as _;
 use cortex_m::asm;
 use cortex_m_rt::entry;
 
-use stm32f3xx_hal::{delay::Delay, pac, prelude::*, serial::config, serial::Serial};
+use stm32f3xx_hal::{block, nb, pac, prelude::*, serial::config, serial::Serial};
+
+use wt_support::millis;
+
+// Baud rate the UART starts at after a reset.
+//
+const DEFAULT_BAUD: u32 = 115_200;
+
+// Baud rates accepted by the baud command.
+//
+// The UART can generate almost any rate from its clock, but a terminal
+// program usually only offers the standard ones, so only those are accepted.
+//
+const SUPPORTED_BAUDS: [u32; 8] = [
+    9_600, 19_200, 38_400, 57_600, 115_200, 230_400, 460_800, 921_600,
+];
+
+// Length of the longest command that can be received, in bytes.
+//
+const COMMAND_CAPACITY: usize = 32;
+
+// Parses a "baud <rate>" command, returning the rate if it's one of the
+// supported ones.
+//
+fn parse_baud_command(command: &[u8]) -> Option<u32> {
+    let command = core::str::from_utf8(command).ok()?.trim();
+    let rate = command.strip_prefix("baud ")?.trim().parse().ok()?;
+    SUPPORTED_BAUDS.contains(&rate).then_some(rate)
+}
 
 #[entry]
 fn main() -> ! {
@@ -52,10 +80,11 @@ fn main() -> ! {
         .sysclk(48.MHz())
         .freeze(&mut flash.acr);
 
-    // Set up delay capability.
+    // Start the millisecond tick.
     //
-    // Use the same unwrap method to get the core periphs, then
-    // create a delay abstrction using SysTick (SYST).
+    // The main loop has to keep checking for received bytes, so it can't block
+    // in a delay between writes. The millis tick counter from wt-support is used
+    // instead to time the writes without blocking.
     //
     let core_periphs = cortex_m::Peripherals::take().unwrap_or_else(|| {
         loop {
@@ -63,7 +92,7 @@ fn main() -> ! {
             asm::nop(); // If real app, replace with actual error handling.
         }
     });
-    let mut delay = Delay::new(core_periphs.SYST, clocks);
+    millis::init(core_periphs.SYST, clocks.sysclk().0);
 
     // Get GPIO Port C.
     //
@@ -87,22 +116,135 @@ fn main() -> ! {
     let mut uart4 = Serial::new(
         device_periphs.UART4,
         (tx_pin, rx_pin),
-        config::Config::default().baudrate(115_200.Bd()),
+        config::Config::default().baudrate(DEFAULT_BAUD.Bd()),
         clocks,
         &mut reset_and_clock_control.apb1,
     );
 
     // Delay in milliseconds between UART writes.
     //
-    const UART_WRITE_DELAY_MS: u16 = 2_000;
+    const UART_WRITE_DELAY_MS: u32 = 2_000;
+
+    // Bytes of the command received so far.
+    //
+    let mut command = [0_u8; COMMAND_CAPACITY];
+    let mut command_len = 0;
+
+    let mut last_write = millis::millis();
 
+    // Main loop.
+    //
+    // Write "Hello, World!" every UART_WRITE_DELAY_MS, and in between, read
+    // and echo back any received bytes, collecting them into a command until
+    // the end of a line.
+    //
+    // Sending "baud <rate>", e.g. "baud 9600", switches the UART to that baud
+    // rate.
+    //
     loop {
-        uart4.write_str("Hello, World!\r\n").unwrap_or_else(|_| {
-            loop {
-                // Failed to write to UART4.
-                asm::nop(); // If real app, replace with actual error handling.
+        if millis::elapsed_since(last_write) >= UART_WRITE_DELAY_MS {
+            last_write = last_write.wrapping_add(UART_WRITE_DELAY_MS);
+            uart4.write_str("Hello, World!\r\n").unwrap_or_else(|_| {
+                loop {
+                    // Failed to write to UART4.
+                    asm::nop(); // If real app, replace with actual error handling.
+                }
+            });
+        }
+
+        let byte = match uart4.read() {
+            Ok(byte) => byte,
+
+            // Nothing has been received.
+            Err(nb::Error::WouldBlock) => continue,
+
+            // A framing, noise, or overrun error. A framing error in
+            // particular is what garbage from the other end sending at a
+            // different baud rate looks like, so drop the partial command
+            // rather than trying to make sense of it.
+            Err(nb::Error::Other(_)) => {
+                command_len = 0;
+                continue;
+            }
+        };
+
+        if byte != b'\r' && byte != b'\n' {
+            // Echo the byte back, since most terminals don't show what's
+            // typed, and add it to the command. A command too long to be
+            // valid is dropped at the end of the line.
+            block!(uart4.write(byte)).ok();
+            if command_len < COMMAND_CAPACITY {
+                command[command_len] = byte;
             }
-        });
-        delay.delay_ms(UART_WRITE_DELAY_MS);
+            command_len += 1;
+            continue;
+        }
+
+        // The end of a line. Ignore empty lines, which a terminal sending
+        // "\r\n" for Enter produces after every command.
+        if command_len == 0 {
+            continue;
+        }
+        uart4.write_str("\r\n").ok();
+
+        let rate = if command_len <= COMMAND_CAPACITY {
+            parse_baud_command(&command[..command_len])
+        } else {
+            None
+        };
+        command_len = 0;
+
+        let rate = match rate {
+            Some(rate) => rate,
+            None => {
+                write!(
+                    uart4,
+                    "Unknown command. Send \"baud <rate>\" with one of {:?}.\r\n",
+                    SUPPORTED_BAUDS
+                )
+                .ok();
+                continue;
+            }
+        };
+
+        // Confirm the change at the old baud rate.
+        //
+        // This has to go out before the switch, since the terminal on the
+        // other end is still listening at the old rate. It also has to have
+        // actually finished going out: write() only waits for room in the
+        // transmit data register, so the last byte or two are still being
+        // shifted out when it returns. flush() waits for the transmission
+        // complete flag, after which it's safe to stop the UART.
+        //
+        write!(
+            uart4,
+            "Switching to {} baud. Switch the terminal to match.\r\n",
+            rate
+        )
+        .ok();
+        block!(uart4.flush()).ok();
+
+        // Rebuild the UART at the new baud rate.
+        //
+        // The baud rate is set when a Serial is created, and the HAL doesn't
+        // provide a way to change it on an existing one. Instead, free()
+        // disables the UART and hands back the peripheral and the pins, which
+        // are then passed to Serial::new() again with the new config. The
+        // result is the same type as before, so it can replace uart4 in place.
+        //
+        // Nothing on the other end changes automatically. The terminal program
+        // has to be switched to the new baud rate as well, otherwise it will
+        // show garbage, and there's no way for the board to tell. Pressing the
+        // reset button gets back to DEFAULT_BAUD.
+        //
+        let (usart, pins) = uart4.free();
+        uart4 = Serial::new(
+            usart,
+            pins,
+            config::Config::default().baudrate(rate.Bd()),
+            clocks,
+            &mut reset_and_clock_control.apb1,
+        );
+        write!(uart4, "Now at {} baud.\r\n", rate).ok();
     }
 }
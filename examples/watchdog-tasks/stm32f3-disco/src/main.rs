@@ -0,0 +1,196 @@
+#![deny(unsafe_code)]
+#![no_std]
+#![no_main]
+
+use core::fmt::Write;
+
+// Define the panic handler selected by the panic-* features in Cargo.toml.
+//
+// The default is panic-halt. A breakpoint can be set on `rust_begin_unwind` to
+// catch panics.
+//
+wt_support::panic_handler!();
+
+use cortex_m::asm;
+use cortex_m_rt::entry;
+
+use stm32f3xx_hal::{
+    pac, prelude::*, serial::config, serial::Serial, watchdog::IndependentWatchDog,
+};
+
+use wt_support::millis;
+use wt_support::reset_reason::ResetReason;
+use wt_support::task_watchdog::TaskWatchdog;
+
+// Numbers of the tasks checking in with the task watchdog.
+//
+const LED_TASK: u32 = 0;
+const HEARTBEAT_TASK: u32 = 1;
+const TASK_COUNT: u32 = 2;
+
+// Intervals the tasks run at.
+//
+const LED_TOGGLE_INTERVAL_MS: u32 = 250;
+const HEARTBEAT_INTERVAL_MS: u32 = 1_000;
+
+// Timeout of the IWDG.
+//
+// The watchdog is only fed once both tasks have checked in, so a feed can be
+// up to the longest interval between check-ins, HEARTBEAT_INTERVAL_MS, after
+// the last one. The timeout is twice that to leave a margin, since the LSI the
+// IWDG runs on can be fast by as much as a quarter.
+//
+const WATCHDOG_TIMEOUT_MS: u32 = 2 * HEARTBEAT_INTERVAL_MS;
+
+#[entry]
+fn main() -> ! {
+    // Get peripherals.
+    //
+    // take() returns an Option, which requires handling the possibility of the
+    // return of an Err or None instead of the desired value, which is of type
+    // pac::Peripherals in this case.
+    //
+    // Since this is an embedded application, it's not as simple as writing to,
+    // stdout. This is a minimal example, so we'll drop into an inifinite loop
+    // to allow a debugger to find where the failure.
+    //
+    let device_periphs = pac::Peripherals::take().unwrap_or_else(|| {
+        loop {
+            // Failed to take pac::Peripherals.
+            asm::nop(); // If real app, replace with actual error handling.
+        }
+    });
+    let core_periphs = cortex_m::Peripherals::take().unwrap_or_else(|| {
+        loop {
+            // Failed to take cortex_m::Peripherals.
+            asm::nop(); // If real app, replace with actual error handling.
+        }
+    });
+
+    // Read why the chip was reset, as in the reset-reason example, to show
+    // when the watchdog has caught a stalled task.
+    //
+    let reason = ResetReason::from_csr(device_periphs.RCC.csr.read().bits());
+    device_periphs.RCC.csr.modify(|_, w| w.rmvf().set_bit());
+
+    // Get RCC peripheral and set the system clock frequency.
+    //
+    let mut reset_and_clock_control = device_periphs.RCC.constrain();
+    let mut flash = device_periphs.FLASH.constrain();
+    let clocks = reset_and_clock_control
+        .cfgr
+        .sysclk(48.MHz())
+        .freeze(&mut flash.acr);
+
+    // Start the millisecond tick the tasks are scheduled on.
+    //
+    millis::init(core_periphs.SYST, clocks.sysclk().0);
+
+    // Get GPIO Ports C and E.
+    //
+    let mut gpioc = device_periphs.GPIOC.split(&mut reset_and_clock_control.ahb);
+    let mut gpioe = device_periphs.GPIOE.split(&mut reset_and_clock_control.ahb);
+
+    // Configure GPIO pins PC10 as TX and PC11 as RX for UART4, the same as in
+    // the uart example, and activate the UART.
+    //
+    let tx_pin = gpioc
+        .pc10
+        .into_af_push_pull(&mut gpioc.moder, &mut gpioc.otyper, &mut gpioc.afrh);
+    let rx_pin = gpioc
+        .pc11
+        .into_af_push_pull(&mut gpioc.moder, &mut gpioc.otyper, &mut gpioc.afrh);
+    let mut uart4 = Serial::new(
+        device_periphs.UART4,
+        (tx_pin, rx_pin),
+        config::Config::default().baudrate(115_200.Bd()),
+        clocks,
+        &mut reset_and_clock_control.apb1,
+    );
+
+    // Create an instance of LD3 (PE9) in push-pull output mode.
+    //
+    let mut led = gpioe
+        .pe9
+        .into_push_pull_output(&mut gpioe.moder, &mut gpioe.otyper);
+
+    writeln!(uart4, "Reset reason: {:?}\r", reason).ok();
+    if reason == ResetReason::IndependentWatchdog {
+        writeln!(uart4, "The watchdog caught a stalled task.\r").ok();
+    }
+    writeln!(uart4, "Send h to hang the heartbeat task.\r").ok();
+
+    // Start the IWDG.
+    //
+    // Once started, the IWDG can't be stopped by anything short of a reset,
+    // so from here on, it has to be fed within WATCHDOG_TIMEOUT_MS every time.
+    //
+    let mut iwdg = IndependentWatchDog::new(device_periphs.IWDG);
+    iwdg.start(WATCHDOG_TIMEOUT_MS.milliseconds());
+
+    let mut task_watchdog = TaskWatchdog::new(TASK_COUNT);
+
+    // State of each of the tasks.
+    //
+    let mut last_toggle = millis::millis();
+    let mut last_heartbeat = millis::millis();
+    let mut heartbeat_count: u32 = 0;
+    let mut heartbeat_hung = false;
+
+    // Main loop.
+    //
+    // Each pass gives every task a turn. A task checks whether it has
+    // anything to do, and if so, does it and returns to the loop straight
+    // away rather than waiting, so that no task holds up the others. Each one
+    // checks in with the task watchdog every time it completes a step, and
+    // the IWDG is only fed once both have checked in since the last feed.
+    //
+    // A naive loop would feed the IWDG on every pass instead, which only
+    // catches the whole loop hanging. Sending h makes the heartbeat task stop
+    // doing its work and checking in, as if it were stuck waiting for
+    // something that never happens, while the loop and the LED task carry on
+    // as before. A feed on every pass would keep going regardless and the
+    // dead heartbeat would never be noticed. Here, the heartbeat's bit is
+    // never set, so the IWDG isn't fed again and resets the chip within
+    // WATCHDOG_TIMEOUT_MS, with the LED still blinking up to the end.
+    //
+    loop {
+        // LED task: Toggle LD3 every LED_TOGGLE_INTERVAL_MS.
+        //
+        if millis::elapsed_since(last_toggle) >= LED_TOGGLE_INTERVAL_MS {
+            last_toggle = last_toggle.wrapping_add(LED_TOGGLE_INTERVAL_MS);
+            led.toggle().ok();
+            task_watchdog.check_in(LED_TASK);
+        }
+
+        // Heartbeat task: Write a count out every HEARTBEAT_INTERVAL_MS,
+        // unless it has been hung.
+        //
+        if !heartbeat_hung && millis::elapsed_since(last_heartbeat) >= HEARTBEAT_INTERVAL_MS {
+            last_heartbeat = last_heartbeat.wrapping_add(HEARTBEAT_INTERVAL_MS);
+            heartbeat_count += 1;
+            writeln!(uart4, "Heartbeat {}\r", heartbeat_count).ok();
+            task_watchdog.check_in(HEARTBEAT_TASK);
+        }
+
+        // Check for the command to hang the heartbeat task, without waiting
+        // for it.
+        //
+        match uart4.read() {
+            Ok(b'h') if !heartbeat_hung => {
+                heartbeat_hung = true;
+                writeln!(
+                    uart4,
+                    "Heartbeat task hung. Reset within {} ms.\r",
+                    WATCHDOG_TIMEOUT_MS
+                )
+                .ok();
+            }
+            _ => {}
+        }
+
+        if task_watchdog.should_feed() {
+            iwdg.feed();
+        }
+    }
+}
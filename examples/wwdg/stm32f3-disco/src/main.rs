@@ -0,0 +1,202 @@
+#![deny(unsafe_code)]
+#![no_std]
+#![no_main]
+
+use core::fmt::Write;
+
+// Define the panic handler selected by the panic-* features in Cargo.toml.
+//
+// The default is panic-halt. A breakpoint can be set on `rust_begin_unwind` to
+// catch panics.
+//
+wt_support::panic_handler!();
+
+use cortex_m::asm;
+use cortex_m_rt::entry;
+
+use stm32f3xx_hal::{
+    block, pac,
+    prelude::*,
+    rcc::{Enable, Reset},
+    serial::config,
+    serial::Serial,
+};
+
+use wt_support::millis;
+use wt_support::reset_reason::ResetReason;
+
+// Value the WWDG counter is reloaded with on every feed, its highest.
+//
+// The counter is 7 bits, and counts down from here. The chip is reset when it
+// goes from 0x40 to 0x3F, i.e. when bit 6 clears, so it can count 64 ticks
+// after a feed before the reset.
+//
+const COUNTER_RELOAD: u8 = 0x7F;
+
+// Top of the window the WWDG can be fed in.
+//
+// Feeding it while the counter is still above this is also a reset. With the
+// reload at 0x7F, the counter reaches it 0x7F - 0x5F = 32 ticks after a feed,
+// halfway to the reset.
+//
+const WINDOW: u8 = 0x5F;
+
+// Time between feeds in milliseconds.
+//
+// The WWDG is clocked from PCLK1, divided by 4096 and then by 8 with the
+// WDGTB prescaler set below, so with PCLK1 at 24 MHz, a tick of its counter is
+// 4096 * 8 / 24 MHz = 1.365 ms. Feeding is then allowed from 32 ticks after
+// the last feed, 43.7 ms, to 64 ticks after it, 87.4 ms. 60 ms is comfortably
+// inside that.
+//
+const FEED_INTERVAL_MS: u32 = 60;
+
+// Too early: fed before the window opens, which resets the chip on the first
+// feed.
+//
+// const FEED_INTERVAL_MS: u32 = 20;
+
+// Too late: the counter runs down past 0x40 before the feed, which resets the
+// chip just as it would if the program had hung.
+//
+// const FEED_INTERVAL_MS: u32 = 100;
+
+// Number of feeds per toggle of the LED, for a blink every half second or so.
+//
+const FEEDS_PER_TOGGLE: u32 = 8;
+
+#[entry]
+fn main() -> ! {
+    // Get peripherals.
+    //
+    // take() returns an Option, which requires handling the possibility of the
+    // return of an Err or None instead of the desired value, which is of type
+    // pac::Peripherals in this case.
+    //
+    // Since this is an embedded application, it's not as simple as writing to,
+    // stdout. This is a minimal example, so we'll drop into an inifinite loop
+    // to allow a debugger to find where the failure.
+    //
+    let device_periphs = pac::Peripherals::take().unwrap_or_else(|| {
+        loop {
+            // Failed to take pac::Peripherals.
+            asm::nop(); // If real app, replace with actual error handling.
+        }
+    });
+    let core_periphs = cortex_m::Peripherals::take().unwrap_or_else(|| {
+        loop {
+            // Failed to take cortex_m::Peripherals.
+            asm::nop(); // If real app, replace with actual error handling.
+        }
+    });
+
+    // Read why the chip was reset, as in the reset-reason example, to show
+    // when the WWDG has reset it.
+    //
+    let reason = ResetReason::from_csr(device_periphs.RCC.csr.read().bits());
+    device_periphs.RCC.csr.modify(|_, w| w.rmvf().set_bit());
+
+    // Get RCC peripheral and set the system clock frequency.
+    //
+    // PCLK1 is set explicitly, since the timing of the WWDG depends on it.
+    //
+    let mut reset_and_clock_control = device_periphs.RCC.constrain();
+    let mut flash = device_periphs.FLASH.constrain();
+    let clocks = reset_and_clock_control
+        .cfgr
+        .sysclk(48.MHz())
+        .pclk1(24.MHz())
+        .freeze(&mut flash.acr);
+
+    // Start the millisecond tick for timing the feeds.
+    //
+    millis::init(core_periphs.SYST, clocks.sysclk().0);
+
+    // Get GPIO Ports C and E.
+    //
+    let mut gpioc = device_periphs.GPIOC.split(&mut reset_and_clock_control.ahb);
+    let mut gpioe = device_periphs.GPIOE.split(&mut reset_and_clock_control.ahb);
+
+    // Configure GPIO pins PC10 as TX and PC11 as RX for UART4, the same as in
+    // the uart example, and activate the UART.
+    //
+    let tx_pin = gpioc
+        .pc10
+        .into_af_push_pull(&mut gpioc.moder, &mut gpioc.otyper, &mut gpioc.afrh);
+    let rx_pin = gpioc
+        .pc11
+        .into_af_push_pull(&mut gpioc.moder, &mut gpioc.otyper, &mut gpioc.afrh);
+    let mut uart4 = Serial::new(
+        device_periphs.UART4,
+        (tx_pin, rx_pin),
+        config::Config::default().baudrate(115_200.Bd()),
+        clocks,
+        &mut reset_and_clock_control.apb1,
+    );
+
+    // Create an instance of LD3 (PE9) in push-pull output mode.
+    //
+    let mut led = gpioe
+        .pe9
+        .into_push_pull_output(&mut gpioe.moder, &mut gpioe.otyper);
+
+    writeln!(uart4, "Reset reason: {:?}\r", reason).ok();
+    if reason == ResetReason::WindowWatchdog {
+        writeln!(uart4, "The WWDG reset the chip.\r").ok();
+    }
+    writeln!(uart4, "Feeding the WWDG every {} ms.\r", FEED_INTERVAL_MS).ok();
+
+    // Wait for the messages to go out before starting the WWDG, since from
+    // then on, there's only so long between feeds.
+    //
+    block!(uart4.flush()).ok();
+
+    // Start the WWDG.
+    //
+    // The IWDG of the watchdog-tasks example only resets the chip if it isn't
+    // fed for too long, which catches code that's stuck. The WWDG also resets
+    // it if it's fed too soon, before the window opens. That catches code that
+    // runs too fast as well: a loop that's skipping its work, or that's gone
+    // wrong in a way that happens to call the feed over and over. An IWDG is
+    // happy with anything that feeds it often enough.
+    //
+    // The other differences:
+    //
+    // - The WWDG is clocked from PCLK1, so it's exact, but stops if the clock
+    //   fails, while the IWDG has the LSI all to itself.
+    // - The WWDG's timeouts are short: 87.4 ms at most here, and around 350 ms
+    //   even with PCLK1 as slow as 6 MHz. The IWDG goes up to seconds.
+    // - The WWDG can raise an early wakeup interrupt at 0x40, just before the
+    //   reset, e.g. to save state. It isn't used here.
+    //
+    // Like the IWDG, once WDGA is set, only a reset stops the WWDG.
+    //
+    pac::WWDG::enable(&mut reset_and_clock_control.apb1);
+    pac::WWDG::reset(&mut reset_and_clock_control.apb1);
+    let wwdg = device_periphs.WWDG;
+    wwdg.cfr.write(|w| w.wdgtb().div8().w().bits(WINDOW));
+    wwdg.cr
+        .write(|w| w.t().bits(COUNTER_RELOAD).wdga().enabled());
+
+    // Main loop.
+    //
+    // Feed the WWDG every FEED_INTERVAL_MS, by writing the counter back to
+    // COUNTER_RELOAD, and toggle LD3 every FEEDS_PER_TOGGLE feeds, so it keeps
+    // blinking as long as the feeds land in the window. With either of the
+    // other values of FEED_INTERVAL_MS above, the chip resets instead, and
+    // comes back up reporting a WindowWatchdog reset.
+    //
+    let mut last_feed = millis::millis();
+    let mut feeds = 0_u32;
+    loop {
+        if millis::elapsed_since(last_feed) >= FEED_INTERVAL_MS {
+            last_feed = millis::millis();
+            wwdg.cr.write(|w| w.t().bits(COUNTER_RELOAD));
+
+            feeds += 1;
+            if feeds.is_multiple_of(FEEDS_PER_TOGGLE) {
+                led.toggle().ok();
+            }
+        }
+    }
+}
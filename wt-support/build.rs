@@ -0,0 +1,97 @@
+//! This build script captures where the firmware was built from and when, for
+//! `wt_support::version`.
+//!
+//! It runs on the host before wt-support is compiled, and passes two values
+//! to the compiler as environment variables, which the module reads with
+//! `env!()`:
+//!
+//! - `WT_GIT_HASH`: The short hash of the commit checked out, from
+//!   `git rev-parse --short HEAD`, or `unknown` if that fails, e.g. when
+//!   building from a source archive or without git installed.
+//! - `WT_BUILD_TIME`: The time of the build in UTC, as e.g.
+//!   `2024-01-31T12:00:00Z`. If `SOURCE_DATE_EPOCH` is set, the time is taken
+//!   from it instead, so that a build can be repeated to give the same binary.
+
+use std::env;
+use std::process::Command;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+fn main() {
+    println!("cargo:rustc-env=WT_GIT_HASH={}", git_hash());
+    println!("cargo:rustc-env=WT_BUILD_TIME={}", build_time());
+
+    // A build script is only re-run when something it depends on changes, so
+    // tell Cargo to re-run it when HEAD moves, whether it's switched to
+    // another branch or the branch it's on gets a new commit. Without these,
+    // the hash would stay at whatever it was on the first build.
+    //
+    // Note that the build time is only updated when it's re-run, too, so it's
+    // the time of the last build after one of these changed, not of every
+    // build.
+    if let Some(git_dir) = git(&["rev-parse", "--git-dir"]) {
+        println!("cargo:rerun-if-changed={}/HEAD", git_dir);
+        if let Some(branch) = git(&["symbolic-ref", "-q", "HEAD"]) {
+            println!("cargo:rerun-if-changed={}/{}", git_dir, branch);
+        }
+    }
+    println!("cargo:rerun-if-changed=build.rs");
+    println!("cargo:rerun-if-env-changed=SOURCE_DATE_EPOCH");
+}
+
+/// Returns the trimmed output of git run with `args`, or `None` if it
+/// couldn't be run or failed.
+fn git(args: &[&str]) -> Option<String> {
+    let output = Command::new("git").args(args).output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    let text = String::from_utf8(output.stdout).ok()?;
+    Some(text.trim().to_string())
+}
+
+fn git_hash() -> String {
+    git(&["rev-parse", "--short", "HEAD"])
+        .filter(|hash| !hash.is_empty())
+        .unwrap_or_else(|| "unknown".to_string())
+}
+
+fn build_time() -> String {
+    let secs = env::var("SOURCE_DATE_EPOCH")
+        .ok()
+        .and_then(|s| s.trim().parse::<u64>().ok())
+        .unwrap_or_else(|| {
+            SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_secs())
+                .unwrap_or(0)
+        });
+
+    let (year, month, day) = civil_from_days((secs / 86_400) as i64);
+    let time_of_day = secs % 86_400;
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+        year,
+        month,
+        day,
+        time_of_day / 3_600,
+        time_of_day / 60 % 60,
+        time_of_day % 60,
+    )
+}
+
+/// Converts a count of days since 1970-01-01 to a year, month, and day, with
+/// Howard Hinnant's `civil_from_days` algorithm, to save depending on a date
+/// crate for one timestamp.
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let day_of_era = z.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1_460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = (day_of_year - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
@@ -0,0 +1,427 @@
+//! Single ADC conversions that report faults instead of bogus readings.
+//!
+//! The ADC examples assume that calibration finishes and every conversion
+//! gives a real reading. [`Adc`] checks both: [`Adc::calibrate`] runs the
+//! self-calibration at startup and fails with an error if the ADC never
+//! finishes it or never becomes ready, and [`Adc::read`] refuses to convert
+//! on an ADC that wasn't calibrated, and turns a conversion of all ones,
+//! which is what an input at or above VREF+ reads, into
+//! [`AdcError::Saturated`]. Every step that waits on the hardware gives up
+//! after [`POLL_LIMIT`] polls, rather than hanging the program.
+//!
+//! ```ignore
+//! let mut adc = Adc::new(F3Adc(adc1), Resolution::Bits12);
+//! if let Err(e) = adc.calibrate(&mut delay) {
+//!     // Report it, and don't trust readings from this ADC.
+//! }
+//! match adc.read(2) {
+//!     Ok(raw) => {}
+//!     Err(AdcError::Saturated) => {} // At or over full scale.
+//!     Err(e) => {}
+//! }
+//! ```
+//!
+//! The registers are reached through [`AdcHw`], one method per step, so the
+//! sequencing and the checks are here and can be tested on the host, while an
+//! implementation for a particular ADC is a handful of register accesses, as
+//! in the adc-check example.
+//!
+//! # F3 calibration sequence
+//!
+//! The ADCs of the STM32F303 calibrate themselves to cancel their offset, and
+//! have to be calibrated after every power-up before they're used (RM0316,
+//! 15.3.8). The sequence, and the [`AdcHw`] method for each step, is:
+//!
+//! 1. [`AdcHw::enable_regulator`]: Take ADVREGEN in ADC_CR from its reset
+//!    state, `0b10`, through the intermediate state, `0b00`, to enabled,
+//!    `0b01`. The regulator then needs `T_ADCVREG_STUP`, 10 us, to start up,
+//!    which [`Adc::calibrate`] waits out with its delay.
+//! 2. [`AdcHw::start_calibration`]: With the ADC disabled (ADEN = 0), set
+//!    ADCALDIF for the kind of inputs being calibrated for, 0 for
+//!    single-ended, and set ADCAL to start.
+//! 3. [`AdcHw::is_calibrating`]: ADCAL stays set until the calibration is
+//!    done, about 112 ADC clock cycles, then the hardware clears it. The
+//!    factor it found is left in ADC_CALFACT, and applied to every conversion
+//!    from then on.
+//! 4. [`AdcHw::enable`]: Set ADEN, at least 4 ADC clock cycles after ADCAL
+//!    cleared.
+//! 5. [`AdcHw::is_ready`]: Wait for ADRDY in ADC_ISR, then clear it by
+//!    writing 1 to it.
+//!
+//! ADCAL that never clears usually means the ADC has no clock. CKMODE in
+//! ADC12_CCR at its reset value picks the asynchronous clock from the RCC,
+//! which is off until ADC12PRES in RCC_CFGR2 is set, so one or the other has
+//! to be set up first. ADRDY that never sets usually means the regulator
+//! wasn't enabled.
+//!
+//! # Saturation
+//!
+//! Any input from VREF+ up converts to the largest value the resolution has,
+//! 4095 at 12 bits, so a reading of all ones only says that the input was
+//! somewhere at or over full scale, not where. It's reported as an error so
+//! it can't pass for a real reading. A reading of 0 isn't, since 0 V is an
+//! everyday input, e.g. a potentiometer turned to the end, that reads
+//! exactly 0.
+
+use embedded_hal::blocking::delay::DelayUs;
+
+/// How many times a flag is polled before giving up on the ADC.
+///
+/// At 48 MHz, this is a few milliseconds, hundreds of times longer than the
+/// slowest step, a conversion at the longest sampling time.
+pub const POLL_LIMIT: u32 = 100_000;
+
+/// Time the ADC's voltage regulator takes to start up in microseconds,
+/// `T_ADCVREG_STUP` (DS9118).
+pub const REGULATOR_STARTUP_US: u32 = 10;
+
+/// Errors from calibrating the ADC or reading from it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AdcError {
+    /// The calibration didn't finish within [`POLL_LIMIT`] polls.
+    CalibrationTimeout,
+    /// The ADC didn't become ready within [`POLL_LIMIT`] polls of being
+    /// enabled.
+    ReadyTimeout,
+    /// The ADC hasn't been calibrated, or calibrating it failed.
+    NotCalibrated,
+    /// A conversion didn't finish within [`POLL_LIMIT`] polls.
+    ConversionTimeout,
+    /// The conversion was all ones, so the input is at or over full scale.
+    Saturated,
+}
+
+/// The resolution of the conversions.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Resolution {
+    /// 12 bits, up to 4095.
+    Bits12,
+    /// 10 bits, up to 1023.
+    Bits10,
+    /// 8 bits, up to 255.
+    Bits8,
+    /// 6 bits, up to 63.
+    Bits6,
+}
+
+impl Resolution {
+    /// Returns the largest value a conversion can have, all ones.
+    pub fn full_scale(self) -> u16 {
+        match self {
+            Resolution::Bits12 => 0x0FFF,
+            Resolution::Bits10 => 0x03FF,
+            Resolution::Bits8 => 0x00FF,
+            Resolution::Bits6 => 0x003F,
+        }
+    }
+}
+
+/// Checks a raw, right-aligned conversion.
+///
+/// Returns the value if it's a real reading, and
+/// [`AdcError::Saturated`] if it's all ones at `resolution`.
+pub fn classify(raw: u16, resolution: Resolution) -> Result<u16, AdcError> {
+    if raw >= resolution.full_scale() {
+        Err(AdcError::Saturated)
+    } else {
+        Ok(raw)
+    }
+}
+
+/// The register accesses of each step of calibrating an ADC and converting
+/// with it.
+///
+/// See the [module documentation](self) for what each does on the F3.
+pub trait AdcHw {
+    /// Enables the ADC's voltage regulator.
+    fn enable_regulator(&mut self);
+
+    /// Starts a calibration of the disabled ADC.
+    fn start_calibration(&mut self);
+
+    /// Returns whether the calibration is still running.
+    fn is_calibrating(&mut self) -> bool;
+
+    /// Enables the ADC.
+    fn enable(&mut self);
+
+    /// Returns whether the ADC is ready, clearing the flag if it is.
+    fn is_ready(&mut self) -> bool;
+
+    /// Starts a single conversion of `channel`.
+    fn start_conversion(&mut self, channel: u8);
+
+    /// Returns the conversion if it's finished.
+    fn conversion(&mut self) -> Option<u16>;
+}
+
+/// An ADC that has to be calibrated before reading from it.
+pub struct Adc<H> {
+    hw: H,
+    resolution: Resolution,
+    calibrated: bool,
+}
+
+impl<H: AdcHw> Adc<H> {
+    /// Creates an ADC converting at `resolution`, which the hardware has to
+    /// be set up for already.
+    ///
+    /// It can't be read from until [`Self::calibrate`] has succeeded.
+    pub fn new(hw: H, resolution: Resolution) -> Self {
+        Adc {
+            hw,
+            resolution,
+            calibrated: false,
+        }
+    }
+
+    /// Polls `done` until it returns true, or fails with `error` after
+    /// [`POLL_LIMIT`] polls.
+    fn poll(&mut self, done: fn(&mut H) -> bool, error: AdcError) -> Result<(), AdcError> {
+        for _ in 0..POLL_LIMIT {
+            if done(&mut self.hw) {
+                return Ok(());
+            }
+        }
+        Err(error)
+    }
+
+    /// Enables the regulator, calibrates the ADC, and enables it, as in the
+    /// [module documentation](self).
+    ///
+    /// If it fails, reads fail with [`AdcError::NotCalibrated`] until it's
+    /// called again and succeeds.
+    pub fn calibrate<D: DelayUs<u32>>(&mut self, delay: &mut D) -> Result<(), AdcError> {
+        self.calibrated = false;
+
+        self.hw.enable_regulator();
+        delay.delay_us(REGULATOR_STARTUP_US);
+
+        self.hw.start_calibration();
+        self.poll(|hw| !hw.is_calibrating(), AdcError::CalibrationTimeout)?;
+
+        // ADEN can't be set until 4 ADC clock cycles after ADCAL clears,
+        // which is well under a microsecond at any ADC clock there is.
+        delay.delay_us(1);
+        self.hw.enable();
+        self.poll(|hw| hw.is_ready(), AdcError::ReadyTimeout)?;
+
+        self.calibrated = true;
+        Ok(())
+    }
+
+    /// Returns whether the last calibration succeeded.
+    pub fn is_calibrated(&self) -> bool {
+        self.calibrated
+    }
+
+    /// Converts `channel` once, and returns the reading if it's a real one.
+    pub fn read(&mut self, channel: u8) -> Result<u16, AdcError> {
+        if !self.calibrated {
+            return Err(AdcError::NotCalibrated);
+        }
+
+        self.hw.start_conversion(channel);
+        for _ in 0..POLL_LIMIT {
+            if let Some(raw) = self.hw.conversion() {
+                return classify(raw, self.resolution);
+            }
+        }
+        Err(AdcError::ConversionTimeout)
+    }
+
+    /// Releases the hardware.
+    pub fn free(self) -> H {
+        self.hw
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::vec::Vec;
+
+    /// An ADC whose flags change after a set number of polls, logging each
+    /// step it's asked to take.
+    #[derive(Default)]
+    struct MockAdc {
+        ops: Vec<&'static str>,
+        /// Polls until calibration finishes, or never with `None`.
+        calibration_polls: Option<u32>,
+        /// Polls until it's ready, or never with `None`.
+        ready_polls: Option<u32>,
+        /// The conversion that comes back, or never with `None`.
+        conversion: Option<u16>,
+        polls: u32,
+    }
+
+    impl MockAdc {
+        fn working(conversion: u16) -> Self {
+            MockAdc {
+                calibration_polls: Some(3),
+                ready_polls: Some(2),
+                conversion: Some(conversion),
+                ..Default::default()
+            }
+        }
+
+        /// Counts a poll, and returns whether `after` polls have been made.
+        fn poll(&mut self, after: Option<u32>) -> bool {
+            self.polls += 1;
+            after.is_some_and(|after| self.polls >= after)
+        }
+    }
+
+    impl AdcHw for MockAdc {
+        fn enable_regulator(&mut self) {
+            self.ops.push("regulator");
+        }
+
+        fn start_calibration(&mut self) {
+            self.ops.push("calibrate");
+            self.polls = 0;
+        }
+
+        fn is_calibrating(&mut self) -> bool {
+            !self.poll(self.calibration_polls)
+        }
+
+        fn enable(&mut self) {
+            self.ops.push("enable");
+            self.polls = 0;
+        }
+
+        fn is_ready(&mut self) -> bool {
+            self.poll(self.ready_polls)
+        }
+
+        fn start_conversion(&mut self, _channel: u8) {
+            self.ops.push("convert");
+        }
+
+        fn conversion(&mut self) -> Option<u16> {
+            self.conversion
+        }
+    }
+
+    /// A delay that adds up how long it's been asked to wait.
+    #[derive(Default)]
+    struct MockDelay {
+        total_us: u32,
+    }
+
+    impl DelayUs<u32> for MockDelay {
+        fn delay_us(&mut self, us: u32) {
+            self.total_us += us;
+        }
+    }
+
+    fn calibrated(hw: MockAdc) -> Adc<MockAdc> {
+        let mut adc = Adc::new(hw, Resolution::Bits12);
+        assert_eq!(adc.calibrate(&mut MockDelay::default()), Ok(()));
+        adc
+    }
+
+    #[test]
+    fn readings_below_full_scale_are_valid() {
+        assert_eq!(classify(0, Resolution::Bits12), Ok(0));
+        assert_eq!(classify(2048, Resolution::Bits12), Ok(2048));
+        assert_eq!(classify(4094, Resolution::Bits12), Ok(4094));
+    }
+
+    #[test]
+    fn all_ones_is_saturated_at_each_resolution() {
+        assert_eq!(classify(4095, Resolution::Bits12), Err(AdcError::Saturated));
+        assert_eq!(classify(1023, Resolution::Bits10), Err(AdcError::Saturated));
+        assert_eq!(classify(255, Resolution::Bits8), Err(AdcError::Saturated));
+        assert_eq!(classify(63, Resolution::Bits6), Err(AdcError::Saturated));
+        // A 12-bit reading that's only full scale at a lower resolution.
+        assert_eq!(classify(1023, Resolution::Bits12), Ok(1023));
+    }
+
+    #[test]
+    fn anything_over_full_scale_is_saturated() {
+        assert_eq!(
+            classify(0xFFFF, Resolution::Bits12),
+            Err(AdcError::Saturated)
+        );
+        assert_eq!(classify(64, Resolution::Bits6), Err(AdcError::Saturated));
+    }
+
+    #[test]
+    fn calibrate_runs_the_steps_in_order_with_the_regulator_delay() {
+        let mut adc = Adc::new(MockAdc::working(100), Resolution::Bits12);
+        let mut delay = MockDelay::default();
+        assert_eq!(adc.calibrate(&mut delay), Ok(()));
+        assert!(adc.is_calibrated());
+        assert!(delay.total_us >= REGULATOR_STARTUP_US);
+        assert_eq!(adc.free().ops, ["regulator", "calibrate", "enable"]);
+    }
+
+    #[test]
+    fn calibration_that_never_finishes_times_out() {
+        let hw = MockAdc {
+            calibration_polls: None,
+            ..MockAdc::working(100)
+        };
+        let mut adc = Adc::new(hw, Resolution::Bits12);
+        assert_eq!(
+            adc.calibrate(&mut MockDelay::default()),
+            Err(AdcError::CalibrationTimeout)
+        );
+        assert!(!adc.is_calibrated());
+        // The ADC isn't enabled after a failed calibration.
+        assert_eq!(adc.free().ops, ["regulator", "calibrate"]);
+    }
+
+    #[test]
+    fn adc_that_never_becomes_ready_times_out() {
+        let hw = MockAdc {
+            ready_polls: None,
+            ..MockAdc::working(100)
+        };
+        let mut adc = Adc::new(hw, Resolution::Bits12);
+        assert_eq!(
+            adc.calibrate(&mut MockDelay::default()),
+            Err(AdcError::ReadyTimeout)
+        );
+        assert!(!adc.is_calibrated());
+    }
+
+    #[test]
+    fn read_before_calibrating_fails_without_converting() {
+        let mut adc = Adc::new(MockAdc::working(100), Resolution::Bits12);
+        assert_eq!(adc.read(2), Err(AdcError::NotCalibrated));
+        assert!(adc.free().ops.is_empty());
+    }
+
+    #[test]
+    fn read_after_failed_calibration_fails() {
+        let hw = MockAdc {
+            calibration_polls: None,
+            ..MockAdc::working(100)
+        };
+        let mut adc = Adc::new(hw, Resolution::Bits12);
+        adc.calibrate(&mut MockDelay::default()).ok();
+        assert_eq!(adc.read(2), Err(AdcError::NotCalibrated));
+    }
+
+    #[test]
+    fn read_returns_valid_and_saturated_conversions() {
+        assert_eq!(calibrated(MockAdc::working(1234)).read(2), Ok(1234));
+        assert_eq!(
+            calibrated(MockAdc::working(4095)).read(2),
+            Err(AdcError::Saturated)
+        );
+    }
+
+    #[test]
+    fn conversion_that_never_finishes_times_out() {
+        let hw = MockAdc {
+            conversion: None,
+            ..MockAdc::working(0)
+        };
+        assert_eq!(calibrated(hw).read(2), Err(AdcError::ConversionTimeout));
+    }
+}
@@ -0,0 +1,234 @@
+//! Blinking an LED in a pattern written as a string.
+//!
+//! [`blink_pattern`] blinks a [`UserLed`] once through a pattern of
+//! characters, the way Morse code is written, with each one a number of
+//! units of time long:
+//!
+//! | Character | LED                          | Units |
+//! | --------- | ---------------------------- | ----- |
+//! | `.`       | On, then off                 | 1 + 1 |
+//! | `-`       | On, then off                 | 3 + 1 |
+//! | ` `       | Off                          | 1     |
+//!
+//! So every mark is followed by a gap of one unit, which keeps two of them in
+//! a row from running together, and a space adds a pause on top. Anything
+//! else in the pattern is ignored.
+//!
+//! ```ignore
+//! // A heartbeat: two quick flashes, then a pause.
+//! loop {
+//!     blink_pattern(&mut ld3, &mut delay, "..      ", 100);
+//! }
+//! ```
+//!
+//! Morse code then reads the way it's written, with timings from the
+//! standard: "SOS" is `"...  ---  ...      "`, the two spaces after a letter
+//! making up its 3 unit gap along with the gap after the last mark, and six
+//! at the end making up the 7 unit gap between words.
+//!
+//! Runs of off time, from a gap followed by spaces, are done as one delay, so
+//! the LED is only switched when it changes. [`for_each_step`] gives the
+//! steps a pattern is made of without blinking anything, which is also how
+//! the pattern can be checked on the host.
+//!
+//! The LED is anything with [`UserLed`], this crate's stand-in for the
+//! `OutputSwitch` of switch-hal, which isn't used here, so a [`PinLed`] of
+//! either board works, and its active level is taken care of.
+//!
+//! [`PinLed`]: crate::led::PinLed
+
+use embedded_hal::blocking::delay::DelayMs;
+
+use crate::led::UserLed;
+
+/// A stretch of time with the LED on or off.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Step {
+    /// Whether the LED is on.
+    pub on: bool,
+    /// How long for, in units.
+    pub units: u32,
+}
+
+/// Calls `f` with each step of `pattern` in turn, with the off time between
+/// marks merged into one step.
+///
+/// See the [module documentation](self) for what the characters mean.
+pub fn for_each_step<F: FnMut(Step)>(pattern: &str, mut f: F) {
+    let mut current: Option<Step> = None;
+    let mut add = |on: bool, units: u32| match current.as_mut() {
+        Some(step) if step.on == on => step.units += units,
+        _ => {
+            if let Some(step) = current.replace(Step { on, units }) {
+                f(step);
+            }
+        }
+    };
+
+    for c in pattern.chars() {
+        match c {
+            '.' => {
+                add(true, 1);
+                add(false, 1);
+            }
+            '-' => {
+                add(true, 3);
+                add(false, 1);
+            }
+            ' ' => add(false, 1),
+            _ => {}
+        }
+    }
+
+    if let Some(step) = current {
+        f(step);
+    }
+}
+
+/// Blinks `led` once through `pattern`, with each unit `unit_ms`
+/// milliseconds long, leaving it off.
+///
+/// See the [module documentation](self) for what the characters mean.
+pub fn blink_pattern<L: UserLed + ?Sized, D: DelayMs<u16>>(
+    led: &mut L,
+    delay: &mut D,
+    pattern: &str,
+    unit_ms: u16,
+) {
+    for_each_step(pattern, |step| {
+        if step.on {
+            led.on();
+        } else {
+            led.off();
+        }
+
+        // A long step can be more than a u16 of milliseconds, so it's
+        // delayed in pieces.
+        let mut remaining = step.units.saturating_mul(unit_ms as u32);
+        while remaining > 0 {
+            let piece = remaining.min(u16::MAX as u32);
+            delay.delay_ms(piece as u16);
+            remaining -= piece;
+        }
+    });
+    led.off();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::vec::Vec;
+
+    use crate::mock::{Log, MockDelay, MockLed, Op};
+
+    /// Returns the steps of `pattern` as (on, units) pairs.
+    fn steps(pattern: &str) -> Vec<(bool, u32)> {
+        let mut steps = Vec::new();
+        for_each_step(pattern, |step| steps.push((step.on, step.units)));
+        steps
+    }
+
+    /// Blinks `pattern` and returns what the LED and delay were asked to do.
+    fn blink(pattern: &str, unit_ms: u16) -> Vec<Op> {
+        let log = Log::default();
+        let mut led = MockLed::new(&log);
+        let mut delay = MockDelay::new(&log);
+        blink_pattern(&mut led, &mut delay, pattern, unit_ms);
+        let ops = log.borrow().clone();
+        ops
+    }
+
+    #[test]
+    fn dot_and_dash_are_one_and_three_units_with_a_gap() {
+        assert_eq!(steps("."), [(true, 1), (false, 1)]);
+        assert_eq!(steps("-"), [(true, 3), (false, 1)]);
+        assert_eq!(steps(".-"), [(true, 1), (false, 1), (true, 3), (false, 1)]);
+    }
+
+    #[test]
+    fn spaces_add_to_the_gap() {
+        assert_eq!(
+            steps(". . "),
+            [(true, 1), (false, 2), (true, 1), (false, 2)]
+        );
+        assert_eq!(
+            steps("..      "),
+            [(true, 1), (false, 1), (true, 1), (false, 7)]
+        );
+    }
+
+    #[test]
+    fn leading_spaces_start_off() {
+        assert_eq!(steps("  ."), [(false, 2), (true, 1), (false, 1)]);
+    }
+
+    #[test]
+    fn only_spaces_is_one_off_step() {
+        assert_eq!(steps("   "), [(false, 3)]);
+        assert_eq!(steps(""), []);
+    }
+
+    #[test]
+    fn other_characters_are_ignored() {
+        assert_eq!(steps(".x-\n"), steps(".-"));
+    }
+
+    #[test]
+    fn sos_has_standard_morse_timing() {
+        let sos = steps("...  ---  ...      ");
+        // Gaps of 1 within a letter, 3 between letters, and 7 at the end.
+        let gaps: Vec<u32> = sos.iter().filter(|s| !s.0).map(|s| s.1).collect();
+        assert_eq!(gaps, [1, 1, 3, 1, 1, 3, 1, 1, 7]);
+    }
+
+    #[test]
+    fn blink_drives_the_led_and_delays_by_unit() {
+        assert_eq!(
+            blink(". - ", 100),
+            [
+                Op::On,
+                Op::Delay(100),
+                Op::Off,
+                Op::Delay(200),
+                Op::On,
+                Op::Delay(300),
+                Op::Off,
+                Op::Delay(200),
+                Op::Off,
+            ]
+        );
+    }
+
+    #[test]
+    fn blink_of_leading_and_trailing_spaces() {
+        assert_eq!(
+            blink(" . ", 50),
+            [
+                Op::Off,
+                Op::Delay(50),
+                Op::On,
+                Op::Delay(50),
+                Op::Off,
+                Op::Delay(100),
+                Op::Off,
+            ]
+        );
+    }
+
+    #[test]
+    fn long_steps_are_delayed_in_pieces() {
+        // 3 units of 30 s is 90 s, more than a u16 of milliseconds.
+        assert_eq!(
+            blink("-", 30_000),
+            [
+                Op::On,
+                Op::Delay(65_535),
+                Op::Delay(24_465), // 90_000 - 65_535
+                Op::Off,
+                Op::Delay(30_000),
+                Op::Off,
+            ]
+        );
+    }
+}
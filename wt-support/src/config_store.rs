@@ -0,0 +1,526 @@
+//! A small config persisted to flash, spread across slots to even out wear.
+//!
+//! Flash can only be programmed from 1s to 0s, and only erased back to 1s a
+//! whole sector at a time, which wears it out: each sector is only rated for
+//! about 10,000 erase cycles. Erasing a sector and rewriting the config in
+//! place on every save, the way the flash-store example does with its boot
+//! counter, uses up one of those cycles every time.
+//!
+//! [`ConfigStore`] instead splits a region of flash into a number of fixed-size
+//! slots and writes each save to the next slot along, so the region only has
+//! to be erased once all of the slots have been used. With 64 slots, that's a
+//! 64th of the erases for the same number of saves. Each slot holds a record:
+//!
+//! | Offset      | Size  | Field                                             |
+//! |-------------|-------|---------------------------------------------------|
+//! | 0           | 4     | Sequence number, little-endian                    |
+//! | 4           | `LEN` | The config, encoded by [`Config::encode`]         |
+//! | 4 + `LEN`   | 4     | CRC-32 of the two fields before it, little-endian |
+//!
+//! On boot, [`ConfigStore::load`] reads every slot and returns the config from
+//! the valid one with the highest sequence number, which is the last one
+//! saved. [`ConfigStore::save`] writes the next sequence number to the slot
+//! after that one, going around to the first slot, and erasing the region, once
+//! it's at the end.
+//!
+//! ```ignore
+//! let mut store = ConfigStore::new(flash, SLOT_COUNT, SLOT_SIZE);
+//! let mut config: Settings = store.load()?.unwrap_or_default();
+//! config.boot_count += 1;
+//! store.save(&config)?;
+//! ```
+//!
+//! # Torn writes
+//!
+//! If the power goes out or the chip resets partway through a save, the slot
+//! being written ends up with part of the new record and the rest still
+//! erased. The CRC of that record doesn't match, so it's skipped on the next
+//! boot, and the one saved before it, which hasn't been touched, is loaded
+//! instead. The save is lost, but the config isn't corrupted. A save that finds
+//! its slot already programmed, which is what a torn write leaves behind,
+//! erases the region rather than programming over it.
+//!
+//! Erasing the region is the one point where that doesn't hold: it clears
+//! every slot, so a reset between the erase and the write of the new record
+//! loses the config. Alternating between two regions would avoid that, at the
+//! cost of twice the flash.
+//!
+//! # Flash
+//!
+//! The module works through the [`SlotFlash`] trait rather than a particular
+//! HAL's flash driver, so it can be used on either board, and tested on the
+//! host against flash simulated in RAM. The config-store example implements it
+//! for the last sector of the STM32F767's flash.
+
+use core::convert::TryInto;
+
+use crate::crc::crc32;
+
+/// Size of the sequence number and the CRC around the config in a record.
+pub const RECORD_OVERHEAD: usize = 8;
+
+/// Largest record supported, in bytes, including [`RECORD_OVERHEAD`].
+pub const MAX_RECORD_LEN: usize = 256;
+
+/// Value of erased flash.
+pub const ERASED_BYTE: u8 = 0xFF;
+
+/// A region of flash to keep the slots in.
+///
+/// Offsets are from the start of the region.
+pub trait SlotFlash {
+    /// Error from the flash driver.
+    type Error;
+
+    /// Reads `buf.len()` bytes starting at `offset`.
+    fn read(&mut self, offset: usize, buf: &mut [u8]) -> Result<(), Self::Error>;
+
+    /// Programs `data` starting at `offset`, which has been erased.
+    fn write(&mut self, offset: usize, data: &[u8]) -> Result<(), Self::Error>;
+
+    /// Erases the whole region.
+    fn erase(&mut self) -> Result<(), Self::Error>;
+}
+
+/// A config that can be stored.
+///
+/// The config is encoded field by field into bytes rather than stored by
+/// casting a `#[repr(C)]` struct to a byte slice. The cast is tempting, but
+/// the padding between the fields of a struct is uninitialized memory, which
+/// it's undefined behavior to read as bytes, and the layout would silently
+/// change with the fields.
+pub trait Config: Sized {
+    /// Length of the encoded config in bytes.
+    const LEN: usize;
+
+    /// Encodes the config into `buf`, which is [`Config::LEN`] bytes long.
+    fn encode(&self, buf: &mut [u8]);
+
+    /// Decodes a config from `buf`, which is [`Config::LEN`] bytes long.
+    fn decode(buf: &[u8]) -> Self;
+}
+
+/// Errors from loading or saving a config.
+#[derive(Debug, PartialEq, Eq)]
+pub enum Error<E> {
+    /// A record of the config doesn't fit in a slot or in
+    /// [`MAX_RECORD_LEN`].
+    TooLarge,
+    /// Reading, writing, or erasing the flash failed.
+    Flash(E),
+}
+
+/// The slot the last save went to and its sequence number.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Latest {
+    /// Index of the slot.
+    pub slot: usize,
+    /// Sequence number of the record in it.
+    pub sequence: u32,
+}
+
+/// Where the next save goes.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Next {
+    /// Index of the slot.
+    pub slot: usize,
+    /// Sequence number to write.
+    pub sequence: u32,
+    /// Whether the region has to be erased first.
+    pub erase: bool,
+}
+
+/// Why a slot doesn't hold a valid record.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RecordError {
+    /// The slice is shorter than a record of the config.
+    Truncated,
+    /// The CRC doesn't match, e.g. because the slot is erased or a write was
+    /// torn.
+    Crc,
+}
+
+/// Returns the sequence number of `record` if its CRC matches.
+///
+/// `record` should be a whole record for a config of `len` bytes, and is
+/// [`RecordError::Truncated`] if it's any shorter. An erased slot fails the
+/// check too, since the CRC of all ones isn't all ones.
+pub fn check_record(record: &[u8], len: usize) -> Result<u32, RecordError> {
+    let crc_offset = 4 + len;
+    let field = |offset: usize| {
+        record
+            .get(offset..offset + 4)
+            .and_then(|bytes| bytes.try_into().ok())
+            .map(u32::from_le_bytes)
+            .ok_or(RecordError::Truncated)
+    };
+
+    let stored = field(crc_offset)?;
+    if crc32(&record[..crc_offset]) != stored {
+        return Err(RecordError::Crc);
+    }
+    field(0)
+}
+
+/// Returns where the save after `latest` goes in a region of `slot_count`
+/// slots.
+///
+/// `next_is_erased` is whether the slot after `latest` is still erased. The
+/// region has to be erased when the next slot wraps around to the first or
+/// isn't erased, in which case the record goes into the first slot.
+pub fn select_next(latest: Option<Latest>, slot_count: usize, next_is_erased: bool) -> Next {
+    let (slot, sequence) = match latest {
+        Some(latest) => (
+            (latest.slot + 1) % slot_count,
+            latest.sequence.wrapping_add(1),
+        ),
+        None => (0, 0),
+    };
+    let wrapped = latest.is_some() && slot == 0;
+
+    if wrapped || !next_is_erased {
+        Next {
+            slot: 0,
+            sequence,
+            erase: true,
+        }
+    } else {
+        Next {
+            slot,
+            sequence,
+            erase: false,
+        }
+    }
+}
+
+/// A config stored across `slot_count` slots of `slot_size` bytes each.
+pub struct ConfigStore<F> {
+    flash: F,
+    slot_count: usize,
+    slot_size: usize,
+}
+
+impl<F: SlotFlash> ConfigStore<F> {
+    /// Creates a store over a region of `flash` of `slot_count` slots of
+    /// `slot_size` bytes each.
+    ///
+    /// `slot_size` has to be a multiple of the size the flash is programmed
+    /// in, 4 bytes on the STM32F767 at 3.3 V, so that each slot starts where a
+    /// write can. A `slot_count` of 0 is treated as 1.
+    pub fn new(flash: F, slot_count: usize, slot_size: usize) -> Self {
+        ConfigStore {
+            flash,
+            slot_count: slot_count.max(1),
+            slot_size,
+        }
+    }
+
+    /// Returns the most recently saved config, or `None` if no slot holds a
+    /// valid one, e.g. before the first save.
+    pub fn load<C: Config>(&mut self) -> Result<Option<C>, Error<F::Error>> {
+        let mut record = [0_u8; MAX_RECORD_LEN];
+        let record = self.record_buf::<C>(&mut record)?;
+        let latest = match self.find_latest::<C>(record)? {
+            Some(latest) => latest,
+            None => return Ok(None),
+        };
+
+        self.read_slot(latest.slot, record)?;
+        Ok(Some(C::decode(&record[4..4 + C::LEN])))
+    }
+
+    /// Saves `config` in the next slot.
+    pub fn save<C: Config>(&mut self, config: &C) -> Result<(), Error<F::Error>> {
+        let mut record = [0_u8; MAX_RECORD_LEN];
+        let record = self.record_buf::<C>(&mut record)?;
+        let latest = self.find_latest::<C>(record)?;
+
+        let candidate = latest.map_or(0, |latest| (latest.slot + 1) % self.slot_count);
+        self.read_slot(candidate, record)?;
+        let next_is_erased = record.iter().all(|&byte| byte == ERASED_BYTE);
+        let next = select_next(latest, self.slot_count, next_is_erased);
+
+        if next.erase {
+            self.flash.erase().map_err(Error::Flash)?;
+        }
+
+        record[..4].copy_from_slice(&next.sequence.to_le_bytes());
+        config.encode(&mut record[4..4 + C::LEN]);
+        let crc = crc32(&record[..4 + C::LEN]);
+        record[4 + C::LEN..].copy_from_slice(&crc.to_le_bytes());
+
+        self.flash
+            .write(next.slot * self.slot_size, record)
+            .map_err(Error::Flash)
+    }
+
+    /// Releases the flash.
+    pub fn free(self) -> F {
+        self.flash
+    }
+
+    /// Returns the part of `buf` a record of `C` takes.
+    fn record_buf<'a, C: Config>(
+        &self,
+        buf: &'a mut [u8],
+    ) -> Result<&'a mut [u8], Error<F::Error>> {
+        let len = C::LEN + RECORD_OVERHEAD;
+        if len > self.slot_size || len > buf.len() {
+            return Err(Error::TooLarge);
+        }
+        Ok(&mut buf[..len])
+    }
+
+    fn read_slot(&mut self, slot: usize, record: &mut [u8]) -> Result<(), Error<F::Error>> {
+        self.flash
+            .read(slot * self.slot_size, record)
+            .map_err(Error::Flash)
+    }
+
+    /// Returns the slot with the highest sequence number of the valid records.
+    ///
+    /// The sequence numbers are compared as plain integers. They'd only wrap
+    /// around after 2^32 saves, which is hundreds of times more than the flash
+    /// can take with any reasonable number of slots.
+    fn find_latest<C: Config>(
+        &mut self,
+        record: &mut [u8],
+    ) -> Result<Option<Latest>, Error<F::Error>> {
+        let mut latest: Option<Latest> = None;
+        for slot in 0..self.slot_count {
+            self.read_slot(slot, record)?;
+            if let Ok(sequence) = check_record(record, C::LEN) {
+                if latest.is_none_or(|latest| sequence > latest.sequence) {
+                    latest = Some(Latest { slot, sequence });
+                }
+            }
+        }
+        Ok(latest)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SLOT_COUNT: usize = 4;
+    const SLOT_SIZE: usize = 16;
+
+    /// Flash simulated in RAM, which, like the real thing, can only clear
+    /// bits when programmed.
+    struct MockFlash {
+        bytes: Vec<u8>,
+        erases: usize,
+    }
+
+    impl MockFlash {
+        fn new() -> Self {
+            MockFlash {
+                bytes: vec![ERASED_BYTE; SLOT_COUNT * SLOT_SIZE],
+                erases: 0,
+            }
+        }
+    }
+
+    impl SlotFlash for MockFlash {
+        type Error = ();
+
+        fn read(&mut self, offset: usize, buf: &mut [u8]) -> Result<(), ()> {
+            buf.copy_from_slice(&self.bytes[offset..offset + buf.len()]);
+            Ok(())
+        }
+
+        fn write(&mut self, offset: usize, data: &[u8]) -> Result<(), ()> {
+            for (byte, &value) in self.bytes[offset..offset + data.len()].iter_mut().zip(data) {
+                *byte &= value;
+            }
+            Ok(())
+        }
+
+        fn erase(&mut self) -> Result<(), ()> {
+            self.bytes.fill(ERASED_BYTE);
+            self.erases += 1;
+            Ok(())
+        }
+    }
+
+    #[derive(Debug, PartialEq, Eq)]
+    struct Settings {
+        boot_count: u32,
+        brightness: u8,
+    }
+
+    impl Config for Settings {
+        const LEN: usize = 5;
+
+        fn encode(&self, buf: &mut [u8]) {
+            buf[..4].copy_from_slice(&self.boot_count.to_le_bytes());
+            buf[4] = self.brightness;
+        }
+
+        fn decode(buf: &[u8]) -> Self {
+            Settings {
+                boot_count: u32::from_le_bytes(buf[..4].try_into().unwrap()),
+                brightness: buf[4],
+            }
+        }
+    }
+
+    fn settings(boot_count: u32) -> Settings {
+        Settings {
+            boot_count,
+            brightness: 50,
+        }
+    }
+
+    fn slot_sequence(store: &mut ConfigStore<MockFlash>, slot: usize) -> Option<u32> {
+        let mut record = [0; Settings::LEN + RECORD_OVERHEAD];
+        store.flash.read(slot * SLOT_SIZE, &mut record).unwrap();
+        check_record(&record, Settings::LEN).ok()
+    }
+
+    #[test]
+    fn first_save_goes_to_first_slot() {
+        assert_eq!(
+            select_next(None, SLOT_COUNT, true),
+            Next {
+                slot: 0,
+                sequence: 0,
+                erase: false
+            }
+        );
+    }
+
+    #[test]
+    fn next_save_goes_to_following_slot() {
+        let latest = Latest {
+            slot: 1,
+            sequence: 7,
+        };
+        assert_eq!(
+            select_next(Some(latest), SLOT_COUNT, true),
+            Next {
+                slot: 2,
+                sequence: 8,
+                erase: false
+            }
+        );
+    }
+
+    #[test]
+    fn save_after_last_slot_erases_and_wraps() {
+        let latest = Latest {
+            slot: SLOT_COUNT - 1,
+            sequence: 3,
+        };
+        assert_eq!(
+            select_next(Some(latest), SLOT_COUNT, true),
+            Next {
+                slot: 0,
+                sequence: 4,
+                erase: true
+            }
+        );
+    }
+
+    #[test]
+    fn programmed_next_slot_erases() {
+        let latest = Latest {
+            slot: 0,
+            sequence: 0,
+        };
+        assert_eq!(
+            select_next(Some(latest), SLOT_COUNT, false),
+            Next {
+                slot: 0,
+                sequence: 1,
+                erase: true
+            }
+        );
+        assert!(select_next(None, SLOT_COUNT, false).erase);
+    }
+
+    #[test]
+    fn erased_slot_is_not_a_record() {
+        assert_eq!(
+            check_record(&[ERASED_BYTE; 13], Settings::LEN),
+            Err(RecordError::Crc)
+        );
+    }
+
+    #[test]
+    fn short_record_is_truncated() {
+        let mut record = [0; Settings::LEN + RECORD_OVERHEAD];
+        record[4..4 + Settings::LEN].copy_from_slice(&[0x2A; Settings::LEN]);
+        let crc = crc32(&record[..4 + Settings::LEN]);
+        record[4 + Settings::LEN..].copy_from_slice(&crc.to_le_bytes());
+        assert_eq!(check_record(&record, Settings::LEN), Ok(0));
+
+        for len in 0..record.len() {
+            assert_eq!(
+                check_record(&record[..len], Settings::LEN),
+                Err(RecordError::Truncated)
+            );
+        }
+    }
+
+    #[test]
+    fn load_before_first_save_is_none() {
+        let mut store = ConfigStore::new(MockFlash::new(), SLOT_COUNT, SLOT_SIZE);
+        assert_eq!(store.load::<Settings>(), Ok(None));
+    }
+
+    #[test]
+    fn saves_rotate_through_slots_and_load_the_latest() {
+        let mut store = ConfigStore::new(MockFlash::new(), SLOT_COUNT, SLOT_SIZE);
+        for boot_count in 0..SLOT_COUNT as u32 {
+            store.save(&settings(boot_count)).unwrap();
+            assert_eq!(store.load(), Ok(Some(settings(boot_count))));
+        }
+
+        for slot in 0..SLOT_COUNT {
+            assert_eq!(slot_sequence(&mut store, slot), Some(slot as u32));
+        }
+        assert_eq!(store.flash.erases, 0);
+    }
+
+    #[test]
+    fn wrapping_around_erases_once_per_lap() {
+        let mut store = ConfigStore::new(MockFlash::new(), SLOT_COUNT, SLOT_SIZE);
+        for boot_count in 0..(2 * SLOT_COUNT + 1) as u32 {
+            store.save(&settings(boot_count)).unwrap();
+        }
+
+        assert_eq!(store.flash.erases, 2);
+        assert_eq!(slot_sequence(&mut store, 0), Some(2 * SLOT_COUNT as u32));
+        assert_eq!(slot_sequence(&mut store, 1), None);
+        assert_eq!(store.load(), Ok(Some(settings(2 * SLOT_COUNT as u32))));
+    }
+
+    #[test]
+    fn torn_write_falls_back_to_previous_save() {
+        let mut store = ConfigStore::new(MockFlash::new(), SLOT_COUNT, SLOT_SIZE);
+        store.save(&settings(1)).unwrap();
+        store.save(&settings(2)).unwrap();
+
+        // Simulate a reset partway through the third save, which leaves the
+        // first few bytes of its record programmed and the rest erased.
+        store.flash.write(2 * SLOT_SIZE, &[2, 0, 0, 0, 3]).unwrap();
+
+        assert_eq!(store.load(), Ok(Some(settings(2))));
+
+        // The next save can't program over the torn record, so it erases the
+        // region and starts again from the first slot.
+        store.save(&settings(3)).unwrap();
+        assert_eq!(store.flash.erases, 1);
+        assert_eq!(slot_sequence(&mut store, 0), Some(2));
+        assert_eq!(store.load(), Ok(Some(settings(3))));
+    }
+
+    #[test]
+    fn config_too_large_for_slot() {
+        let mut store = ConfigStore::new(MockFlash::new(), SLOT_COUNT, 8);
+        assert_eq!(store.save(&settings(0)), Err(Error::TooLarge));
+        assert_eq!(store.load::<Settings>(), Err(Error::TooLarge));
+    }
+}
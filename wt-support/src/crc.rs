@@ -0,0 +1,150 @@
+//! Software CRC-32.
+//!
+//! [`crc32`] computes the standard CRC-32 (the one used by zlib, PNG, and
+//! Ethernet, also known as CRC-32/ISO-HDLC) one bit at a time. It's slow
+//! compared to a table-driven implementation or the CRC unit on the STM32s,
+//! but it's small and easy to check, which makes it useful as a reference to
+//! cross-check the hardware against.
+//!
+//! The standard CRC-32 is defined by:
+//!
+//! | Parameter      | Value        |
+//! |----------------|--------------|
+//! | Polynomial     | `0x04C11DB7` |
+//! | Initial value  | `0xFFFFFFFF` |
+//! | Reflect input  | Yes          |
+//! | Reflect output | Yes          |
+//! | Final XOR      | `0xFFFFFFFF` |
+//!
+//! "Reflected" means the bits of each input byte are processed least
+//! significant bit first, and the bits of the result come out in the same
+//! reversed order. The CRC units on the STM32s process bits most significant
+//! bit first, so they have to be set up to reverse the input and output to
+//! compute it. See the crc example for how that's done on the STM32F303.
+//!
+//! ```ignore
+//! assert_eq!(wt_support::crc::crc32(b"123456789"), 0xCBF4_3926);
+//! ```
+
+/// The CRC-32 generator polynomial, in normal (most significant bit first)
+/// form.
+pub const POLYNOMIAL: u32 = 0x04C1_1DB7;
+
+/// The CRC-32 generator polynomial, bit-reversed for processing the data least
+/// significant bit first.
+const POLYNOMIAL_REFLECTED: u32 = POLYNOMIAL.reverse_bits();
+
+/// Value the CRC is started from, which is also the reset value of the data
+/// register of the STM32 CRC units.
+pub const INITIAL: u32 = 0xFFFF_FFFF;
+
+/// Value the result is XORed with at the end.
+pub const FINAL_XOR: u32 = 0xFFFF_FFFF;
+
+/// Returns the standard CRC-32 of `data`.
+pub fn crc32(data: &[u8]) -> u32 {
+    let mut crc = INITIAL;
+    for &byte in data {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            // Shifting the CRC right brings the next bit of the data down to
+            // bit 0. If the bit shifted out was set, the polynomial divides
+            // into what's left, so subtract (XOR) it off.
+            crc = if crc & 1 != 0 {
+                (crc >> 1) ^ POLYNOMIAL_REFLECTED
+            } else {
+                crc >> 1
+            };
+        }
+    }
+    crc ^ FINAL_XOR
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Models the CRC unit of the STM32F303 set up the way the crc example sets
+    /// it up: input bit order reversed by byte, output bit order reversed, and
+    /// the data written as big-endian 32-bit words followed by any bytes left
+    /// over.
+    ///
+    /// The unit itself processes each write most significant bit first with
+    /// the polynomial in normal form, and the final XOR isn't done by the
+    /// hardware, so it's done here at the end, the same as in the example.
+    fn hardware_model(data: &[u8]) -> u32 {
+        fn process(crc: u32, value: u32, bits: u32) -> u32 {
+            let mut crc = crc ^ (value << (32 - bits));
+            for _ in 0..bits {
+                crc = if crc & 0x8000_0000 != 0 {
+                    (crc << 1) ^ POLYNOMIAL
+                } else {
+                    crc << 1
+                };
+            }
+            crc
+        }
+
+        /// Reverses the bits within each byte of `value`, which is what the
+        /// unit does to the input in its byte reversal mode.
+        fn reverse_bits_by_byte(value: u32) -> u32 {
+            u32::from_be_bytes(value.to_be_bytes().map(u8::reverse_bits))
+        }
+
+        let mut crc = INITIAL;
+        let mut words = data.chunks_exact(4);
+        for word in &mut words {
+            let word = u32::from_be_bytes([word[0], word[1], word[2], word[3]]);
+            crc = process(crc, reverse_bits_by_byte(word), 32);
+        }
+        for &byte in words.remainder() {
+            crc = process(crc, byte.reverse_bits() as u32, 8);
+        }
+        crc.reverse_bits() ^ FINAL_XOR
+    }
+
+    #[test]
+    fn check_value() {
+        // The standard check value for a CRC is the CRC of the ASCII digits 1
+        // through 9.
+        assert_eq!(crc32(b"123456789"), 0xCBF4_3926);
+    }
+
+    #[test]
+    fn empty_input() {
+        assert_eq!(crc32(&[]), 0);
+    }
+
+    #[test]
+    fn matches_the_crc_example() {
+        // Same data and expected value as examples/crc.
+        assert_eq!(
+            crc32(b"The quick brown fox jumps over the lazy dog"),
+            0x414F_A339
+        );
+    }
+
+    #[test]
+    fn single_bit_changes_change_the_crc() {
+        let data = *b"The quick brown fox jumps over the lazy dog";
+        let expected = crc32(&data);
+        for i in 0..data.len() {
+            for bit in 0..8 {
+                let mut corrupted = data;
+                corrupted[i] ^= 1 << bit;
+                assert_ne!(crc32(&corrupted), expected);
+            }
+        }
+    }
+
+    #[test]
+    fn hardware_model_matches_software() {
+        let data = b"The quick brown fox jumps over the lazy dog";
+
+        // Every length from 0 to the whole buffer, so every combination of
+        // whole words and left-over bytes is covered.
+        for len in 0..=data.len() {
+            assert_eq!(hardware_model(&data[..len]), crc32(&data[..len]));
+        }
+    }
+}
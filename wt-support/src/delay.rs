@@ -0,0 +1,228 @@
+//! One blocking delay trait over the three ways the examples delay.
+//!
+//! The examples delay with SysTick through the HAL `Delay` types, with a timer,
+//! and with the DWT cycle counter through [`crate::dwt_delay`], each with its
+//! own API. [`BlockingDelay`] puts them behind the same two methods, with an
+//! implementation for each:
+//!
+//! - [`SysTickDelay`] counts down SysTick for each delay. It needs nothing but
+//!   the core, but it takes SysTick, so it can't be used alongside
+//!   [`crate::millis`] or an RTOS that runs its tick on SysTick.
+//! - [`TimerDelay`](crate::tim_delay::TimerDelay), in [`crate::tim_delay`],
+//!   polls a free-running timer counter, such as TIM2's, set up by the
+//!   caller. It leaves SysTick free, at the cost of a timer.
+//! - [`DwtDelay`] polls the DWT cycle counter. It leaves both SysTick and the
+//!   timers free, but the counter is part of the debug hardware, which some
+//!   cores don't have.
+//!
+//! ```ignore
+//! fn blink(led: &mut impl ToggleableOutputPin, delay: &mut impl BlockingDelay) {
+//!     led.toggle().ok();
+//!     delay.delay_ms(500);
+//! }
+//! ```
+//!
+//! # Resolution and accuracy
+//!
+//! SysTick and the DWT counter both count core clock cycles, so they resolve
+//! to a cycle, but [`SysTickDelay`] has to stop and reprogram SysTick for every
+//! delay, which adds a fixed overhead of some tens of cycles. The dwt-delay
+//! example measures it. `TimerDelay` resolves to one tick of the timer, and
+//! waits a tick more than asked, since a delay starts partway through one, so
+//! with a 1 MHz tick, a delay can run up to a microsecond over. It has a
+//! similar overhead from reading the counter over the bus.
+//!
+//! All three are as accurate as the clock they count, and all of them are
+//! minimums: an interrupt that fires during a delay can stretch it by however
+//! long the handler takes.
+
+use cortex_m::peripheral::{syst::SystClkSource, SYST};
+
+use crate::dwt_delay;
+
+/// Largest number of ticks SysTick can count down in one go, since its reload
+/// register is 24 bits.
+pub const SYSTICK_MAX_TICKS: u64 = 1 << 24;
+
+/// A delay that blocks until at least the given time has passed.
+pub trait BlockingDelay {
+    /// Blocks for at least `ms` milliseconds.
+    fn delay_ms(&mut self, ms: u32);
+
+    /// Blocks for at least `us` microseconds.
+    fn delay_us(&mut self, us: u32);
+}
+
+/// Returns the number of ticks of a `tick_hz` clock in `us` microseconds,
+/// rounding up.
+///
+/// The calculation is done in 64 bits, so it can't overflow for any delay.
+pub fn us_to_ticks(us: u32, tick_hz: u32) -> u64 {
+    (us as u64 * tick_hz as u64).div_ceil(1_000_000)
+}
+
+/// Returns the number of ticks of a `tick_hz` clock in `ms` milliseconds,
+/// rounding up.
+///
+/// Converting straight from milliseconds, rather than through
+/// [`us_to_ticks`], avoids `ms * 1000` overflowing 32 bits after about 71
+/// minutes.
+pub fn ms_to_ticks(ms: u32, tick_hz: u32) -> u64 {
+    (ms as u64 * tick_hz as u64).div_ceil(1_000)
+}
+
+/// Returns the SysTick reload value that counts down `ticks` ticks, which is
+/// at most [`SYSTICK_MAX_TICKS`].
+///
+/// SysTick counts from the reload value down to 0 inclusive, so the reload
+/// value is one less than the number of ticks. A reload value of 0 never
+/// wraps, so a delay of less than 2 ticks is rounded up to 2.
+pub fn systick_reload(ticks: u64) -> u32 {
+    (ticks.clamp(2, SYSTICK_MAX_TICKS) - 1) as u32
+}
+
+/// Splits `ticks` into pieces of at most `max_piece` ticks and calls `spin`
+/// with each one.
+fn spin_in_pieces(ticks: u64, max_piece: u64, mut spin: impl FnMut(u32)) {
+    let mut remaining = ticks;
+    while remaining > 0 {
+        let piece = remaining.min(max_piece);
+        spin(piece as u32);
+        remaining -= piece;
+    }
+}
+
+/// A delay that counts down SysTick, clocked by the core.
+pub struct SysTickDelay {
+    syst: SYST,
+    sysclk_hz: u32,
+}
+
+impl SysTickDelay {
+    /// Takes SysTick for delays with the core clocked at `sysclk_hz`.
+    pub fn new(mut syst: SYST, sysclk_hz: u32) -> Self {
+        syst.set_clock_source(SystClkSource::Core);
+        SysTickDelay { syst, sysclk_hz }
+    }
+
+    /// Releases SysTick.
+    pub fn free(self) -> SYST {
+        self.syst
+    }
+
+    fn spin(&mut self, cycles: u64) {
+        let syst = &mut self.syst;
+        spin_in_pieces(cycles, SYSTICK_MAX_TICKS, |piece| {
+            syst.set_reload(systick_reload(piece as u64));
+            syst.clear_current();
+            syst.enable_counter();
+            while !syst.has_wrapped() {}
+            syst.disable_counter();
+        });
+    }
+}
+
+impl BlockingDelay for SysTickDelay {
+    fn delay_ms(&mut self, ms: u32) {
+        self.spin(ms_to_ticks(ms, self.sysclk_hz));
+    }
+
+    fn delay_us(&mut self, us: u32) {
+        self.spin(us_to_ticks(us, self.sysclk_hz));
+    }
+}
+
+/// A delay that polls the DWT cycle counter.
+///
+/// The counter has to be enabled first with [`crate::dwt_delay::init`].
+pub struct DwtDelay {
+    sysclk_hz: u32,
+}
+
+impl DwtDelay {
+    /// Creates a delay with the core clocked at `sysclk_hz`.
+    pub fn new(sysclk_hz: u32) -> Self {
+        DwtDelay { sysclk_hz }
+    }
+
+    fn spin(&mut self, cycles: u64) {
+        spin_in_pieces(cycles, (u32::MAX / 2) as u64, dwt_delay::delay_cycles);
+    }
+}
+
+impl BlockingDelay for DwtDelay {
+    fn delay_ms(&mut self, ms: u32) {
+        self.spin(ms_to_ticks(ms, self.sysclk_hz));
+    }
+
+    fn delay_us(&mut self, us: u32) {
+        self.spin(us_to_ticks(us, self.sysclk_hz));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ticks_per_microsecond() {
+        assert_eq!(us_to_ticks(1, 48_000_000), 48);
+        assert_eq!(us_to_ticks(10, 1_000_000), 10);
+        assert_eq!(us_to_ticks(0, 48_000_000), 0);
+    }
+
+    #[test]
+    fn ticks_per_millisecond() {
+        assert_eq!(ms_to_ticks(1, 48_000_000), 48_000);
+        assert_eq!(ms_to_ticks(500, 1_000_000), 500_000);
+        assert_eq!(ms_to_ticks(0, 1_000_000), 0);
+    }
+
+    #[test]
+    fn rounds_up_partial_ticks() {
+        // A 32.768 kHz tick is about 30.5 us long.
+        assert_eq!(us_to_ticks(1, 32_768), 1);
+        assert_eq!(us_to_ticks(31, 32_768), 2);
+        assert_eq!(ms_to_ticks(1, 32_768), 33);
+    }
+
+    #[test]
+    fn long_delays_do_not_overflow() {
+        // The longest delay_ms() is about 49.7 days, which is far more than
+        // u32::MAX cycles at 216 MHz.
+        assert_eq!(
+            ms_to_ticks(u32::MAX, 216_000_000),
+            u32::MAX as u64 * 216_000
+        );
+        assert_eq!(us_to_ticks(u32::MAX, 216_000_000), u32::MAX as u64 * 216);
+    }
+
+    #[test]
+    fn systick_reload_is_one_less_than_ticks() {
+        assert_eq!(systick_reload(48_000), 47_999);
+        assert_eq!(systick_reload(SYSTICK_MAX_TICKS), 0x00FF_FFFF);
+    }
+
+    #[test]
+    fn systick_reload_is_clamped() {
+        assert_eq!(systick_reload(0), 1);
+        assert_eq!(systick_reload(1), 1);
+        assert_eq!(systick_reload(SYSTICK_MAX_TICKS + 1), 0x00FF_FFFF);
+    }
+
+    #[test]
+    fn pieces_add_up_to_the_delay() {
+        let mut pieces = Vec::new();
+        spin_in_pieces(2 * SYSTICK_MAX_TICKS + 5, SYSTICK_MAX_TICKS, |piece| {
+            pieces.push(piece)
+        });
+        assert_eq!(
+            pieces,
+            [SYSTICK_MAX_TICKS as u32, SYSTICK_MAX_TICKS as u32, 5]
+        );
+
+        pieces.clear();
+        spin_in_pieces(0, SYSTICK_MAX_TICKS, |piece| pieces.push(piece));
+        assert!(pieces.is_empty());
+    }
+}
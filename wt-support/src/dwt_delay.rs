@@ -0,0 +1,115 @@
+//! Busy-wait delays timed with the DWT cycle counter.
+//!
+//! The DWT (Data Watchpoint and Trace unit) is a debug block in the core
+//! with, among other things, a 32-bit counter, CYCCNT, that counts up by one
+//! on every core clock cycle. [`delay_cycles`] spins until the counter has
+//! gone up by the requested number of cycles, which makes for a delay that's
+//! accurate to within a few cycles and, unlike the HAL `Delay` types, doesn't
+//! take SysTick, so it can be used alongside [`crate::millis`].
+//!
+//! ```ignore
+//! let mut core_periphs = cortex_m::Peripherals::take().unwrap();
+//! wt_support::dwt_delay::init(&mut core_periphs.DCB, &mut core_periphs.DWT);
+//!
+//! wt_support::dwt_delay::delay_us(10, clocks.sysclk().0);
+//! ```
+//!
+//! # Enabling the DWT
+//!
+//! The counter doesn't run out of reset. The DWT is part of the debug
+//! hardware, which is powered down until the TRCENA bit in the DCB's DEMCR
+//! register is set, and CYCCNT then has to be enabled separately in the DWT's
+//! own control register. [`init`] does both. A debugger attaching may set
+//! TRCENA on its own, which can make the counter seem to work without
+//! [`init`] until the board is run without one, so always call it.
+//!
+//! The cycle counter is optional in the architecture. The Cortex-M4 and M7 on
+//! the boards used here have it, but the Cortex-M0 and M0+ have no DWT cycle
+//! counter at all, and a given M3/M4/M7 part may be built without it. [`init`]
+//! checks the NOCYCCNT bit the DWT reports and returns whether the counter is
+//! there.
+//!
+//! # Limits
+//!
+//! The counter wraps around after 2^32 cycles, which is about 89 s at 48 MHz.
+//! The elapsed count is computed with wrapping subtraction, so a wraparound
+//! during a delay doesn't matter, but a single [`delay_cycles`] can't be
+//! longer than that. [`delay_us`] splits longer delays into pieces.
+//!
+//! The delay is a minimum. An interrupt that fires near the end of a delay runs
+//! to completion before the loop gets to check the counter again, so the delay
+//! can run over by up to however long the handler takes.
+
+use cortex_m::peripheral::{DCB, DWT};
+
+/// Enables the DWT cycle counter, returning whether the core has one.
+///
+/// If it returns false, the delays in this module never return, since the
+/// counter never moves.
+pub fn init(dcb: &mut DCB, dwt: &mut DWT) -> bool {
+    if !DWT::has_cycle_counter() {
+        return false;
+    }
+
+    dcb.enable_trace();
+    // The Cortex-M7 has a lock on the DWT registers that has to be lifted
+    // before they can be written. Other cores ignore this.
+    DWT::unlock();
+    dwt.enable_cycle_counter();
+    true
+}
+
+/// Spins for at least `cycles` core clock cycles.
+pub fn delay_cycles(cycles: u32) {
+    let start = DWT::cycle_count();
+    while DWT::cycle_count().wrapping_sub(start) < cycles {}
+}
+
+/// Spins for at least `us` microseconds with the core clocked at `sysclk_hz`.
+pub fn delay_us(us: u32, sysclk_hz: u32) {
+    let mut remaining = us_to_cycles(us, sysclk_hz);
+
+    // Delay in pieces of half the range of the counter, well clear of the
+    // wraparound.
+    const MAX_PIECE: u64 = (u32::MAX / 2) as u64;
+    while remaining > 0 {
+        let piece = remaining.min(MAX_PIECE);
+        delay_cycles(piece as u32);
+        remaining -= piece;
+    }
+}
+
+/// Returns the number of core clock cycles in `us` microseconds with the core
+/// clocked at `sysclk_hz`, rounding up.
+///
+/// The calculation is done in 64 bits, since `us * sysclk_hz` overflows 32
+/// bits after well under a millisecond.
+pub fn us_to_cycles(us: u32, sysclk_hz: u32) -> u64 {
+    (us as u64 * sysclk_hz as u64).div_ceil(1_000_000)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn whole_cycles_per_microsecond() {
+        assert_eq!(us_to_cycles(1, 48_000_000), 48);
+        assert_eq!(us_to_cycles(1_000, 48_000_000), 48_000);
+        assert_eq!(us_to_cycles(0, 48_000_000), 0);
+    }
+
+    #[test]
+    fn rounds_up_fractional_cycles() {
+        // 8 MHz is 8 cycles per microsecond, but 1 MHz + 1 Hz isn't a whole
+        // number of cycles per microsecond.
+        assert_eq!(us_to_cycles(3, 8_000_000), 24);
+        assert_eq!(us_to_cycles(1, 1_000_001), 2);
+    }
+
+    #[test]
+    fn does_not_overflow_32_bits() {
+        // A minute at 216 MHz is well over u32::MAX cycles.
+        assert_eq!(us_to_cycles(60_000_000, 216_000_000), 12_960_000_000);
+    }
+}
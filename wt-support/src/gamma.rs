@@ -0,0 +1,111 @@
+//! Gamma correction of LED brightness levels.
+//!
+//! A PWM duty sets how much light an LED puts out, but the eye doesn't see
+//! brightness in proportion to light. It's far more sensitive to changes in
+//! dim light than in bright light, so an LED at a 10% duty looks much brighter
+//! than a tenth of full. A fade that steps the duty up evenly seems to jump up
+//! from off and then hang around nearly full for most of the ramp, and one
+//! that steps it down seems to hang and then drop out at the end.
+//!
+//! Perceived brightness is roughly the light raised to the power of 1 / 2.2,
+//! so the light for a given perceived brightness is that brightness raised to
+//! the power of 2.2, the gamma. [`GAMMA8`] maps each 8-bit brightness level,
+//! which a fade can step through evenly, to the 8-bit duty that looks like it,
+//! and [`gamma_correct`] looks a level up in it:
+//!
+//! ```ignore
+//! for level in 0..=255 {
+//!     pwm.set_duty(u16::from(gamma::gamma_correct(level)));
+//!     delay.delay_ms(4_u16);
+//! }
+//! ```
+//!
+//! The table is
+//!
+//! ```text
+//! GAMMA8[i] = round(255 * (i / 255) ^ 2.2)
+//! ```
+//!
+//! generated with Python as
+//!
+//! ```text
+//! [round(255 * (i / 255) ** 2.2) for i in range(256)]
+//! ```
+//!
+//! and the tests check it against the same formula. It's a table rather than
+//! computed at runtime, since `powf()` isn't in `core`, and a lookup is faster
+//! than one anyway.
+//!
+//! 2.2 is the usual figure, from the sRGB standard for displays, and a good
+//! place to start, but the right value depends on the LED and the room.
+//! Values from about 1.8 to 2.8 are all in use.
+//!
+//! # Resolution
+//!
+//! Gamma correction spends most of the duties on the bright end, where the
+//! eye can't tell them apart, and squeezes the dim end into few: the first 15
+//! levels all map to a duty of 0, and the next 10 to 1. The dimmest steps of
+//! a fade are then visibly coarse. A PWM with more steps than 256, like a
+//! timer with a reload value of 1023 or more, can fix that with a wider table
+//! of the same formula.
+
+/// The gamma the table was generated with.
+pub const GAMMA: f32 = 2.2;
+
+/// The duty for each 8-bit brightness level, corrected for a gamma of 2.2.
+///
+/// See the [module documentation](self) for how it was generated.
+#[rustfmt::skip]
+pub const GAMMA8: [u8; 256] = [
+    0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1,
+    1, 1, 1, 1, 1, 1, 1, 1, 1, 2, 2, 2, 2, 2, 2, 2,
+    3, 3, 3, 3, 3, 4, 4, 4, 4, 5, 5, 5, 5, 6, 6, 6,
+    6, 7, 7, 7, 8, 8, 8, 9, 9, 9, 10, 10, 11, 11, 11, 12,
+    12, 13, 13, 13, 14, 14, 15, 15, 16, 16, 17, 17, 18, 18, 19, 19,
+    20, 20, 21, 22, 22, 23, 23, 24, 25, 25, 26, 26, 27, 28, 28, 29,
+    30, 30, 31, 32, 33, 33, 34, 35, 35, 36, 37, 38, 39, 39, 40, 41,
+    42, 43, 43, 44, 45, 46, 47, 48, 49, 49, 50, 51, 52, 53, 54, 55,
+    56, 57, 58, 59, 60, 61, 62, 63, 64, 65, 66, 67, 68, 69, 70, 71,
+    73, 74, 75, 76, 77, 78, 79, 81, 82, 83, 84, 85, 87, 88, 89, 90,
+    91, 93, 94, 95, 97, 98, 99, 100, 102, 103, 105, 106, 107, 109, 110, 111,
+    113, 114, 116, 117, 119, 120, 121, 123, 124, 126, 127, 129, 130, 132, 133, 135,
+    137, 138, 140, 141, 143, 145, 146, 148, 149, 151, 153, 154, 156, 158, 159, 161,
+    163, 165, 166, 168, 170, 172, 173, 175, 177, 179, 181, 182, 184, 186, 188, 190,
+    192, 194, 196, 197, 199, 201, 203, 205, 207, 209, 211, 213, 215, 217, 219, 221,
+    223, 225, 227, 229, 231, 234, 236, 238, 240, 242, 244, 246, 248, 251, 253, 255,
+];
+
+/// Returns the duty, out of 255, that looks like brightness `level` out of
+/// 255.
+pub fn gamma_correct(level: u8) -> u8 {
+    GAMMA8[level as usize]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn endpoints_are_off_and_fully_on() {
+        assert_eq!(gamma_correct(0), 0);
+        assert_eq!(gamma_correct(255), 255);
+    }
+
+    #[test]
+    fn table_is_monotonic() {
+        assert!(GAMMA8.windows(2).all(|pair| pair[0] <= pair[1]));
+    }
+
+    #[test]
+    fn table_matches_the_formula() {
+        for (level, &duty) in GAMMA8.iter().enumerate() {
+            let expected = (255.0 * (level as f64 / 255.0).powf(GAMMA as f64)).round();
+            assert_eq!(duty as f64, expected, "level {}", level);
+        }
+    }
+
+    #[test]
+    fn half_brightness_takes_about_a_fifth_of_the_light() {
+        assert_eq!(gamma_correct(128), 56);
+    }
+}
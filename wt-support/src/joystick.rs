@@ -0,0 +1,296 @@
+//! Turning the raw ADC readings of an analog thumbstick into a position.
+//!
+//! A thumbstick is two potentiometers, one per axis, each putting out a
+//! voltage that moves from one end of the supply to the other as the stick
+//! goes from one side to the other, and rests somewhere in the middle when
+//! it's let go. [`Axis`] turns the raw reading of one of them into a position
+//! from -100 at one end to 100 at the other, with 0 at the center it was
+//! calibrated at, and [`Direction::of`] turns the two positions into the
+//! direction the stick is pushed in.
+//!
+//! ```ignore
+//! let x = Axis::new(center_of(x_readings).unwrap_or(2_048), 4_095, 10);
+//! let y = Axis::new(center_of(y_readings).unwrap_or(2_048), 4_095, 10);
+//!
+//! let (x, y) = (x.position(x_raw), y.position(y_raw));
+//! if let Some(direction) = Direction::of(x, y) {
+//!     // Light the LED for the direction.
+//! }
+//! ```
+//!
+//! # Center
+//!
+//! The resting position is rarely exactly halfway: the potentiometers and the
+//! springs that center the stick are only so accurate, so it can easily be a
+//! few percent off to either side, and it's different from one stick to the
+//! next. [`center_of`] averages a number of readings taken while the stick is
+//! known to be resting, such as at startup, to find where the center actually
+//! is. Averaging also smooths out the noise of the individual readings.
+//!
+//! For the position to reach -100 and 100 at both ends whatever the center,
+//! each side of it is scaled on its own, from the center to 0 on one side and
+//! from the center to the full scale reading on the other.
+//!
+//! # Deadzone
+//!
+//! Even after calibrating, a stick let go doesn't come back to exactly the
+//! same reading every time, and the readings are noisy, so a position that's
+//! only a few counts from 0 is more likely noise than the stick being pushed.
+//! A deadzone of `d` reads every position from `-d` to `d` as 0. Outside it,
+//! the position is scaled back up to start from 0 at the edge of the deadzone
+//! and still reach 100 at the end, so pushing the stick out of the deadzone
+//! doesn't make the position jump straight to `d`.
+
+/// One axis of a thumbstick, calibrated to its center.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Axis {
+    center: u16,
+    full_scale: u16,
+    deadzone: u8,
+}
+
+impl Axis {
+    /// Creates an axis with its resting reading at `center`, out of readings
+    /// from 0 to `full_scale`, and the given deadzone, from 0 to 100.
+    ///
+    /// A `center` above `full_scale` is taken as `full_scale`, and a deadzone
+    /// over 99 as 99, so there's always some travel left outside it.
+    pub fn new(center: u16, full_scale: u16, deadzone: u8) -> Self {
+        Axis {
+            center: center.min(full_scale),
+            full_scale,
+            deadzone: deadzone.min(99),
+        }
+    }
+
+    /// Returns the position of the raw reading `raw`, from -100 at 0, to 0 at
+    /// the center, to 100 at the full scale reading, with the deadzone applied.
+    pub fn position(&self, raw: u16) -> i8 {
+        apply_deadzone(self.normalize(raw), self.deadzone)
+    }
+
+    /// Returns the position of `raw` with no deadzone.
+    pub fn normalize(&self, raw: u16) -> i8 {
+        let raw = i32::from(raw.min(self.full_scale));
+        let center = i32::from(self.center);
+        let span = if raw >= center {
+            i32::from(self.full_scale) - center
+        } else {
+            center
+        };
+        if span == 0 {
+            return 0;
+        }
+        ((raw - center) * 100 / span) as i8
+    }
+}
+
+/// Returns `position` with a deadzone of `deadzone` around 0, rescaled so the
+/// result still goes from -100 to 100 outside it.
+pub fn apply_deadzone(position: i8, deadzone: u8) -> i8 {
+    let deadzone = i32::from(deadzone.min(99));
+    let magnitude = i32::from(position).abs();
+    if magnitude <= deadzone {
+        return 0;
+    }
+    let scaled = (magnitude - deadzone) * 100 / (100 - deadzone);
+    (scaled * i32::from(position).signum()) as i8
+}
+
+/// Returns the average of `readings`, rounded to the nearest count, or `None`
+/// if there aren't any.
+pub fn center_of<I: IntoIterator<Item = u16>>(readings: I) -> Option<u16> {
+    let (sum, count) = readings
+        .into_iter()
+        .fold((0_u32, 0_u32), |(sum, count), reading| {
+            (sum + u32::from(reading), count + 1)
+        });
+    if count == 0 {
+        return None;
+    }
+    Some(((sum + count / 2) / count) as u16)
+}
+
+/// The eight directions a stick can be pushed in, with up as north.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Direction {
+    North,
+    NorthEast,
+    East,
+    SouthEast,
+    South,
+    SouthWest,
+    West,
+    NorthWest,
+}
+
+impl Direction {
+    /// All of the directions, clockwise from north.
+    pub const ALL: [Direction; 8] = [
+        Direction::North,
+        Direction::NorthEast,
+        Direction::East,
+        Direction::SouthEast,
+        Direction::South,
+        Direction::SouthWest,
+        Direction::West,
+        Direction::NorthWest,
+    ];
+
+    /// Returns the direction of the position `x`, `y`, with `x` positive to
+    /// the east and `y` positive to the north, or `None` at the center.
+    ///
+    /// Each direction covers the 45 degrees either side of it. The edges
+    /// between them are at 22.5 degrees from an axis, where one coordinate is
+    /// tan(22.5) = 0.414 times the other, which is taken as 0.414 exactly.
+    pub fn of(x: i8, y: i8) -> Option<Direction> {
+        if x == 0 && y == 0 {
+            return None;
+        }
+        let (ax, ay) = (i32::from(x).abs(), i32::from(y).abs());
+        let direction = if ax * 1_000 < ay * 414 {
+            if y > 0 {
+                Direction::North
+            } else {
+                Direction::South
+            }
+        } else if ay * 1_000 < ax * 414 {
+            if x > 0 {
+                Direction::East
+            } else {
+                Direction::West
+            }
+        } else {
+            match (x > 0, y > 0) {
+                (true, true) => Direction::NorthEast,
+                (true, false) => Direction::SouthEast,
+                (false, false) => Direction::SouthWest,
+                (false, true) => Direction::NorthWest,
+            }
+        };
+        Some(direction)
+    }
+
+    /// Returns the index of the direction in [`Self::ALL`], clockwise from
+    /// 0 at north.
+    pub fn index(self) -> usize {
+        self as usize
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn normalize_ends_and_center() {
+        let axis = Axis::new(2_048, 4_095, 0);
+        assert_eq!(axis.normalize(0), -100);
+        assert_eq!(axis.normalize(2_048), 0);
+        assert_eq!(axis.normalize(4_095), 100);
+    }
+
+    #[test]
+    fn normalize_scales_each_side_on_its_own() {
+        // With the center well off halfway, both ends still reach 100.
+        let axis = Axis::new(1_000, 4_095, 0);
+        assert_eq!(axis.normalize(0), -100);
+        assert_eq!(axis.normalize(500), -50);
+        assert_eq!(axis.normalize(1_000 + 3_095 / 2), 49);
+        assert_eq!(axis.normalize(4_095), 100);
+    }
+
+    #[test]
+    fn normalize_clamps_above_full_scale() {
+        let axis = Axis::new(2_048, 4_095, 0);
+        assert_eq!(axis.normalize(u16::MAX), 100);
+    }
+
+    #[test]
+    fn normalize_with_center_at_an_end() {
+        let axis = Axis::new(0, 4_095, 0);
+        assert_eq!(axis.normalize(0), 0);
+        assert_eq!(axis.normalize(4_095), 100);
+
+        let axis = Axis::new(5_000, 4_095, 0);
+        assert_eq!(axis.normalize(4_095), 0);
+        assert_eq!(axis.normalize(0), -100);
+    }
+
+    #[test]
+    fn deadzone_reads_as_center() {
+        for position in -10..=10 {
+            assert_eq!(apply_deadzone(position, 10), 0);
+        }
+        assert_ne!(apply_deadzone(11, 10), 0);
+        assert_ne!(apply_deadzone(-11, 10), 0);
+    }
+
+    #[test]
+    fn deadzone_rescales_outside_it() {
+        assert_eq!(apply_deadzone(100, 10), 100);
+        assert_eq!(apply_deadzone(-100, 10), -100);
+        assert_eq!(apply_deadzone(55, 10), 50);
+        assert_eq!(apply_deadzone(-55, 10), -50);
+        assert_eq!(apply_deadzone(19, 10), 10);
+    }
+
+    #[test]
+    fn zero_deadzone_changes_nothing() {
+        for position in -100..=100 {
+            assert_eq!(apply_deadzone(position, 0), position);
+        }
+    }
+
+    #[test]
+    fn deadzone_is_capped() {
+        assert_eq!(apply_deadzone(99, 200), 0);
+        assert_eq!(apply_deadzone(100, 200), 100);
+    }
+
+    #[test]
+    fn position_applies_the_deadzone() {
+        let axis = Axis::new(2_000, 4_000, 10);
+        assert_eq!(axis.position(2_150), 0);
+        assert_eq!(axis.position(4_000), 100);
+        assert_eq!(axis.position(0), -100);
+        assert_eq!(axis.position(900), -50);
+    }
+
+    #[test]
+    fn center_of_averages_and_rounds() {
+        assert_eq!(center_of([2_000, 2_001, 2_003, 2_004]), Some(2_002));
+        assert_eq!(center_of([1, 2]), Some(2));
+        assert_eq!(center_of([4_095; 64]), Some(4_095));
+        assert_eq!(center_of([]), None);
+    }
+
+    #[test]
+    fn directions_along_the_axes_and_diagonals() {
+        assert_eq!(Direction::of(0, 0), None);
+        assert_eq!(Direction::of(0, 100), Some(Direction::North));
+        assert_eq!(Direction::of(100, 100), Some(Direction::NorthEast));
+        assert_eq!(Direction::of(100, 0), Some(Direction::East));
+        assert_eq!(Direction::of(100, -100), Some(Direction::SouthEast));
+        assert_eq!(Direction::of(0, -100), Some(Direction::South));
+        assert_eq!(Direction::of(-100, -100), Some(Direction::SouthWest));
+        assert_eq!(Direction::of(-100, 0), Some(Direction::West));
+        assert_eq!(Direction::of(-100, 100), Some(Direction::NorthWest));
+    }
+
+    #[test]
+    fn direction_edges_at_22_5_degrees() {
+        // 41 is just under 0.414 of 100, and 42 just over.
+        assert_eq!(Direction::of(41, 100), Some(Direction::North));
+        assert_eq!(Direction::of(42, 100), Some(Direction::NorthEast));
+        assert_eq!(Direction::of(100, 42), Some(Direction::NorthEast));
+        assert_eq!(Direction::of(100, 41), Some(Direction::East));
+    }
+
+    #[test]
+    fn direction_indices_go_clockwise() {
+        for (index, direction) in Direction::ALL.iter().enumerate() {
+            assert_eq!(direction.index(), index);
+        }
+    }
+}
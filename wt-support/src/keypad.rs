@@ -0,0 +1,355 @@
+//! Scanning a matrix keypad.
+//!
+//! A matrix keypad wires its keys in a grid: each key connects one row wire
+//! to one column wire when it's pressed, so a keypad of `R` rows and `C`
+//! columns needs only `R + C` pins for its `R * C` keys. A [`Keypad`] finds
+//! which keys are pressed by driving one row low at a time, with the others
+//! released, and reading the columns. A column reads low only if a key
+//! joining it to the row being driven is pressed.
+//!
+//! ```ignore
+//! const KEYS: [[char; 4]; 4] = [
+//!     ['1', '2', '3', 'A'],
+//!     ['4', '5', '6', 'B'],
+//!     ['7', '8', '9', 'C'],
+//!     ['*', '0', '#', 'D'],
+//! ];
+//!
+//! let mut keypad = Keypad::new(rows, cols, KEYS, 10);
+//! if let Ok(Some(key)) = keypad.scan(&mut delay) {
+//!     // Handle the key.
+//! }
+//! ```
+//!
+//! # Pull-ups
+//!
+//! The columns are inputs, and need pull-ups so that they read high when no
+//! key connects them to the row being driven low. The internal pull-ups of
+//! the GPIO pins, with a value of around 40 kohms, are enough for the short
+//! wires of a keypad on the board.
+//!
+//! The rows are best driven as open-drain outputs, so a row that isn't being
+//! driven low floats rather than being driven high. With push-pull rows,
+//! pressing two keys in the same column at once connects a row driven high
+//! straight to one driven low, shorting the two pins together.
+//!
+//! # Settling
+//!
+//! After a row is driven low, the columns take some time to follow it: the
+//! pull-ups have to charge and discharge the capacitance of the wiring, and
+//! the pins' input synchronizers need a clock cycle or two. Reading the
+//! columns straight away can miss a key, or see it in the row scanned before.
+//! [`Keypad::scan`] waits `settle_us` microseconds after driving each row before
+//! reading the columns. A few microseconds is usually enough, and more are
+//! needed for longer wires or weaker pull-ups.
+//!
+//! # Ghosting
+//!
+//! With no diodes in series with the keys, which most cheap keypads don't
+//! have, pressing three keys at the corners of a rectangle makes the key at
+//! the fourth corner look pressed too: the current flows from its column,
+//! through the other three keys, back to the row being driven. There's no
+//! telling a ghost from a real press, so a scan of more than two keys at once
+//! can't be relied on. [`Keypad::scan`] only reports one key, and
+//! [`Keypad::scan_matrix`] reports everything the columns read, ghosts and all.
+//!
+//! # Bouncing
+//!
+//! The contacts of a key bounce for a few milliseconds when pressed or
+//! released, the same as a button's. [`Keypad`] doesn't debounce, so scan no
+//! more often than every 10 ms or so, or only take a key as pressed when
+//! two scans in a row agree.
+
+use embedded_hal::blocking::delay::DelayUs;
+use embedded_hal::digital::v2::{InputPin, OutputPin};
+
+/// A keypad of `R` rows and `C` columns of keys, with a key map giving what
+/// each key is.
+///
+/// See the [module documentation](self) for how the pins are wired.
+pub struct Keypad<RowPin, ColPin, const R: usize, const C: usize> {
+    rows: [RowPin; R],
+    cols: [ColPin; C],
+    keys: [[char; C]; R],
+    settle_us: u32,
+}
+
+impl<RowPin, ColPin, E, const R: usize, const C: usize> Keypad<RowPin, ColPin, R, C>
+where
+    RowPin: OutputPin<Error = E>,
+    ColPin: InputPin<Error = E>,
+{
+    /// Creates a keypad from its row and column pins, in order, the key map,
+    /// with `keys[row][col]` for the key joining `rows[row]` and `cols[col]`,
+    /// and the time to let the columns settle after driving each row.
+    ///
+    /// The rows should start out released, i.e. set high. The pins aren't
+    /// written until the first scan.
+    pub fn new(rows: [RowPin; R], cols: [ColPin; C], keys: [[char; C]; R], settle_us: u32) -> Self {
+        Keypad {
+            rows,
+            cols,
+            keys,
+            settle_us,
+        }
+    }
+
+    /// Scans every row, and returns an array with `true` for each key that
+    /// reads as pressed, at `[row][col]`.
+    pub fn scan_matrix<D: DelayUs<u32>>(&mut self, delay: &mut D) -> Result<[[bool; C]; R], E> {
+        let mut pressed = [[false; C]; R];
+        for (row, row_pressed) in self.rows.iter_mut().zip(pressed.iter_mut()) {
+            row.set_low()?;
+            delay.delay_us(self.settle_us);
+            // Read every column before putting the row back, even after an
+            // error, so the row is never left driven low.
+            let read = read_columns(&self.cols, row_pressed);
+            row.set_high()?;
+            read?;
+        }
+        Ok(pressed)
+    }
+
+    /// Scans every row, and returns the key of the first pressed key found,
+    /// going across each row in turn from the first, or `None` if no key is
+    /// pressed.
+    pub fn scan<D: DelayUs<u32>>(&mut self, delay: &mut D) -> Result<Option<char>, E> {
+        let pressed = self.scan_matrix(delay)?;
+        Ok(self.first_key(&pressed))
+    }
+
+    /// Returns the key of the first `true` in `pressed`, going across each row
+    /// in turn from the first.
+    pub fn first_key(&self, pressed: &[[bool; C]; R]) -> Option<char> {
+        pressed
+            .iter()
+            .zip(self.keys.iter())
+            .flat_map(|(row_pressed, row_keys)| row_pressed.iter().zip(row_keys.iter()))
+            .find(|(&is_pressed, _)| is_pressed)
+            .map(|(_, &key)| key)
+    }
+
+    /// Releases the pins.
+    pub fn free(self) -> ([RowPin; R], [ColPin; C]) {
+        (self.rows, self.cols)
+    }
+}
+
+fn read_columns<P: InputPin>(cols: &[P], pressed: &mut [bool]) -> Result<(), P::Error> {
+    for (col, is_pressed) in cols.iter().zip(pressed.iter_mut()) {
+        *is_pressed = col.is_low()?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use core::convert::Infallible;
+    use std::{cell::RefCell, rc::Rc};
+
+    const KEYS: [[char; 4]; 4] = [
+        ['1', '2', '3', 'A'],
+        ['4', '5', '6', 'B'],
+        ['7', '8', '9', 'C'],
+        ['*', '0', '#', 'D'],
+    ];
+
+    /// A 4x4 keypad with no diodes: the levels the rows are driven to, and
+    /// which keys are held down.
+    #[derive(Default)]
+    struct Matrix {
+        row_low: [bool; 4],
+        held: [[bool; 4]; 4],
+        settled: bool,
+    }
+
+    type SharedMatrix = Rc<RefCell<Matrix>>;
+
+    impl Matrix {
+        /// Returns whether `col` is pulled low, through any path of held keys
+        /// to a row driven low, which is what gives ghosting.
+        fn col_low(&self, col: usize) -> bool {
+            let mut rows = self.row_low;
+            let mut cols = [false; 4];
+            // Spread "connected to a low row" across held keys until nothing
+            // changes.
+            loop {
+                let mut changed = false;
+                for (row_low, row_held) in rows.iter_mut().zip(self.held.iter()) {
+                    for (col_low, &held) in cols.iter_mut().zip(row_held.iter()) {
+                        if held && *row_low != *col_low {
+                            *row_low = true;
+                            *col_low = true;
+                            changed = true;
+                        }
+                    }
+                }
+                if !changed {
+                    return cols[col];
+                }
+            }
+        }
+    }
+
+    struct MockRow {
+        index: usize,
+        matrix: SharedMatrix,
+    }
+
+    impl OutputPin for MockRow {
+        type Error = Infallible;
+
+        fn set_low(&mut self) -> Result<(), Self::Error> {
+            let mut matrix = self.matrix.borrow_mut();
+            matrix.row_low[self.index] = true;
+            matrix.settled = false;
+            Ok(())
+        }
+
+        fn set_high(&mut self) -> Result<(), Self::Error> {
+            let mut matrix = self.matrix.borrow_mut();
+            matrix.row_low[self.index] = false;
+            matrix.settled = false;
+            Ok(())
+        }
+    }
+
+    /// A column input that reads high until the delay has been waited after
+    /// a row changes, like a real one that hasn't settled yet.
+    struct MockCol {
+        index: usize,
+        matrix: SharedMatrix,
+    }
+
+    impl InputPin for MockCol {
+        type Error = Infallible;
+
+        fn is_high(&self) -> Result<bool, Self::Error> {
+            self.is_low().map(|low| !low)
+        }
+
+        fn is_low(&self) -> Result<bool, Self::Error> {
+            let matrix = self.matrix.borrow();
+            Ok(matrix.settled && matrix.col_low(self.index))
+        }
+    }
+
+    /// A delay that settles the matrix if it's long enough.
+    struct MockDelay {
+        matrix: SharedMatrix,
+        settle_us: u32,
+    }
+
+    impl DelayUs<u32> for MockDelay {
+        fn delay_us(&mut self, us: u32) {
+            if us >= self.settle_us {
+                self.matrix.borrow_mut().settled = true;
+            }
+        }
+    }
+
+    type MockKeypad = Keypad<MockRow, MockCol, 4, 4>;
+
+    fn keypad(settle_us: u32) -> (MockKeypad, MockDelay, SharedMatrix) {
+        let matrix = SharedMatrix::default();
+        let rows = [0, 1, 2, 3].map(|index| MockRow {
+            index,
+            matrix: matrix.clone(),
+        });
+        let cols = [0, 1, 2, 3].map(|index| MockCol {
+            index,
+            matrix: matrix.clone(),
+        });
+        let delay = MockDelay {
+            matrix: matrix.clone(),
+            settle_us: 5,
+        };
+        (Keypad::new(rows, cols, KEYS, settle_us), delay, matrix)
+    }
+
+    #[test]
+    fn no_key_pressed() {
+        let (mut keypad, mut delay, _) = keypad(5);
+        assert_eq!(keypad.scan(&mut delay), Ok(None));
+    }
+
+    #[test]
+    fn every_key_maps_to_its_key() {
+        let (mut keypad, mut delay, matrix) = keypad(5);
+        for (r, row_keys) in KEYS.iter().enumerate() {
+            for (c, &key) in row_keys.iter().enumerate() {
+                matrix.borrow_mut().held = Default::default();
+                matrix.borrow_mut().held[r][c] = true;
+                assert_eq!(keypad.scan(&mut delay), Ok(Some(key)));
+            }
+        }
+    }
+
+    #[test]
+    fn scan_matrix_reports_the_held_keys() {
+        let (mut keypad, mut delay, matrix) = keypad(5);
+        matrix.borrow_mut().held[1][2] = true;
+        matrix.borrow_mut().held[3][0] = true;
+        let mut expected = [[false; 4]; 4];
+        expected[1][2] = true;
+        expected[3][0] = true;
+        assert_eq!(keypad.scan_matrix(&mut delay), Ok(expected));
+    }
+
+    #[test]
+    fn first_key_in_scan_order_wins() {
+        let (mut keypad, mut delay, matrix) = keypad(5);
+        matrix.borrow_mut().held[2][1] = true;
+        matrix.borrow_mut().held[0][3] = true;
+        assert_eq!(keypad.scan(&mut delay), Ok(Some('A')));
+    }
+
+    #[test]
+    fn rows_are_left_high_after_a_scan() {
+        let (mut keypad, mut delay, matrix) = keypad(5);
+        matrix.borrow_mut().held[0][0] = true;
+        keypad.scan(&mut delay).unwrap();
+        assert_eq!(matrix.borrow().row_low, [false; 4]);
+    }
+
+    #[test]
+    fn too_short_a_settling_delay_misses_keys() {
+        let (mut keypad, mut delay, matrix) = keypad(1);
+        matrix.borrow_mut().held[1][1] = true;
+        assert_eq!(keypad.scan(&mut delay), Ok(None));
+    }
+
+    #[test]
+    fn three_keys_in_a_rectangle_ghost_the_fourth() {
+        // Holding 1, 2, and 4 joins row 1 to column 1 through them, so 5
+        // reads as pressed too.
+        let (mut keypad, mut delay, matrix) = keypad(5);
+        {
+            let mut matrix = matrix.borrow_mut();
+            matrix.held[0][0] = true;
+            matrix.held[0][1] = true;
+            matrix.held[1][0] = true;
+        }
+        let pressed = keypad.scan_matrix(&mut delay).unwrap();
+        assert!(pressed[1][1]);
+        assert_eq!(
+            pressed
+                .iter()
+                .flatten()
+                .filter(|&&is_pressed| is_pressed)
+                .count(),
+            4
+        );
+    }
+
+    #[test]
+    fn first_key_uses_the_key_map() {
+        let (keypad, _, _) = keypad(5);
+        let mut pressed = [[false; 4]; 4];
+        assert_eq!(keypad.first_key(&pressed), None);
+        pressed[3][2] = true;
+        assert_eq!(keypad.first_key(&pressed), Some('#'));
+    }
+}
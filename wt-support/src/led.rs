@@ -0,0 +1,326 @@
+//! A board LED, whatever board it's on.
+//!
+//! The user LEDs of the STM32F3DISCOVERY and the NUCLEO-F767ZI are GPIO pins
+//! of different HALs, with different types, so code that just wants to turn
+//! an LED on or off would otherwise have to be written once per board.
+//! [`UserLed`] is the interface that code can be written against instead, and
+//! [`PinLed`] implements it for an output pin of either HAL, or any other pin
+//! with the embedded-hal `OutputPin` trait.
+//!
+//! ```ignore
+//! let mut ld1 = PinLed::new(gpiob.pb0.into_push_pull_output());
+//! let mut ld2 = PinLed::new(gpiob.pb7.into_push_pull_output());
+//! loop {
+//!     blink_all(&mut [&mut ld1, &mut ld2], &mut delay, 100, 400);
+//! }
+//! ```
+//!
+//! The LEDs are taken as trait objects, `&mut dyn UserLed`, so LEDs on pins
+//! of different types, e.g. on different ports, can go in the same slice
+//! without erasing their pin numbers first.
+//!
+//! # Active level
+//!
+//! The user LEDs of both boards are wired from the pin to GND, so they light
+//! with the pin high: they're active high. Plenty of LEDs are wired the other
+//! way, from the supply to the pin, so they light with the pin low instead,
+//! e.g. the user LEDs of many other boards, or an external LED whose
+//! anode is on 3V. Driving one of those as if it were active high silently
+//! inverts it, so it's on whenever it should be off.
+//!
+//! [`PinLed::with_level`] takes the [`ActiveLevel`] of the LED, and drives
+//! the pin to it for [`UserLed::on`], and to the other level for
+//! [`UserLed::off`]. The code using the LED only ever says on or off, and the
+//! wiring is stated once, where the LED is created.
+//!
+//! ```ignore
+//! let mut external = PinLed::with_level(gpiod.pd0.into_push_pull_output(), ActiveLevel::Low);
+//! external.on(); // Drives PD0 low.
+//! ```
+
+use embedded_hal::blocking::delay::DelayMs;
+use embedded_hal::digital::v2::OutputPin;
+
+/// An LED that can be turned on and off.
+pub trait UserLed {
+    /// Turns the LED on.
+    fn on(&mut self);
+
+    /// Turns the LED off.
+    fn off(&mut self);
+
+    /// Turns the LED on if it's off, and off if it's on.
+    fn toggle(&mut self);
+}
+
+/// The level of its pin that lights an LED.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ActiveLevel {
+    /// Lit with the pin high, for an LED wired from the pin to GND.
+    High,
+    /// Lit with the pin low, for an LED wired from the supply to the pin.
+    Low,
+}
+
+/// An LED driven by an output pin, lit with the pin at its active level.
+///
+/// The state of the LED is kept here rather than read back from the pin, so
+/// the pin only has to implement `OutputPin`, and errors from the pin are
+/// ignored, since the pins of the HALs used here can't fail.
+pub struct PinLed<P> {
+    pin: P,
+    level: ActiveLevel,
+    lit: bool,
+}
+
+impl<P: OutputPin> PinLed<P> {
+    /// Creates an active high LED on `pin`, turning it off to start with.
+    pub fn new(pin: P) -> Self {
+        Self::with_level(pin, ActiveLevel::High)
+    }
+
+    /// Creates an LED on `pin`, lit with the pin at `level`, turning it off
+    /// to start with.
+    pub fn with_level(pin: P, level: ActiveLevel) -> Self {
+        let mut led = PinLed {
+            pin,
+            level,
+            lit: false,
+        };
+        led.off();
+        led
+    }
+
+    /// Returns whether the LED is on.
+    pub fn is_on(&self) -> bool {
+        self.lit
+    }
+
+    /// Returns the level of the pin that lights the LED.
+    pub fn active_level(&self) -> ActiveLevel {
+        self.level
+    }
+
+    /// Drives the pin to light the LED if `lit`, and to turn it off if not.
+    fn drive(&mut self, lit: bool) {
+        if lit == (self.level == ActiveLevel::High) {
+            self.pin.set_high().ok();
+        } else {
+            self.pin.set_low().ok();
+        }
+        self.lit = lit;
+    }
+
+    /// Releases the pin.
+    pub fn free(self) -> P {
+        self.pin
+    }
+}
+
+impl<P: OutputPin> UserLed for PinLed<P> {
+    fn on(&mut self) {
+        self.drive(true);
+    }
+
+    fn off(&mut self) {
+        self.drive(false);
+    }
+
+    fn toggle(&mut self) {
+        if self.lit {
+            self.off();
+        } else {
+            self.on();
+        }
+    }
+}
+
+/// Blinks every LED in `leds` once, together: on for `on_ms` milliseconds,
+/// then off for `off_ms`.
+pub fn blink_all<D: DelayMs<u32>>(
+    leds: &mut [&mut dyn UserLed],
+    delay: &mut D,
+    on_ms: u32,
+    off_ms: u32,
+) {
+    for led in leds.iter_mut() {
+        led.on();
+    }
+    delay.delay_ms(on_ms);
+    for led in leds.iter_mut() {
+        led.off();
+    }
+    delay.delay_ms(off_ms);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::mock::{Log, MockDelay, MockLed, MockPin, Op};
+
+    fn led(index: usize, log: &Log) -> PinLed<MockPin> {
+        PinLed::new(MockPin::new(index, log))
+    }
+
+    #[test]
+    fn new_turns_the_led_off() {
+        let log = Log::default();
+        let led = led(0, &log);
+        assert!(!led.is_on());
+        assert_eq!(*log.borrow(), [Op::Low(0)]);
+    }
+
+    #[test]
+    fn on_and_off_drive_the_pin() {
+        let log = Log::default();
+        let mut led = led(0, &log);
+        log.borrow_mut().clear();
+
+        led.on();
+        assert!(led.is_on());
+        led.off();
+        assert!(!led.is_on());
+        assert_eq!(*log.borrow(), [Op::High(0), Op::Low(0)]);
+    }
+
+    #[test]
+    fn toggle_alternates() {
+        let log = Log::default();
+        let mut led = led(0, &log);
+        log.borrow_mut().clear();
+
+        led.toggle();
+        led.toggle();
+        led.toggle();
+        assert!(led.is_on());
+        assert_eq!(*log.borrow(), [Op::High(0), Op::Low(0), Op::High(0)]);
+    }
+
+    #[test]
+    fn toggle_follows_on_and_off() {
+        let log = Log::default();
+        let mut led = led(0, &log);
+        led.on();
+        log.borrow_mut().clear();
+
+        led.toggle();
+        assert_eq!(*log.borrow(), [Op::Low(0)]);
+    }
+
+    #[test]
+    fn blink_all_turns_every_led_on_then_off() {
+        let log = Log::default();
+        let mut led0 = led(0, &log);
+        let mut led1 = led(1, &log);
+        let mut delay = MockDelay::new(&log);
+        log.borrow_mut().clear();
+
+        blink_all(&mut [&mut led0, &mut led1], &mut delay, 100, 400);
+        assert_eq!(
+            *log.borrow(),
+            [
+                Op::High(0),
+                Op::High(1),
+                Op::Delay(100),
+                Op::Low(0),
+                Op::Low(1),
+                Op::Delay(400),
+            ]
+        );
+        assert!(!led0.is_on() && !led1.is_on());
+    }
+
+    #[test]
+    fn blink_all_takes_any_user_led() {
+        let log = Log::default();
+        let mut pin_led = led(0, &log);
+        let mut mock_led = MockLed::new(&log);
+        let mut delay = MockDelay::new(&log);
+        log.borrow_mut().clear();
+
+        blink_all(&mut [&mut pin_led, &mut mock_led], &mut delay, 1, 1);
+        assert_eq!(
+            *log.borrow(),
+            [
+                Op::High(0),
+                Op::On,
+                Op::Delay(1),
+                Op::Low(0),
+                Op::Off,
+                Op::Delay(1),
+            ]
+        );
+    }
+
+    #[test]
+    fn blink_all_with_no_leds_still_waits() {
+        let log = Log::default();
+        let mut delay = MockDelay::new(&log);
+        blink_all(&mut [], &mut delay, 100, 400);
+        assert_eq!(*log.borrow(), [Op::Delay(100), Op::Delay(400)]);
+    }
+
+    fn active_low_led(index: usize, log: &Log) -> PinLed<MockPin> {
+        PinLed::with_level(MockPin::new(index, log), ActiveLevel::Low)
+    }
+
+    #[test]
+    fn new_is_active_high() {
+        let log = Log::default();
+        assert_eq!(led(0, &log).active_level(), ActiveLevel::High);
+    }
+
+    #[test]
+    fn active_low_starts_off_with_the_pin_high() {
+        let log = Log::default();
+        let led = active_low_led(0, &log);
+        assert!(!led.is_on());
+        assert_eq!(led.active_level(), ActiveLevel::Low);
+        assert_eq!(*log.borrow(), [Op::High(0)]);
+    }
+
+    #[test]
+    fn active_low_on_and_off_drive_the_pin_inverted() {
+        let log = Log::default();
+        let mut led = active_low_led(0, &log);
+        log.borrow_mut().clear();
+
+        led.on();
+        assert!(led.is_on());
+        led.off();
+        assert!(!led.is_on());
+        led.toggle();
+        assert!(led.is_on());
+        assert_eq!(*log.borrow(), [Op::Low(0), Op::High(0), Op::Low(0)]);
+    }
+
+    #[test]
+    fn blink_all_lights_both_polarities_together() {
+        let log = Log::default();
+        let mut high = led(0, &log);
+        let mut low = active_low_led(1, &log);
+        let mut delay = MockDelay::new(&log);
+        log.borrow_mut().clear();
+
+        blink_all(&mut [&mut high, &mut low], &mut delay, 100, 400);
+        assert_eq!(
+            *log.borrow(),
+            [
+                Op::High(0),
+                Op::Low(1),
+                Op::Delay(100),
+                Op::Low(0),
+                Op::High(1),
+                Op::Delay(400),
+            ]
+        );
+    }
+
+    #[test]
+    fn free_returns_the_pin() {
+        let log = Log::default();
+        let pin = led(3, &log).free();
+        assert_eq!(pin.id, 3);
+    }
+}
@@ -0,0 +1,47 @@
+//! Support code shared by the examples in this work-through.
+//!
+//! Everything in here is written against the Cortex-M core peripherals and the
+//! `embedded-hal` traits rather than a specific HAL, so it can be used from the
+//! examples for both the STM32F3DISCOVERY and the NUCLEO-F767ZI.
+
+#![cfg_attr(not(test), no_std)]
+
+pub mod adc;
+pub mod blink;
+pub mod config_store;
+pub mod crc;
+pub mod debug_pin;
+pub mod delay;
+pub mod dwt_delay;
+pub mod gamma;
+pub mod joystick;
+pub mod keypad;
+pub mod led;
+pub mod log;
+pub mod lsm303;
+pub mod millis;
+pub mod notes;
+pub mod one_shot;
+pub mod panic;
+pub mod post;
+pub mod press;
+pub mod profile;
+pub mod proto;
+pub mod quadrature;
+pub mod reset_reason;
+pub mod scheduler;
+pub mod servo;
+pub mod seven_seg;
+pub mod shift_reg;
+pub mod soft_i2c;
+pub mod soft_pwm;
+pub mod stepper;
+pub mod task_watchdog;
+pub mod tim_delay;
+pub mod uart_errors;
+pub mod uart_timeout;
+pub mod uart_tx;
+pub mod version;
+
+#[cfg(test)]
+mod mock;
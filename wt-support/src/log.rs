@@ -0,0 +1,271 @@
+//! Logging macros with a backend picked by cargo features.
+//!
+//! [`log_info!`](crate::log_info), [`log_warn!`](crate::log_warn), and
+//! [`log_error!`](crate::log_error) take the same arguments as `format!()`,
+//! and write the message out on a line of its own, after the time since boot
+//! from [`crate::millis`] and the level:
+//!
+//! ```text
+//! [    1.250] INFO  Started, 3 sensors found
+//! [    6.003] WARN  Sensor 2 didn't answer, retrying
+//! ```
+//!
+//! Where the line goes depends on which of these features the invoking
+//! crate has enabled in its own `Cargo.toml`:
+//!
+//! ```toml
+//! [features]
+//! default = ["log-uart"]
+//! log-uart = []
+//! log-itm = []
+//! log-rtt = ["dep:rtt-target"]
+//! ```
+//!
+//! - `log-uart`: To the function passed to [`set_uart_writer`], e.g. one that
+//!   queues it on a [`crate::uart_tx::BufferedTx`], so logging doesn't block.
+//! - `log-itm`: Out ITM stimulus port 0, as in the itm example, if the
+//!   debugger has enabled it.
+//! - `log-rtt`: To RTT channel 0 with `rtt-target`, which the invoking crate
+//!   has to depend on and set up with `rtt_init_print!()`.
+//!
+//! With more than one enabled, every line goes to each of them, and with none,
+//! the macros compile to nothing at all, leaving no formatting code or
+//! strings in the binary. As with [`panic_handler!`](crate::panic_handler),
+//! the features belong to the invoking crate, since the `cfg` gates in the
+//! macros are evaluated there, and it has to declare all three, even the ones
+//! it doesn't use, for the compiler to know them.
+//!
+//! # Why macros
+//!
+//! Application code only ever calls the macros, and never names a backend,
+//! so retargeting the logs from a UART to the debug probe is a change of
+//! feature, not of code, e.g. `--no-default-features --features log-rtt` to
+//! keep the UART free for something else. The only part that changes with the
+//! backend is the setup in `main()`. A function taking a `fmt::Arguments`
+//! could pick the backend too, but only the macros can take the formatting
+//! away entirely when logging is off, and check the format string against its
+//! arguments at compile time either way.
+
+use core::cell::Cell;
+use core::fmt;
+
+use cortex_m::interrupt::{self, Mutex};
+use cortex_m::peripheral::ITM;
+
+/// How serious a log message is.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Level {
+    /// Normal operation.
+    Info,
+    /// Something unexpected that the program can carry on from.
+    Warn,
+    /// Something that stops the program doing what it should.
+    Error,
+}
+
+impl Level {
+    /// Returns the name of the level, as written in the log.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Level::Info => "INFO",
+            Level::Warn => "WARN",
+            Level::Error => "ERROR",
+        }
+    }
+}
+
+impl fmt::Display for Level {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // pad() rather than write_str(), so the width in "{:<5}" applies.
+        f.pad(self.as_str())
+    }
+}
+
+/// A log message with its time and level, displayed as a whole line of the
+/// log, ending in `"\r\n"`.
+pub struct Record<'a> {
+    /// Milliseconds since boot.
+    pub ms: u32,
+    /// How serious it is.
+    pub level: Level,
+    /// The formatted message.
+    pub args: fmt::Arguments<'a>,
+}
+
+impl<'a> Record<'a> {
+    /// Creates a record of a message logged `ms` milliseconds after boot.
+    pub fn new(ms: u32, level: Level, args: fmt::Arguments<'a>) -> Self {
+        Record { ms, level, args }
+    }
+}
+
+impl fmt::Display for Record<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "[{:>5}.{:03}] {:<5} {}\r\n",
+            self.ms / 1_000,
+            self.ms % 1_000,
+            self.level,
+            self.args
+        )
+    }
+}
+
+/// A function that writes a line of the log to a UART.
+pub type UartWriter = fn(fmt::Arguments);
+
+/// The function `log-uart` writes each line with, if one has been set.
+static UART_WRITER: Mutex<Cell<Option<UartWriter>>> = Mutex::new(Cell::new(None));
+
+/// Sets the function the `log-uart` backend writes each line with.
+///
+/// Lines logged before it's set are dropped. The function owns the UART, so
+/// it's up to it how the line gets there. It can be called from interrupt
+/// handlers as well as `main()`, if they log, so it should lock whatever it
+/// writes to.
+pub fn set_uart_writer(write: UartWriter) {
+    interrupt::free(|cs| UART_WRITER.borrow(cs).set(Some(write)));
+}
+
+/// Writes a record with the function set with [`set_uart_writer`].
+///
+/// Public for use by the macros.
+#[doc(hidden)]
+pub fn uart(record: &Record) {
+    let writer = interrupt::free(|cs| UART_WRITER.borrow(cs).get());
+    if let Some(write) = writer {
+        write(format_args!("{}", record));
+    }
+}
+
+/// ITMENA bit of ITM TCR.
+const TCR_ITMENA: u32 = 1 << 0;
+
+/// Writes a record out ITM stimulus port 0, if the debugger has enabled it.
+///
+/// Public for use by the macros.
+#[doc(hidden)]
+pub fn itm(record: &Record) {
+    interrupt::free(|_| {
+        // Interrupts are disabled, so nothing else logging can be using the
+        // ITM while it's borrowed here. Code that writes to the ITM itself
+        // shouldn't log as well.
+        let itm = unsafe { &mut *ITM::PTR };
+        if itm.tcr.read() & TCR_ITMENA != 0 && itm.ter[0].read() & 1 != 0 {
+            cortex_m::itm::write_fmt(&mut itm.stim[0], format_args!("{}", record));
+        }
+    });
+}
+
+/// Logs a message at a level to the backends selected by the invoking
+/// crate's features.
+///
+/// Use [`log_info!`](crate::log_info), [`log_warn!`](crate::log_warn), or
+/// [`log_error!`](crate::log_error) instead. See the
+/// [module docs](crate::log) for the features.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __log {
+    ($level:expr, $($arg:tt)+) => {
+        // Matching on format_args!() rather than binding it with let keeps
+        // the temporaries it borrows the arguments through alive for the
+        // whole match, so the record can be used in more than one statement.
+        #[cfg(any(feature = "log-uart", feature = "log-itm", feature = "log-rtt"))]
+        match format_args!($($arg)+) {
+            args => {
+                let record = $crate::log::Record::new($crate::millis::millis(), $level, args);
+
+                #[cfg(feature = "log-uart")]
+                $crate::log::uart(&record);
+
+                #[cfg(feature = "log-itm")]
+                $crate::log::itm(&record);
+
+                #[cfg(feature = "log-rtt")]
+                ::rtt_target::rprint!("{}", record);
+            }
+        }
+
+        // With logging off, the arguments are still "used", so turning it off
+        // doesn't bring up warnings about unused variables. Nothing's
+        // formatted, so this compiles to nothing.
+        #[cfg(not(any(feature = "log-uart", feature = "log-itm", feature = "log-rtt")))]
+        {
+            let _ = format_args!($($arg)+);
+        }
+    };
+}
+
+/// Logs a message at [`Level::Info`](crate::log::Level::Info).
+///
+/// Takes the same arguments as `format!()`. See the
+/// [module docs](crate::log) for where it goes.
+#[macro_export]
+macro_rules! log_info {
+    ($($arg:tt)+) => {
+        $crate::__log!($crate::log::Level::Info, $($arg)+)
+    };
+}
+
+/// Logs a message at [`Level::Warn`](crate::log::Level::Warn).
+///
+/// Takes the same arguments as `format!()`. See the
+/// [module docs](crate::log) for where it goes.
+#[macro_export]
+macro_rules! log_warn {
+    ($($arg:tt)+) => {
+        $crate::__log!($crate::log::Level::Warn, $($arg)+)
+    };
+}
+
+/// Logs a message at [`Level::Error`](crate::log::Level::Error).
+///
+/// Takes the same arguments as `format!()`. See the
+/// [module docs](crate::log) for where it goes.
+#[macro_export]
+macro_rules! log_error {
+    ($($arg:tt)+) => {
+        $crate::__log!($crate::log::Level::Error, $($arg)+)
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Returns the line of the log for a message.
+    fn line(ms: u32, level: Level, args: fmt::Arguments) -> String {
+        Record::new(ms, level, args).to_string()
+    }
+
+    #[test]
+    fn record_has_time_and_padded_level() {
+        assert_eq!(
+            line(1_250, Level::Info, format_args!("{} sensors", 3)),
+            "[    1.250] INFO  3 sensors\r\n"
+        );
+    }
+
+    #[test]
+    fn milliseconds_keep_leading_zeros() {
+        assert_eq!(
+            line(6_003, Level::Warn, format_args!("late")),
+            "[    6.003] WARN  late\r\n"
+        );
+    }
+
+    #[test]
+    fn long_uptime_widens_the_seconds() {
+        assert_eq!(
+            line(u32::MAX, Level::Error, format_args!("x")),
+            "[4294967.295] ERROR x\r\n"
+        );
+    }
+
+    #[test]
+    fn levels_are_ordered_by_severity() {
+        assert!(Level::Info < Level::Warn);
+        assert!(Level::Warn < Level::Error);
+    }
+}
@@ -0,0 +1,355 @@
+//! A driver for the LSM303DLHC accelerometer and magnetometer.
+//!
+//! The LSM303DLHC on the STM32F3DISCOVERY is two sensors in one package, each
+//! at its own I2C address. [`Lsm303`] sets both up and reads them, returning
+//! each sample as a [`Vector`] with the axes in X, Y, Z order:
+//!
+//! ```ignore
+//! let mut sensor = Lsm303::new(i2c1);
+//! sensor.init()?;
+//! let accel = sensor.read_accel()?; // mg
+//! let mag = sensor.read_mag()?; // counts
+//! ```
+//!
+//! # Generic over the bus
+//!
+//! The driver only uses the blocking I2C traits from `embedded-hal`, `Write`
+//! and `WriteRead`, rather than a HAL's I2C type. Anything that implements
+//! them works: the I2C peripheral of any HAL, the bit-banged
+//! [`SoftI2c`](crate::soft_i2c::SoftI2c), or a mock that records what's
+//! written to it and hands back canned register contents, which is how the
+//! driver's unit tests check the register addresses and the decoding on the
+//! host, without the sensor. These are the `embedded-hal` 0.2 traits, which
+//! the HALs used here implement. In `embedded-hal` 1.0 they're merged into a
+//! single `I2c` trait, and porting the driver is a matter of changing the
+//! bounds.
+//!
+//! # Configuration
+//!
+//! [`Lsm303::init`] sets them up the same as the tilt-compass example:
+//!
+//! - Accelerometer: 100 Hz, all three axes, high resolution (12-bit) at the
+//!   default full scale of +/-2 g, where one LSB is 1 mg.
+//! - Magnetometer: 30 Hz, continuous conversion, at the most sensitive full
+//!   scale of +/-1.3 gauss, with gains of [`MAG_GAIN_XY`] and [`MAG_GAIN_Z`].
+//!
+//! # Register layouts
+//!
+//! The two halves of the chip came from different designs, and it shows:
+//!
+//! - The accelerometer's output registers are little-endian, in X, Y, Z
+//!   order, and left-justified, so the 12-bit value is shifted down by 4.
+//!   Reading more than one register at once needs the MSB of the register
+//!   address set, to make it increment the address after each byte.
+//! - The magnetometer's are big-endian and in X, Z, Y order, and it always
+//!   increments.
+//!
+//! [`Lsm303::read_accel`] and [`Lsm303::read_mag`] both read all six output
+//! registers in one transfer, and sort this out, so both return X, Y, Z.
+
+use embedded_hal::blocking::i2c::{Write, WriteRead};
+
+/// I2C address of the accelerometer.
+pub const ACCEL_ADDRESS: u8 = 0x19;
+/// I2C address of the magnetometer.
+pub const MAG_ADDRESS: u8 = 0x1E;
+
+/// What the accelerometer's WHO_AM_I register reads.
+///
+/// The register is documented for the LSM303AGR on newer revisions of the
+/// board. The LSM303DLHC on older ones doesn't document it, but reads back the
+/// same value from the same address, as the soft-i2c example shows.
+pub const WHO_AM_I_A_VALUE: u8 = 0x33;
+
+/// Magnetometer counts per gauss on the X and Y axes at +/-1.3 gauss.
+pub const MAG_GAIN_XY: f32 = 1100.0;
+/// Magnetometer counts per gauss on the Z axis at +/-1.3 gauss, which is less
+/// sensitive than X and Y.
+pub const MAG_GAIN_Z: f32 = 980.0;
+
+// Accelerometer registers, and the bit that makes the address increment.
+const WHO_AM_I_A: u8 = 0x0F;
+const CTRL_REG1_A: u8 = 0x20;
+const CTRL_REG4_A: u8 = 0x23;
+const OUT_X_L_A: u8 = 0x28;
+const AUTO_INCREMENT: u8 = 0x80;
+
+// Magnetometer registers.
+const CRA_REG_M: u8 = 0x00;
+const CRB_REG_M: u8 = 0x01;
+const MR_REG_M: u8 = 0x02;
+const OUT_X_H_M: u8 = 0x03;
+
+// CTRL_REG1_A: 100 Hz, normal power mode, X, Y, and Z enabled.
+const CTRL_REG1_A_100HZ_XYZ: u8 = 0x57;
+// CTRL_REG4_A: High resolution, +/-2 g.
+const CTRL_REG4_A_HR: u8 = 0x08;
+// CRA_REG_M: 30 Hz.
+const CRA_REG_M_30HZ: u8 = 0x14;
+// CRB_REG_M: +/-1.3 gauss.
+const CRB_REG_M_1_3_GAUSS: u8 = 0x20;
+// MR_REG_M: Continuous conversion.
+const MR_REG_M_CONTINUOUS: u8 = 0x00;
+
+/// A sample of all three axes.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Vector {
+    /// X axis.
+    pub x: i16,
+    /// Y axis.
+    pub y: i16,
+    /// Z axis.
+    pub z: i16,
+}
+
+impl Vector {
+    /// Returns the axes as an array, in X, Y, Z order.
+    pub fn to_array(self) -> [i16; 3] {
+        [self.x, self.y, self.z]
+    }
+}
+
+/// An LSM303DLHC on the I2C bus `I2C`.
+pub struct Lsm303<I2C> {
+    i2c: I2C,
+}
+
+impl<I2C, E> Lsm303<I2C>
+where
+    I2C: Write<Error = E> + WriteRead<Error = E>,
+{
+    /// Creates the driver on `i2c`, without talking to the sensor. Call
+    /// [`init`](Self::init) to set it up before reading it.
+    pub fn new(i2c: I2C) -> Self {
+        Lsm303 { i2c }
+    }
+
+    /// Sets up both sensors, as described in the
+    /// [module documentation](self).
+    pub fn init(&mut self) -> Result<(), E> {
+        self.write_register(ACCEL_ADDRESS, CTRL_REG1_A, CTRL_REG1_A_100HZ_XYZ)?;
+        self.write_register(ACCEL_ADDRESS, CTRL_REG4_A, CTRL_REG4_A_HR)?;
+        self.write_register(MAG_ADDRESS, CRA_REG_M, CRA_REG_M_30HZ)?;
+        self.write_register(MAG_ADDRESS, CRB_REG_M, CRB_REG_M_1_3_GAUSS)?;
+        self.write_register(MAG_ADDRESS, MR_REG_M, MR_REG_M_CONTINUOUS)
+    }
+
+    /// Reads the accelerometer's WHO_AM_I register, which should be
+    /// [`WHO_AM_I_A_VALUE`].
+    pub fn accel_id(&mut self) -> Result<u8, E> {
+        let mut id = [0_u8];
+        self.i2c.write_read(ACCEL_ADDRESS, &[WHO_AM_I_A], &mut id)?;
+        Ok(id[0])
+    }
+
+    /// Reads the acceleration on each axis, in mg.
+    pub fn read_accel(&mut self) -> Result<Vector, E> {
+        let mut buf = [0_u8; 6];
+        self.i2c
+            .write_read(ACCEL_ADDRESS, &[OUT_X_L_A | AUTO_INCREMENT], &mut buf)?;
+
+        let axis = |i: usize| i16::from_le_bytes([buf[2 * i], buf[2 * i + 1]]) >> 4;
+        Ok(Vector {
+            x: axis(0),
+            y: axis(1),
+            z: axis(2),
+        })
+    }
+
+    /// Reads the magnetic field on each axis, in counts. Divide by
+    /// [`MAG_GAIN_XY`] or [`MAG_GAIN_Z`] to get gauss.
+    pub fn read_mag(&mut self) -> Result<Vector, E> {
+        let mut buf = [0_u8; 6];
+        self.i2c.write_read(MAG_ADDRESS, &[OUT_X_H_M], &mut buf)?;
+
+        Ok(Vector {
+            x: i16::from_be_bytes([buf[0], buf[1]]),
+            z: i16::from_be_bytes([buf[2], buf[3]]),
+            y: i16::from_be_bytes([buf[4], buf[5]]),
+        })
+    }
+
+    /// Releases the I2C bus.
+    pub fn free(self) -> I2C {
+        self.i2c
+    }
+
+    fn write_register(&mut self, address: u8, register: u8, value: u8) -> Result<(), E> {
+        self.i2c.write(address, &[register, value])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::collections::VecDeque;
+    use std::vec::Vec;
+
+    /// A transfer made on the bus.
+    #[derive(Clone, Debug, PartialEq, Eq)]
+    enum Op {
+        Write(u8, Vec<u8>),
+        WriteRead(u8, Vec<u8>),
+    }
+
+    #[derive(Debug, PartialEq, Eq)]
+    struct BusError;
+
+    /// A bus that records every transfer, and answers reads with the next of
+    /// `replies`.
+    #[derive(Default)]
+    struct MockI2c {
+        ops: Vec<Op>,
+        replies: VecDeque<Vec<u8>>,
+        fail: bool,
+    }
+
+    impl MockI2c {
+        fn replying(reply: &[u8]) -> Self {
+            MockI2c {
+                replies: VecDeque::from([reply.to_vec()]),
+                ..Default::default()
+            }
+        }
+    }
+
+    impl Write for MockI2c {
+        type Error = BusError;
+
+        fn write(&mut self, address: u8, bytes: &[u8]) -> Result<(), BusError> {
+            self.ops.push(Op::Write(address, bytes.to_vec()));
+            if self.fail {
+                Err(BusError)
+            } else {
+                Ok(())
+            }
+        }
+    }
+
+    impl WriteRead for MockI2c {
+        type Error = BusError;
+
+        fn write_read(
+            &mut self,
+            address: u8,
+            bytes: &[u8],
+            buffer: &mut [u8],
+        ) -> Result<(), BusError> {
+            self.ops.push(Op::WriteRead(address, bytes.to_vec()));
+            if self.fail {
+                return Err(BusError);
+            }
+            let reply = self.replies.pop_front().expect("no reply queued");
+            assert_eq!(reply.len(), buffer.len());
+            buffer.copy_from_slice(&reply);
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn new_does_not_touch_the_bus() {
+        let sensor = Lsm303::new(MockI2c::default());
+        assert!(sensor.free().ops.is_empty());
+    }
+
+    #[test]
+    fn init_writes_both_sensors_configuration() {
+        let mut sensor = Lsm303::new(MockI2c::default());
+        sensor.init().unwrap();
+        assert_eq!(
+            sensor.free().ops,
+            [
+                Op::Write(0x19, std::vec![0x20, 0x57]),
+                Op::Write(0x19, std::vec![0x23, 0x08]),
+                Op::Write(0x1E, std::vec![0x00, 0x14]),
+                Op::Write(0x1E, std::vec![0x01, 0x20]),
+                Op::Write(0x1E, std::vec![0x02, 0x00]),
+            ]
+        );
+    }
+
+    #[test]
+    fn accel_id_reads_who_am_i_a() {
+        let mut sensor = Lsm303::new(MockI2c::replying(&[0x33]));
+        assert_eq!(sensor.accel_id(), Ok(WHO_AM_I_A_VALUE));
+        assert_eq!(sensor.free().ops, [Op::WriteRead(0x19, std::vec![0x0F])]);
+    }
+
+    #[test]
+    fn read_accel_reads_from_out_x_l_a_with_auto_increment() {
+        let mut sensor = Lsm303::new(MockI2c::replying(&[0; 6]));
+        sensor.read_accel().unwrap();
+        assert_eq!(sensor.free().ops, [Op::WriteRead(0x19, std::vec![0xA8])]);
+    }
+
+    #[test]
+    fn read_accel_decodes_little_endian_left_justified_samples() {
+        // 1000 mg, -500 mg, and -1 mg, shifted up by 4.
+        let reply = [0x80, 0x3E, 0xC0, 0xE0, 0xF0, 0xFF];
+        let mut sensor = Lsm303::new(MockI2c::replying(&reply));
+        assert_eq!(
+            sensor.read_accel(),
+            Ok(Vector {
+                x: 1000,
+                y: -500,
+                z: -1
+            })
+        );
+    }
+
+    #[test]
+    fn read_accel_drops_the_unused_low_bits() {
+        let reply = [0x8F, 0x3E, 0x0F, 0x00, 0x00, 0x00];
+        let mut sensor = Lsm303::new(MockI2c::replying(&reply));
+        assert_eq!(
+            sensor.read_accel(),
+            Ok(Vector {
+                x: 1000,
+                y: 0,
+                z: 0
+            })
+        );
+    }
+
+    #[test]
+    fn read_mag_reads_from_out_x_h_m() {
+        let mut sensor = Lsm303::new(MockI2c::replying(&[0; 6]));
+        sensor.read_mag().unwrap();
+        assert_eq!(sensor.free().ops, [Op::WriteRead(0x1E, std::vec![0x03])]);
+    }
+
+    #[test]
+    fn read_mag_decodes_big_endian_samples_in_x_z_y_order() {
+        // X = 300, Z = -2, Y = 1100.
+        let reply = [0x01, 0x2C, 0xFF, 0xFE, 0x04, 0x4C];
+        let mut sensor = Lsm303::new(MockI2c::replying(&reply));
+        assert_eq!(
+            sensor.read_mag(),
+            Ok(Vector {
+                x: 300,
+                y: 1100,
+                z: -2
+            })
+        );
+    }
+
+    #[test]
+    fn bus_errors_are_passed_on() {
+        let mut sensor = Lsm303::new(MockI2c {
+            fail: true,
+            ..Default::default()
+        });
+        assert_eq!(sensor.init(), Err(BusError));
+        assert_eq!(sensor.read_accel(), Err(BusError));
+        assert_eq!(sensor.read_mag(), Err(BusError));
+        // init() stops at the first write that fails.
+        assert_eq!(sensor.free().ops.len(), 3);
+    }
+
+    #[test]
+    fn vector_to_array_is_in_x_y_z_order() {
+        let v = Vector { x: 1, y: 2, z: 3 };
+        assert_eq!(v.to_array(), [1, 2, 3]);
+    }
+}
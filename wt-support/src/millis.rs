@@ -0,0 +1,98 @@
+//! Millisecond tick counter driven by SysTick.
+//!
+//! [`init`] configures SysTick to fire an exception every 1 ms and the
+//! `SysTick` handler defined in this module increments a global counter. The
+//! counter can then be read from anywhere with [`millis`], which makes it
+//! possible to check how much time has passed without blocking the way
+//! `delay_ms` does.
+//!
+//! ```ignore
+//! wt_support::millis::init(core_periphs.SYST, clocks.sysclk().0);
+//!
+//! let mut last_toggle = wt_support::millis::millis();
+//! loop {
+//!     if wt_support::millis::elapsed_since(last_toggle) >= 500 {
+//!         last_toggle = wt_support::millis::millis();
+//!         led.toggle().ok();
+//!     }
+//!
+//!     // Other work can be done here while waiting.
+//! }
+//! ```
+//!
+//! # Wraparound
+//!
+//! The counter is a `u32`, so it wraps back around to zero after
+//! 2^32 ms, which is about 49.7 days. Comparing two tick values directly, e.g.
+//! `millis() > start + 500`, breaks when that happens. Subtracting with
+//! `wrapping_sub` does not, since the difference between two values modulo
+//! 2^32 is still the number of ticks between them as long as less than 49.7
+//! days have passed. [`elapsed_since`] does the subtraction this way, so always
+//! compare the elapsed time against the interval rather than comparing tick
+//! values.
+
+use core::sync::atomic::{AtomicU32, Ordering};
+
+use cortex_m::peripheral::syst::SystClkSource;
+use cortex_m::peripheral::SYST;
+use cortex_m_rt::exception;
+
+/// Number of milliseconds since [`init`] was called.
+static MILLIS: AtomicU32 = AtomicU32::new(0);
+
+/// Configures SysTick to fire an exception every millisecond.
+///
+/// `sysclk_hz` is the frequency of the core clock, which SysTick is clocked
+/// from. SysTick is consumed since the HAL `Delay` types also use it and
+/// reprogramming it would break the tick.
+pub fn init(mut syst: SYST, sysclk_hz: u32) {
+    // The reload value is the number of core clock cycles in 1 ms minus one,
+    // since the counter counts down to zero inclusive. The register is only 24
+    // bits wide, which is plenty for 1 ms at any of the clock speeds used here.
+    syst.set_clock_source(SystClkSource::Core);
+    syst.set_reload(sysclk_hz / 1_000 - 1);
+    syst.clear_current();
+    syst.enable_counter();
+    syst.enable_interrupt();
+}
+
+/// Returns the number of milliseconds since [`init`] was called.
+///
+/// The count wraps around to zero after about 49.7 days.
+pub fn millis() -> u32 {
+    MILLIS.load(Ordering::Relaxed)
+}
+
+/// Returns the number of milliseconds since the tick count `start`.
+///
+/// Uses wrapping subtraction so the result is correct across a wraparound of
+/// the counter.
+pub fn elapsed_since(start: u32) -> u32 {
+    millis().wrapping_sub(start)
+}
+
+/// A source of the current time in milliseconds.
+///
+/// Code that needs the time takes one of these rather than calling [`millis`]
+/// itself, so it can be tested on the host with a fake clock.
+pub trait TickSource {
+    /// Returns the number of milliseconds since some fixed point, wrapping
+    /// around after `u32::MAX`.
+    fn now_ms(&self) -> u32;
+}
+
+/// The SysTick tick counter from this module, which has to be started with
+/// [`init`].
+#[derive(Clone, Copy, Debug)]
+pub struct SysTickMillis;
+
+impl TickSource for SysTickMillis {
+    fn now_ms(&self) -> u32 {
+        millis()
+    }
+}
+
+#[exception]
+fn SysTick() {
+    MILLIS.fetch_add(1, Ordering::Relaxed);
+}
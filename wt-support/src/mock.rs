@@ -0,0 +1,184 @@
+//! Recording mocks of pins, delays, and LEDs for the unit tests.
+//!
+//! Each mock pushes what it's asked to do onto a [`Log`] shared with the
+//! other mocks and the test, so a test can check the order things happened
+//! in across all of them, e.g. that a pin went high before a delay.
+//!
+//! ```ignore
+//! let log = Log::default();
+//! let mut led = PinLed::new(MockPin::new(0, &log));
+//! let mut delay = MockDelay::new(&log);
+//! blink_all(&mut [&mut led], &mut delay, 100, 400);
+//! assert_eq!(
+//!     *log.borrow(),
+//!     [Op::Low(0), Op::High(0), Op::Delay(100), Op::Low(0), Op::Delay(400)]
+//! );
+//! ```
+
+use core::convert::Infallible;
+use std::{cell::RefCell, collections::VecDeque, rc::Rc, vec::Vec};
+
+use embedded_hal::blocking::delay::{DelayMs, DelayUs};
+use embedded_hal::digital::v2::{InputPin, OutputPin};
+
+use crate::led::UserLed;
+
+/// Something a mock was asked to do, or a test marked, in the order it
+/// happened.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Op {
+    /// The [`MockPin`] with this ID was set high.
+    High(usize),
+    /// The [`MockPin`] with this ID was set low.
+    Low(usize),
+    /// A [`MockDelay`] was asked to wait this many milliseconds.
+    Delay(u32),
+    /// A [`MockDelay`] was asked to wait this many microseconds.
+    DelayUs(u32),
+    /// A [`MockLed`] was turned on, by `on()` or `toggle()`.
+    On,
+    /// A [`MockLed`] was turned off, by `off()` or `toggle()`.
+    Off,
+    /// Pushed by a test itself, e.g. from a closure run by the code under
+    /// test, to check where it ran.
+    Mark,
+}
+
+/// The log the mocks push onto, shared with the test.
+pub type Log = Rc<RefCell<Vec<Op>>>;
+
+/// An output pin that logs each level it's set to, with its ID.
+///
+/// It can be read back too, like an open-drain line. Reads return the levels
+/// given to [`MockPin::driven`] first, as if another device were driving the
+/// line, and then the level the pin was last set to.
+pub struct MockPin {
+    pub id: usize,
+    log: Log,
+    driven: RefCell<VecDeque<bool>>,
+}
+
+impl MockPin {
+    pub fn new(id: usize, log: &Log) -> Self {
+        MockPin {
+            id,
+            log: log.clone(),
+            driven: RefCell::default(),
+        }
+    }
+
+    /// Queues levels for the next reads of the pin to return, one per read.
+    pub fn driven(mut self, levels: &[bool]) -> Self {
+        self.driven.get_mut().extend(levels);
+        self
+    }
+}
+
+impl OutputPin for MockPin {
+    type Error = Infallible;
+
+    fn set_low(&mut self) -> Result<(), Self::Error> {
+        self.log.borrow_mut().push(Op::Low(self.id));
+        Ok(())
+    }
+
+    fn set_high(&mut self) -> Result<(), Self::Error> {
+        self.log.borrow_mut().push(Op::High(self.id));
+        Ok(())
+    }
+}
+
+impl InputPin for MockPin {
+    type Error = Infallible;
+
+    fn is_high(&self) -> Result<bool, Self::Error> {
+        let driven = self.driven.borrow_mut().pop_front();
+        Ok(driven.unwrap_or_else(|| is_high(&self.log, self.id)))
+    }
+
+    fn is_low(&self) -> Result<bool, Self::Error> {
+        self.is_high().map(|high| !high)
+    }
+}
+
+/// Returns whether the [`MockPin`] with this ID was last set high, or `false`
+/// if it hasn't been set at all.
+pub fn is_high(log: &Log, id: usize) -> bool {
+    log.borrow()
+        .iter()
+        .rev()
+        .find_map(|op| match *op {
+            Op::High(pin) if pin == id => Some(true),
+            Op::Low(pin) if pin == id => Some(false),
+            _ => None,
+        })
+        .unwrap_or(false)
+}
+
+/// A delay that logs how long it was asked to wait, and returns straight
+/// away.
+pub struct MockDelay {
+    log: Log,
+}
+
+impl MockDelay {
+    pub fn new(log: &Log) -> Self {
+        MockDelay { log: log.clone() }
+    }
+}
+
+impl DelayMs<u32> for MockDelay {
+    fn delay_ms(&mut self, ms: u32) {
+        self.log.borrow_mut().push(Op::Delay(ms));
+    }
+}
+
+impl DelayMs<u16> for MockDelay {
+    fn delay_ms(&mut self, ms: u16) {
+        self.log.borrow_mut().push(Op::Delay(ms.into()));
+    }
+}
+
+impl DelayUs<u32> for MockDelay {
+    fn delay_us(&mut self, us: u32) {
+        self.log.borrow_mut().push(Op::DelayUs(us));
+    }
+}
+
+/// A [`UserLed`] with no pin behind it, which logs the state it ends up in
+/// after each call.
+pub struct MockLed {
+    lit: bool,
+    log: Log,
+}
+
+impl MockLed {
+    /// Creates a LED that starts off, without logging anything.
+    pub fn new(log: &Log) -> Self {
+        MockLed {
+            lit: false,
+            log: log.clone(),
+        }
+    }
+
+    fn set(&mut self, lit: bool) {
+        self.lit = lit;
+        self.log
+            .borrow_mut()
+            .push(if lit { Op::On } else { Op::Off });
+    }
+}
+
+impl UserLed for MockLed {
+    fn on(&mut self) {
+        self.set(true);
+    }
+
+    fn off(&mut self) {
+        self.set(false);
+    }
+
+    fn toggle(&mut self) {
+        self.set(!self.lit);
+    }
+}
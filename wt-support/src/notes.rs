@@ -0,0 +1,160 @@
+//! Musical note names to frequencies.
+//!
+//! [`frequency_hz`] maps a note name in scientific pitch notation, like `"A4"`
+//! or `"C#5"`, to its frequency in twelve-tone equal temperament with A4 tuned
+//! to 440 Hz, which is what's needed to play a melody on a buzzer by setting
+//! the PWM frequency per note.
+//!
+//! ```ignore
+//! let hz = wt_support::notes::frequency_hz("A4"); // Some(440)
+//! ```
+
+/// Frequencies of the notes of octave 8, C8 through B8, in hundredths of a
+/// hertz.
+///
+/// Each octave down halves the frequency, so the lower octaves are derived
+/// from these by shifting rather than storing a row per octave. Starting from
+/// the highest octave keeps the rounding error of the lowest notes below a
+/// hundredth of a hertz.
+const OCTAVE_8_CENTIHERTZ: [u32; 12] = [
+    418_601, // C8
+    443_492, // C#8/Db8
+    469_864, // D8
+    497_803, // D#8/Eb8
+    527_404, // E8
+    558_765, // F8
+    591_991, // F#8/Gb8
+    627_193, // G8
+    664_488, // G#8/Ab8
+    704_000, // A8
+    745_862, // A#8/Bb8
+    790_213, // B8
+];
+
+/// Highest octave that can be looked up.
+pub const MAX_OCTAVE: u8 = 8;
+
+/// Returns the frequency in hertz of the note `name`, rounded to the nearest
+/// whole hertz.
+///
+/// The name is a note letter from `A` to `G`, an optional `#` (sharp) or `b`
+/// (flat), and an octave number from 0 to [`MAX_OCTAVE`], e.g. `"C4"` for
+/// middle C, `"F#3"`, or `"Bb5"`. Returns `None` if the name isn't a valid
+/// note or is outside of that range.
+pub fn frequency_hz(name: &str) -> Option<u32> {
+    let bytes = name.as_bytes();
+    let (&letter, rest) = bytes.split_first()?;
+
+    // Semitones above C within the octave.
+    let mut semitone: i8 = match letter {
+        b'C' => 0,
+        b'D' => 2,
+        b'E' => 4,
+        b'F' => 5,
+        b'G' => 7,
+        b'A' => 9,
+        b'B' => 11,
+        _ => return None,
+    };
+
+    let rest = match rest.split_first() {
+        Some((b'#', rest)) => {
+            semitone += 1;
+            rest
+        }
+        Some((b'b', rest)) => {
+            semitone -= 1;
+            rest
+        }
+        _ => rest,
+    };
+
+    let octave = match rest {
+        [digit @ b'0'..=b'9'] => (digit - b'0') as i8,
+        _ => return None,
+    };
+
+    // B# and Cb cross into the next and previous octave, e.g. Cb4 is B3.
+    let (semitone, octave) = match semitone {
+        -1 => (11, octave - 1),
+        12 => (0, octave + 1),
+        _ => (semitone, octave),
+    };
+    if !(0..=MAX_OCTAVE as i8).contains(&octave) {
+        return None;
+    }
+
+    let divisor = 1 << (MAX_OCTAVE as i8 - octave);
+    let centihertz = OCTAVE_8_CENTIHERTZ[semitone as usize];
+
+    // Divide with rounding to the nearest hertz, in one step so the rounding
+    // only happens once.
+    Some((centihertz + 50 * divisor) / (100 * divisor))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn concert_a_is_440_hz() {
+        assert_eq!(frequency_hz("A4"), Some(440));
+    }
+
+    #[test]
+    fn octaves_double_the_frequency() {
+        assert_eq!(frequency_hz("A3"), Some(220));
+        assert_eq!(frequency_hz("A5"), Some(880));
+        assert_eq!(frequency_hz("A8"), Some(7040));
+    }
+
+    #[test]
+    fn natural_notes_match_the_table() {
+        assert_eq!(frequency_hz("C4"), Some(262));
+        assert_eq!(frequency_hz("D4"), Some(294));
+        assert_eq!(frequency_hz("E4"), Some(330));
+        assert_eq!(frequency_hz("F4"), Some(349));
+        assert_eq!(frequency_hz("G4"), Some(392));
+        assert_eq!(frequency_hz("B4"), Some(494));
+        assert_eq!(frequency_hz("C5"), Some(523));
+    }
+
+    #[test]
+    fn lowest_and_highest_notes() {
+        assert_eq!(frequency_hz("C0"), Some(16));
+        assert_eq!(frequency_hz("A0"), Some(28));
+        assert_eq!(frequency_hz("B8"), Some(7902));
+    }
+
+    #[test]
+    fn sharps_and_flats_are_enharmonic() {
+        assert_eq!(frequency_hz("C#4"), Some(277));
+        assert_eq!(frequency_hz("Db4"), frequency_hz("C#4"));
+        assert_eq!(frequency_hz("A#4"), Some(466));
+        assert_eq!(frequency_hz("Bb4"), frequency_hz("A#4"));
+    }
+
+    #[test]
+    fn sharps_and_flats_cross_octaves() {
+        assert_eq!(frequency_hz("Cb4"), frequency_hz("B3"));
+        assert_eq!(frequency_hz("B#3"), frequency_hz("C4"));
+    }
+
+    #[test]
+    fn invalid_names_are_rejected() {
+        assert_eq!(frequency_hz(""), None);
+        assert_eq!(frequency_hz("A"), None);
+        assert_eq!(frequency_hz("H4"), None);
+        assert_eq!(frequency_hz("a4"), None);
+        assert_eq!(frequency_hz("A#"), None);
+        assert_eq!(frequency_hz("A44"), None);
+        assert_eq!(frequency_hz("A4 "), None);
+    }
+
+    #[test]
+    fn out_of_range_octaves_are_rejected() {
+        assert_eq!(frequency_hz("A9"), None);
+        assert_eq!(frequency_hz("Cb0"), None);
+        assert_eq!(frequency_hz("B#8"), None);
+    }
+}
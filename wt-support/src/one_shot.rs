@@ -0,0 +1,350 @@
+//! Single pulses of a set width, timed and ended by a timer in hardware.
+//!
+//! [`OneShotPulse`] drives a timer output high for a precise width each time
+//! it's triggered, using the timer's one-pulse mode (OPM). Triggering it only
+//! starts the timer's counter. The timer raises the pin, lowers it again once
+//! the width is up, and stops itself, all without the CPU, so
+//! [`OneShotPulse::trigger`] returns straight away.
+//!
+//! ```ignore
+//! let timer_clock_hz = pac::TIM4::timer_clock(&clocks).0;
+//! let mut pulse = OneShotPulse::new(Tim4OnePulse(tim4), timer_clock_hz, 10)?;
+//! pulse.trigger()?; // A 10 us pulse on TIM4_CH1.
+//! ```
+//!
+//! # Compared with a delay
+//!
+//! The obvious way to make a pulse, setting a pin high, delaying, and setting
+//! it low, has the CPU time it, so:
+//!
+//! - Anything that delays the CPU stretches the pulse: an interrupt in the
+//!   middle of the delay adds however long its handler takes, and the delay
+//!   itself is only exact to a tick or so, as [`crate::tim_delay`] goes
+//!   into. The width jitters from one pulse to the next.
+//! - The CPU can't do anything else until the pulse is over.
+//!
+//! A timer counts the width in cycles of its own clock, from the edge that
+//! starts the pulse to the one that ends it, in hardware. Every pulse comes
+//! out the same width, to within a cycle of the timer clock, whatever the CPU
+//! is doing, and the CPU is free for the whole of it.
+//!
+//! # One-pulse mode
+//!
+//! In one-pulse mode, the timer counts from 0 up to ARR once, and at the
+//! update event at the end, clears CEN and stops, instead of wrapping around
+//! and counting again. The output channel is set to PWM mode 2, which drives
+//! it low while CNT is below CCR, and high from CCR on. So starting the
+//! counter gives:
+//!
+//! ```text
+//! CNT:    0 | 1 2 3 ... ARR | 0 (stopped)
+//! Output: low | high ...high | low
+//! ```
+//!
+//! With CCR at 1, the pulse starts one tick after the counter does, and is
+//! ARR ticks wide. And since CEN stays set until the end of the pulse,
+//! reading it back tells whether a pulse is still going.
+//!
+//! The one-time setup of the timer is left to the application, since the
+//! registers differ between timers, and it's a few lines with the PAC:
+//!
+//! 1. OPM in CR1, for one-pulse mode.
+//! 2. OCxM in CCMRx to PWM mode 2, and CCxE in CCER to enable the output.
+//! 3. On the advanced timers, TIM1, TIM8, and TIM15 to TIM17, MOE in BDTR
+//!    too, or the outputs stay off.
+//! 4. The pin switched to the timer channel's alternate function.
+//!
+//! [`OnePulseTimer`] is then just the timing registers, CEN, and reading CEN
+//! back.
+//!
+//! # Timing
+//!
+//! [`pulse_timing`] works out the timing registers for a width. It picks the
+//! smallest prescaler that fits the width in the 16-bit ARR, so the width is
+//! counted in the shortest ticks it can be: with a 48 MHz timer clock, a
+//! pulse of up to 1.3 ms is counted at the full 48 MHz, in steps of 21 ns.
+//! Longer pulses are counted in longer ticks, up to 2^32 cycles of the timer
+//! clock, about 89 s at 48 MHz. [`OneShotPulse::width_ns`] gives the width
+//! that actually comes out, after rounding to whole ticks.
+//!
+//! The pulse starts a tick, plus however long it takes the CPU to set CEN,
+//! after [`OneShotPulse::trigger`] is called, so its start is as exact as the
+//! code that calls it. For a start that's exact too, the timer's slave mode
+//! controller can set CEN itself on an edge of one of its inputs, and this
+//! module still works out the timing for it.
+
+/// Values of the timer registers for a pulse.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PulseTiming {
+    /// PSC: the timer counts once every `prescaler + 1` cycles of its clock.
+    pub prescaler: u16,
+    /// CCR of the output channel, the tick the pulse starts on.
+    pub compare: u16,
+    /// ARR, the last tick of the pulse.
+    pub reload: u16,
+}
+
+impl PulseTiming {
+    /// Returns the width of the pulse in timer ticks.
+    pub fn width_ticks(&self) -> u32 {
+        u32::from(self.reload) - u32::from(self.compare) + 1
+    }
+
+    /// Returns the width of the pulse in nanoseconds, for a timer clocked at
+    /// `timer_clock_hz`.
+    pub fn width_ns(&self, timer_clock_hz: u32) -> u64 {
+        let cycles = u64::from(self.width_ticks()) * (u64::from(self.prescaler) + 1);
+        cycles * 1_000_000_000 / u64::from(timer_clock_hz.max(1))
+    }
+}
+
+/// Returns the timing register values for a pulse `width_us` wide from a
+/// timer clocked at `timer_clock_hz`.
+///
+/// Returns `None` if the width is 0, rounds to less than a tick, or is too
+/// long for the timer, over 2^32 cycles of its clock.
+pub fn pulse_timing(timer_clock_hz: u32, width_us: u32) -> Option<PulseTiming> {
+    let cycles = u64::from(width_us) * u64::from(timer_clock_hz) / 1_000_000;
+    if cycles == 0 {
+        return None;
+    }
+
+    // With CCR at 1, ARR is the width in ticks, so the width has to fit in
+    // 16 bits.
+    let max_ticks = u64::from(u16::MAX);
+    let divider = cycles.div_ceil(max_ticks);
+    if divider > 1 << 16 {
+        return None;
+    }
+    let ticks = (cycles + divider / 2) / divider;
+
+    Some(PulseTiming {
+        prescaler: (divider - 1) as u16,
+        compare: 1,
+        reload: ticks.min(max_ticks) as u16,
+    })
+}
+
+/// Errors from a [`OneShotPulse`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OneShotError {
+    /// The width can't be made with the timer, see [`pulse_timing`].
+    WidthOutOfRange,
+    /// The last pulse is still going.
+    Busy,
+}
+
+/// A timer set up in one-pulse mode, as described in the
+/// [module documentation](self).
+pub trait OnePulseTimer {
+    /// Writes PSC, CCR, and ARR from `timing`, and generates an update event
+    /// to load the new PSC. Only called with the counter stopped.
+    fn set_timing(&mut self, timing: PulseTiming);
+
+    /// Sets CEN, starting a pulse.
+    fn start(&mut self);
+
+    /// Returns whether CEN is set, i.e. a pulse is still going.
+    fn is_running(&self) -> bool;
+}
+
+/// A pulse output of a set width on a timer in one-pulse mode.
+pub struct OneShotPulse<T> {
+    timer: T,
+    timer_clock_hz: u32,
+    timing: PulseTiming,
+}
+
+impl<T: OnePulseTimer> OneShotPulse<T> {
+    /// Sets up pulses `width_us` wide on `timer`, which is clocked at
+    /// `timer_clock_hz`.
+    pub fn new(mut timer: T, timer_clock_hz: u32, width_us: u32) -> Result<Self, OneShotError> {
+        let timing = pulse_timing(timer_clock_hz, width_us).ok_or(OneShotError::WidthOutOfRange)?;
+        timer.set_timing(timing);
+        Ok(OneShotPulse {
+            timer,
+            timer_clock_hz,
+            timing,
+        })
+    }
+
+    /// Sends a pulse, unless the last one is still going.
+    pub fn trigger(&mut self) -> Result<(), OneShotError> {
+        if self.timer.is_running() {
+            return Err(OneShotError::Busy);
+        }
+        self.timer.start();
+        Ok(())
+    }
+
+    /// Returns whether a pulse is still going.
+    pub fn is_busy(&self) -> bool {
+        self.timer.is_running()
+    }
+
+    /// Changes the width of the pulses to `width_us`, unless a pulse is
+    /// still going.
+    pub fn set_width_us(&mut self, width_us: u32) -> Result<(), OneShotError> {
+        if self.timer.is_running() {
+            return Err(OneShotError::Busy);
+        }
+        self.timing =
+            pulse_timing(self.timer_clock_hz, width_us).ok_or(OneShotError::WidthOutOfRange)?;
+        self.timer.set_timing(self.timing);
+        Ok(())
+    }
+
+    /// Returns the timing register values in use.
+    pub fn timing(&self) -> PulseTiming {
+        self.timing
+    }
+
+    /// Returns the width of the pulses in nanoseconds, as they come out after
+    /// rounding to whole ticks.
+    pub fn width_ns(&self) -> u64 {
+        self.timing.width_ns(self.timer_clock_hz)
+    }
+
+    /// Releases the timer.
+    pub fn free(self) -> T {
+        self.timer
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::cell::Cell;
+    use std::vec::Vec;
+
+    const CLOCK_HZ: u32 = 48_000_000;
+
+    /// A timer whose pulses last until `finish()` is called.
+    #[derive(Default)]
+    struct MockTimer {
+        timings: Vec<PulseTiming>,
+        starts: u32,
+        running: Cell<bool>,
+    }
+
+    impl MockTimer {
+        fn finish(&self) {
+            self.running.set(false);
+        }
+    }
+
+    impl OnePulseTimer for MockTimer {
+        fn set_timing(&mut self, timing: PulseTiming) {
+            assert!(!self.running.get());
+            self.timings.push(timing);
+        }
+
+        fn start(&mut self) {
+            self.starts += 1;
+            self.running.set(true);
+        }
+
+        fn is_running(&self) -> bool {
+            self.running.get()
+        }
+    }
+
+    #[test]
+    fn short_pulse_is_counted_at_the_full_timer_clock() {
+        let timing = pulse_timing(CLOCK_HZ, 10).unwrap();
+        assert_eq!(
+            timing,
+            PulseTiming {
+                prescaler: 0,
+                compare: 1,
+                reload: 480
+            }
+        );
+        assert_eq!(timing.width_ticks(), 480);
+        assert_eq!(timing.width_ns(CLOCK_HZ), 10_000);
+    }
+
+    #[test]
+    fn longest_pulse_at_full_clock_fits_in_arr() {
+        // 1365 us is 65_520 cycles at 48 MHz.
+        let timing = pulse_timing(CLOCK_HZ, 1_365).unwrap();
+        assert_eq!(timing.prescaler, 0);
+        assert_eq!(timing.reload, 65_520);
+    }
+
+    #[test]
+    fn long_pulse_uses_the_smallest_prescaler_that_fits() {
+        let timing = pulse_timing(CLOCK_HZ, 1_000_000).unwrap();
+        // 48_000_000 cycles / 65_535 rounds up to a divider of 733.
+        assert_eq!(timing.prescaler, 732);
+        assert_eq!(timing.reload, 65_484);
+        let error_ns = timing.width_ns(CLOCK_HZ).abs_diff(1_000_000_000);
+        assert!(error_ns < 15_300, "off by {} ns", error_ns);
+    }
+
+    #[test]
+    fn zero_and_sub_tick_widths_are_rejected() {
+        assert_eq!(pulse_timing(CLOCK_HZ, 0), None);
+        // A 1 us pulse is less than a tick of a 500 kHz clock.
+        assert_eq!(pulse_timing(500_000, 1), None);
+    }
+
+    #[test]
+    fn too_long_a_pulse_is_rejected() {
+        // 2^32 cycles at 48 MHz is 89.48 s.
+        assert!(pulse_timing(CLOCK_HZ, 89_000_000).is_some());
+        assert_eq!(pulse_timing(CLOCK_HZ, 90_000_000), None);
+    }
+
+    #[test]
+    fn new_sets_the_timing_without_starting() {
+        let pulse = OneShotPulse::new(MockTimer::default(), CLOCK_HZ, 10).unwrap();
+        assert_eq!(pulse.width_ns(), 10_000);
+        let timer = pulse.free();
+        assert_eq!(timer.timings.len(), 1);
+        assert_eq!(timer.starts, 0);
+    }
+
+    #[test]
+    fn new_rejects_a_width_out_of_range() {
+        assert!(matches!(
+            OneShotPulse::new(MockTimer::default(), CLOCK_HZ, 0),
+            Err(OneShotError::WidthOutOfRange)
+        ));
+    }
+
+    #[test]
+    fn trigger_starts_a_pulse_only_when_idle() {
+        let mut pulse = OneShotPulse::new(MockTimer::default(), CLOCK_HZ, 10).unwrap();
+        assert_eq!(pulse.trigger(), Ok(()));
+        assert!(pulse.is_busy());
+        assert_eq!(pulse.trigger(), Err(OneShotError::Busy));
+
+        pulse.timer.finish();
+        assert!(!pulse.is_busy());
+        assert_eq!(pulse.trigger(), Ok(()));
+        assert_eq!(pulse.free().starts, 2);
+    }
+
+    #[test]
+    fn width_only_changes_between_pulses() {
+        let mut pulse = OneShotPulse::new(MockTimer::default(), CLOCK_HZ, 10).unwrap();
+        pulse.trigger().unwrap();
+        assert_eq!(pulse.set_width_us(20), Err(OneShotError::Busy));
+        assert_eq!(pulse.width_ns(), 10_000);
+
+        pulse.timer.finish();
+        assert_eq!(pulse.set_width_us(20), Ok(()));
+        assert_eq!(pulse.width_ns(), 20_000);
+        assert_eq!(pulse.timing().reload, 960);
+        assert_eq!(pulse.free().timings.len(), 2);
+    }
+
+    #[test]
+    fn bad_width_keeps_the_old_one() {
+        let mut pulse = OneShotPulse::new(MockTimer::default(), CLOCK_HZ, 10).unwrap();
+        assert_eq!(pulse.set_width_us(0), Err(OneShotError::WidthOutOfRange));
+        assert_eq!(pulse.width_ns(), 10_000);
+    }
+}
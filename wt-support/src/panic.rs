@@ -0,0 +1,270 @@
+//! Panic handler selection with cargo features.
+//!
+//! Exactly one panic handler has to be linked into a `no_std` binary. Rather
+//! than hard-coding `use panic_halt as _;` and commenting out the
+//! alternatives, an example invokes [`panic_handler!`](crate::panic_handler)
+//! once at the crate root and picks the behavior with one of these features
+//! in its own `Cargo.toml`.
+//!
+//! ```toml
+//! [features]
+//! default = ["panic-halt"]
+//! panic-halt = []
+//! panic-semihosting = []
+//! panic-itm = []
+//! panic-probe = []
+//! panic-sos = []
+//! ```
+//!
+//! The features belong to the example rather than to this crate since cargo
+//! unifies the features of a dependency across everything built together, so
+//! two examples selecting different handlers through this crate could never be
+//! built at the same time. The `cfg` gates in the macro are evaluated in the
+//! crate that invokes it, so they see the example's features instead.
+//!
+//! To pick a different handler, build without the default feature.
+//!
+//! ``` console
+//! $ cargo build --no-default-features --features panic-itm
+//! ```
+//!
+//! # Blinking an SOS
+//!
+//! All of the other handlers need a debugger or a probe to show anything, so
+//! on a board running on its own, a panic just looks like it froze. With
+//! `panic-sos`, the handler blinks SOS in Morse code on an LED, forever, so
+//! it's plain to see that it panicked.
+//!
+//! The handler can't get hold of an LED by itself, though. The peripherals
+//! have already been taken by the time it runs, by the code that panicked,
+//! so `take()` gives `None`, and conjuring up a pin out of nowhere, with
+//! `steal()`, would both be unsafe and skip setting it up as an output. So
+//! the LED has to be handed over in advance, while setting everything else
+//! up, with [`register_sos_led`]. It's kept in a static until the handler
+//! takes it back out.
+//!
+//! ```ignore
+//! let ld3: &'static mut Ld3 = cortex_m::singleton!(: Ld3 = PinLed::new(pin)).unwrap();
+//! wt_support::panic::register_sos_led(ld3, clocks.sysclk().0);
+//! ```
+//!
+//! That means the LED is given up for good: the rest of the program can't
+//! use it. And a panic before it's registered has no LED to blink, so the
+//! handler halts like `panic-halt` instead. The SysTick and the timers may be
+//! in use, or the reason for the panic, so the handler times the blinks by
+//! counting cycles, from the system clock frequency given with the LED, which
+//! is only roughly as accurate as a delay, but plenty for Morse code.
+
+use core::cell::RefCell;
+use core::fmt::Write;
+use core::panic::PanicInfo;
+use core::sync::atomic::{self, Ordering};
+
+use cortex_m::interrupt::{self, Mutex};
+use cortex_m::peripheral::ITM;
+use cortex_m_semihosting::hio;
+use embedded_hal::blocking::delay::DelayMs;
+
+use crate::blink;
+use crate::led::UserLed;
+
+/// SOS in Morse code, as a [`blink::blink_pattern`] pattern.
+pub const SOS_PATTERN: &str = "...  ---  ...      ";
+
+/// Length of one unit of [`SOS_PATTERN`] in milliseconds.
+pub const SOS_UNIT_MS: u16 = 150;
+
+/// Defines the `#[panic_handler]` selected by the invoking crate's features.
+///
+/// Produces a compile error if none or more than one of the `panic-halt`,
+/// `panic-semihosting`, `panic-itm`, `panic-probe`, and `panic-sos` features
+/// are enabled.
+/// See the [module docs](crate::panic) for the features to declare.
+#[macro_export]
+macro_rules! panic_handler {
+    () => {
+        #[cfg(not(any(
+            feature = "panic-halt",
+            feature = "panic-semihosting",
+            feature = "panic-itm",
+            feature = "panic-probe",
+            feature = "panic-sos",
+        )))]
+        compile_error!(
+            "no panic handler selected; enable one of the `panic-halt`, \
+             `panic-semihosting`, `panic-itm`, `panic-probe`, or `panic-sos` \
+             features"
+        );
+
+        #[cfg(any(
+            all(feature = "panic-halt", feature = "panic-semihosting"),
+            all(feature = "panic-halt", feature = "panic-itm"),
+            all(feature = "panic-halt", feature = "panic-probe"),
+            all(feature = "panic-halt", feature = "panic-sos"),
+            all(feature = "panic-semihosting", feature = "panic-itm"),
+            all(feature = "panic-semihosting", feature = "panic-probe"),
+            all(feature = "panic-semihosting", feature = "panic-sos"),
+            all(feature = "panic-itm", feature = "panic-probe"),
+            all(feature = "panic-itm", feature = "panic-sos"),
+            all(feature = "panic-probe", feature = "panic-sos"),
+        ))]
+        compile_error!(
+            "more than one panic handler selected; enable only one of the \
+             `panic-halt`, `panic-semihosting`, `panic-itm`, `panic-probe`, \
+             or `panic-sos` features, e.g. with `--no-default-features`"
+        );
+
+        // `panic-halt`: Halts in an infinite loop and ignores the message.
+        //
+        // Has no requirements and is the smallest, but a debugger is needed to
+        // see that a panic happened at all, by breaking on
+        // `rust_begin_unwind`.
+        #[cfg(feature = "panic-halt")]
+        #[panic_handler]
+        fn panic_halt(_info: &core::panic::PanicInfo) -> ! {
+            $crate::panic::halt()
+        }
+
+        // `panic-semihosting`: Prints the message to the host's stderr through
+        // the debugger, then halts.
+        //
+        // Semihosting stops the core for every call, so it's slow, and it
+        // hard faults if no debugger is attached. Only use it while debugging.
+        #[cfg(feature = "panic-semihosting")]
+        #[panic_handler]
+        fn panic_semihosting(info: &core::panic::PanicInfo) -> ! {
+            $crate::panic::semihosting(info)
+        }
+
+        // `panic-itm`: Writes the message out ITM stimulus port 0, then halts.
+        //
+        // Much faster than semihosting and doesn't stop the core, but the SWO
+        // pin has to be connected to the probe and the trace output set up on
+        // the host to see the message. Not available on Cortex-M0(+).
+        #[cfg(feature = "panic-itm")]
+        #[panic_handler]
+        fn panic_itm(info: &core::panic::PanicInfo) -> ! {
+            $crate::panic::itm(info)
+        }
+
+        // `panic-probe`: Triggers a HardFault with a permanently undefined
+        // instruction.
+        //
+        // probe-rs recognizes this as the program ending in a panic, prints a
+        // backtrace, and exits with an error code, which suits running
+        // firmware from `cargo run` and in CI. The message itself isn't
+        // printed; that needs a logging transport like RTT or defmt.
+        #[cfg(feature = "panic-probe")]
+        #[panic_handler]
+        fn panic_probe(_info: &core::panic::PanicInfo) -> ! {
+            $crate::panic::probe()
+        }
+
+        // `panic-sos`: Blinks SOS on the LED registered with
+        // `wt_support::panic::register_sos_led()`, forever.
+        //
+        // Needs no debugger, so it's the one to use on a board running on its
+        // own. The message is lost. Halts if no LED was registered.
+        #[cfg(feature = "panic-sos")]
+        #[panic_handler]
+        fn panic_sos(_info: &core::panic::PanicInfo) -> ! {
+            $crate::panic::sos()
+        }
+    };
+}
+
+/// Halts in an infinite loop.
+pub fn halt() -> ! {
+    interrupt::disable();
+
+    loop {
+        // The fence keeps the loop from being optimized into an abort.
+        atomic::compiler_fence(Ordering::SeqCst);
+    }
+}
+
+/// Writes the panic message to the host's stderr via semihosting and halts.
+pub fn semihosting(info: &PanicInfo) -> ! {
+    interrupt::disable();
+
+    if let Ok(mut hstderr) = hio::hstderr() {
+        writeln!(hstderr, "{}", info).ok();
+    }
+
+    halt()
+}
+
+/// Writes the panic message out ITM stimulus port 0 and halts.
+pub fn itm(info: &PanicInfo) -> ! {
+    interrupt::disable();
+
+    // Interrupts are disabled and this never returns, so nothing else
+    // can be using the ITM while it's borrowed here.
+    let itm = unsafe { &mut *ITM::PTR };
+    cortex_m::iprintln!(&mut itm.stim[0], "{}", info);
+
+    halt()
+}
+
+/// Triggers a HardFault with a permanently undefined instruction.
+pub fn probe() -> ! {
+    interrupt::disable();
+
+    cortex_m::asm::udf()
+}
+
+/// The LED registered for [`sos`], and the system clock frequency to time its
+/// blinks from.
+struct SosLed {
+    led: &'static mut (dyn UserLed + Send),
+    sysclk_hz: u32,
+}
+
+static SOS_LED: Mutex<RefCell<Option<SosLed>>> = Mutex::new(RefCell::new(None));
+
+/// Hands `led` over to [`sos`] to blink on a panic, with the system clock
+/// running at `sysclk_hz`, replacing any LED registered before.
+pub fn register_sos_led(led: &'static mut (dyn UserLed + Send), sysclk_hz: u32) {
+    interrupt::free(move |cs| {
+        SOS_LED.borrow(cs).replace(Some(SosLed { led, sysclk_hz }));
+    });
+}
+
+/// Blinks [`SOS_PATTERN`] on the LED registered with [`register_sos_led`],
+/// forever, or halts if there isn't one.
+pub fn sos() -> ! {
+    interrupt::disable();
+
+    // A panic in the middle of register_sos_led() would find the RefCell
+    // borrowed, and panicking again here would just abort, so treat that the
+    // same as no LED.
+    let registered = interrupt::free(|cs| {
+        SOS_LED
+            .borrow(cs)
+            .try_borrow_mut()
+            .ok()
+            .and_then(|mut sos_led| sos_led.take())
+    });
+    let SosLed { led, sysclk_hz } = match registered {
+        Some(sos_led) => sos_led,
+        None => halt(),
+    };
+
+    let mut delay = CycleDelay {
+        cycles_per_ms: sysclk_hz / 1_000,
+    };
+    loop {
+        blink::blink_pattern(led, &mut delay, SOS_PATTERN, SOS_UNIT_MS);
+    }
+}
+
+/// A delay made by counting cycles, which needs no peripherals.
+struct CycleDelay {
+    cycles_per_ms: u32,
+}
+
+impl DelayMs<u16> for CycleDelay {
+    fn delay_ms(&mut self, ms: u16) {
+        cortex_m::asm::delay(self.cycles_per_ms.saturating_mul(u32::from(ms)));
+    }
+}
@@ -0,0 +1,374 @@
+//! A power-on self-test (POST) of the board's key peripherals.
+//!
+//! [`run_self_test`] checks, once at boot, that the hardware the program
+//! depends on is there and working, before the program starts relying on it.
+//! A failure is reported as a [`PostError`], whose [`code`](PostError::code)
+//! can be blinked on an LED with [`PostError::blink_pattern`], so a board with
+//! nothing else attached can still say what's wrong with it.
+//!
+//! ```ignore
+//! if let Err(e) = post::run_self_test(&mut hw, &config) {
+//!     loop {
+//!         blink::blink_pattern(&mut ld3, &mut delay, e.blink_pattern(), 150);
+//!     }
+//! }
+//! ```
+//!
+//! The checks are done through [`PostHw`], so the order they're done in, and
+//! what counts as a failure, can be tested on the host. The post example
+//! implements it for the STM32F3DISCOVERY.
+//!
+//! # The checks
+//!
+//! They're done in this order, and the first one to fail stops the test:
+//!
+//! 1. Accelerometer: Reads the WHO_AM_I register of the accelerometer, which
+//!    has to match [`PostConfig::accel_id`]. This checks the I2C bus and the
+//!    sensor at once, since any fault on the bus, like a missing pull-up or a
+//!    short, stops the sensor answering.
+//! 2. System clock: Measures the frequency the core is running at against a
+//!    reference clock that doesn't depend on it, and checks it's within
+//!    [`PostConfig::sysclk_tolerance_hz`] of [`PostConfig::sysclk_hz`]. A
+//!    clock running at the wrong speed, from a PLL set up wrong or a
+//!    different oscillator than expected, throws every baud rate and delay
+//!    off with it, which shows up as garbled output rather than an error.
+//! 3. UART: Sends each of [`LOOPBACK_BYTES`] with the UART's output looped
+//!    back to its input, inside the chip, and checks each comes back
+//!    unchanged. Between them, the bytes toggle every bit both ways.
+//!
+//! # Failure codes
+//!
+//! | Code | Error                    | Meaning                           |
+//! | ---- | ------------------------ | --------------------------------- |
+//! | 1    | [`AccelNoResponse`]      | No answer from the accelerometer  |
+//! | 2    | [`AccelWrongId`]         | The wrong chip answered           |
+//! | 3    | [`ClockNoReference`]     | The reference clock isn't running |
+//! | 4    | [`ClockOutOfRange`]      | The system clock is off           |
+//! | 5    | [`UartNoLoopback`]       | A byte sent never came back       |
+//! | 6    | [`UartLoopbackMismatch`] | A byte came back different        |
+//!
+//! [`PostError::blink_pattern`] gives a [`blink::blink_pattern`] pattern of
+//! one long flash, then one short flash per unit of the code, then a pause,
+//! so blinking it over and over, the long flash shows where each repeat of
+//! the code starts.
+//!
+//! [`AccelNoResponse`]: PostError::AccelNoResponse
+//! [`AccelWrongId`]: PostError::AccelWrongId
+//! [`ClockNoReference`]: PostError::ClockNoReference
+//! [`ClockOutOfRange`]: PostError::ClockOutOfRange
+//! [`UartNoLoopback`]: PostError::UartNoLoopback
+//! [`UartLoopbackMismatch`]: PostError::UartLoopbackMismatch
+//! [`blink::blink_pattern`]: crate::blink::blink_pattern
+
+/// Bytes sent through the UART loopback: alternating bits both ways round,
+/// then all zeros and all ones.
+pub const LOOPBACK_BYTES: [u8; 4] = [0x55, 0xAA, 0x00, 0xFF];
+
+/// What the self-test expects of the board.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PostConfig {
+    /// What the accelerometer's WHO_AM_I register should read.
+    pub accel_id: u8,
+    /// The system clock frequency it should run at, in Hz.
+    pub sysclk_hz: u32,
+    /// How far the measured system clock frequency may be from
+    /// [`sysclk_hz`](Self::sysclk_hz), either way, in Hz.
+    pub sysclk_tolerance_hz: u32,
+}
+
+/// Why the self-test failed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PostError {
+    /// The accelerometer didn't answer on the I2C bus.
+    AccelNoResponse,
+    /// The accelerometer's WHO_AM_I register read this instead of the
+    /// expected value.
+    AccelWrongId(u8),
+    /// The reference clock to measure the system clock against isn't
+    /// running.
+    ClockNoReference,
+    /// The system clock was measured at this many Hz, outside the tolerance.
+    ClockOutOfRange(u32),
+    /// A byte sent through the UART loopback didn't come back.
+    UartNoLoopback,
+    /// A byte sent through the UART loopback came back as something else.
+    UartLoopbackMismatch {
+        /// The byte sent.
+        sent: u8,
+        /// The byte that came back.
+        received: u8,
+    },
+}
+
+impl PostError {
+    /// Returns the failure code of the error, from 1 to 6, as listed in the
+    /// [module documentation](self).
+    pub fn code(&self) -> u8 {
+        match self {
+            PostError::AccelNoResponse => 1,
+            PostError::AccelWrongId(_) => 2,
+            PostError::ClockNoReference => 3,
+            PostError::ClockOutOfRange(_) => 4,
+            PostError::UartNoLoopback => 5,
+            PostError::UartLoopbackMismatch { .. } => 6,
+        }
+    }
+
+    /// Returns the failure code as a [`blink::blink_pattern`] pattern: one
+    /// long flash, one short flash per unit of the code, and a pause.
+    ///
+    /// [`blink::blink_pattern`]: crate::blink::blink_pattern
+    pub fn blink_pattern(&self) -> &'static str {
+        match self.code() {
+            1 => "-  .      ",
+            2 => "-  ..      ",
+            3 => "-  ...      ",
+            4 => "-  ....      ",
+            5 => "-  .....      ",
+            _ => "-  ......      ",
+        }
+    }
+}
+
+/// The hardware accesses the self-test needs.
+pub trait PostHw {
+    /// Reads the accelerometer's WHO_AM_I register, or returns `None` if it
+    /// didn't answer.
+    fn accel_id(&mut self) -> Option<u8>;
+
+    /// Measures the system clock frequency in Hz, or returns `None` if the
+    /// reference clock it's measured against isn't running.
+    fn measure_sysclk_hz(&mut self) -> Option<u32>;
+
+    /// Sends `byte` with the UART looped back to itself, and returns the byte
+    /// received, or `None` if none was.
+    fn loopback(&mut self, byte: u8) -> Option<u8>;
+}
+
+/// Runs the checks listed in the [module documentation](self) on `hw`,
+/// against what's expected in `config`, and returns the first failure.
+pub fn run_self_test<H: PostHw>(hw: &mut H, config: &PostConfig) -> Result<(), PostError> {
+    match hw.accel_id() {
+        None => return Err(PostError::AccelNoResponse),
+        Some(id) if id != config.accel_id => return Err(PostError::AccelWrongId(id)),
+        Some(_) => {}
+    }
+
+    let sysclk_hz = hw.measure_sysclk_hz().ok_or(PostError::ClockNoReference)?;
+    if sysclk_hz.abs_diff(config.sysclk_hz) > config.sysclk_tolerance_hz {
+        return Err(PostError::ClockOutOfRange(sysclk_hz));
+    }
+
+    for sent in LOOPBACK_BYTES {
+        match hw.loopback(sent) {
+            None => return Err(PostError::UartNoLoopback),
+            Some(received) if received != sent => {
+                return Err(PostError::UartLoopbackMismatch { sent, received })
+            }
+            Some(_) => {}
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::blink::{for_each_step, Step};
+
+    use std::vec::Vec;
+
+    const CONFIG: PostConfig = PostConfig {
+        accel_id: 0x33,
+        sysclk_hz: 48_000_000,
+        sysclk_tolerance_hz: 480_000,
+    };
+
+    /// Hardware that answers each check as it's told to, and records which
+    /// checks were done.
+    struct MockHw {
+        accel_id: Option<u8>,
+        sysclk_hz: Option<u32>,
+        /// What's received in place of each byte, or `None` to echo it.
+        loopback: Option<Option<u8>>,
+        checks: Vec<&'static str>,
+    }
+
+    impl MockHw {
+        fn healthy() -> Self {
+            MockHw {
+                accel_id: Some(0x33),
+                sysclk_hz: Some(48_000_000),
+                loopback: None,
+                checks: Vec::new(),
+            }
+        }
+    }
+
+    impl PostHw for MockHw {
+        fn accel_id(&mut self) -> Option<u8> {
+            self.checks.push("accel");
+            self.accel_id
+        }
+
+        fn measure_sysclk_hz(&mut self) -> Option<u32> {
+            self.checks.push("clock");
+            self.sysclk_hz
+        }
+
+        fn loopback(&mut self, byte: u8) -> Option<u8> {
+            self.checks.push("uart");
+            self.loopback.unwrap_or(Some(byte))
+        }
+    }
+
+    #[test]
+    fn healthy_board_passes_every_check() {
+        let mut hw = MockHw::healthy();
+        assert_eq!(run_self_test(&mut hw, &CONFIG), Ok(()));
+        assert_eq!(
+            hw.checks,
+            ["accel", "clock", "uart", "uart", "uart", "uart"]
+        );
+    }
+
+    #[test]
+    fn missing_accelerometer_stops_the_test() {
+        let mut hw = MockHw {
+            accel_id: None,
+            ..MockHw::healthy()
+        };
+        assert_eq!(
+            run_self_test(&mut hw, &CONFIG),
+            Err(PostError::AccelNoResponse)
+        );
+        assert_eq!(hw.checks, ["accel"]);
+    }
+
+    #[test]
+    fn wrong_accelerometer_is_reported_with_its_id() {
+        let mut hw = MockHw {
+            accel_id: Some(0x40),
+            ..MockHw::healthy()
+        };
+        assert_eq!(
+            run_self_test(&mut hw, &CONFIG),
+            Err(PostError::AccelWrongId(0x40))
+        );
+    }
+
+    #[test]
+    fn clock_without_reference_fails() {
+        let mut hw = MockHw {
+            sysclk_hz: None,
+            ..MockHw::healthy()
+        };
+        assert_eq!(
+            run_self_test(&mut hw, &CONFIG),
+            Err(PostError::ClockNoReference)
+        );
+        assert_eq!(hw.checks, ["accel", "clock"]);
+    }
+
+    #[test]
+    fn clock_within_tolerance_passes_either_way() {
+        for hz in [47_520_000, 48_480_000] {
+            let mut hw = MockHw {
+                sysclk_hz: Some(hz),
+                ..MockHw::healthy()
+            };
+            assert_eq!(run_self_test(&mut hw, &CONFIG), Ok(()));
+        }
+    }
+
+    #[test]
+    fn clock_outside_tolerance_fails_either_way() {
+        for hz in [47_519_999, 48_480_001, 8_000_000] {
+            let mut hw = MockHw {
+                sysclk_hz: Some(hz),
+                ..MockHw::healthy()
+            };
+            assert_eq!(
+                run_self_test(&mut hw, &CONFIG),
+                Err(PostError::ClockOutOfRange(hz))
+            );
+        }
+    }
+
+    #[test]
+    fn uart_with_nothing_received_fails() {
+        let mut hw = MockHw {
+            loopback: Some(None),
+            ..MockHw::healthy()
+        };
+        assert_eq!(
+            run_self_test(&mut hw, &CONFIG),
+            Err(PostError::UartNoLoopback)
+        );
+        assert_eq!(hw.checks, ["accel", "clock", "uart"]);
+    }
+
+    #[test]
+    fn uart_mismatch_reports_both_bytes() {
+        let mut hw = MockHw {
+            loopback: Some(Some(0x54)),
+            ..MockHw::healthy()
+        };
+        assert_eq!(
+            run_self_test(&mut hw, &CONFIG),
+            Err(PostError::UartLoopbackMismatch {
+                sent: 0x55,
+                received: 0x54
+            })
+        );
+    }
+
+    const ERRORS: [PostError; 6] = [
+        PostError::AccelNoResponse,
+        PostError::AccelWrongId(0),
+        PostError::ClockNoReference,
+        PostError::ClockOutOfRange(0),
+        PostError::UartNoLoopback,
+        PostError::UartLoopbackMismatch {
+            sent: 0,
+            received: 0,
+        },
+    ];
+
+    #[test]
+    fn codes_are_one_to_six_in_order() {
+        let codes: Vec<u8> = ERRORS.iter().map(PostError::code).collect();
+        assert_eq!(codes, [1, 2, 3, 4, 5, 6]);
+    }
+
+    #[test]
+    fn blink_pattern_is_a_long_flash_then_one_short_flash_per_unit() {
+        for error in ERRORS {
+            let mut flashes = Vec::new();
+            for_each_step(error.blink_pattern(), |step: Step| {
+                if step.on {
+                    flashes.push(step.units);
+                }
+            });
+            let mut expected = std::vec![3];
+            expected.extend(std::iter::repeat_n(1, error.code() as usize));
+            assert_eq!(flashes, expected, "{:?}", error);
+        }
+    }
+
+    #[test]
+    fn blink_pattern_starts_short_flashes_after_a_letter_gap_and_ends_with_a_word_gap() {
+        for error in ERRORS {
+            let mut gaps = Vec::new();
+            for_each_step(error.blink_pattern(), |step: Step| {
+                if !step.on {
+                    gaps.push(step.units);
+                }
+            });
+            assert_eq!(gaps.first(), Some(&3), "{:?}", error);
+            assert_eq!(gaps.last(), Some(&7), "{:?}", error);
+        }
+    }
+}
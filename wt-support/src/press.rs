@@ -0,0 +1,237 @@
+//! Telling a short press of a button from a long one.
+//!
+//! A [`PressDetector`] is fed the state of a button, already debounced, along
+//! with the millisecond tick, every time the button is polled, and says when
+//! the button has been pressed briefly or held down, with a [`PressEvent`].
+//! That lets one button do two things, e.g. a short press to step through the
+//! modes of a device and a long one to turn it off.
+//!
+//! ```ignore
+//! let mut detector = PressDetector::new(1_000);
+//! loop {
+//!     match detector.update(button_pressed, millis::millis()) {
+//!         Some(PressEvent::Short) => { /* Quick press and release. */ }
+//!         Some(PressEvent::Long) => { /* Held for a second. */ }
+//!         None => {}
+//!     }
+//! }
+//! ```
+//!
+//! # State machine
+//!
+//! ```text
+//!            pressed                 held for long_press_ms
+//!   Idle ------------> Pressed -----------------------------> WaitForRelease
+//!    ^                    |          (Long)                         |
+//!    |   released early   |                                         |
+//!    +--------------------+                                         |
+//!    |   (Short)                                                    |
+//!    |                             released                         |
+//!    +--------------------------------------------------------------+
+//! ```
+//!
+//! - Idle: The button is up. Pressing it moves to Pressed, noting the time.
+//! - Pressed: The button is down, and it's not yet known which kind of press
+//!   this is. Releasing it before `long_press_ms` has passed is a short press.
+//!   Still holding it once `long_press_ms` has passed is a long press.
+//! - WaitForRelease: A long press has already been reported, so nothing more
+//!   happens until the button is released, however long it's held for.
+//!
+//! A long press is reported as soon as the hold reaches the threshold, rather
+//! than on release, so whatever it does happens while the button is still
+//! down, which tells the user when they can let go. A short press can only be
+//! reported on release, since until then, it could still turn into a long
+//! one.
+//!
+//! # Polling
+//!
+//! The threshold is only checked when [`PressDetector::update`] is called,
+//! so a long press is reported up to one polling interval late. If the button
+//! is released having been held past the threshold without an update seeing
+//! it, it's still reported as long, on the release. The elapsed time is worked
+//! out with wrapping subtraction, the same as [`crate::millis::elapsed_since`],
+//! so it's correct across a wraparound of the tick count.
+
+/// What kind of press a [`PressDetector`] has seen.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PressEvent {
+    /// The button was released before the long press threshold.
+    Short,
+    /// The button has been held down for the long press threshold.
+    Long,
+}
+
+/// Where a [`PressDetector`] is in its state machine.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum State {
+    Idle,
+    Pressed { since: u32 },
+    WaitForRelease,
+}
+
+/// Turns the debounced state of a button into short and long presses.
+///
+/// See the [module documentation](self) for the state machine.
+#[derive(Clone, Copy, Debug)]
+pub struct PressDetector {
+    long_press_ms: u32,
+    state: State,
+}
+
+impl PressDetector {
+    /// Creates a detector that takes a hold of `long_press_ms` milliseconds or
+    /// more as a long press, starting with the button up.
+    pub const fn new(long_press_ms: u32) -> Self {
+        PressDetector {
+            long_press_ms,
+            state: State::Idle,
+        }
+    }
+
+    /// Updates the detector with whether the button is `pressed` at the tick
+    /// count `now_ms`, and returns the press that's just finished, if any.
+    pub fn update(&mut self, pressed: bool, now_ms: u32) -> Option<PressEvent> {
+        let (state, event) = match (self.state, pressed) {
+            (State::Idle, true) => (State::Pressed { since: now_ms }, None),
+            (State::Idle, false) => (State::Idle, None),
+
+            (State::Pressed { since }, true) => {
+                if now_ms.wrapping_sub(since) >= self.long_press_ms {
+                    (State::WaitForRelease, Some(PressEvent::Long))
+                } else {
+                    (State::Pressed { since }, None)
+                }
+            }
+            (State::Pressed { since }, false) => {
+                if now_ms.wrapping_sub(since) >= self.long_press_ms {
+                    (State::Idle, Some(PressEvent::Long))
+                } else {
+                    (State::Idle, Some(PressEvent::Short))
+                }
+            }
+
+            (State::WaitForRelease, true) => (State::WaitForRelease, None),
+            (State::WaitForRelease, false) => (State::Idle, None),
+        };
+        self.state = state;
+        event
+    }
+
+    /// Returns whether the button is down, as of the last update.
+    pub fn is_pressed(&self) -> bool {
+        self.state != State::Idle
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::vec::Vec;
+
+    const LONG_PRESS_MS: u32 = 1_000;
+
+    /// Feeds `detector` the button state every millisecond from `start`, held
+    /// down for the spans in `presses`, each a start and end offset from
+    /// `start`, and up the rest of the time until `end`, and returns the
+    /// events with when they happened.
+    fn run(
+        detector: &mut PressDetector,
+        start: u32,
+        end: u32,
+        presses: &[(u32, u32)],
+    ) -> Vec<(u32, PressEvent)> {
+        let mut events = Vec::new();
+        for offset in 0..end {
+            let pressed = presses
+                .iter()
+                .any(|&(down, up)| (down..up).contains(&offset));
+            if let Some(event) = detector.update(pressed, start.wrapping_add(offset)) {
+                events.push((offset, event));
+            }
+        }
+        events
+    }
+
+    #[test]
+    fn nothing_without_a_press() {
+        let mut detector = PressDetector::new(LONG_PRESS_MS);
+        assert!(run(&mut detector, 0, 5_000, &[]).is_empty());
+    }
+
+    #[test]
+    fn quick_release_is_short() {
+        let mut detector = PressDetector::new(LONG_PRESS_MS);
+        assert_eq!(
+            run(&mut detector, 0, 1_000, &[(100, 250)]),
+            [(250, PressEvent::Short)]
+        );
+    }
+
+    #[test]
+    fn release_just_before_threshold_is_short() {
+        let mut detector = PressDetector::new(LONG_PRESS_MS);
+        assert_eq!(
+            run(&mut detector, 0, 2_000, &[(100, 99 + LONG_PRESS_MS)]),
+            [(99 + LONG_PRESS_MS, PressEvent::Short)]
+        );
+    }
+
+    #[test]
+    fn hold_is_long_at_threshold_and_not_again_on_release() {
+        let mut detector = PressDetector::new(LONG_PRESS_MS);
+        assert_eq!(
+            run(&mut detector, 0, 5_000, &[(100, 3_000)]),
+            [(100 + LONG_PRESS_MS, PressEvent::Long)]
+        );
+        assert!(!detector.is_pressed());
+    }
+
+    #[test]
+    fn short_and_long_presses_in_turn() {
+        let mut detector = PressDetector::new(LONG_PRESS_MS);
+        assert_eq!(
+            run(
+                &mut detector,
+                0,
+                6_000,
+                &[(100, 200), (500, 2_000), (2_500, 2_600), (3_000, 5_000)]
+            ),
+            [
+                (200, PressEvent::Short),
+                (1_500, PressEvent::Long),
+                (2_600, PressEvent::Short),
+                (4_000, PressEvent::Long),
+            ]
+        );
+    }
+
+    #[test]
+    fn release_past_threshold_without_an_update_is_long() {
+        let mut detector = PressDetector::new(LONG_PRESS_MS);
+        assert_eq!(detector.update(true, 0), None);
+        assert_eq!(detector.update(true, 500), None);
+        assert_eq!(detector.update(false, 1_500), Some(PressEvent::Long));
+        assert!(!detector.is_pressed());
+    }
+
+    #[test]
+    fn is_pressed_follows_the_button() {
+        let mut detector = PressDetector::new(LONG_PRESS_MS);
+        assert!(!detector.is_pressed());
+        detector.update(true, 0);
+        assert!(detector.is_pressed());
+        detector.update(true, 2_000);
+        assert!(detector.is_pressed());
+        detector.update(false, 2_001);
+        assert!(!detector.is_pressed());
+    }
+
+    #[test]
+    fn hold_across_tick_wraparound() {
+        let mut detector = PressDetector::new(LONG_PRESS_MS);
+        assert_eq!(
+            run(&mut detector, u32::MAX - 499, 3_000, &[(0, 2_000)]),
+            [(LONG_PRESS_MS, PressEvent::Long)]
+        );
+    }
+}
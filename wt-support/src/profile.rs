@@ -0,0 +1,83 @@
+//! Measuring how many cycles code takes with the DWT cycle counter.
+//!
+//! [`Stopwatch`] reads CYCCNT when it's started and again whenever asked for
+//! the elapsed time, and [`Stopwatch::measure`] wraps that around a closure.
+//! The counter has to be enabled first with [`crate::dwt_delay::init`].
+//!
+//! ```ignore
+//! wt_support::dwt_delay::init(&mut core_periphs.DCB, &mut core_periphs.DWT);
+//!
+//! let overhead = wt_support::profile::overhead_cycles();
+//! let cycles = Stopwatch::measure(|| {
+//!     led.toggle().ok();
+//! })
+//! .saturating_sub(overhead);
+//! ```
+//!
+//! # Overhead
+//!
+//! Reading the counter takes a few cycles itself, and the reads at either end
+//! of a measurement land partway through those cycles, so the count always
+//! includes a small fixed overhead on top of the code being measured. For code
+//! that takes thousands of cycles, that doesn't matter, but for a handful of
+//! instructions it can be most of the count. [`overhead_cycles`] measures an
+//! empty closure to get that baseline, which can then be subtracted from each
+//! measurement.
+//!
+//! Subtract it with `saturating_sub()` rather than `-`. The overhead varies by
+//! a cycle or two from run to run, with where the reads land, so a short
+//! measurement can come out smaller than the baseline, and plain subtraction
+//! would then panic in a debug build, or wrap around to a count of billions
+//! in a release build. Saturating gives 0 instead.
+//!
+//! The baseline only holds for code built the same way. Measurements in a
+//! debug build are dominated by the unoptimized code around them, so profile
+//! in a release build. The optimizer can also move code out of the
+//! measurement, or drop it entirely if its result isn't used; passing inputs
+//! and results through `core::hint::black_box` stops that.
+//!
+//! # Limits
+//!
+//! The counter wraps around after 2^32 cycles, about 89 s at 48 MHz, so that's
+//! the longest that can be measured. Interrupts that fire during a
+//! measurement are counted too, so measure with them masked, or take the
+//! smallest of several runs, for the cost of the code alone.
+
+use cortex_m::peripheral::DWT;
+
+/// Measures elapsed core clock cycles from when it was started.
+#[derive(Clone, Copy, Debug)]
+pub struct Stopwatch {
+    start: u32,
+}
+
+impl Stopwatch {
+    /// Starts a stopwatch at the current cycle count.
+    pub fn start() -> Self {
+        Stopwatch {
+            start: DWT::cycle_count(),
+        }
+    }
+
+    /// Returns the number of cycles since the stopwatch was started.
+    ///
+    /// The stopwatch keeps running, so this can be called more than once to
+    /// take split times.
+    pub fn elapsed_cycles(&self) -> u32 {
+        DWT::cycle_count().wrapping_sub(self.start)
+    }
+
+    /// Runs `f` and returns the number of cycles it took, including the
+    /// overhead of the measurement.
+    pub fn measure<F: FnOnce()>(f: F) -> u32 {
+        let stopwatch = Stopwatch::start();
+        f();
+        stopwatch.elapsed_cycles()
+    }
+}
+
+/// Returns the overhead of a [`Stopwatch::measure`] in cycles, measured with
+/// an empty closure, to subtract from other measurements.
+pub fn overhead_cycles() -> u32 {
+    Stopwatch::measure(|| {})
+}
@@ -0,0 +1,431 @@
+//! A minimal framed binary protocol for talking to a host over a UART.
+//!
+//! A UART only moves bytes. To send messages over it, both ends have to agree
+//! on where each message starts and ends, and on how to tell one that got
+//! damaged on the way. This module frames each message, or payload, with a
+//! start byte, its length, and a CRC-8:
+//!
+//! | Offset    | Size | Field                                        |
+//! |-----------|------|----------------------------------------------|
+//! | 0         | 1    | [`START`], 0x7E                              |
+//! | 1         | 1    | Length of the payload, 0 to [`MAX_PAYLOAD`]  |
+//! | 2         | n    | Payload                                      |
+//! | 2 + n     | 1    | CRC-8 of the length and payload bytes        |
+//!
+//! The CRC is CRC-8/SMBUS: polynomial 0x07, initial value 0, no reflection,
+//! and no final XOR, computed over the length byte and then the payload, but
+//! not the start byte. A host script can compute it with e.g.
+//! `crcmod.predefined.mkCrcFun("crc-8")` in Python, or in a few lines by hand,
+//! like [`crc8`]. Its check value, the CRC of the ASCII bytes `123456789`, is
+//! 0xF4.
+//!
+//! A frame of the payload `[0x01, 0x05]`, for example, is:
+//!
+//! ```text
+//! 7E 02 01 05 D8
+//! ```
+//!
+//! [`encode`] builds a frame, and [`decode`] checks a whole one. On the
+//! receiving end, the bytes of a frame usually come in over several reads, so
+//! [`Decoder`] takes them one at a time, holds on to a partial frame between
+//! calls, and hands back the payload once the frame is complete and its CRC
+//! checks out:
+//!
+//! ```ignore
+//! let mut decoder = proto::Decoder::new();
+//! loop {
+//!     if let Ok(byte) = uart.read() {
+//!         match decoder.push(byte) {
+//!             Some(Ok(payload)) => { /* Handle the payload. */ }
+//!             Some(Err(error)) => { /* Count or report the bad frame. */ }
+//!             None => {}
+//!         }
+//!     }
+//! }
+//! ```
+//!
+//! # Resyncing
+//!
+//! A receiver that starts listening partway through a frame, or loses a byte
+//! of one, has to find the start of the next frame. [`Decoder`] skips bytes
+//! until it sees [`START`], and takes that as the start of a frame. A
+//! payload byte can be 0x7E too, so it may be wrong, but then the CRC almost
+//! certainly won't match, and the frame is rejected with
+//! [`DecodeError::CrcMismatch`]. The same goes for a length byte damaged into
+//! one over [`MAX_PAYLOAD`], which is rejected as soon as it arrives with
+//! [`DecodeError::TooLong`].
+//!
+//! Either way, the decoder goes back to looking for [`START`] from the byte
+//! after the bad frame, without looking back through it, so a real frame
+//! starting inside the bad one is lost too. That's fine for a
+//! command-and-response link, where the host times out waiting for the reply
+//! and sends the command again, but a link that can't afford to drop frames
+//! needs more than this, like escaping the start byte in the payload the way
+//! HDLC and SLIP do.
+//!
+//! A CRC-8 catches every error of up to 8 bits in a row, and all but 1 in 256
+//! worse ones, which is plenty for a short cable on a bench.
+
+/// The first byte of every frame.
+pub const START: u8 = 0x7E;
+
+/// The longest payload a frame can carry, in bytes.
+pub const MAX_PAYLOAD: usize = 32;
+
+/// Bytes a frame adds around its payload: start, length, and CRC.
+pub const OVERHEAD: usize = 3;
+
+/// The longest a frame can be, in bytes.
+pub const MAX_FRAME: usize = MAX_PAYLOAD + OVERHEAD;
+
+/// The CRC-8/SMBUS generator polynomial, x^8 + x^2 + x + 1.
+pub const CRC8_POLYNOMIAL: u8 = 0x07;
+
+/// Returns the CRC-8/SMBUS of `data`.
+pub fn crc8(data: &[u8]) -> u8 {
+    crc8_update(0, data)
+}
+
+/// Continues the CRC-8 `crc` over `data`.
+fn crc8_update(mut crc: u8, data: &[u8]) -> u8 {
+    for &byte in data {
+        crc ^= byte;
+        for _ in 0..8 {
+            // The same long division as in crc::crc32, but most significant
+            // bit first, so the CRC shifts left.
+            crc = if crc & 0x80 != 0 {
+                (crc << 1) ^ CRC8_POLYNOMIAL
+            } else {
+                crc << 1
+            };
+        }
+    }
+    crc
+}
+
+/// Ways building a frame can fail.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EncodeError {
+    /// The payload is longer than [`MAX_PAYLOAD`].
+    PayloadTooLong,
+    /// The buffer is too small for the frame.
+    BufferTooSmall,
+}
+
+/// Ways a received frame can be bad.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DecodeError {
+    /// The frame doesn't start with [`START`].
+    NoStart,
+    /// The length byte is over [`MAX_PAYLOAD`].
+    TooLong(u8),
+    /// The frame is shorter than its length byte says.
+    Truncated,
+    /// The CRC received doesn't match the one computed from the frame.
+    CrcMismatch {
+        /// The CRC computed from the length and payload received.
+        computed: u8,
+        /// The CRC at the end of the frame.
+        received: u8,
+    },
+}
+
+/// Builds the frame of `payload` at the start of `buf`.
+///
+/// Returns the length of the frame, which is the length of the payload plus
+/// [`OVERHEAD`].
+pub fn encode(payload: &[u8], buf: &mut [u8]) -> Result<usize, EncodeError> {
+    if payload.len() > MAX_PAYLOAD {
+        return Err(EncodeError::PayloadTooLong);
+    }
+    let frame_len = payload.len() + OVERHEAD;
+    let frame = buf
+        .get_mut(..frame_len)
+        .ok_or(EncodeError::BufferTooSmall)?;
+
+    frame[0] = START;
+    frame[1] = payload.len() as u8;
+    frame[2..2 + payload.len()].copy_from_slice(payload);
+    frame[frame_len - 1] = crc8(&frame[1..frame_len - 1]);
+    Ok(frame_len)
+}
+
+/// Checks the whole frame `frame` and returns its payload.
+///
+/// Bytes after the end of the frame, as given by its length byte, are
+/// ignored.
+pub fn decode(frame: &[u8]) -> Result<&[u8], DecodeError> {
+    match frame.first() {
+        Some(&START) => {}
+        Some(_) => return Err(DecodeError::NoStart),
+        None => return Err(DecodeError::Truncated),
+    }
+    let len = *frame.get(1).ok_or(DecodeError::Truncated)?;
+    if len as usize > MAX_PAYLOAD {
+        return Err(DecodeError::TooLong(len));
+    }
+    let crc_index = 2 + len as usize;
+    let received = *frame.get(crc_index).ok_or(DecodeError::Truncated)?;
+    let computed = crc8(&frame[1..crc_index]);
+    if computed != received {
+        return Err(DecodeError::CrcMismatch { computed, received });
+    }
+    Ok(&frame[2..crc_index])
+}
+
+/// Where a [`Decoder`] is in a frame.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum State {
+    /// Skipping bytes until a start byte.
+    Start,
+    /// Waiting for the length byte.
+    Length,
+    /// Collecting the payload.
+    Payload,
+    /// Waiting for the CRC.
+    Crc,
+}
+
+/// Reassembles frames from bytes received one at a time.
+///
+/// See the [module documentation](self) for how it finds the start of each
+/// frame.
+#[derive(Clone, Debug)]
+pub struct Decoder {
+    state: State,
+    len: usize,
+    received: usize,
+    payload: [u8; MAX_PAYLOAD],
+}
+
+impl Decoder {
+    /// Returns a decoder waiting for the start of a frame.
+    pub const fn new() -> Self {
+        Self {
+            state: State::Start,
+            len: 0,
+            received: 0,
+            payload: [0; MAX_PAYLOAD],
+        }
+    }
+
+    /// Drops any partial frame, and goes back to waiting for the start of
+    /// one.
+    pub fn reset(&mut self) {
+        self.state = State::Start;
+    }
+
+    /// Returns whether the decoder is partway through a frame.
+    pub fn in_frame(&self) -> bool {
+        self.state != State::Start
+    }
+
+    /// Takes the next byte received.
+    ///
+    /// Returns `None` while a frame is still incomplete, the payload when
+    /// `byte` completes a good one, and the error when it shows the frame to
+    /// be bad. The payload borrows from the decoder, so it has to be handled,
+    /// or copied, before the next byte is pushed.
+    pub fn push(&mut self, byte: u8) -> Option<Result<&[u8], DecodeError>> {
+        match self.state {
+            State::Start => {
+                if byte == START {
+                    self.state = State::Length;
+                }
+                None
+            }
+            State::Length => {
+                if byte as usize > MAX_PAYLOAD {
+                    self.state = State::Start;
+                    return Some(Err(DecodeError::TooLong(byte)));
+                }
+                self.len = byte as usize;
+                self.received = 0;
+                self.state = if self.len == 0 {
+                    State::Crc
+                } else {
+                    State::Payload
+                };
+                None
+            }
+            State::Payload => {
+                self.payload[self.received] = byte;
+                self.received += 1;
+                if self.received == self.len {
+                    self.state = State::Crc;
+                }
+                None
+            }
+            State::Crc => {
+                self.state = State::Start;
+                let payload = &self.payload[..self.len];
+                let computed = crc8_update(crc8(&[self.len as u8]), payload);
+                if computed == byte {
+                    Some(Ok(payload))
+                } else {
+                    Some(Err(DecodeError::CrcMismatch {
+                        computed,
+                        received: byte,
+                    }))
+                }
+            }
+        }
+    }
+}
+
+impl Default for Decoder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::vec::Vec;
+
+    /// Returns the frame of `payload`.
+    fn frame(payload: &[u8]) -> Vec<u8> {
+        let mut buf = [0; MAX_FRAME];
+        let len = encode(payload, &mut buf).unwrap();
+        buf[..len].to_vec()
+    }
+
+    /// Pushes `bytes` through `decoder`, and returns what came out, with each
+    /// good payload copied.
+    fn push_all(decoder: &mut Decoder, bytes: &[u8]) -> Vec<Result<Vec<u8>, DecodeError>> {
+        bytes
+            .iter()
+            .filter_map(|&byte| decoder.push(byte).map(|r| r.map(<[u8]>::to_vec)))
+            .collect()
+    }
+
+    #[test]
+    fn crc8_matches_the_check_value() {
+        assert_eq!(crc8(b"123456789"), 0xF4);
+        assert_eq!(crc8(&[]), 0);
+    }
+
+    #[test]
+    fn encode_matches_the_documented_example() {
+        assert_eq!(frame(&[0x01, 0x05]), [0x7E, 0x02, 0x01, 0x05, 0xD8]);
+    }
+
+    #[test]
+    fn encode_then_decode_round_trips() {
+        let payloads: [&[u8]; 4] = [&[], &[0x7E], b"hello", &[0xA5; MAX_PAYLOAD]];
+        for payload in payloads {
+            let frame = frame(payload);
+            assert_eq!(frame.len(), payload.len() + OVERHEAD);
+            assert_eq!(decode(&frame), Ok(payload));
+        }
+    }
+
+    #[test]
+    fn encode_rejects_an_oversized_payload_or_a_small_buffer() {
+        let mut buf = [0; MAX_FRAME + 1];
+        assert_eq!(
+            encode(&[0; MAX_PAYLOAD + 1], &mut buf),
+            Err(EncodeError::PayloadTooLong)
+        );
+        assert_eq!(
+            encode(b"abc", &mut [0; 5]),
+            Err(EncodeError::BufferTooSmall)
+        );
+    }
+
+    #[test]
+    fn decode_rejects_every_single_bit_flip() {
+        let good = frame(b"set led");
+        for index in 1..good.len() {
+            for bit in 0..8 {
+                let mut bad = good.clone();
+                bad[index] ^= 1 << bit;
+                assert!(decode(&bad).is_err(), "byte {} bit {}", index, bit);
+            }
+        }
+    }
+
+    #[test]
+    fn decode_reports_what_is_wrong() {
+        let mut bad = frame(&[1, 2, 3]);
+        bad[3] ^= 0xFF;
+        assert!(matches!(decode(&bad), Err(DecodeError::CrcMismatch { .. })));
+        assert_eq!(decode(&[0x00, 0x00, 0x00]), Err(DecodeError::NoStart));
+        assert_eq!(decode(&[START, 33, 0]), Err(DecodeError::TooLong(33)));
+        assert_eq!(decode(&frame(b"abc")[..5]), Err(DecodeError::Truncated));
+        assert_eq!(decode(&[]), Err(DecodeError::Truncated));
+    }
+
+    #[test]
+    fn decoder_reassembles_a_frame_split_across_reads() {
+        let frame = frame(b"read");
+        let mut decoder = Decoder::new();
+        assert!(push_all(&mut decoder, &frame[..2]).is_empty());
+        assert!(decoder.in_frame());
+        assert!(push_all(&mut decoder, &frame[2..5]).is_empty());
+        assert_eq!(push_all(&mut decoder, &frame[5..]), [Ok(b"read".to_vec())]);
+        assert!(!decoder.in_frame());
+    }
+
+    #[test]
+    fn decoder_handles_back_to_back_frames_and_empty_payloads() {
+        let mut bytes = frame(&[]);
+        bytes.extend(frame(&[START, START]));
+        bytes.extend(frame(&[9]));
+        let mut decoder = Decoder::new();
+        assert_eq!(
+            push_all(&mut decoder, &bytes),
+            [Ok(vec![]), Ok(vec![START, START]), Ok(vec![9])]
+        );
+    }
+
+    #[test]
+    fn decoder_skips_noise_before_a_frame() {
+        let mut bytes = vec![0x00, 0xFF, 0x12];
+        bytes.extend(frame(b"hi"));
+        let mut decoder = Decoder::new();
+        assert_eq!(push_all(&mut decoder, &bytes), [Ok(b"hi".to_vec())]);
+    }
+
+    #[test]
+    fn decoder_rejects_a_corrupt_frame_and_recovers() {
+        let mut bad = frame(&[0x01, 0x05]);
+        bad[3] ^= 0x04;
+        let mut bytes = bad.clone();
+        bytes.extend(frame(&[0x02]));
+        let mut decoder = Decoder::new();
+        let out = push_all(&mut decoder, &bytes);
+        assert_eq!(out.len(), 2);
+        assert_eq!(
+            out[0],
+            Err(DecodeError::CrcMismatch {
+                computed: crc8(&bad[1..4]),
+                received: 0xD8,
+            })
+        );
+        assert_eq!(out[1], Ok(vec![0x02]));
+    }
+
+    #[test]
+    fn decoder_rejects_a_length_over_the_maximum_right_away() {
+        let mut decoder = Decoder::new();
+        assert_eq!(decoder.push(START), None);
+        assert_eq!(
+            decoder.push(MAX_PAYLOAD as u8 + 1),
+            Some(Err(DecodeError::TooLong(MAX_PAYLOAD as u8 + 1)))
+        );
+        assert!(!decoder.in_frame());
+    }
+
+    #[test]
+    fn reset_drops_a_partial_frame() {
+        let frame = frame(b"abc");
+        let mut decoder = Decoder::new();
+        push_all(&mut decoder, &frame[..3]);
+        decoder.reset();
+        assert!(!decoder.in_frame());
+        assert_eq!(push_all(&mut decoder, &frame), [Ok(b"abc".to_vec())]);
+    }
+}
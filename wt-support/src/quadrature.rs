@@ -0,0 +1,234 @@
+//! Decoding a quadrature encoder in software.
+//!
+//! A rotary encoder has two outputs, A and B, that each switch on and off as
+//! it turns, a quarter of a cycle apart. Which of them leads says which way
+//! it's turning, and every edge of either is a step. A
+//! [`QuadratureDecoder`] is given the levels of both every time either one
+//! changes, e.g. from the interrupts on their pins, and keeps a signed count
+//! of the steps.
+//!
+//! ```ignore
+//! let mut decoder = QuadratureDecoder::new(pin_a.is_high(), pin_b.is_high());
+//! // On every edge of A or B:
+//! decoder.update(pin_a.is_high(), pin_b.is_high());
+//! let detents = decoder.position().div_euclid(4);
+//! ```
+//!
+//! # State transitions
+//!
+//! The two levels make a 2-bit state, A in the high bit. Turning forwards,
+//! with A leading B, steps through them in Gray code order, one bit changing
+//! at a time, and turning backwards steps through them the other way:
+//!
+//! ```text
+//!            forward
+//!       ---------------->
+//!   00 -> 10 -> 11 -> 01 -> 00
+//!       <----------------
+//!            backward
+//! ```
+//!
+//! Every pair of old and new state is one of four [`Transition`]s:
+//!
+//! | From \ To | 00       | 01       | 10       | 11       |
+//! | --------- | -------- | -------- | -------- | -------- |
+//! | 00        | Stay     | Backward | Forward  | Invalid  |
+//! | 01        | Forward  | Stay     | Invalid  | Backward |
+//! | 10        | Backward | Invalid  | Stay     | Forward  |
+//! | 11        | Invalid  | Forward  | Backward | Stay     |
+//!
+//! A change of both bits at once can't come from turning the encoder one
+//! step, so it means at least one edge was missed, and the direction can't
+//! be known. It's counted as an error, and the position is left alone.
+//!
+//! # Contact bounce
+//!
+//! The contacts of a mechanical encoder bounce, like a button's, so one edge
+//! can arrive as several. Decoding every edge of both channels through the
+//! table copes with that without any debouncing: while one channel bounces,
+//! the other is steady, so the state just steps back and forth between two
+//! neighbours, and the forward and backward steps cancel out. Decoding only
+//! the edges of A, and reading B to find the direction, counts every bounce.
+//!
+//! Most encoders go through a whole cycle, 4 steps, between detents, the
+//! clicks that can be felt while turning. Divide the position by 4 with
+//! `div_euclid()` for the number of clicks.
+
+/// What a change of state did, from [the table](self#state-transitions).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Transition {
+    /// Nothing changed.
+    Stay,
+    /// One step forwards, with A leading B.
+    Forward,
+    /// One step backwards, with B leading A.
+    Backward,
+    /// Both channels changed at once, so a step was missed.
+    Invalid,
+}
+
+/// The transition for every old state, in the high two bits of the index,
+/// and new state, in the low two.
+const TRANSITIONS: [Transition; 16] = {
+    use Transition::*;
+    [
+        Stay, Backward, Forward, Invalid, // From 00.
+        Forward, Stay, Invalid, Backward, // From 01.
+        Backward, Invalid, Stay, Forward, // From 10.
+        Invalid, Forward, Backward, Stay, // From 11.
+    ]
+};
+
+/// Returns the 2-bit state for the levels of the two channels.
+const fn state(a: bool, b: bool) -> u8 {
+    ((a as u8) << 1) | (b as u8)
+}
+
+/// Returns what going from one 2-bit state to another did.
+pub fn transition(from: u8, to: u8) -> Transition {
+    TRANSITIONS[usize::from(((from & 0b11) << 2) | (to & 0b11))]
+}
+
+/// Keeps the position of a quadrature encoder from the levels of its two
+/// channels.
+///
+/// See the [module documentation](self) for how the levels are decoded.
+#[derive(Clone, Copy, Debug)]
+pub struct QuadratureDecoder {
+    state: u8,
+    position: i32,
+    errors: u32,
+}
+
+impl QuadratureDecoder {
+    /// Creates a decoder at position 0, with channel A at level `a` and
+    /// channel B at level `b`.
+    pub const fn new(a: bool, b: bool) -> Self {
+        QuadratureDecoder {
+            state: state(a, b),
+            position: 0,
+            errors: 0,
+        }
+    }
+
+    /// Updates the decoder with the levels of the two channels, and returns
+    /// the transition from the last ones.
+    pub fn update(&mut self, a: bool, b: bool) -> Transition {
+        let new_state = state(a, b);
+        let transition = transition(self.state, new_state);
+        match transition {
+            Transition::Stay => {}
+            Transition::Forward => self.position = self.position.wrapping_add(1),
+            Transition::Backward => self.position = self.position.wrapping_sub(1),
+            Transition::Invalid => self.errors = self.errors.wrapping_add(1),
+        }
+        self.state = new_state;
+        transition
+    }
+
+    /// Returns the position, in steps forward from the start.
+    pub fn position(&self) -> i32 {
+        self.position
+    }
+
+    /// Returns the number of invalid transitions seen, each one at least a
+    /// step missed.
+    pub fn errors(&self) -> u32 {
+        self.errors
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The states in forward order.
+    const FORWARD: [(bool, bool); 4] = [(false, false), (true, false), (true, true), (false, true)];
+
+    #[test]
+    fn table_is_stay_on_the_diagonal() {
+        for s in 0..4 {
+            assert_eq!(transition(s, s), Transition::Stay);
+        }
+    }
+
+    #[test]
+    fn table_steps_forward_and_backward_between_neighbours() {
+        for (i, &(a, b)) in FORWARD.iter().enumerate() {
+            let (next_a, next_b) = FORWARD[(i + 1) % 4];
+            assert_eq!(
+                transition(state(a, b), state(next_a, next_b)),
+                Transition::Forward
+            );
+            assert_eq!(
+                transition(state(next_a, next_b), state(a, b)),
+                Transition::Backward
+            );
+        }
+    }
+
+    #[test]
+    fn table_is_invalid_when_both_change() {
+        for s in 0..4 {
+            assert_eq!(transition(s, s ^ 0b11), Transition::Invalid);
+        }
+    }
+
+    #[test]
+    fn table_is_antisymmetric() {
+        for from in 0..4 {
+            for to in 0..4 {
+                let reverse = match transition(from, to) {
+                    Transition::Forward => Transition::Backward,
+                    Transition::Backward => Transition::Forward,
+                    other => other,
+                };
+                assert_eq!(transition(to, from), reverse);
+            }
+        }
+    }
+
+    #[test]
+    fn full_cycles_count_four_steps_each() {
+        let mut decoder = QuadratureDecoder::new(false, false);
+        for &(a, b) in FORWARD.iter().cycle().skip(1).take(8) {
+            decoder.update(a, b);
+        }
+        assert_eq!(decoder.position(), 8);
+        for &(a, b) in FORWARD.iter().rev().cycle().take(12) {
+            decoder.update(a, b);
+        }
+        assert_eq!(decoder.position(), -4);
+        assert_eq!(decoder.position().div_euclid(4), -1);
+        assert_eq!(decoder.errors(), 0);
+    }
+
+    #[test]
+    fn bounce_on_one_channel_cancels_out() {
+        let mut decoder = QuadratureDecoder::new(false, false);
+        // A chatters on its way up, with B steady low.
+        for &a in &[true, false, true, false, true] {
+            decoder.update(a, false);
+        }
+        assert_eq!(decoder.position(), 1);
+        assert_eq!(decoder.errors(), 0);
+    }
+
+    #[test]
+    fn missed_step_is_an_error_and_does_not_move() {
+        let mut decoder = QuadratureDecoder::new(false, false);
+        assert_eq!(decoder.update(true, true), Transition::Invalid);
+        assert_eq!(decoder.position(), 0);
+        assert_eq!(decoder.errors(), 1);
+        // Decoding carries on from the new state.
+        assert_eq!(decoder.update(false, true), Transition::Forward);
+        assert_eq!(decoder.position(), 1);
+    }
+
+    #[test]
+    fn repeated_levels_stay() {
+        let mut decoder = QuadratureDecoder::new(true, false);
+        assert_eq!(decoder.update(true, false), Transition::Stay);
+        assert_eq!(decoder.position(), 0);
+    }
+}
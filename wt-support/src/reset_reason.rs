@@ -0,0 +1,173 @@
+//! Reason for the last reset, from the reset flags in RCC_CSR.
+//!
+//! Every time the chip comes out of reset, the RCC sets a flag in its control
+//! and status register (RCC_CSR) for what caused it: a power-on, the NRST pin,
+//! a software reset, one of the watchdogs, or entering a low-power mode when
+//! the option bytes make that reset. [`ResetReason::from_csr`] decodes those
+//! flags, which is handy for telling a watchdog reset from a power cycle when a
+//! board seems to restart by itself.
+//!
+//! RCC isn't a core peripheral, so the register is read through the chip's PAC
+//! by the caller, and only its value is passed in here. That keeps this module
+//! independent of the chip like the rest of the crate. The flags are in the
+//! same bits on the STM32F303 and the STM32F767.
+//!
+//! ```ignore
+//! let reason = ResetReason::from_csr(device_periphs.RCC.csr.read().bits());
+//! device_periphs.RCC.csr.modify(|_, w| w.rmvf().set_bit());
+//! writeln!(uart4, "Reset reason: {:?}\r", reason).ok();
+//! ```
+//!
+//! # Sticky flags
+//!
+//! The flags are only cleared by a power-on reset or by writing the RMVF bit.
+//! Any other reset leaves the flags from the ones before it set, so after, say,
+//! a software reset followed by a watchdog reset, both flags are set and
+//! there's no telling which came last. Setting RMVF after reading them, as
+//! above, means each boot only sees the flags for its own reset. That also
+//! means the reason can only be read once per boot, so read it once, early on,
+//! before `RCC.constrain()` takes the register block, and keep the result.
+
+/// Low-power reset flag.
+pub const LPWRRSTF: u32 = 1 << 31;
+/// Window watchdog reset flag.
+pub const WWDGRSTF: u32 = 1 << 30;
+/// Independent watchdog reset flag.
+pub const IWDGRSTF: u32 = 1 << 29;
+/// Software reset flag.
+pub const SFTRSTF: u32 = 1 << 28;
+/// Power-on/power-down reset flag.
+pub const PORRSTF: u32 = 1 << 27;
+/// NRST pin reset flag.
+pub const PINRSTF: u32 = 1 << 26;
+/// Remove reset flags bit. Writing a 1 clears all of the flags above.
+pub const RMVF: u32 = 1 << 24;
+
+/// What caused the last reset.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ResetReason {
+    /// The supply came up from below the power-on threshold.
+    PowerOn,
+    /// The NRST pin was pulled low, e.g. by the reset button or the debugger.
+    Pin,
+    /// Software requested a reset through SCB AIRCR, e.g. with
+    /// `cortex_m::peripheral::SCB::sys_reset()`.
+    Software,
+    /// The independent watchdog (IWDG) timed out.
+    IndependentWatchdog,
+    /// The window watchdog (WWDG) timed out or was refreshed too early.
+    WindowWatchdog,
+    /// A Stop or Standby mode was entered with the option bytes set to reset
+    /// instead.
+    LowPower,
+    /// None of the flags were set, e.g. because they were already cleared
+    /// during this boot.
+    Unknown,
+}
+
+impl ResetReason {
+    /// Decodes the value of RCC_CSR into the reason for the reset.
+    ///
+    /// Every reset other than a pin reset drives the NRST pin low on its way
+    /// through, so PINRSTF is set along with the flag for the real cause. A
+    /// power-on reset is then the cause if it's set, since it also clears any
+    /// flags left from before. That leaves the pin as the cause only when its
+    /// flag is the only one set, so the flags are checked from the most
+    /// specific cause to the least.
+    pub fn from_csr(csr: u32) -> Self {
+        if csr & LPWRRSTF != 0 {
+            ResetReason::LowPower
+        } else if csr & WWDGRSTF != 0 {
+            ResetReason::WindowWatchdog
+        } else if csr & IWDGRSTF != 0 {
+            ResetReason::IndependentWatchdog
+        } else if csr & SFTRSTF != 0 {
+            ResetReason::Software
+        } else if csr & PORRSTF != 0 {
+            ResetReason::PowerOn
+        } else if csr & PINRSTF != 0 {
+            ResetReason::Pin
+        } else {
+            ResetReason::Unknown
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The LSI enable and ready bits, which are in the same register but have
+    /// nothing to do with the reset.
+    const LSI_ON_RDY: u32 = 0b11;
+
+    #[test]
+    fn power_on_sets_por_and_pin_flags() {
+        assert_eq!(
+            ResetReason::from_csr(PORRSTF | PINRSTF),
+            ResetReason::PowerOn
+        );
+    }
+
+    #[test]
+    fn pin_flag_alone_is_pin_reset() {
+        assert_eq!(ResetReason::from_csr(PINRSTF), ResetReason::Pin);
+    }
+
+    #[test]
+    fn software_reset_also_sets_pin_flag() {
+        assert_eq!(
+            ResetReason::from_csr(SFTRSTF | PINRSTF),
+            ResetReason::Software
+        );
+    }
+
+    #[test]
+    fn watchdog_resets() {
+        assert_eq!(
+            ResetReason::from_csr(IWDGRSTF | PINRSTF),
+            ResetReason::IndependentWatchdog
+        );
+        assert_eq!(
+            ResetReason::from_csr(WWDGRSTF | PINRSTF),
+            ResetReason::WindowWatchdog
+        );
+    }
+
+    #[test]
+    fn low_power_reset() {
+        assert_eq!(
+            ResetReason::from_csr(LPWRRSTF | PINRSTF),
+            ResetReason::LowPower
+        );
+    }
+
+    #[test]
+    fn watchdog_wins_over_sticky_flags_from_earlier_resets() {
+        // A software reset followed by an IWDG reset without the flags being
+        // cleared in between.
+        assert_eq!(
+            ResetReason::from_csr(IWDGRSTF | SFTRSTF | PINRSTF),
+            ResetReason::IndependentWatchdog
+        );
+    }
+
+    #[test]
+    fn other_bits_are_ignored() {
+        assert_eq!(
+            ResetReason::from_csr(SFTRSTF | PINRSTF | LSI_ON_RDY),
+            ResetReason::Software
+        );
+        // Bit 25 is OBLRSTF on the STM32F303 and BORRSTF on the STM32F767.
+        assert_eq!(
+            ResetReason::from_csr(PORRSTF | PINRSTF | 1 << 25),
+            ResetReason::PowerOn
+        );
+    }
+
+    #[test]
+    fn no_flags_is_unknown() {
+        assert_eq!(ResetReason::from_csr(0), ResetReason::Unknown);
+        assert_eq!(ResetReason::from_csr(LSI_ON_RDY), ResetReason::Unknown);
+    }
+}
@@ -0,0 +1,272 @@
+//! A minimal cooperative scheduler for periodic tasks.
+//!
+//! A [`Scheduler`] holds a fixed set of [`Task`]s, each a function and the
+//! interval to call it at. [`Scheduler::run`] is called over and over from the
+//! main loop, and calls each task that's due on the millisecond tick of a
+//! [`TickSource`], such as [`SysTickMillis`](crate::millis::SysTickMillis).
+//!
+//! ```ignore
+//! fn blink() { /* Toggle the LED. */ }
+//! fn heartbeat() { /* Write to the UART. */ }
+//!
+//! wt_support::millis::init(core_periphs.SYST, clocks.sysclk().0);
+//!
+//! let mut scheduler = Scheduler::new([
+//!     Task::new(500, blink),
+//!     Task::new(1_000, heartbeat),
+//! ]);
+//! loop {
+//!     scheduler.run(&SysTickMillis);
+//!     asm::wfi();
+//! }
+//! ```
+//!
+//! # Between a bare loop and RTIC
+//!
+//! Examples like millis and watchdog-tasks write their main loop by hand,
+//! checking [`crate::millis::elapsed_since`] for every job that runs at an
+//! interval, which gets harder to follow with every job added. The scheduler
+//! takes that bookkeeping out of the loop, so each job is a function on its
+//! own and the intervals are all in one place.
+//!
+//! It's still cooperative, though: tasks run one after another, in the order
+//! they were given, and each one has to return before the next can run, so one
+//! that takes a long time, or blocks, delays all of the others. Nothing runs
+//! at a higher priority than anything else, either. RTIC, as in the rtic-uart
+//! example, solves both by running tasks from interrupt handlers, so a higher
+//! priority task preempts a lower one, and checks at compile time that tasks
+//! sharing data can't interrupt each other while using it.
+//!
+//! # Timing
+//!
+//! A task is due once its interval has passed since it was last due, rather
+//! than since it last ran, so a task that runs late doesn't push its later
+//! runs back with it. If it falls a whole interval or more behind, e.g. behind
+//! a slow task, the runs it missed are skipped rather than made up in a burst,
+//! and it's next due an interval after it finally runs. The elapsed time is
+//! worked out with wrapping subtraction, the same as
+//! [`crate::millis::elapsed_since`], so it's correct across a wraparound of the
+//! tick count.
+//!
+//! Every task is first due one interval after the tick count the
+//! [`Scheduler`] is created at, which is 0 if it's created just after
+//! [`crate::millis::init`].
+
+use crate::millis::TickSource;
+
+/// A function to call periodically, and when it was last due.
+#[derive(Clone, Copy, Debug)]
+pub struct Task {
+    interval_ms: u32,
+    last_run: u32,
+    run: fn(),
+}
+
+impl Task {
+    /// Creates a task that calls `run` every `interval_ms` milliseconds.
+    pub const fn new(interval_ms: u32, run: fn()) -> Self {
+        Task {
+            interval_ms,
+            last_run: 0,
+            run,
+        }
+    }
+}
+
+/// A fixed set of `N` periodic tasks.
+#[derive(Debug)]
+pub struct Scheduler<const N: usize> {
+    tasks: [Task; N],
+}
+
+impl<const N: usize> Scheduler<N> {
+    /// Creates a scheduler for `tasks`, with every task first due one
+    /// interval after tick 0.
+    pub const fn new(tasks: [Task; N]) -> Self {
+        Scheduler { tasks }
+    }
+
+    /// Creates a scheduler for `tasks`, with every task first due one
+    /// interval after `tick`'s count now.
+    pub fn starting_now<T: TickSource>(mut tasks: [Task; N], tick: &T) -> Self {
+        let now = tick.now_ms();
+        for task in tasks.iter_mut() {
+            task.last_run = now;
+        }
+        Scheduler { tasks }
+    }
+
+    /// Calls every task that's due on `tick`, in order, and returns the
+    /// number called.
+    pub fn run<T: TickSource>(&mut self, tick: &T) -> usize {
+        let mut count = 0;
+        for task in self.tasks.iter_mut() {
+            let now = tick.now_ms();
+            let elapsed = now.wrapping_sub(task.last_run);
+            if elapsed < task.interval_ms {
+                continue;
+            }
+
+            task.last_run = if elapsed < task.interval_ms.saturating_mul(2) {
+                task.last_run.wrapping_add(task.interval_ms)
+            } else {
+                now
+            };
+            (task.run)();
+            count += 1;
+        }
+        count
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::{Cell, RefCell};
+    use std::vec::Vec;
+
+    /// A tick source that only changes when the test sets it.
+    struct MockClock {
+        now: Cell<u32>,
+    }
+
+    impl MockClock {
+        fn at(now: u32) -> Self {
+            MockClock {
+                now: Cell::new(now),
+            }
+        }
+
+        fn set(&self, now: u32) {
+            self.now.set(now);
+        }
+    }
+
+    impl TickSource for MockClock {
+        fn now_ms(&self) -> u32 {
+            self.now.get()
+        }
+    }
+
+    std::thread_local! {
+        /// Names of the tasks run so far on this test's thread.
+        static RAN: RefCell<Vec<&'static str>> = const { RefCell::new(Vec::new()) };
+    }
+
+    fn blink() {
+        RAN.with(|ran| ran.borrow_mut().push("blink"));
+    }
+
+    fn heartbeat() {
+        RAN.with(|ran| ran.borrow_mut().push("heartbeat"));
+    }
+
+    /// Returns the names of the tasks run since the last call.
+    fn take_ran() -> Vec<&'static str> {
+        RAN.with(|ran| ran.take())
+    }
+
+    /// Sets `clock` to each time in `times` in turn, running `scheduler` at
+    /// each, and returns the times each task ran at.
+    fn run_at<const N: usize>(
+        scheduler: &mut Scheduler<N>,
+        clock: &MockClock,
+        times: impl IntoIterator<Item = u32>,
+    ) -> Vec<(u32, &'static str)> {
+        take_ran();
+        let mut ran = Vec::new();
+        for now in times {
+            clock.set(now);
+            scheduler.run(clock);
+            ran.extend(take_ran().into_iter().map(|name| (now, name)));
+        }
+        ran
+    }
+
+    #[test]
+    fn tasks_run_at_their_intervals() {
+        let clock = MockClock::at(0);
+        let mut scheduler = Scheduler::new([Task::new(500, blink), Task::new(1_000, heartbeat)]);
+
+        assert_eq!(
+            run_at(&mut scheduler, &clock, 0..=2_000),
+            vec![
+                (500, "blink"),
+                (1_000, "blink"),
+                (1_000, "heartbeat"),
+                (1_500, "blink"),
+                (2_000, "blink"),
+                (2_000, "heartbeat"),
+            ]
+        );
+    }
+
+    #[test]
+    fn nothing_runs_before_first_interval() {
+        let clock = MockClock::at(499);
+        let mut scheduler = Scheduler::new([Task::new(500, blink)]);
+        assert_eq!(scheduler.run(&clock), 0);
+        assert!(take_ran().is_empty());
+    }
+
+    #[test]
+    fn run_returns_number_of_tasks_run() {
+        let clock = MockClock::at(1_000);
+        let mut scheduler = Scheduler::new([Task::new(500, blink), Task::new(1_000, heartbeat)]);
+        assert_eq!(scheduler.run(&clock), 2);
+        take_ran();
+    }
+
+    #[test]
+    fn late_run_keeps_schedule() {
+        // Run late at 600 rather than 500, the next run is still due at 1_000.
+        let clock = MockClock::at(0);
+        let mut scheduler = Scheduler::new([Task::new(500, blink)]);
+        assert_eq!(
+            run_at(&mut scheduler, &clock, [600, 999, 1_000]),
+            vec![(600, "blink"), (1_000, "blink")]
+        );
+    }
+
+    #[test]
+    fn missed_runs_are_skipped() {
+        // Not run at all until 1_700, the runs due at 500, 1_000, and 1_500
+        // are replaced by the one run at 1_700, and the next is due at 2_200.
+        let clock = MockClock::at(0);
+        let mut scheduler = Scheduler::new([Task::new(500, blink)]);
+        assert_eq!(
+            run_at(&mut scheduler, &clock, [1_700, 1_701, 2_199, 2_200]),
+            vec![(1_700, "blink"), (2_200, "blink")]
+        );
+    }
+
+    #[test]
+    fn starting_now_delays_first_run() {
+        let clock = MockClock::at(10_000);
+        let mut scheduler = Scheduler::starting_now([Task::new(500, blink)], &clock);
+        assert_eq!(
+            run_at(&mut scheduler, &clock, [10_000, 10_499, 10_500]),
+            vec![(10_500, "blink")]
+        );
+    }
+
+    #[test]
+    fn runs_across_tick_wraparound() {
+        let clock = MockClock::at(u32::MAX - 299);
+        let mut scheduler = Scheduler::starting_now([Task::new(500, blink)], &clock);
+        assert_eq!(
+            run_at(&mut scheduler, &clock, [u32::MAX, 199, 200, 700]),
+            vec![(200, "blink"), (700, "blink")]
+        );
+    }
+
+    #[test]
+    fn zero_interval_runs_every_time() {
+        let clock = MockClock::at(0);
+        let mut scheduler = Scheduler::new([Task::new(0, blink)]);
+        assert_eq!(
+            run_at(&mut scheduler, &clock, [0, 0, 1]),
+            vec![(0, "blink"), (0, "blink"), (1, "blink")]
+        );
+    }
+}
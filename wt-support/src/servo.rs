@@ -0,0 +1,73 @@
+//! Hobby servo pulse widths.
+//!
+//! A standard RC servo is driven with a pulse every 20 ms (50 Hz), and the
+//! width of the pulse sets the angle of the horn: 1.0 ms for one end of travel
+//! (0 degrees), 1.5 ms for the center (90 degrees), and 2.0 ms for the other end
+//! (180 degrees), scaling linearly in between. Many servos accept pulses a bit
+//! outside this window for a wider range of travel, but that varies from servo
+//! to servo, so it's not assumed here.
+//!
+//! With a PWM output running at 50 Hz, the pulse width is set by the duty. If
+//! the channel's maximum duty, the value that would hold the output high for
+//! the whole period, is `max_duty`, then a pulse of `t` ms takes a duty of
+//! `max_duty * t / 20`. [`angle_to_duty`] does this calculation for an angle.
+//!
+//! ```ignore
+//! // A timer ticking at 1 MHz with an ARR of 19_999 has a max duty of 20_000.
+//! let duty = wt_support::servo::angle_to_duty(90, 20_000); // 1_500, i.e. 1.5 ms
+//! ```
+
+/// Period of the servo signal in microseconds (50 Hz).
+pub const PERIOD_US: u32 = 20_000;
+
+/// Width of the pulse for 0 degrees in microseconds.
+pub const MIN_PULSE_US: u32 = 1_000;
+
+/// Width of the pulse for 180 degrees in microseconds.
+pub const MAX_PULSE_US: u32 = 2_000;
+
+/// Largest angle in degrees. Larger angles are clamped to it.
+pub const MAX_ANGLE_DEG: u8 = 180;
+
+/// Returns the duty for `angle_deg` on a 50 Hz PWM channel whose maximum duty
+/// (100%, a pulse as long as the 20 ms period) is `max_duty`.
+///
+/// The angle is mapped linearly onto the 1.0–2.0 ms pulse window, rounding
+/// down, and angles over [`MAX_ANGLE_DEG`] are clamped to it.
+pub fn angle_to_duty(angle_deg: u8, max_duty: u16) -> u16 {
+    let angle_deg = angle_deg.min(MAX_ANGLE_DEG) as u32;
+    let pulse_us = MIN_PULSE_US + (MAX_PULSE_US - MIN_PULSE_US) * angle_deg / MAX_ANGLE_DEG as u32;
+
+    // max_duty * pulse_us is at most 65_535 * 2_000, so this can't overflow.
+    (max_duty as u32 * pulse_us / PERIOD_US) as u16
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn one_microsecond_ticks() {
+        assert_eq!(angle_to_duty(0, 20_000), 1_000);
+        assert_eq!(angle_to_duty(90, 20_000), 1_500);
+        assert_eq!(angle_to_duty(180, 20_000), 2_000);
+    }
+
+    #[test]
+    fn full_scale_max_duty() {
+        assert_eq!(angle_to_duty(0, u16::MAX), 3_276);
+        assert_eq!(angle_to_duty(90, u16::MAX), 4_915);
+        assert_eq!(angle_to_duty(180, u16::MAX), 6_553);
+    }
+
+    #[test]
+    fn angles_past_180_are_clamped() {
+        assert_eq!(angle_to_duty(181, 20_000), angle_to_duty(180, 20_000));
+        assert_eq!(angle_to_duty(u8::MAX, 20_000), angle_to_duty(180, 20_000));
+    }
+
+    #[test]
+    fn zero_max_duty() {
+        assert_eq!(angle_to_duty(90, 0), 0);
+    }
+}
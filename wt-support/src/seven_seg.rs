@@ -0,0 +1,385 @@
+//! Driving a multiplexed 7-segment display.
+//!
+//! A display of `D` digits has 7 segments per digit, and wiring every one to
+//! its own pin would take `7 * D` pins. A multiplexed display instead joins
+//! each segment across all the digits, and gives each digit one common pin,
+//! the cathode or anode that all its segments share, for `7 + D` pins in
+//! all. Only the digit whose common pin is driven lights up, showing the
+//! segments driven at the time.
+//!
+//! A [`MultiplexedDisplay`] shows a number by lighting one digit at a time,
+//! with that digit's segments, and moving on to the next on every
+//! [`MultiplexedDisplay::refresh`]. Done quickly enough, the eye blurs the
+//! digits together into a steady display of all of them, by persistence of
+//! vision.
+//!
+//! ```ignore
+//! let mut display = MultiplexedDisplay::new(segments, digits, Wiring::COMMON_CATHODE);
+//! display.show(1234);
+//! // From a timer interrupt, every millisecond:
+//! display.refresh()?;
+//! ```
+//!
+//! # Segments
+//!
+//! The segments are named from `a` at the top, clockwise around the outside,
+//! to `g` in the middle:
+//!
+//! ```text
+//!      a
+//!     ---
+//!  f |   | b
+//!     -g-
+//!  e |   | c
+//!     ---
+//!      d
+//! ```
+//!
+//! A pattern is a byte with one bit per segment, `a` in bit 0 up to `g` in
+//! bit 6, in the same order as the segment pins given to
+//! [`MultiplexedDisplay::new`]:
+//!
+//! | Digit | g f e d c b a | Pattern |
+//! | ----- | ------------- | ------- |
+//! | 0     | 0 1 1 1 1 1 1 | 0x3F    |
+//! | 1     | 0 0 0 0 1 1 0 | 0x06    |
+//! | 2     | 1 0 1 1 0 1 1 | 0x5B    |
+//! | 3     | 1 0 0 1 1 1 1 | 0x4F    |
+//! | 4     | 1 1 0 0 1 1 0 | 0x66    |
+//! | 5     | 1 1 0 1 1 0 1 | 0x6D    |
+//! | 6     | 1 1 1 1 1 0 1 | 0x7D    |
+//! | 7     | 0 0 0 0 1 1 1 | 0x07    |
+//! | 8     | 1 1 1 1 1 1 1 | 0x7F    |
+//! | 9     | 1 1 0 1 1 1 1 | 0x6F    |
+//!
+//! # Current
+//!
+//! Each segment is an LED, and needs a resistor to limit its current, one on
+//! each segment line, not on the common pins. With a resistor on a common
+//! pin, the lit segments of a digit would share its current, so an 8 would
+//! be dimmer than a 1.
+//!
+//! The common pin of a digit carries the current of all its lit segments at
+//! once, up to 7 times that of one. A GPIO pin of the STM32F303 or STM32F767
+//! can take 25 mA at most, and far less to keep its output levels within
+//! spec, so driving the commons directly from pins needs the segment current
+//! kept to a couple of milliamps, e.g. with 1 kohm resistors from 3.3 V. For
+//! more, switch each common through a transistor and drive the transistor
+//! from the pin, which usually inverts the digit pin's active level.
+//!
+//! Only one digit is lit at a time, for `1 / D` of the time, so a
+//! multiplexed display is dimmer than the same LEDs lit all the time.
+//!
+//! # Refresh rate
+//!
+//! Every digit has to be lit often enough for the display not to flicker:
+//! at least 60 times a second, and nearer 100 for it not to flicker when the
+//! eye moves across it. [`MultiplexedDisplay::refresh`] lights one digit per
+//! call, so it has to be called `D` times for each of those, e.g. every
+//! millisecond for a 4-digit display lit 250 times a second. A timer
+//! interrupt keeps that steady whatever else the program is doing. The
+//! refreshes have to be evenly spread as well, since a digit lit for longer
+//! than the others is brighter.
+
+use embedded_hal::digital::v2::OutputPin;
+
+/// The segment pattern of each decimal digit, from [the
+/// table](self#segments).
+pub const DIGITS: [u8; 10] = [0x3F, 0x06, 0x5B, 0x4F, 0x66, 0x6D, 0x7D, 0x07, 0x7F, 0x6F];
+
+/// The pattern with no segments lit.
+pub const BLANK: u8 = 0x00;
+
+/// The pattern of a dash, segment `g` alone, shown in every digit for a
+/// number too big for the display.
+pub const DASH: u8 = 0x40;
+
+/// Returns the segment patterns that show `number` on `D` digits, most
+/// significant first.
+///
+/// Leading zeros are blank, except for the last digit, so 0 shows as a
+/// single 0. A number with more than `D` digits shows as dashes.
+pub fn decode<const D: usize>(number: u16) -> [u8; D] {
+    let mut patterns = [BLANK; D];
+    let mut rest = number;
+    for (i, pattern) in patterns.iter_mut().enumerate().rev() {
+        if rest == 0 && i + 1 < D {
+            break;
+        }
+        *pattern = DIGITS[usize::from(rest % 10)];
+        rest /= 10;
+    }
+    if rest != 0 {
+        patterns = [DASH; D];
+    }
+    patterns
+}
+
+/// Which level of the pins lights a segment.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Wiring {
+    /// Whether a segment pin lights its segment when high.
+    pub segment_active_high: bool,
+    /// Whether a digit pin lights its digit when high.
+    pub digit_active_high: bool,
+}
+
+impl Wiring {
+    /// A common cathode display with its pins wired directly, so a segment
+    /// is lit by its pin driving its anode high, and a digit by its pin
+    /// sinking the common cathode low.
+    pub const COMMON_CATHODE: Wiring = Wiring {
+        segment_active_high: true,
+        digit_active_high: false,
+    };
+
+    /// A common anode display with its pins wired directly, the other way
+    /// round from [`Wiring::COMMON_CATHODE`].
+    pub const COMMON_ANODE: Wiring = Wiring {
+        segment_active_high: false,
+        digit_active_high: true,
+    };
+}
+
+/// A multiplexed 7-segment display of `D` digits, lit one digit at a time.
+///
+/// See the [module documentation](self) for the wiring and refresh rate.
+pub struct MultiplexedDisplay<SegPin, DigitPin, const D: usize> {
+    segments: [SegPin; 7],
+    digits: [DigitPin; D],
+    wiring: Wiring,
+    patterns: [u8; D],
+    current: usize,
+}
+
+impl<SegPin, DigitPin, E, const D: usize> MultiplexedDisplay<SegPin, DigitPin, D>
+where
+    SegPin: OutputPin<Error = E>,
+    DigitPin: OutputPin<Error = E>,
+{
+    /// Creates a display from its segment pins, `a` to `g`, its digit pins,
+    /// most significant first, and how they're wired, showing nothing.
+    ///
+    /// The digit pins should start out off, for the wiring. The pins aren't
+    /// written until the first refresh.
+    pub fn new(segments: [SegPin; 7], digits: [DigitPin; D], wiring: Wiring) -> Self {
+        MultiplexedDisplay {
+            segments,
+            digits,
+            wiring,
+            patterns: [BLANK; D],
+            current: 0,
+        }
+    }
+
+    /// Shows `number`, as [`decode`] does, from the next refresh.
+    pub fn show(&mut self, number: u16) {
+        self.patterns = decode(number);
+    }
+
+    /// Shows a segment pattern in each digit, most significant first, from
+    /// the next refresh.
+    pub fn show_patterns(&mut self, patterns: [u8; D]) {
+        self.patterns = patterns;
+    }
+
+    /// Returns the patterns being shown, most significant digit first.
+    pub fn patterns(&self) -> [u8; D] {
+        self.patterns
+    }
+
+    /// Turns off the digit that's lit, and lights the next one, with its
+    /// segments.
+    ///
+    /// The digit is turned off before the segments change, and the next is
+    /// only turned on after, so the segments of one digit never show on the
+    /// other, even faintly.
+    pub fn refresh(&mut self) -> Result<(), E> {
+        if D == 0 {
+            return Ok(());
+        }
+        set(
+            &mut self.digits[self.current],
+            !self.wiring.digit_active_high,
+        )?;
+
+        self.current = (self.current + 1) % D;
+        let pattern = self.patterns[self.current];
+        for (bit, segment) in self.segments.iter_mut().enumerate() {
+            let lit = pattern & (1 << bit) != 0;
+            set(segment, lit == self.wiring.segment_active_high)?;
+        }
+
+        set(
+            &mut self.digits[self.current],
+            self.wiring.digit_active_high,
+        )
+    }
+
+    /// Releases the pins.
+    pub fn free(self) -> ([SegPin; 7], [DigitPin; D]) {
+        (self.segments, self.digits)
+    }
+}
+
+/// Sets `pin` high if `high`, and low otherwise.
+fn set<P: OutputPin>(pin: &mut P, high: bool) -> Result<(), P::Error> {
+    if high {
+        pin.set_high()
+    } else {
+        pin.set_low()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::vec::Vec;
+
+    use crate::mock::{Log, MockPin, Op};
+
+    #[test]
+    fn digit_patterns_light_the_right_segments() {
+        // a, b, c, d, e, f, g for each digit.
+        const SEGMENTS: [&str; 10] = [
+            "abcdef", "bc", "abdeg", "abcdg", "bcfg", "acdfg", "acdefg", "abc", "abcdefg", "abcdfg",
+        ];
+        for (pattern, segments) in DIGITS.iter().zip(SEGMENTS.iter()) {
+            let expected = segments
+                .bytes()
+                .fold(0, |bits, segment| bits | (1 << (segment - b'a')));
+            assert_eq!(*pattern, expected, "{}", segments);
+        }
+    }
+
+    #[test]
+    fn decode_fills_every_digit() {
+        assert_eq!(
+            decode::<4>(1234),
+            [DIGITS[1], DIGITS[2], DIGITS[3], DIGITS[4]]
+        );
+        assert_eq!(decode::<4>(9999), [DIGITS[9]; 4]);
+        assert_eq!(
+            decode::<4>(1000),
+            [DIGITS[1], DIGITS[0], DIGITS[0], DIGITS[0]]
+        );
+    }
+
+    #[test]
+    fn decode_blanks_leading_zeros() {
+        assert_eq!(decode::<4>(42), [BLANK, BLANK, DIGITS[4], DIGITS[2]]);
+        assert_eq!(decode::<4>(7), [BLANK, BLANK, BLANK, DIGITS[7]]);
+        assert_eq!(decode::<4>(305), [BLANK, DIGITS[3], DIGITS[0], DIGITS[5]]);
+    }
+
+    #[test]
+    fn decode_zero_is_a_single_zero() {
+        assert_eq!(decode::<4>(0), [BLANK, BLANK, BLANK, DIGITS[0]]);
+        assert_eq!(decode::<1>(0), [DIGITS[0]]);
+    }
+
+    #[test]
+    fn decode_too_big_is_dashes() {
+        assert_eq!(decode::<4>(10_000), [DASH; 4]);
+        assert_eq!(decode::<4>(u16::MAX), [DASH; 4]);
+        assert_eq!(decode::<2>(100), [DASH; 2]);
+        assert_eq!(
+            decode::<5>(u16::MAX),
+            [DIGITS[6], DIGITS[5], DIGITS[5], DIGITS[3], DIGITS[5]]
+        );
+    }
+
+    // ID of the mock pin of the first digit. The segments are 0 to 6.
+    const DIGIT: usize = 10;
+
+    fn display(wiring: Wiring) -> (MultiplexedDisplay<MockPin, MockPin, 4>, Log) {
+        let log = Log::default();
+        let segments = [0, 1, 2, 3, 4, 5, 6].map(|index| MockPin::new(index, &log));
+        let digits = [0, 1, 2, 3].map(|index| MockPin::new(DIGIT + index, &log));
+        (MultiplexedDisplay::new(segments, digits, wiring), log)
+    }
+
+    /// Returns the segment pattern and digit lit by each refresh in `log`,
+    /// checking that the segments only ever change with every digit off.
+    fn lit(log: &[Op], wiring: Wiring) -> Vec<(usize, u8)> {
+        let mut on = [false; 4];
+        let mut segments = [false; 7];
+        let mut lit = Vec::new();
+        for &op in log {
+            let (id, level) = match op {
+                Op::High(id) => (id, true),
+                Op::Low(id) => (id, false),
+                other => panic!("not a pin write: {:?}", other),
+            };
+            match id.checked_sub(DIGIT) {
+                None => {
+                    assert!(on.iter().all(|&on| !on), "segment changed with a digit on");
+                    segments[id] = level == wiring.segment_active_high;
+                }
+                Some(index) => {
+                    on[index] = level == wiring.digit_active_high;
+                    if on[index] {
+                        let pattern = segments
+                            .iter()
+                            .enumerate()
+                            .fold(0, |bits, (bit, &lit)| bits | (u8::from(lit) << bit));
+                        lit.push((index, pattern));
+                    }
+                }
+            }
+        }
+        lit
+    }
+
+    #[test]
+    fn refresh_cycles_through_the_digits() {
+        let (mut display, log) = display(Wiring::COMMON_CATHODE);
+        display.show(1234);
+        for _ in 0..8 {
+            display.refresh().unwrap();
+        }
+        let patterns = decode::<4>(1234);
+        let expected: Vec<_> = [1, 2, 3, 0, 1, 2, 3, 0]
+            .iter()
+            .map(|&digit| (digit, patterns[digit]))
+            .collect();
+        assert_eq!(lit(&log.borrow(), Wiring::COMMON_CATHODE), expected);
+    }
+
+    #[test]
+    fn refresh_follows_the_wiring() {
+        for wiring in [Wiring::COMMON_CATHODE, Wiring::COMMON_ANODE] {
+            let (mut display, log) = display(wiring);
+            display.show(8);
+            for _ in 0..4 {
+                display.refresh().unwrap();
+            }
+            assert_eq!(
+                lit(&log.borrow(), wiring),
+                [(1, BLANK), (2, BLANK), (3, DIGITS[8]), (0, BLANK)]
+            );
+        }
+    }
+
+    #[test]
+    fn refresh_turns_off_the_last_digit_first() {
+        let (mut display, log) = display(Wiring::COMMON_CATHODE);
+        display.refresh().unwrap();
+        display.refresh().unwrap();
+        let log = log.borrow();
+        let second = &log[log.len() / 2..];
+        assert_eq!(second.first(), Some(&Op::High(DIGIT + 1)));
+        assert_eq!(second.last(), Some(&Op::Low(DIGIT + 2)));
+    }
+
+    #[test]
+    fn show_changes_the_patterns() {
+        let (mut display, _log) = display(Wiring::COMMON_CATHODE);
+        assert_eq!(display.patterns(), [BLANK; 4]);
+        display.show(56);
+        assert_eq!(display.patterns(), decode::<4>(56));
+        display.show_patterns([DASH, BLANK, DASH, BLANK]);
+        assert_eq!(display.patterns(), [DASH, BLANK, DASH, BLANK]);
+    }
+}
@@ -0,0 +1,299 @@
+//! Driving 74HC595 shift registers to add outputs.
+//!
+//! A 74HC595 turns three GPIO outputs into eight: bits are shifted in one at
+//! a time on a data and a clock pin, then copied to its eight outputs all at
+//! once by a pulse on a third, latch pin. Several can be daisy-chained, with
+//! the QH' output of each one wired to the SER input of the next, so the same
+//! three pins drive eight outputs per register in the chain.
+//!
+//! | Pin     | 74HC595 pin | Does                                              |
+//! |---------|-------------|---------------------------------------------------|
+//! | `data`  | SER (14)    | Level of the next bit to shift in                 |
+//! | `clock` | SRCLK (11)  | Shifts in the bit on SER on a rising edge         |
+//! | `latch` | RCLK (12)   | Copies the shifted bits to QA-QH on a rising edge |
+//!
+//! OE (13) has to be tied low for the outputs to be driven, and SRCLR (10)
+//! high so that the shift register isn't held cleared.
+//!
+//! ```ignore
+//! let mut shift_reg = ShiftRegister::new(data_pin, clock_pin, latch_pin);
+//! shift_reg.write_byte(0b1000_0001).ok();
+//! ```
+//!
+//! # Bit order
+//!
+//! Bytes are shifted out MSB-first. Every clock moves each bit in the register
+//! one place along, from QA towards QH, so the first bit shifted in is the one
+//! that ends up furthest along: bit 7 on QH, down to bit 0 on QA.
+//!
+//! # Timing
+//!
+//! Each bit is shifted in by setting `data`, then raising `clock`, so the
+//! data is set up before the rising edge that samples it, and by lowering
+//! `clock` again before the next bit, so it doesn't change while the clock is
+//! high. The 74HC595 needs the data held for some time either side of the edge
+//! and each clock level for some minimum time, all of them in the tens of
+//! nanoseconds at 3.3 V, and longest at a low supply voltage (the datasheet
+//! gives them at 2 V, 4.5 V, and 6 V). This doesn't wait at all between pin
+//! writes, and relies on each write taking a few cycles of a core clock of
+//! 48 MHz or so to meet them. At a much faster core clock, a driver would need
+//! to add a delay between each write.
+//!
+//! # Latch
+//!
+//! The outputs don't change while the bits are being shifted in, only once
+//! the latch is pulsed, so the outputs go straight from one pattern to the
+//! next with none of the patterns in between showing, however long the
+//! shifting takes. [`ShiftRegister::write_bytes`] only latches once at the
+//! end, so every register in a chain changes its outputs at the same time.
+
+use embedded_hal::digital::v2::OutputPin;
+
+/// A chain of one or more 74HC595 shift registers, driven through three
+/// output pins.
+///
+/// See the [module documentation](self) for how the pins are wired.
+pub struct ShiftRegister<DataPin, ClockPin, LatchPin> {
+    data: DataPin,
+    clock: ClockPin,
+    latch: LatchPin,
+}
+
+impl<DataPin, ClockPin, LatchPin, E> ShiftRegister<DataPin, ClockPin, LatchPin>
+where
+    DataPin: OutputPin<Error = E>,
+    ClockPin: OutputPin<Error = E>,
+    LatchPin: OutputPin<Error = E>,
+{
+    /// Creates a shift register driver from its three pins.
+    ///
+    /// The `clock` and `latch` pins should start out low. The pins aren't
+    /// written until the first call to [`Self::write_byte`] or
+    /// [`Self::write_bytes`].
+    pub fn new(data: DataPin, clock: ClockPin, latch: LatchPin) -> Self {
+        ShiftRegister { data, clock, latch }
+    }
+
+    /// Shifts `byte` out MSB-first to a single register and latches it to the
+    /// outputs.
+    pub fn write_byte(&mut self, byte: u8) -> Result<(), E> {
+        self.shift_out(byte)?;
+        self.pulse_latch()
+    }
+
+    /// Shifts `bytes` out to a chain of registers, one byte each, and latches
+    /// them all to the outputs at once.
+    ///
+    /// `bytes[0]` ends up in the register wired to the pins, `bytes[1]` in
+    /// the one after it in the chain, and so on. The last byte is shifted out
+    /// first, since the first bits in are the ones pushed furthest along.
+    pub fn write_bytes(&mut self, bytes: &[u8]) -> Result<(), E> {
+        for &byte in bytes.iter().rev() {
+            self.shift_out(byte)?;
+        }
+        self.pulse_latch()
+    }
+
+    /// Releases the pins.
+    pub fn free(self) -> (DataPin, ClockPin, LatchPin) {
+        (self.data, self.clock, self.latch)
+    }
+
+    fn shift_out(&mut self, byte: u8) -> Result<(), E> {
+        for bit in (0..8).rev() {
+            if byte & (1 << bit) != 0 {
+                self.data.set_high()?;
+            } else {
+                self.data.set_low()?;
+            }
+            self.clock.set_high()?;
+            self.clock.set_low()?;
+        }
+        Ok(())
+    }
+
+    fn pulse_latch(&mut self) -> Result<(), E> {
+        self.latch.set_high()?;
+        self.latch.set_low()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::vec::Vec;
+
+    use crate::mock::{Log, MockPin, Op};
+
+    // IDs of the mock pins.
+    const DATA: usize = 0;
+    const CLOCK: usize = 1;
+    const LATCH: usize = 2;
+
+    type MockShiftRegister = ShiftRegister<MockPin, MockPin, MockPin>;
+
+    fn shift_reg() -> (MockShiftRegister, Log) {
+        let log = Log::default();
+        let pin = |id| MockPin::new(id, &log);
+        (ShiftRegister::new(pin(DATA), pin(CLOCK), pin(LATCH)), log)
+    }
+
+    /// Returns the ID of the pin an op drove, and whether it drove it high.
+    fn level(op: Op) -> (usize, bool) {
+        match op {
+            Op::High(id) => (id, true),
+            Op::Low(id) => (id, false),
+            other => panic!("not a pin write: {:?}", other),
+        }
+    }
+
+    /// A model of a chain of 74HC595s, fed the pin writes from a log.
+    struct Model {
+        data: bool,
+        shift: Vec<bool>,
+        outputs: Vec<bool>,
+        latches: usize,
+    }
+
+    impl Model {
+        fn run(registers: usize, log: &Log) -> Self {
+            let mut model = Model {
+                data: false,
+                shift: vec![false; 8 * registers],
+                outputs: vec![false; 8 * registers],
+                latches: 0,
+            };
+            let (mut clock, mut latch) = (false, false);
+            for &op in log.borrow().iter() {
+                let (pin, high) = level(op);
+                match pin {
+                    DATA => model.data = high,
+                    CLOCK => {
+                        if high && !clock {
+                            model.shift.insert(0, model.data);
+                            model.shift.pop();
+                        }
+                        clock = high;
+                    }
+                    LATCH => {
+                        if high && !latch {
+                            model.outputs = model.shift.clone();
+                            model.latches += 1;
+                        }
+                        latch = high;
+                    }
+                    _ => unreachable!(),
+                }
+            }
+            model
+        }
+
+        /// Outputs QA-QH of register `index` in the chain, as a byte with QA
+        /// as bit 0.
+        fn output_byte(&self, index: usize) -> u8 {
+            self.outputs[8 * index..8 * index + 8]
+                .iter()
+                .enumerate()
+                .fold(0, |byte, (bit, &high)| byte | (high as u8) << bit)
+        }
+    }
+
+    #[test]
+    fn write_byte_shifts_msb_first() {
+        let (mut shift_reg, log) = shift_reg();
+        shift_reg.write_byte(0b1011_0010).unwrap();
+
+        let data_on_rising_clock: Vec<bool> = {
+            let mut data = false;
+            let mut bits = Vec::new();
+            for &op in log.borrow().iter() {
+                match level(op) {
+                    (DATA, high) => data = high,
+                    (CLOCK, true) => bits.push(data),
+                    _ => {}
+                }
+            }
+            bits
+        };
+        assert_eq!(
+            data_on_rising_clock,
+            [true, false, true, true, false, false, true, false]
+        );
+    }
+
+    #[test]
+    fn write_byte_sets_the_outputs() {
+        for byte in [0x00, 0xFF, 0x01, 0x80, 0xA5] {
+            let (mut shift_reg, log) = shift_reg();
+            shift_reg.write_byte(byte).unwrap();
+            assert_eq!(Model::run(1, &log).output_byte(0), byte);
+        }
+    }
+
+    #[test]
+    fn msb_ends_up_on_qh() {
+        let (mut shift_reg, log) = shift_reg();
+        shift_reg.write_byte(0x80).unwrap();
+        let model = Model::run(1, &log);
+        assert!(model.outputs[7]);
+        assert!(model.outputs[..7].iter().all(|&high| !high));
+    }
+
+    #[test]
+    fn data_is_set_before_each_rising_clock_edge() {
+        // Each rising edge of the clock comes straight after a data write,
+        // with the clock low before it, and the clock goes low again before
+        // the next data write.
+        let (mut shift_reg, log) = shift_reg();
+        shift_reg.write_byte(0x5A).unwrap();
+        let log = log.borrow();
+        let shifting = &log[..log.len() - 2];
+        assert_eq!(shifting.len(), 8 * 3);
+        for step in shifting.chunks(3) {
+            assert_eq!(level(step[0]).0, DATA);
+            assert_eq!(step[1], Op::High(CLOCK));
+            assert_eq!(step[2], Op::Low(CLOCK));
+        }
+    }
+
+    #[test]
+    fn latch_pulses_once_after_shifting() {
+        let (mut shift_reg, log) = shift_reg();
+        shift_reg.write_byte(0x5A).unwrap();
+        let log = log.borrow();
+        assert_eq!(log[log.len() - 2..], [Op::High(LATCH), Op::Low(LATCH)]);
+        assert_eq!(log.iter().filter(|&&op| level(op).0 == LATCH).count(), 2);
+    }
+
+    #[test]
+    fn outputs_only_change_on_the_latch() {
+        let (mut shift_reg, log) = shift_reg();
+        shift_reg.write_byte(0xFF).unwrap();
+        let before_latch = log.borrow().len() - 2;
+        log.borrow_mut().truncate(before_latch);
+        let model = Model::run(1, &log);
+        assert_eq!(model.latches, 0);
+        assert_eq!(model.output_byte(0), 0x00);
+    }
+
+    #[test]
+    fn write_bytes_fills_a_chain_and_latches_once() {
+        let (mut shift_reg, log) = shift_reg();
+        shift_reg.write_bytes(&[0x12, 0x34, 0x56]).unwrap();
+        let model = Model::run(3, &log);
+        assert_eq!(model.latches, 1);
+        assert_eq!(model.output_byte(0), 0x12);
+        assert_eq!(model.output_byte(1), 0x34);
+        assert_eq!(model.output_byte(2), 0x56);
+    }
+
+    #[test]
+    fn write_bytes_with_one_byte_matches_write_byte() {
+        let (mut chain, chain_log) = shift_reg();
+        chain.write_bytes(&[0xC3]).unwrap();
+        let (mut single, single_log) = shift_reg();
+        single.write_byte(0xC3).unwrap();
+        assert_eq!(*chain_log.borrow(), *single_log.borrow());
+    }
+}
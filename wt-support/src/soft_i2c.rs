@@ -0,0 +1,478 @@
+//! A bit-banged I2C master on any two GPIO pins.
+//!
+//! [`SoftI2c`] drives the I2C protocol in software, one bit at a time, on a
+//! pair of pins configured as open-drain outputs. It implements the blocking
+//! I2C traits from `embedded-hal`, so it can be used in place of a hardware
+//! I2C peripheral with any driver written against them, on pins that the I2C
+//! peripherals can't be routed to.
+//!
+//! # The bus
+//!
+//! Both lines, SCL (clock) and SDA (data), are open drain: devices can only
+//! pull them low, and pull-up resistors bring them back high when nothing is
+//! pulling them down. Setting one of the pins high here doesn't drive the line
+//! high, it releases it. This is what lets devices on the bus take over SDA to
+//! answer, and is why the pins have to be open drain rather than push-pull. A
+//! push-pull output driving high while a device pulls low is a short.
+//!
+//! A transfer is made up of a few conditions built from the two lines:
+//!
+//! - Start: SDA falls while SCL is high.
+//! - Data bits: SDA is set while SCL is low and read while SCL is high, most
+//!   significant bit first. SDA must not change while SCL is high, since that
+//!   would be a start or stop instead.
+//! - Acknowledge: after every byte, the receiver pulls SDA low for a 9th clock
+//!   to ACK it, or leaves it high to NACK it.
+//! - Stop: SDA rises while SCL is high.
+//!
+//! # Timing
+//!
+//! Each half of a clock period is timed with a delay of `half_period_us`, so
+//! the clock runs at no more than `1 / (2 * half_period_us)`. A half period of
+//! 5 us gives a clock of at most 100 kHz, standard mode, which every device
+//! supports. The actual clock is slower than that, since setting pins and
+//! entering the delay take time too, but I2C has no minimum clock rate, so
+//! running slow is never a problem.
+//!
+//! # Clock stretching
+//!
+//! A device that needs more time, e.g. to fetch the data for a read, can hold
+//! SCL low after the master releases it. This is called clock stretching. Every
+//! time SCL is released here, it's read back and the master waits for it to
+//! actually go high before carrying on. If it stays low for more than
+//! [`STRETCH_TIMEOUT_US`], the transfer fails with [`Error::Timeout`], since a
+//! SCL stuck low usually means a device has hung, or there's no pull-up.
+//!
+//! The master doesn't support multiple masters on the bus. It doesn't check
+//! for arbitration loss.
+
+use embedded_hal::{
+    blocking::{
+        delay::DelayUs,
+        i2c::{Read, Write, WriteRead},
+    },
+    digital::v2::{InputPin, OutputPin},
+};
+
+/// The longest a device may stretch the clock for, in microseconds.
+pub const STRETCH_TIMEOUT_US: u32 = 1_000;
+
+/// Errors from a transfer.
+#[derive(Debug, PartialEq, Eq)]
+pub enum Error<E> {
+    /// The device didn't acknowledge its address or a byte written to it.
+    Nack,
+    /// SCL was held low for longer than [`STRETCH_TIMEOUT_US`].
+    Timeout,
+    /// Setting or reading one of the pins failed.
+    Pin(E),
+}
+
+/// A bit-banged I2C master.
+///
+/// `SCL` and `SDA` have to be open-drain outputs that can also be read back,
+/// with pull-ups on both lines, either external or enabled internally.
+pub struct SoftI2c<SCL, SDA, D> {
+    scl: SCL,
+    sda: SDA,
+    delay: D,
+    half_period_us: u32,
+}
+
+impl<SCL, SDA, D, E> SoftI2c<SCL, SDA, D>
+where
+    SCL: OutputPin<Error = E> + InputPin<Error = E>,
+    SDA: OutputPin<Error = E> + InputPin<Error = E>,
+    D: DelayUs<u32>,
+{
+    /// Creates a master on the `scl` and `sda` pins, timing the clock with
+    /// `delay`.
+    ///
+    /// The clock runs at no more than `1 / (2 * half_period_us)`, e.g. 100 kHz
+    /// for a half period of 5 us. Both lines are released, so the bus is left
+    /// idle.
+    pub fn new(scl: SCL, sda: SDA, delay: D, half_period_us: u32) -> Result<Self, Error<E>> {
+        let mut i2c = SoftI2c {
+            scl,
+            sda,
+            delay,
+            half_period_us,
+        };
+        i2c.sda.set_high().map_err(Error::Pin)?;
+        i2c.scl.set_high().map_err(Error::Pin)?;
+        Ok(i2c)
+    }
+
+    /// Releases the pins and the delay.
+    pub fn free(self) -> (SCL, SDA, D) {
+        (self.scl, self.sda, self.delay)
+    }
+
+    fn wait_half_period(&mut self) {
+        self.delay.delay_us(self.half_period_us);
+    }
+
+    /// Releases SCL and waits for it to go high, allowing for clock
+    /// stretching.
+    fn release_scl(&mut self) -> Result<(), Error<E>> {
+        self.scl.set_high().map_err(Error::Pin)?;
+        let mut waited_us = 0;
+        while self.scl.is_low().map_err(Error::Pin)? {
+            if waited_us >= STRETCH_TIMEOUT_US {
+                return Err(Error::Timeout);
+            }
+            self.delay.delay_us(1);
+            waited_us += 1;
+        }
+        Ok(())
+    }
+
+    /// Sends a start condition, or a repeated start in the middle of a
+    /// transfer.
+    ///
+    /// SDA is released first while SCL is low, then SCL, so that the one
+    /// change of SDA while SCL is high is the falling edge of the start.
+    fn start(&mut self) -> Result<(), Error<E>> {
+        self.sda.set_high().map_err(Error::Pin)?;
+        self.wait_half_period();
+        self.release_scl()?;
+        self.wait_half_period();
+        self.sda.set_low().map_err(Error::Pin)?;
+        self.wait_half_period();
+        self.scl.set_low().map_err(Error::Pin)?;
+        self.wait_half_period();
+        Ok(())
+    }
+
+    /// Sends a stop condition, leaving the bus idle.
+    fn stop(&mut self) -> Result<(), Error<E>> {
+        self.sda.set_low().map_err(Error::Pin)?;
+        self.wait_half_period();
+        self.release_scl()?;
+        self.wait_half_period();
+        self.sda.set_high().map_err(Error::Pin)?;
+        self.wait_half_period();
+        Ok(())
+    }
+
+    /// Clocks out one bit. SCL must be low on entry, and is left low.
+    fn write_bit(&mut self, bit: bool) -> Result<(), Error<E>> {
+        if bit {
+            self.sda.set_high().map_err(Error::Pin)?;
+        } else {
+            self.sda.set_low().map_err(Error::Pin)?;
+        }
+        self.wait_half_period();
+        self.release_scl()?;
+        self.wait_half_period();
+        self.scl.set_low().map_err(Error::Pin)?;
+        Ok(())
+    }
+
+    /// Clocks in one bit. SCL must be low on entry, and is left low.
+    fn read_bit(&mut self) -> Result<bool, Error<E>> {
+        // Release SDA so the device can drive it.
+        self.sda.set_high().map_err(Error::Pin)?;
+        self.wait_half_period();
+        self.release_scl()?;
+        self.wait_half_period();
+        let bit = self.sda.is_high().map_err(Error::Pin)?;
+        self.scl.set_low().map_err(Error::Pin)?;
+        Ok(bit)
+    }
+
+    /// Writes a byte and reads the acknowledge bit after it, returning
+    /// [`Error::Nack`] if the device didn't pull SDA low for it.
+    fn write_byte(&mut self, byte: u8) -> Result<(), Error<E>> {
+        for i in (0..8).rev() {
+            self.write_bit(byte & (1 << i) != 0)?;
+        }
+        if self.read_bit()? {
+            return Err(Error::Nack);
+        }
+        Ok(())
+    }
+
+    /// Reads a byte, then ACKs it if more are to follow, or NACKs it to tell
+    /// the device it was the last one.
+    fn read_byte(&mut self, ack: bool) -> Result<u8, Error<E>> {
+        let mut byte = 0;
+        for _ in 0..8 {
+            byte = (byte << 1) | self.read_bit()? as u8;
+        }
+        self.write_bit(!ack)?;
+        Ok(byte)
+    }
+
+    fn write_bytes(&mut self, address: u8, bytes: &[u8]) -> Result<(), Error<E>> {
+        self.write_byte(address << 1)?;
+        for &byte in bytes {
+            self.write_byte(byte)?;
+        }
+        Ok(())
+    }
+
+    fn read_bytes(&mut self, address: u8, buffer: &mut [u8]) -> Result<(), Error<E>> {
+        self.write_byte((address << 1) | 1)?;
+        let len = buffer.len();
+        for (i, byte) in buffer.iter_mut().enumerate() {
+            *byte = self.read_byte(i + 1 < len)?;
+        }
+        Ok(())
+    }
+
+    /// Sends a stop after a transfer whether or not it succeeded, so a failed
+    /// transfer doesn't leave the bus busy, and returns the first error.
+    fn finish(&mut self, result: Result<(), Error<E>>) -> Result<(), Error<E>> {
+        let stop = self.stop();
+        result.and(stop)
+    }
+}
+
+impl<SCL, SDA, D, E> Write for SoftI2c<SCL, SDA, D>
+where
+    SCL: OutputPin<Error = E> + InputPin<Error = E>,
+    SDA: OutputPin<Error = E> + InputPin<Error = E>,
+    D: DelayUs<u32>,
+{
+    type Error = Error<E>;
+
+    fn write(&mut self, address: u8, bytes: &[u8]) -> Result<(), Self::Error> {
+        self.start()?;
+        let result = self.write_bytes(address, bytes);
+        self.finish(result)
+    }
+}
+
+impl<SCL, SDA, D, E> Read for SoftI2c<SCL, SDA, D>
+where
+    SCL: OutputPin<Error = E> + InputPin<Error = E>,
+    SDA: OutputPin<Error = E> + InputPin<Error = E>,
+    D: DelayUs<u32>,
+{
+    type Error = Error<E>;
+
+    fn read(&mut self, address: u8, buffer: &mut [u8]) -> Result<(), Self::Error> {
+        self.start()?;
+        let result = self.read_bytes(address, buffer);
+        self.finish(result)
+    }
+}
+
+impl<SCL, SDA, D, E> WriteRead for SoftI2c<SCL, SDA, D>
+where
+    SCL: OutputPin<Error = E> + InputPin<Error = E>,
+    SDA: OutputPin<Error = E> + InputPin<Error = E>,
+    D: DelayUs<u32>,
+{
+    type Error = Error<E>;
+
+    /// Writes `bytes`, then reads into `buffer` after a repeated start,
+    /// without releasing the bus in between.
+    ///
+    /// This is how a register is usually read: the register address is
+    /// written, then the value is read back.
+    fn write_read(
+        &mut self,
+        address: u8,
+        bytes: &[u8],
+        buffer: &mut [u8],
+    ) -> Result<(), Self::Error> {
+        self.start()?;
+        let result = self.write_bytes(address, bytes).and_then(|()| {
+            self.start()?;
+            self.read_bytes(address, buffer)
+        });
+        self.finish(result)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::mock::{Log, MockDelay, MockPin, Op};
+
+    const SCL_PIN: usize = 0;
+    const SDA_PIN: usize = 1;
+
+    const ACK: bool = false;
+    const NACK: bool = true;
+
+    /// What the master put on the bus, as a device would see it.
+    #[derive(Debug, PartialEq, Eq)]
+    enum Bus {
+        Start,
+        Stop,
+        /// The level of SDA while SCL was high, as the master set it.
+        Bit(bool),
+    }
+
+    /// Replays the pin writes in `log` into starts, stops, and the bits
+    /// clocked in between.
+    ///
+    /// A bit is taken on each rising edge of SCL, but if SDA then changes
+    /// before SCL falls again, that's a start or stop rather than a bit.
+    fn bus(log: &Log) -> Vec<Bus> {
+        let (mut scl, mut sda) = (true, true);
+        let mut bit_this_high = false;
+        let mut bus = Vec::new();
+        for op in log.borrow().iter() {
+            match *op {
+                Op::High(SCL_PIN) => {
+                    if !scl {
+                        bus.push(Bus::Bit(sda));
+                        bit_this_high = true;
+                    }
+                    scl = true;
+                }
+                Op::Low(SCL_PIN) => {
+                    scl = false;
+                    bit_this_high = false;
+                }
+                Op::High(SDA_PIN) | Op::Low(SDA_PIN) => {
+                    let level = *op == Op::High(SDA_PIN);
+                    if scl && level != sda {
+                        if bit_this_high {
+                            bus.pop();
+                            bit_this_high = false;
+                        }
+                        bus.push(if level { Bus::Stop } else { Bus::Start });
+                    }
+                    sda = level;
+                }
+                _ => {}
+            }
+        }
+        bus
+    }
+
+    /// The bits of `byte`, most significant first, followed by the
+    /// acknowledge bit as the master sets SDA for it.
+    fn byte(byte: u8, ack: bool) -> impl Iterator<Item = Bus> {
+        (0..8)
+            .rev()
+            .map(move |i| Bus::Bit(byte & (1 << i) != 0))
+            .chain([Bus::Bit(ack)])
+    }
+
+    /// A master whose SDA reads return `sda_reads`, for the acknowledge bits
+    /// and data a device would drive.
+    fn i2c(log: &Log, sda_reads: &[bool]) -> SoftI2c<MockPin, MockPin, MockDelay> {
+        let scl = MockPin::new(SCL_PIN, log);
+        let sda = MockPin::new(SDA_PIN, log).driven(sda_reads);
+        SoftI2c::new(scl, sda, MockDelay::new(log), 5).unwrap()
+    }
+
+    #[test]
+    fn write_is_framed_by_a_start_and_a_stop() {
+        let log = Log::default();
+        let mut i2c = i2c(&log, &[ACK, ACK]);
+        i2c.write(0x1E, &[0x02]).unwrap();
+
+        let bus = bus(&log);
+        assert_eq!(bus.first(), Some(&Bus::Start));
+        assert_eq!(bus.last(), Some(&Bus::Stop));
+        assert_eq!(bus.len(), 2 + 2 * 9);
+    }
+
+    #[test]
+    fn bytes_are_written_most_significant_bit_first() {
+        let log = Log::default();
+        let mut i2c = i2c(&log, &[ACK, ACK]);
+        i2c.write(0x50, &[0xA5]).unwrap();
+
+        // The master releases SDA for each acknowledge bit, so it's high as
+        // far as the master set it, whatever the device drove.
+        let mut expected = vec![Bus::Start];
+        expected.extend(byte(0x50 << 1, true));
+        expected.extend(byte(0xA5, true));
+        expected.push(Bus::Stop);
+        assert_eq!(bus(&log), expected);
+    }
+
+    #[test]
+    fn nack_of_the_address_stops_without_sending_the_data() {
+        let log = Log::default();
+        let mut i2c = i2c(&log, &[NACK]);
+        assert_eq!(i2c.write(0x50, &[0xA5, 0x5A]), Err(Error::Nack));
+
+        let mut expected = vec![Bus::Start];
+        expected.extend(byte(0x50 << 1, true));
+        expected.push(Bus::Stop);
+        assert_eq!(bus(&log), expected);
+    }
+
+    #[test]
+    fn nack_of_a_data_byte_stops_the_write() {
+        let log = Log::default();
+        let mut i2c = i2c(&log, &[ACK, NACK]);
+        assert_eq!(i2c.write(0x50, &[0xA5, 0x5A]), Err(Error::Nack));
+
+        let mut expected = vec![Bus::Start];
+        expected.extend(byte(0x50 << 1, true));
+        expected.extend(byte(0xA5, true));
+        expected.push(Bus::Stop);
+        assert_eq!(bus(&log), expected);
+    }
+
+    #[test]
+    fn read_acks_every_byte_but_the_last() {
+        let log = Log::default();
+        let bits = |byte: u8| (0..8).rev().map(move |i| byte & (1 << i) != 0);
+        let mut sda_reads = vec![ACK];
+        sda_reads.extend(bits(0x3C));
+        sda_reads.extend(bits(0xC3));
+        let mut i2c = i2c(&log, &sda_reads);
+
+        let mut buffer = [0; 2];
+        i2c.read(0x50, &mut buffer).unwrap();
+        assert_eq!(buffer, [0x3C, 0xC3]);
+
+        // SDA is released for every bit the device sends, so only the
+        // master's ACK and NACK show up among them.
+        let mut expected = vec![Bus::Start];
+        expected.extend(byte((0x50 << 1) | 1, true));
+        expected.extend(byte(0xFF, ACK));
+        expected.extend(byte(0xFF, NACK));
+        expected.push(Bus::Stop);
+        assert_eq!(bus(&log), expected);
+    }
+
+    #[test]
+    fn write_read_uses_a_repeated_start() {
+        let log = Log::default();
+        let mut sda_reads = vec![ACK, ACK, ACK];
+        sda_reads.extend([false; 8]);
+        let mut i2c = i2c(&log, &sda_reads);
+
+        let mut buffer = [0xFF; 1];
+        i2c.write_read(0x50, &[0x0F], &mut buffer).unwrap();
+        assert_eq!(buffer, [0x00]);
+
+        let mut expected = vec![Bus::Start];
+        expected.extend(byte(0x50 << 1, true));
+        expected.extend(byte(0x0F, true));
+        expected.push(Bus::Start);
+        expected.extend(byte((0x50 << 1) | 1, true));
+        expected.extend(byte(0xFF, NACK));
+        expected.push(Bus::Stop);
+        assert_eq!(bus(&log), expected);
+    }
+
+    #[test]
+    fn scl_held_low_times_out() {
+        let log = Log::default();
+        let scl = MockPin::new(SCL_PIN, &log).driven(&[false; 2_000]);
+        let sda = MockPin::new(SDA_PIN, &log);
+        let mut i2c = SoftI2c::new(scl, sda, MockDelay::new(&log), 5).unwrap();
+        assert_eq!(i2c.write(0x50, &[]), Err(Error::Timeout));
+
+        let stretch_waits = log
+            .borrow()
+            .iter()
+            .filter(|op| **op == Op::DelayUs(1))
+            .count();
+        assert_eq!(stretch_waits, STRETCH_TIMEOUT_US as usize);
+    }
+}
@@ -0,0 +1,306 @@
+//! Software PWM on any GPIO output.
+//!
+//! The hardware timers can only drive PWM on the pins their channels are
+//! routed to. [`SoftPwm`] produces PWM on any other output pin instead by
+//! having a timer interrupt call [`SoftPwm::tick`] at a fixed rate. Each
+//! period of the output is `steps` ticks long: a counter counts the ticks of
+//! the period, and the pin is high while the counter is below a threshold set
+//! by the duty and low for the rest of the period.
+//!
+//! ```ignore
+//! // Called from a timer interrupt firing every 100 us, so with 100 steps
+//! // the output runs at 100 Hz in steps of 1%.
+//! pwm.tick().ok();
+//! ```
+//!
+//! The three things that can be traded off against each other are the
+//! resolution (`steps`), the PWM frequency, and the interrupt rate, which are
+//! tied together by
+//!
+//! ```text
+//! interrupt rate = PWM frequency * steps
+//! ```
+//!
+//! An LED needs at least 100 Hz or so to not visibly flicker, so 100 steps
+//! takes a 10 kHz interrupt, and 1000 steps for finer fades takes 100 kHz. Each
+//! interrupt costs the entry and exit of the handler plus the work in it, which
+//! is a few hundred cycles with a critical section and a HAL pin, so at 48 MHz a
+//! 100 kHz interrupt would take up a large share of the CPU before the
+//! application does anything. The ISR also adds jitter: anything that delays
+//! the interrupt, like a critical section or a higher-priority interrupt,
+//! delays the edge. Hardware PWM has neither cost, so it's the better choice
+//! wherever a channel is available; software PWM is for when they run out, or
+//! for slow loads like LEDs and heaters that don't mind a little jitter.
+
+use embedded_hal::digital::v2::OutputPin;
+
+/// Largest duty in percent. Larger duties are clamped to it.
+pub const MAX_DUTY_PERCENT: u8 = 100;
+
+/// Returns the number of ticks out of each period of `steps` ticks that the
+/// output is high for at a duty of `percent`.
+///
+/// Rounds down, and duties over [`MAX_DUTY_PERCENT`] are clamped to it.
+pub fn duty_to_threshold(percent: u8, steps: u16) -> u16 {
+    let percent = percent.min(MAX_DUTY_PERCENT) as u32;
+
+    // steps * percent is at most 65_535 * 100, so this can't overflow.
+    (steps as u32 * percent / MAX_DUTY_PERCENT as u32) as u16
+}
+
+/// PWM on an output pin, driven by calling [`Self::tick`] at a fixed rate.
+///
+/// See the [module documentation](self) for how the tick rate, the number of
+/// steps, and the PWM frequency relate.
+pub struct SoftPwm<P> {
+    pin: P,
+    steps: u16,
+    counter: u16,
+    threshold: u16,
+    next_threshold: u16,
+    level: Option<bool>,
+}
+
+impl<P: OutputPin> SoftPwm<P> {
+    /// Creates a PWM output on `pin` with `steps` ticks per period, starting
+    /// at a duty of 0%.
+    ///
+    /// `steps` is the resolution of the duty: each step is `100 / steps`
+    /// percent. A `steps` of 0 is treated as 1, which can only be fully on or
+    /// fully off. The pin isn't written until the first tick.
+    pub fn new(pin: P, steps: u16) -> Self {
+        SoftPwm {
+            pin,
+            steps: steps.max(1),
+            counter: 0,
+            threshold: 0,
+            next_threshold: 0,
+            level: None,
+        }
+    }
+
+    /// Sets the duty in percent, clamped to 100.
+    ///
+    /// The new duty takes effect at the start of the next period, so the
+    /// period in progress isn't cut short or stretched.
+    pub fn set_duty(&mut self, percent: u8) {
+        self.next_threshold = duty_to_threshold(percent, self.steps);
+    }
+
+    /// Number of ticks per period.
+    pub fn steps(&self) -> u16 {
+        self.steps
+    }
+
+    /// Advances the output by one tick, driving the pin to the level for the
+    /// new position in the period.
+    ///
+    /// The pin is only written when its level changes, which keeps the
+    /// handler this is called from short for most ticks. The position advances
+    /// even if writing the pin fails, so the period keeps its length, and the
+    /// write is tried again on the next tick.
+    pub fn tick(&mut self) -> Result<(), P::Error> {
+        if self.counter == 0 {
+            self.threshold = self.next_threshold;
+        }
+
+        let high = self.counter < self.threshold;
+
+        self.counter += 1;
+        if self.counter == self.steps {
+            self.counter = 0;
+        }
+
+        if self.level != Some(high) {
+            if high {
+                self.pin.set_high()?;
+            } else {
+                self.pin.set_low()?;
+            }
+            self.level = Some(high);
+        }
+
+        Ok(())
+    }
+
+    /// Releases the pin.
+    pub fn free(self) -> P {
+        self.pin
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::mock::{is_high, Log, MockPin, Op};
+
+    /// A pin whose first write fails without being logged.
+    struct FlakyPin {
+        pin: MockPin,
+        failed: bool,
+    }
+
+    impl FlakyPin {
+        fn fail_once(&mut self) -> Result<(), ()> {
+            if self.failed {
+                Ok(())
+            } else {
+                self.failed = true;
+                Err(())
+            }
+        }
+    }
+
+    impl OutputPin for FlakyPin {
+        type Error = ();
+
+        fn set_low(&mut self) -> Result<(), Self::Error> {
+            self.fail_once()?;
+            self.pin.set_low().map_err(|never| match never {})
+        }
+
+        fn set_high(&mut self) -> Result<(), Self::Error> {
+            self.fail_once()?;
+            self.pin.set_high().map_err(|never| match never {})
+        }
+    }
+
+    /// Ticks through one period and returns the number of ticks the pin was
+    /// high for.
+    fn high_ticks(pwm: &mut SoftPwm<MockPin>, log: &Log) -> u16 {
+        let mut high = 0;
+        for _ in 0..pwm.steps() {
+            pwm.tick().unwrap();
+            if is_high(log, 0) {
+                high += 1;
+            }
+        }
+        high
+    }
+
+    #[test]
+    fn threshold_for_100_steps() {
+        assert_eq!(duty_to_threshold(0, 100), 0);
+        assert_eq!(duty_to_threshold(1, 100), 1);
+        assert_eq!(duty_to_threshold(50, 100), 50);
+        assert_eq!(duty_to_threshold(100, 100), 100);
+    }
+
+    #[test]
+    fn threshold_rounds_down() {
+        assert_eq!(duty_to_threshold(33, 10), 3);
+        assert_eq!(duty_to_threshold(99, 10), 9);
+        assert_eq!(duty_to_threshold(u8::MAX, u16::MAX), u16::MAX);
+    }
+
+    #[test]
+    fn duties_past_100_are_clamped() {
+        assert_eq!(duty_to_threshold(101, 100), 100);
+        assert_eq!(duty_to_threshold(u8::MAX, 100), 100);
+    }
+
+    #[test]
+    fn high_for_the_threshold_at_the_start_of_each_period() {
+        let log = Log::default();
+        let mut pwm = SoftPwm::new(MockPin::new(0, &log), 10);
+        pwm.set_duty(30);
+
+        let levels: Vec<bool> = (0..20)
+            .map(|_| {
+                pwm.tick().unwrap();
+                is_high(&log, 0)
+            })
+            .collect();
+
+        let period = [
+            true, true, true, false, false, false, false, false, false, false,
+        ];
+        assert_eq!(levels[..10], period);
+        assert_eq!(levels[10..], period);
+    }
+
+    #[test]
+    fn zero_and_full_duty() {
+        let log = Log::default();
+        let mut pwm = SoftPwm::new(MockPin::new(0, &log), 100);
+        assert_eq!(high_ticks(&mut pwm, &log), 0);
+
+        pwm.set_duty(100);
+        assert_eq!(high_ticks(&mut pwm, &log), 100);
+    }
+
+    #[test]
+    fn duty_change_waits_for_the_next_period() {
+        let log = Log::default();
+        let mut pwm = SoftPwm::new(MockPin::new(0, &log), 10);
+        pwm.set_duty(80);
+        for _ in 0..5 {
+            pwm.tick().unwrap();
+        }
+
+        // Lowering the duty partway through the period doesn't end the pulse
+        // early.
+        pwm.set_duty(20);
+        for _ in 0..3 {
+            pwm.tick().unwrap();
+            assert!(is_high(&log, 0));
+        }
+        pwm.tick().unwrap();
+        pwm.tick().unwrap();
+        assert!(!is_high(&log, 0));
+
+        assert_eq!(high_ticks(&mut pwm, &log), 2);
+    }
+
+    #[test]
+    fn pin_is_only_written_on_edges() {
+        let log = Log::default();
+        let mut pwm = SoftPwm::new(MockPin::new(0, &log), 10);
+        pwm.set_duty(50);
+        for _ in 0..30 {
+            pwm.tick().unwrap();
+        }
+        // A rising and a falling edge per period.
+        assert_eq!(log.borrow().len(), 6);
+    }
+
+    #[test]
+    fn zero_steps_is_one_step() {
+        let log = Log::default();
+        let mut pwm = SoftPwm::new(MockPin::new(0, &log), 0);
+        assert_eq!(pwm.steps(), 1);
+
+        pwm.set_duty(100);
+        pwm.tick().unwrap();
+        assert!(is_high(&log, 0));
+        pwm.set_duty(99);
+        pwm.tick().unwrap();
+        assert!(!is_high(&log, 0));
+    }
+
+    #[test]
+    fn failed_write_still_advances_and_is_retried() {
+        let log = Log::default();
+        let pin = FlakyPin {
+            pin: MockPin::new(0, &log),
+            failed: false,
+        };
+        let mut pwm = SoftPwm::new(pin, 10);
+        pwm.set_duty(30);
+
+        assert_eq!(pwm.tick(), Err(()));
+        assert!(log.borrow().is_empty());
+
+        // The high the first tick failed to write goes out on the second, and
+        // the pulse still ends and restarts on time.
+        pwm.tick().unwrap();
+        assert_eq!(*log.borrow(), [Op::High(0)]);
+        for _ in 2..10 {
+            pwm.tick().unwrap();
+        }
+        assert_eq!(*log.borrow(), [Op::High(0), Op::Low(0)]);
+        pwm.tick().unwrap();
+        assert_eq!(*log.borrow(), [Op::High(0), Op::Low(0), Op::High(0)]);
+    }
+}
@@ -0,0 +1,290 @@
+//! Driving a bipolar stepper motor from four GPIO outputs.
+//!
+//! A bipolar stepper has two coils, A and B, and turns one step each time the
+//! current through them changes to the next pattern of an excitation
+//! sequence. The coils can't be driven from GPIO pins directly, so the four
+//! outputs here are meant to go to the four inputs of a dual H-bridge driver
+//! such as a DRV8833 or an L298N, one pair of inputs per coil:
+//!
+//! | Output | Drives     |
+//! |--------|------------|
+//! | `p0`   | Coil A, +  |
+//! | `p1`   | Coil A, -  |
+//! | `p2`   | Coil B, +  |
+//! | `p3`   | Coil B, -  |
+//!
+//! Setting `p0` high and `p1` low drives current through coil A one way,
+//! setting `p1` high and `p0` low drives it the other way, and setting both
+//! low leaves the coil off. Coil B works the same with `p2` and `p3`.
+//!
+//! Two sequences are supported, selected with [`StepMode`]:
+//!
+//! - [`StepMode::FullStep`]: Both coils are energized on every step, for 4
+//!   steps per electrical cycle at full torque.
+//! - [`StepMode::HalfStep`]: Alternates between both coils and only one coil
+//!   being energized, for 8 steps per cycle. This doubles the resolution to
+//!   half a step, at the cost of less torque on the one-coil steps.
+//!
+//! Stepping forward walks through the sequence in order and stepping backward
+//! walks through it in reverse, which reverses the direction of rotation. How
+//! fast the motor turns is set by how often [`Stepper::step_forward`] or
+//! [`Stepper::step_backward`] is called. Stepping faster than the motor can
+//! follow makes it stall and skip steps rather than turning faster.
+//!
+//! ```ignore
+//! let mut stepper = Stepper::new(p0, p1, p2, p3, StepMode::FullStep);
+//! stepper.energize().ok();
+//! for _ in 0..200 {
+//!     stepper.step_forward().ok();
+//!     delay.delay_ms(5_u32);
+//! }
+//! ```
+
+use embedded_hal::digital::v2::OutputPin;
+
+/// Full-step sequence: both coils energized, the current through one of them
+/// reversing on each step.
+const FULL_STEP: [[bool; 4]; 4] = [
+    [true, false, true, false],
+    [false, true, true, false],
+    [false, true, false, true],
+    [true, false, false, true],
+];
+
+/// Half-step sequence: the full steps with a single-coil step in between each
+/// of them.
+const HALF_STEP: [[bool; 4]; 8] = [
+    [true, false, false, false],
+    [true, false, true, false],
+    [false, false, true, false],
+    [false, true, true, false],
+    [false, true, false, false],
+    [false, true, false, true],
+    [false, false, false, true],
+    [true, false, false, true],
+];
+
+/// Excitation sequence used to step the motor.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StepMode {
+    /// 4 steps per cycle, with both coils energized on every step.
+    FullStep,
+    /// 8 steps per cycle, alternating between one and both coils energized.
+    HalfStep,
+}
+
+impl StepMode {
+    fn sequence(self) -> &'static [[bool; 4]] {
+        match self {
+            StepMode::FullStep => &FULL_STEP,
+            StepMode::HalfStep => &HALF_STEP,
+        }
+    }
+}
+
+/// A bipolar stepper motor driven through four output pins.
+///
+/// See the [module documentation](self) for how the pins map to the coils.
+pub struct Stepper<P0, P1, P2, P3> {
+    p0: P0,
+    p1: P1,
+    p2: P2,
+    p3: P3,
+    mode: StepMode,
+    phase: usize,
+}
+
+impl<P0, P1, P2, P3, E> Stepper<P0, P1, P2, P3>
+where
+    P0: OutputPin<Error = E>,
+    P1: OutputPin<Error = E>,
+    P2: OutputPin<Error = E>,
+    P3: OutputPin<Error = E>,
+{
+    /// Creates a stepper from its four pins, starting at the first phase of
+    /// the sequence for `mode`.
+    ///
+    /// The pins aren't written until the first call to [`Self::energize`] or
+    /// one of the step methods.
+    pub fn new(p0: P0, p1: P1, p2: P2, p3: P3, mode: StepMode) -> Self {
+        Stepper {
+            p0,
+            p1,
+            p2,
+            p3,
+            mode,
+            phase: 0,
+        }
+    }
+
+    /// Index of the current phase in the excitation sequence.
+    pub fn phase(&self) -> usize {
+        self.phase
+    }
+
+    /// Drives the outputs for the current phase without stepping.
+    ///
+    /// This locks the rotor in place at the current phase, so it's worth
+    /// calling once before stepping: the first step then moves the motor by
+    /// one step from a known position.
+    pub fn energize(&mut self) -> Result<(), E> {
+        self.write(self.mode.sequence()[self.phase])
+    }
+
+    /// Turns off both coils.
+    ///
+    /// The motor stops drawing current, but also stops holding its position.
+    /// The phase is kept, so [`Self::energize`] picks up where it left off,
+    /// as long as the shaft wasn't turned in the meantime.
+    pub fn release(&mut self) -> Result<(), E> {
+        self.write([false; 4])
+    }
+
+    /// Advances one step forward through the sequence.
+    pub fn step_forward(&mut self) -> Result<(), E> {
+        let len = self.mode.sequence().len();
+        self.phase = (self.phase + 1) % len;
+        self.energize()
+    }
+
+    /// Moves one step backward through the sequence.
+    pub fn step_backward(&mut self) -> Result<(), E> {
+        let len = self.mode.sequence().len();
+        self.phase = (self.phase + len - 1) % len;
+        self.energize()
+    }
+
+    /// Releases the pins.
+    pub fn free(self) -> (P0, P1, P2, P3) {
+        (self.p0, self.p1, self.p2, self.p3)
+    }
+
+    fn write(&mut self, outputs: [bool; 4]) -> Result<(), E> {
+        set(&mut self.p0, outputs[0])?;
+        set(&mut self.p1, outputs[1])?;
+        set(&mut self.p2, outputs[2])?;
+        set(&mut self.p3, outputs[3])
+    }
+}
+
+fn set<P: OutputPin>(pin: &mut P, high: bool) -> Result<(), P::Error> {
+    if high {
+        pin.set_high()
+    } else {
+        pin.set_low()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::mock::{is_high, Log, MockPin};
+
+    type MockStepper = Stepper<MockPin, MockPin, MockPin, MockPin>;
+
+    fn stepper(mode: StepMode) -> (MockStepper, Log) {
+        let log = Log::default();
+        let [p0, p1, p2, p3] = [0, 1, 2, 3].map(|id| MockPin::new(id, &log));
+        (Stepper::new(p0, p1, p2, p3, mode), log)
+    }
+
+    fn outputs(log: &Log) -> [bool; 4] {
+        [0, 1, 2, 3].map(|id| is_high(log, id))
+    }
+
+    #[test]
+    fn new_does_not_drive_the_pins() {
+        let (stepper, log) = stepper(StepMode::FullStep);
+        assert_eq!(stepper.phase(), 0);
+        assert_eq!(outputs(&log), [false; 4]);
+    }
+
+    #[test]
+    fn energize_drives_the_current_phase() {
+        let (mut stepper, log) = stepper(StepMode::FullStep);
+        stepper.energize().unwrap();
+        assert_eq!(outputs(&log), FULL_STEP[0]);
+    }
+
+    #[test]
+    fn full_step_forward_cycles_through_the_sequence() {
+        let (mut stepper, log) = stepper(StepMode::FullStep);
+        for expected in FULL_STEP.iter().cycle().skip(1).take(8) {
+            stepper.step_forward().unwrap();
+            assert_eq!(&outputs(&log), expected);
+        }
+        assert_eq!(stepper.phase(), 0);
+    }
+
+    #[test]
+    fn full_step_backward_cycles_through_the_sequence_in_reverse() {
+        let (mut stepper, log) = stepper(StepMode::FullStep);
+        for expected in FULL_STEP.iter().rev().cycle().take(8) {
+            stepper.step_backward().unwrap();
+            assert_eq!(&outputs(&log), expected);
+        }
+        assert_eq!(stepper.phase(), 0);
+    }
+
+    #[test]
+    fn full_steps_always_energize_both_coils() {
+        for outputs in FULL_STEP.iter() {
+            assert!(outputs[0] != outputs[1]);
+            assert!(outputs[2] != outputs[3]);
+        }
+    }
+
+    #[test]
+    fn half_step_forward_cycles_through_the_sequence() {
+        let (mut stepper, log) = stepper(StepMode::HalfStep);
+        for expected in HALF_STEP.iter().cycle().skip(1).take(16) {
+            stepper.step_forward().unwrap();
+            assert_eq!(&outputs(&log), expected);
+        }
+        assert_eq!(stepper.phase(), 0);
+    }
+
+    #[test]
+    fn half_step_backward_cycles_through_the_sequence_in_reverse() {
+        let (mut stepper, log) = stepper(StepMode::HalfStep);
+        for expected in HALF_STEP.iter().rev().cycle().take(16) {
+            stepper.step_backward().unwrap();
+            assert_eq!(&outputs(&log), expected);
+        }
+        assert_eq!(stepper.phase(), 0);
+    }
+
+    #[test]
+    fn half_steps_include_the_full_steps() {
+        for (i, outputs) in FULL_STEP.iter().enumerate() {
+            assert_eq!(&HALF_STEP[2 * i + 1], outputs);
+        }
+    }
+
+    #[test]
+    fn forward_then_backward_returns_to_the_same_phase() {
+        let (mut stepper, log) = stepper(StepMode::HalfStep);
+        stepper.energize().unwrap();
+        let start = outputs(&log);
+        for _ in 0..3 {
+            stepper.step_forward().unwrap();
+        }
+        for _ in 0..3 {
+            stepper.step_backward().unwrap();
+        }
+        assert_eq!(stepper.phase(), 0);
+        assert_eq!(outputs(&log), start);
+    }
+
+    #[test]
+    fn release_turns_off_the_coils_and_keeps_the_phase() {
+        let (mut stepper, log) = stepper(StepMode::FullStep);
+        stepper.step_forward().unwrap();
+        stepper.release().unwrap();
+        assert_eq!(outputs(&log), [false; 4]);
+        assert_eq!(stepper.phase(), 1);
+        stepper.energize().unwrap();
+        assert_eq!(outputs(&log), FULL_STEP[1]);
+    }
+}
@@ -0,0 +1,195 @@
+//! Feeding a watchdog only when every task has shown it's still running.
+//!
+//! The usual way to use a watchdog is to feed it once per pass of the main
+//! loop, which catches the whole loop hanging, but nothing less. With several
+//! tasks sharing the loop, one task can stop making progress, e.g. stuck
+//! waiting for a flag that never gets set or for a reply that never comes,
+//! while the loop itself and the other tasks carry on around it, still
+//! feeding the watchdog every pass. The firmware is broken, but the watchdog
+//! never finds out.
+//!
+//! A [`TaskWatchdog`] closes that gap. Each task checks in with
+//! [`TaskWatchdog::check_in`] whenever it gets something done, and the loop
+//! asks [`TaskWatchdog::should_feed`] before feeding the watchdog, which only
+//! says yes once every task has checked in since the last feed. If any one
+//! task stops checking in, the watchdog stops being fed and resets the chip.
+//!
+//! ```ignore
+//! const LED_TASK: u32 = 0;
+//! const HEARTBEAT_TASK: u32 = 1;
+//!
+//! let mut task_watchdog = TaskWatchdog::new(2);
+//! loop {
+//!     if led_task.poll() {
+//!         task_watchdog.check_in(LED_TASK);
+//!     }
+//!     if heartbeat_task.poll() {
+//!         task_watchdog.check_in(HEARTBEAT_TASK);
+//!     }
+//!
+//!     if task_watchdog.should_feed() {
+//!         iwdg.feed();
+//!     }
+//! }
+//! ```
+//!
+//! # Check-in bitmask
+//!
+//! Each task is given a number from 0 to 31 and checks in by setting its bit in
+//! a bitmask. Once every bit of the tasks being watched is set, the mask is
+//! cleared again as the watchdog is fed, so each task has to check in again
+//! before the next feed. The watchdog timeout then has to be longer than the
+//! longest time any task goes between check-ins, with some margin, or it
+//! resets the chip with all of the tasks working.
+//!
+//! A check-in for a task number that isn't being watched has no effect, so it
+//! can't stand in for a task that is, and a mistake in the numbering shows up
+//! as a reset rather than as a task going unwatched.
+
+/// Bitmask of the tasks that have checked in since the watchdog was last
+/// fed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TaskWatchdog {
+    /// A bit set for each of the tasks being watched.
+    expected: u32,
+    /// A bit set for each of the tasks that have checked in.
+    checked_in: u32,
+}
+
+impl TaskWatchdog {
+    /// Creates a `TaskWatchdog` watching tasks 0 to `task_count - 1`, none of
+    /// which have checked in.
+    ///
+    /// A `task_count` over 32 is the same as 32. With no tasks, the watchdog
+    /// is always fed, as it would be without a `TaskWatchdog`.
+    pub const fn new(task_count: u32) -> Self {
+        let expected = if task_count >= u32::BITS {
+            u32::MAX
+        } else {
+            (1 << task_count) - 1
+        };
+        TaskWatchdog {
+            expected,
+            checked_in: 0,
+        }
+    }
+
+    /// Records that `task` is still making progress.
+    pub fn check_in(&mut self, task: u32) {
+        if task < u32::BITS {
+            self.checked_in |= (1 << task) & self.expected;
+        }
+    }
+
+    /// Returns a bitmask of the tasks that haven't checked in since the last
+    /// feed, e.g. to report which one stalled.
+    pub fn pending(&self) -> u32 {
+        self.expected & !self.checked_in
+    }
+
+    /// Returns whether every task has checked in since the last feed, and if
+    /// so, starts over with none of them checked in, since the watchdog is
+    /// about to be fed.
+    pub fn should_feed(&mut self) -> bool {
+        if self.pending() != 0 {
+            return false;
+        }
+        self.checked_in = 0;
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn not_fed_until_every_task_checks_in() {
+        let mut task_watchdog = TaskWatchdog::new(3);
+        assert!(!task_watchdog.should_feed());
+
+        task_watchdog.check_in(0);
+        task_watchdog.check_in(2);
+        assert!(!task_watchdog.should_feed());
+        assert_eq!(task_watchdog.pending(), 0b010);
+
+        task_watchdog.check_in(1);
+        assert!(task_watchdog.should_feed());
+    }
+
+    #[test]
+    fn feeding_starts_over() {
+        let mut task_watchdog = TaskWatchdog::new(2);
+        task_watchdog.check_in(0);
+        task_watchdog.check_in(1);
+        assert!(task_watchdog.should_feed());
+
+        assert_eq!(task_watchdog.pending(), 0b11);
+        assert!(!task_watchdog.should_feed());
+    }
+
+    #[test]
+    fn not_feeding_keeps_check_ins() {
+        let mut task_watchdog = TaskWatchdog::new(2);
+        task_watchdog.check_in(0);
+        assert!(!task_watchdog.should_feed());
+
+        task_watchdog.check_in(1);
+        assert!(task_watchdog.should_feed());
+    }
+
+    #[test]
+    fn stalled_task_stops_feeding() {
+        // Task 1 checks in once and then stalls, while task 0 keeps checking
+        // in on every pass, as it would with a feed on every pass of the loop.
+        let mut task_watchdog = TaskWatchdog::new(2);
+        task_watchdog.check_in(0);
+        task_watchdog.check_in(1);
+        assert!(task_watchdog.should_feed());
+
+        for _ in 0..100 {
+            task_watchdog.check_in(0);
+            assert!(!task_watchdog.should_feed());
+            assert_eq!(task_watchdog.pending(), 0b10);
+        }
+    }
+
+    #[test]
+    fn repeated_check_ins_count_once() {
+        let mut task_watchdog = TaskWatchdog::new(2);
+        task_watchdog.check_in(0);
+        task_watchdog.check_in(0);
+        assert!(!task_watchdog.should_feed());
+    }
+
+    #[test]
+    fn unwatched_task_is_ignored() {
+        let mut task_watchdog = TaskWatchdog::new(2);
+        task_watchdog.check_in(0);
+        task_watchdog.check_in(2);
+        task_watchdog.check_in(31);
+        task_watchdog.check_in(32);
+        task_watchdog.check_in(u32::MAX);
+        assert!(!task_watchdog.should_feed());
+        assert_eq!(task_watchdog.pending(), 0b10);
+    }
+
+    #[test]
+    fn thirty_two_tasks() {
+        let mut task_watchdog = TaskWatchdog::new(40);
+        assert_eq!(task_watchdog.pending(), u32::MAX);
+        for task in 0..31 {
+            task_watchdog.check_in(task);
+        }
+        assert!(!task_watchdog.should_feed());
+        task_watchdog.check_in(31);
+        assert!(task_watchdog.should_feed());
+    }
+
+    #[test]
+    fn no_tasks_always_feeds() {
+        let mut task_watchdog = TaskWatchdog::new(0);
+        assert!(task_watchdog.should_feed());
+        assert!(task_watchdog.should_feed());
+    }
+}
@@ -0,0 +1,338 @@
+//! Busy-wait delays timed with a free-running hardware timer.
+//!
+//! The HAL `Delay` types only go down to whole milliseconds on SysTick, and
+//! take SysTick to do it. [`TimerDelay`] times delays from the counter of one
+//! of the general-purpose timers instead, set up to count up freely at a
+//! known rate, usually 1 MHz, so one tick is 1 us. A delay reads the counter,
+//! then spins until it has gone up by the right number of ticks.
+//!
+//! ```ignore
+//! // TIM2 counting at 1 MHz from a 48 MHz timer clock, as in the tim-delay
+//! // example.
+//! let mut delay = TimerDelay::new(Tim2Counter(tim2), tim_delay::TICK_HZ);
+//! pin.set_high().ok();
+//! delay.delay_us(2);
+//! pin.set_low().ok();
+//! ```
+//!
+//! Besides its own methods, it implements the `embedded-hal` `DelayUs` and
+//! `DelayMs` traits, and [`BlockingDelay`], so it can stand in for the other
+//! delays of [`crate::delay`], as in the delay-select example.
+//!
+//! The timer is any that implements [`FreeRunningCounter`], which is only a
+//! read of its counter register, so the setup is left to the application:
+//! the prescaler for the tick rate, the auto-reload register at its largest,
+//! and the counter enabled. Doing that with the PAC is a few lines, and this
+//! module doesn't have to know about any particular HAL.
+//!
+//! # Prescaler
+//!
+//! The counter goes up once every PSC + 1 cycles of the timer clock, so for
+//! a tick rate of `tick_hz`:
+//!
+//! ```text
+//! PSC = timer clock / tick_hz - 1
+//! ```
+//!
+//! [`prescaler`] works it out, and checks that it comes out whole and fits
+//! in the 16-bit register. The timer clock isn't always the APB clock: on the
+//! STM32F3 and F7, when the APB prescaler isn't 1, the timers on that bus run
+//! at twice the bus clock. With sysclk at 48 MHz, stm32f3xx-hal sets PCLK1
+//! to 24 MHz, so the APB1 timers run at 48 MHz, and a 1 MHz tick takes a PSC of
+//! 47. The HAL's `BusTimerClock::timer_clock()` gives the right figure.
+//!
+//! A new PSC only takes effect at the next update event, so write UG in EGR
+//! after setting it, or the first overflow runs at the old rate.
+//!
+//! # Wraparound
+//!
+//! The counter counts up to [`FreeRunningCounter::MAX`] and wraps to 0, which
+//! for a 16-bit timer at 1 MHz is every 65.5 ms, and for TIM2, which is 32
+//! bits, every 71.6 minutes. The ticks elapsed are worked out with wrapping
+//! subtraction masked to the counter's width, which is right across a
+//! wraparound as long as the loop reads the counter at least once per wrap.
+//! An interrupt taking longer than that in the middle of a delay would lose a
+//! whole wrap, so long delays are split into pieces of half the counter's
+//! range, and each piece is timed from where the last should have ended
+//! rather than where it did, so the pieces don't add up any error.
+//!
+//! # Resolution
+//!
+//! A delay of `n` ticks starts at a random point within a tick, so to be sure
+//! of at least `n` whole ticks it waits for the counter to go up `n + 1`
+//! times, and can run up to one tick over. At 1 MHz, `delay_us(2)` takes 2 to
+//! 3 us. Running the counter faster narrows that down: at the 48 MHz of the
+//! timer clock itself, with a PSC of 0, [`TimerDelay::delay_ticks`] has a
+//! resolution of 21 ns, at the cost of a 16-bit counter wrapping every
+//! 1.4 ms. Like any busy wait, an interrupt during a delay can also stretch it
+//! by however long the handler takes.
+
+use embedded_hal::blocking::delay::{DelayMs, DelayUs};
+
+use crate::delay::BlockingDelay;
+
+/// The tick rate that makes one tick 1 us.
+pub const TICK_HZ: u32 = 1_000_000;
+
+/// A hardware counter that counts up freely from 0 to [`Self::MAX`] and wraps
+/// back to 0.
+pub trait FreeRunningCounter {
+    /// The largest value the counter reaches before wrapping, one less than a
+    /// power of two, e.g. `0xFFFF` for a 16-bit timer with its auto-reload
+    /// register at its largest.
+    const MAX: u32;
+
+    /// Returns the value of the counter.
+    fn count(&self) -> u32;
+}
+
+/// Returns the PSC value that makes a timer clocked at `timer_clock_hz`
+/// count at `tick_hz`.
+///
+/// Returns `None` if `tick_hz` doesn't divide `timer_clock_hz` exactly, or
+/// needs a prescaler too big for the 16-bit register.
+pub fn prescaler(timer_clock_hz: u32, tick_hz: u32) -> Option<u16> {
+    if tick_hz == 0 || !timer_clock_hz.is_multiple_of(tick_hz) {
+        return None;
+    }
+    let divider = timer_clock_hz / tick_hz;
+    if divider == 0 || divider > 1 << 16 {
+        return None;
+    }
+    Some((divider - 1) as u16)
+}
+
+/// Delays timed with a free-running counter.
+///
+/// See the [module documentation](self) for how to set up the timer, and how
+/// accurate the delays are.
+pub struct TimerDelay<C> {
+    counter: C,
+    ticks_per_us: u32,
+}
+
+impl<C: FreeRunningCounter> TimerDelay<C> {
+    /// Creates a delay from a counter counting at `tick_hz`.
+    ///
+    /// `tick_hz` has to be a whole number of MHz for [`Self::delay_us`] to
+    /// be right. Anything under 1 MHz is treated as 1 MHz.
+    pub fn new(counter: C, tick_hz: u32) -> Self {
+        TimerDelay {
+            counter,
+            ticks_per_us: (tick_hz / TICK_HZ).max(1),
+        }
+    }
+
+    /// Returns the number of ticks since the counter read `start`.
+    fn elapsed(&self, start: u32) -> u32 {
+        self.counter.count().wrapping_sub(start) & C::MAX
+    }
+
+    /// Spins for at least `ticks` ticks of the counter.
+    pub fn delay_ticks(&mut self, ticks: u32) {
+        self.delay_long_ticks(ticks.into());
+    }
+
+    /// Spins for at least `ticks` ticks of the counter, split into pieces of
+    /// half the counter's range, with the extra tick only at the end.
+    fn delay_long_ticks(&mut self, ticks: u64) {
+        if ticks == 0 {
+            return;
+        }
+
+        let max_piece = C::MAX / 2;
+        let mut start = self.counter.count();
+        let mut remaining = ticks;
+        loop {
+            let piece = remaining.min(max_piece.into()) as u32;
+            remaining -= u64::from(piece);
+
+            if remaining == 0 {
+                // Wait for one tick more than asked, since the start was
+                // partway through a tick.
+                while self.elapsed(start) <= piece {}
+                return;
+            }
+
+            while self.elapsed(start) < piece {}
+            start = start.wrapping_add(piece) & C::MAX;
+        }
+    }
+
+    /// Spins for at least `us` microseconds.
+    pub fn delay_us(&mut self, us: u32) {
+        self.delay_long_ticks(u64::from(us) * u64::from(self.ticks_per_us));
+    }
+
+    /// Returns the counter.
+    pub fn free(self) -> C {
+        self.counter
+    }
+}
+
+impl<C: FreeRunningCounter> DelayUs<u32> for TimerDelay<C> {
+    fn delay_us(&mut self, us: u32) {
+        TimerDelay::delay_us(self, us);
+    }
+}
+
+impl<C: FreeRunningCounter> DelayUs<u16> for TimerDelay<C> {
+    fn delay_us(&mut self, us: u16) {
+        TimerDelay::delay_us(self, us.into());
+    }
+}
+
+impl<C: FreeRunningCounter> DelayUs<u8> for TimerDelay<C> {
+    fn delay_us(&mut self, us: u8) {
+        TimerDelay::delay_us(self, us.into());
+    }
+}
+
+impl<C: FreeRunningCounter> DelayMs<u32> for TimerDelay<C> {
+    fn delay_ms(&mut self, ms: u32) {
+        self.delay_long_ticks(u64::from(ms) * 1_000 * u64::from(self.ticks_per_us));
+    }
+}
+
+impl<C: FreeRunningCounter> DelayMs<u16> for TimerDelay<C> {
+    fn delay_ms(&mut self, ms: u16) {
+        DelayMs::<u32>::delay_ms(self, ms.into());
+    }
+}
+
+impl<C: FreeRunningCounter> DelayMs<u8> for TimerDelay<C> {
+    fn delay_ms(&mut self, ms: u8) {
+        DelayMs::<u32>::delay_ms(self, ms.into());
+    }
+}
+
+impl<C: FreeRunningCounter> BlockingDelay for TimerDelay<C> {
+    fn delay_ms(&mut self, ms: u32) {
+        DelayMs::<u32>::delay_ms(self, ms);
+    }
+
+    fn delay_us(&mut self, us: u32) {
+        TimerDelay::delay_us(self, us);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::cell::Cell;
+
+    /// A counter of `MAX + 1` values that goes up by `step` every time it's
+    /// read, keeping a total of the ticks that have passed.
+    struct MockCounter<const MAX: u32> {
+        now: Cell<u32>,
+        step: u32,
+        total: Cell<u64>,
+    }
+
+    impl<const MAX: u32> MockCounter<MAX> {
+        fn starting_at(now: u32, step: u32) -> Self {
+            MockCounter {
+                now: Cell::new(now),
+                step,
+                total: Cell::new(0),
+            }
+        }
+    }
+
+    impl<const MAX: u32> FreeRunningCounter for MockCounter<MAX> {
+        const MAX: u32 = MAX;
+
+        fn count(&self) -> u32 {
+            let now = self.now.get();
+            self.now.set(now.wrapping_add(self.step) & MAX);
+            self.total.set(self.total.get() + self.step as u64);
+            now
+        }
+    }
+
+    type Counter16 = MockCounter<0xFFFF>;
+    type Counter32 = MockCounter<{ u32::MAX }>;
+
+    #[test]
+    fn prescaler_for_a_1_mhz_tick() {
+        assert_eq!(prescaler(48_000_000, TICK_HZ), Some(47));
+        assert_eq!(prescaler(216_000_000, TICK_HZ), Some(215));
+        assert_eq!(prescaler(1_000_000, TICK_HZ), Some(0));
+    }
+
+    #[test]
+    fn prescaler_must_be_whole_and_fit() {
+        assert_eq!(prescaler(48_000_000, 7_000_000), None);
+        assert_eq!(prescaler(48_000_000, 0), None);
+        assert_eq!(prescaler(1_000_000, 2_000_000), None);
+        // 72 MHz to 1 kHz is a divider of 72_000, past 65_536.
+        assert_eq!(prescaler(72_000_000, 1_000), None);
+        assert_eq!(prescaler(65_536_000, 1_000), Some(0xFFFF));
+    }
+
+    #[test]
+    fn waits_one_tick_over() {
+        let mut delay = TimerDelay::new(Counter32::starting_at(100, 1), TICK_HZ);
+        delay.delay_us(2);
+        let counter = delay.free();
+        // Past 102, so 3 ticks, read once more to find that.
+        assert_eq!(counter.now.get(), 104);
+    }
+
+    #[test]
+    fn zero_returns_straight_away() {
+        let mut delay = TimerDelay::new(Counter16::starting_at(0, 1), TICK_HZ);
+        delay.delay_ticks(0);
+        assert_eq!(delay.free().total.get(), 0);
+    }
+
+    #[test]
+    fn handles_wraparound_of_a_16_bit_counter() {
+        let mut delay = TimerDelay::new(Counter16::starting_at(0xFFF0, 1), TICK_HZ);
+        delay.delay_us(100);
+        let counter = delay.free();
+        assert_eq!(counter.now.get(), (0xFFF0 + 102) & 0xFFFF);
+    }
+
+    #[test]
+    fn long_delays_span_several_wraps_without_drift() {
+        // 1 s on a 16-bit counter wraps it 15 times.
+        let mut delay = TimerDelay::new(Counter16::starting_at(1234, 1), TICK_HZ);
+        delay.delay_us(1_000_000);
+        let total = delay.free().total.get();
+        assert!((1_000_001..=1_000_002).contains(&total), "{}", total);
+    }
+
+    #[test]
+    fn coarse_reads_still_wait_long_enough() {
+        // The loop only gets to read the counter every 7 ticks.
+        let mut delay = TimerDelay::new(Counter16::starting_at(0, 7), TICK_HZ);
+        delay.delay_us(200_000);
+        let total = delay.free().total.get();
+        assert!(total > 200_000, "{}", total);
+        assert!(total <= 200_000 + 7 * 8, "{}", total);
+    }
+
+    #[test]
+    fn faster_ticks_scale_microseconds() {
+        let mut delay = TimerDelay::new(Counter32::starting_at(0, 1), 48_000_000);
+        delay.delay_us(2);
+        assert_eq!(delay.free().total.get(), 98);
+    }
+
+    #[test]
+    fn delay_ms_is_a_thousand_microseconds_each() {
+        let mut delay = TimerDelay::new(Counter32::starting_at(0, 1), TICK_HZ);
+        DelayMs::<u16>::delay_ms(&mut delay, 3);
+        // One delay of 3_000 ticks, which runs a tick over only once.
+        assert_eq!(delay.free().total.get(), 3_002);
+    }
+
+    #[test]
+    fn blocking_delay_matches_the_embedded_hal_delays() {
+        let mut delay = TimerDelay::new(Counter32::starting_at(0, 1), TICK_HZ);
+        BlockingDelay::delay_us(&mut delay, 2);
+        BlockingDelay::delay_ms(&mut delay, 3);
+        assert_eq!(delay.free().total.get(), 4 + 3_002);
+    }
+}
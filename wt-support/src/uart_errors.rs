@@ -0,0 +1,335 @@
+//! Checking, clearing, and counting UART receive errors.
+//!
+//! A UART flags four kinds of errors on the bytes it receives: overrun,
+//! noise, framing, and parity. Code that only ever looks at whether a byte
+//! has arrived never sees them, and one of them, overrun, can stop the
+//! receiver dead if it isn't cleared. [`receive`] checks the flags on every
+//! poll, clears the ones it found in the right order, counts each kind in
+//! [`ErrorCounts`], and only returns bytes that arrived intact.
+//!
+//! ```ignore
+//! let mut counts = ErrorCounts::default();
+//! loop {
+//!     if let Some(byte) = uart_errors::receive(&mut uart, &mut counts) {
+//!         // Handle the byte.
+//!     }
+//! }
+//! ```
+//!
+//! The registers are reached through [`RxRegisters`], so the checks can be
+//! tested on the host, and an implementation is three register accesses, as
+//! in the uart-errors example.
+//!
+//! # The errors
+//!
+//! - Overrun (ORE): A byte finished arriving while the last one was still
+//!   waiting in the data register, so the new one was lost. The byte in the
+//!   data register is still good.
+//! - Noise (NF): The samples taken of one of the bits of the byte didn't all
+//!   agree. The UART went with the majority, so the byte is most likely right,
+//!   but the line is noisy.
+//! - Framing (FE): The stop bit was low, from a baud rate mismatch, a break,
+//!   or noise, so the byte is probably garbage.
+//! - Parity (PE): With parity enabled, the parity bit didn't match the byte,
+//!   so one of its bits is wrong.
+//!
+//! [`receive`] hands on the byte with an overrun or noise, and drops it with
+//! a framing or parity error.
+//!
+//! # Clearing the flags
+//!
+//! The flags stay set until they're cleared, and how depends on the UART. On
+//! the STM32F3 and F7, whose USARTs have an ICR (interrupt flag clear
+//! register), per RM0316:
+//!
+//! 1. Read ISR once, and keep what it said.
+//! 2. If RXNE is set, read RDR. That clears RXNE, but none of the error
+//!    flags.
+//! 3. Write a 1 to the bit in ICR of each error flag that was set in step 1,
+//!    ORECF for ORE, NCF for NF, FECF for FE, and PECF for PE, and 0 to the
+//!    rest.
+//!
+//! Only the flags read in step 1 are cleared, rather than all of them, so a
+//! flag that's set in between is left for the next poll to count, rather than
+//! cleared without ever being seen. ICR is write-only, and writing 0 to a bit
+//! does nothing, so it's written, not modified.
+//!
+//! The STM32F1 and F4 have no ICR. There, the error flags in SR are cleared
+//! by reading SR and then DR, in that order, which steps 1 and 2 already do,
+//! so an implementation of [`RxRegisters::clear_errors`] for them does
+//! nothing.
+//!
+//! # Why an overrun stalls reception
+//!
+//! On the F3 and F7, while ORE is set, the receiver doesn't move any more
+//! bytes into RDR, even once it has been read. RXNE never sets again, so code
+//! that waits for RXNE and ignores the error flags waits forever, and to the
+//! rest of the program, the other end has just stopped sending. With the
+//! RXNE interrupt enabled, it's worse: ORE raises the same interrupt, which a
+//! handler that only reads RDR never clears, so the handler runs over and
+//! over and the main loop stops as well. Clearing ORECF is the only way out,
+//! and all it takes to cause one is the program being busy for longer than a
+//! byte takes to arrive, 87 us at 115200 baud.
+
+/// The receive error flags of a UART.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ErrorFlags {
+    /// A byte was lost because the last one hadn't been read yet (ORE).
+    pub overrun: bool,
+    /// Noise was detected on the byte (NF).
+    pub noise: bool,
+    /// The stop bit of the byte was missing (FE).
+    pub framing: bool,
+    /// The parity of the byte was wrong (PE).
+    pub parity: bool,
+}
+
+impl ErrorFlags {
+    /// Returns whether any error flag is set.
+    pub fn any(&self) -> bool {
+        self.overrun || self.noise || self.framing || self.parity
+    }
+
+    /// Returns whether the byte in the data register is corrupt, with a
+    /// framing or parity error.
+    pub fn is_corrupt(&self) -> bool {
+        self.framing || self.parity
+    }
+}
+
+/// What one read of a UART's status register said about reception.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct RxStatus {
+    /// The error flags that were set.
+    pub errors: ErrorFlags,
+    /// Whether there was a byte in the data register (RXNE).
+    pub rx_not_empty: bool,
+}
+
+/// The register accesses of receiving a byte from a UART and clearing its
+/// error flags.
+///
+/// See the [module documentation](self) for the order they're done in.
+pub trait RxRegisters {
+    /// Reads the status register, once.
+    fn status(&mut self) -> RxStatus;
+
+    /// Reads the data register.
+    fn read_data(&mut self) -> u8;
+
+    /// Clears the error flags set in `errors`, and no others.
+    fn clear_errors(&mut self, errors: ErrorFlags);
+}
+
+/// A count of each kind of receive error.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ErrorCounts {
+    /// Overrun errors.
+    pub overrun: u32,
+    /// Noise errors.
+    pub noise: u32,
+    /// Framing errors.
+    pub framing: u32,
+    /// Parity errors.
+    pub parity: u32,
+}
+
+impl ErrorCounts {
+    /// Adds one to the count of each error set in `errors`.
+    pub fn record(&mut self, errors: ErrorFlags) {
+        let count = |n: &mut u32, set: bool| *n = n.wrapping_add(set as u32);
+        count(&mut self.overrun, errors.overrun);
+        count(&mut self.noise, errors.noise);
+        count(&mut self.framing, errors.framing);
+        count(&mut self.parity, errors.parity);
+    }
+
+    /// Returns the number of errors of all kinds.
+    pub fn total(&self) -> u32 {
+        self.overrun
+            .wrapping_add(self.noise)
+            .wrapping_add(self.framing)
+            .wrapping_add(self.parity)
+    }
+}
+
+/// Polls `uart` for a byte, checking, counting in `counts`, and clearing any
+/// receive errors along the way.
+///
+/// Returns the byte if there was one and it isn't corrupt. See the
+/// [module documentation](self) for what's counted and cleared.
+pub fn receive<U: RxRegisters>(uart: &mut U, counts: &mut ErrorCounts) -> Option<u8> {
+    let status = uart.status();
+
+    let byte = if status.rx_not_empty {
+        Some(uart.read_data())
+    } else {
+        None
+    };
+
+    if status.errors.any() {
+        counts.record(status.errors);
+        uart.clear_errors(status.errors);
+    }
+
+    byte.filter(|_| !status.errors.is_corrupt())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::vec::Vec;
+
+    /// What the UART was asked to do, in order.
+    #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+    enum Op {
+        Status,
+        Read,
+        Clear(ErrorFlags),
+    }
+
+    /// A UART that reports a given status, with `data` in its data register.
+    struct MockUart {
+        status: RxStatus,
+        data: u8,
+        ops: Vec<Op>,
+    }
+
+    impl MockUart {
+        fn new(rx_not_empty: bool, errors: ErrorFlags) -> Self {
+            MockUart {
+                status: RxStatus {
+                    errors,
+                    rx_not_empty,
+                },
+                data: b'x',
+                ops: Vec::new(),
+            }
+        }
+    }
+
+    impl RxRegisters for MockUart {
+        fn status(&mut self) -> RxStatus {
+            self.ops.push(Op::Status);
+            self.status
+        }
+
+        fn read_data(&mut self) -> u8 {
+            self.ops.push(Op::Read);
+            self.data
+        }
+
+        fn clear_errors(&mut self, errors: ErrorFlags) {
+            self.ops.push(Op::Clear(errors));
+        }
+    }
+
+    const OVERRUN: ErrorFlags = ErrorFlags {
+        overrun: true,
+        noise: false,
+        framing: false,
+        parity: false,
+    };
+
+    #[test]
+    fn clean_byte_is_returned_without_clearing() {
+        let mut uart = MockUart::new(true, ErrorFlags::default());
+        let mut counts = ErrorCounts::default();
+        assert_eq!(receive(&mut uart, &mut counts), Some(b'x'));
+        assert_eq!(uart.ops, [Op::Status, Op::Read]);
+        assert_eq!(counts.total(), 0);
+    }
+
+    #[test]
+    fn nothing_received_reads_nothing() {
+        let mut uart = MockUart::new(false, ErrorFlags::default());
+        let mut counts = ErrorCounts::default();
+        assert_eq!(receive(&mut uart, &mut counts), None);
+        assert_eq!(uart.ops, [Op::Status]);
+    }
+
+    #[test]
+    fn overrun_keeps_the_byte_and_clears_after_reading_it() {
+        let mut uart = MockUart::new(true, OVERRUN);
+        let mut counts = ErrorCounts::default();
+        assert_eq!(receive(&mut uart, &mut counts), Some(b'x'));
+        assert_eq!(uart.ops, [Op::Status, Op::Read, Op::Clear(OVERRUN)]);
+        assert_eq!(
+            counts,
+            ErrorCounts {
+                overrun: 1,
+                ..Default::default()
+            }
+        );
+    }
+
+    #[test]
+    fn overrun_with_no_byte_is_still_cleared() {
+        let mut uart = MockUart::new(false, OVERRUN);
+        let mut counts = ErrorCounts::default();
+        assert_eq!(receive(&mut uart, &mut counts), None);
+        assert_eq!(uart.ops, [Op::Status, Op::Clear(OVERRUN)]);
+        assert_eq!(counts.overrun, 1);
+    }
+
+    #[test]
+    fn noisy_byte_is_kept() {
+        let noise = ErrorFlags {
+            noise: true,
+            ..Default::default()
+        };
+        let mut counts = ErrorCounts::default();
+        assert_eq!(
+            receive(&mut MockUart::new(true, noise), &mut counts),
+            Some(b'x')
+        );
+        assert_eq!(counts.noise, 1);
+    }
+
+    #[test]
+    fn framing_and_parity_errors_drop_the_byte() {
+        let framing = ErrorFlags {
+            framing: true,
+            ..Default::default()
+        };
+        let parity = ErrorFlags {
+            parity: true,
+            ..Default::default()
+        };
+        let mut counts = ErrorCounts::default();
+
+        let mut uart = MockUart::new(true, framing);
+        assert_eq!(receive(&mut uart, &mut counts), None);
+        // The byte is still read out, to clear RXNE.
+        assert_eq!(uart.ops, [Op::Status, Op::Read, Op::Clear(framing)]);
+
+        assert_eq!(receive(&mut MockUart::new(true, parity), &mut counts), None);
+        assert_eq!(counts.framing, 1);
+        assert_eq!(counts.parity, 1);
+    }
+
+    #[test]
+    fn only_the_flags_that_were_set_are_cleared() {
+        let both = ErrorFlags {
+            overrun: true,
+            framing: true,
+            ..Default::default()
+        };
+        let mut uart = MockUart::new(true, both);
+        let mut counts = ErrorCounts::default();
+        receive(&mut uart, &mut counts);
+        assert_eq!(uart.ops.last(), Some(&Op::Clear(both)));
+        assert_eq!(counts.total(), 2);
+    }
+
+    #[test]
+    fn counts_add_up_over_several_polls() {
+        let mut counts = ErrorCounts::default();
+        for _ in 0..3 {
+            receive(&mut MockUart::new(true, OVERRUN), &mut counts);
+        }
+        assert_eq!(counts.overrun, 3);
+        assert_eq!(counts.total(), 3);
+    }
+}
@@ -0,0 +1,241 @@
+//! Reading from a UART with a timeout.
+//!
+//! A blocking read, e.g. `nb::block!(rx.read())`, waits for as long as it
+//! takes for a byte to arrive, which is forever if the other end has stopped
+//! sending, been unplugged, or was never there. [`read_with_timeout`] polls
+//! for a byte the same way, but gives up once a timeout measured against a
+//! [`TickSource`] has passed, so the caller gets control back and can decide
+//! what to do about it.
+//!
+//! ```ignore
+//! wt_support::millis::init(core_periphs.SYST, clocks.sysclk().0);
+//!
+//! match read_with_timeout(&mut rx, 5_000, &SysTickMillis) {
+//!     Ok(byte) => { /* Handle the byte. */ }
+//!     Err(TimeoutError::Timeout) => { /* Nothing arrived in 5 s. */ }
+//!     Err(TimeoutError::Serial(_)) => { /* Framing, noise, or overrun. */ }
+//! }
+//! ```
+//!
+//! # Why timeouts matter
+//!
+//! Any protocol over a wire has to cope with the other end going quiet partway
+//! through a message, whether from a dropped byte, a reset, or a loose cable.
+//! Without a timeout, a receiver waiting for the rest of a message waits
+//! forever, and the rest of the firmware, like a watchdog refresh or a status
+//! LED, stops along with it. With one, a half-received message can be thrown
+//! away and the receiver gets back to waiting for the start of the next one,
+//! so a single glitch doesn't take the whole link down.
+//!
+//! # Accounting
+//!
+//! The timeout starts when [`read_with_timeout`] is called and is checked
+//! after every poll of the UART, so a byte that's already waiting is always
+//! returned, even with a timeout of 0, and one that arrives just as the
+//! timeout runs out isn't missed. The elapsed time is worked out with wrapping
+//! subtraction, the same as [`crate::millis::elapsed_since`], so it's correct
+//! across a wraparound of the tick count.
+//!
+//! The timeout is a minimum. The call can start anywhere within a tick, so a
+//! tick count that has gone up by `timeout_ms` may only be a moment more than
+//! `timeout_ms - 1` ms later. The read only times out once the count has gone
+//! up by more than `timeout_ms`, so with a 1 ms tick, it can run up to 1 ms
+//! over, but never short. A timeout of 0 keeps polling until the next tick.
+
+use embedded_hal::serial;
+
+pub use crate::millis::{SysTickMillis, TickSource};
+
+/// Errors from reading with a timeout.
+#[derive(Debug, PartialEq, Eq)]
+pub enum TimeoutError<E> {
+    /// No byte arrived before the timeout.
+    Timeout,
+    /// The UART reported an error, e.g. framing, noise, or overrun.
+    Serial(E),
+}
+
+/// Returns whether at least `timeout_ms` has passed between the tick counts
+/// `start` and `now`, which is once they're more than `timeout_ms` apart.
+pub fn timed_out(start: u32, now: u32, timeout_ms: u32) -> bool {
+    now.wrapping_sub(start) > timeout_ms
+}
+
+/// Reads a byte from `rx`, giving up once `timeout_ms` milliseconds have
+/// passed on `tick` without one arriving.
+pub fn read_with_timeout<R, T>(
+    rx: &mut R,
+    timeout_ms: u32,
+    tick: &T,
+) -> Result<u8, TimeoutError<R::Error>>
+where
+    R: serial::Read<u8>,
+    T: TickSource,
+{
+    let start = tick.now_ms();
+    loop {
+        match rx.read() {
+            Ok(byte) => return Ok(byte),
+            Err(nb::Error::Other(error)) => return Err(TimeoutError::Serial(error)),
+            Err(nb::Error::WouldBlock) => {}
+        }
+
+        if timed_out(start, tick.now_ms(), timeout_ms) {
+            return Err(TimeoutError::Timeout);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::cell::Cell;
+
+    /// A tick source that goes up by 1 ms every time it's read.
+    struct MockTicks {
+        now: Cell<u32>,
+    }
+
+    impl MockTicks {
+        fn starting_at(now: u32) -> Self {
+            MockTicks {
+                now: Cell::new(now),
+            }
+        }
+    }
+
+    impl TickSource for MockTicks {
+        fn now_ms(&self) -> u32 {
+            let now = self.now.get();
+            self.now.set(now.wrapping_add(1));
+            now
+        }
+    }
+
+    /// A receiver with nothing to read for the first `polls_before_byte`
+    /// polls, then the result in `result`.
+    struct MockRx {
+        polls: u32,
+        polls_before_byte: u32,
+        result: Result<u8, ()>,
+    }
+
+    impl MockRx {
+        fn after(polls_before_byte: u32, result: Result<u8, ()>) -> Self {
+            MockRx {
+                polls: 0,
+                polls_before_byte,
+                result,
+            }
+        }
+
+        fn silent() -> Self {
+            MockRx::after(u32::MAX, Ok(0))
+        }
+    }
+
+    impl serial::Read<u8> for MockRx {
+        type Error = ();
+
+        fn read(&mut self) -> nb::Result<u8, ()> {
+            self.polls += 1;
+            if self.polls <= self.polls_before_byte {
+                return Err(nb::Error::WouldBlock);
+            }
+            self.result.map_err(nb::Error::Other)
+        }
+    }
+
+    #[test]
+    fn byte_waiting_is_returned() {
+        let mut rx = MockRx::after(0, Ok(b'a'));
+        assert_eq!(
+            read_with_timeout(&mut rx, 100, &MockTicks::starting_at(0)),
+            Ok(b'a')
+        );
+    }
+
+    #[test]
+    fn byte_waiting_is_returned_with_zero_timeout() {
+        let mut rx = MockRx::after(0, Ok(b'a'));
+        assert_eq!(
+            read_with_timeout(&mut rx, 0, &MockTicks::starting_at(0)),
+            Ok(b'a')
+        );
+    }
+
+    #[test]
+    fn byte_arriving_before_timeout_is_returned() {
+        let mut rx = MockRx::after(50, Ok(b'b'));
+        assert_eq!(
+            read_with_timeout(&mut rx, 100, &MockTicks::starting_at(0)),
+            Ok(b'b')
+        );
+    }
+
+    #[test]
+    fn times_out_after_timeout_and_no_sooner() {
+        // Each poll takes 1 ms of the mock ticks, so the byte arriving on the
+        // 101st poll comes in just before a 100 ms timeout is checked for the
+        // last time.
+        let mut rx = MockRx::after(100, Ok(b'c'));
+        assert_eq!(
+            read_with_timeout(&mut rx, 100, &MockTicks::starting_at(0)),
+            Ok(b'c')
+        );
+
+        let mut rx = MockRx::after(101, Ok(b'c'));
+        assert_eq!(
+            read_with_timeout(&mut rx, 100, &MockTicks::starting_at(0)),
+            Err(TimeoutError::Timeout)
+        );
+        assert_eq!(rx.polls, 101);
+    }
+
+    #[test]
+    fn zero_timeout_gives_up_at_the_next_tick() {
+        let mut rx = MockRx::silent();
+        assert_eq!(
+            read_with_timeout(&mut rx, 0, &MockTicks::starting_at(0)),
+            Err(TimeoutError::Timeout)
+        );
+        assert_eq!(rx.polls, 1);
+    }
+
+    #[test]
+    fn times_out_across_tick_wraparound() {
+        let mut rx = MockRx::silent();
+        assert_eq!(
+            read_with_timeout(&mut rx, 10, &MockTicks::starting_at(u32::MAX - 4)),
+            Err(TimeoutError::Timeout)
+        );
+        assert_eq!(rx.polls, 11);
+    }
+
+    #[test]
+    fn serial_error_is_returned() {
+        let mut rx = MockRx::after(3, Err(()));
+        assert_eq!(
+            read_with_timeout(&mut rx, 100, &MockTicks::starting_at(0)),
+            Err(TimeoutError::Serial(()))
+        );
+    }
+
+    #[test]
+    fn timed_out_accounting() {
+        assert!(!timed_out(1_000, 1_999, 1_000));
+        assert!(timed_out(1_000, 2_001, 1_000));
+        assert!(timed_out(u32::MAX - 499, 501, 1_000));
+        assert!(!timed_out(u32::MAX - 499, 500, 1_000));
+    }
+
+    #[test]
+    fn timed_out_is_not_early_at_the_boundary() {
+        // Counts exactly timeout_ms apart can be only a moment over
+        // timeout_ms - 1 ms apart in time, so that's not a timeout yet.
+        assert!(!timed_out(1_000, 2_000, 1_000));
+        assert!(timed_out(1_000, 2_001, 1_000));
+        assert!(!timed_out(0, 0, 0));
+        assert!(timed_out(0, 1, 0));
+    }
+}
@@ -0,0 +1,303 @@
+//! Writing to a UART without blocking.
+//!
+//! Writing with `write!()` straight to the UART waits for every byte to go
+//! out, about 87 us each at 115200 baud, so a 40 character line holds up the
+//! caller for 3.5 ms. [`BufferedTx`] puts the bytes in a queue instead and
+//! returns straight away, and the UART's transmit data register empty (TXE)
+//! interrupt takes them off the queue and writes them out one at a time as
+//! the UART is ready for them.
+//!
+//! ```ignore
+//! static SERIAL: Mutex<RefCell<Option<BufferedTx<Uart, 256>>>> =
+//!     Mutex::new(RefCell::new(None));
+//!
+//! // In the main loop:
+//! critical_section::with(|cs| {
+//!     if let Some(tx) = SERIAL.borrow(cs).borrow_mut().as_mut() {
+//!         writeln!(tx, "Count: {}\r", count).ok();
+//!     }
+//! });
+//!
+//! // In the UART's interrupt handler:
+//! critical_section::with(|cs| {
+//!     if let Some(tx) = SERIAL.borrow(cs).borrow_mut().as_mut() {
+//!         tx.on_interrupt();
+//!     }
+//! });
+//! ```
+//!
+//! The UART has to implement [`TxInterrupt`] as well as the `embedded-hal`
+//! serial `Write`, so the TXE interrupt can be turned on when there's
+//! something to send and off once there isn't. The interrupt fires whenever
+//! the data register is empty, so leaving it on with nothing to send would
+//! keep it firing.
+//!
+//! # Backpressure
+//!
+//! The queue can only hold so much, and if the caller writes faster than the
+//! UART sends for long enough, it fills up. Blocking until there's room would
+//! bring back the stalls the queue is there to avoid, so instead a string
+//! that doesn't fit in the space left is dropped, all of it, and its length
+//! is added to [`BufferedTx::dropped`]. Dropping whole strings rather than
+//! whatever doesn't fit keeps what does go out readable, since each piece of
+//! a `write!()` is either sent in full or not at all. Newer output is lost
+//! rather than older, since what's already queued may be partway through a
+//! line.
+//!
+//! The queue, a `heapless::spsc::Queue<u8, N>`, holds up to `N - 1` bytes.
+//! Size it for the longest burst of output between the times the UART
+//! catches up, and keep the average rate under the baud rate divided by 10,
+//! the bits per byte with a start and a stop bit. Checking the dropped count
+//! now and then shows whether it's big enough.
+
+use core::fmt;
+
+use embedded_hal::serial;
+use heapless::spsc::Queue;
+
+/// Turning the transmit data register empty (TXE) interrupt of a UART on and
+/// off.
+pub trait TxInterrupt {
+    /// Enables the TXE interrupt.
+    fn enable_tx_interrupt(&mut self);
+
+    /// Disables the TXE interrupt.
+    fn disable_tx_interrupt(&mut self);
+}
+
+/// A UART transmitter with a queue of `N - 1` bytes, written out from the
+/// TXE interrupt.
+///
+/// See the [module documentation](self) for how to use it, and what happens
+/// when the queue is full.
+pub struct BufferedTx<TX, const N: usize> {
+    tx: TX,
+    queue: Queue<u8, N>,
+    dropped: u32,
+}
+
+impl<TX, const N: usize> BufferedTx<TX, N>
+where
+    TX: serial::Write<u8> + TxInterrupt,
+{
+    /// Creates a transmitter with an empty queue, writing to `tx`.
+    pub fn new(mut tx: TX) -> Self {
+        tx.disable_tx_interrupt();
+        BufferedTx {
+            tx,
+            queue: Queue::new(),
+            dropped: 0,
+        }
+    }
+
+    /// Queues the bytes of `s` to be sent, and enables the TXE interrupt to
+    /// send them.
+    ///
+    /// Returns `false`, and drops all of `s`, if it doesn't fit in the space
+    /// left in the queue. Never blocks.
+    pub fn write_str(&mut self, s: &str) -> bool {
+        let bytes = s.as_bytes();
+        if bytes.len() > self.queue.capacity() - self.queue.len() {
+            self.dropped = self.dropped.saturating_add(bytes.len() as u32);
+            return false;
+        }
+
+        for &byte in bytes {
+            // There's room for all of them, so this can't fail.
+            self.queue.enqueue(byte).ok();
+        }
+        if !self.queue.is_empty() {
+            self.tx.enable_tx_interrupt();
+        }
+        true
+    }
+
+    /// Writes queued bytes to the UART for as long as it takes them, and
+    /// disables the TXE interrupt once the queue is empty.
+    ///
+    /// To be called from the UART's interrupt handler.
+    pub fn on_interrupt(&mut self) {
+        while let Some(&byte) = self.queue.peek() {
+            match self.tx.write(byte) {
+                Ok(()) => {
+                    self.queue.dequeue();
+                }
+                Err(nb::Error::WouldBlock) => return,
+
+                // Transmitting doesn't report errors on the STM32 UARTs, but
+                // if one did, the byte is thrown away rather than retried
+                // forever.
+                Err(nb::Error::Other(_)) => {
+                    self.queue.dequeue();
+                    self.dropped = self.dropped.saturating_add(1);
+                }
+            }
+        }
+        self.tx.disable_tx_interrupt();
+    }
+
+    /// Returns the number of bytes waiting to be sent.
+    pub fn len(&self) -> usize {
+        self.queue.len()
+    }
+
+    /// Returns whether there's nothing waiting to be sent.
+    pub fn is_empty(&self) -> bool {
+        self.queue.is_empty()
+    }
+
+    /// Returns the number of bytes dropped because the queue was full.
+    pub fn dropped(&self) -> u32 {
+        self.dropped
+    }
+
+    /// Returns the UART, dropping anything still in the queue.
+    pub fn free(mut self) -> TX {
+        self.tx.disable_tx_interrupt();
+        self.tx
+    }
+}
+
+/// Lets `write!()` and `writeln!()` queue formatted output.
+///
+/// Always returns `Ok`, even for the pieces of the output that are dropped,
+/// so that one full queue doesn't stop the rest of a `write!()` from going
+/// out once there's room. See [`BufferedTx::dropped`] for whether anything
+/// was.
+impl<TX, const N: usize> fmt::Write for BufferedTx<TX, N>
+where
+    TX: serial::Write<u8> + TxInterrupt,
+{
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        BufferedTx::write_str(self, s);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::convert::Infallible;
+    use core::fmt::Write;
+
+    /// A UART that takes up to `ready` bytes, then blocks.
+    #[derive(Default)]
+    struct MockTx {
+        sent: Vec<u8>,
+        ready: usize,
+        interrupt_enabled: bool,
+    }
+
+    impl serial::Write<u8> for MockTx {
+        type Error = Infallible;
+
+        fn write(&mut self, byte: u8) -> nb::Result<(), Infallible> {
+            if self.ready == 0 {
+                return Err(nb::Error::WouldBlock);
+            }
+            self.ready -= 1;
+            self.sent.push(byte);
+            Ok(())
+        }
+
+        fn flush(&mut self) -> nb::Result<(), Infallible> {
+            Ok(())
+        }
+    }
+
+    impl TxInterrupt for MockTx {
+        fn enable_tx_interrupt(&mut self) {
+            self.interrupt_enabled = true;
+        }
+
+        fn disable_tx_interrupt(&mut self) {
+            self.interrupt_enabled = false;
+        }
+    }
+
+    #[test]
+    fn write_queues_and_enables_the_interrupt() {
+        let mut tx: BufferedTx<MockTx, 16> = BufferedTx::new(MockTx::default());
+        assert!(!tx.tx.interrupt_enabled);
+
+        assert!(tx.write_str("hello"));
+        assert_eq!(tx.len(), 5);
+        assert!(tx.tx.interrupt_enabled);
+        assert!(tx.tx.sent.is_empty());
+    }
+
+    #[test]
+    fn empty_write_leaves_the_interrupt_off() {
+        let mut tx: BufferedTx<MockTx, 16> = BufferedTx::new(MockTx::default());
+        assert!(tx.write_str(""));
+        assert!(!tx.tx.interrupt_enabled);
+    }
+
+    #[test]
+    fn interrupt_sends_what_the_uart_takes_in_order() {
+        let mut tx: BufferedTx<MockTx, 16> = BufferedTx::new(MockTx::default());
+        tx.write_str("abc");
+        tx.write_str("de");
+
+        tx.tx.ready = 2;
+        tx.on_interrupt();
+        assert_eq!(tx.tx.sent, b"ab");
+        assert_eq!(tx.len(), 3);
+        assert!(tx.tx.interrupt_enabled);
+
+        tx.tx.ready = 10;
+        tx.on_interrupt();
+        assert_eq!(tx.tx.sent, b"abcde");
+        assert!(tx.is_empty());
+        assert!(!tx.tx.interrupt_enabled);
+    }
+
+    #[test]
+    fn holds_one_less_than_n() {
+        let mut tx: BufferedTx<MockTx, 8> = BufferedTx::new(MockTx::default());
+        assert!(tx.write_str("1234567"));
+        assert!(!tx.write_str("8"));
+        assert_eq!(tx.len(), 7);
+    }
+
+    #[test]
+    fn string_that_does_not_fit_is_dropped_whole() {
+        let mut tx: BufferedTx<MockTx, 8> = BufferedTx::new(MockTx::default());
+        tx.write_str("12345");
+        assert!(!tx.write_str("abc"));
+        assert_eq!(tx.len(), 5);
+        assert_eq!(tx.dropped(), 3);
+
+        // A shorter one still fits.
+        assert!(tx.write_str("ab"));
+        tx.tx.ready = 10;
+        tx.on_interrupt();
+        assert_eq!(tx.tx.sent, b"12345ab");
+        assert_eq!(tx.dropped(), 3);
+    }
+
+    #[test]
+    fn formatted_writes_queue_each_piece() {
+        let mut tx: BufferedTx<MockTx, 32> = BufferedTx::new(MockTx::default());
+        write!(tx, "Count: {}\r\n", 42).unwrap();
+        tx.tx.ready = 32;
+        tx.on_interrupt();
+        assert_eq!(tx.tx.sent, b"Count: 42\r\n");
+    }
+
+    #[test]
+    fn formatted_write_is_ok_even_when_dropped() {
+        let mut tx: BufferedTx<MockTx, 4> = BufferedTx::new(MockTx::default());
+        assert!(write!(tx, "too long").is_ok());
+        assert_eq!(tx.dropped(), 8);
+        assert!(tx.is_empty());
+    }
+
+    #[test]
+    fn free_disables_the_interrupt() {
+        let mut tx: BufferedTx<MockTx, 16> = BufferedTx::new(MockTx::default());
+        tx.write_str("x");
+        let uart = tx.free();
+        assert!(!uart.interrupt_enabled);
+    }
+}
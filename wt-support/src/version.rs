@@ -0,0 +1,86 @@
+//! Which build of the firmware is running.
+//!
+//! Once a board is out of reach of the debugger, in a test rig or with a
+//! user, there's no telling from the outside which binary was last flashed to
+//! it. A bug report from it is only any use if the code it came from can be
+//! found again, so firmware commonly prints where and when it was built as
+//! soon as it starts, for the log to carry along with everything else:
+//!
+//! ```ignore
+//! writeln!(uart4, "{}\r", wt_support::version::banner()).ok();
+//! ```
+//!
+//! which prints e.g. `firmware 1a2b3c4 built 2024-01-31T12:00:00Z`.
+//!
+//! # Where the values come from
+//!
+//! The firmware can't ask git anything at run time, so the values are fixed
+//! into it at compile time. wt-support's `build.rs` runs on the host before
+//! the crate is compiled, runs `git rev-parse --short HEAD`, reads the clock,
+//! and passes both to the compiler with `cargo:rustc-env=`, as the
+//! environment variables `WT_GIT_HASH` and `WT_BUILD_TIME`. This module reads
+//! them with `env!()`, which is expanded at compile time, so they end up as
+//! string constants in flash.
+//!
+//! Without git, or outside a repository, the hash is `unknown` rather than
+//! the build failing. The build time is in UTC, and is taken from
+//! `SOURCE_DATE_EPOCH` instead if that's set.
+//!
+//! # Caveats
+//!
+//! The hash is of the commit checked out, so a build with uncommitted changes
+//! has the same hash as the commit without them. And the build script is only
+//! re-run when HEAD moves, so the build time is of the last build after that,
+//! not of every build. `cargo clean -p wt-support` forces both to be redone.
+
+/// The short hash of the commit the firmware was built from, or `unknown`.
+pub const GIT_HASH: &str = env!("WT_GIT_HASH");
+
+/// When the firmware was built, in UTC, as e.g. `2024-01-31T12:00:00Z`.
+pub const BUILD_TIME: &str = env!("WT_BUILD_TIME");
+
+/// Returns a line describing the build, `firmware <hash> built <timestamp>`.
+pub fn banner() -> &'static str {
+    concat!(
+        "firmware ",
+        env!("WT_GIT_HASH"),
+        " built ",
+        env!("WT_BUILD_TIME")
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn banner_is_made_of_the_hash_and_time() {
+        assert_eq!(
+            banner(),
+            std::format!("firmware {} built {}", GIT_HASH, BUILD_TIME)
+        );
+    }
+
+    #[test]
+    fn build_time_is_an_iso_8601_utc_timestamp() {
+        let bytes = BUILD_TIME.as_bytes();
+        assert_eq!(bytes.len(), 20);
+        for (i, &b) in bytes.iter().enumerate() {
+            match i {
+                4 | 7 => assert_eq!(b, b'-'),
+                10 => assert_eq!(b, b'T'),
+                13 | 16 => assert_eq!(b, b':'),
+                19 => assert_eq!(b, b'Z'),
+                _ => assert!(b.is_ascii_digit()),
+            }
+        }
+    }
+
+    #[test]
+    fn git_hash_is_hex_or_unknown() {
+        assert!(
+            GIT_HASH == "unknown"
+                || (!GIT_HASH.is_empty() && GIT_HASH.bytes().all(|b| b.is_ascii_hexdigit()))
+        );
+    }
+}